@@ -0,0 +1,278 @@
+//! Benchmarks of query planning: building the federated query graph of supergraphs of various
+//! sizes, normalizing deeply nested and merge-heavy operations, and planning operations end to
+//! end.
+//!
+//! The benchmarks need the `benchmarks` feature:
+//!
+//! ```sh
+//! cargo bench --features benchmarks
+//! ```
+use apollo_compiler::validation::Valid;
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::prelude::*;
+use apollo_federation::query_graph::build_query_graph::build_federated_query_graph;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::fmt::Write;
+
+const SMALL_SUPERGRAPH: &str =
+    include_str!("../tests/query_plan/fixtures/entity_jump/supergraph.graphql");
+
+const SUPERGRAPH_HEADER: &str = r#"schema
+  @link(url: "https://specs.apollo.dev/link/v1.0")
+  @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION)
+{
+  query: Query
+}
+
+directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+scalar join__FieldSet
+
+scalar link__Import
+
+enum link__Purpose {
+  SECURITY
+  EXECUTION
+}
+"#;
+
+/// Generates a supergraph of `subgraph_count` subgraphs, each defining `types_per_subgraph` entity
+/// types. The `next` field of each entity type returns the entity type of the same index in the
+/// next subgraph (wrapping around), so every type is reachable from any other through key jumps.
+fn generated_supergraph(subgraph_count: usize, types_per_subgraph: usize) -> String {
+    let graph = |subgraph: usize| format!("S{subgraph}");
+    let type_name = |subgraph: usize, index: usize| format!("T{subgraph}_{index}");
+
+    let mut sdl = SUPERGRAPH_HEADER.to_owned();
+    sdl.push_str("\nenum join__Graph {\n");
+    for subgraph in 0..subgraph_count {
+        writeln!(
+            sdl,
+            "  {} @join__graph(name: \"s{subgraph}\", url: \"http://s{subgraph}\")",
+            graph(subgraph),
+        )
+        .unwrap();
+    }
+    sdl.push_str("}\n\ntype Query\n");
+    for subgraph in 0..subgraph_count {
+        writeln!(sdl, "  @join__type(graph: {})", graph(subgraph)).unwrap();
+    }
+    sdl.push_str("{\n");
+    for subgraph in 0..subgraph_count {
+        for index in 0..types_per_subgraph {
+            writeln!(
+                sdl,
+                "  t{subgraph}_{index}(id: ID!): {} @join__field(graph: {})",
+                type_name(subgraph, index),
+                graph(subgraph),
+            )
+            .unwrap();
+        }
+    }
+    sdl.push_str("}\n");
+    for subgraph in 0..subgraph_count {
+        let next_subgraph = (subgraph + 1) % subgraph_count;
+        let previous_subgraph = (subgraph + subgraph_count - 1) % subgraph_count;
+        for index in 0..types_per_subgraph {
+            writeln!(sdl, "\ntype {}", type_name(subgraph, index)).unwrap();
+            writeln!(
+                sdl,
+                "  @join__type(graph: {}, key: \"id\")",
+                graph(subgraph)
+            )
+            .unwrap();
+            // The previous subgraph references this type from its own `next` fields.
+            if previous_subgraph != subgraph {
+                writeln!(
+                    sdl,
+                    "  @join__type(graph: {}, key: \"id\")",
+                    graph(previous_subgraph)
+                )
+                .unwrap();
+            }
+            writeln!(sdl, "{{\n  id: ID!").unwrap();
+            writeln!(
+                sdl,
+                "  name: String @join__field(graph: {})",
+                graph(subgraph)
+            )
+            .unwrap();
+            writeln!(
+                sdl,
+                "  next: {} @join__field(graph: {})\n}}",
+                type_name(next_subgraph, index),
+                graph(subgraph),
+            )
+            .unwrap();
+        }
+    }
+    sdl
+}
+
+/// Generates an operation selecting `next` `depth` times from `t0_0`, selecting `id` and `name`
+/// at every level through a named fragment.
+fn nested_operation(subgraph_count: usize, depth: usize) -> String {
+    let mut operation = "query Nested {\n  t0_0(id: \"1\") {\n".to_owned();
+    for _ in 0..depth {
+        operation.push_str("    next {\n");
+    }
+    operation.push_str("    __typename\n");
+    for level in (0..depth).rev() {
+        let subgraph = (level + 1) % subgraph_count;
+        writeln!(operation, "    ...T{subgraph}_0Fields\n    }}").unwrap();
+    }
+    operation.push_str("    ...T0_0Fields\n  }\n}\n");
+    for subgraph in 0..subgraph_count {
+        write!(
+            operation,
+            "\nfragment T{subgraph}_0Fields on T{subgraph}_0 {{\n  id\n  name\n}}\n"
+        )
+        .unwrap();
+    }
+    operation
+}
+
+/// Generates an operation repeating, `repetitions` times, the same fields and inline fragments with
+/// directives under `t0_0`, which normalization merges back into a single selection per selection
+/// key. Directives alternate between two variables, so that keys with the same response name or
+/// type condition only differ by their directives.
+fn merge_heavy_operation(repetitions: usize) -> String {
+    let mut operation =
+        "query Merging($a: Boolean!, $b: Boolean!) {\n  t0_0(id: \"1\") {\n".to_owned();
+    for repetition in 0..repetitions {
+        let arguments = if repetition % 2 == 0 {
+            "(if: $a)"
+        } else {
+            "(if: $b)"
+        };
+        writeln!(
+            operation,
+            "    id\n    name @include{arguments}\n    next @skip{arguments} {{\n      id\n      name\n    }}"
+        )
+        .unwrap();
+        writeln!(
+            operation,
+            "    ... on T0_0 @include{arguments} {{\n      name\n      next {{\n        name\n      }}\n    }}"
+        )
+        .unwrap();
+    }
+    operation.push_str("  }\n}\n");
+    operation
+}
+
+fn planner(supergraph: &Supergraph) -> QueryPlanner {
+    let mut config = QueryPlannerConfig::default();
+    config.debug.bypass_planner_for_single_subgraph = true;
+    QueryPlanner::new(supergraph, config).unwrap()
+}
+
+fn parse_operation(supergraph: &Supergraph, operation: &str) -> Valid<ExecutableDocument> {
+    let api_schema = supergraph
+        .to_api_schema(ApiSchemaOptions {
+            include_defer: true,
+            ..Default::default()
+        })
+        .unwrap();
+    ExecutableDocument::parse_and_validate(&api_schema, operation, "operation.graphql").unwrap()
+}
+
+fn query_graph_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query_graph_construction");
+    let supergraphs = [
+        ("small", SMALL_SUPERGRAPH.to_owned()),
+        ("large", generated_supergraph(10, 20)),
+    ];
+    for (name, sdl) in &supergraphs {
+        let supergraph = Supergraph::new(sdl).unwrap();
+        let supergraph_schema = ValidFederationSchema::new(supergraph.schema().clone()).unwrap();
+        let api_schema =
+            ValidFederationSchema::new(supergraph.to_api_schema(Default::default()).unwrap())
+                .unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &(), |b, _| {
+            b.iter(|| {
+                build_federated_query_graph(
+                    supergraph_schema.clone(),
+                    api_schema.clone(),
+                    Some(true),
+                    Some(true),
+                )
+                .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn normalization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("normalization");
+    let supergraph = Supergraph::new(&generated_supergraph(10, 20)).unwrap();
+    let planner = planner(&supergraph);
+    for depth in [10, 50] {
+        let document = parse_operation(&supergraph, &nested_operation(10, depth));
+        group.bench_with_input(
+            BenchmarkId::new("nested", depth),
+            &document,
+            |b, document| {
+                b.iter(|| {
+                    planner
+                        .normalize_operation(black_box(document), None)
+                        .unwrap()
+                })
+            },
+        );
+    }
+    for repetitions in [10, 100] {
+        let document = parse_operation(&supergraph, &merge_heavy_operation(repetitions));
+        group.bench_with_input(
+            BenchmarkId::new("merging", repetitions),
+            &document,
+            |b, document| {
+                b.iter(|| {
+                    planner
+                        .normalize_operation(black_box(document), None)
+                        .unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+// TODO: Plan operations with the full query planner once graph traversal is ported; until then,
+// only supergraphs with a single subgraph can be planned (by bypassing the planner).
+fn planning(c: &mut Criterion) {
+    let mut group = c.benchmark_group("planning");
+    let supergraph = Supergraph::new(&generated_supergraph(1, 200)).unwrap();
+    let planner = planner(&supergraph);
+    for depth in [10, 50] {
+        let document = parse_operation(&supergraph, &nested_operation(1, depth));
+        group.bench_with_input(
+            BenchmarkId::new("nested", depth),
+            &document,
+            |b, document| {
+                b.iter(|| {
+                    let operation = planner
+                        .normalize_operation(black_box(document), None)
+                        .unwrap();
+                    planner.single_subgraph_plan(&operation).unwrap().unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, query_graph_construction, normalization, planning);
+criterion_main!(benches);
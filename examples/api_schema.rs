@@ -11,9 +11,10 @@ fn main() -> ExitCode {
     };
 
     let schema = Schema::parse_and_validate(source, name).unwrap();
-    let supergraph = Supergraph::from(schema);
+    let result = Supergraph::try_from(schema)
+        .and_then(|supergraph| supergraph.to_api_schema(Default::default()));
 
-    match supergraph.to_api_schema(Default::default()) {
+    match result {
         Ok(result) => println!("{result}"),
         Err(error) => {
             eprintln!("{error}");
@@ -0,0 +1,389 @@
+//! Implements the authorization metadata of a supergraph, i.e. which of its types and fields
+//! require authentication (`@authenticated`), specific scopes (`@requiresScopes`) or specific
+//! policies (`@policy`).
+use crate::error::{FederationError, SingleFederationError};
+use crate::link::spec::Identity;
+use crate::schema::FederationSchema;
+use crate::Supergraph;
+use apollo_compiler::executable::{Field, Fragment, Selection, SelectionSet};
+use apollo_compiler::schema::{Directive, ExtendedType, Name, Value};
+use apollo_compiler::validation::Valid;
+use apollo_compiler::{name, ExecutableDocument, Node, Schema};
+use indexmap::{IndexMap, IndexSet};
+
+/// What a client must satisfy to access a type or field of a supergraph.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthorizationRequirements {
+    /// Whether the client must be authenticated.
+    pub authenticated: bool,
+    /// The scopes the client must have, as alternatives: the client must have all the scopes of at
+    /// least one of the inner lists. This is empty when no scope is required.
+    pub scopes: Vec<Vec<String>>,
+    /// The policies that must be satisfied, as alternatives like `scopes`. Routers evaluate these
+    /// policies themselves (e.g. through a coprocessor), and can then use
+    /// `AuthorizationMetadata::filter_by_policies()` to remove what isn't authorized.
+    pub policies: Vec<Vec<String>>,
+}
+
+impl AuthorizationRequirements {
+    /// Whether these requirements don't restrict anything.
+    pub fn is_empty(&self) -> bool {
+        !self.authenticated && self.scopes.is_empty() && self.policies.is_empty()
+    }
+
+    /// Adds the given requirements to these ones, so that these requirements are only satisfied
+    /// when both original requirements are.
+    pub fn merge(&mut self, other: &AuthorizationRequirements) {
+        self.authenticated |= other.authenticated;
+        merge_alternatives(&mut self.scopes, &other.scopes);
+        merge_alternatives(&mut self.policies, &other.policies);
+    }
+
+    /// Whether the given policies satisfy the policy requirements, i.e. whether they include all
+    /// the policies of at least one alternative.
+    pub fn policies_satisfied_by(&self, satisfied_policies: &IndexSet<String>) -> bool {
+        self.policies.is_empty()
+            || self.policies.iter().any(|alternative| {
+                alternative
+                    .iter()
+                    .all(|policy| satisfied_policies.contains(policy))
+            })
+    }
+}
+
+/// Merges two lists of alternatives (see `AuthorizationRequirements::scopes`), so that the merged
+/// alternatives are only satisfied when one alternative of each list is.
+fn merge_alternatives(alternatives: &mut Vec<Vec<String>>, other_alternatives: &[Vec<String>]) {
+    if other_alternatives.is_empty() {
+        return;
+    }
+    if alternatives.is_empty() {
+        *alternatives = other_alternatives.to_vec();
+        return;
+    }
+    // Satisfying both means satisfying one alternative of each, so the merged alternatives are the
+    // unions of each pair of alternatives.
+    let mut merged: Vec<Vec<String>> = Vec::new();
+    for alternative in alternatives.iter() {
+        for other_alternative in other_alternatives {
+            let union = alternative
+                .iter()
+                .chain(other_alternative)
+                .cloned()
+                .collect::<IndexSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            if !merged.contains(&union) {
+                merged.push(union);
+            }
+        }
+    }
+    *alternatives = merged;
+}
+
+/// The authorization requirements of the types and fields of a supergraph, as declared by its
+/// `@authenticated`, `@requiresScopes` and `@policy` applications. Routers can use them to reject
+/// (or filter out) the parts of an operation a client isn't allowed to access, before executing it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthorizationMetadata {
+    types: IndexMap<Name, AuthorizationRequirements>,
+    /// The requirements of fields, by parent type name and field name.
+    fields: IndexMap<Name, IndexMap<Name, AuthorizationRequirements>>,
+}
+
+impl AuthorizationMetadata {
+    /// Whether no type or field of the supergraph has authorization requirements.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.fields.is_empty()
+    }
+
+    /// The requirements to access values of the given type, if any.
+    pub fn type_requirements(&self, type_name: &str) -> Option<&AuthorizationRequirements> {
+        self.types.get(type_name)
+    }
+
+    /// The requirements to access the given field, if any. Note this doesn't include the
+    /// requirements of the field's type (see `type_requirements()`).
+    pub fn field_requirements(
+        &self,
+        type_name: &str,
+        field_name: &str,
+    ) -> Option<&AuthorizationRequirements> {
+        self.fields.get(type_name)?.get(field_name)
+    }
+
+    /// The requirements to access everything the given selection set selects, i.e. the
+    /// requirements of its fields, of their types, and of the type conditions of its fragments.
+    pub fn selection_set_requirements(
+        &self,
+        selection_set: &SelectionSet,
+    ) -> AuthorizationRequirements {
+        let mut requirements = AuthorizationRequirements::default();
+        self.collect_requirements(selection_set, &mut requirements);
+        requirements
+    }
+
+    fn collect_requirements(
+        &self,
+        selection_set: &SelectionSet,
+        requirements: &mut AuthorizationRequirements,
+    ) {
+        for selection in &selection_set.selections {
+            match selection {
+                Selection::Field(field) => {
+                    if let Some(field_requirements) =
+                        self.field_requirements(&selection_set.ty, &field.name)
+                    {
+                        requirements.merge(field_requirements);
+                    }
+                    if let Some(type_requirements) =
+                        self.type_requirements(field.definition.ty.inner_named_type())
+                    {
+                        requirements.merge(type_requirements);
+                    }
+                    self.collect_requirements(&field.selection_set, requirements);
+                }
+                Selection::InlineFragment(inline_fragment) => {
+                    if let Some(type_requirements) = inline_fragment
+                        .type_condition
+                        .as_ref()
+                        .and_then(|type_condition| self.type_requirements(type_condition))
+                    {
+                        requirements.merge(type_requirements);
+                    }
+                    self.collect_requirements(&inline_fragment.selection_set, requirements);
+                }
+                // Fragment spreads can't be resolved without their document, and fetch operations
+                // inline them anyway.
+                Selection::FragmentSpread(_) => {}
+            }
+        }
+    }
+}
+
+impl AuthorizationMetadata {
+    /// Removes from the given document the selections whose policy requirements (see
+    /// `AuthorizationRequirements::policies`) aren't satisfied by `satisfied_policies`, so that
+    /// only what the client is allowed to access gets planned. A selection is removed when its
+    /// field, the type of its field or its type condition requires unsatisfied policies, and so
+    /// are the selections that end up with an empty selection set.
+    ///
+    /// To keep the document valid, operations whose selections are all removed only select
+    /// `__typename`. Only policies are checked, as scopes and authentication are known before
+    /// planning and can already be checked with `selection_set_requirements()`.
+    pub fn filter_by_policies(
+        &self,
+        schema: &Valid<Schema>,
+        document: &Valid<ExecutableDocument>,
+        satisfied_policies: &IndexSet<String>,
+    ) -> Result<PolicyFilteredDocument, FederationError> {
+        let mut filter = PolicyFilter {
+            metadata: self,
+            satisfied_policies,
+            fragments: document.fragments.clone(),
+            is_filtered: false,
+        };
+        let mut filtered = document.clone().into_inner();
+        for (fragment_name, fragment) in &document.fragments {
+            let mut fragment = fragment.clone();
+            let is_authorized = filter.is_type_authorized(fragment.type_condition())
+                && filter.filter_selection_set(&mut fragment.make_mut().selection_set);
+            if is_authorized {
+                filter.fragments.insert(fragment_name.clone(), fragment);
+            } else {
+                filter.fragments.shift_remove(fragment_name);
+                filter.is_filtered = true;
+            }
+        }
+        let operations = filtered
+            .anonymous_operation
+            .iter_mut()
+            .chain(filtered.named_operations.values_mut());
+        for operation in operations {
+            let selection_set = &mut operation.make_mut().selection_set;
+            if !filter.filter_selection_set(selection_set) {
+                let typename =
+                    schema
+                        .type_field(&selection_set.ty, "__typename")
+                        .map_err(|_| SingleFederationError::Internal {
+                            message: format!(
+                                "Missing __typename field on \"{}\"",
+                                selection_set.ty
+                            ),
+                        })?;
+                selection_set.push(Field::new(name!("__typename"), typename.node.clone()));
+            }
+        }
+        filtered.fragments = filter.fragments;
+        Ok(PolicyFilteredDocument {
+            document: Valid::assume_valid(filtered),
+            is_filtered: filter.is_filtered,
+        })
+    }
+}
+
+/// A document filtered by `AuthorizationMetadata::filter_by_policies()`.
+#[derive(Debug, Clone)]
+pub struct PolicyFilteredDocument {
+    /// The document without the selections requiring unsatisfied policies.
+    pub document: Valid<ExecutableDocument>,
+    /// Whether any selection was removed, in which case executors should report the parts of the
+    /// response that are missing due to unsatisfied policies.
+    pub is_filtered: bool,
+}
+
+struct PolicyFilter<'a> {
+    metadata: &'a AuthorizationMetadata,
+    satisfied_policies: &'a IndexSet<String>,
+    /// The fragments of the document, filtered (and removed when unauthorized) as they're processed.
+    fragments: IndexMap<Name, Node<Fragment>>,
+    is_filtered: bool,
+}
+
+impl PolicyFilter<'_> {
+    fn is_type_authorized(&self, type_name: &str) -> bool {
+        self.metadata
+            .type_requirements(type_name)
+            .is_none_or(|requirements| requirements.policies_satisfied_by(self.satisfied_policies))
+    }
+
+    fn is_field_authorized(&self, type_name: &str, field: &Field) -> bool {
+        self.metadata
+            .field_requirements(type_name, &field.name)
+            .is_none_or(|requirements| requirements.policies_satisfied_by(self.satisfied_policies))
+            && self.is_type_authorized(field.definition.ty.inner_named_type())
+    }
+
+    /// Removes the unauthorized selections of the given selection set. Returns whether it still
+    /// has selections.
+    fn filter_selection_set(&mut self, selection_set: &mut SelectionSet) -> bool {
+        let selections = std::mem::take(&mut selection_set.selections);
+        for mut selection in selections {
+            let is_authorized = match &mut selection {
+                Selection::Field(field) => {
+                    self.is_field_authorized(&selection_set.ty, field)
+                        && (field.selection_set.selections.is_empty()
+                            || self.filter_selection_set(&mut field.make_mut().selection_set))
+                }
+                Selection::InlineFragment(inline_fragment) => {
+                    inline_fragment
+                        .type_condition
+                        .as_ref()
+                        .is_none_or(|type_condition| self.is_type_authorized(type_condition))
+                        && self.filter_selection_set(&mut inline_fragment.make_mut().selection_set)
+                }
+                Selection::FragmentSpread(fragment_spread) => {
+                    self.fragments.contains_key(&fragment_spread.fragment_name)
+                }
+            };
+            if is_authorized {
+                selection_set.selections.push(selection);
+            } else {
+                self.is_filtered = true;
+            }
+        }
+        !selection_set.selections.is_empty()
+    }
+}
+
+pub(crate) fn authorization_metadata(
+    supergraph: &Supergraph,
+) -> Result<AuthorizationMetadata, FederationError> {
+    let schema = FederationSchema::new(supergraph.schema().clone().into_inner())?;
+    let directive_name = |identity: Identity, name_in_spec: Name| {
+        schema
+            .metadata()
+            .and_then(|metadata| metadata.for_identity(&identity))
+            .map(|link| link.directive_name_in_schema(&name_in_spec))
+    };
+    let authenticated_directive_name =
+        directive_name(Identity::authenticated_identity(), name!("authenticated"));
+    let requires_scopes_directive_name = directive_name(
+        Identity::requires_scopes_identity(),
+        name!("requiresScopes"),
+    );
+    let policy_directive_name = directive_name(Identity::policy_identity(), name!("policy"));
+    let requirements = |directives: &mut dyn Iterator<Item = &Node<Directive>>| {
+        let mut requirements = AuthorizationRequirements::default();
+        for directive in directives {
+            if Some(&directive.name) == authenticated_directive_name.as_ref() {
+                requirements.authenticated = true;
+            } else if Some(&directive.name) == requires_scopes_directive_name.as_ref() {
+                requirements.merge(&AuthorizationRequirements {
+                    scopes: alternatives_argument(directive, "scopes")?,
+                    ..Default::default()
+                });
+            } else if Some(&directive.name) == policy_directive_name.as_ref() {
+                requirements.merge(&AuthorizationRequirements {
+                    policies: alternatives_argument(directive, "policies")?,
+                    ..Default::default()
+                });
+            }
+        }
+        Ok::<_, FederationError>(requirements)
+    };
+
+    let mut metadata = AuthorizationMetadata::default();
+    if authenticated_directive_name.is_none()
+        && requires_scopes_directive_name.is_none()
+        && policy_directive_name.is_none()
+    {
+        return Ok(metadata);
+    }
+    for (type_name, type_) in &schema.schema().types {
+        let type_requirements = requirements(&mut type_.directives().iter().map(|d| &d.node))?;
+        if !type_requirements.is_empty() {
+            metadata.types.insert(type_name.clone(), type_requirements);
+        }
+        let fields = match type_ {
+            ExtendedType::Object(type_) => &type_.fields,
+            ExtendedType::Interface(type_) => &type_.fields,
+            _ => continue,
+        };
+        for (field_name, field) in fields {
+            let field_requirements = requirements(&mut field.directives.iter())?;
+            if !field_requirements.is_empty() {
+                metadata
+                    .fields
+                    .entry(type_name.clone())
+                    .or_default()
+                    .insert(field_name.clone(), field_requirements);
+            }
+        }
+    }
+    Ok(metadata)
+}
+
+/// Reads the given argument of a `@requiresScopes` or `@policy` application, a list of lists of
+/// scopes or policies.
+fn alternatives_argument(
+    directive: &Directive,
+    argument_name: &str,
+) -> Result<Vec<Vec<String>>, FederationError> {
+    let invalid = || SingleFederationError::InvalidGraphQL {
+        message: format!(
+            "Invalid value for argument \"{}\" of @{}: must be a list of lists of strings.",
+            argument_name, directive.name,
+        ),
+        locations: Vec::new(),
+    };
+    let Some(Value::List(alternatives)) = directive
+        .argument_by_name(argument_name)
+        .map(|value| value.as_ref())
+    else {
+        return Err(invalid().into());
+    };
+    alternatives
+        .iter()
+        .map(|alternative| match alternative.as_ref() {
+            Value::List(scopes) => scopes
+                .iter()
+                .map(|scope| match scope.as_ref() {
+                    Value::String(scope) => Ok(scope.to_string()),
+                    _ => Err(invalid().into()),
+                })
+                .collect(),
+            _ => Err(invalid().into()),
+        })
+        .collect()
+}
@@ -0,0 +1,412 @@
+//! Implements contracts, i.e. variants of a supergraph exposing only the parts of its API tagged
+//! (with `@tag`) for a given audience.
+use crate::error::FederationError;
+use crate::link::inaccessible_spec_definition::{
+    validate_inaccessible, INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC,
+};
+use crate::link::spec::Identity;
+use crate::schema::FederationSchema;
+use crate::Supergraph;
+use apollo_compiler::schema::{
+    Component, Directive, DirectiveDefinition, DirectiveLocation, ExtendedType, Name, Type, Value,
+};
+use apollo_compiler::{name, Node, NodeStr, Schema};
+use indexmap::IndexSet;
+
+/// Which tags a contract keeps.
+struct TagFilter<'a> {
+    /// The name of `@tag` in the supergraph, if it links the tag spec.
+    tag_directive_name: Option<Name>,
+    include: &'a IndexSet<String>,
+    exclude: &'a IndexSet<String>,
+}
+
+impl TagFilter<'_> {
+    fn tags<'d>(
+        &'d self,
+        directives: &'d [impl AsRef<Directive>],
+    ) -> impl Iterator<Item = &'d str> {
+        directives.iter().filter_map(move |directive| {
+            let directive = directive.as_ref();
+            if Some(&directive.name) != self.tag_directive_name.as_ref() {
+                return None;
+            }
+            match directive
+                .argument_by_name("name")
+                .map(|value| value.as_ref())
+            {
+                Some(Value::String(tag)) => Some(tag.as_str()),
+                _ => None,
+            }
+        })
+    }
+
+    /// Whether the element with the given directives is tagged with an excluded tag.
+    fn is_excluded(&self, directives: &[impl AsRef<Directive>]) -> bool {
+        self.tags(directives).any(|tag| self.exclude.contains(tag))
+    }
+
+    /// Whether the element with the given directives is tagged with an included tag (which is
+    /// always the case when no tag is included).
+    fn is_included(&self, directives: &[impl AsRef<Directive>]) -> bool {
+        self.include.is_empty() || self.tags(directives).any(|tag| self.include.contains(tag))
+    }
+}
+
+/// Filters the supergraph for a contract, by making `@inaccessible`:
+/// - the elements tagged with an `exclude` tag,
+/// - when `include` isn't empty, the types, fields, input fields and enum values that aren't tagged
+///   with an `include` tag (nor have their parent type or one of their children tagged with one),
+/// - the (optional) fields and arguments whose type became inaccessible, and the types whose
+///   fields, values or members all became inaccessible.
+///
+/// The filtered supergraph is then validated like any supergraph using `@inaccessible`, which
+/// fails when filtering hides something still required (e.g. the type of a required argument).
+pub(crate) fn filter_by_tags(
+    supergraph: &Supergraph,
+    include: &IndexSet<String>,
+    exclude: &IndexSet<String>,
+) -> Result<Supergraph, FederationError> {
+    let supergraph_schema = FederationSchema::new(supergraph.schema().clone().into_inner())?;
+    let metadata = supergraph_schema.metadata();
+    let filter = TagFilter {
+        tag_directive_name: metadata
+            .and_then(|metadata| metadata.for_identity(&Identity::tag_identity()))
+            .map(|link| link.directive_name_in_schema(&name!("tag"))),
+        include,
+        exclude,
+    };
+    // Elements of linked features (e.g. `join__Graph`) are never filtered.
+    let filtered_types = supergraph_schema
+        .schema()
+        .types
+        .iter()
+        .filter(|(type_name, type_)| {
+            !type_.is_built_in()
+                && metadata.is_none_or(|metadata| metadata.source_link_of_type(type_name).is_none())
+        })
+        .map(|(type_name, _)| type_name.clone())
+        .collect::<IndexSet<_>>();
+
+    let mut schema = supergraph.schema().clone().into_inner();
+    let inaccessible_directive = Directive {
+        name: match metadata
+            .and_then(|metadata| metadata.for_identity(&Identity::inaccessible_identity()))
+        {
+            Some(link) => link.directive_name_in_schema(&INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC),
+            None => add_inaccessible_spec(&mut schema),
+        },
+        arguments: Vec::new(),
+    };
+    let inaccessible = &inaccessible_directive;
+
+    for type_name in &filtered_types {
+        let Some(type_) = schema.types.get_mut(type_name) else {
+            continue;
+        };
+        match type_ {
+            ExtendedType::Object(type_) => {
+                let type_ = type_.make_mut();
+                let type_included = filter.is_included(&type_.directives);
+                let some_field_included = type_
+                    .fields
+                    .values()
+                    .any(|field| filter.is_included(&field.directives));
+                if filter.is_excluded(&type_.directives) || !(type_included || some_field_included)
+                {
+                    mark_inaccessible(&mut type_.directives.0, inaccessible);
+                    continue;
+                }
+                for field in type_.fields.values_mut() {
+                    let field = field.make_mut();
+                    if filter.is_excluded(&field.directives)
+                        || !(type_included || filter.is_included(&field.directives))
+                    {
+                        mark_inaccessible(&mut field.directives.0, inaccessible);
+                    }
+                    for argument in &mut field.arguments {
+                        if filter.is_excluded(&argument.directives) {
+                            mark_inaccessible(&mut argument.make_mut().directives.0, inaccessible);
+                        }
+                    }
+                }
+            }
+            ExtendedType::Interface(type_) => {
+                let type_ = type_.make_mut();
+                let type_included = filter.is_included(&type_.directives);
+                let some_field_included = type_
+                    .fields
+                    .values()
+                    .any(|field| filter.is_included(&field.directives));
+                if filter.is_excluded(&type_.directives) || !(type_included || some_field_included)
+                {
+                    mark_inaccessible(&mut type_.directives.0, inaccessible);
+                    continue;
+                }
+                for field in type_.fields.values_mut() {
+                    let field = field.make_mut();
+                    if filter.is_excluded(&field.directives)
+                        || !(type_included || filter.is_included(&field.directives))
+                    {
+                        mark_inaccessible(&mut field.directives.0, inaccessible);
+                    }
+                    for argument in &mut field.arguments {
+                        if filter.is_excluded(&argument.directives) {
+                            mark_inaccessible(&mut argument.make_mut().directives.0, inaccessible);
+                        }
+                    }
+                }
+            }
+            ExtendedType::InputObject(type_) => {
+                let type_ = type_.make_mut();
+                let type_included = filter.is_included(&type_.directives);
+                let some_field_included = type_
+                    .fields
+                    .values()
+                    .any(|field| filter.is_included(&field.directives));
+                if filter.is_excluded(&type_.directives) || !(type_included || some_field_included)
+                {
+                    mark_inaccessible(&mut type_.directives.0, inaccessible);
+                    continue;
+                }
+                for field in type_.fields.values_mut() {
+                    if filter.is_excluded(&field.directives)
+                        || !(type_included || filter.is_included(&field.directives))
+                    {
+                        mark_inaccessible(&mut field.make_mut().directives.0, inaccessible);
+                    }
+                }
+            }
+            ExtendedType::Enum(type_) => {
+                let type_ = type_.make_mut();
+                let type_included = filter.is_included(&type_.directives);
+                let some_value_included = type_
+                    .values
+                    .values()
+                    .any(|value| filter.is_included(&value.directives));
+                if filter.is_excluded(&type_.directives) || !(type_included || some_value_included)
+                {
+                    mark_inaccessible(&mut type_.directives.0, inaccessible);
+                    continue;
+                }
+                for value in type_.values.values_mut() {
+                    if filter.is_excluded(&value.directives)
+                        || !(type_included || filter.is_included(&value.directives))
+                    {
+                        mark_inaccessible(&mut value.make_mut().directives.0, inaccessible);
+                    }
+                }
+            }
+            // Unions and scalars have no children to include, so they're kept as long as some
+            // included field uses them.
+            ExtendedType::Union(type_) => {
+                if filter.is_excluded(&type_.directives) {
+                    mark_inaccessible(&mut type_.make_mut().directives.0, inaccessible);
+                }
+            }
+            ExtendedType::Scalar(type_) => {
+                if filter.is_excluded(&type_.directives) {
+                    mark_inaccessible(&mut type_.make_mut().directives.0, inaccessible);
+                }
+            }
+        }
+    }
+
+    hide_elements_using_inaccessible_types(&mut schema, &filtered_types, inaccessible);
+
+    let schema = schema.validate()?;
+    validate_inaccessible(&FederationSchema::new(schema.clone().into_inner())?)?;
+    Supergraph::try_from(schema)
+}
+
+/// Makes inaccessible what can't be kept once the types it uses are inaccessible: fields,
+/// optional arguments and optional input fields of those types, and types whose fields, values
+/// or members are all inaccessible. Required arguments and input fields are kept, as hiding them
+/// would change the API, so `validate_inaccessible()` reports them instead.
+fn hide_elements_using_inaccessible_types(
+    schema: &mut Schema,
+    filtered_types: &IndexSet<Name>,
+    inaccessible: &Directive,
+) {
+    let is_inaccessible = |directives: &[Component<Directive>]| {
+        directives
+            .iter()
+            .any(|directive| directive.name == inaccessible.name)
+    };
+    loop {
+        let inaccessible_types = schema
+            .types
+            .iter()
+            .filter(|(_, type_)| is_inaccessible(&type_.directives().0))
+            .map(|(type_name, _)| type_name.clone())
+            .collect::<IndexSet<_>>();
+        let uses_inaccessible_type = |ty: &Type| inaccessible_types.contains(ty.inner_named_type());
+        let is_optional_and_uses_inaccessible_type =
+            |ty: &Type| !ty.is_non_null() && uses_inaccessible_type(ty);
+        let mut changed = false;
+        for type_name in filtered_types {
+            if inaccessible_types.contains(type_name) {
+                continue;
+            }
+            let Some(type_) = schema.types.get_mut(type_name) else {
+                continue;
+            };
+            let hide_type = match type_ {
+                ExtendedType::Object(type_) => {
+                    let type_ = type_.make_mut();
+                    for field in type_.fields.values_mut() {
+                        let field = field.make_mut();
+                        if field.directives.has(&inaccessible.name) {
+                            continue;
+                        }
+                        if uses_inaccessible_type(&field.ty) {
+                            changed |= mark_inaccessible(&mut field.directives.0, inaccessible);
+                            continue;
+                        }
+                        for argument in &mut field.arguments {
+                            if is_optional_and_uses_inaccessible_type(&argument.ty) {
+                                changed |= mark_inaccessible(
+                                    &mut argument.make_mut().directives.0,
+                                    inaccessible,
+                                );
+                            }
+                        }
+                    }
+                    type_
+                        .fields
+                        .values()
+                        .all(|field| field.directives.has(&inaccessible.name))
+                }
+                ExtendedType::Interface(type_) => {
+                    let type_ = type_.make_mut();
+                    for field in type_.fields.values_mut() {
+                        let field = field.make_mut();
+                        if field.directives.has(&inaccessible.name) {
+                            continue;
+                        }
+                        if uses_inaccessible_type(&field.ty) {
+                            changed |= mark_inaccessible(&mut field.directives.0, inaccessible);
+                            continue;
+                        }
+                        for argument in &mut field.arguments {
+                            if is_optional_and_uses_inaccessible_type(&argument.ty) {
+                                changed |= mark_inaccessible(
+                                    &mut argument.make_mut().directives.0,
+                                    inaccessible,
+                                );
+                            }
+                        }
+                    }
+                    type_
+                        .fields
+                        .values()
+                        .all(|field| field.directives.has(&inaccessible.name))
+                }
+                ExtendedType::InputObject(type_) => {
+                    let type_ = type_.make_mut();
+                    for field in type_.fields.values_mut() {
+                        if !field.directives.has(&inaccessible.name)
+                            && is_optional_and_uses_inaccessible_type(&field.ty)
+                        {
+                            changed |=
+                                mark_inaccessible(&mut field.make_mut().directives.0, inaccessible);
+                        }
+                    }
+                    type_
+                        .fields
+                        .values()
+                        .all(|field| field.directives.has(&inaccessible.name))
+                }
+                ExtendedType::Enum(type_) => type_
+                    .values
+                    .values()
+                    .all(|value| value.directives.has(&inaccessible.name)),
+                ExtendedType::Union(type_) => type_
+                    .members
+                    .iter()
+                    .all(|member| inaccessible_types.contains(&member.name)),
+                ExtendedType::Scalar(_) => false,
+            };
+            if hide_type {
+                changed |= mark_inaccessible(type_directives_mut(type_), inaccessible);
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+fn type_directives_mut(type_: &mut ExtendedType) -> &mut Vec<Component<Directive>> {
+    match type_ {
+        ExtendedType::Scalar(type_) => &mut type_.make_mut().directives.0,
+        ExtendedType::Object(type_) => &mut type_.make_mut().directives.0,
+        ExtendedType::Interface(type_) => &mut type_.make_mut().directives.0,
+        ExtendedType::Union(type_) => &mut type_.make_mut().directives.0,
+        ExtendedType::Enum(type_) => &mut type_.make_mut().directives.0,
+        ExtendedType::InputObject(type_) => &mut type_.make_mut().directives.0,
+    }
+}
+
+/// Adds the given `@inaccessible` application to the given directives, returning whether it
+/// wasn't there already.
+fn mark_inaccessible<T: AsRef<Directive> + From<Directive>>(
+    directives: &mut Vec<T>,
+    inaccessible: &Directive,
+) -> bool {
+    if directives
+        .iter()
+        .any(|directive| directive.as_ref().name == inaccessible.name)
+    {
+        return false;
+    }
+    directives.push(inaccessible.clone().into());
+    true
+}
+
+/// Links the inaccessible spec from the given supergraph schema, returning the name of
+/// `@inaccessible`.
+fn add_inaccessible_spec(schema: &mut Schema) -> Name {
+    let name = INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC;
+    schema
+        .schema_definition
+        .make_mut()
+        .directives
+        .push(Component::new(Directive {
+            name: name!("link"),
+            arguments: vec![
+                Node::new(apollo_compiler::ast::Argument {
+                    name: name!("url"),
+                    value: Node::new(Value::String(NodeStr::new(
+                        "https://specs.apollo.dev/inaccessible/v0.2",
+                    ))),
+                }),
+                Node::new(apollo_compiler::ast::Argument {
+                    name: name!("for"),
+                    value: Node::new(Value::Enum(name!("SECURITY"))),
+                }),
+            ],
+        }));
+    schema.directive_definitions.insert(
+        name.clone(),
+        Node::new(DirectiveDefinition {
+            description: None,
+            name: name.clone(),
+            arguments: Vec::new(),
+            repeatable: false,
+            locations: vec![
+                DirectiveLocation::FieldDefinition,
+                DirectiveLocation::Object,
+                DirectiveLocation::Interface,
+                DirectiveLocation::Union,
+                DirectiveLocation::ArgumentDefinition,
+                DirectiveLocation::Scalar,
+                DirectiveLocation::Enum,
+                DirectiveLocation::EnumValue,
+                DirectiveLocation::InputObject,
+                DirectiveLocation::InputFieldDefinition,
+            ],
+        }),
+    );
+    name
+}
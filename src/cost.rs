@@ -0,0 +1,399 @@
+//! Static cost estimation of operations, based on the `@cost` and `@listSize` directives of the
+//! cost spec (`https://specs.apollo.dev/cost/v0.1`), so gateways can implement demand control
+//! (i.e. reject operations that are too expensive) before executing them.
+//!
+//! The estimated cost of an operation is the sum of the costs of its fields, where the cost of a
+//! field is its weight (1 for composite types and 0 for leaf types by default) plus the cost of its
+//! sub-selections, multiplied by the expected size of the field's list (if it returns a list), plus
+//! the weights of its arguments. Mutations additionally cost `MUTATION_BASE_COST`.
+use crate::error::{FederationError, SingleFederationError};
+use crate::link::cost_spec_definition::{
+    get_cost_spec_definition_from_schema, COST_DIRECTIVE_NAME_IN_SPEC,
+    LIST_SIZE_DIRECTIVE_NAME_IN_SPEC,
+};
+use crate::link::spec_definition::SpecDefinition;
+use crate::query_plan::operation::normalized_field_selection::NormalizedFieldSelection;
+use crate::query_plan::operation::{NormalizedOperation, NormalizedSelection};
+use crate::query_plan::NormalizedSelectionSet;
+use crate::schema::position::{FieldDefinitionPosition, SchemaRootDefinitionKind};
+use crate::schema::FederationSchema;
+use crate::Supergraph;
+use apollo_compiler::ast::{Argument, Value};
+use apollo_compiler::schema::{Directive, ExtendedType, Name};
+use apollo_compiler::{Node, Schema};
+use indexmap::IndexMap;
+use serde_json::{Map, Value as JsonValue};
+
+/// The size assumed for lists whose size isn't known from `@listSize`.
+pub const DEFAULT_LIST_SIZE: f64 = 10.0;
+
+/// The cost added to the cost of mutations, on top of the cost of their fields.
+pub const MUTATION_BASE_COST: f64 = 10.0;
+
+/// The `@cost` and `@listSize` applications of a supergraph, by type and field.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostMetadata {
+    /// The weights of types with `@cost`.
+    types: IndexMap<Name, f64>,
+    /// The cost directives of fields, by parent type name and field name.
+    fields: IndexMap<Name, IndexMap<Name, FieldCost>>,
+    /// The weights of input fields with `@cost`, by input type name and input field name.
+    input_fields: IndexMap<Name, IndexMap<Name, f64>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct FieldCost {
+    weight: Option<f64>,
+    argument_weights: IndexMap<Name, f64>,
+    list_size: Option<ListSize>,
+}
+
+/// The arguments of a `@listSize` application.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ListSize {
+    assumed_size: Option<f64>,
+    slicing_arguments: Vec<Name>,
+    sized_fields: Vec<Name>,
+    require_one_slicing_argument: bool,
+}
+
+impl ListSize {
+    /// The size of the list returned by the given field with the given arguments: the largest
+    /// value of its slicing arguments, or the assumed size if no slicing argument is given. Errors
+    /// if the field requires exactly one slicing argument and the arguments don't give exactly one.
+    fn size(
+        &self,
+        field: &FieldDefinitionPosition,
+        arguments: &[Node<Argument>],
+        variables: &Map<String, JsonValue>,
+    ) -> Result<Option<f64>, FederationError> {
+        let slicing_arguments = arguments
+            .iter()
+            .filter(|argument| self.slicing_arguments.contains(&argument.name))
+            .collect::<Vec<_>>();
+        if self.require_one_slicing_argument
+            && !self.slicing_arguments.is_empty()
+            && slicing_arguments.len() != 1
+        {
+            return Err(SingleFederationError::InvalidGraphQL {
+                message: format!(
+                    "Field \"{}\" requires exactly one of its slicing arguments ({}), but {} were given",
+                    field,
+                    self.slicing_arguments
+                        .iter()
+                        .map(|name| format!("\"{}\"", name))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    slicing_arguments.len(),
+                ),
+                locations: Vec::new(),
+            }
+            .into());
+        }
+        Ok(slicing_arguments
+            .into_iter()
+            .filter_map(|argument| match argument.value.as_ref() {
+                Value::Variable(variable) => variables.get(variable.as_str())?.as_f64(),
+                value => value.to_f64(),
+            })
+            .reduce(f64::max)
+            .or(self.assumed_size))
+    }
+}
+
+/// Estimates the cost of the given operation (see the module documentation), where `variables` are
+/// the variable values of the request (used for the slicing arguments of `@listSize`). This reads
+/// the cost directives of the supergraph on each call, so callers estimating many operations should
+/// use `Supergraph::cost_metadata()` and `CostMetadata::estimate()` instead.
+pub fn estimate(
+    operation: &NormalizedOperation,
+    supergraph: &Supergraph,
+    variables: &Map<String, JsonValue>,
+) -> Result<f64, FederationError> {
+    supergraph.cost_metadata()?.estimate(operation, variables)
+}
+
+impl CostMetadata {
+    /// Whether the supergraph has no cost directive, in which case costs only depend on the shape
+    /// of operations.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.fields.is_empty() && self.input_fields.is_empty()
+    }
+
+    /// Estimates the cost of the given operation, which must have been normalized against the
+    /// supergraph (or its API schema).
+    pub fn estimate(
+        &self,
+        operation: &NormalizedOperation,
+        variables: &Map<String, JsonValue>,
+    ) -> Result<f64, FederationError> {
+        let base_cost = if operation.root_kind == SchemaRootDefinitionKind::Mutation {
+            MUTATION_BASE_COST
+        } else {
+            0.0
+        };
+        Ok(base_cost + self.selection_set_cost(&operation.selection_set, variables, None)?)
+    }
+
+    /// The cost of the given selection set. When the parent field has a `@listSize` with sized
+    /// fields, `sized_fields` are those fields along with their list size.
+    fn selection_set_cost(
+        &self,
+        selection_set: &NormalizedSelectionSet,
+        variables: &Map<String, JsonValue>,
+        sized_fields: Option<(&[Name], f64)>,
+    ) -> Result<f64, FederationError> {
+        let mut cost = 0.0;
+        for selection in selection_set.selections.values() {
+            cost += match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    self.field_cost(field_selection, variables, sized_fields)?
+                }
+                // All the fragments of an abstract type are counted, as any of them may apply.
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => self
+                    .selection_set_cost(
+                        &inline_fragment_selection.selection_set,
+                        variables,
+                        sized_fields,
+                    )?,
+                NormalizedSelection::FragmentSpread(_) => {
+                    return Err(SingleFederationError::Internal {
+                        message: "Cannot estimate the cost of unexpanded fragment spreads"
+                            .to_owned(),
+                    }
+                    .into())
+                }
+            };
+        }
+        Ok(cost)
+    }
+
+    fn field_cost(
+        &self,
+        field_selection: &NormalizedFieldSelection,
+        variables: &Map<String, JsonValue>,
+        parent_sized_fields: Option<(&[Name], f64)>,
+    ) -> Result<f64, FederationError> {
+        let field_data = field_selection.field.data();
+        if field_data.field_position.is_introspection_typename_field() {
+            return Ok(0.0);
+        }
+        let schema = field_data.schema.schema();
+        let definition = field_data.field_position.get(schema)?;
+        let field_cost = self
+            .fields
+            .get(field_data.field_position.type_name())
+            .and_then(|fields| fields.get(field_data.name()));
+        let weight = field_cost
+            .and_then(|field_cost| field_cost.weight)
+            .or_else(|| self.types.get(definition.ty.inner_named_type()).copied())
+            .unwrap_or(if field_selection.selection_set.is_some() {
+                1.0
+            } else {
+                0.0
+            });
+
+        let list_size = field_cost.and_then(|field_cost| field_cost.list_size.as_ref());
+        let size = list_size
+            .map(|list_size| {
+                list_size.size(&field_data.field_position, &field_data.arguments, variables)
+            })
+            .transpose()?
+            .flatten();
+        let instance_count = match parent_sized_fields {
+            Some((sized_fields, sized_fields_size)) if sized_fields.contains(field_data.name()) => {
+                sized_fields_size
+            }
+            _ if definition.ty.is_list() => size.unwrap_or(DEFAULT_LIST_SIZE),
+            _ => 1.0,
+        };
+        let sized_fields = list_size
+            .filter(|list_size| !list_size.sized_fields.is_empty())
+            .map(|list_size| {
+                (
+                    list_size.sized_fields.as_slice(),
+                    size.unwrap_or(DEFAULT_LIST_SIZE),
+                )
+            });
+        let selection_set_cost = match &field_selection.selection_set {
+            Some(selection_set) => {
+                self.selection_set_cost(selection_set, variables, sized_fields)?
+            }
+            None => 0.0,
+        };
+
+        let mut arguments_cost = 0.0;
+        for argument in field_data.arguments.iter() {
+            if let Some(weight) =
+                field_cost.and_then(|field_cost| field_cost.argument_weights.get(&argument.name))
+            {
+                arguments_cost += weight;
+            }
+            if let Some(argument_definition) = definition.argument_by_name(&argument.name) {
+                arguments_cost += self.input_value_cost(
+                    schema,
+                    argument_definition.ty.inner_named_type(),
+                    &argument.value,
+                    variables,
+                );
+            }
+        }
+        Ok(instance_count * (weight + selection_set_cost) + arguments_cost)
+    }
+
+    /// The weights of the input fields set by the given value of the given input type.
+    fn input_value_cost(
+        &self,
+        schema: &Schema,
+        type_name: &Name,
+        value: &Value,
+        variables: &Map<String, JsonValue>,
+    ) -> f64 {
+        match value {
+            Value::Variable(variable) => variables.get(variable.as_str()).map_or(0.0, |value| {
+                self.json_input_value_cost(schema, type_name, value)
+            }),
+            Value::List(values) => values
+                .iter()
+                .map(|value| self.input_value_cost(schema, type_name, value, variables))
+                .sum(),
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(field_name, value)| {
+                    self.input_field_weight(type_name, field_name)
+                        + self.input_field_type(schema, type_name, field_name).map_or(
+                            0.0,
+                            |field_type_name| {
+                                self.input_value_cost(schema, field_type_name, value, variables)
+                            },
+                        )
+                })
+                .sum(),
+            _ => 0.0,
+        }
+    }
+
+    /// Like `input_value_cost()`, for values given as variables.
+    fn json_input_value_cost(&self, schema: &Schema, type_name: &Name, value: &JsonValue) -> f64 {
+        match value {
+            JsonValue::Array(values) => values
+                .iter()
+                .map(|value| self.json_input_value_cost(schema, type_name, value))
+                .sum(),
+            JsonValue::Object(fields) => fields
+                .iter()
+                .map(|(field_name, value)| {
+                    self.input_field_weight(type_name, field_name)
+                        + self.input_field_type(schema, type_name, field_name).map_or(
+                            0.0,
+                            |field_type_name| {
+                                self.json_input_value_cost(schema, field_type_name, value)
+                            },
+                        )
+                })
+                .sum(),
+            _ => 0.0,
+        }
+    }
+
+    fn input_field_weight(&self, type_name: &str, field_name: &str) -> f64 {
+        self.input_fields
+            .get(type_name)
+            .and_then(|fields| fields.get(field_name))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn input_field_type<'schema>(
+        &self,
+        schema: &'schema Schema,
+        type_name: &str,
+        field_name: &str,
+    ) -> Option<&'schema Name> {
+        match schema.types.get(type_name)? {
+            ExtendedType::InputObject(type_) => {
+                Some(type_.fields.get(field_name)?.ty.inner_named_type())
+            }
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn cost_metadata(supergraph: &Supergraph) -> Result<CostMetadata, FederationError> {
+    let schema = FederationSchema::new(supergraph.schema().clone().into_inner())?;
+    let Some(cost_spec_definition) = get_cost_spec_definition_from_schema(&schema)? else {
+        return Ok(CostMetadata::default());
+    };
+    let directive_name =
+        |name_in_spec: &Name| cost_spec_definition.directive_name_in_schema(&schema, name_in_spec);
+    let (Some(cost_directive_name), Some(list_size_directive_name)) = (
+        directive_name(&COST_DIRECTIVE_NAME_IN_SPEC)?,
+        directive_name(&LIST_SIZE_DIRECTIVE_NAME_IN_SPEC)?,
+    ) else {
+        return Ok(CostMetadata::default());
+    };
+    let weight = |directives: &mut dyn Iterator<Item = &Node<Directive>>| {
+        for directive in directives {
+            if directive.name == cost_directive_name {
+                return cost_spec_definition
+                    .cost_directive_arguments(directive)
+                    .map(|arguments| Some(arguments.weight));
+            }
+        }
+        Ok(None)
+    };
+
+    let mut metadata = CostMetadata::default();
+    for (type_name, type_) in &schema.schema().types {
+        if let Some(weight) = weight(&mut type_.directives().iter().map(|d| &d.node))? {
+            metadata.types.insert(type_name.clone(), weight);
+        }
+        let fields = match type_ {
+            ExtendedType::Object(type_) => &type_.fields,
+            ExtendedType::Interface(type_) => &type_.fields,
+            ExtendedType::InputObject(type_) => {
+                for (field_name, field) in &type_.fields {
+                    if let Some(weight) = weight(&mut field.directives.iter())? {
+                        metadata
+                            .input_fields
+                            .entry(type_name.clone())
+                            .or_default()
+                            .insert(field_name.clone(), weight);
+                    }
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        for (field_name, field) in fields {
+            let mut field_cost = FieldCost {
+                weight: weight(&mut field.directives.iter())?,
+                ..Default::default()
+            };
+            for argument in &field.arguments {
+                if let Some(weight) = weight(&mut argument.directives.iter())? {
+                    field_cost
+                        .argument_weights
+                        .insert(argument.name.clone(), weight);
+                }
+            }
+            if let Some(directive) = field.directives.get(&list_size_directive_name) {
+                let arguments = cost_spec_definition.list_size_directive_arguments(directive)?;
+                field_cost.list_size = Some(ListSize {
+                    assumed_size: arguments.assumed_size,
+                    slicing_arguments: arguments.slicing_arguments,
+                    sized_fields: arguments.sized_fields,
+                    require_one_slicing_argument: arguments.require_one_slicing_argument,
+                });
+            }
+            if field_cost != FieldCost::default() {
+                metadata
+                    .fields
+                    .entry(type_name.clone())
+                    .or_default()
+                    .insert(field_name.clone(), field_cost);
+            }
+        }
+    }
+    Ok(metadata)
+}
@@ -1,4 +1,5 @@
 use crate::subgraph::spec::FederationSpecError;
+use apollo_compiler::execution::GraphQLLocation;
 use apollo_compiler::validation::DiagnosticList;
 use apollo_compiler::{ast::InvalidNameError, validation::WithErrors};
 use lazy_static::lazy_static;
@@ -29,7 +30,11 @@ pub enum SingleFederationError {
     )]
     Internal { message: String },
     #[error("{message}")]
-    InvalidGraphQL { message: String },
+    InvalidGraphQL {
+        message: String,
+        /// The locations in the source document(s) this error relates to, if any.
+        locations: Vec<GraphQLLocation>,
+    },
     #[error("{message}")]
     DirectiveDefinitionInvalid { message: String },
     #[error("{message}")]
@@ -180,9 +185,101 @@ pub enum SingleFederationError {
     InterfaceKeyNotOnImplementation { message: String },
     #[error("{message}")]
     InterfaceKeyMissingImplementationType { message: String },
+    #[error("{message}")]
+    ContextNotSet { message: String },
+    #[error("{message}")]
+    NoContextInSelection { message: String },
+    #[error("{message}")]
+    InterfaceFieldImplemTypeMismatch { message: String },
+    #[error("{message}")]
+    OverrideLabelInvalid { message: String },
+    #[error("{message}")]
+    ContextNameInvalid { message: String },
+    #[error("{message}")]
+    NoSelectionForContext { message: String },
+    #[error("{message}")]
+    ContextNoResolvableKey { message: String },
+    #[error("{message}")]
+    SourceFederationVersionRequired { message: String },
+    #[error("{message}")]
+    SourceApiNameInvalid { message: String },
+    #[error("{message}")]
+    SourceApiProtocolInvalid { message: String },
+    #[error("{message}")]
+    SourceApiHttpBaseUrlInvalid { message: String },
+    #[error("{message}")]
+    SourceHttpHeadersInvalid { message: String },
+    #[error("{message}")]
+    SourceTypeApiError { message: String },
+    #[error("{message}")]
+    SourceTypeProtocolInvalid { message: String },
+    #[error("{message}")]
+    SourceTypeHttpMethodInvalid { message: String },
+    #[error("{message}")]
+    SourceTypeHttpPathInvalid { message: String },
+    #[error("{message}")]
+    SourceTypeHttpBodyInvalid { message: String },
+    #[error("{message}")]
+    SourceTypeOnNonObjectOrNonEntity { message: String },
+    #[error("{message}")]
+    SourceTypeSelectionInvalid { message: String },
+    #[error("{message}")]
+    SourceFieldApiError { message: String },
+    #[error("{message}")]
+    SourceFieldProtocolInvalid { message: String },
+    #[error("{message}")]
+    SourceFieldHttpMethodInvalid { message: String },
+    #[error("{message}")]
+    SourceFieldHttpPathInvalid { message: String },
+    #[error("{message}")]
+    SourceFieldHttpBodyInvalid { message: String },
+    #[error("{message}")]
+    SourceFieldSelectionInvalid { message: String },
+    #[error("{message}")]
+    SourceFieldNotOnRootOrEntityField { message: String },
+    #[error("{message}")]
+    CostAppliedToInterfaceField { message: String },
+    #[error("{message}")]
+    ListSizeAppliedToNonList { message: String },
+    #[error("{message}")]
+    ListSizeInvalidAssumedSize { message: String },
+    #[error("{message}")]
+    ListSizeInvalidSlicingArgument { message: String },
+    #[error("{message}")]
+    ListSizeInvalidSizedField { message: String },
+    #[error("{message}")]
+    QueryPlanningBudgetExceeded { message: String },
+    #[error("{message}{context}")]
+    OperationRebaseFailed {
+        message: String,
+        /// The types involved, so that the error can be acted on without parsing its message.
+        context: Box<RebaseErrorContext>,
+    },
+    #[error("{message}")]
+    SelectionMergeConflict { message: String },
+    #[error("{message}")]
+    UnsupportedFederationVersion { message: String },
 }
 
 impl SingleFederationError {
+    /// The locations in the source document(s) this error relates to. Only errors raised while
+    /// validating GraphQL documents currently record locations, so this is empty for others.
+    pub fn locations(&self) -> &[GraphQLLocation] {
+        match self {
+            SingleFederationError::InvalidGraphQL { locations, .. } => locations,
+            _ => &[],
+        }
+    }
+
+    /// The types involved in this error, when it's an error rebasing (or normalizing) an operation
+    /// element onto a type.
+    pub fn rebase_context(&self) -> Option<&RebaseErrorContext> {
+        match self {
+            SingleFederationError::OperationRebaseFailed { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
     pub fn code(&self) -> ErrorCode {
         match self {
             SingleFederationError::Internal { .. } => ErrorCode::Internal,
@@ -350,7 +447,135 @@ impl SingleFederationError {
             SingleFederationError::InterfaceKeyMissingImplementationType { .. } => {
                 ErrorCode::InterfaceKeyMissingImplementationType
             }
+            SingleFederationError::ContextNotSet { .. } => ErrorCode::ContextNotSet,
+            SingleFederationError::NoContextInSelection { .. } => ErrorCode::NoContextInSelection,
+            SingleFederationError::InterfaceFieldImplemTypeMismatch { .. } => {
+                ErrorCode::InterfaceFieldImplemTypeMismatch
+            }
+            SingleFederationError::OverrideLabelInvalid { .. } => ErrorCode::OverrideLabelInvalid,
+            SingleFederationError::ContextNameInvalid { .. } => ErrorCode::ContextNameInvalid,
+            SingleFederationError::NoSelectionForContext { .. } => ErrorCode::NoSelectionForContext,
+            SingleFederationError::ContextNoResolvableKey { .. } => {
+                ErrorCode::ContextNoResolvableKey
+            }
+            SingleFederationError::SourceFederationVersionRequired { .. } => {
+                ErrorCode::SourceFederationVersionRequired
+            }
+            SingleFederationError::SourceApiNameInvalid { .. } => ErrorCode::SourceApiNameInvalid,
+            SingleFederationError::SourceApiProtocolInvalid { .. } => {
+                ErrorCode::SourceApiProtocolInvalid
+            }
+            SingleFederationError::SourceApiHttpBaseUrlInvalid { .. } => {
+                ErrorCode::SourceApiHttpBaseUrlInvalid
+            }
+            SingleFederationError::SourceHttpHeadersInvalid { .. } => {
+                ErrorCode::SourceHttpHeadersInvalid
+            }
+            SingleFederationError::SourceTypeApiError { .. } => ErrorCode::SourceTypeApiError,
+            SingleFederationError::SourceTypeProtocolInvalid { .. } => {
+                ErrorCode::SourceTypeProtocolInvalid
+            }
+            SingleFederationError::SourceTypeHttpMethodInvalid { .. } => {
+                ErrorCode::SourceTypeHttpMethodInvalid
+            }
+            SingleFederationError::SourceTypeHttpPathInvalid { .. } => {
+                ErrorCode::SourceTypeHttpPathInvalid
+            }
+            SingleFederationError::SourceTypeHttpBodyInvalid { .. } => {
+                ErrorCode::SourceTypeHttpBodyInvalid
+            }
+            SingleFederationError::SourceTypeOnNonObjectOrNonEntity { .. } => {
+                ErrorCode::SourceTypeOnNonObjectOrNonEntity
+            }
+            SingleFederationError::SourceTypeSelectionInvalid { .. } => {
+                ErrorCode::SourceTypeSelectionInvalid
+            }
+            SingleFederationError::SourceFieldApiError { .. } => ErrorCode::SourceFieldApiError,
+            SingleFederationError::SourceFieldProtocolInvalid { .. } => {
+                ErrorCode::SourceFieldProtocolInvalid
+            }
+            SingleFederationError::SourceFieldHttpMethodInvalid { .. } => {
+                ErrorCode::SourceFieldHttpMethodInvalid
+            }
+            SingleFederationError::SourceFieldHttpPathInvalid { .. } => {
+                ErrorCode::SourceFieldHttpPathInvalid
+            }
+            SingleFederationError::SourceFieldHttpBodyInvalid { .. } => {
+                ErrorCode::SourceFieldHttpBodyInvalid
+            }
+            SingleFederationError::SourceFieldSelectionInvalid { .. } => {
+                ErrorCode::SourceFieldSelectionInvalid
+            }
+            SingleFederationError::SourceFieldNotOnRootOrEntityField { .. } => {
+                ErrorCode::SourceFieldNotOnRootOrEntityField
+            }
+            SingleFederationError::CostAppliedToInterfaceField { .. } => {
+                ErrorCode::CostAppliedToInterfaceField
+            }
+            SingleFederationError::ListSizeAppliedToNonList { .. } => {
+                ErrorCode::ListSizeAppliedToNonList
+            }
+            SingleFederationError::ListSizeInvalidAssumedSize { .. } => {
+                ErrorCode::ListSizeInvalidAssumedSize
+            }
+            SingleFederationError::ListSizeInvalidSlicingArgument { .. } => {
+                ErrorCode::ListSizeInvalidSlicingArgument
+            }
+            SingleFederationError::ListSizeInvalidSizedField { .. } => {
+                ErrorCode::ListSizeInvalidSizedField
+            }
+            SingleFederationError::QueryPlanningBudgetExceeded { .. } => {
+                ErrorCode::QueryPlanningBudgetExceeded
+            }
+            SingleFederationError::OperationRebaseFailed { .. } => ErrorCode::OperationRebaseFailed,
+            SingleFederationError::SelectionMergeConflict { .. } => {
+                ErrorCode::SelectionMergeConflict
+            }
+            SingleFederationError::UnsupportedFederationVersion { .. } => {
+                ErrorCode::UnsupportedFederationVersion
+            }
+        }
+    }
+}
+
+/// The types involved in an error rebasing (or normalizing) an operation element (a field, an inline
+/// fragment or a fragment spread) onto a type, possibly of another schema.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RebaseErrorContext {
+    /// The type the element was rebased onto, i.e. the parent type of its new selection set.
+    pub parent_type: String,
+    /// The possible runtime types of `parent_type`.
+    pub parent_runtime_types: Vec<String>,
+    /// The type the element applies to: the type condition of a fragment, or the type declaring a
+    /// field. This is `None` for fragments without type condition.
+    pub attempted_type: Option<String>,
+    /// The possible runtime types of `attempted_type` (in the schema the element came from).
+    pub attempted_runtime_types: Vec<String>,
+    /// The name of the subgraph the element was rebased onto, when rebasing onto a subgraph schema
+    /// and the subgraph is known.
+    pub subgraph_name: Option<String>,
+}
+
+impl Display for RebaseErrorContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            " (parent type \"{}\" with runtime types [{}]",
+            self.parent_type,
+            self.parent_runtime_types.join(", "),
+        )?;
+        if let Some(attempted_type) = &self.attempted_type {
+            write!(
+                f,
+                ", attempted type \"{}\" with runtime types [{}]",
+                attempted_type,
+                self.attempted_runtime_types.join(", "),
+            )?;
+        }
+        if let Some(subgraph_name) = &self.subgraph_name {
+            write!(f, ", in subgraph \"{}\"", subgraph_name)?;
         }
+        write!(f, ")")
     }
 }
 
@@ -358,6 +583,7 @@ impl From<InvalidNameError> for SingleFederationError {
     fn from(err: InvalidNameError) -> Self {
         SingleFederationError::InvalidGraphQL {
             message: format!("Invalid GraphQL name \"{}\"", err.0),
+            locations: Vec::new(),
         }
     }
 }
@@ -369,10 +595,25 @@ impl From<InvalidNameError> for FederationError {
 }
 
 impl From<FederationSpecError> for FederationError {
-    fn from(_err: FederationSpecError) -> Self {
+    fn from(err: FederationSpecError) -> Self {
         // TODO: When we get around to finishing the composition port, we should really switch it to
         // using FederationError instead of FederationSpecError.
-        todo!()
+        let message = err.to_string();
+        match err {
+            FederationSpecError::UnsupportedVersionError { .. } => {
+                SingleFederationError::UnknownFederationLinkVersion { message }.into()
+            }
+            FederationSpecError::UnsupportedFederationDirective(_) => {
+                SingleFederationError::UnsupportedLinkedFeature { message }.into()
+            }
+            FederationSpecError::InvalidGraphQLName(message) => {
+                SingleFederationError::InvalidGraphQL {
+                    message,
+                    locations: Vec::new(),
+                }
+                .into()
+            }
+        }
     }
 }
 
@@ -421,6 +662,7 @@ impl From<DiagnosticList> for MultipleFederationErrors {
                 .iter()
                 .map(|e| SingleFederationError::InvalidGraphQL {
                     message: e.error.to_string(),
+                    locations: e.get_line_column().into_iter().collect(),
                 })
                 .collect(),
         }
@@ -479,12 +721,39 @@ impl<T> From<WithErrors<T>> for FederationError {
 }
 
 impl FederationError {
+    /// The individual errors making up this error, in order.
+    pub fn errors(&self) -> Vec<&SingleFederationError> {
+        match self {
+            FederationError::SingleFederationError(error) => vec![error],
+            FederationError::MultipleFederationErrors(errors) => errors.errors.iter().collect(),
+            FederationError::AggregateFederationError(errors) => errors.causes.iter().collect(),
+        }
+    }
+
     pub(crate) fn internal(message: impl Into<String>) -> Self {
         SingleFederationError::Internal {
             message: message.into(),
         }
         .into()
     }
+
+    /// Records the given subgraph as the one rebased onto in the rebase errors of this error that
+    /// don't record one yet.
+    pub(crate) fn with_subgraph_name(mut self, subgraph_name: &str) -> Self {
+        let errors = match &mut self {
+            FederationError::SingleFederationError(error) => std::slice::from_mut(error),
+            FederationError::MultipleFederationErrors(errors) => &mut errors.errors[..],
+            FederationError::AggregateFederationError(errors) => &mut errors.causes[..],
+        };
+        for error in errors {
+            if let SingleFederationError::OperationRebaseFailed { context, .. } = error {
+                context
+                    .subgraph_name
+                    .get_or_insert_with(|| subgraph_name.to_owned());
+            }
+        }
+        self
+    }
 }
 
 // We didn't track errors addition precisely pre-2.0 and tracking it now has an unclear ROI, so we
@@ -583,6 +852,15 @@ impl ErrorCodeCategory<String> {
     }
 }
 
+// This is kept out of the main block below, as that block is at the macro recursion limit.
+lazy_static! {
+    static ref INTERNAL: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "INTERNAL".to_owned(),
+        "An internal federation error occurred (this is a bug, which should be reported to Apollo).".to_owned(),
+        None,
+    );
+}
+
 lazy_static! {
     static ref INVALID_GRAPHQL: ErrorCodeDefinition = ErrorCodeDefinition::new(
         "INVALID_GRAPHQL".to_owned(),
@@ -1102,6 +1380,316 @@ lazy_static! {
             replaces: &[],
         }),
     );
+
+    static ref CONTEXT_NOT_SET: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "CONTEXT_NOT_SET".to_owned(),
+        "Context is never set for context trying to be used.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.8.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref NO_CONTEXT_IN_SELECTION: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "NO_CONTEXT_IN_SELECTION".to_owned(),
+        "Selection in @fromContext field argument does not reference a context.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.8.0",
+            replaces: &[],
+        }),
+    );
+}
+
+// The codes added after the ones above are kept in their own block, as that one is at the macro
+// recursion limit.
+lazy_static! {
+    static ref INTERFACE_FIELD_IMPLEM_TYPE_MISMATCH: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "INTERFACE_FIELD_IMPLEM_TYPE_MISMATCH".to_owned(),
+        "For an interface field, some of its concrete implementations have @external or @requires and there is difference in those implementations return type (which is currently not supported; see https://github.com/apollographql/federation/issues/1257).".to_owned(),
+        None,
+    );
+
+    static ref OVERRIDE_LABEL_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "OVERRIDE_LABEL_INVALID".to_owned(),
+        "The @override directive `label` argument must match the pattern /^[a-zA-Z][a-zA-Z0-9_\\-:./]*$/ or /^percent\\((\\d{1,2}(\\.\\d{1,8})?|100)\\)$/".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref CONTEXT_NAME_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "CONTEXT_NAME_INVALID".to_owned(),
+        "Context name is invalid.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.8.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref NO_SELECTION_FOR_CONTEXT: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "NO_SELECTION_FOR_CONTEXT".to_owned(),
+        "The field parameter in @fromContext must contain a selection set.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.8.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref CONTEXT_NO_RESOLVABLE_KEY: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "CONTEXT_NO_RESOLVABLE_KEY".to_owned(),
+        "If an ObjectType uses a @fromContext, at least one of its keys must be resolvable.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.8.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_FEDERATION_VERSION_REQUIRED: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_FEDERATION_VERSION_REQUIRED".to_owned(),
+        "Schemas that @link to https://specs.apollo.dev/source must also @link to federation version v2.7 or later.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_API_NAME_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_API_NAME_INVALID".to_owned(),
+        "Each @sourceAPI directive must take a unique and valid name as an argument.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_API_PROTOCOL_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_API_PROTOCOL_INVALID".to_owned(),
+        "Each @sourceAPI directive must specify exactly one of the known protocols.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_API_HTTP_BASE_URL_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_API_HTTP_BASE_URL_INVALID".to_owned(),
+        "The @sourceAPI directive must specify a valid http.baseURL.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_HTTP_HEADERS_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_HTTP_HEADERS_INVALID".to_owned(),
+        "The http.headers argument of @source* directives must specify valid HTTP headers.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_TYPE_API_ERROR: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_TYPE_API_ERROR".to_owned(),
+        "The api argument of @sourceType must match a valid @sourceAPI name.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_TYPE_PROTOCOL_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_TYPE_PROTOCOL_INVALID".to_owned(),
+        "The @sourceType directive must specify the same protocol as its corresponding @sourceAPI.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_TYPE_HTTP_METHOD_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_TYPE_HTTP_METHOD_INVALID".to_owned(),
+        "The @sourceType directive must specify exactly one of http.GET or http.POST.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_TYPE_HTTP_PATH_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_TYPE_HTTP_PATH_INVALID".to_owned(),
+        "The @sourceType directive must specify a valid URL template for http.GET or http.POST.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_TYPE_HTTP_BODY_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_TYPE_HTTP_BODY_INVALID".to_owned(),
+        "If the @sourceType specifies http.body, it must be a valid JSONSelection.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_TYPE_ON_NON_OBJECT_OR_NON_ENTITY: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_TYPE_ON_NON_OBJECT_OR_NON_ENTITY".to_owned(),
+        "The @sourceType directive must be applied to an object or interface type that also has @key.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_TYPE_SELECTION_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_TYPE_SELECTION_INVALID".to_owned(),
+        "The selection argument of the @sourceType directive must be a valid JSONSelection that outputs fields of the GraphQL type.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_FIELD_API_ERROR: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_FIELD_API_ERROR".to_owned(),
+        "The api argument of @sourceField must match a valid @sourceAPI name.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_FIELD_PROTOCOL_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_FIELD_PROTOCOL_INVALID".to_owned(),
+        "If @sourceField specifies a protocol, it must match the corresponding @sourceAPI protocol.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_FIELD_HTTP_METHOD_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_FIELD_HTTP_METHOD_INVALID".to_owned(),
+        "The @sourceField directive must specify at most one of http.{GET,POST,PUT,PATCH,DELETE}.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_FIELD_HTTP_PATH_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_FIELD_HTTP_PATH_INVALID".to_owned(),
+        "The @sourceField directive must specify a valid URL template for http.{GET,POST,PUT,PATCH,DELETE}.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_FIELD_HTTP_BODY_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_FIELD_HTTP_BODY_INVALID".to_owned(),
+        "If @sourceField specifies http.body, it must be a valid JSONSelection matching available arguments and fields.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_FIELD_SELECTION_INVALID: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_FIELD_SELECTION_INVALID".to_owned(),
+        "The selection argument of the @sourceField directive must be a valid JSONSelection that outputs fields of the GraphQL type.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref SOURCE_FIELD_NOT_ON_ROOT_OR_ENTITY_FIELD: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SOURCE_FIELD_NOT_ON_ROOT_OR_ENTITY_FIELD".to_owned(),
+        "The @sourceField directive must be applied to a field of the Query or Mutation types, or of an entity type.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.7.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref COST_APPLIED_TO_INTERFACE_FIELD: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "COST_APPLIED_TO_INTERFACE_FIELD".to_owned(),
+        "The `@cost` directive must be applied to concrete types.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.9.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref LIST_SIZE_APPLIED_TO_NON_LIST: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "LIST_SIZE_APPLIED_TO_NON_LIST".to_owned(),
+        "The `@listSize` directive must be applied to a list.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.9.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref LIST_SIZE_INVALID_ASSUMED_SIZE: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "LIST_SIZE_INVALID_ASSUMED_SIZE".to_owned(),
+        "The `@listSize` directive assumed size cannot be negative.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.9.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref LIST_SIZE_INVALID_SLICING_ARGUMENT: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "LIST_SIZE_INVALID_SLICING_ARGUMENT".to_owned(),
+        "The `@listSize` directive must have existing integer slicing arguments.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.9.0",
+            replaces: &[],
+        }),
+    );
+
+    static ref LIST_SIZE_INVALID_SIZED_FIELD: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "LIST_SIZE_INVALID_SIZED_FIELD".to_owned(),
+        "The `@listSize` directive must reference existing list fields as sized fields.".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.9.0",
+            replaces: &[],
+        }),
+    );
+
+    // PORT_NOTE: The JS codebase has no query planning budgets, so this code has no JS equivalent.
+    static ref QUERY_PLANNING_BUDGET_EXCEEDED: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "QUERY_PLANNING_BUDGET_EXCEEDED".to_owned(),
+        "Planning an operation used more resources than the budget given to the query planner allows.".to_owned(),
+        None,
+    );
+
+    // PORT_NOTE: The JS codebase threw assertion errors (without a code) for these.
+    static ref OPERATION_REBASE_FAILED: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "OPERATION_REBASE_FAILED".to_owned(),
+        "An element of an operation could not be rebased onto a type, usually because the schema rebased onto doesn't define that element or type.".to_owned(),
+        None,
+    );
+
+    // PORT_NOTE: The JS codebase threw assertion errors (without a code) for these.
+    static ref SELECTION_MERGE_CONFLICT: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "SELECTION_MERGE_CONFLICT".to_owned(),
+        "Selections with the same response name could not be merged, e.g. because they select different fields or the same field with different arguments.".to_owned(),
+        None,
+    );
+
+    static ref UNSUPPORTED_FEDERATION_VERSION: ErrorCodeDefinition = ErrorCodeDefinition::new(
+        "UNSUPPORTED_FEDERATION_VERSION".to_owned(),
+        "Supergraphs composed with federation version 1 are not supported. Please recompose your supergraph with federation version 2 or greater".to_owned(),
+        Some(ErrorCodeMetadata {
+            added_in: "2.6.0",
+            replaces: &[],
+        }),
+    );
 }
 
 #[derive(Debug, strum_macros::EnumIter)]
@@ -1183,13 +1771,54 @@ pub enum ErrorCode {
     InterfaceObjectUsageError,
     InterfaceKeyNotOnImplementation,
     InterfaceKeyMissingImplementationType,
+    ContextNotSet,
+    NoContextInSelection,
+    InterfaceFieldImplemTypeMismatch,
+    OverrideLabelInvalid,
+    ContextNameInvalid,
+    NoSelectionForContext,
+    ContextNoResolvableKey,
+    SourceFederationVersionRequired,
+    SourceApiNameInvalid,
+    SourceApiProtocolInvalid,
+    SourceApiHttpBaseUrlInvalid,
+    SourceHttpHeadersInvalid,
+    SourceTypeApiError,
+    SourceTypeProtocolInvalid,
+    SourceTypeHttpMethodInvalid,
+    SourceTypeHttpPathInvalid,
+    SourceTypeHttpBodyInvalid,
+    SourceTypeOnNonObjectOrNonEntity,
+    SourceTypeSelectionInvalid,
+    SourceFieldApiError,
+    SourceFieldProtocolInvalid,
+    SourceFieldHttpMethodInvalid,
+    SourceFieldHttpPathInvalid,
+    SourceFieldHttpBodyInvalid,
+    SourceFieldSelectionInvalid,
+    SourceFieldNotOnRootOrEntityField,
+    CostAppliedToInterfaceField,
+    ListSizeAppliedToNonList,
+    ListSizeInvalidAssumedSize,
+    ListSizeInvalidSlicingArgument,
+    ListSizeInvalidSizedField,
+    QueryPlanningBudgetExceeded,
+    OperationRebaseFailed,
+    SelectionMergeConflict,
+    UnsupportedFederationVersion,
 }
 
 impl ErrorCode {
+    /// The code of this error, as used by the JS implementation (e.g. `KEY_INVALID_FIELDS`). These
+    /// codes are stable, and are what tooling should match errors on.
+    pub fn code(&self) -> &'static str {
+        self.definition().code()
+    }
+
     pub fn definition(&self) -> &'static ErrorCodeDefinition {
         match self {
-            // TODO: We should determine the code and doc info for internal errors.
-            ErrorCode::Internal => todo!(),
+            // PORT_NOTE: The JS code throws assertion errors without a code for internal errors.
+            ErrorCode::Internal => &INTERNAL,
             ErrorCode::InvalidGraphQL => &INVALID_GRAPHQL,
             ErrorCode::DirectiveDefinitionInvalid => &DIRECTIVE_DEFINITION_INVALID,
             ErrorCode::TypeDefinitionInvalid => &TYPE_DEFINITION_INVALID,
@@ -1280,6 +1909,86 @@ impl ErrorCode {
             ErrorCode::InterfaceKeyMissingImplementationType => {
                 &INTERFACE_KEY_MISSING_IMPLEMENTATION_TYPE
             }
+            ErrorCode::ContextNotSet => &CONTEXT_NOT_SET,
+            ErrorCode::NoContextInSelection => &NO_CONTEXT_IN_SELECTION,
+            ErrorCode::InterfaceFieldImplemTypeMismatch => &INTERFACE_FIELD_IMPLEM_TYPE_MISMATCH,
+            ErrorCode::OverrideLabelInvalid => &OVERRIDE_LABEL_INVALID,
+            ErrorCode::ContextNameInvalid => &CONTEXT_NAME_INVALID,
+            ErrorCode::NoSelectionForContext => &NO_SELECTION_FOR_CONTEXT,
+            ErrorCode::ContextNoResolvableKey => &CONTEXT_NO_RESOLVABLE_KEY,
+            ErrorCode::SourceFederationVersionRequired => &SOURCE_FEDERATION_VERSION_REQUIRED,
+            ErrorCode::SourceApiNameInvalid => &SOURCE_API_NAME_INVALID,
+            ErrorCode::SourceApiProtocolInvalid => &SOURCE_API_PROTOCOL_INVALID,
+            ErrorCode::SourceApiHttpBaseUrlInvalid => &SOURCE_API_HTTP_BASE_URL_INVALID,
+            ErrorCode::SourceHttpHeadersInvalid => &SOURCE_HTTP_HEADERS_INVALID,
+            ErrorCode::SourceTypeApiError => &SOURCE_TYPE_API_ERROR,
+            ErrorCode::SourceTypeProtocolInvalid => &SOURCE_TYPE_PROTOCOL_INVALID,
+            ErrorCode::SourceTypeHttpMethodInvalid => &SOURCE_TYPE_HTTP_METHOD_INVALID,
+            ErrorCode::SourceTypeHttpPathInvalid => &SOURCE_TYPE_HTTP_PATH_INVALID,
+            ErrorCode::SourceTypeHttpBodyInvalid => &SOURCE_TYPE_HTTP_BODY_INVALID,
+            ErrorCode::SourceTypeOnNonObjectOrNonEntity => &SOURCE_TYPE_ON_NON_OBJECT_OR_NON_ENTITY,
+            ErrorCode::SourceTypeSelectionInvalid => &SOURCE_TYPE_SELECTION_INVALID,
+            ErrorCode::SourceFieldApiError => &SOURCE_FIELD_API_ERROR,
+            ErrorCode::SourceFieldProtocolInvalid => &SOURCE_FIELD_PROTOCOL_INVALID,
+            ErrorCode::SourceFieldHttpMethodInvalid => &SOURCE_FIELD_HTTP_METHOD_INVALID,
+            ErrorCode::SourceFieldHttpPathInvalid => &SOURCE_FIELD_HTTP_PATH_INVALID,
+            ErrorCode::SourceFieldHttpBodyInvalid => &SOURCE_FIELD_HTTP_BODY_INVALID,
+            ErrorCode::SourceFieldSelectionInvalid => &SOURCE_FIELD_SELECTION_INVALID,
+            ErrorCode::SourceFieldNotOnRootOrEntityField => {
+                &SOURCE_FIELD_NOT_ON_ROOT_OR_ENTITY_FIELD
+            }
+            ErrorCode::CostAppliedToInterfaceField => &COST_APPLIED_TO_INTERFACE_FIELD,
+            ErrorCode::ListSizeAppliedToNonList => &LIST_SIZE_APPLIED_TO_NON_LIST,
+            ErrorCode::ListSizeInvalidAssumedSize => &LIST_SIZE_INVALID_ASSUMED_SIZE,
+            ErrorCode::ListSizeInvalidSlicingArgument => &LIST_SIZE_INVALID_SLICING_ARGUMENT,
+            ErrorCode::ListSizeInvalidSizedField => &LIST_SIZE_INVALID_SIZED_FIELD,
+            ErrorCode::QueryPlanningBudgetExceeded => &QUERY_PLANNING_BUDGET_EXCEEDED,
+            ErrorCode::OperationRebaseFailed => &OPERATION_REBASE_FAILED,
+            ErrorCode::SelectionMergeConflict => &SELECTION_MERGE_CONFLICT,
+            ErrorCode::UnsupportedFederationVersion => &UNSUPPORTED_FEDERATION_VERSION,
         }
     }
 }
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{ErrorCode, FederationError};
+    use apollo_compiler::Schema;
+    use std::collections::HashSet;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn error_codes_are_unique() {
+        let mut codes = HashSet::new();
+        for error_code in ErrorCode::iter() {
+            assert!(
+                codes.insert(error_code.code()),
+                "duplicate code {error_code}"
+            );
+        }
+        assert_eq!(
+            ErrorCode::KeyInvalidFields.to_string(),
+            "KEY_INVALID_FIELDS"
+        );
+        assert_eq!(ErrorCode::Internal.to_string(), "INTERNAL");
+    }
+
+    #[test]
+    fn invalid_graphql_errors_have_locations() {
+        let errors = Schema::parse_and_validate("type Query {\n  t: T\n}", "schema.graphql")
+            .unwrap_err()
+            .errors;
+        let error = FederationError::from(errors);
+        let errors = error.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code().code(), "INVALID_GRAPHQL");
+        let location = errors[0].locations()[0];
+        assert_eq!((location.line, location.column), (2, 6));
+    }
+}
@@ -0,0 +1,114 @@
+//! Parsing, validation and printing of federation `FieldSet` scalars, i.e. the `fields` argument
+//! of `@key`, `@requires` and `@provides`.
+use crate::error::{FederationError, MultipleFederationErrors, SingleFederationError};
+use crate::query_plan::operation::{
+    FragmentSpreadNormalizationOption, IdGenerator, NormalizedSelectionSet,
+};
+use crate::schema::ValidFederationSchema;
+use apollo_compiler::executable::{FieldSet, Selection, SelectionSet};
+use apollo_compiler::schema::NamedType;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::{NodeStr, Schema};
+use indexmap::IndexMap;
+
+/// Parses the given field set against the given parent type, returning it as a normalized
+/// selection set. Surrounding curly braces (`{` and `}`) are optional.
+// TODO: In the JS codebase, this has some error-rewriting to help give the user better hints
+// around non-existent fields.
+pub fn parse_field_set(
+    schema: &ValidFederationSchema,
+    parent_type_name: NamedType,
+    value: NodeStr,
+) -> Result<NormalizedSelectionSet, FederationError> {
+    // Note this parsing takes care of adding curly braces ("{" and "}") if they aren't in the
+    // string.
+    let field_set = FieldSet::parse_and_validate(
+        schema.schema(),
+        parent_type_name,
+        value.as_str(),
+        "field_set.graphql",
+    )?;
+    // Field sets can't use `@defer`, so their selection IDs don't need to be unique across field
+    // sets (or operations).
+    NormalizedSelectionSet::normalize_and_expand_fragments(
+        &field_set.selection_set,
+        &IndexMap::new(),
+        schema,
+        FragmentSpreadNormalizationOption::InlineFragmentSpread,
+        &IdGenerator::new(),
+    )
+}
+
+/// Validates the given field set against the given parent type. On top of GraphQL validation,
+/// this checks the field set doesn't use aliases, which federation forbids in field sets.
+pub fn validate_field_set(
+    schema: &Valid<Schema>,
+    parent_type_name: NamedType,
+    value: &str,
+) -> Result<(), FederationError> {
+    let field_set =
+        FieldSet::parse_and_validate(schema, parent_type_name, value, "field_set.graphql")?;
+    let mut errors = MultipleFederationErrors { errors: vec![] };
+    collect_aliases(&field_set.selection_set, &mut errors);
+    if errors.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.into())
+    }
+}
+
+fn collect_aliases(selection_set: &SelectionSet, errors: &mut MultipleFederationErrors) {
+    for selection in &selection_set.selections {
+        match selection {
+            Selection::Field(field) => {
+                if let Some(alias) = &field.alias {
+                    errors.errors.push(SingleFederationError::InvalidGraphQL {
+                        message: format!(
+                            "Cannot use alias \"{}\" in \"{}: {}\": aliases are not currently supported in field sets",
+                            alias, alias, field.name,
+                        ),
+                        locations: Vec::new(),
+                    });
+                }
+                collect_aliases(&field.selection_set, errors);
+            }
+            Selection::InlineFragment(fragment) => {
+                collect_aliases(&fragment.selection_set, errors);
+            }
+            // Field sets can't define fragments, so validation already rejects spreads.
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+/// Prints the given selection set as a field set, i.e. on a single line and without the
+/// surrounding curly braces (e.g. `id organization { id }`).
+pub fn print_field_set(selection_set: &NormalizedSelectionSet) -> String {
+    selection_set
+        .selections
+        .values()
+        .map(|selection| selection.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// This exists because there's a single callsite in extract_subgraphs_from_supergraph() that needs
+/// to parse field sets before the schema has finished building. Outside that case, you should
+/// always use `parse_field_set()` instead.
+// TODO: As noted in the single callsite, ideally we could move the parsing to after extraction, but
+// it takes time to determine whether that impacts correctness, so we're leaving it for later.
+pub(crate) fn parse_field_set_without_normalization(
+    schema: &Valid<Schema>,
+    parent_type_name: NamedType,
+    value: NodeStr,
+) -> Result<SelectionSet, FederationError> {
+    // Note this parsing takes care of adding curly braces ("{" and "}") if they aren't in the
+    // string.
+    let field_set = FieldSet::parse_and_validate(
+        schema,
+        parent_type_name,
+        value.as_str(),
+        "field_set.graphql",
+    )?;
+    Ok(field_set.into_inner().selection_set)
+}
@@ -1,40 +1,107 @@
+//! The stable API of this crate is the one re-exported by [`prelude`], along with the items
+//! documented here. Modules hidden from the documentation are public only for the crate's own
+//! tests and tooling, and can change in any release.
+//!
+//! Everything this crate produces is deterministic: the same inputs produce byte-identical
+//! supergraphs, subgraphs, query plans and errors (including their order). Collections whose
+//! order can reach an output are ordered ones (e.g. `IndexMap`), never `HashMap`s.
+
 #![allow(dead_code)] // TODO: This is fine while we're iterating, but should be removed later.
 
+use crate::cost::CostMetadata;
 use crate::error::FederationError;
 use crate::merge::merge_subgraphs;
 use crate::merge::MergeFailure;
-use crate::schema::FederationSchema;
+use crate::query_graph::build_query_graph::build_federated_query_graph;
+use crate::query_graph::extract_subgraphs_from_supergraph::extract_subgraphs_from_supergraph;
+use crate::query_graph::QueryGraph;
+use crate::schema::{FederationSchema, ValidFederationSchema};
 use crate::subgraph::ValidSubgraph;
 use apollo_compiler::validation::Valid;
 use apollo_compiler::Schema;
+use indexmap::IndexSet;
 
 mod api_schema;
+mod authorization;
 mod compat;
+mod contracts;
+pub mod cost;
+#[doc(hidden)]
 pub mod database;
 pub mod error;
+pub mod field_set;
+#[doc(hidden)]
 pub mod link;
 pub mod merge;
+pub mod prelude;
+#[doc(hidden)]
 pub mod query_graph;
 pub mod query_plan;
 pub mod schema;
 pub mod subgraph;
+mod supergraph_diagnostics;
+mod supergraph_report;
+mod supergraph_sdl;
+#[cfg(test)]
+mod test_supergraph;
+#[cfg(feature = "test_vectors")]
+pub mod test_vectors;
 
 pub use api_schema::ApiSchemaOptions;
+pub use authorization::{AuthorizationMetadata, AuthorizationRequirements, PolicyFilteredDocument};
+pub use link::join_metadata::JoinMetadata;
+pub use link::join_spec_definition::{
+    JoinContextArgument, JoinEnumValueDirectiveArguments, JoinFieldDirectiveArguments,
+    JoinGraphDirectiveArguments, JoinImplementsDirectiveArguments, JoinTypeDirectiveArguments,
+    JoinUnionMemberDirectiveArguments,
+};
+pub use query_graph::extract_subgraphs_from_supergraph::{
+    ValidFederationSubgraph, ValidFederationSubgraphs,
+};
+pub use query_graph::{KeyEdge, MovedField, PlanningDiff, RequiresChange};
+pub use supergraph_diagnostics::SupergraphDiagnostics;
+pub use supergraph_report::SupergraphReport;
+pub use supergraph_sdl::print_supergraph_sdl;
 
 pub struct Supergraph {
-    pub schema: Valid<Schema>,
+    /// The supergraph schema, along with its join metadata (see `join_metadata()`).
+    schema: ValidFederationSchema,
 }
 
 impl Supergraph {
     pub fn new(schema_str: &str) -> Result<Self, FederationError> {
         let schema = Schema::parse_and_validate(schema_str, "schema.graphql")?;
         // TODO: federation-specific validation
-        Ok(Self { schema })
+        Self::try_from(schema)
     }
 
     pub fn compose(subgraphs: Vec<&ValidSubgraph>) -> Result<Self, MergeFailure> {
         let schema = merge_subgraphs(subgraphs)?.schema;
-        Ok(Self { schema })
+        Self::try_from(schema).map_err(|error| MergeFailure {
+            schema: None,
+            errors: vec![format!(
+                "composed supergraph has invalid join spec directive applications: {error}"
+            )],
+            composition_hints: Vec::new(),
+        })
+    }
+
+    /// The schema of this supergraph. It isn't mutable, so that it always matches the join metadata
+    /// parsed from it when loading the supergraph.
+    pub fn schema(&self) -> &Valid<Schema> {
+        self.schema.schema()
+    }
+
+    /// Consumes this supergraph, returning its schema.
+    pub fn into_schema(self) -> Valid<Schema> {
+        self.schema.schema().clone()
+    }
+
+    /// The `@join__*` directive applications of this supergraph, which describe how its types and
+    /// fields are defined and resolved by its subgraphs. These are parsed when the supergraph is
+    /// loaded.
+    pub fn join_metadata(&self) -> &JoinMetadata {
+        self.schema.join_metadata()
     }
 
     /// Generates an API Schema from this supergraph schema. The API Schema represents the combined
@@ -43,14 +110,75 @@ impl Supergraph {
         &self,
         options: ApiSchemaOptions,
     ) -> Result<Valid<Schema>, FederationError> {
-        let api_schema = FederationSchema::new(self.schema.clone().into_inner())?;
+        let api_schema = FederationSchema::new(self.schema().clone().into_inner())?;
         api_schema::to_api_schema(api_schema, options)
     }
+
+    /// Extracts the subgraphs of this supergraph, along with their routing URLs.
+    pub fn extract_subgraphs(&self) -> Result<ValidFederationSubgraphs, FederationError> {
+        extract_subgraphs_from_supergraph(&self.schema, Some(true))
+    }
+
+    /// Summarizes the shape of this supergraph (subgraphs, entities, cross-subgraph jumps, etc.),
+    /// as computed from its federated query graph.
+    pub fn report(&self) -> Result<SupergraphReport, FederationError> {
+        supergraph_report::report(self)
+    }
+
+    /// The differences between this supergraph and the given new one that can change query plans:
+    /// key edges between subgraphs added or removed, fields resolved by different subgraphs, and
+    /// `@requires` conditions added, removed or changed. Operations that don't select any of the
+    /// reported types and fields keep the same query plans.
+    pub fn planning_diff(&self, new: &Supergraph) -> Result<PlanningDiff, FederationError> {
+        let old_query_graph = self.federated_query_graph()?;
+        let new_query_graph = new.federated_query_graph()?;
+        old_query_graph.planning_diff(&new_query_graph)
+    }
+
+    fn federated_query_graph(&self) -> Result<QueryGraph, FederationError> {
+        let api_schema = ValidFederationSchema::new(self.to_api_schema(Default::default())?)?;
+        build_federated_query_graph(self.schema.clone(), api_schema, None, None)
+    }
+
+    /// Loads this supergraph in diagnostic mode, which collects every error of the supergraph
+    /// (instead of stopping at the first one) along with the parts of the supergraph they make
+    /// unusable.
+    pub fn diagnose(&self) -> SupergraphDiagnostics {
+        supergraph_diagnostics::diagnose(self)
+    }
+
+    /// Collects the authorization requirements (`@authenticated`, `@requiresScopes` and `@policy`)
+    /// of the types and fields of this supergraph.
+    pub fn authorization_metadata(&self) -> Result<AuthorizationMetadata, FederationError> {
+        authorization::authorization_metadata(self)
+    }
+
+    /// Collects the cost directives (`@cost` and `@listSize`) of this supergraph, to estimate the
+    /// cost of operations (see `CostMetadata::estimate()`).
+    pub fn cost_metadata(&self) -> Result<CostMetadata, FederationError> {
+        cost::cost_metadata(self)
+    }
+
+    /// Filters this supergraph for a contract: the elements tagged (with `@tag`) with an `exclude`
+    /// tag are made `@inaccessible`, and so are those not tagged with an `include` tag when
+    /// `include` isn't empty. The API schema of the returned supergraph (see `to_api_schema()`) is
+    /// the API of the contract. Fails if filtering hides elements still required by others.
+    pub fn filter_by_tags(
+        &self,
+        include: &IndexSet<String>,
+        exclude: &IndexSet<String>,
+    ) -> Result<Supergraph, FederationError> {
+        contracts::filter_by_tags(self, include, exclude)
+    }
 }
 
-impl From<Valid<Schema>> for Supergraph {
-    fn from(schema: Valid<Schema>) -> Self {
-        Self { schema }
+impl TryFrom<Valid<Schema>> for Supergraph {
+    type Error = FederationError;
+
+    fn try_from(schema: Valid<Schema>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            schema: ValidFederationSchema::new(schema)?,
+        })
     }
 }
 
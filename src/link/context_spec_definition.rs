@@ -0,0 +1,89 @@
+use crate::error::{FederationError, SingleFederationError};
+use crate::link::argument::directive_required_string_argument;
+use crate::link::spec::{Identity, Url, Version};
+use crate::link::spec_definition::{SpecDefinition, SpecDefinitions};
+use crate::schema::FederationSchema;
+use apollo_compiler::schema::{Directive, Name};
+use apollo_compiler::{name, Node, NodeStr};
+use lazy_static::lazy_static;
+
+pub(crate) const CONTEXT_DIRECTIVE_NAME_IN_SPEC: Name = name!("context");
+
+pub(crate) const CONTEXT_NAME_ARGUMENT_NAME: Name = name!("name");
+
+pub(crate) struct ContextDirectiveArguments {
+    /// The name of the context in the supergraph, which is prefixed by the name of the subgraph
+    /// setting it (e.g. `Subgraph1__context`).
+    pub(crate) name: NodeStr,
+}
+
+/// The context spec, which supergraphs link to for the `@context` applications of their types.
+pub(crate) struct ContextSpecDefinition {
+    url: Url,
+    minimum_federation_version: Option<Version>,
+}
+
+impl ContextSpecDefinition {
+    pub(crate) fn new(version: Version, minimum_federation_version: Option<Version>) -> Self {
+        Self {
+            url: Url {
+                identity: Identity::context_identity(),
+                version,
+            },
+            minimum_federation_version,
+        }
+    }
+
+    pub(crate) fn context_directive_arguments(
+        &self,
+        application: &Node<Directive>,
+    ) -> Result<ContextDirectiveArguments, FederationError> {
+        Ok(ContextDirectiveArguments {
+            name: directive_required_string_argument(application, &CONTEXT_NAME_ARGUMENT_NAME)?,
+        })
+    }
+}
+
+impl SpecDefinition for ContextSpecDefinition {
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    fn minimum_federation_version(&self) -> Option<&Version> {
+        self.minimum_federation_version.as_ref()
+    }
+}
+
+lazy_static! {
+    pub(crate) static ref CONTEXT_VERSIONS: SpecDefinitions<ContextSpecDefinition> = {
+        let mut definitions = SpecDefinitions::new(Identity::context_identity());
+        definitions.add(ContextSpecDefinition::new(
+            Version { major: 0, minor: 1 },
+            Some(Version { major: 2, minor: 8 }),
+        ));
+        definitions
+    };
+}
+
+/// Returns the context spec definition the given schema links to, if any.
+pub(crate) fn get_context_spec_definition_from_schema(
+    schema: &FederationSchema,
+) -> Result<Option<&'static ContextSpecDefinition>, FederationError> {
+    let Some(context_link) = schema
+        .metadata()
+        .as_ref()
+        .and_then(|metadata| metadata.for_identity(&Identity::context_identity()))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(
+        CONTEXT_VERSIONS
+            .find(&context_link.url.version)
+            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Invalid supergraph: uses unsupported context spec version {}",
+                    context_link.url.version,
+                ),
+            })?,
+    ))
+}
@@ -0,0 +1,167 @@
+use crate::error::{FederationError, SingleFederationError};
+use crate::link::spec::{Identity, Url, Version};
+use crate::link::spec_definition::{SpecDefinition, SpecDefinitions};
+use crate::schema::FederationSchema;
+use apollo_compiler::schema::{Directive, Name};
+use apollo_compiler::{name, Node};
+use lazy_static::lazy_static;
+
+pub(crate) const COST_DIRECTIVE_NAME_IN_SPEC: Name = name!("cost");
+pub(crate) const LIST_SIZE_DIRECTIVE_NAME_IN_SPEC: Name = name!("listSize");
+
+pub(crate) const COST_WEIGHT_ARGUMENT_NAME: Name = name!("weight");
+pub(crate) const LIST_SIZE_ASSUMED_SIZE_ARGUMENT_NAME: Name = name!("assumedSize");
+pub(crate) const LIST_SIZE_SLICING_ARGUMENTS_ARGUMENT_NAME: Name = name!("slicingArguments");
+pub(crate) const LIST_SIZE_SIZED_FIELDS_ARGUMENT_NAME: Name = name!("sizedFields");
+pub(crate) const LIST_SIZE_REQUIRE_ONE_SLICING_ARGUMENT_ARGUMENT_NAME: Name =
+    name!("requireOneSlicingArgument");
+
+pub(crate) struct CostDirectiveArguments {
+    /// The weight of the type, field, argument or input field.
+    pub(crate) weight: f64,
+}
+
+pub(crate) struct ListSizeDirectiveArguments {
+    /// The size assumed for the list when no slicing argument is given.
+    pub(crate) assumed_size: Option<f64>,
+    /// The arguments of the field whose value is the size of the list (e.g. `first`).
+    pub(crate) slicing_arguments: Vec<Name>,
+    /// The fields of the returned type that are lists of the size given by the slicing arguments
+    /// (e.g. `edges` of a connection).
+    pub(crate) sized_fields: Vec<Name>,
+    /// Whether operations must give exactly one of the slicing arguments (the default).
+    pub(crate) require_one_slicing_argument: bool,
+}
+
+/// The cost spec, which supergraphs link to for the `@cost` and `@listSize` applications used to
+/// estimate the cost of operations.
+pub(crate) struct CostSpecDefinition {
+    url: Url,
+    minimum_federation_version: Option<Version>,
+}
+
+impl CostSpecDefinition {
+    pub(crate) fn new(version: Version, minimum_federation_version: Option<Version>) -> Self {
+        Self {
+            url: Url {
+                identity: Identity::cost_identity(),
+                version,
+            },
+            minimum_federation_version,
+        }
+    }
+
+    pub(crate) fn cost_directive_arguments(
+        &self,
+        application: &Node<Directive>,
+    ) -> Result<CostDirectiveArguments, FederationError> {
+        Ok(CostDirectiveArguments {
+            weight: application
+                .argument_by_name(&COST_WEIGHT_ARGUMENT_NAME)
+                .and_then(|value| value.to_f64())
+                .ok_or_else(|| invalid_argument(application, &COST_WEIGHT_ARGUMENT_NAME))?,
+        })
+    }
+
+    pub(crate) fn list_size_directive_arguments(
+        &self,
+        application: &Node<Directive>,
+    ) -> Result<ListSizeDirectiveArguments, FederationError> {
+        let names_argument =
+            |argument_name: &Name| match application.argument_by_name(argument_name) {
+                None => Ok(Vec::new()),
+                Some(value) if value.is_null() => Ok(Vec::new()),
+                Some(value) => value
+                    .as_list()
+                    .ok_or_else(|| invalid_argument(application, argument_name))?
+                    .iter()
+                    .map(|name| {
+                        name.as_str()
+                            .and_then(|name| Name::new(name).ok())
+                            .ok_or_else(|| invalid_argument(application, argument_name))
+                    })
+                    .collect(),
+            };
+        let assumed_size = match application.argument_by_name(&LIST_SIZE_ASSUMED_SIZE_ARGUMENT_NAME)
+        {
+            None => None,
+            Some(value) if value.is_null() => None,
+            Some(value) => Some(value.to_f64().ok_or_else(|| {
+                invalid_argument(application, &LIST_SIZE_ASSUMED_SIZE_ARGUMENT_NAME)
+            })?),
+        };
+        let require_one_slicing_argument = match application
+            .argument_by_name(&LIST_SIZE_REQUIRE_ONE_SLICING_ARGUMENT_ARGUMENT_NAME)
+        {
+            None => true,
+            Some(value) => value.to_bool().ok_or_else(|| {
+                invalid_argument(
+                    application,
+                    &LIST_SIZE_REQUIRE_ONE_SLICING_ARGUMENT_ARGUMENT_NAME,
+                )
+            })?,
+        };
+        Ok(ListSizeDirectiveArguments {
+            assumed_size,
+            slicing_arguments: names_argument(&LIST_SIZE_SLICING_ARGUMENTS_ARGUMENT_NAME)?,
+            sized_fields: names_argument(&LIST_SIZE_SIZED_FIELDS_ARGUMENT_NAME)?,
+            require_one_slicing_argument,
+        })
+    }
+}
+
+fn invalid_argument(application: &Directive, argument_name: &Name) -> FederationError {
+    SingleFederationError::InvalidGraphQL {
+        message: format!(
+            "Invalid value for argument \"{}\" of @{}.",
+            argument_name, application.name,
+        ),
+        locations: Vec::new(),
+    }
+    .into()
+}
+
+impl SpecDefinition for CostSpecDefinition {
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    fn minimum_federation_version(&self) -> Option<&Version> {
+        self.minimum_federation_version.as_ref()
+    }
+}
+
+lazy_static! {
+    pub(crate) static ref COST_VERSIONS: SpecDefinitions<CostSpecDefinition> = {
+        let mut definitions = SpecDefinitions::new(Identity::cost_identity());
+        definitions.add(CostSpecDefinition::new(
+            Version { major: 0, minor: 1 },
+            Some(Version { major: 2, minor: 9 }),
+        ));
+        definitions
+    };
+}
+
+/// Returns the cost spec definition the given schema links to, if any. Like for the federation spec,
+/// a newer minor version than any known one is read as the latest known one of the same major.
+pub(crate) fn get_cost_spec_definition_from_schema(
+    schema: &FederationSchema,
+) -> Result<Option<&'static CostSpecDefinition>, FederationError> {
+    let Some(cost_link) = schema
+        .metadata()
+        .as_ref()
+        .and_then(|metadata| metadata.for_identity(&Identity::cost_identity()))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(
+        COST_VERSIONS
+            .find_compatible(&cost_link.url.version)
+            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Invalid supergraph: uses unsupported cost spec version {}",
+                    cost_link.url.version,
+                ),
+            })?,
+    ))
+}
@@ -1,7 +1,8 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
 use apollo_compiler::ast::{Directive, DirectiveLocation, Type};
 use apollo_compiler::Schema;
+use indexmap::IndexMap;
 
 use crate::link::{
     spec::{Identity, Url},
@@ -29,10 +30,10 @@ pub fn links_metadata(schema: &Schema) -> Result<Option<LinksMetadata>, LinkErro
     // all of the @link usages (starting with the bootstrapping one) and extract their metadata.
     let link_name_in_schema = &bootstrap_directive.unwrap().name;
     let mut links = Vec::new();
-    let mut by_identity = HashMap::new();
-    let mut by_name_in_schema = HashMap::new();
-    let mut types_by_imported_name = HashMap::new();
-    let mut directives_by_imported_name = HashMap::new();
+    let mut by_identity = IndexMap::new();
+    let mut by_name_in_schema = IndexMap::new();
+    let mut types_by_imported_name = IndexMap::new();
+    let mut directives_by_imported_name = IndexMap::new();
     let link_applications = schema
         .schema_definition
         .directives
@@ -1,6 +1,7 @@
 use crate::error::{FederationError, SingleFederationError};
 use crate::link::argument::{
-    directive_optional_boolean_argument, directive_required_fieldset_argument,
+    directive_optional_boolean_argument, directive_optional_string_argument,
+    directive_required_fieldset_argument, directive_required_string_argument,
 };
 use crate::link::spec::{Identity, Url, Version};
 use crate::link::spec_definition::{SpecDefinition, SpecDefinitions};
@@ -16,15 +17,21 @@ pub(crate) const FEDERATION_ENTITY_TYPE_NAME_IN_SPEC: Name = name!("_Entity");
 pub(crate) const FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC: Name = name!("key");
 pub(crate) const FEDERATION_INTERFACEOBJECT_DIRECTIVE_NAME_IN_SPEC: Name = name!("interfaceObject");
 pub(crate) const FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC: Name = name!("external");
+pub(crate) const FEDERATION_EXTENDS_DIRECTIVE_NAME_IN_SPEC: Name = name!("extends");
 pub(crate) const FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC: Name = name!("requires");
 pub(crate) const FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC: Name = name!("provides");
 pub(crate) const FEDERATION_SHAREABLE_DIRECTIVE_NAME_IN_SPEC: Name = name!("shareable");
 pub(crate) const FEDERATION_OVERRIDE_DIRECTIVE_NAME_IN_SPEC: Name = name!("override");
+pub(crate) const FEDERATION_TAG_DIRECTIVE_NAME_IN_SPEC: Name = name!("tag");
+pub(crate) const FEDERATION_CONTEXT_DIRECTIVE_NAME_IN_SPEC: Name = name!("context");
+pub(crate) const FEDERATION_FROM_CONTEXT_DIRECTIVE_NAME_IN_SPEC: Name = name!("fromContext");
 
 pub(crate) const FEDERATION_FIELDS_ARGUMENT_NAME: Name = name!("fields");
 pub(crate) const FEDERATION_RESOLVABLE_ARGUMENT_NAME: Name = name!("resolvable");
 pub(crate) const FEDERATION_REASON_ARGUMENT_NAME: Name = name!("reason");
 pub(crate) const FEDERATION_FROM_ARGUMENT_NAME: Name = name!("from");
+pub(crate) const FEDERATION_NAME_ARGUMENT_NAME: Name = name!("name");
+pub(crate) const FEDERATION_FIELD_ARGUMENT_NAME: Name = name!("field");
 
 pub(crate) struct KeyDirectiveArguments {
     pub(crate) fields: NodeStr,
@@ -39,6 +46,16 @@ pub(crate) struct ProvidesDirectiveArguments {
     pub(crate) fields: NodeStr,
 }
 
+pub(crate) struct ContextDirectiveArguments {
+    pub(crate) name: NodeStr,
+}
+
+pub(crate) struct FromContextDirectiveArguments {
+    /// The context and selection the argument's value is taken from, e.g. `$ctx { id }`.
+    pub(crate) field: Option<NodeStr>,
+}
+
+#[derive(Debug)]
 pub(crate) struct FederationSpecDefinition {
     url: Url,
 }
@@ -47,12 +64,66 @@ impl FederationSpecDefinition {
     pub(crate) fn new(version: Version) -> Self {
         Self {
             url: Url {
-                identity: Identity::join_identity(),
+                identity: Identity::federation_identity(),
                 version,
             },
         }
     }
 
+    /// The definition to use for the given federation spec version. Minor versions newer than
+    /// those we know of are accepted (with a warning) since they are backwards-compatible, and
+    /// federation 1 versions are auto-upgraded (with a warning too), but other unknown major
+    /// versions are rejected.
+    pub(crate) fn for_version(
+        version: &Version,
+    ) -> Result<FederationSpecDefinitionLookup, FederationError> {
+        if version.major == 1 {
+            let definition = FEDERATION_VERSIONS
+                .find(&FEDERATION_1_UPGRADE_VERSION)
+                .ok_or_else(|| SingleFederationError::Internal {
+                    message: format!(
+                        "Missing definition for federation spec version {}",
+                        FEDERATION_1_UPGRADE_VERSION
+                    ),
+                })?;
+            return Ok(FederationSpecDefinitionLookup {
+                definition,
+                warning: Some(format!(
+                    "Federation spec version {} is a federation 1 version, and will be handled as if it were {}",
+                    version, FEDERATION_1_UPGRADE_VERSION,
+                )),
+            });
+        }
+        let Some(definition) = FEDERATION_VERSIONS.find_compatible(version) else {
+            let supported_versions = FEDERATION_VERSIONS
+                .versions()
+                .map(|version| version.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(SingleFederationError::UnknownFederationLinkVersion {
+                message: format!(
+                    "Invalid version {} for the federation feature in @link directive on schema (supported versions: {})",
+                    version, supported_versions,
+                ),
+            }
+            .into());
+        };
+        let warning = if definition.version() != version {
+            Some(format!(
+                "Federation spec version {} is newer than the latest version known to this library ({}), and will be handled as if it were {}",
+                version,
+                definition.version(),
+                definition.version(),
+            ))
+        } else {
+            None
+        };
+        Ok(FederationSpecDefinitionLookup {
+            definition,
+            warning,
+        })
+    }
+
     pub(crate) fn entity_type_definition<'schema>(
         &self,
         schema: &'schema FederationSchema,
@@ -340,6 +411,118 @@ impl FederationSpecDefinition {
             })],
         })
     }
+
+    pub(crate) fn context_directive_definition<'schema>(
+        &self,
+        schema: &'schema FederationSchema,
+    ) -> Result<Option<&'schema Node<DirectiveDefinition>>, FederationError> {
+        if *self.version() < (Version { major: 2, minor: 8 }) {
+            return Ok(None);
+        }
+        self.directive_definition(schema, &FEDERATION_CONTEXT_DIRECTIVE_NAME_IN_SPEC)?
+            .ok_or_else(|| {
+                SingleFederationError::Internal {
+                    message: format!(
+                        "Unexpectedly could not find federation spec's \"@{}\" directive definition",
+                        FEDERATION_CONTEXT_DIRECTIVE_NAME_IN_SPEC
+                    ),
+                }.into()
+            })
+            .map(Some)
+    }
+
+    pub(crate) fn context_directive(
+        &self,
+        schema: &FederationSchema,
+        name: NodeStr,
+    ) -> Result<Directive, FederationError> {
+        if *self.version() < (Version { major: 2, minor: 8 }) {
+            return Err(SingleFederationError::Internal {
+                message: "Must be using federation >= v2.8 to use context".to_owned(),
+            }
+            .into());
+        }
+        let name_in_schema = self
+            .directive_name_in_schema(schema, &FEDERATION_CONTEXT_DIRECTIVE_NAME_IN_SPEC)?
+            .ok_or_else(|| SingleFederationError::Internal {
+                message: "Unexpectedly could not find federation spec in schema".to_owned(),
+            })?;
+        Ok(Directive {
+            name: name_in_schema,
+            arguments: vec![Node::new(Argument {
+                name: FEDERATION_NAME_ARGUMENT_NAME,
+                value: Node::new(Value::String(name)),
+            })],
+        })
+    }
+
+    pub(crate) fn context_directive_arguments(
+        &self,
+        application: &Node<Directive>,
+    ) -> Result<ContextDirectiveArguments, FederationError> {
+        Ok(ContextDirectiveArguments {
+            name: directive_required_string_argument(application, &FEDERATION_NAME_ARGUMENT_NAME)?,
+        })
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn from_context_directive_definition<'schema>(
+        &self,
+        schema: &'schema FederationSchema,
+    ) -> Result<Option<&'schema Node<DirectiveDefinition>>, FederationError> {
+        if *self.version() < (Version { major: 2, minor: 8 }) {
+            return Ok(None);
+        }
+        self.directive_definition(schema, &FEDERATION_FROM_CONTEXT_DIRECTIVE_NAME_IN_SPEC)?
+            .ok_or_else(|| {
+                SingleFederationError::Internal {
+                    message: format!(
+                        "Unexpectedly could not find federation spec's \"@{}\" directive definition",
+                        FEDERATION_FROM_CONTEXT_DIRECTIVE_NAME_IN_SPEC
+                    ),
+                }.into()
+            })
+            .map(Some)
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn from_context_directive(
+        &self,
+        schema: &FederationSchema,
+        field: NodeStr,
+    ) -> Result<Directive, FederationError> {
+        if *self.version() < (Version { major: 2, minor: 8 }) {
+            return Err(SingleFederationError::Internal {
+                message: "Must be using federation >= v2.8 to use context".to_owned(),
+            }
+            .into());
+        }
+        let name_in_schema = self
+            .directive_name_in_schema(schema, &FEDERATION_FROM_CONTEXT_DIRECTIVE_NAME_IN_SPEC)?
+            .ok_or_else(|| SingleFederationError::Internal {
+                message: "Unexpectedly could not find federation spec in schema".to_owned(),
+            })?;
+        Ok(Directive {
+            name: name_in_schema,
+            arguments: vec![Node::new(Argument {
+                name: FEDERATION_FIELD_ARGUMENT_NAME,
+                value: Node::new(Value::String(field)),
+            })],
+        })
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub(crate) fn from_context_directive_arguments(
+        &self,
+        application: &Node<Directive>,
+    ) -> Result<FromContextDirectiveArguments, FederationError> {
+        Ok(FromContextDirectiveArguments {
+            field: directive_optional_string_argument(
+                application,
+                &FEDERATION_FIELD_ARGUMENT_NAME,
+            )?,
+        })
+    }
 }
 
 impl SpecDefinition for FederationSpecDefinition {
@@ -379,10 +562,39 @@ lazy_static! {
             major: 2,
             minor: 5,
         }));
+        definitions.add(FederationSpecDefinition::new(Version {
+            major: 2,
+            minor: 6,
+        }));
+        definitions.add(FederationSpecDefinition::new(Version {
+            major: 2,
+            minor: 7,
+        }));
+        definitions.add(FederationSpecDefinition::new(Version {
+            major: 2,
+            minor: 8,
+        }));
         definitions
     };
 }
 
+/// The result of looking up a federation spec definition for a requested version.
+pub(crate) struct FederationSpecDefinitionLookup {
+    pub(crate) definition: &'static FederationSpecDefinition,
+    /// Set when the requested version is a newer minor version than any we know of, in which case
+    /// the latest known definition with the same major version is used instead, or when it's a
+    /// federation 1 version, in which case it's upgraded to the first federation 2 version.
+    pub(crate) warning: Option<String>,
+}
+
+/// The version federation 1 schemas are upgraded to. Federation 2 directives are a superset of
+/// federation 1 ones, so their federation 1 applications can be read with this definition.
+const FEDERATION_1_UPGRADE_VERSION: Version = Version { major: 2, minor: 0 };
+
+/// The federation spec definition to use for the given subgraph schema (see
+/// `FederationSpecDefinition::for_version()` for how versions we don't know of are handled). The lookup
+/// warning isn't returned, as it's reported once when subgraphs are composed (see
+/// `merge_subgraphs()`) rather than by each of the many users of the definition.
 pub(crate) fn get_federation_spec_definition_from_subgraph(
     schema: &FederationSchema,
 ) -> Result<&'static FederationSpecDefinition, FederationError> {
@@ -393,10 +605,61 @@ pub(crate) fn get_federation_spec_definition_from_subgraph(
         .ok_or_else(|| SingleFederationError::Internal {
             message: "Subgraph unexpectedly does not use federation spec".to_owned(),
         })?;
-    Ok(FEDERATION_VERSIONS
-        .find(&federation_link.url.version)
-        .ok_or_else(|| SingleFederationError::Internal {
-            message: "Subgraph unexpectedly does not use a supported federation spec version"
-                .to_owned(),
-        })?)
+    Ok(FederationSpecDefinition::for_version(&federation_link.url.version)?.definition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_known_federation_versions() {
+        for version in FEDERATION_VERSIONS.versions() {
+            let lookup = FederationSpecDefinition::for_version(version).unwrap();
+            assert_eq!(lookup.definition.version(), version);
+            assert_eq!(
+                *lookup.definition.identity(),
+                Identity::federation_identity()
+            );
+            assert!(lookup.warning.is_none());
+        }
+    }
+
+    #[test]
+    fn accepts_newer_minor_federation_version_with_warning() {
+        let requested = Version {
+            major: 2,
+            minor: 99,
+        };
+        let lookup = FederationSpecDefinition::for_version(&requested).unwrap();
+        assert_eq!(
+            lookup.definition.version(),
+            FEDERATION_VERSIONS.latest().unwrap().version()
+        );
+        assert!(lookup.warning.unwrap().contains("2.99"));
+    }
+
+    #[test]
+    fn upgrades_federation_1_versions() {
+        let lookup =
+            FederationSpecDefinition::for_version(&Version { major: 1, minor: 0 }).unwrap();
+        assert_eq!(*lookup.definition.version(), Version { major: 2, minor: 0 });
+        assert!(lookup.warning.unwrap().contains("federation 1"));
+    }
+
+    #[test]
+    fn rejects_unknown_major_federation_version() {
+        for requested in [
+            Version { major: 0, minor: 1 },
+            Version { major: 3, minor: 0 },
+        ] {
+            let Err(FederationError::SingleFederationError(
+                SingleFederationError::UnknownFederationLinkVersion { message },
+            )) = FederationSpecDefinition::for_version(&requested)
+            else {
+                panic!("Expected an unknown federation link version error");
+            };
+            assert!(message.contains(&requested.to_string()));
+        }
+    }
 }
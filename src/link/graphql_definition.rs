@@ -2,6 +2,7 @@ use crate::error::FederationError;
 use crate::link::argument::{
     directive_optional_string_argument, directive_optional_variable_boolean_argument,
 };
+use apollo_compiler::ast::{Argument, Value};
 use apollo_compiler::executable::{Directive, Name};
 use apollo_compiler::{name, Node, NodeStr};
 
@@ -11,6 +12,12 @@ pub(crate) struct DeferDirectiveArguments {
     if_: Option<BooleanOrVariable>,
 }
 
+impl DeferDirectiveArguments {
+    pub(crate) fn label(&self) -> Option<&NodeStr> {
+        self.label.as_ref()
+    }
+}
+
 pub(crate) fn defer_directive_arguments(
     application: &Node<Directive>,
 ) -> Result<DeferDirectiveArguments, FederationError> {
@@ -31,6 +38,26 @@ pub(crate) struct OperationConditional {
     pub(crate) value: BooleanOrVariable,
 }
 
+impl OperationConditional {
+    /// The `@skip`/`@include` application this conditional corresponds to.
+    pub(crate) fn to_directive(&self) -> Directive {
+        let value = match &self.value {
+            BooleanOrVariable::Boolean(value) => Value::Boolean(*value),
+            BooleanOrVariable::Variable(name) => Value::Variable(name.clone()),
+        };
+        Directive {
+            name: match self.kind {
+                OperationConditionalKind::Include => name!("include"),
+                OperationConditionalKind::Skip => name!("skip"),
+            },
+            arguments: vec![Node::new(Argument {
+                name: name!("if"),
+                value: Node::new(value),
+            })],
+        }
+    }
+}
+
 #[derive(
     Debug,
     Clone,
@@ -0,0 +1,266 @@
+//! Implements the join metadata of a supergraph, i.e. its `@join__graph`, `@join__type`,
+//! `@join__field`, `@join__implements`, `@join__unionMember` and `@join__enumValue` applications,
+//! parsed once so that the code reading them doesn't have to extract directive arguments itself.
+use crate::error::{FederationError, SingleFederationError};
+use crate::link::join_spec_definition::{
+    get_join_spec_definition_from_schema, JoinEnumValueDirectiveArguments,
+    JoinFieldDirectiveArguments, JoinGraphDirectiveArguments, JoinImplementsDirectiveArguments,
+    JoinSpecDefinition, JoinTypeDirectiveArguments, JoinUnionMemberDirectiveArguments,
+};
+use crate::schema::position::FieldDefinitionPosition;
+use crate::schema::FederationSchema;
+use apollo_compiler::schema::{ExtendedType, Name};
+use indexmap::IndexMap;
+
+/// The join spec directive applications of a supergraph, by the position of the element they're
+/// applied to. Supergraphs that don't use the join spec have no applications.
+#[derive(Debug, Clone, Default)]
+pub struct JoinMetadata {
+    /// The `@join__graph` applications, by `join__Graph` enum value name.
+    graphs: IndexMap<Name, JoinGraphDirectiveArguments>,
+    /// The `@join__type` applications, by type name.
+    types: IndexMap<Name, Vec<JoinTypeDirectiveArguments>>,
+    /// The `@join__implements` applications, by type name.
+    implements: IndexMap<Name, Vec<JoinImplementsDirectiveArguments>>,
+    /// The `@join__unionMember` applications, by union name.
+    union_members: IndexMap<Name, Vec<JoinUnionMemberDirectiveArguments>>,
+    /// The `@join__field` applications, by parent type name and field name. This includes the
+    /// fields of input object types.
+    fields: IndexMap<Name, IndexMap<Name, Vec<JoinFieldDirectiveArguments>>>,
+    /// The `@join__enumValue` applications, by enum name and value name.
+    enum_values: IndexMap<Name, IndexMap<Name, Vec<JoinEnumValueDirectiveArguments>>>,
+}
+
+impl JoinMetadata {
+    pub(crate) fn new(supergraph_schema: &FederationSchema) -> Result<Self, FederationError> {
+        let mut metadata = Self::default();
+        let Some(join_spec_definition) = get_join_spec_definition_from_schema(supergraph_schema)?
+        else {
+            return Ok(metadata);
+        };
+        metadata.graphs = join_graphs(supergraph_schema, join_spec_definition)?;
+
+        let type_directive_name = join_spec_definition
+            .type_directive_definition(supergraph_schema)?
+            .name
+            .clone();
+        let field_directive_name = join_spec_definition
+            .field_directive_definition(supergraph_schema)?
+            .name
+            .clone();
+        let implements_directive_name = join_spec_definition
+            .implements_directive_definition(supergraph_schema)?
+            .map(|definition| definition.name.clone());
+        let union_member_directive_name = join_spec_definition
+            .union_member_directive_definition(supergraph_schema)?
+            .map(|definition| definition.name.clone());
+        let enum_value_directive_name = join_spec_definition
+            .enum_value_directive_definition(supergraph_schema)?
+            .map(|definition| definition.name.clone());
+
+        for (type_name, type_) in &supergraph_schema.schema().types {
+            let type_applications = type_
+                .directives()
+                .get_all(&type_directive_name)
+                .map(|directive| join_spec_definition.type_directive_arguments(directive))
+                .collect::<Result<Vec<_>, _>>()?;
+            if !type_applications.is_empty() {
+                metadata.types.insert(type_name.clone(), type_applications);
+            }
+            if let Some(implements_directive_name) = &implements_directive_name {
+                let implements_applications = type_
+                    .directives()
+                    .get_all(implements_directive_name)
+                    .map(|directive| join_spec_definition.implements_directive_arguments(directive))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !implements_applications.is_empty() {
+                    metadata
+                        .implements
+                        .insert(type_name.clone(), implements_applications);
+                }
+            }
+            let field_directives = match type_ {
+                ExtendedType::Object(type_) => type_
+                    .fields
+                    .iter()
+                    .map(|(name, field)| (name, &field.directives))
+                    .collect::<Vec<_>>(),
+                ExtendedType::Interface(type_) => type_
+                    .fields
+                    .iter()
+                    .map(|(name, field)| (name, &field.directives))
+                    .collect(),
+                ExtendedType::InputObject(type_) => type_
+                    .fields
+                    .iter()
+                    .map(|(name, field)| (name, &field.directives))
+                    .collect(),
+                ExtendedType::Union(type_) => {
+                    if let Some(union_member_directive_name) = &union_member_directive_name {
+                        let union_member_applications = type_
+                            .directives
+                            .get_all(union_member_directive_name)
+                            .map(|directive| {
+                                join_spec_definition.union_member_directive_arguments(directive)
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        if !union_member_applications.is_empty() {
+                            metadata
+                                .union_members
+                                .insert(type_name.clone(), union_member_applications);
+                        }
+                    }
+                    continue;
+                }
+                ExtendedType::Enum(type_) => {
+                    if let Some(enum_value_directive_name) = &enum_value_directive_name {
+                        for (value_name, value) in &type_.values {
+                            let enum_value_applications = value
+                                .directives
+                                .get_all(enum_value_directive_name)
+                                .map(|directive| {
+                                    join_spec_definition.enum_value_directive_arguments(directive)
+                                })
+                                .collect::<Result<Vec<_>, _>>()?;
+                            if !enum_value_applications.is_empty() {
+                                metadata
+                                    .enum_values
+                                    .entry(type_name.clone())
+                                    .or_default()
+                                    .insert(value_name.clone(), enum_value_applications);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                ExtendedType::Scalar(_) => continue,
+            };
+            for (field_name, directives) in field_directives {
+                let field_applications = directives
+                    .get_all(&field_directive_name)
+                    .map(|directive| join_spec_definition.field_directive_arguments(directive))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !field_applications.is_empty() {
+                    metadata
+                        .fields
+                        .entry(type_name.clone())
+                        .or_default()
+                        .insert(field_name.clone(), field_applications);
+                }
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// The `@join__graph` applications of the supergraph, by `join__Graph` enum value name.
+    pub fn graphs(&self) -> &IndexMap<Name, JoinGraphDirectiveArguments> {
+        &self.graphs
+    }
+
+    /// The name of the subgraph of the given `join__Graph` enum value, if any.
+    pub fn subgraph_name(&self, graph_enum_value: &str) -> Option<&str> {
+        self.graphs
+            .get(graph_enum_value)
+            .map(|graph| graph.name.as_str())
+    }
+
+    /// The `@join__type` applications of the given type.
+    pub fn type_applications(&self, type_name: &str) -> &[JoinTypeDirectiveArguments] {
+        self.types.get(type_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The `@join__implements` applications of the given object or interface type.
+    pub fn implements_applications(&self, type_name: &str) -> &[JoinImplementsDirectiveArguments] {
+        self.implements.get(type_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The `@join__unionMember` applications of the given union type.
+    pub fn union_member_applications(
+        &self,
+        union_name: &str,
+    ) -> &[JoinUnionMemberDirectiveArguments] {
+        self.union_members
+            .get(union_name)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The `@join__field` applications of the given field (of an object, interface or input object
+    /// type).
+    pub fn field_applications(
+        &self,
+        type_name: &str,
+        field_name: &str,
+    ) -> &[JoinFieldDirectiveArguments] {
+        self.fields
+            .get(type_name)
+            .and_then(|fields| fields.get(field_name))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The `@join__enumValue` applications of the given enum value.
+    pub fn enum_value_applications(
+        &self,
+        enum_name: &str,
+        value_name: &str,
+    ) -> &[JoinEnumValueDirectiveArguments] {
+        self.enum_values
+            .get(enum_name)
+            .and_then(|values| values.get(value_name))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn field_applications_at(
+        &self,
+        field_definition_position: &FieldDefinitionPosition,
+    ) -> &[JoinFieldDirectiveArguments] {
+        self.field_applications(
+            field_definition_position.type_name(),
+            field_definition_position.field_name(),
+        )
+    }
+
+    /// Iterates over the `@join__field` applications of the supergraph, by parent type name and
+    /// field name.
+    pub(crate) fn all_field_applications(
+        &self,
+    ) -> impl Iterator<Item = (&Name, &Name, &JoinFieldDirectiveArguments)> {
+        self.fields.iter().flat_map(|(type_name, fields)| {
+            fields.iter().flat_map(move |(field_name, applications)| {
+                applications
+                    .iter()
+                    .map(move |application| (type_name, field_name, application))
+            })
+        })
+    }
+}
+
+/// Parses the `@join__graph` application of each value of the `join__Graph` enum, by enum value
+/// name. Every value must have one.
+fn join_graphs(
+    supergraph_schema: &FederationSchema,
+    join_spec_definition: &JoinSpecDefinition,
+) -> Result<IndexMap<Name, JoinGraphDirectiveArguments>, FederationError> {
+    let graph_directive_definition =
+        join_spec_definition.graph_directive_definition(supergraph_schema)?;
+    let mut graphs = IndexMap::new();
+    for (enum_value_name, enum_value_definition) in join_spec_definition
+        .graph_enum_definition(supergraph_schema)?
+        .values
+        .iter()
+    {
+        let graph_application = enum_value_definition
+            .directives
+            .get(&graph_directive_definition.name)
+            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Value \"{}\" of join__Graph enum has no @join__graph directive",
+                    enum_value_name
+                ),
+            })?;
+        graphs.insert(
+            enum_value_name.clone(),
+            join_spec_definition.graph_directive_arguments(graph_application)?,
+        );
+    }
+    Ok(graphs)
+}
@@ -7,9 +7,12 @@ use crate::link::argument::{
 use crate::link::spec::{Identity, Url, Version};
 use crate::link::spec_definition::{SpecDefinition, SpecDefinitions};
 use crate::schema::FederationSchema;
-use apollo_compiler::schema::{Directive, DirectiveDefinition, EnumType, ExtendedType, Name};
+use apollo_compiler::schema::{
+    Directive, DirectiveDefinition, EnumType, ExtendedType, Name, Value,
+};
 use apollo_compiler::{name, Node, NodeStr};
 use lazy_static::lazy_static;
+use std::ops::Deref;
 
 pub(crate) const JOIN_GRAPH_ENUM_NAME_IN_SPEC: Name = name!("Graph");
 pub(crate) const JOIN_GRAPH_DIRECTIVE_NAME_IN_SPEC: Name = name!("graph");
@@ -31,45 +34,95 @@ pub(crate) const JOIN_PROVIDES_ARGUMENT_NAME: Name = name!("provides");
 pub(crate) const JOIN_TYPE_ARGUMENT_NAME: Name = name!("type");
 pub(crate) const JOIN_EXTERNAL_ARGUMENT_NAME: Name = name!("external");
 pub(crate) const JOIN_OVERRIDE_ARGUMENT_NAME: Name = name!("override");
-pub(crate) const JOIN_USEROVERRIDDEN_ARGUMENT_NAME: Name = name!("usedOverridden");
+pub(crate) const JOIN_USEDOVERRIDDEN_ARGUMENT_NAME: Name = name!("usedOverridden");
+pub(crate) const JOIN_OVERRIDE_LABEL_ARGUMENT_NAME: Name = name!("overrideLabel");
 pub(crate) const JOIN_INTERFACE_ARGUMENT_NAME: Name = name!("interface");
 pub(crate) const JOIN_MEMBER_ARGUMENT_NAME: Name = name!("member");
+pub(crate) const JOIN_CONTEXTARGUMENTS_ARGUMENT_NAME: Name = name!("contextArguments");
+pub(crate) const JOIN_CONTEXT_ARGUMENT_NAME: Name = name!("context");
+pub(crate) const JOIN_SELECTION_ARGUMENT_NAME: Name = name!("selection");
+
+/// The arguments of a `@join__graph` application, which maps a value of the `join__Graph` enum to a
+/// subgraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinGraphDirectiveArguments {
+    /// The name of the subgraph.
+    pub name: NodeStr,
+    /// The routing URL of the subgraph.
+    pub url: NodeStr,
+}
 
-pub(crate) struct GraphDirectiveArguments {
-    pub(crate) name: NodeStr,
-    pub(crate) url: NodeStr,
+/// The arguments of a `@join__type` application, which marks a type as defined by a subgraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinTypeDirectiveArguments {
+    /// The `join__Graph` enum value of the subgraph.
+    pub graph: Name,
+    pub key: Option<NodeStr>,
+    pub extension: bool,
+    pub resolvable: bool,
+    pub is_interface_object: bool,
 }
 
-pub(crate) struct TypeDirectiveArguments {
-    pub(crate) graph: Name,
-    pub(crate) key: Option<NodeStr>,
-    pub(crate) extension: bool,
-    pub(crate) resolvable: bool,
-    pub(crate) is_interface_object: bool,
+/// The arguments of a `@join__field` application, which describes how a subgraph resolves a field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinFieldDirectiveArguments {
+    /// The `join__Graph` enum value of the subgraph, if any (fields without it are resolved by
+    /// every subgraph defining their parent type).
+    pub graph: Option<Name>,
+    pub requires: Option<NodeStr>,
+    pub provides: Option<NodeStr>,
+    /// The type of the field in the subgraph, when it differs from the supergraph one.
+    pub type_: Option<NodeStr>,
+    pub external: Option<bool>,
+    /// The name of the subgraph the field is overridden from.
+    pub override_: Option<NodeStr>,
+    /// Whether the subgraph the field is overridden from still uses it (e.g. in a key), so it must
+    /// keep it as an external field.
+    pub used_overridden: Option<bool>,
+    /// The label of a progressive override (e.g. `percent(50)`), for join spec 0.4 and later.
+    pub override_label: Option<NodeStr>,
+    pub context_arguments: Option<Vec<JoinContextArgument>>,
 }
 
-pub(crate) struct FieldDirectiveArguments {
-    pub(crate) graph: Option<Name>,
-    pub(crate) requires: Option<NodeStr>,
-    pub(crate) provides: Option<NodeStr>,
-    pub(crate) type_: Option<NodeStr>,
-    pub(crate) external: Option<bool>,
-    pub(crate) override_: Option<NodeStr>,
-    pub(crate) user_overridden: Option<bool>,
+/// An element of the `contextArguments` of a `@join__field`, describing a field argument whose
+/// value is taken from a context (i.e. an argument using `@fromContext` in its subgraph).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinContextArgument {
+    /// The name of the argument.
+    pub name: NodeStr,
+    /// The type of the argument, as printed in the subgraph.
+    pub type_: NodeStr,
+    /// The name of the context in the supergraph, which is prefixed by the name of the subgraph
+    /// setting it (e.g. `Subgraph1__context`).
+    pub context: NodeStr,
+    /// The selection of the argument value within the context, e.g. `{ id }`.
+    pub selection: NodeStr,
 }
 
-pub(crate) struct ImplementsDirectiveArguments {
-    pub(crate) graph: Name,
-    pub(crate) interface: NodeStr,
+/// The arguments of a `@join__implements` application, which marks an object or interface type as
+/// implementing an interface in a subgraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinImplementsDirectiveArguments {
+    /// The `join__Graph` enum value of the subgraph.
+    pub graph: Name,
+    pub interface: NodeStr,
 }
 
-pub(crate) struct UnionMemberDirectiveArguments {
-    pub(crate) graph: Name,
-    pub(crate) member: NodeStr,
+/// The arguments of a `@join__unionMember` application, which marks an object type as a member of
+/// a union in a subgraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinUnionMemberDirectiveArguments {
+    /// The `join__Graph` enum value of the subgraph.
+    pub graph: Name,
+    pub member: NodeStr,
 }
 
-pub(crate) struct EnumValueDirectiveArguments {
-    pub(crate) graph: Name,
+/// The arguments of a `@join__enumValue` application, which marks an enum value as defined by a
+/// subgraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinEnumValueDirectiveArguments {
+    /// The `join__Graph` enum value of the subgraph.
+    pub graph: Name,
 }
 
 pub(crate) struct JoinSpecDefinition {
@@ -126,8 +179,8 @@ impl JoinSpecDefinition {
     pub(crate) fn graph_directive_arguments(
         &self,
         application: &Node<Directive>,
-    ) -> Result<GraphDirectiveArguments, FederationError> {
-        Ok(GraphDirectiveArguments {
+    ) -> Result<JoinGraphDirectiveArguments, FederationError> {
+        Ok(JoinGraphDirectiveArguments {
             name: directive_required_string_argument(application, &JOIN_NAME_ARGUMENT_NAME)?,
             url: directive_required_string_argument(application, &JOIN_URL_ARGUMENT_NAME)?,
         })
@@ -149,8 +202,8 @@ impl JoinSpecDefinition {
     pub(crate) fn type_directive_arguments(
         &self,
         application: &Node<Directive>,
-    ) -> Result<TypeDirectiveArguments, FederationError> {
-        Ok(TypeDirectiveArguments {
+    ) -> Result<JoinTypeDirectiveArguments, FederationError> {
+        Ok(JoinTypeDirectiveArguments {
             graph: directive_required_enum_argument(application, &JOIN_GRAPH_ARGUMENT_NAME)?,
             key: directive_optional_fieldset_argument(application, &JOIN_KEY_ARGUMENT_NAME)?,
             extension: directive_optional_boolean_argument(
@@ -187,8 +240,8 @@ impl JoinSpecDefinition {
     pub(crate) fn field_directive_arguments(
         &self,
         application: &Node<Directive>,
-    ) -> Result<FieldDirectiveArguments, FederationError> {
-        Ok(FieldDirectiveArguments {
+    ) -> Result<JoinFieldDirectiveArguments, FederationError> {
+        Ok(JoinFieldDirectiveArguments {
             graph: directive_optional_enum_argument(application, &JOIN_GRAPH_ARGUMENT_NAME)?,
             requires: directive_optional_fieldset_argument(
                 application,
@@ -207,10 +260,15 @@ impl JoinSpecDefinition {
                 application,
                 &JOIN_OVERRIDE_ARGUMENT_NAME,
             )?,
-            user_overridden: directive_optional_boolean_argument(
+            used_overridden: directive_optional_boolean_argument(
+                application,
+                &JOIN_USEDOVERRIDDEN_ARGUMENT_NAME,
+            )?,
+            override_label: directive_optional_string_argument(
                 application,
-                &JOIN_USEROVERRIDDEN_ARGUMENT_NAME,
+                &JOIN_OVERRIDE_LABEL_ARGUMENT_NAME,
             )?,
+            context_arguments: context_arguments(application)?,
         })
     }
 
@@ -234,8 +292,8 @@ impl JoinSpecDefinition {
     pub(crate) fn implements_directive_arguments(
         &self,
         application: &Node<Directive>,
-    ) -> Result<ImplementsDirectiveArguments, FederationError> {
-        Ok(ImplementsDirectiveArguments {
+    ) -> Result<JoinImplementsDirectiveArguments, FederationError> {
+        Ok(JoinImplementsDirectiveArguments {
             graph: directive_required_enum_argument(application, &JOIN_GRAPH_ARGUMENT_NAME)?,
             interface: directive_required_string_argument(
                 application,
@@ -264,8 +322,8 @@ impl JoinSpecDefinition {
     pub(crate) fn union_member_directive_arguments(
         &self,
         application: &Node<Directive>,
-    ) -> Result<UnionMemberDirectiveArguments, FederationError> {
-        Ok(UnionMemberDirectiveArguments {
+    ) -> Result<JoinUnionMemberDirectiveArguments, FederationError> {
+        Ok(JoinUnionMemberDirectiveArguments {
             graph: directive_required_enum_argument(application, &JOIN_GRAPH_ARGUMENT_NAME)?,
             member: directive_required_string_argument(application, &JOIN_MEMBER_ARGUMENT_NAME)?,
         })
@@ -291,13 +349,76 @@ impl JoinSpecDefinition {
     pub(crate) fn enum_value_directive_arguments(
         &self,
         application: &Node<Directive>,
-    ) -> Result<EnumValueDirectiveArguments, FederationError> {
-        Ok(EnumValueDirectiveArguments {
+    ) -> Result<JoinEnumValueDirectiveArguments, FederationError> {
+        Ok(JoinEnumValueDirectiveArguments {
             graph: directive_required_enum_argument(application, &JOIN_GRAPH_ARGUMENT_NAME)?,
         })
     }
 }
 
+pub(crate) fn get_join_spec_definition_from_schema(
+    schema: &FederationSchema,
+) -> Result<Option<&'static JoinSpecDefinition>, FederationError> {
+    let Some(join_link) = schema
+        .metadata()
+        .as_ref()
+        .and_then(|metadata| metadata.for_identity(&Identity::join_identity()))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(
+        JOIN_VERSIONS.find(&join_link.url.version).ok_or_else(|| {
+            SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Invalid supergraph: uses unsupported join spec version {}",
+                    join_link.url.version,
+                ),
+            }
+        })?,
+    ))
+}
+
+fn context_arguments(
+    application: &Node<Directive>,
+) -> Result<Option<Vec<JoinContextArgument>>, FederationError> {
+    let Some(value) = application.argument_by_name(&JOIN_CONTEXTARGUMENTS_ARGUMENT_NAME) else {
+        return Ok(None);
+    };
+    let invalid = || SingleFederationError::Internal {
+        message: format!(
+            "Argument \"{}\" of directive \"@{}\" must be a list of join__ContextArgument.",
+            JOIN_CONTEXTARGUMENTS_ARGUMENT_NAME, application.name
+        ),
+    };
+    let values = match value.deref() {
+        Value::Null => return Ok(None),
+        Value::List(values) => values.iter().collect::<Vec<_>>(),
+        // Input coercion allows a single value where a list is expected.
+        _ => vec![value],
+    };
+    let mut context_arguments = Vec::new();
+    for value in values {
+        let Value::Object(fields) = value.deref() else {
+            return Err(invalid().into());
+        };
+        let field = |name: &Name| {
+            fields
+                .iter()
+                .find(|(field_name, _)| field_name == name)
+                .and_then(|(_, value)| value.as_str())
+                .map(NodeStr::new)
+                .ok_or_else(invalid)
+        };
+        context_arguments.push(JoinContextArgument {
+            name: field(&JOIN_NAME_ARGUMENT_NAME)?,
+            type_: field(&JOIN_TYPE_ARGUMENT_NAME)?,
+            context: field(&JOIN_CONTEXT_ARGUMENT_NAME)?,
+            selection: field(&JOIN_SELECTION_ARGUMENT_NAME)?,
+        });
+    }
+    Ok(Some(context_arguments))
+}
+
 impl SpecDefinition for JoinSpecDefinition {
     fn url(&self) -> &Url {
         &self.url
@@ -324,8 +445,12 @@ lazy_static! {
             None,
         ));
         definitions.add(JoinSpecDefinition::new(
-            Version { major: 0, minor: 1 },
-            Some(Version { major: 2, minor: 0 }),
+            Version { major: 0, minor: 4 },
+            Some(Version { major: 2, minor: 7 }),
+        ));
+        definitions.add(JoinSpecDefinition::new(
+            Version { major: 0, minor: 5 },
+            Some(Version { major: 2, minor: 8 }),
         ));
         definitions
     };
@@ -5,16 +5,20 @@ use crate::link::spec::Url;
 use apollo_compiler::ast::{Directive, InvalidNameError, Value};
 use apollo_compiler::schema::Name;
 use apollo_compiler::{name, Node, NodeStr};
+use indexmap::IndexMap;
 use std::fmt;
 use std::str;
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 use thiserror::Error;
 
 mod argument;
+pub(crate) mod context_spec_definition;
+pub(crate) mod cost_spec_definition;
 pub mod database;
 pub(crate) mod federation_spec_definition;
 pub(crate) mod graphql_definition;
 pub(crate) mod inaccessible_spec_definition;
+pub(crate) mod join_metadata;
 pub(crate) mod join_spec_definition;
 pub(crate) mod link_spec_definition;
 pub mod spec;
@@ -241,7 +245,11 @@ impl Link {
         // imported under. Otherwise, it is usually fully qualified by the spec name (so,
         // something like 'federation__key'), but there is a special case for directives
         // whose name match the one of the spec: those don't get qualified.
-        if let Some(import) = self.imports.iter().find(|i| i.element == *name) {
+        if let Some(import) = self
+            .imports
+            .iter()
+            .find(|i| i.is_directive && i.element == *name)
+        {
             import.alias.clone().unwrap_or_else(|| name.clone())
         } else if name == self.url.identity.name.as_str() {
             self.spec_name_in_schema().clone()
@@ -254,7 +262,11 @@ impl Link {
     pub fn type_name_in_schema(&self, name: &Name) -> Name {
         // Similar to directives, but the special case of a directive name matching the spec
         // name does not apply to types.
-        if let Some(import) = self.imports.iter().find(|i| i.element == *name) {
+        if let Some(import) = self
+            .imports
+            .iter()
+            .find(|i| !i.is_directive && i.element == *name)
+        {
             import.alias.clone().unwrap_or_else(|| name.clone())
         } else {
             // Both sides are `Name`s and we just add valid characters in between.
@@ -335,10 +347,10 @@ pub struct LinkedElement {
 #[derive(Default, Eq, PartialEq, Debug)]
 pub struct LinksMetadata {
     pub(crate) links: Vec<Arc<Link>>,
-    pub(crate) by_identity: HashMap<Identity, Arc<Link>>,
-    pub(crate) by_name_in_schema: HashMap<Name, Arc<Link>>,
-    pub(crate) types_by_imported_name: HashMap<Name, (Arc<Link>, Arc<Import>)>,
-    pub(crate) directives_by_imported_name: HashMap<Name, (Arc<Link>, Arc<Import>)>,
+    pub(crate) by_identity: IndexMap<Identity, Arc<Link>>,
+    pub(crate) by_name_in_schema: IndexMap<Name, Arc<Link>>,
+    pub(crate) types_by_imported_name: IndexMap<Name, (Arc<Link>, Arc<Import>)>,
+    pub(crate) directives_by_imported_name: IndexMap<Name, (Arc<Link>, Arc<Import>)>,
 }
 
 impl LinksMetadata {
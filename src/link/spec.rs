@@ -86,6 +86,48 @@ impl Identity {
             name: name!("inaccessible"),
         }
     }
+
+    pub fn tag_identity() -> Identity {
+        Identity {
+            domain: APOLLO_SPEC_DOMAIN.to_string(),
+            name: name!("tag"),
+        }
+    }
+
+    pub fn context_identity() -> Identity {
+        Identity {
+            domain: APOLLO_SPEC_DOMAIN.to_string(),
+            name: name!("context"),
+        }
+    }
+
+    pub fn authenticated_identity() -> Identity {
+        Identity {
+            domain: APOLLO_SPEC_DOMAIN.to_string(),
+            name: name!("authenticated"),
+        }
+    }
+
+    pub fn requires_scopes_identity() -> Identity {
+        Identity {
+            domain: APOLLO_SPEC_DOMAIN.to_string(),
+            name: name!("requiresScopes"),
+        }
+    }
+
+    pub fn cost_identity() -> Identity {
+        Identity {
+            domain: APOLLO_SPEC_DOMAIN.to_string(),
+            name: name!("cost"),
+        }
+    }
+
+    pub fn policy_identity() -> Identity {
+        Identity {
+            domain: APOLLO_SPEC_DOMAIN.to_string(),
+            name: name!("policy"),
+        }
+    }
 }
 
 /// The version of a `@link` specification, in the form of a major and minor version numbers.
@@ -174,6 +174,26 @@ impl<T: SpecDefinition> SpecDefinitions<T> {
         self.definitions.get(requested)
     }
 
+    /// Finds the definition to use for the requested version, falling back to the latest known
+    /// definition with the same major version when the requested minor version is newer than any
+    /// we know of (minor versions are backwards-compatible, so such a definition can still be
+    /// used to read the schema). Returns `None` if no definition has the requested major version,
+    /// or if the requested minor version is older than every known one.
+    pub(crate) fn find_compatible(&self, requested: &Version) -> Option<&T> {
+        if let Some(definition) = self.definitions.get(requested) {
+            return Some(definition);
+        }
+        self.definitions
+            .range(..requested)
+            .next_back()
+            .filter(|(version, _)| version.major == requested.major)
+            .map(|(_, definition)| definition)
+    }
+
+    pub(crate) fn latest(&self) -> Option<&T> {
+        self.definitions.values().next_back()
+    }
+
     pub(crate) fn versions(&self) -> Keys<Version, T> {
         self.definitions.keys()
     }
@@ -1,4 +1,3 @@
-use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
 use std::iter;
 use std::sync::Arc;
@@ -8,6 +7,7 @@ use apollo_compiler::ast::{
     Argument, Directive, DirectiveDefinition, DirectiveLocation, EnumValueDefinition,
     FieldDefinition, NamedType, Value,
 };
+use apollo_compiler::executable::{FieldSet, Selection, SelectionSet};
 use apollo_compiler::schema::{
     Component, EnumType, ExtendedType, InputObjectType, InputValueDefinition, InterfaceType, Name,
     ObjectType, ScalarType, UnionType,
@@ -19,14 +19,69 @@ use indexmap::map::Entry::{Occupied, Vacant};
 use indexmap::map::Iter;
 use indexmap::{IndexMap, IndexSet};
 
+use crate::link::federation_spec_definition::{
+    FederationSpecDefinition, FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_INTERFACEOBJECT_DIRECTIVE_NAME_IN_SPEC, FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC, FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_TAG_DIRECTIVE_NAME_IN_SPEC,
+};
+use crate::link::spec::{Identity, APOLLO_SPEC_DOMAIN};
+use crate::link::{Import, Link, DEFAULT_LINK_NAME};
 use crate::subgraph::ValidSubgraph;
 
-type MergeWarning = &'static str;
-type MergeError = &'static str;
+type MergeWarning = String;
+type MergeError = String;
 
 struct Merger {
     errors: Vec<MergeError>,
     composition_hints: Vec<MergeWarning>,
+    /// The (supergraph) names of the directives subgraphs compose with `@composeDirective`, whose
+    /// applications are kept in the supergraph.
+    composed_directives: IndexSet<Name>,
+    /// Whether some subgraph element is tagged with `@tag`, in which case the supergraph links the
+    /// tag spec.
+    has_tags: bool,
+}
+
+/// The names under which a subgraph applies the federation directives read when merging it. Those
+/// depend on its federation `@link`: imported directives are applied under their (possibly
+/// aliased) imported name, and the others under their namespaced name (e.g. `@federation__key`).
+struct FederationDirectiveNames {
+    key: Name,
+    requires: Name,
+    provides: Name,
+    external: Name,
+    interface_object: Name,
+    tag: Name,
+}
+
+impl FederationDirectiveNames {
+    fn of(subgraph: &ValidSubgraph) -> Self {
+        let federation_link = federation_link(subgraph);
+        let name_in_schema = |name_in_spec: Name| match &federation_link {
+            Some(link) => link.directive_name_in_schema(&name_in_spec),
+            None => name_in_spec,
+        };
+        Self {
+            key: name_in_schema(FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC),
+            requires: name_in_schema(FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC),
+            provides: name_in_schema(FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC),
+            external: name_in_schema(FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC),
+            interface_object: name_in_schema(FEDERATION_INTERFACEOBJECT_DIRECTIVE_NAME_IN_SPEC),
+            tag: name_in_schema(FEDERATION_TAG_DIRECTIVE_NAME_IN_SPEC),
+        }
+    }
+}
+
+/// A feature (i.e. a `@link`ed spec) some of whose directives are composed with
+/// `@composeDirective`.
+struct ComposedFeature {
+    /// The link with the highest version among the subgraphs linking the feature.
+    link: Arc<Link>,
+    /// The imports of the composed directives of the feature, by their name in the schema.
+    imports: IndexMap<Name, Arc<Import>>,
+    /// The definitions of the composed directives, from the subgraph with the highest version.
+    definitions: IndexMap<Name, Node<DirectiveDefinition>>,
 }
 
 pub struct MergeSuccess {
@@ -59,6 +114,8 @@ impl Merger {
         Merger {
             composition_hints: Vec::new(),
             errors: Vec::new(),
+            composed_directives: IndexSet::new(),
+            has_tags: false,
         }
     }
     fn merge(&mut self, mut subgraphs: Vec<&ValidSubgraph>) -> Result<MergeSuccess, MergeFailure> {
@@ -70,8 +127,9 @@ impl Merger {
             if let Ok(subgraph_name) = Name::new(&subgraph.name.to_uppercase()) {
                 subgraphs_and_enum_values.push((*subgraph, subgraph_name));
             } else {
-                self.errors
-                    .push("Subgraph name couldn't be transformed into valid GraphQL name");
+                self.errors.push(
+                    "Subgraph name couldn't be transformed into valid GraphQL name".to_owned(),
+                );
             }
         }
         if !self.errors.is_empty() {
@@ -87,11 +145,14 @@ impl Merger {
 
         // add core features
         // TODO verify federation versions across subgraphs
+        self.check_federation_versions(&subgraphs);
         add_core_feature_link(&mut supergraph);
         add_core_feature_join(&mut supergraph, &subgraphs_and_enum_values);
+        self.add_composed_directives(&mut supergraph, &subgraphs);
 
         // create stubs
         for (subgraph, subgraph_name) in &subgraphs_and_enum_values {
+            let names = FederationDirectiveNames::of(subgraph);
             let sources = Arc::make_mut(&mut supergraph.sources);
             for (key, source) in subgraph.schema.sources.iter() {
                 sources.entry(*key).or_insert_with(|| source.clone());
@@ -112,30 +173,35 @@ impl Merger {
                         subgraph_name.clone(),
                         key.clone(),
                         value,
+                        &names,
                     ),
                     ExtendedType::InputObject(value) => self.merge_input_object_type(
                         &mut supergraph.types,
                         subgraph_name.clone(),
                         key.clone(),
                         value,
+                        &names,
                     ),
                     ExtendedType::Interface(value) => self.merge_interface_type(
                         &mut supergraph.types,
                         subgraph_name.clone(),
                         key.clone(),
                         value,
+                        &names,
                     ),
                     ExtendedType::Object(value) => self.merge_object_type(
                         &mut supergraph.types,
                         subgraph_name.clone(),
                         key.clone(),
                         value,
+                        &names,
                     ),
                     ExtendedType::Union(value) => self.merge_union_type(
                         &mut supergraph.types,
                         subgraph_name.clone(),
                         key.clone(),
                         value,
+                        &names,
                     ),
                     ExtendedType::Scalar(_value) => {
                         // DO NOTHING
@@ -151,6 +217,12 @@ impl Merger {
             }
         }
 
+        if self.has_tags {
+            add_core_feature_tag(&mut supergraph);
+        }
+
+        self.validate_requires(&subgraphs);
+
         if self.errors.is_empty() {
             // TODO: validate here and extend `MergeFailure` to propagate validation errors
             let supergraph = Valid::assume_valid(supergraph);
@@ -167,6 +239,216 @@ impl Merger {
         }
     }
 
+    /// Checks that every field required through `@requires` can be resolved by at least one
+    /// subgraph. Required fields are usually `@external` in the requiring subgraph, and may be
+    /// `@external` in other subgraphs as well (e.g. when they require them too), so at least one
+    /// subgraph must define them without `@external` for the query planner to fetch them first.
+    fn validate_requires(&mut self, subgraphs: &[&ValidSubgraph]) {
+        let subgraphs_and_names = subgraphs
+            .iter()
+            .map(|subgraph| (*subgraph, FederationDirectiveNames::of(subgraph)))
+            .collect::<Vec<_>>();
+        for (subgraph, names) in &subgraphs_and_names {
+            for (type_name, type_) in &subgraph.schema.types {
+                let ExtendedType::Object(object) = type_ else {
+                    continue;
+                };
+                for field in object.fields.values() {
+                    let Some(fields) = field.directives.get(&names.requires).and_then(|requires| {
+                        directive_string_arg_value(requires, &name!("fields"))
+                    }) else {
+                        continue;
+                    };
+                    let Ok(field_set) = FieldSet::parse_and_validate(
+                        &subgraph.schema,
+                        type_name.clone(),
+                        fields.as_str(),
+                        "field_set.graphql",
+                    ) else {
+                        self.errors
+                            .push("@requires field set is invalid".to_owned());
+                        continue;
+                    };
+                    let mut required_fields = IndexSet::new();
+                    collect_leaf_fields(&field_set.selection_set, &mut required_fields);
+                    let all_resolvable = required_fields.iter().all(|(parent_type, field_name)| {
+                        subgraphs_and_names.iter().any(|(subgraph, names)| {
+                            is_resolved_by_subgraph(subgraph, names, parent_type, field_name)
+                        })
+                    });
+                    if !all_resolvable {
+                        self.errors
+                            .push("@requires field is not resolvable by any subgraph".to_owned());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reports the subgraphs whose federation spec version is read as another one (federation 1
+    /// versions, and minor versions newer than any we know of) as composition hints, and fails for
+    /// the subgraphs whose federation spec version isn't supported.
+    fn check_federation_versions(&mut self, subgraphs: &[&ValidSubgraph]) {
+        for subgraph in subgraphs {
+            let Some(federation_link) = federation_link(subgraph) else {
+                continue;
+            };
+            match FederationSpecDefinition::for_version(&federation_link.url.version) {
+                Ok(lookup) => {
+                    if let Some(warning) = lookup.warning {
+                        self.composition_hints
+                            .push(format!("Subgraph \"{}\": {}", subgraph.name, warning));
+                    }
+                }
+                Err(error) => self
+                    .errors
+                    .push(format!("Subgraph \"{}\": {}", subgraph.name, error)),
+            }
+        }
+    }
+
+    /// Adds the directives subgraphs compose with `@composeDirective` to the supergraph, along with
+    /// `@link`s to their features. Composed directives must come from a (non-Apollo) linked
+    /// feature, so that the supergraph can link it too: when subgraphs link different versions of
+    /// a feature, the highest one is used, and they must agree on the major version.
+    fn add_composed_directives(&mut self, supergraph: &mut Schema, subgraphs: &[&ValidSubgraph]) {
+        let mut features: IndexMap<Identity, ComposedFeature> = IndexMap::new();
+        for subgraph in subgraphs {
+            let links = subgraph
+                .schema
+                .schema_definition
+                .directives
+                .get_all(&DEFAULT_LINK_NAME)
+                .filter_map(|link| Link::from_directive_application(link).ok().map(Arc::new))
+                .collect::<Vec<_>>();
+            for compose in subgraph
+                .schema
+                .schema_definition
+                .directives
+                .get_all("composeDirective")
+            {
+                let Some(name) = directive_string_arg_value(compose, &name!("name"))
+                    .and_then(|name| name.strip_prefix('@'))
+                    .and_then(|name| Name::new(name).ok())
+                else {
+                    self.errors.push(
+                        "@composeDirective name must be a directive name starting with \"@\""
+                            .to_owned(),
+                    );
+                    continue;
+                };
+                let Some(definition) = subgraph.schema.directive_definitions.get(&name) else {
+                    self.errors.push(
+                        "@composeDirective references a directive that isn't defined".to_owned(),
+                    );
+                    continue;
+                };
+                let Some((link, import)) = links
+                    .iter()
+                    .find_map(|link| linked_directive_import(link, &name).map(|i| (link, i)))
+                else {
+                    self.errors.push(
+                        "@composeDirective references a directive that isn't from a linked feature"
+                            .to_owned(),
+                    );
+                    continue;
+                };
+                if link.url.identity.domain == APOLLO_SPEC_DOMAIN {
+                    self.errors.push(
+                        "@composeDirective cannot be used on Apollo spec directives".to_owned(),
+                    );
+                    continue;
+                }
+                let collides = features.iter().any(|(identity, feature)| {
+                    *identity != link.url.identity && feature.definitions.contains_key(&name)
+                });
+                if collides {
+                    self.errors.push(
+                        "@composeDirective directives of different features have the same name"
+                            .to_owned(),
+                    );
+                    continue;
+                }
+                let feature = features
+                    .entry(link.url.identity.clone())
+                    .or_insert_with(|| ComposedFeature {
+                        link: link.clone(),
+                        imports: IndexMap::new(),
+                        definitions: IndexMap::new(),
+                    });
+                let version = &link.url.version;
+                if version.major != feature.link.url.version.major {
+                    self.errors.push(
+                        "@composeDirective features are linked with different major versions"
+                            .to_owned(),
+                    );
+                    continue;
+                }
+                if *version > feature.link.url.version {
+                    feature.link = link.clone();
+                    feature.definitions.insert(name.clone(), definition.clone());
+                } else {
+                    feature
+                        .definitions
+                        .entry(name.clone())
+                        .or_insert_with(|| definition.clone());
+                }
+                if let Some(import) = import {
+                    feature.imports.insert(name.clone(), import);
+                }
+            }
+        }
+
+        for feature in features.into_values() {
+            supergraph
+                .schema_definition
+                .make_mut()
+                .directives
+                .push(Component::new(feature_link_directive(
+                    &feature.link,
+                    feature.imports.values(),
+                )));
+            for (name, definition) in feature.definitions {
+                self.composed_directives.insert(name.clone());
+                supergraph.directive_definitions.insert(name, definition);
+            }
+        }
+    }
+
+    /// Copies the applications of the directives kept in the supergraph (`@tag` and composed
+    /// directives) from a subgraph element's directives to the corresponding supergraph element's
+    /// directives (skipping those already there).
+    fn merge_directive_applications<'a, T>(
+        &mut self,
+        supergraph_directives: &mut Vec<T>,
+        subgraph_directives: impl IntoIterator<Item = &'a T>,
+        names: &FederationDirectiveNames,
+    ) where
+        T: 'a + AsRef<Directive> + From<Directive>,
+    {
+        for directive in subgraph_directives {
+            let directive = directive.as_ref();
+            let name = if directive.name == names.tag {
+                self.has_tags = true;
+                TAG_DIRECTIVE_NAME
+            } else if self.composed_directives.contains(&directive.name) {
+                directive.name.clone()
+            } else {
+                continue;
+            };
+            let directive = Directive {
+                name,
+                arguments: directive.arguments.clone(),
+            };
+            if !supergraph_directives
+                .iter()
+                .any(|existing| *existing.as_ref() == directive)
+            {
+                supergraph_directives.push(directive.into());
+            }
+        }
+    }
+
     fn merge_descriptions<T: Eq + Clone>(&mut self, merged: &mut Option<T>, new: &Option<T>) {
         match (&mut *merged, new) {
             (_, None) => {}
@@ -174,7 +456,8 @@ impl Merger {
             (Some(a), Some(b)) => {
                 if a != b {
                     // TODO add info about type and from/to subgraph
-                    self.composition_hints.push("conflicting descriptions");
+                    self.composition_hints
+                        .push("conflicting descriptions".to_owned());
                 }
             }
         }
@@ -205,6 +488,7 @@ impl Merger {
         subgraph_name: Name,
         enum_name: NamedType,
         enum_type: &Node<EnumType>,
+        names: &FederationDirectiveNames,
     ) {
         let existing_type = types
             .entry(enum_name.clone())
@@ -213,6 +497,11 @@ impl Merger {
             let join_type_directives =
                 join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             e.make_mut().directives.extend(join_type_directives);
+            self.merge_directive_applications(
+                &mut e.make_mut().directives,
+                enum_type.directives.iter(),
+                names,
+            );
 
             self.merge_descriptions(&mut e.make_mut().description, &enum_type.description);
 
@@ -238,6 +527,11 @@ impl Merger {
                         })),
                     ],
                 }));
+                self.merge_directive_applications(
+                    &mut ev.make_mut().directives,
+                    enum_value.directives.iter(),
+                    names,
+                );
             }
         } else {
             // TODO - conflict
@@ -250,6 +544,7 @@ impl Merger {
         subgraph_name: Name,
         input_object_name: NamedType,
         input_object: &Node<InputObjectType>,
+        names: &FederationDirectiveNames,
     ) {
         let existing_type = types
             .entry(input_object_name.clone())
@@ -259,14 +554,24 @@ impl Merger {
                 join_type_applied_directive(subgraph_name, iter::empty(), false);
             let mutable_object = obj.make_mut();
             mutable_object.directives.extend(join_type_directives);
+            self.merge_directive_applications(
+                &mut mutable_object.directives,
+                input_object.directives.iter(),
+                names,
+            );
 
-            for (field_name, _field) in input_object.fields.iter() {
+            for (field_name, field) in input_object.fields.iter() {
                 let existing_field = mutable_object.fields.entry(field_name.clone());
                 match existing_field {
                     Vacant(_i) => {
                         // TODO warning - mismatch on input fields
                     }
-                    Occupied(_i) => {
+                    Occupied(mut i) => {
+                        self.merge_directive_applications(
+                            &mut i.get_mut().make_mut().directives,
+                            field.directives.iter(),
+                            names,
+                        );
                         // merge_options(&i.get_mut().description, &field.description);
                         // TODO check description
                         // TODO check type
@@ -286,20 +591,26 @@ impl Merger {
         subgraph_name: Name,
         interface_name: NamedType,
         interface: &Node<InterfaceType>,
+        names: &FederationDirectiveNames,
     ) {
         let existing_type = types
             .entry(interface_name.clone())
             .or_insert(copy_interface_type(interface_name, interface));
         if let ExtendedType::Interface(intf) = existing_type {
-            let key_directives = interface.directives.get_all("key");
+            let key_directives = interface.directives.get_all(&names.key);
             let join_type_directives =
                 join_type_applied_directive(subgraph_name, key_directives, false);
             let mutable_intf = intf.make_mut();
             mutable_intf.directives.extend(join_type_directives);
+            self.merge_directive_applications(
+                &mut mutable_intf.directives,
+                interface.directives.iter(),
+                names,
+            );
 
             for (field_name, field) in interface.fields.iter() {
                 let existing_field = mutable_intf.fields.entry(field_name.clone());
-                match existing_field {
+                let supergraph_field = match existing_field {
                     Vacant(i) => {
                         // TODO warning mismatch missing fields
                         i.insert(Component::new(FieldDefinition {
@@ -308,15 +619,20 @@ impl Merger {
                             arguments: vec![],
                             ty: field.ty.clone(),
                             directives: Default::default(),
-                        }));
+                        }))
                     }
-                    Occupied(_i) => {
+                    Occupied(i) => {
                         // TODO check description
                         // TODO check type
                         // TODO check default value
-                        // TODO process directives
+                        i.into_mut()
                     }
-                }
+                };
+                self.merge_directive_applications(
+                    &mut supergraph_field.make_mut().directives,
+                    field.directives.iter(),
+                    names,
+                );
             }
         } else {
             // TODO conflict on type
@@ -329,8 +645,9 @@ impl Merger {
         subgraph_name: Name,
         object_name: NamedType,
         object: &Node<ObjectType>,
+        names: &FederationDirectiveNames,
     ) {
-        let is_interface_object = object.directives.has("interfaceObject");
+        let is_interface_object = object.directives.has(&names.interface_object);
         let existing_type = types
             .entry(object_name.clone())
             .or_insert(copy_object_type_stub(
@@ -339,13 +656,18 @@ impl Merger {
                 is_interface_object,
             ));
         if let ExtendedType::Object(obj) = existing_type {
-            let key_fields: HashSet<&str> = parse_keys(object.directives.get_all("key"));
+            let key_fields: IndexSet<&str> = parse_keys(object.directives.get_all(&names.key));
             let is_join_field = !key_fields.is_empty() || object_name == "Query";
-            let key_directives = object.directives.get_all("key");
+            let key_directives = object.directives.get_all(&names.key);
             let join_type_directives =
                 join_type_applied_directive(subgraph_name.clone(), key_directives, false);
             let mutable_object = obj.make_mut();
             mutable_object.directives.extend(join_type_directives);
+            self.merge_directive_applications(
+                &mut mutable_object.directives,
+                object.directives.iter(),
+                names,
+            );
             self.merge_descriptions(&mut mutable_object.description, &object.description);
             object.implements_interfaces.iter().for_each(|intf_name| {
                 // IndexSet::insert deduplicates
@@ -383,6 +705,11 @@ impl Merger {
                     &mut supergraph_field.make_mut().description,
                     &field.description,
                 );
+                self.merge_directive_applications(
+                    &mut supergraph_field.make_mut().directives,
+                    field.directives.iter(),
+                    names,
+                );
                 for arg in field.arguments.iter() {
                     if let Some(_existing_arg) = supergraph_field.argument_by_name(&arg.name) {
                     } else {
@@ -393,19 +720,24 @@ impl Merger {
                 if is_join_field {
                     let is_key_field = key_fields.contains(field_name.as_str());
                     if !is_key_field {
-                        let requires_directive_option =
-                            Option::and_then(field.directives.get_all("requires").next(), |p| {
+                        let requires_directive_option = Option::and_then(
+                            field.directives.get_all(&names.requires).next(),
+                            |p| {
                                 let requires_fields =
                                     directive_string_arg_value(p, &name!("fields")).unwrap();
                                 Some(requires_fields.as_str())
-                            });
-                        let provides_directive_option =
-                            Option::and_then(field.directives.get_all("provides").next(), |p| {
+                            },
+                        );
+                        let provides_directive_option = Option::and_then(
+                            field.directives.get_all(&names.provides).next(),
+                            |p| {
                                 let provides_fields =
                                     directive_string_arg_value(p, &name!("fields")).unwrap();
                                 Some(provides_fields.as_str())
-                            });
-                        let external_field = field.directives.get_all("external").next().is_some();
+                            },
+                        );
+                        let external_field =
+                            field.directives.get_all(&names.external).next().is_some();
                         let join_field_directive = join_field_applied_directive(
                             subgraph_name.clone(),
                             requires_directive_option,
@@ -422,7 +754,7 @@ impl Merger {
             }
         } else if let ExtendedType::Interface(intf) = existing_type {
             // TODO support interface object
-            let key_directives = object.directives.get_all("key");
+            let key_directives = object.directives.get_all(&names.key);
             let join_type_directives =
                 join_type_applied_directive(subgraph_name, key_directives, true);
             intf.make_mut().directives.extend(join_type_directives);
@@ -436,6 +768,7 @@ impl Merger {
         subgraph_name: Name,
         union_name: NamedType,
         union: &Node<UnionType>,
+        names: &FederationDirectiveNames,
     ) {
         let existing_type = types.entry(union_name.clone()).or_insert(copy_union_type(
             union_name.clone(),
@@ -445,6 +778,11 @@ impl Merger {
             let join_type_directives =
                 join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             u.make_mut().directives.extend(join_type_directives);
+            self.merge_directive_applications(
+                &mut u.make_mut().directives,
+                union.directives.iter(),
+                names,
+            );
 
             for union_member in union.members.iter() {
                 // IndexSet::insert deduplicates
@@ -740,6 +1078,55 @@ fn add_core_feature_link(supergraph: &mut Schema) {
         .insert(name!("link"), Node::new(link_directive_definition));
 }
 
+const TAG_DIRECTIVE_NAME: Name = name!("tag");
+
+fn add_core_feature_tag(supergraph: &mut Schema) {
+    // @link(url: "https://specs.apollo.dev/tag/v0.3")
+    supergraph
+        .schema_definition
+        .make_mut()
+        .directives
+        .push(Component::new(Directive {
+            name: name!("link"),
+            arguments: vec![Node::new(Argument {
+                name: name!("url"),
+                value: Node::new(Value::String(NodeStr::new(
+                    "https://specs.apollo.dev/tag/v0.3",
+                ))),
+            })],
+        }));
+
+    // directive @tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT | INTERFACE | UNION
+    //   | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION
+    supergraph.directive_definitions.insert(
+        TAG_DIRECTIVE_NAME,
+        Node::new(DirectiveDefinition {
+            name: TAG_DIRECTIVE_NAME,
+            description: None,
+            arguments: vec![Node::new(InputValueDefinition {
+                name: name!("name"),
+                description: None,
+                directives: Default::default(),
+                ty: ty!(String!).into(),
+                default_value: None,
+            })],
+            locations: vec![
+                DirectiveLocation::FieldDefinition,
+                DirectiveLocation::Object,
+                DirectiveLocation::Interface,
+                DirectiveLocation::Union,
+                DirectiveLocation::ArgumentDefinition,
+                DirectiveLocation::Scalar,
+                DirectiveLocation::Enum,
+                DirectiveLocation::EnumValue,
+                DirectiveLocation::InputObject,
+                DirectiveLocation::InputFieldDefinition,
+            ],
+            repeatable: true,
+        }),
+    );
+}
+
 /// directive @link(url: String, as: String, import: [Import], for: link__Purpose) repeatable on SCHEMA
 fn link_directive_definition() -> DirectiveDefinition {
     DirectiveDefinition {
@@ -1205,8 +1592,8 @@ fn join_graph_enum_type(
 // TODO use apollo_compiler::executable::FieldSet
 fn parse_keys<'a>(
     directives: impl Iterator<Item = &'a Component<Directive>> + Sized,
-) -> HashSet<&'a str> {
-    HashSet::from_iter(
+) -> IndexSet<&'a str> {
+    IndexSet::from_iter(
         directives
             .flat_map(|k| {
                 let field_set = directive_string_arg_value(k, &name!("fields")).unwrap();
@@ -1216,6 +1603,113 @@ fn parse_keys<'a>(
     )
 }
 
+/// Collects the leaf fields selected by the given selection set (recursively), as parent type and
+/// field names. Only leaves are collected as their parents are necessarily resolvable when they are.
+fn collect_leaf_fields(selection_set: &SelectionSet, fields: &mut IndexSet<(Name, Name)>) {
+    for selection in &selection_set.selections {
+        match selection {
+            Selection::Field(field) if field.selection_set.selections.is_empty() => {
+                fields.insert((selection_set.ty.clone(), field.name.clone()));
+            }
+            Selection::Field(field) => collect_leaf_fields(&field.selection_set, fields),
+            Selection::InlineFragment(inline_fragment) => {
+                collect_leaf_fields(&inline_fragment.selection_set, fields)
+            }
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+/// Whether the subgraph defines the given field without `@external` (on the field or its type),
+/// i.e. whether the subgraph can resolve it. Key fields are always resolvable, as federation 1
+/// subgraphs mark the key fields of their type extensions `@external`.
+fn is_resolved_by_subgraph(
+    subgraph: &ValidSubgraph,
+    names: &FederationDirectiveNames,
+    type_name: &Name,
+    field_name: &Name,
+) -> bool {
+    let (directives, field) = match subgraph.schema.types.get(type_name) {
+        Some(ExtendedType::Object(type_)) => (&type_.directives, type_.fields.get(field_name)),
+        Some(ExtendedType::Interface(type_)) => (&type_.directives, type_.fields.get(field_name)),
+        _ => return false,
+    };
+    let Some(field) = field else {
+        return false;
+    };
+    let is_external = directives.has(&names.external) || field.directives.has(&names.external);
+    !is_external || parse_keys(directives.get_all(&names.key)).contains(field_name.as_str())
+}
+
+/// The federation `@link` of the given subgraph, if any.
+fn federation_link(subgraph: &ValidSubgraph) -> Option<Link> {
+    subgraph
+        .schema
+        .schema_definition
+        .directives
+        .get_all(&DEFAULT_LINK_NAME)
+        .filter_map(|link| Link::from_directive_application(link).ok())
+        .find(|link| link.url.identity == Identity::federation_identity())
+}
+
+/// If the given directive (by name in schema) comes from the given link, returns its import (or
+/// `None` when it's used with its namespaced name).
+fn linked_directive_import(link: &Link, name: &Name) -> Option<Option<Arc<Import>>> {
+    if let Some(import) = link
+        .imports
+        .iter()
+        .find(|import| import.is_directive && import.imported_name() == name)
+    {
+        return Some(Some(import.clone()));
+    }
+    let is_namespaced = name
+        .strip_prefix(link.spec_name_in_schema().as_str())
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("__"));
+    is_namespaced.then_some(None)
+}
+
+/// @link(url: "<feature url>", as: "<alias>", import: [...])
+fn feature_link_directive<'a>(
+    link: &Link,
+    imports: impl Iterator<Item = &'a Arc<Import>>,
+) -> Directive {
+    let imports = imports
+        .map(|import| {
+            let name = Value::String(NodeStr::new(&format!("@{}", import.element)));
+            Node::new(match &import.alias {
+                Some(alias) => Value::Object(vec![
+                    (name!("name"), Node::new(name)),
+                    (
+                        name!("as"),
+                        Node::new(Value::String(NodeStr::new(&format!("@{}", alias)))),
+                    ),
+                ]),
+                None => name,
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut arguments = vec![Node::new(Argument {
+        name: name!("url"),
+        value: Node::new(Value::String(NodeStr::new(&link.url.to_string()))),
+    })];
+    if let Some(spec_alias) = &link.spec_alias {
+        arguments.push(Node::new(Argument {
+            name: name!("as"),
+            value: Node::new(Value::String(NodeStr::new(spec_alias))),
+        }));
+    }
+    if !imports.is_empty() {
+        arguments.push(Node::new(Argument {
+            name: name!("import"),
+            value: Node::new(Value::List(imports)),
+        }));
+    }
+    Directive {
+        name: DEFAULT_LINK_NAME,
+        arguments,
+    }
+}
+
 fn merge_directive(
     supergraph_directives: &mut IndexMap<Name, Node<DirectiveDefinition>>,
     directive: &Node<DirectiveDefinition>,
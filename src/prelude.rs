@@ -0,0 +1,15 @@
+//! The types most users of this crate need, which make up its stable API:
+//!
+//! ```
+//! use apollo_federation::prelude::*;
+//! ```
+pub use crate::cost::CostMetadata;
+pub use crate::error::{FederationError, SingleFederationError};
+pub use crate::query_plan::query_planner::{QueryPlanner, QueryPlannerConfig};
+pub use crate::query_plan::{NormalizedOperation, NormalizedSelectionSet, QueryPlan};
+pub use crate::schema::ValidFederationSchema;
+pub use crate::subgraph::Subgraph;
+pub use crate::{
+    ApiSchemaOptions, AuthorizationMetadata, PlanningDiff, Supergraph, SupergraphDiagnostics,
+    SupergraphReport,
+};
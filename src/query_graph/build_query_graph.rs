@@ -1,15 +1,19 @@
 use crate::error::{FederationError, SingleFederationError};
+use crate::field_set::parse_field_set;
 use crate::link::federation_spec_definition::{
     get_federation_spec_definition_from_subgraph, FederationSpecDefinition, KeyDirectiveArguments,
 };
-use crate::query_graph::extract_subgraphs_from_supergraph::extract_subgraphs_from_supergraph;
-use crate::query_graph::field_set::parse_field_set;
+use crate::query_graph::extract_subgraphs_from_supergraph::{
+    extract_subgraphs_from_supergraph, ValidFederationSubgraph, ValidFederationSubgraphs,
+};
 use crate::query_graph::{
-    QueryGraph, QueryGraphEdge, QueryGraphEdgeTransition, QueryGraphNode, QueryGraphNodeType,
+    ContextCondition, OverrideCondition, QueryGraph, QueryGraphEdge, QueryGraphEdgeTransition,
+    QueryGraphNode, QueryGraphNodeType,
 };
 use crate::query_plan::operation::{
     equal_selection_sets, merge_selection_sets, NormalizedSelection, NormalizedSelectionSet,
 };
+use crate::schema::external::ExternalMetadata;
 use crate::schema::position::{
     AbstractTypeDefinitionPosition, CompositeTypeDefinitionPosition, FieldDefinitionPosition,
     InterfaceTypeDefinitionPosition, ObjectFieldDefinitionPosition,
@@ -39,6 +43,48 @@ pub fn build_federated_query_graph(
     api_schema: ValidFederationSchema,
     validate_extracted_subgraphs: Option<bool>,
     for_query_planning: Option<bool>,
+) -> Result<QueryGraph, FederationError> {
+    build_federated_query_graph_with_extensions(
+        supergraph_schema,
+        api_schema,
+        validate_extracted_subgraphs,
+        for_query_planning,
+        &[],
+    )
+}
+
+/// Builds a "federated" query graph like `build_federated_query_graph()`, additionally letting the
+/// given extensions contribute edges to it (see `QueryGraphExtension`).
+///
+/// Assumes the given schemas have been validated.
+pub fn build_federated_query_graph_with_extensions(
+    supergraph_schema: ValidFederationSchema,
+    api_schema: ValidFederationSchema,
+    validate_extracted_subgraphs: Option<bool>,
+    for_query_planning: Option<bool>,
+    extensions: &[&dyn QueryGraphExtension],
+) -> Result<QueryGraph, FederationError> {
+    let subgraphs =
+        extract_subgraphs_from_supergraph(&supergraph_schema, validate_extracted_subgraphs)?;
+    build_federated_query_graph_from_subgraphs(
+        supergraph_schema,
+        api_schema,
+        subgraphs,
+        for_query_planning,
+        extensions,
+    )
+}
+
+/// Builds a "federated" query graph based on the provided supergraph and API schema, using the
+/// given subgraphs (extracted from that supergraph) instead of extracting them.
+///
+/// Assumes the given schemas have been validated.
+pub(crate) fn build_federated_query_graph_from_subgraphs(
+    supergraph_schema: ValidFederationSchema,
+    api_schema: ValidFederationSchema,
+    subgraphs: ValidFederationSubgraphs,
+    for_query_planning: Option<bool>,
+    extensions: &[&dyn QueryGraphExtension],
 ) -> Result<QueryGraph, FederationError> {
     let for_query_planning = for_query_planning.unwrap_or(true);
     let mut query_graph = QueryGraph {
@@ -49,9 +95,8 @@ pub fn build_federated_query_graph(
         types_to_nodes_by_source: Default::default(),
         root_kinds_to_nodes_by_source: Default::default(),
         non_trivial_followup_edges: Default::default(),
+        interface_object_types_by_source: Default::default(),
     };
-    let subgraphs =
-        extract_subgraphs_from_supergraph(&supergraph_schema, validate_extracted_subgraphs)?;
     for (subgraph_name, subgraph) in subgraphs {
         let builder = SchemaQueryGraphBuilder::new(
             query_graph,
@@ -63,10 +108,254 @@ pub fn build_federated_query_graph(
         query_graph = builder.build()?;
     }
     let federated_builder = FederatedQueryGraphBuilder::new(query_graph, supergraph_schema)?;
-    query_graph = federated_builder.build()?;
+    query_graph = federated_builder.build(extensions)?;
     Ok(query_graph)
 }
 
+/// Builds a "federated" query graph like `build_federated_query_graph_with_extensions()`, but when
+/// only the given subgraph changed from those of the given federated query graph. The nodes/edges
+/// of the other subgraphs are copied from that query graph instead of being rebuilt from their
+/// schemas, which avoids extracting and traversing them again. The subgraph is added if the query
+/// graph doesn't have it yet.
+///
+/// The federated nodes/edges (for @key, @requires, @provides, etc.) are then recomputed, along with
+/// the non-trivial followup edges, as the changed subgraph may be connected to any other subgraph.
+/// The resulting query graph is equivalent to a rebuilt one, although its node/edge indices may
+/// differ.
+///
+/// Assumes the given schemas have been validated, and that `supergraph_schema` and `api_schema`
+/// are those of the updated supergraph. `for_query_planning` must be the value the given query
+/// graph was built with.
+pub fn update_federated_query_graph(
+    query_graph: &QueryGraph,
+    supergraph_schema: ValidFederationSchema,
+    api_schema: ValidFederationSchema,
+    subgraph: ValidFederationSubgraph,
+    for_query_planning: Option<bool>,
+    extensions: &[&dyn QueryGraphExtension],
+) -> Result<QueryGraph, FederationError> {
+    let for_query_planning = for_query_planning.unwrap_or(true);
+    let subgraph_name = NodeStr::new(&subgraph.name);
+    let mut subgraph = Some(subgraph);
+    let mut updated_query_graph = QueryGraph {
+        // Note this name is a dummy initial name that gets overridden as we build the query graph.
+        current_source: NodeStr::new(""),
+        graph: Default::default(),
+        sources: Default::default(),
+        types_to_nodes_by_source: Default::default(),
+        root_kinds_to_nodes_by_source: Default::default(),
+        non_trivial_followup_edges: Default::default(),
+        interface_object_types_by_source: Default::default(),
+    };
+    // Subgraphs are built in order of their names (as extracted from the supergraph), so a new
+    // subgraph is built before the first subgraph whose name comes after its own.
+    for (source, schema) in query_graph.subgraphs() {
+        if *source >= subgraph_name {
+            if let Some(subgraph) = subgraph.take() {
+                let builder = SchemaQueryGraphBuilder::new(
+                    updated_query_graph,
+                    subgraph_name.clone(),
+                    subgraph.schema,
+                    Some(api_schema.clone()),
+                    for_query_planning,
+                )?;
+                updated_query_graph = builder.build()?;
+            }
+            if *source == subgraph_name {
+                continue;
+            }
+        }
+        updated_query_graph =
+            copy_subgraph_query_graph(query_graph, updated_query_graph, source, schema.clone())?;
+    }
+    if let Some(subgraph) = subgraph {
+        let builder = SchemaQueryGraphBuilder::new(
+            updated_query_graph,
+            subgraph_name,
+            subgraph.schema,
+            Some(api_schema),
+            for_query_planning,
+        )?;
+        updated_query_graph = builder.build()?;
+    }
+    let federated_builder =
+        FederatedQueryGraphBuilder::new(updated_query_graph, supergraph_schema)?;
+    federated_builder.build(extensions)
+}
+
+/// Copies the nodes/edges of the given source from the given federated query graph into the query
+/// graph being built, as `SchemaQueryGraphBuilder` builds them. This means the nodes/edges added by
+/// `FederatedQueryGraphBuilder` are skipped, and the field-collecting edges redirected to the copies
+/// made for @provides are redirected back to the copied nodes.
+fn copy_subgraph_query_graph(
+    federated_query_graph: &QueryGraph,
+    query_graph: QueryGraph,
+    source: &NodeStr,
+    schema: ValidFederationSchema,
+) -> Result<QueryGraph, FederationError> {
+    let mut base = BaseQueryGraphBuilder::new(query_graph, source.clone(), schema);
+    let mut copied_nodes = IndexMap::new();
+    for node in federated_query_graph.graph.node_indices() {
+        let node_weight = federated_query_graph.node_weight(node)?;
+        if node_weight.source != *source || node_weight.provide_id.is_some() {
+            continue;
+        }
+        let new_node = base.create_new_node(node_weight.type_.clone())?;
+        if let Some(root_kind) = node_weight.root_kind {
+            base.set_as_root(new_node, root_kind)?;
+        }
+        copied_nodes.insert(node, new_node);
+    }
+    let types_to_nodes = federated_query_graph.types_to_nodes_by_source(source)?;
+    for edge_ref in federated_query_graph.graph.edge_references() {
+        // This skips the edges from other sources and from @provides copies.
+        let Some(head) = copied_nodes.get(&edge_ref.source()) else {
+            continue;
+        };
+        let edge_weight = edge_ref.weight();
+        match &edge_weight.transition {
+            QueryGraphEdgeTransition::FieldCollection {
+                is_part_of_provides: false,
+                ..
+            }
+            | QueryGraphEdgeTransition::Downcast { .. } => {}
+            _ => continue,
+        }
+        let tail = edge_ref.target();
+        let tail = match copied_nodes.get(&tail) {
+            Some(tail) => Some(*tail),
+            None => {
+                let QueryGraphNodeType::SchemaType(type_pos) =
+                    &federated_query_graph.node_weight(tail)?.type_
+                else {
+                    return Err(SingleFederationError::Internal {
+                        message: "Unexpectedly found edge to federated root node".to_owned(),
+                    }
+                    .into());
+                };
+                types_to_nodes
+                    .get(type_pos.type_name())
+                    .into_iter()
+                    .flatten()
+                    .find_map(|node| copied_nodes.get(node).copied())
+            }
+        };
+        let Some(tail) = tail else {
+            return Err(SingleFederationError::Internal {
+                message: format!(
+                    "Missing non-provides node for edge \"{}\" in subgraph \"{}\"",
+                    edge_weight, source,
+                ),
+            }
+            .into());
+        };
+        base.add_edge(*head, tail, edge_weight.transition.clone(), None)?;
+    }
+    if let Some(interface_object_types) = federated_query_graph
+        .interface_object_types_by_source
+        .get(source)
+    {
+        base.query_graph
+            .interface_object_types_by_source
+            .insert(source.clone(), interface_object_types.clone());
+    }
+    Ok(base.build())
+}
+
+/// A hook into the building of federated query graphs, letting callers contribute edges that aren't
+/// derived from the join spec (e.g. to experiment with new entity resolution mechanisms, or with
+/// subgraphs backed by something other than GraphQL).
+///
+/// Extensions run once all the standard nodes and edges have been added. Their edges use the
+/// `ExtensionResolution` transition, and are taken and planned like @key edges: their conditions
+/// must be resolved before taking them, and are then sent as the representation of the entity.
+pub trait QueryGraphExtension {
+    /// The name of this extension, which edges added by it are labelled with.
+    fn name(&self) -> &str;
+
+    /// Adds this extension's edges to the federated query graph being built.
+    fn add_edges(&self, builder: &mut QueryGraphExtensionBuilder) -> Result<(), FederationError>;
+}
+
+/// The view of a federated query graph being built that `QueryGraphExtension`s are given.
+pub struct QueryGraphExtensionBuilder<'builder> {
+    base: &'builder mut BaseQueryGraphBuilder,
+    extension: NodeStr,
+}
+
+impl QueryGraphExtensionBuilder<'_> {
+    /// The names of the subgraphs in the query graph.
+    pub fn subgraph_names(&self) -> Vec<NodeStr> {
+        self.base
+            .query_graph
+            .subgraphs()
+            .map(|(source, _)| source.clone())
+            .collect()
+    }
+
+    /// The nodes for the type of the given name in the given subgraph. There can be more than one
+    /// when the type is part of some @provides, as those copy the nodes of the provided types.
+    pub fn nodes_for_type(&self, subgraph_name: &str, type_name: &str) -> Vec<NodeIndex> {
+        self.base
+            .query_graph
+            .types_to_nodes_by_source
+            .get(subgraph_name)
+            .and_then(|types_to_nodes| types_to_nodes.get(type_name))
+            .map(|nodes| nodes.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Adds an edge from the `head` node to the `tail` node, labelled with `label`. The
+    /// `conditions` are a field set on the tail type, parsed against the tail subgraph (like the
+    /// fields of an @key).
+    pub fn add_edge(
+        &mut self,
+        head: NodeIndex,
+        tail: NodeIndex,
+        label: &str,
+        conditions: &str,
+    ) -> Result<(), FederationError> {
+        self.composite_type_of_node(head)?;
+        let tail_type_position = self.composite_type_of_node(tail)?;
+        let tail_schema = self
+            .base
+            .query_graph
+            .schema_by_source(&self.base.query_graph.node_weight(tail)?.source)?;
+        let conditions = parse_field_set(
+            tail_schema,
+            tail_type_position.type_name().clone(),
+            NodeStr::new(conditions),
+        )?;
+        self.base.add_edge(
+            head,
+            tail,
+            QueryGraphEdgeTransition::ExtensionResolution {
+                extension: self.extension.clone(),
+                label: NodeStr::new(label),
+            },
+            Some(Arc::new(conditions)),
+        )
+    }
+
+    fn composite_type_of_node(
+        &self,
+        node: NodeIndex,
+    ) -> Result<CompositeTypeDefinitionPosition, FederationError> {
+        let QueryGraphNodeType::SchemaType(type_position) =
+            &self.base.query_graph.node_weight(node)?.type_
+        else {
+            return Err(SingleFederationError::Internal {
+                message: format!(
+                    "Extension \"{}\" cannot add edges to federated root nodes",
+                    self.extension,
+                ),
+            }
+            .into());
+        };
+        type_position.clone().try_into()
+    }
+}
+
 /// Builds a query graph based on the provided schema (usually an API schema outside of testing).
 ///
 /// Assumes the given schemas have been validated.
@@ -82,6 +371,7 @@ pub fn build_query_graph(
         types_to_nodes_by_source: Default::default(),
         root_kinds_to_nodes_by_source: Default::default(),
         non_trivial_followup_edges: Default::default(),
+        interface_object_types_by_source: Default::default(),
     };
     let builder = SchemaQueryGraphBuilder::new(query_graph, name, schema, None, false)?;
     query_graph = builder.build()?;
@@ -122,6 +412,8 @@ impl BaseQueryGraphBuilder {
             QueryGraphEdge {
                 transition,
                 conditions,
+                override_condition: None,
+                required_contexts: Vec::new(),
             },
         );
         let head_weight = self.query_graph.node_weight(head)?;
@@ -216,6 +508,7 @@ struct SchemaQueryGraphBuilder {
 struct SchemaQueryGraphBuilderSubgraphData {
     federation_spec_definition: &'static FederationSpecDefinition,
     api_schema: ValidFederationSchema,
+    external_metadata: ExternalMetadata,
 }
 
 impl SchemaQueryGraphBuilder {
@@ -230,9 +523,11 @@ impl SchemaQueryGraphBuilder {
     ) -> Result<Self, FederationError> {
         let subgraph = if let Some(api_schema) = api_schema {
             let federation_spec_definition = get_federation_spec_definition_from_subgraph(&schema)?;
+            let external_metadata = ExternalMetadata::new(&schema)?;
             Some(SchemaQueryGraphBuilderSubgraphData {
                 federation_spec_definition,
                 api_schema,
+                external_metadata,
             })
         } else {
             None
@@ -270,20 +565,11 @@ impl SchemaQueryGraphBuilder {
         &self,
         field_definition_position: FieldDefinitionPosition,
     ) -> Result<bool, FederationError> {
-        // TODO: Should port JS ExternalTester for this, as we're missing some fields which are
-        // effectively external.
-        Ok(if let Some(subgraph) = &self.subgraph {
-            let external_directive_definition = subgraph
-                .federation_spec_definition
-                .external_directive_definition(self.base.query_graph.schema()?)?;
-            field_definition_position
-                .get(self.base.query_graph.schema()?.schema())?
-                .directives
-                .iter()
-                .any(|d| d.name == external_directive_definition.name)
-        } else {
-            false
-        })
+        Ok(self.subgraph.as_ref().is_some_and(|subgraph| {
+            subgraph
+                .external_metadata
+                .is_external(&field_definition_position)
+        }))
     }
 
     /// Adds a node for the provided root object type (marking that node as a root node for the
@@ -382,21 +668,15 @@ impl SchemaQueryGraphBuilder {
     ) -> Result<(), FederationError> {
         let type_ =
             object_type_definition_position.get(self.base.query_graph.schema()?.schema())?;
-        let is_interface_object = if let Some(subgraph) = &self.subgraph {
-            let interface_object_directive_definition = subgraph
-                .federation_spec_definition
-                .interface_object_directive_definition(self.base.query_graph.schema()?)?
-                .ok_or_else(|| SingleFederationError::Internal {
-                    message: "Interface object directive definition unexpectedly missing"
-                        .to_owned(),
-                })?;
-            type_
-                .directives
-                .iter()
-                .any(|d| d.name == interface_object_directive_definition.name)
-        } else {
-            false
-        };
+        let is_interface_object = self.subgraph.is_some()
+            && self
+                .base
+                .query_graph
+                .schema()?
+                .subgraph_metadata()
+                .is_some_and(|metadata| {
+                    metadata.is_interface_object_type(&object_type_definition_position.type_name)
+                });
 
         // Add edges to the query graph for each field. Note subgraph extraction adds the _entities
         // field to subgraphs, so when we recursively handle that field on the root query type, we
@@ -427,7 +707,15 @@ impl SchemaQueryGraphBuilder {
         // anything, but it shouldn't be answered by an @interfaceObject and so we don't add an
         // edge in that case, ensuring the query planner has to get it from another subgraph (than
         // the one with said @interfaceObject).
-        if !is_interface_object {
+        if is_interface_object {
+            let source = self.base.query_graph.current_source.clone();
+            self.base
+                .query_graph
+                .interface_object_types_by_source
+                .entry(source)
+                .or_default()
+                .insert(object_type_definition_position.type_name.clone());
+        } else {
             let pos = object_type_definition_position.introspection_typename_field();
             self.add_edge_for_field(pos.into(), head, false)?;
         }
@@ -588,13 +876,13 @@ impl SchemaQueryGraphBuilder {
             .base
             .query_graph
             .schema()?
-            .possible_runtime_types(abstract_type_definition_position.clone().into())?;
-        for pos in implementations {
+            .cached_possible_runtime_types(abstract_type_definition_position.clone().into())?;
+        for pos in implementations.iter() {
             let tail = self.add_type_recursively(pos.clone().into())?;
             let transition = QueryGraphEdgeTransition::Downcast {
                 source: self.base.query_graph.current_source.clone(),
                 from_type_position: abstract_type_definition_position.clone().into(),
-                to_type_position: pos.into(),
+                to_type_position: pos.clone().into(),
             };
             self.base.add_edge(head, tail, transition, None)?;
         }
@@ -718,10 +1006,10 @@ impl SchemaQueryGraphBuilder {
                     .base
                     .query_graph
                     .schema()?
-                    .possible_runtime_types(pos.clone().into())?,
+                    .cached_possible_runtime_types(pos.clone().into())?,
                 api_runtime_type_positions: subgraph
                     .api_schema
-                    .possible_runtime_types(pos.clone().into())?,
+                    .cached_possible_runtime_types(pos.clone().into())?,
             });
         }
 
@@ -934,8 +1222,8 @@ impl SchemaQueryGraphBuilder {
 
 struct AbstractTypeWithRuntimeTypes {
     abstract_type_definition_position: AbstractTypeDefinitionPosition,
-    subgraph_runtime_type_positions: IndexSet<ObjectTypeDefinitionPosition>,
-    api_runtime_type_positions: IndexSet<ObjectTypeDefinitionPosition>,
+    subgraph_runtime_type_positions: Arc<IndexSet<ObjectTypeDefinitionPosition>>,
+    api_runtime_type_positions: Arc<IndexSet<ObjectTypeDefinitionPosition>>,
 }
 
 struct FederatedQueryGraphBuilder {
@@ -964,7 +1252,10 @@ impl FederatedQueryGraphBuilder {
         })
     }
 
-    fn build(mut self) -> Result<QueryGraph, FederationError> {
+    fn build(
+        mut self,
+        extensions: &[&dyn QueryGraphExtension],
+    ) -> Result<QueryGraph, FederationError> {
         self.add_federated_root_nodes()?;
         self.copy_types_to_nodes()?;
         self.add_root_edges()?;
@@ -977,6 +1268,13 @@ impl FederatedQueryGraphBuilder {
         // want to add self-edges for copied @provides nodes. (See the comments in this method for
         // more details).
         self.handle_interface_object()?;
+        // Progressive @override only annotates existing field-collecting edges (including those
+        // copied for @provides), so it's handled once all those edges exist.
+        self.handle_progressive_override()?;
+        // Similarly, @fromContext only annotates existing field-collecting edges.
+        self.handle_context()?;
+        // Extensions may connect any of the nodes added above, so they run once those all exist.
+        self.add_extension_edges(extensions)?;
         // This method adds no nodes/edges, but just precomputes followup edge information.
         self.precompute_non_trivial_followup_edges()?;
         Ok(self.base.build())
@@ -1095,8 +1393,10 @@ impl FederatedQueryGraphBuilder {
                 })?
                 .directives();
             let subgraph_data = self.subgraphs.get(source)?;
-            let is_interface_object = matches!(type_pos, OutputTypeDefinitionPosition::Object(_))
-                && directives.has(&subgraph_data.interface_object_directive_definition_name);
+            let is_interface_object = self
+                .base
+                .query_graph
+                .is_interface_object_type(source, type_pos.type_name());
 
             for application in resolvable_key_applications(
                 directives,
@@ -1199,7 +1499,8 @@ impl FederatedQueryGraphBuilder {
                         };
                         for implementation_type_in_supergraph_pos in self
                             .supergraph_schema
-                            .possible_runtime_types(type_in_supergraph_pos.into())?
+                            .cached_possible_runtime_types(type_in_supergraph_pos.into())?
+                            .iter()
                         {
                             // That implementation type may or may not exists in the "other
                             // subgraph". If it doesn't, we just have nothing to do for that
@@ -1373,7 +1674,7 @@ impl FederatedQueryGraphBuilder {
             let mut all_conditions = Vec::new();
             for directive in field
                 .directives
-                .get_all(&subgraph_data.requires_directive_definition_name)
+                .get_all(&subgraph_data.provides_directive_definition_name)
             {
                 let application = subgraph_data
                     .federation_spec_definition
@@ -1438,8 +1739,20 @@ impl FederatedQueryGraphBuilder {
         provided: &NormalizedSelectionSet,
         provide_id: u32,
     ) -> Result<(), FederationError> {
-        let mut stack = vec![(head, provided)];
+        let mut stack = vec![(head, provided.clone())];
         while let Some((node, selection_set)) = stack.pop() {
+            // Fields provided on an interface must also be provided on its implementations, since
+            // query planning may have to type-explode the interface (e.g. because some other field
+            // can't be collected on the interface directly).
+            if let CompositeTypeDefinitionPosition::Interface(_) = &selection_set.type_position {
+                Self::add_provides_edges_to_implementations(
+                    base,
+                    node,
+                    &selection_set,
+                    provide_id,
+                    &mut stack,
+                )?;
+            }
             // We reverse-iterate through the selections to cancel out the reversing that the stack
             // does.
             for selection in selection_set.selections.values().rev() {
@@ -1473,7 +1786,7 @@ impl FederatedQueryGraphBuilder {
                             if let Some(selections) = &field_selection.selection_set {
                                 let new_tail = Self::copy_for_provides(base, tail, provide_id)?;
                                 Self::update_edge_tail(base, edge, new_tail)?;
-                                stack.push((new_tail, selections))
+                                stack.push((new_tail, selections.clone()))
                             }
                         } else {
                             // There are no existing edges, which means that it's an edge added by
@@ -1544,7 +1857,7 @@ impl FederatedQueryGraphBuilder {
                             if let Some(selections) = &field_selection.selection_set {
                                 let new_tail = Self::copy_for_provides(base, tail, provide_id)?;
                                 base.add_edge(node, new_tail, transition, None)?;
-                                stack.push((new_tail, selections))
+                                stack.push((new_tail, selections.clone()))
                             } else {
                                 base.add_edge(node, tail, transition, None)?;
                             }
@@ -1556,56 +1869,65 @@ impl FederatedQueryGraphBuilder {
                             .data()
                             .type_condition_position
                         {
-                            // We should always have an edge: otherwise it would mean we list a type
-                            // condition for a type that isn't in the subgraph, but the @provides
-                            // shouldn't have validated in the first place (another way to put this
-                            // is, contrary to fields, there is no way currently to mark a full type
-                            // as @external).
-                            //
-                            // TODO: Query graph creation during composition only creates Downcast
-                            // edges between abstract types and their possible runtime object types,
-                            // so the requirement above effectively prohibits the use of type
-                            // conditions containing abstract types in @provides. This is fine for
-                            // query planning since we have the same Downcast edges (additionally
-                            // for query planning, there are usually edges between abstract types
-                            // when their intersection is at least size 2). However, when this code
-                            // gets used in composition, the assertion below will be the error users
-                            // will see in those cases, and it would be better to do a formal check
-                            // with better messaging during merging instead of during query graph
-                            // construction.
-                            let (edge, tail) = base
-                                .query_graph
-                                .graph
-                                .edges_directed(node, Direction::Outgoing)
-                                .find_map(|edge_ref| {
-                                    let edge_weight = edge_ref.weight();
-                                    let QueryGraphEdgeTransition::Downcast {
-                                        to_type_position, ..
-                                    } = &edge_weight.transition
-                                    else {
-                                        return None;
-                                    };
-                                    if to_type_position == type_condition_pos {
-                                        Some((edge_ref.id(), edge_ref.target()))
-                                    } else {
-                                        None
-                                    }
-                                })
-                                .ok_or_else(|| {
-                                    SingleFederationError::Internal {
-                                        message: format!(
-                                            "Shouldn't have selection \"{}\" in an @provides, as its type condition has no query graph edge",
-                                            inline_fragment_selection,
-                                        )
-                                    }
-                                })?;
-                            let new_tail = Self::copy_for_provides(base, tail, provide_id)?;
-                            Self::update_edge_tail(base, edge, new_tail)?;
-                            stack.push((new_tail, &inline_fragment_selection.selection_set))
+                            // We should always have an edge (or edges to the runtime types of an
+                            // abstract type condition, see below): otherwise it would mean we list
+                            // a type condition for a type that isn't in the subgraph, but the
+                            // @provides shouldn't have validated in the first place (another way to
+                            // put this is, contrary to fields, there is no way currently to mark a
+                            // full type as @external).
+                            let downcasts = Self::downcast_edges(base, node);
+                            if let Some((edge, tail, _)) = downcasts
+                                .iter()
+                                .find(|(_, _, to_type_pos)| to_type_pos == type_condition_pos)
+                            {
+                                let new_tail = Self::copy_for_provides(base, *tail, provide_id)?;
+                                Self::update_edge_tail(base, *edge, new_tail)?;
+                                stack.push((
+                                    new_tail,
+                                    inline_fragment_selection.selection_set.clone(),
+                                ));
+                                continue;
+                            }
+                            // There's no edge for an abstract type condition that isn't a
+                            // "sub-type" of the current type (e.g. an interface within a union),
+                            // in which case the selections are provided on each of the current
+                            // type's runtime types that the type condition includes.
+                            let schema = &inline_fragment_selection.selection_set.schema;
+                            let runtime_types =
+                                schema.cached_possible_runtime_types(type_condition_pos.clone())?;
+                            let mut has_runtime_type_edges = false;
+                            for (edge, tail, to_type_pos) in downcasts {
+                                let CompositeTypeDefinitionPosition::Object(to_object_type_pos) =
+                                    &to_type_pos
+                                else {
+                                    continue;
+                                };
+                                if !runtime_types.contains(to_object_type_pos) {
+                                    continue;
+                                }
+                                has_runtime_type_edges = true;
+                                let new_tail = Self::copy_for_provides(base, tail, provide_id)?;
+                                Self::update_edge_tail(base, edge, new_tail)?;
+                                stack.push((
+                                    new_tail,
+                                    inline_fragment_selection
+                                        .selection_set
+                                        .rebase_on(&to_type_pos, schema)?,
+                                ));
+                            }
+                            if !has_runtime_type_edges {
+                                return Err(SingleFederationError::Internal {
+                                    message: format!(
+                                        "Shouldn't have selection \"{}\" in an @provides, as its type condition has no query graph edge",
+                                        inline_fragment_selection,
+                                    ),
+                                }
+                                .into());
+                            }
                         } else {
                             // Essentially ignore the condition in this case, and continue
                             // propagating the provided selections.
-                            stack.push((node, &inline_fragment_selection.selection_set));
+                            stack.push((node, inline_fragment_selection.selection_set.clone()));
                         }
                     }
                     NormalizedSelection::FragmentSpread(_) => {
@@ -1621,6 +1943,67 @@ impl FederatedQueryGraphBuilder {
         Ok(())
     }
 
+    /// Copies the nodes of the implementations of the interface of the given node (i.e. the
+    /// tails of its downcast edges), and queues the field selections of the given selection set
+    /// (rebased onto each implementation) to be provided on the copies.
+    fn add_provides_edges_to_implementations(
+        base: &mut BaseQueryGraphBuilder,
+        node: NodeIndex,
+        selection_set: &NormalizedSelectionSet,
+        provide_id: u32,
+        stack: &mut Vec<(NodeIndex, NormalizedSelectionSet)>,
+    ) -> Result<(), FederationError> {
+        let mut field_selections = selection_set.clone();
+        let selections = Arc::make_mut(&mut field_selections.selections);
+        let non_field_keys = selections
+            .iter()
+            .filter(|(_, selection)| match selection {
+                NormalizedSelection::Field(field_selection) => field_selection
+                    .field
+                    .data()
+                    .field_position
+                    .is_introspection_typename_field(),
+                _ => true,
+            })
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in non_field_keys {
+            selections.remove(&key);
+        }
+        if field_selections.selections.is_empty() {
+            return Ok(());
+        }
+        for (edge, tail, to_type_pos) in Self::downcast_edges(base, node) {
+            if !matches!(to_type_pos, CompositeTypeDefinitionPosition::Object(_)) {
+                continue;
+            }
+            let new_tail = Self::copy_for_provides(base, tail, provide_id)?;
+            Self::update_edge_tail(base, edge, new_tail)?;
+            stack.push((
+                new_tail,
+                field_selections.rebase_on(&to_type_pos, &selection_set.schema)?,
+            ));
+        }
+        Ok(())
+    }
+
+    /// The downcast edges of the given node, along with their tail and the type they cast to.
+    fn downcast_edges(
+        base: &BaseQueryGraphBuilder,
+        node: NodeIndex,
+    ) -> Vec<(EdgeIndex, NodeIndex, CompositeTypeDefinitionPosition)> {
+        base.query_graph
+            .graph
+            .edges_directed(node, Direction::Outgoing)
+            .filter_map(|edge_ref| match &edge_ref.weight().transition {
+                QueryGraphEdgeTransition::Downcast {
+                    to_type_position, ..
+                } => Some((edge_ref.id(), edge_ref.target(), to_type_position.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
     fn update_edge_tail(
         base: &mut BaseQueryGraphBuilder,
         edge: EdgeIndex,
@@ -1708,6 +2091,270 @@ impl FederatedQueryGraphBuilder {
         Ok((new_node, type_pos))
     }
 
+    /// Handle progressive @override (i.e. an @override with a label) by adding the appropriate
+    /// override conditions to field-collecting edges. The edge in the overriding subgraph can only
+    /// be taken when the label is enabled, while the edge in the overridden subgraph can only be
+    /// taken when the label is disabled.
+    fn handle_progressive_override(&mut self) -> Result<(), FederationError> {
+        let override_conditions = self.progressive_override_conditions()?;
+        if override_conditions.is_empty() {
+            return Ok(());
+        }
+        for edge in self.base.query_graph.graph.edge_indices() {
+            let edge_weight = self.base.query_graph.edge_weight(edge)?;
+            let QueryGraphEdgeTransition::FieldCollection {
+                source,
+                field_definition_position,
+                ..
+            } = &edge_weight.transition
+            else {
+                continue;
+            };
+            let Some(override_condition) = override_conditions.get(&(
+                source.clone(),
+                field_definition_position.type_name().clone(),
+                field_definition_position.field_name().clone(),
+            )) else {
+                continue;
+            };
+            let override_condition = override_condition.clone();
+            self.base
+                .query_graph
+                .edge_weight_mut(edge)?
+                .override_condition = Some(override_condition);
+        }
+        Ok(())
+    }
+
+    /// Handle @context/@fromContext by adding the appropriate context conditions to the
+    /// field-collecting edges of fields with @fromContext arguments. Those edges can only be taken
+    /// when some parent type of the field sets the context used by the argument.
+    fn handle_context(&mut self) -> Result<(), FederationError> {
+        let required_contexts = self.required_contexts()?;
+        if required_contexts.is_empty() {
+            return Ok(());
+        }
+        for edge in self.base.query_graph.graph.edge_indices() {
+            let edge_weight = self.base.query_graph.edge_weight(edge)?;
+            let QueryGraphEdgeTransition::FieldCollection {
+                source,
+                field_definition_position,
+                ..
+            } = &edge_weight.transition
+            else {
+                continue;
+            };
+            let Some(conditions) = required_contexts.get(&(
+                source.clone(),
+                field_definition_position.type_name().clone(),
+                field_definition_position.field_name().clone(),
+            )) else {
+                continue;
+            };
+            let conditions = conditions.clone();
+            self.base
+                .query_graph
+                .edge_weight_mut(edge)?
+                .required_contexts = conditions;
+        }
+        Ok(())
+    }
+
+    /// Collects the context conditions of fields with @fromContext arguments, keyed by subgraph
+    /// name, parent type name, and field name. Errors if an argument uses a context that's never
+    /// set in its subgraph.
+    fn required_contexts(&self) -> Result<RequiredContexts, FederationError> {
+        let mut required_contexts: IndexMap<_, Vec<_>> = IndexMap::new();
+        for (source, schema) in &self.base.query_graph.sources {
+            if *source == self.base.query_graph.current_source {
+                continue;
+            }
+            let subgraph_data = self.subgraphs.get(source)?;
+            let (
+                Some(context_directive_definition_name),
+                Some(from_context_directive_definition_name),
+            ) = (
+                &subgraph_data.context_directive_definition_name,
+                &subgraph_data.from_context_directive_definition_name,
+            )
+            else {
+                continue;
+            };
+
+            // Collect the types setting each context. A context set on an abstract type is also
+            // set on its possible runtime types, as paths may go through those directly.
+            let mut types_by_context: IndexMap<NodeStr, IndexSet<CompositeTypeDefinitionPosition>> =
+                IndexMap::new();
+            let context_referencers = schema
+                .referencers()
+                .get_directive(context_directive_definition_name)?;
+            let context_types = context_referencers
+                .object_types
+                .iter()
+                .cloned()
+                .map(CompositeTypeDefinitionPosition::from)
+                .chain(
+                    context_referencers
+                        .interface_types
+                        .iter()
+                        .cloned()
+                        .map(CompositeTypeDefinitionPosition::from),
+                )
+                .chain(
+                    context_referencers
+                        .union_types
+                        .iter()
+                        .cloned()
+                        .map(CompositeTypeDefinitionPosition::from),
+                );
+            for type_pos in context_types {
+                let directives = match &type_pos {
+                    CompositeTypeDefinitionPosition::Object(pos) => {
+                        &pos.get(schema.schema())?.directives
+                    }
+                    CompositeTypeDefinitionPosition::Interface(pos) => {
+                        &pos.get(schema.schema())?.directives
+                    }
+                    CompositeTypeDefinitionPosition::Union(pos) => {
+                        &pos.get(schema.schema())?.directives
+                    }
+                };
+                for application in directives.get_all(context_directive_definition_name) {
+                    let context = subgraph_data
+                        .federation_spec_definition
+                        .context_directive_arguments(application)?
+                        .name;
+                    let types = types_by_context.entry(context).or_default();
+                    if !matches!(type_pos, CompositeTypeDefinitionPosition::Object(_)) {
+                        types.extend(
+                            schema
+                                .cached_possible_runtime_types(type_pos.clone())?
+                                .iter()
+                                .cloned()
+                                .map(CompositeTypeDefinitionPosition::from),
+                        );
+                    }
+                    types.insert(type_pos.clone());
+                }
+            }
+
+            let from_context_referencers = schema
+                .referencers()
+                .get_directive(from_context_directive_definition_name)?;
+            let arguments = from_context_referencers
+                .object_field_arguments
+                .iter()
+                .map(|pos| {
+                    (
+                        pos.type_name.clone(),
+                        pos.field_name.clone(),
+                        pos.argument_name.clone(),
+                        pos.get(schema.schema()),
+                    )
+                })
+                .chain(
+                    from_context_referencers
+                        .interface_field_arguments
+                        .iter()
+                        .map(|pos| {
+                            (
+                                pos.type_name.clone(),
+                                pos.field_name.clone(),
+                                pos.argument_name.clone(),
+                                pos.get(schema.schema()),
+                            )
+                        }),
+                );
+            for (type_name, field_name, argument_name, argument) in arguments {
+                let argument = argument?;
+                let coordinate = format!("{}.{}({}:)", type_name, field_name, argument_name);
+                for application in argument
+                    .directives
+                    .get_all(from_context_directive_definition_name)
+                {
+                    let field = subgraph_data
+                        .federation_spec_definition
+                        .from_context_directive_arguments(application)?
+                        .field;
+                    let field = field.as_ref().map_or("", |field| field.as_str());
+                    let (context, selection) = parse_context_selection(field, &coordinate)?;
+                    let Some(types_with_context_set) = types_by_context.get(context) else {
+                        return Err(SingleFederationError::ContextNotSet {
+                            message: format!(
+                                "Context \"{}\" is used at location \"{}\" but is never set.",
+                                context, coordinate,
+                            ),
+                        }
+                        .into());
+                    };
+                    required_contexts
+                        .entry((source.clone(), type_name.clone(), field_name.clone()))
+                        .or_default()
+                        .push(ContextCondition {
+                            context: NodeStr::new(context),
+                            subgraph_name: source.clone(),
+                            named_parameter: argument_name.clone(),
+                            selection: NodeStr::new(selection),
+                            types_with_context_set: types_with_context_set.clone(),
+                            argument_type: argument.ty.clone(),
+                            coordinate: coordinate.clone(),
+                        });
+                }
+            }
+        }
+        Ok(required_contexts)
+    }
+
+    /// Collects the override conditions of fields using progressive @override from the supergraph
+    /// `@join__field` applications, keyed by subgraph name, parent type name, and field name.
+    fn progressive_override_conditions(
+        &self,
+    ) -> Result<IndexMap<(NodeStr, Name, Name), OverrideCondition>, FederationError> {
+        let mut override_conditions = IndexMap::new();
+        let join_metadata = self.supergraph_schema.join_metadata();
+        for (type_name, field_name, application) in join_metadata.all_field_applications() {
+            let (Some(label), Some(graph_enum_value)) =
+                (&application.override_label, &application.graph)
+            else {
+                continue;
+            };
+            let subgraph_name = join_metadata
+                .graphs()
+                .get(graph_enum_value)
+                .map(|graph| &graph.name)
+                .ok_or_else(|| SingleFederationError::Internal {
+                    message: format!(
+                        "Value \"{}\" of join__Graph enum has no @join__graph directive",
+                        graph_enum_value,
+                    ),
+                })?;
+            if let Some(from) = &application.override_ {
+                override_conditions.insert(
+                    (subgraph_name.clone(), type_name.clone(), field_name.clone()),
+                    OverrideCondition {
+                        label: label.clone(),
+                        condition: true,
+                    },
+                );
+                override_conditions.insert(
+                    (from.clone(), type_name.clone(), field_name.clone()),
+                    OverrideCondition {
+                        label: label.clone(),
+                        condition: false,
+                    },
+                );
+            } else {
+                override_conditions
+                    .entry((subgraph_name.clone(), type_name.clone(), field_name.clone()))
+                    .or_insert_with(|| OverrideCondition {
+                        label: label.clone(),
+                        condition: false,
+                    });
+            }
+        }
+        Ok(override_conditions)
+    }
+
     /// Handle @interfaceObject by adding the appropriate fake-downcast self-edges.
     fn handle_interface_object(&mut self) -> Result<(), FederationError> {
         // There are cases where only an/some implementation(s) of an interface are queried, and
@@ -1819,12 +2466,13 @@ impl FederatedQueryGraphBuilder {
                 )?);
                 for implementation_type_in_supergraph_pos in self
                     .supergraph_schema
-                    .possible_runtime_types(type_in_supergraph_pos.into())?
+                    .cached_possible_runtime_types(type_in_supergraph_pos.into())?
+                    .iter()
                 {
                     let transition = QueryGraphEdgeTransition::InterfaceObjectFakeDownCast {
                         source: source.clone(),
                         from_type_position: type_pos.clone().into(),
-                        to_type_name: implementation_type_in_supergraph_pos.type_name,
+                        to_type_name: implementation_type_in_supergraph_pos.type_name.clone(),
                     };
                     new_edges.push(QueryGraphEdgeData {
                         head: node,
@@ -1842,6 +2490,20 @@ impl FederatedQueryGraphBuilder {
     }
 
     /// Precompute which followup edges for a given edge are non-trivial.
+    fn add_extension_edges(
+        &mut self,
+        extensions: &[&dyn QueryGraphExtension],
+    ) -> Result<(), FederationError> {
+        for extension in extensions {
+            let mut builder = QueryGraphExtensionBuilder {
+                base: &mut self.base,
+                extension: NodeStr::new(extension.name()),
+            };
+            extension.add_edges(&mut builder)?;
+        }
+        Ok(())
+    }
+
     fn precompute_non_trivial_followup_edges(&mut self) -> Result<(), FederationError> {
         for edge in self.base.query_graph.graph.edge_indices() {
             let edge_weight = self.base.query_graph.edge_weight(edge)?;
@@ -1923,7 +2585,36 @@ impl FederatedQueryGraphBuilder {
     }
 }
 
-const FEDERATED_GRAPH_ROOT_SOURCE: &str = "_";
+pub(crate) const FEDERATED_GRAPH_ROOT_SOURCE: &str = "_";
+
+/// The context conditions of fields, keyed by subgraph name, parent type name, and field name.
+type RequiredContexts = IndexMap<(NodeStr, Name, Name), Vec<ContextCondition>>;
+
+/// Splits the `field` argument of a @fromContext application (e.g. `$context { id }`) into the
+/// context name and the selection within the context type.
+fn parse_context_selection<'a>(
+    field: &'a str,
+    coordinate: &str,
+) -> Result<(&'a str, &'a str), FederationError> {
+    let no_context = || SingleFederationError::NoContextInSelection {
+        message: format!(
+            "@fromContext argument does not reference a context \"{}\" at location \"{}\".",
+            field, coordinate,
+        ),
+    };
+    let rest = field
+        .trim_start()
+        .strip_prefix('$')
+        .ok_or_else(no_context)?;
+    let context_end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    if context_end == 0 {
+        return Err(no_context().into());
+    }
+    let (context, selection) = rest.split_at(context_end);
+    Ok((context, selection.trim()))
+}
 
 struct FederatedQueryGraphBuilderSubgraphs {
     map: IndexMap<NodeStr, FederatedQueryGraphBuilderSubgraphData>,
@@ -1964,6 +2655,12 @@ impl FederatedQueryGraphBuilderSubgraphs {
                         ),
                     }
                 })?;
+            let context_directive_definition_name = federation_spec_definition
+                .context_directive_definition(schema)?
+                .map(|d| d.name.clone());
+            let from_context_directive_definition_name = federation_spec_definition
+                .from_context_directive_definition(schema)?
+                .map(|d| d.name.clone());
             subgraphs.map.insert(
                 source.clone(),
                 FederatedQueryGraphBuilderSubgraphData {
@@ -1972,6 +2669,8 @@ impl FederatedQueryGraphBuilderSubgraphs {
                     requires_directive_definition_name,
                     provides_directive_definition_name,
                     interface_object_directive_definition_name,
+                    context_directive_definition_name,
+                    from_context_directive_definition_name,
                 },
             );
         }
@@ -1997,6 +2696,8 @@ struct FederatedQueryGraphBuilderSubgraphData {
     requires_directive_definition_name: Name,
     provides_directive_definition_name: Name,
     interface_object_directive_definition_name: Name,
+    context_directive_definition_name: Option<Name>,
+    from_context_directive_definition_name: Option<Name>,
 }
 
 struct QueryGraphEdgeData {
@@ -2032,15 +2733,23 @@ fn resolvable_key_applications(
 #[cfg(test)]
 mod tests {
     use crate::error::FederationError;
-    use crate::query_graph::build_query_graph::build_query_graph;
+    use crate::query_graph::build_query_graph::{
+        build_federated_query_graph, build_federated_query_graph_with_extensions,
+        build_query_graph, QueryGraphExtension, QueryGraphExtensionBuilder,
+    };
+    use crate::query_graph::extract_subgraphs_from_supergraph::extract_subgraphs_from_supergraph;
     use crate::query_graph::{
-        QueryGraph, QueryGraphEdgeTransition, QueryGraphNode, QueryGraphNodeType,
+        EnabledOverrideConditions, OverrideCondition, QueryGraph, QueryGraphEdgeTransition,
+        QueryGraphNode, QueryGraphNodeType,
     };
+    use crate::query_plan::operation::{FragmentSpreadNormalizationOption, NormalizedSelectionSet};
     use crate::schema::position::{
         ObjectOrInterfaceTypeDefinitionPosition, ObjectTypeDefinitionPosition,
         OutputTypeDefinitionPosition, ScalarTypeDefinitionPosition, SchemaRootDefinitionKind,
     };
     use crate::schema::ValidFederationSchema;
+    use crate::test_supergraph::supergraph_sdl;
+    use apollo_compiler::executable::FieldSet;
     use apollo_compiler::schema::Name;
     use apollo_compiler::{name, NodeStr, Schema};
     use indexmap::{IndexMap, IndexSet};
@@ -2311,4 +3020,507 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn building_federated_query_graphs_handles_progressive_override() -> Result<(), FederationError>
+    {
+        let supergraph = crate::Supergraph::new(&supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              a: Int @join__field(graph: S1, override: "S2", overrideLabel: "percent(50)") @join__field(graph: S2, overrideLabel: "percent(50)")
+              b: Int @join__field(graph: S2)
+            }
+            "#,
+        ))?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let query_graph = build_federated_query_graph(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?;
+
+        let mut override_conditions = query_graph
+            .graph
+            .edge_references()
+            .filter_map(|edge_ref| {
+                let edge_weight = edge_ref.weight();
+                let QueryGraphEdgeTransition::FieldCollection {
+                    source,
+                    field_definition_position,
+                    ..
+                } = &edge_weight.transition
+                else {
+                    return None;
+                };
+                Some((
+                    format!("{}({})", field_definition_position, source),
+                    edge_weight.override_condition.clone(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        override_conditions.sort_by(|a, b| a.0.cmp(&b.0));
+        let label = NodeStr::new("percent(50)");
+        assert_eq!(
+            override_conditions
+                .into_iter()
+                .filter(|(name, _)| name.starts_with("Query.") && !name.starts_with("Query._"))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "Query.a(S1)".to_owned(),
+                    Some(OverrideCondition {
+                        label: label.clone(),
+                        condition: true,
+                    })
+                ),
+                (
+                    "Query.a(S2)".to_owned(),
+                    Some(OverrideCondition {
+                        label,
+                        condition: false,
+                    })
+                ),
+                ("Query.b(S2)".to_owned(), None),
+            ],
+        );
+
+        let enabled = EnabledOverrideConditions::new(["percent(50)".to_owned()]);
+        let disabled = EnabledOverrideConditions::default();
+        for edge_ref in query_graph.graph.edge_references() {
+            let edge_weight = edge_ref.weight();
+            if let Some(override_condition) = &edge_weight.override_condition {
+                assert_eq!(
+                    edge_weight.satisfies_override_conditions(&enabled),
+                    override_condition.condition
+                );
+                assert_eq!(
+                    edge_weight.satisfies_override_conditions(&disabled),
+                    !override_condition.condition
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn context_supergraph() -> String {
+        supergraph_sdl(
+            &["S1"],
+            r#"
+            extend schema @link(url: "https://specs.apollo.dev/context/v0.1", for: SECURITY)
+
+            directive @context(name: String!) repeatable on INTERFACE | OBJECT | UNION
+
+            type Query
+              @join__type(graph: S1)
+            {
+              t: T!
+            }
+
+            type T
+              @join__type(graph: S1)
+              @context(name: "S1__ctx")
+            {
+              prop: String!
+              u: U!
+            }
+
+            type U
+              @join__type(graph: S1)
+            {
+              field: Int! @join__field(graph: S1, contextArguments: [{context: "S1__ctx", name: "a", type: "String", selection: "{ prop }"}])
+            }
+            "#,
+        )
+    }
+
+    #[test]
+    fn building_federated_query_graphs_handles_context() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&context_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let query_graph = build_federated_query_graph(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?;
+
+        let required_contexts = query_graph
+            .graph
+            .edge_references()
+            .filter(|edge_ref| !edge_ref.weight().required_contexts.is_empty())
+            .map(|edge_ref| {
+                let edge_weight = edge_ref.weight();
+                format!(
+                    "{} requires {}",
+                    edge_weight.transition, edge_weight.required_contexts[0]
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(required_contexts, ["field requires $ctx { prop }"]);
+        let [context] = query_graph
+            .graph
+            .edge_references()
+            .flat_map(|edge_ref| edge_ref.weight().required_contexts.clone())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        assert_eq!(context.subgraph_name, "S1");
+        assert_eq!(context.named_parameter, name!("a"));
+        assert_eq!(context.coordinate, "U.field(a:)");
+        assert_eq!(
+            context
+                .types_with_context_set
+                .iter()
+                .map(|type_pos| type_pos.type_name().clone())
+                .collect::<Vec<_>>(),
+            [name!("T")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn building_federated_query_graphs_rejects_unset_contexts() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(
+            &context_supergraph().replace("@context(name: \"S1__ctx\")", ""),
+        )?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let Err(error) = build_federated_query_graph(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        ) else {
+            panic!("Expected an error for a context that is never set");
+        };
+        assert_eq!(
+            error.to_string(),
+            "Context \"ctx\" is used at location \"U.field(a:)\" but is never set."
+        );
+        Ok(())
+    }
+
+    struct LookupExtension;
+
+    impl QueryGraphExtension for LookupExtension {
+        fn name(&self) -> &str {
+            "lookup"
+        }
+
+        fn add_edges(
+            &self,
+            builder: &mut QueryGraphExtensionBuilder,
+        ) -> Result<(), FederationError> {
+            for head in builder.nodes_for_type("S1", "T") {
+                for tail in builder.nodes_for_type("S2", "T") {
+                    builder.add_edge(head, tail, "byId", "id")?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn building_federated_query_graphs_adds_extension_edges() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              t: T @join__field(graph: S1)
+              allTs: [T] @join__field(graph: S2)
+            }
+
+            type T
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              id: ID!
+              x: Int @join__field(graph: S2)
+            }
+            "#,
+        ))?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let query_graph = build_federated_query_graph_with_extensions(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+            &[&LookupExtension],
+        )?;
+
+        let extension_edges = query_graph
+            .graph
+            .edge_references()
+            .filter(|edge_ref| {
+                matches!(
+                    edge_ref.weight().transition,
+                    QueryGraphEdgeTransition::ExtensionResolution { .. }
+                )
+            })
+            .map(|edge_ref| {
+                Ok(format!(
+                    "{} -> {} [{}]",
+                    query_graph.node_weight(edge_ref.source())?,
+                    query_graph.node_weight(edge_ref.target())?,
+                    edge_ref.weight(),
+                ))
+            })
+            .collect::<Result<Vec<_>, FederationError>>()?;
+        assert_eq!(
+            extension_edges,
+            vec!["T(S1) -> T(S2) [{ id } ⊢ lookup:byId()]".to_owned()],
+        );
+        // The extension edge makes the other subgraph reachable from the field returning `T`.
+        let t_node = query_graph.types_to_nodes_by_source("S1")?["T"][0];
+        assert!(
+            query_graph
+                .node_weight(t_node)?
+                .has_reachable_cross_subgraph_edges
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn building_federated_query_graphs_handles_provides_on_abstract_types(
+    ) -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              media: [Media] @join__field(graph: S1, provides: "title")
+              search: [Result] @join__field(graph: S1, provides: "... on Media { title }")
+            }
+
+            interface Media
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              id: ID!
+              title: String @join__field(graph: S1, external: true) @join__field(graph: S2)
+            }
+
+            type Book implements Media
+              @join__implements(graph: S1, interface: "Media")
+              @join__implements(graph: S2, interface: "Media")
+              @join__type(graph: S1, key: "id")
+              @join__type(graph: S2, key: "id")
+            {
+              id: ID!
+              title: String @join__field(graph: S1, external: true) @join__field(graph: S2)
+            }
+
+            type Movie implements Media
+              @join__implements(graph: S1, interface: "Media")
+              @join__implements(graph: S2, interface: "Media")
+              @join__type(graph: S1, key: "id")
+              @join__type(graph: S2, key: "id")
+            {
+              id: ID!
+              title: String @join__field(graph: S1, external: true) @join__field(graph: S2)
+            }
+
+            union Result
+              @join__type(graph: S1)
+              @join__unionMember(graph: S1, member: "Book")
+              @join__unionMember(graph: S1, member: "Movie")
+             = Book | Movie
+            "#,
+        ))?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let query_graph = build_federated_query_graph(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?;
+
+        // The provided `title` can be collected in S1 on the provided copies of the interface and
+        // of its implementations (through the interface or the union).
+        let mut provided_edges = query_graph
+            .graph
+            .edge_references()
+            .filter(|edge_ref| {
+                matches!(
+                    &edge_ref.weight().transition,
+                    QueryGraphEdgeTransition::FieldCollection {
+                        field_definition_position,
+                        ..
+                    } if field_definition_position.field_name() == "title"
+                )
+            })
+            .map(|edge_ref| {
+                Ok(format!(
+                    "{} -> {} [{}]",
+                    query_graph.node_weight(edge_ref.source())?,
+                    query_graph.node_weight(edge_ref.target())?,
+                    edge_ref.weight(),
+                ))
+            })
+            .filter(|edge| !matches!(edge, Ok(edge) if edge.contains("(S2)")))
+            .collect::<Result<Vec<_>, FederationError>>()?;
+        provided_edges.sort();
+        assert_eq!(
+            provided_edges,
+            vec![
+                "Book(S1)-1 -> String(S1) [title]",
+                "Book(S1)-2 -> String(S1) [title]",
+                "Media(S1)-1 -> String(S1) [title]",
+                "Media(S1)-2 -> String(S1) [title]",
+                "Movie(S1)-1 -> String(S1) [title]",
+                "Movie(S1)-2 -> String(S1) [title]",
+            ],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_selection_sets_locally_from_shared_root_fields() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              products: [Product]
+            }
+
+            type Product
+              @join__type(graph: S1, key: "id")
+              @join__type(graph: S2, key: "id")
+            {
+              id: ID!
+              name: String @join__field(graph: S1)
+              price: Int @join__field(graph: S2)
+            }
+            "#,
+        ))?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?;
+        let query_root = |source: &str| {
+            query_graph
+                .graph
+                .node_indices()
+                .find(|node| {
+                    let node_weight = &query_graph.graph[*node];
+                    node_weight.root_kind == Some(SchemaRootDefinitionKind::Query)
+                        && node_weight.source.as_str() == source
+                })
+                .unwrap()
+        };
+        let resolves_locally = |source: &str, selection_set: &str| {
+            let field_set = FieldSet::parse_and_validate(
+                supergraph_schema.schema(),
+                name!("Query"),
+                selection_set,
+                "field_set.graphql",
+            )
+            .unwrap();
+            let selection_set = NormalizedSelectionSet::normalize_and_expand_fragments(
+                &field_set.selection_set,
+                &Default::default(),
+                &supergraph_schema,
+                FragmentSpreadNormalizationOption::InlineFragmentSpread,
+                &Default::default(),
+            )?;
+            query_graph.resolves_selection_set_locally(query_root(source), &selection_set)
+        };
+
+        assert!(resolves_locally("S1", "products { id name }")?);
+        assert!(resolves_locally(
+            "S1",
+            "products { __typename ... on Product { name } }"
+        )?);
+        assert!(!resolves_locally("S1", "products { id price }")?);
+        assert!(resolves_locally("S2", "products { id price }")?);
+        assert!(!resolves_locally("S2", "products { name }")?);
+        Ok(())
+    }
+
+    fn updatable_supergraph(s2_fields: &str) -> String {
+        supergraph_sdl(
+            &["S1", "S2"],
+            &format!(
+                r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {{
+              t: T @join__field(graph: S1, provides: "name")
+            }}
+
+            type T
+              @join__type(graph: S1, key: "id")
+              @join__type(graph: S2, key: "id")
+            {{
+              id: ID!
+              name: String @join__field(graph: S1, external: true) @join__field(graph: S2)
+              {s2_fields}
+            }}
+            "#
+            ),
+        )
+    }
+
+    fn build_updatable_query_graph(
+        s2_fields: &str,
+    ) -> Result<(QueryGraph, ValidFederationSchema, ValidFederationSchema), FederationError> {
+        let supergraph = crate::Supergraph::new(&updatable_supergraph(s2_fields))?;
+        let supergraph_schema = supergraph.schema.clone();
+        let api_schema = ValidFederationSchema::new(supergraph.to_api_schema(Default::default())?)?;
+        let query_graph = build_federated_query_graph(
+            supergraph_schema.clone(),
+            api_schema.clone(),
+            Some(true),
+            Some(true),
+        )?;
+        Ok((query_graph, supergraph_schema, api_schema))
+    }
+
+    #[test]
+    fn updating_federated_query_graphs_only_rebuilds_changed_subgraph(
+    ) -> Result<(), FederationError> {
+        let (old, _, _) = build_updatable_query_graph("x: Int @join__field(graph: S2)")?;
+        let (rebuilt, supergraph_schema, api_schema) = build_updatable_query_graph(
+            "x: Int @join__field(graph: S2)\ny: String @join__field(graph: S2)",
+        )?;
+        let mut subgraphs = extract_subgraphs_from_supergraph(&supergraph_schema, Some(true))?
+            .into_iter()
+            .collect::<IndexMap<_, _>>();
+
+        // S1's @provides copies have to be skipped when copying it, and S2 is rebuilt.
+        let s2 = subgraphs.shift_remove("S2").unwrap();
+        let updated =
+            old.with_updated_subgraph(supergraph_schema.clone(), api_schema.clone(), s2)?;
+        assert!(updated.diff(&rebuilt)?.is_empty());
+        insta::assert_snapshot!(old.diff(&updated)?, @r###"
+        + T(S2) -> String(S2) [y]
+        "###);
+        assert_eq!(
+            updated.non_trivial_followup_edges().len(),
+            rebuilt.non_trivial_followup_edges().len()
+        );
+
+        // Updating S1 copies S2 instead, and S1's @provides copies are rebuilt.
+        let s1 = subgraphs.shift_remove("S1").unwrap();
+        let updated = updated.with_updated_subgraph(supergraph_schema, api_schema, s1)?;
+        assert!(updated.diff(&rebuilt)?.is_empty());
+        Ok(())
+    }
 }
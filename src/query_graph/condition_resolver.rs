@@ -4,6 +4,7 @@ use crate::query_graph::graph_path::{
 };
 use crate::query_graph::path_tree::OpPathTree;
 use crate::query_plan::QueryPlanCost;
+use indexmap::IndexMap;
 use petgraph::graph::EdgeIndex;
 use std::sync::Arc;
 
@@ -47,16 +48,107 @@ impl ConditionResolution {
     }
 }
 
-pub(crate) struct CachingConditionResolver;
+/// A cache of the resolutions of edge conditions, keyed by edge.
+///
+/// Resolutions are saved along with the excluded destinations used to compute them, since those
+/// impact the resolution: a cached resolution is only used for the same excluded destinations.
+/// Only the first excluded destinations seen for an edge are cached, which works in practice since
+/// during a given traversal an edge is almost always resolved with the same ones.
+// PORT_NOTE: The JS codebase wrapped the resolver callback in `cachingConditionResolver()`, and
+// stored the cache in a `QueryGraphState`.
+#[derive(Debug, Default)]
+pub(crate) struct ConditionResolverCache {
+    edge_states: IndexMap<EdgeIndex, (ConditionResolution, ExcludedDestinations)>,
+}
 
-impl ConditionResolver for CachingConditionResolver {
-    fn resolve(
+#[derive(Debug)]
+pub(crate) enum ConditionResolutionCacheResult {
+    /// The cached resolution for the edge.
+    Hit(ConditionResolution),
+    /// Nothing is cached for the edge, and the resolution should be inserted once computed.
+    Miss,
+    /// The resolution can't be cached, e.g. because it was cached for other excluded destinations,
+    /// and should neither be taken from nor inserted into the cache.
+    NotApplicable,
+}
+
+impl ConditionResolverCache {
+    pub(crate) fn new() -> Self {
+        Default::default()
+    }
+
+    pub(crate) fn contains(
+        &self,
+        edge: EdgeIndex,
+        context: &OpGraphPathContext,
+        excluded_destinations: &ExcludedDestinations,
+        excluded_conditions: &ExcludedConditions,
+    ) -> ConditionResolutionCacheResult {
+        // We don't cache if there is a context or excluded conditions, because those would impact
+        // the resolution and we don't want to cache a value per context and per excluded
+        // conditions.
+        if !context.is_empty() || !excluded_conditions.is_empty() {
+            return ConditionResolutionCacheResult::NotApplicable;
+        }
+        match self.edge_states.get(&edge) {
+            Some((resolution, cached_excluded_destinations)) => {
+                if cached_excluded_destinations.is_equivalent(excluded_destinations) {
+                    ConditionResolutionCacheResult::Hit(resolution.clone())
+                } else {
+                    ConditionResolutionCacheResult::NotApplicable
+                }
+            }
+            None => ConditionResolutionCacheResult::Miss,
+        }
+    }
+
+    pub(crate) fn insert(
         &mut self,
-        _edge: EdgeIndex,
-        _context: &OpGraphPathContext,
-        _excluded_destinations: &ExcludedDestinations,
-        _excluded_conditions: &ExcludedConditions,
-    ) -> Result<ConditionResolution, FederationError> {
-        todo!()
+        edge: EdgeIndex,
+        resolution: ConditionResolution,
+        excluded_destinations: ExcludedDestinations,
+    ) {
+        self.edge_states
+            .insert(edge, (resolution, excluded_destinations));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_resolutions_per_edge() {
+        let mut cache = ConditionResolverCache::new();
+        let edge = EdgeIndex::new(0);
+        let context = OpGraphPathContext::default();
+        let excluded_destinations = ExcludedDestinations::default();
+        let excluded_conditions = ExcludedConditions::default();
+        assert!(matches!(
+            cache.contains(edge, &context, &excluded_destinations, &excluded_conditions),
+            ConditionResolutionCacheResult::Miss
+        ));
+
+        cache.insert(
+            edge,
+            ConditionResolution::Satisfied {
+                cost: 3,
+                path_tree: None,
+            },
+            excluded_destinations.clone(),
+        );
+        assert!(matches!(
+            cache.contains(edge, &context, &excluded_destinations, &excluded_conditions),
+            ConditionResolutionCacheResult::Hit(ConditionResolution::Satisfied { cost: 3, .. })
+        ));
+        assert!(matches!(
+            cache.contains(
+                EdgeIndex::new(1),
+                &context,
+                &excluded_destinations,
+                &excluded_conditions
+            ),
+            ConditionResolutionCacheResult::Miss
+        ));
     }
 }
@@ -1,14 +1,18 @@
 use crate::error::{FederationError, MultipleFederationErrors, SingleFederationError};
+use crate::field_set::parse_field_set_without_normalization;
+use crate::link::context_spec_definition::{
+    get_context_spec_definition_from_schema, CONTEXT_DIRECTIVE_NAME_IN_SPEC,
+};
 use crate::link::federation_spec_definition::{
-    get_federation_spec_definition_from_subgraph, FederationSpecDefinition, FEDERATION_VERSIONS,
+    get_federation_spec_definition_from_subgraph, FederationSpecDefinition,
 };
+use crate::link::join_metadata::JoinMetadata;
 use crate::link::join_spec_definition::{
-    FieldDirectiveArguments, JoinSpecDefinition, TypeDirectiveArguments, JOIN_VERSIONS,
+    JoinFieldDirectiveArguments, JoinSpecDefinition, JoinTypeDirectiveArguments, JOIN_VERSIONS,
 };
 use crate::link::link_spec_definition::LinkSpecDefinition;
-use crate::link::spec::{Identity, Version};
+use crate::link::spec::{Identity, Version, APOLLO_SPEC_DOMAIN};
 use crate::link::spec_definition::SpecDefinition;
-use crate::query_graph::field_set::parse_field_set_without_normalization;
 use crate::schema::position::{
     is_graphql_reserved_name, CompositeTypeDefinitionPosition, DirectiveDefinitionPosition,
     EnumTypeDefinitionPosition, FieldDefinitionPosition, InputObjectFieldDefinitionPosition,
@@ -22,7 +26,7 @@ use crate::schema::{FederationSchema, ValidFederationSchema};
 use apollo_compiler::ast::FieldDefinition;
 use apollo_compiler::executable::{Field, Selection, SelectionSet};
 use apollo_compiler::schema::{
-    Component, ComponentName, ComponentOrigin, DirectiveDefinition, DirectiveList,
+    Component, ComponentName, ComponentOrigin, Directive, DirectiveDefinition, DirectiveList,
     DirectiveLocation, EnumType, EnumValueDefinition, ExtendedType, ExtensionId, InputObjectType,
     InputValueDefinition, InterfaceType, Name, NamedType, ObjectType, ScalarType, Type, UnionType,
 };
@@ -31,25 +35,51 @@ use apollo_compiler::{name, Node, NodeStr, Schema};
 use indexmap::{IndexMap, IndexSet};
 use lazy_static::lazy_static;
 use std::collections::BTreeMap;
+use std::fmt::Display;
 use std::ops::Deref;
-use std::sync::Arc;
 
+/// Assumes the given schema has been validated.
+pub(crate) fn extract_subgraphs_from_supergraph(
+    supergraph_schema: &ValidFederationSchema,
+    validate_extracted_subgraphs: Option<bool>,
+) -> Result<ValidFederationSubgraphs, FederationError> {
+    extract_subgraphs_from_supergraph_with_diagnostics(
+        supergraph_schema,
+        validate_extracted_subgraphs,
+        &mut ExtractionDiagnostics::new(false),
+    )
+}
+
+/// Same as `extract_subgraphs_from_supergraph()`, but if the given diagnostics are recovering, then
+/// errors in individual types, fields and subgraphs are collected in them instead of failing the
+/// extraction. Subgraphs made unusable by such errors are left out of the returned subgraphs.
+///
 /// Assumes the given schema has been validated.
 ///
 /// TODO: A lot of common data gets passed around in the functions called by this one, considering
 /// making an e.g. ExtractSubgraphs struct to contain the data.
-pub(super) fn extract_subgraphs_from_supergraph(
-    supergraph_schema: &FederationSchema,
+pub(crate) fn extract_subgraphs_from_supergraph_with_diagnostics(
+    supergraph_schema: &ValidFederationSchema,
     validate_extracted_subgraphs: Option<bool>,
+    diagnostics: &mut ExtractionDiagnostics,
 ) -> Result<ValidFederationSubgraphs, FederationError> {
     let validate_extracted_subgraphs = validate_extracted_subgraphs.unwrap_or(true);
     let (link_spec_definition, join_spec_definition) = validate_supergraph(supergraph_schema)?;
+    let join_metadata = supergraph_schema.join_metadata();
     let is_fed_1 = *join_spec_definition.version() == Version { major: 0, minor: 1 };
     let (mut subgraphs, federation_spec_definitions, graph_enum_value_name_to_subgraph_name) =
-        collect_empty_subgraphs(supergraph_schema, join_spec_definition)?;
+        collect_empty_subgraphs(join_metadata)?;
 
     let mut filtered_types = Vec::new();
     for type_definition_position in supergraph_schema.get_types() {
+        // Built-in types (introspection types and built-in scalars) are never marked with
+        // @join__type, and each subgraph schema already has them.
+        if type_definition_position
+            .get(supergraph_schema.schema())?
+            .is_built_in()
+        {
+            continue;
+        }
         if !join_spec_definition
             .is_spec_type_name(supergraph_schema, type_definition_position.type_name())?
             && !link_spec_definition
@@ -59,18 +89,19 @@ pub(super) fn extract_subgraphs_from_supergraph(
         }
     }
     if is_fed_1 {
-        // Handle Fed 1 supergraphs eventually, the extraction logic is gnarly
-        todo!()
-    } else {
-        extract_subgraphs_from_fed_2_supergraph(
-            supergraph_schema,
-            &mut subgraphs,
-            &graph_enum_value_name_to_subgraph_name,
-            &federation_spec_definitions,
-            join_spec_definition,
-            &filtered_types,
-        )?;
+        // PORT_NOTE: The JS codebase extracts Fed 1 supergraphs, but its query planner rejects
+        // them anyway, and the extraction logic is gnarly.
+        return Err(fed_1_supergraph_error());
     }
+    extract_subgraphs_from_fed_2_supergraph(
+        supergraph_schema,
+        &mut subgraphs,
+        &graph_enum_value_name_to_subgraph_name,
+        &federation_spec_definitions,
+        join_metadata,
+        &filtered_types,
+        diagnostics,
+    )?;
 
     for graph_enum_value in graph_enum_value_name_to_subgraph_name.keys() {
         let subgraph = get_subgraph(
@@ -83,21 +114,24 @@ pub(super) fn extract_subgraphs_from_supergraph(
             .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
                 message: "Subgraph unexpectedly does not use federation spec".to_owned(),
             })?;
-        add_federation_operations(subgraph, federation_spec_definition)?;
+        let subgraph_name = subgraph.name.clone();
+        diagnostics.recover_subgraph(&subgraph_name, || {
+            add_federation_operations(subgraph, federation_spec_definition)
+        })?;
     }
 
     let mut valid_subgraphs = ValidFederationSubgraphs::new();
     for (_, subgraph) in subgraphs {
+        if diagnostics.unusable_subgraphs.contains(&subgraph.name) {
+            continue;
+        }
         let valid_subgraph_schema = if validate_extracted_subgraphs {
-            match subgraph.schema.validate() {
-                Ok(schema) => schema,
-                Err(error) => {
-                    // TODO: Implement maybeDumpSubgraphSchema() for better error messaging
-                    if is_fed_1 {
-                        // See message above about Fed 1 supergraphs
-                        todo!()
-                    } else {
-                        return Err(
+            let valid_subgraph_schema = diagnostics.recover_subgraph(&subgraph.name, || {
+                match subgraph.schema.validate() {
+                    Ok(schema) => Ok(schema),
+                    Err(error) => {
+                        // TODO: Implement maybeDumpSubgraphSchema() for better error messaging
+                        Err(
                             SingleFederationError::InvalidFederationSupergraph {
                                 message: format!(
                                     "Unexpected error extracting {} from the supergraph: this is either a bug, or the supergraph has been corrupted.\n\nDetails:\n{}",
@@ -105,12 +139,16 @@ pub(super) fn extract_subgraphs_from_supergraph(
                                     error,
                                 ),
                             }.into()
-                        );
+                        )
                     }
                 }
-            }
+            })?;
+            let Some(valid_subgraph_schema) = valid_subgraph_schema else {
+                continue;
+            };
+            valid_subgraph_schema
         } else {
-            ValidFederationSchema(Arc::new(Valid::assume_valid(subgraph.schema)))
+            ValidFederationSchema::new_assume_valid(subgraph.schema)?
         };
         valid_subgraphs.add(ValidFederationSubgraph {
             name: subgraph.name,
@@ -122,12 +160,111 @@ pub(super) fn extract_subgraphs_from_supergraph(
     Ok(valid_subgraphs)
 }
 
+/// The errors encountered while extracting subgraphs in diagnostic mode, along with the parts of
+/// the supergraph they made unusable.
+///
+/// When recovering, an error in a type, field (or enum value) or subgraph is collected here and
+/// the extraction skips what it affected, so that every problem of a broken supergraph can be
+/// reported at once. Otherwise, errors are returned as-is, failing the extraction.
+pub(crate) struct ExtractionDiagnostics {
+    recover: bool,
+    pub(crate) errors: MultipleFederationErrors,
+    /// The types whose extraction failed, possibly leaving them partially extracted.
+    pub(crate) unusable_types: IndexSet<NamedType>,
+    /// The fields (and enum values) whose extraction failed, by parent type name and name.
+    pub(crate) unusable_fields: IndexSet<(NamedType, Name)>,
+    /// The subgraphs whose extracted schema is invalid, and which are thus not extracted at all.
+    pub(crate) unusable_subgraphs: IndexSet<String>,
+}
+
+impl ExtractionDiagnostics {
+    pub(crate) fn new(recover: bool) -> Self {
+        Self {
+            recover,
+            errors: MultipleFederationErrors { errors: vec![] },
+            unusable_types: IndexSet::new(),
+            unusable_fields: IndexSet::new(),
+            unusable_subgraphs: IndexSet::new(),
+        }
+    }
+
+    /// Runs the given extraction of (part of) a type, returning `None` if it failed and we're
+    /// recovering (in which case the type is marked unusable).
+    fn recover_type<T>(
+        &mut self,
+        type_name: &NamedType,
+        extract: impl FnOnce() -> Result<T, FederationError>,
+    ) -> Result<Option<T>, FederationError> {
+        let result = extract();
+        self.recover(result, |diagnostics| {
+            diagnostics.unusable_types.insert(type_name.clone());
+        })
+    }
+
+    /// Runs the given extraction of a field (or enum value), skipping it if it failed and we're
+    /// recovering (in which case the field is marked unusable).
+    fn recover_field(
+        &mut self,
+        type_name: &NamedType,
+        field_name: &Name,
+        extract: impl FnOnce() -> Result<(), FederationError>,
+    ) -> Result<(), FederationError> {
+        let result = extract();
+        self.recover(result, |diagnostics| {
+            diagnostics
+                .unusable_fields
+                .insert((type_name.clone(), field_name.clone()));
+        })?;
+        Ok(())
+    }
+
+    /// Runs the given processing of a subgraph, returning `None` if it failed and we're recovering
+    /// (in which case the subgraph is marked unusable).
+    fn recover_subgraph<T>(
+        &mut self,
+        subgraph_name: &str,
+        process: impl FnOnce() -> Result<T, FederationError>,
+    ) -> Result<Option<T>, FederationError> {
+        let result = process();
+        self.recover(result, |diagnostics| {
+            diagnostics
+                .unusable_subgraphs
+                .insert(subgraph_name.to_owned());
+        })
+    }
+
+    fn recover<T>(
+        &mut self,
+        result: Result<T, FederationError>,
+        mark_unusable: impl FnOnce(&mut Self),
+    ) -> Result<Option<T>, FederationError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if self.recover => {
+                self.errors.push(error);
+                mark_unusable(self);
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
 type ValidateSupergraphOk = (&'static LinkSpecDefinition, &'static JoinSpecDefinition);
 
 fn validate_supergraph(
     supergraph_schema: &FederationSchema,
 ) -> Result<ValidateSupergraphOk, FederationError> {
     let Some(metadata) = supergraph_schema.metadata() else {
+        // Fed 1 supergraphs are core schemas using the `@core` directive instead of `@link`.
+        if supergraph_schema
+            .schema()
+            .schema_definition
+            .directives
+            .has("core")
+        {
+            return Err(fed_1_supergraph_error());
+        }
         return Err(SingleFederationError::InvalidFederationSupergraph {
             message: "Invalid supergraph: must be a core schema".to_owned(),
         }
@@ -152,32 +289,25 @@ fn validate_supergraph(
     Ok((link_spec_definition, join_spec_definition))
 }
 
+fn fed_1_supergraph_error() -> FederationError {
+    SingleFederationError::UnsupportedFederationVersion {
+        message: "Supergraphs composed with federation version 1 are not supported. Please recompose your supergraph with federation version 2 or greater".to_owned(),
+    }
+    .into()
+}
+
 type CollectEmptySubgraphsOk = (
     FederationSubgraphs,
     IndexMap<Name, &'static FederationSpecDefinition>,
     IndexMap<Name, NodeStr>,
 );
 fn collect_empty_subgraphs(
-    supergraph_schema: &FederationSchema,
-    join_spec_definition: &JoinSpecDefinition,
+    join_metadata: &JoinMetadata,
 ) -> Result<CollectEmptySubgraphsOk, FederationError> {
     let mut subgraphs = FederationSubgraphs::new();
-    let graph_directive_definition =
-        join_spec_definition.graph_directive_definition(supergraph_schema)?;
-    let graph_enum = join_spec_definition.graph_enum_definition(supergraph_schema)?;
     let mut federation_spec_definitions = IndexMap::new();
     let mut graph_enum_value_name_to_subgraph_name = IndexMap::new();
-    for (enum_value_name, enum_value_definition) in graph_enum.values.iter() {
-        let graph_application = enum_value_definition
-            .directives
-            .get(&graph_directive_definition.name)
-            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
-                message: format!(
-                    "Value \"{}\" of join__Graph enum has no @join__graph directive",
-                    enum_value_name
-                ),
-            })?;
-        let graph_arguments = join_spec_definition.graph_directive_arguments(graph_application)?;
+    for (enum_value_name, graph_arguments) in join_metadata.graphs() {
         let subgraph = FederationSubgraph {
             name: graph_arguments.name.as_str().to_owned(),
             url: graph_arguments.url.as_str().to_owned(),
@@ -191,15 +321,17 @@ fn collect_empty_subgraphs(
             .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
                 message: "Subgraph unexpectedly does not use federation spec".to_owned(),
             })?;
-        let federation_spec_definition = FEDERATION_VERSIONS
-            .find(&federation_link.url.version)
-            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
-                message: "Subgraph unexpectedly does not use a supported federation spec version"
-                    .to_owned(),
-            })?;
+        let federation_spec_definition =
+            FederationSpecDefinition::for_version(&federation_link.url.version)
+                .map_err(|_| SingleFederationError::InvalidFederationSupergraph {
+                    message:
+                        "Subgraph unexpectedly does not use a supported federation spec version"
+                            .to_owned(),
+                })?
+                .definition;
         subgraphs.add(subgraph)?;
         graph_enum_value_name_to_subgraph_name
-            .insert(enum_value_name.clone(), graph_arguments.name);
+            .insert(enum_value_name.clone(), graph_arguments.name.clone());
         federation_spec_definitions.insert(enum_value_name.clone(), federation_spec_definition);
     }
     Ok((
@@ -211,11 +343,14 @@ fn collect_empty_subgraphs(
 
 /// TODO: Use the JS/programmatic approach instead of hard-coding definitions.
 pub(crate) fn new_empty_fed_2_subgraph_schema() -> Result<FederationSchema, FederationError> {
-    FederationSchema::new(Schema::parse(
-        r#"
+    FederationSchema::new(
+        Schema::builder()
+            .adopt_orphan_extensions()
+            .parse(
+                r#"
     extend schema
         @link(url: "https://specs.apollo.dev/link/v1.0")
-        @link(url: "https://specs.apollo.dev/federation/v2.5")
+        @link(url: "https://specs.apollo.dev/federation/v2.8")
 
     directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
 
@@ -249,7 +384,7 @@ pub(crate) fn new_empty_fed_2_subgraph_schema() -> Result<FederationSchema, Fede
 
     directive @federation__inaccessible on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION
 
-    directive @federation__override(from: String!) on FIELD_DEFINITION
+    directive @federation__override(from: String!, label: String) on FIELD_DEFINITION
 
     directive @federation__composeDirective(name: String) repeatable on SCHEMA
 
@@ -259,12 +394,24 @@ pub(crate) fn new_empty_fed_2_subgraph_schema() -> Result<FederationSchema, Fede
 
     directive @federation__requiresScopes(scopes: [[federation__Scope!]!]!) on FIELD_DEFINITION | OBJECT | INTERFACE | SCALAR | ENUM
 
+    directive @federation__policy(policies: [[federation__Policy!]!]!) on FIELD_DEFINITION | OBJECT | INTERFACE | SCALAR | ENUM
+
+    directive @federation__context(name: String!) repeatable on INTERFACE | OBJECT | UNION
+
+    directive @federation__fromContext(field: federation__ContextFieldValue) on ARGUMENT_DEFINITION
+
     scalar federation__FieldSet
 
     scalar federation__Scope
+
+    scalar federation__Policy
+
+    scalar federation__ContextFieldValue
     "#,
-        "subgraph.graphql",
-    )?)
+                "subgraph.graphql",
+            )
+            .build()?,
+    )
 }
 
 struct TypeInfo {
@@ -286,8 +433,9 @@ fn extract_subgraphs_from_fed_2_supergraph(
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
     federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
-    join_spec_definition: &'static JoinSpecDefinition,
+    join_metadata: &JoinMetadata,
     filtered_types: &Vec<TypeDefinitionPosition>,
+    diagnostics: &mut ExtractionDiagnostics,
 ) -> Result<(), FederationError> {
     let TypeInfos {
         object_types,
@@ -300,8 +448,9 @@ fn extract_subgraphs_from_fed_2_supergraph(
         subgraphs,
         graph_enum_value_name_to_subgraph_name,
         federation_spec_definitions,
-        join_spec_definition,
+        join_metadata,
         filtered_types,
+        diagnostics,
     )?;
 
     extract_object_type_content(
@@ -309,37 +458,42 @@ fn extract_subgraphs_from_fed_2_supergraph(
         subgraphs,
         graph_enum_value_name_to_subgraph_name,
         federation_spec_definitions,
-        join_spec_definition,
+        join_metadata,
         &object_types,
+        diagnostics,
     )?;
     extract_interface_type_content(
         supergraph_schema,
         subgraphs,
         graph_enum_value_name_to_subgraph_name,
         federation_spec_definitions,
-        join_spec_definition,
+        join_metadata,
         &interface_types,
+        diagnostics,
     )?;
     extract_union_type_content(
         supergraph_schema,
         subgraphs,
         graph_enum_value_name_to_subgraph_name,
-        join_spec_definition,
+        join_metadata,
         &union_types,
+        diagnostics,
     )?;
     extract_enum_type_content(
         supergraph_schema,
         subgraphs,
         graph_enum_value_name_to_subgraph_name,
-        join_spec_definition,
+        join_metadata,
         &enum_types,
+        diagnostics,
     )?;
     extract_input_object_type_content(
         supergraph_schema,
         subgraphs,
         graph_enum_value_name_to_subgraph_name,
-        join_spec_definition,
+        join_metadata,
         &input_object_types,
+        diagnostics,
     )?;
 
     // We add all the "executable" directive definitions from the supergraph to each subgraphs, as
@@ -394,20 +548,161 @@ fn extract_subgraphs_from_fed_2_supergraph(
             .insert(&mut subgraph.schema, definition.clone())?;
         }
     }
+    extract_composed_directives(supergraph_schema, subgraphs)?;
 
     Ok(())
 }
 
+/// Copies the directives composed into the supergraph through `@composeDirective` (i.e. those
+/// from non-Apollo linked features) back into the subgraphs, along with their applications. The
+/// supergraph doesn't record which subgraph applied a composed directive, so applications are
+/// copied to every subgraph defining the element they apply to.
+fn extract_composed_directives(
+    supergraph_schema: &FederationSchema,
+    subgraphs: &mut FederationSubgraphs,
+) -> Result<(), FederationError> {
+    let Some(metadata) = supergraph_schema.metadata() else {
+        return Ok(());
+    };
+    let composed_directive_definitions = supergraph_schema
+        .schema()
+        .directive_definitions
+        .values()
+        .filter(|directive_definition| {
+            metadata
+                .source_link_of_directive(&directive_definition.name)
+                .is_some_and(|linked| linked.link.url.identity.domain != APOLLO_SPEC_DOMAIN)
+        })
+        .collect::<Vec<_>>();
+    if composed_directive_definitions.is_empty() {
+        return Ok(());
+    }
+
+    for subgraph in subgraphs.subgraphs.values_mut() {
+        let mut directive_names = IndexSet::new();
+        for definition in &composed_directive_definitions {
+            let subgraph_schema = subgraph.schema.schema();
+            if subgraph_schema
+                .directive_definitions
+                .contains_key(&definition.name)
+            {
+                directive_names.insert(definition.name.clone());
+                continue;
+            }
+            // A definition whose arguments use types the subgraph doesn't have can't be added.
+            if !definition.arguments.iter().all(|argument| {
+                subgraph_schema
+                    .types
+                    .contains_key(argument.ty.inner_named_type())
+            }) {
+                continue;
+            }
+            let pos = DirectiveDefinitionPosition {
+                directive_name: definition.name.clone(),
+            };
+            pos.pre_insert(&mut subgraph.schema)?;
+            pos.insert(&mut subgraph.schema, (*definition).clone())?;
+            directive_names.insert(definition.name.clone());
+        }
+
+        for (type_name, type_) in supergraph_schema.schema().types.iter() {
+            let Some(subgraph_type) = subgraph.schema.schema().types.get(type_name) else {
+                continue;
+            };
+            match (type_, subgraph_type) {
+                (ExtendedType::Object(type_), ExtendedType::Object(subgraph_type)) => {
+                    let pos = ObjectTypeDefinitionPosition {
+                        type_name: type_name.clone(),
+                    };
+                    let subgraph_fields = subgraph_type.fields.keys().cloned().collect::<Vec<_>>();
+                    for directive in composed_applications(&type_.directives, &directive_names) {
+                        pos.insert_directive(&mut subgraph.schema, directive.clone())?;
+                    }
+                    for field_name in subgraph_fields {
+                        let Some(field) = type_.fields.get(&field_name) else {
+                            continue;
+                        };
+                        let field_pos = pos.field(field_name);
+                        for directive in composed_applications(&field.directives, &directive_names)
+                        {
+                            field_pos.insert_directive(&mut subgraph.schema, directive.clone())?;
+                        }
+                    }
+                }
+                (ExtendedType::Interface(type_), ExtendedType::Interface(subgraph_type)) => {
+                    let pos = InterfaceTypeDefinitionPosition {
+                        type_name: type_name.clone(),
+                    };
+                    let subgraph_fields = subgraph_type.fields.keys().cloned().collect::<Vec<_>>();
+                    for directive in composed_applications(&type_.directives, &directive_names) {
+                        pos.insert_directive(&mut subgraph.schema, directive.clone())?;
+                    }
+                    for field_name in subgraph_fields {
+                        let Some(field) = type_.fields.get(&field_name) else {
+                            continue;
+                        };
+                        let field_pos = pos.field(field_name);
+                        for directive in composed_applications(&field.directives, &directive_names)
+                        {
+                            field_pos.insert_directive(&mut subgraph.schema, directive.clone())?;
+                        }
+                    }
+                }
+                (ExtendedType::Union(type_), ExtendedType::Union(_)) => {
+                    let pos = UnionTypeDefinitionPosition {
+                        type_name: type_name.clone(),
+                    };
+                    for directive in composed_applications(&type_.directives, &directive_names) {
+                        pos.insert_directive(&mut subgraph.schema, directive.clone())?;
+                    }
+                }
+                (ExtendedType::Enum(type_), ExtendedType::Enum(_)) => {
+                    let pos = EnumTypeDefinitionPosition {
+                        type_name: type_name.clone(),
+                    };
+                    for directive in composed_applications(&type_.directives, &directive_names) {
+                        pos.insert_directive(&mut subgraph.schema, directive.clone())?;
+                    }
+                }
+                (ExtendedType::InputObject(type_), ExtendedType::InputObject(_)) => {
+                    let pos = InputObjectTypeDefinitionPosition {
+                        type_name: type_name.clone(),
+                    };
+                    for directive in composed_applications(&type_.directives, &directive_names) {
+                        pos.insert_directive(&mut subgraph.schema, directive.clone())?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn composed_applications<'a, T: AsRef<Directive>>(
+    directives: &'a [T],
+    directive_names: &'a IndexSet<Name>,
+) -> impl Iterator<Item = &'a T> {
+    directives
+        .iter()
+        .filter(move |directive| directive_names.contains(&directive.as_ref().name))
+}
+
 fn add_all_empty_subgraph_types(
     supergraph_schema: &FederationSchema,
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
     federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
-    join_spec_definition: &'static JoinSpecDefinition,
+    join_metadata: &JoinMetadata,
     filtered_types: &Vec<TypeDefinitionPosition>,
+    diagnostics: &mut ExtractionDiagnostics,
 ) -> Result<TypeInfos, FederationError> {
-    let type_directive_definition =
-        join_spec_definition.type_directive_definition(supergraph_schema)?;
+    let context_directive = match get_context_spec_definition_from_schema(supergraph_schema)? {
+        Some(context_spec_definition) => context_spec_definition
+            .directive_name_in_schema(supergraph_schema, &CONTEXT_DIRECTIVE_NAME_IN_SPEC)?
+            .map(|name| (context_spec_definition, name)),
+        None => None,
+    };
 
     let mut object_types: Vec<TypeInfo> = Vec::new();
     let mut interface_types: Vec<TypeInfo> = Vec::new();
@@ -416,19 +711,16 @@ fn add_all_empty_subgraph_types(
     let mut input_object_types: Vec<TypeInfo> = Vec::new();
 
     for type_definition_position in filtered_types {
-        let type_ = type_definition_position.get(supergraph_schema.schema())?;
-        let mut type_directive_applications = Vec::new();
-        for directive in type_.directives().get_all(&type_directive_definition.name) {
-            type_directive_applications
-                .push(join_spec_definition.type_directive_arguments(directive)?);
-        }
-        let types_mut = match &type_definition_position {
-            TypeDefinitionPosition::Scalar(pos) => {
+        let type_info = diagnostics.recover_type(type_definition_position.type_name(), || {
+            let type_ = type_definition_position.get(supergraph_schema.schema())?;
+            let type_directive_applications =
+                join_metadata.type_applications(type_definition_position.type_name());
+            if let TypeDefinitionPosition::Scalar(pos) = &type_definition_position {
                 // Scalar are a bit special in that they don't have any sub-component, so we don't
                 // track them beyond adding them to the proper subgraphs. It's also simple because
-                // there is no possible key so there is exactly one @join__type application for each
-                // subgraph having the scalar (and most arguments cannot be present).
-                for type_directive_application in &type_directive_applications {
+                // there is no possible key so there is exactly one @join__type application for
+                // each subgraph having the scalar (and most arguments cannot be present).
+                for type_directive_application in type_directive_applications {
                     let subgraph = get_subgraph(
                         subgraphs,
                         graph_enum_value_name_to_subgraph_name,
@@ -444,22 +736,40 @@ fn add_all_empty_subgraph_types(
                         }),
                     )?;
                 }
-                None
+                return Ok(None);
             }
-            TypeDefinitionPosition::Object(_) => Some(&mut object_types),
-            TypeDefinitionPosition::Interface(_) => Some(&mut interface_types),
-            TypeDefinitionPosition::Union(_) => Some(&mut union_types),
-            TypeDefinitionPosition::Enum(_) => Some(&mut enum_types),
-            TypeDefinitionPosition::InputObject(_) => Some(&mut input_object_types),
-        };
-        if let Some(types_mut) = types_mut {
-            types_mut.push(add_empty_type(
+            let type_info = add_empty_type(
                 type_definition_position.clone(),
-                &type_directive_applications,
+                type_directive_applications,
                 subgraphs,
                 graph_enum_value_name_to_subgraph_name,
                 federation_spec_definitions,
-            )?);
+            )?;
+            if let Some((context_spec_definition, context_directive_name)) = &context_directive {
+                for directive in type_.directives().get_all(context_directive_name) {
+                    let context_directive_application =
+                        context_spec_definition.context_directive_arguments(directive)?;
+                    add_context_directive(
+                        type_definition_position,
+                        &context_directive_application.name,
+                        subgraphs,
+                        graph_enum_value_name_to_subgraph_name,
+                        federation_spec_definitions,
+                    )?;
+                }
+            }
+            Ok(Some(type_info))
+        })?;
+        let Some(type_info) = type_info.flatten() else {
+            continue;
+        };
+        match &type_definition_position {
+            TypeDefinitionPosition::Scalar(_) => {}
+            TypeDefinitionPosition::Object(_) => object_types.push(type_info),
+            TypeDefinitionPosition::Interface(_) => interface_types.push(type_info),
+            TypeDefinitionPosition::Union(_) => union_types.push(type_info),
+            TypeDefinitionPosition::Enum(_) => enum_types.push(type_info),
+            TypeDefinitionPosition::InputObject(_) => input_object_types.push(type_info),
         }
     }
 
@@ -474,7 +784,7 @@ fn add_all_empty_subgraph_types(
 
 fn add_empty_type(
     type_definition_position: TypeDefinitionPosition,
-    type_directive_applications: &Vec<TypeDirectiveArguments>,
+    type_directive_applications: &[JoinTypeDirectiveArguments],
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
     federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
@@ -681,24 +991,95 @@ fn add_empty_type(
     Ok(type_info)
 }
 
-fn extract_object_type_content(
-    supergraph_schema: &FederationSchema,
+/// Splits a supergraph context name (e.g. `Subgraph1__context`) into the name of the subgraph
+/// setting the context and the name of the context in that subgraph.
+fn split_context_name(name: &str) -> Result<(&str, &str), FederationError> {
+    name.rsplit_once("__")
+        .filter(|(subgraph_name, context)| !subgraph_name.is_empty() && !context.is_empty())
+        .ok_or_else(|| {
+            SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Invalid context name \"{}\": does not match the expected \"<subgraph>__<context>\" pattern",
+                    name,
+                ),
+            }
+            .into()
+        })
+}
+
+/// Adds a `@context` application for the given supergraph context name to the type in the
+/// subgraph setting that context.
+fn add_context_directive(
+    type_definition_position: &TypeDefinitionPosition,
+    supergraph_context_name: &str,
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
     federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
-    join_spec_definition: &JoinSpecDefinition,
-    info: &[TypeInfo],
 ) -> Result<(), FederationError> {
-    let field_directive_definition =
-        join_spec_definition.field_directive_definition(supergraph_schema)?;
-    // join__implements was added in join 0.2, and this method does not run for join 0.1, so it
-    // should be defined.
-    let implements_directive_definition = join_spec_definition
-        .implements_directive_definition(supergraph_schema)?
+    let (subgraph_name, context) = split_context_name(supergraph_context_name)?;
+    let Some(graph_enum_value) = graph_enum_value_name_to_subgraph_name
+        .iter()
+        .find(|(graph_enum_value, name)| {
+            name.as_str() == subgraph_name || graph_enum_value.as_str() == subgraph_name
+        })
+        .map(|(graph_enum_value, _)| graph_enum_value)
+    else {
+        return Err(SingleFederationError::InvalidFederationSupergraph {
+            message: format!(
+                "Invalid context name \"{}\" on \"{}\": \"{}\" is not a subgraph of the supergraph",
+                supergraph_context_name, type_definition_position, subgraph_name,
+            ),
+        }
+        .into());
+    };
+    let subgraph = get_subgraph(
+        subgraphs,
+        graph_enum_value_name_to_subgraph_name,
+        graph_enum_value,
+    )?;
+    let federation_spec_definition = federation_spec_definitions
+        .get(graph_enum_value)
         .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
-            message: "@join__implements should exist for a fed2 supergraph".to_owned(),
+            message: "Subgraph unexpectedly does not use federation spec".to_owned(),
         })?;
+    let context_directive = Component::new(
+        federation_spec_definition.context_directive(&subgraph.schema, NodeStr::new(context))?,
+    );
+    match subgraph
+        .schema
+        .get_type(type_definition_position.type_name().clone())?
+    {
+        TypeDefinitionPosition::Object(pos) => {
+            pos.insert_directive(&mut subgraph.schema, context_directive)?;
+        }
+        TypeDefinitionPosition::Interface(pos) => {
+            pos.insert_directive(&mut subgraph.schema, context_directive)?;
+        }
+        TypeDefinitionPosition::Union(pos) => {
+            pos.insert_directive(&mut subgraph.schema, context_directive)?;
+        }
+        _ => {
+            return Err(SingleFederationError::InvalidFederationSupergraph {
+                message: format!(
+                    "Invalid @context on \"{}\": only object, interface and union types can set a context",
+                    type_definition_position,
+                ),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
 
+fn extract_object_type_content(
+    supergraph_schema: &FederationSchema,
+    subgraphs: &mut FederationSubgraphs,
+    graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
+    federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
+    join_metadata: &JoinMetadata,
+    info: &[TypeInfo],
+    diagnostics: &mut ExtractionDiagnostics,
+) -> Result<(), FederationError> {
     for TypeInfo {
         name: type_name,
         subgraph_info,
@@ -709,116 +1090,115 @@ fn extract_object_type_content(
         };
         let type_ = pos.get(supergraph_schema.schema())?;
 
-        for directive in type_
-            .directives
-            .get_all(&implements_directive_definition.name)
-        {
-            let implements_directive_application =
-                join_spec_definition.implements_directive_arguments(directive)?;
-            if !subgraph_info.contains_key(&implements_directive_application.graph) {
-                return Err(
-                    SingleFederationError::InvalidFederationSupergraph {
-                        message: format!(
-                            "@join__implements cannot exist on \"{}\" for subgraph \"{}\" without type-level @join__type",
-                            type_name,
-                            implements_directive_application.graph,
-                        ),
-                    }.into()
-                );
+        diagnostics.recover_type(type_name, || {
+            for implements_directive_application in join_metadata.implements_applications(type_name)
+            {
+                if !subgraph_info.contains_key(&implements_directive_application.graph) {
+                    return Err(
+                        SingleFederationError::InvalidFederationSupergraph {
+                            message: format!(
+                                "@join__implements cannot exist on \"{}\" for subgraph \"{}\" without type-level @join__type",
+                                type_name,
+                                implements_directive_application.graph,
+                            ),
+                        }.into()
+                    );
+                }
+                let subgraph = get_subgraph(
+                    subgraphs,
+                    graph_enum_value_name_to_subgraph_name,
+                    &implements_directive_application.graph,
+                )?;
+                pos.insert_implements_interface(
+                    &mut subgraph.schema,
+                    ComponentName::from(Name::new(&implements_directive_application.interface)?),
+                )?;
             }
-            let subgraph = get_subgraph(
-                subgraphs,
-                graph_enum_value_name_to_subgraph_name,
-                &implements_directive_application.graph,
-            )?;
-            pos.insert_implements_interface(
-                &mut subgraph.schema,
-                ComponentName::from(Name::new(implements_directive_application.interface)?),
-            )?;
-        }
+            Ok(())
+        })?;
 
         for (field_name, field) in type_.fields.iter() {
-            let field_pos = pos.field(field_name.clone());
-            let mut field_directive_applications = Vec::new();
-            for directive in field.directives.get_all(&field_directive_definition.name) {
-                field_directive_applications
-                    .push(join_spec_definition.field_directive_arguments(directive)?);
-            }
-            if field_directive_applications.is_empty() {
-                // In a fed2 subgraph, no @join__field means that the field is in all the subgraphs
-                // in which the type is.
-                let is_shareable = subgraph_info.len() > 1;
-                for graph_enum_value in subgraph_info.keys() {
-                    let subgraph = get_subgraph(
-                        subgraphs,
-                        graph_enum_value_name_to_subgraph_name,
-                        graph_enum_value,
-                    )?;
-                    let federation_spec_definition = federation_spec_definitions
-                        .get(graph_enum_value)
-                        .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
-                            message: "Subgraph unexpectedly does not use federation spec"
-                                .to_owned(),
-                        })?;
-                    add_subgraph_field(
-                        field_pos.clone().into(),
-                        field,
-                        subgraph,
-                        federation_spec_definition,
-                        is_shareable,
-                        None,
-                    )?;
-                }
-            } else {
-                let is_shareable = field_directive_applications
-                    .iter()
-                    .filter(|field_directive_application| {
-                        !field_directive_application.external.unwrap_or(false)
-                            && !field_directive_application.user_overridden.unwrap_or(false)
-                    })
-                    .count()
-                    > 1;
-
-                for field_directive_application in &field_directive_applications {
-                    let Some(graph_enum_value) = &field_directive_application.graph else {
-                        // We use a @join__field with no graph to indicates when a field in the
-                        // supergraph does not come directly from any subgraph and there is thus
-                        // nothing to do to "extract" it.
-                        continue;
-                    };
-                    let subgraph = get_subgraph(
-                        subgraphs,
-                        graph_enum_value_name_to_subgraph_name,
-                        graph_enum_value,
-                    )?;
-                    let federation_spec_definition = federation_spec_definitions
-                        .get(graph_enum_value)
-                        .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
-                            message: "Subgraph unexpectedly does not use federation spec"
-                                .to_owned(),
-                        })?;
-                    if !subgraph_info.contains_key(graph_enum_value) {
-                        return Err(
-                            SingleFederationError::InvalidFederationSupergraph {
-                                message: format!(
-                                    "@join__field cannot exist on {}.{} for subgraph {} without type-level @join__type",
-                                    type_name,
-                                    field_name,
-                                    graph_enum_value,
-                                ),
-                            }.into()
-                        );
+            diagnostics.recover_field(type_name, field_name, || {
+                let field_pos = pos.field(field_name.clone());
+                let field_directive_applications =
+                    join_metadata.field_applications(type_name, field_name);
+                if field_directive_applications.is_empty() {
+                    // In a fed2 subgraph, no @join__field means that the field is in all the
+                    // subgraphs in which the type is.
+                    let is_shareable = subgraph_info.len() > 1;
+                    for graph_enum_value in subgraph_info.keys() {
+                        let subgraph = get_subgraph(
+                            subgraphs,
+                            graph_enum_value_name_to_subgraph_name,
+                            graph_enum_value,
+                        )?;
+                        let federation_spec_definition = federation_spec_definitions
+                            .get(graph_enum_value)
+                            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                                message: "Subgraph unexpectedly does not use federation spec"
+                                    .to_owned(),
+                            })?;
+                        add_subgraph_field(
+                            field_pos.clone().into(),
+                            field,
+                            subgraph,
+                            federation_spec_definition,
+                            is_shareable,
+                            None,
+                        )?;
+                    }
+                } else {
+                    let is_shareable = field_directive_applications
+                        .iter()
+                        .filter(|field_directive_application| {
+                            !field_directive_application.external.unwrap_or(false)
+                                && !field_directive_application.used_overridden.unwrap_or(false)
+                        })
+                        .count()
+                        > 1;
+
+                    for field_directive_application in field_directive_applications {
+                        let Some(graph_enum_value) = &field_directive_application.graph else {
+                            // We use a @join__field with no graph to indicates when a field in the
+                            // supergraph does not come directly from any subgraph and there is thus
+                            // nothing to do to "extract" it.
+                            continue;
+                        };
+                        let subgraph = get_subgraph(
+                            subgraphs,
+                            graph_enum_value_name_to_subgraph_name,
+                            graph_enum_value,
+                        )?;
+                        let federation_spec_definition = federation_spec_definitions
+                            .get(graph_enum_value)
+                            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                                message: "Subgraph unexpectedly does not use federation spec"
+                                    .to_owned(),
+                            })?;
+                        if !subgraph_info.contains_key(graph_enum_value) {
+                            return Err(
+                                SingleFederationError::InvalidFederationSupergraph {
+                                    message: format!(
+                                        "@join__field cannot exist on {}.{} for subgraph {} without type-level @join__type",
+                                        type_name,
+                                        field_name,
+                                        graph_enum_value,
+                                    ),
+                                }.into()
+                            );
+                        }
+                        add_subgraph_field(
+                            field_pos.clone().into(),
+                            field,
+                            subgraph,
+                            federation_spec_definition,
+                            is_shareable,
+                            Some(field_directive_application),
+                        )?;
                     }
-                    add_subgraph_field(
-                        field_pos.clone().into(),
-                        field,
-                        subgraph,
-                        federation_spec_definition,
-                        is_shareable,
-                        Some(field_directive_application),
-                    )?;
                 }
-            }
+                Ok(())
+            })?;
         }
     }
 
@@ -830,19 +1210,10 @@ fn extract_interface_type_content(
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
     federation_spec_definitions: &IndexMap<Name, &'static FederationSpecDefinition>,
-    join_spec_definition: &JoinSpecDefinition,
+    join_metadata: &JoinMetadata,
     info: &[TypeInfo],
+    diagnostics: &mut ExtractionDiagnostics,
 ) -> Result<(), FederationError> {
-    let field_directive_definition =
-        join_spec_definition.field_directive_definition(supergraph_schema)?;
-    // join_implements was added in join 0.2, and this method does not run for join 0.1, so it
-    // should be defined.
-    let implements_directive_definition = join_spec_definition
-        .implements_directive_definition(supergraph_schema)?
-        .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
-            message: "@join__implements should exist for a fed2 supergraph".to_owned(),
-        })?;
-
     for TypeInfo {
         name: type_name,
         subgraph_info,
@@ -898,118 +1269,117 @@ fn extract_interface_type_content(
             })
         }
 
-        for directive in type_
-            .directives
-            .get_all(&implements_directive_definition.name)
-        {
-            let implements_directive_application =
-                join_spec_definition.implements_directive_arguments(directive)?;
-            let subgraph = get_subgraph(
-                subgraphs,
-                graph_enum_value_name_to_subgraph_name,
-                &implements_directive_application.graph,
-            )?;
-            let pos = get_pos(
-                subgraph,
-                subgraph_info,
-                &implements_directive_application.graph,
-                type_name.clone(),
-            )?;
-            match pos {
-                ObjectOrInterfaceTypeDefinitionPosition::Object(pos) => {
-                    pos.insert_implements_interface(
-                        &mut subgraph.schema,
-                        ComponentName::from(Name::new(
-                            &implements_directive_application.interface,
-                        )?),
-                    )?;
-                }
-                ObjectOrInterfaceTypeDefinitionPosition::Interface(pos) => {
-                    pos.insert_implements_interface(
-                        &mut subgraph.schema,
-                        ComponentName::from(Name::new(
-                            &implements_directive_application.interface,
-                        )?),
-                    )?;
+        diagnostics.recover_type(type_name, || {
+            for implements_directive_application in join_metadata.implements_applications(type_name)
+            {
+                let subgraph = get_subgraph(
+                    subgraphs,
+                    graph_enum_value_name_to_subgraph_name,
+                    &implements_directive_application.graph,
+                )?;
+                let pos = get_pos(
+                    subgraph,
+                    subgraph_info,
+                    &implements_directive_application.graph,
+                    type_name.clone(),
+                )?;
+                match pos {
+                    ObjectOrInterfaceTypeDefinitionPosition::Object(pos) => {
+                        pos.insert_implements_interface(
+                            &mut subgraph.schema,
+                            ComponentName::from(Name::new(
+                                &implements_directive_application.interface,
+                            )?),
+                        )?;
+                    }
+                    ObjectOrInterfaceTypeDefinitionPosition::Interface(pos) => {
+                        pos.insert_implements_interface(
+                            &mut subgraph.schema,
+                            ComponentName::from(Name::new(
+                                &implements_directive_application.interface,
+                            )?),
+                        )?;
+                    }
                 }
             }
-        }
+            Ok(())
+        })?;
 
         for (field_name, field) in type_.fields.iter() {
-            let mut field_directive_applications = Vec::new();
-            for directive in field.directives.get_all(&field_directive_definition.name) {
-                field_directive_applications
-                    .push(join_spec_definition.field_directive_arguments(directive)?);
-            }
-            if field_directive_applications.is_empty() {
-                // In a fed2 subgraph, no @join__field means that the field is in all the subgraphs
-                // in which the type is.
-                for graph_enum_value in subgraph_info.keys() {
-                    let subgraph = get_subgraph(
-                        subgraphs,
-                        graph_enum_value_name_to_subgraph_name,
-                        graph_enum_value,
-                    )?;
-                    let pos =
-                        get_pos(subgraph, subgraph_info, graph_enum_value, type_name.clone())?;
-                    let federation_spec_definition = federation_spec_definitions
-                        .get(graph_enum_value)
-                        .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
-                            message: "Subgraph unexpectedly does not use federation spec"
-                                .to_owned(),
-                        })?;
-                    add_subgraph_field(
-                        pos.field(field_name.clone()),
-                        field,
-                        subgraph,
-                        federation_spec_definition,
-                        false,
-                        None,
-                    )?;
-                }
-            } else {
-                for field_directive_application in &field_directive_applications {
-                    let Some(graph_enum_value) = &field_directive_application.graph else {
-                        // We use a @join__field with no graph to indicates when a field in the
-                        // supergraph does not come directly from any subgraph and there is thus
-                        // nothing to do to "extract" it.
-                        continue;
-                    };
-                    let subgraph = get_subgraph(
-                        subgraphs,
-                        graph_enum_value_name_to_subgraph_name,
-                        graph_enum_value,
-                    )?;
-                    let pos =
-                        get_pos(subgraph, subgraph_info, graph_enum_value, type_name.clone())?;
-                    let federation_spec_definition = federation_spec_definitions
-                        .get(graph_enum_value)
-                        .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
-                            message: "Subgraph unexpectedly does not use federation spec"
-                                .to_owned(),
-                        })?;
-                    if !subgraph_info.contains_key(graph_enum_value) {
-                        return Err(
-                            SingleFederationError::InvalidFederationSupergraph {
-                                message: format!(
-                                    "@join__field cannot exist on {}.{} for subgraph {} without type-level @join__type",
-                                    type_name,
-                                    field_name,
-                                    graph_enum_value,
-                                ),
-                            }.into()
-                        );
+            diagnostics.recover_field(type_name, field_name, || {
+                let field_directive_applications =
+                    join_metadata.field_applications(type_name, field_name);
+                if field_directive_applications.is_empty() {
+                    // In a fed2 subgraph, no @join__field means that the field is in all the
+                    // subgraphs in which the type is.
+                    for graph_enum_value in subgraph_info.keys() {
+                        let subgraph = get_subgraph(
+                            subgraphs,
+                            graph_enum_value_name_to_subgraph_name,
+                            graph_enum_value,
+                        )?;
+                        let pos =
+                            get_pos(subgraph, subgraph_info, graph_enum_value, type_name.clone())?;
+                        let federation_spec_definition = federation_spec_definitions
+                            .get(graph_enum_value)
+                            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                                message: "Subgraph unexpectedly does not use federation spec"
+                                    .to_owned(),
+                            })?;
+                        add_subgraph_field(
+                            pos.field(field_name.clone()),
+                            field,
+                            subgraph,
+                            federation_spec_definition,
+                            false,
+                            None,
+                        )?;
+                    }
+                } else {
+                    for field_directive_application in field_directive_applications {
+                        let Some(graph_enum_value) = &field_directive_application.graph else {
+                            // We use a @join__field with no graph to indicates when a field in the
+                            // supergraph does not come directly from any subgraph and there is thus
+                            // nothing to do to "extract" it.
+                            continue;
+                        };
+                        let subgraph = get_subgraph(
+                            subgraphs,
+                            graph_enum_value_name_to_subgraph_name,
+                            graph_enum_value,
+                        )?;
+                        let pos =
+                            get_pos(subgraph, subgraph_info, graph_enum_value, type_name.clone())?;
+                        let federation_spec_definition = federation_spec_definitions
+                            .get(graph_enum_value)
+                            .ok_or_else(|| SingleFederationError::InvalidFederationSupergraph {
+                                message: "Subgraph unexpectedly does not use federation spec"
+                                    .to_owned(),
+                            })?;
+                        if !subgraph_info.contains_key(graph_enum_value) {
+                            return Err(
+                                SingleFederationError::InvalidFederationSupergraph {
+                                    message: format!(
+                                        "@join__field cannot exist on {}.{} for subgraph {} without type-level @join__type",
+                                        type_name,
+                                        field_name,
+                                        graph_enum_value,
+                                    ),
+                                }.into()
+                            );
+                        }
+                        add_subgraph_field(
+                            pos.field(field_name.clone()),
+                            field,
+                            subgraph,
+                            federation_spec_definition,
+                            false,
+                            Some(field_directive_application),
+                        )?;
                     }
-                    add_subgraph_field(
-                        pos.field(field_name.clone()),
-                        field,
-                        subgraph,
-                        federation_spec_definition,
-                        false,
-                        Some(field_directive_application),
-                    )?;
                 }
-            }
+                Ok(())
+            })?;
         }
     }
 
@@ -1020,13 +1390,10 @@ fn extract_union_type_content(
     supergraph_schema: &FederationSchema,
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
-    join_spec_definition: &JoinSpecDefinition,
+    join_metadata: &JoinMetadata,
     info: &[TypeInfo],
+    diagnostics: &mut ExtractionDiagnostics,
 ) -> Result<(), FederationError> {
-    // This was added in join 0.3, so it can genuinely be None.
-    let union_member_directive_definition =
-        join_spec_definition.union_member_directive_definition(supergraph_schema)?;
-
     // Note that union members works a bit differently from fields or enum values, and this because
     // we cannot have directive applications on type members. So the `join_unionMember` directive
     // applications are on the type itself, and they mention the member that they target.
@@ -1040,69 +1407,65 @@ fn extract_union_type_content(
         };
         let type_ = pos.get(supergraph_schema.schema())?;
 
-        let mut union_member_directive_applications = Vec::new();
-        if let Some(union_member_directive_definition) = union_member_directive_definition {
-            for directive in type_
-                .directives
-                .get_all(&union_member_directive_definition.name)
-            {
-                union_member_directive_applications
-                    .push(join_spec_definition.union_member_directive_arguments(directive)?);
-            }
-        }
-        if union_member_directive_applications.is_empty() {
-            // No @join__unionMember; every member should be added to every subgraph having the
-            // union (at least as long as the subgraph has the member itself).
-            for graph_enum_value in subgraph_info.keys() {
-                let subgraph = get_subgraph(
-                    subgraphs,
-                    graph_enum_value_name_to_subgraph_name,
-                    graph_enum_value,
-                )?;
-                // Note that object types in the supergraph are guaranteed to be object types in
-                // subgraphs.
-                let subgraph_members = type_
-                    .members
-                    .iter()
-                    .filter(|member| {
-                        subgraph
-                            .schema
-                            .schema()
-                            .types
-                            .contains_key((*member).deref())
-                    })
-                    .collect::<Vec<_>>();
-                for member in subgraph_members {
-                    pos.insert_member(&mut subgraph.schema, ComponentName::from(&member.name))?;
+        diagnostics.recover_type(type_name, || {
+            // @join__unionMember was added in join 0.3, so older supergraphs have no applications.
+            let union_member_directive_applications =
+                join_metadata.union_member_applications(type_name);
+            if union_member_directive_applications.is_empty() {
+                // No @join__unionMember; every member should be added to every subgraph having the
+                // union (at least as long as the subgraph has the member itself).
+                for graph_enum_value in subgraph_info.keys() {
+                    let subgraph = get_subgraph(
+                        subgraphs,
+                        graph_enum_value_name_to_subgraph_name,
+                        graph_enum_value,
+                    )?;
+                    // Note that object types in the supergraph are guaranteed to be object types in
+                    // subgraphs.
+                    let subgraph_members = type_
+                        .members
+                        .iter()
+                        .filter(|member| {
+                            subgraph
+                                .schema
+                                .schema()
+                                .types
+                                .contains_key((*member).deref())
+                        })
+                        .collect::<Vec<_>>();
+                    for member in subgraph_members {
+                        pos.insert_member(&mut subgraph.schema, ComponentName::from(&member.name))?;
+                    }
                 }
-            }
-        } else {
-            for union_member_directive_application in &union_member_directive_applications {
-                let subgraph = get_subgraph(
-                    subgraphs,
-                    graph_enum_value_name_to_subgraph_name,
-                    &union_member_directive_application.graph,
-                )?;
-                if !subgraph_info.contains_key(&union_member_directive_application.graph) {
-                    return Err(
-                        SingleFederationError::InvalidFederationSupergraph {
-                            message: format!(
-                                "@join__unionMember cannot exist on {} for subgraph {} without type-level @join__type",
-                                type_name,
-                                union_member_directive_application.graph,
-                            ),
-                        }.into()
-                    );
+            } else {
+                for union_member_directive_application in union_member_directive_applications {
+                    let subgraph = get_subgraph(
+                        subgraphs,
+                        graph_enum_value_name_to_subgraph_name,
+                        &union_member_directive_application.graph,
+                    )?;
+                    if !subgraph_info.contains_key(&union_member_directive_application.graph) {
+                        return Err(
+                            SingleFederationError::InvalidFederationSupergraph {
+                                message: format!(
+                                    "@join__unionMember cannot exist on {} for subgraph {} without type-level @join__type",
+                                    type_name,
+                                    union_member_directive_application.graph,
+                                ),
+                            }.into()
+                        );
+                    }
+                    // Note that object types in the supergraph are guaranteed to be object
+                    // types in subgraphs. We also know that the type must exist in this case (we
+                    // don't generate broken @join__unionMember).
+                    pos.insert_member(
+                        &mut subgraph.schema,
+                        ComponentName::from(Name::new(&union_member_directive_application.member)?),
+                    )?;
                 }
-                // Note that object types in the supergraph are guaranteed to be object types in
-                // subgraphs. We also know that the type must exist in this case (we don't generate
-                // broken @join__unionMember).
-                pos.insert_member(
-                    &mut subgraph.schema,
-                    ComponentName::from(Name::new(&union_member_directive_application.member)?),
-                )?;
             }
-        }
+            Ok(())
+        })?;
     }
 
     Ok(())
@@ -1112,13 +1475,10 @@ fn extract_enum_type_content(
     supergraph_schema: &FederationSchema,
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
-    join_spec_definition: &JoinSpecDefinition,
+    join_metadata: &JoinMetadata,
     info: &[TypeInfo],
+    diagnostics: &mut ExtractionDiagnostics,
 ) -> Result<(), FederationError> {
-    // This was added in join 0.3, so it can genuinely be None.
-    let enum_value_directive_definition =
-        join_spec_definition.enum_value_directive_definition(supergraph_schema)?;
-
     for TypeInfo {
         name: type_name,
         subgraph_info,
@@ -1129,63 +1489,60 @@ fn extract_enum_type_content(
         };
         let type_ = pos.get(supergraph_schema.schema())?;
 
-        for (value_name, value) in type_.values.iter() {
-            let value_pos = pos.value(value_name.clone());
-            let mut enum_value_directive_applications = Vec::new();
-            if let Some(enum_value_directive_definition) = enum_value_directive_definition {
-                for directive in value
-                    .directives
-                    .get_all(&enum_value_directive_definition.name)
-                {
-                    enum_value_directive_applications
-                        .push(join_spec_definition.enum_value_directive_arguments(directive)?);
-                }
-            }
-            if enum_value_directive_applications.is_empty() {
-                for graph_enum_value in subgraph_info.keys() {
-                    let subgraph = get_subgraph(
-                        subgraphs,
-                        graph_enum_value_name_to_subgraph_name,
-                        graph_enum_value,
-                    )?;
-                    value_pos.insert(
-                        &mut subgraph.schema,
-                        Component::new(EnumValueDefinition {
-                            description: None,
-                            value: value_name.clone(),
-                            directives: Default::default(),
-                        }),
-                    )?;
-                }
-            } else {
-                for enum_value_directive_application in &enum_value_directive_applications {
-                    let subgraph = get_subgraph(
-                        subgraphs,
-                        graph_enum_value_name_to_subgraph_name,
-                        &enum_value_directive_application.graph,
-                    )?;
-                    if !subgraph_info.contains_key(&enum_value_directive_application.graph) {
-                        return Err(
-                            SingleFederationError::InvalidFederationSupergraph {
-                                message: format!(
-                                    "@join__enumValue cannot exist on {}.{} for subgraph {} without type-level @join__type",
-                                    type_name,
-                                    value_name,
-                                    enum_value_directive_application.graph,
-                                ),
-                            }.into()
-                        );
+        for value_name in type_.values.keys() {
+            diagnostics.recover_field(type_name, value_name, || {
+                let value_pos = pos.value(value_name.clone());
+                // @join__enumValue was added in join 0.3, so older supergraphs have no
+                // applications.
+                let enum_value_directive_applications =
+                    join_metadata.enum_value_applications(type_name, value_name);
+                if enum_value_directive_applications.is_empty() {
+                    for graph_enum_value in subgraph_info.keys() {
+                        let subgraph = get_subgraph(
+                            subgraphs,
+                            graph_enum_value_name_to_subgraph_name,
+                            graph_enum_value,
+                        )?;
+                        value_pos.insert(
+                            &mut subgraph.schema,
+                            Component::new(EnumValueDefinition {
+                                description: None,
+                                value: value_name.clone(),
+                                directives: Default::default(),
+                            }),
+                        )?;
+                    }
+                } else {
+                    for enum_value_directive_application in enum_value_directive_applications {
+                        let subgraph = get_subgraph(
+                            subgraphs,
+                            graph_enum_value_name_to_subgraph_name,
+                            &enum_value_directive_application.graph,
+                        )?;
+                        if !subgraph_info.contains_key(&enum_value_directive_application.graph) {
+                            return Err(
+                                SingleFederationError::InvalidFederationSupergraph {
+                                    message: format!(
+                                        "@join__enumValue cannot exist on {}.{} for subgraph {} without type-level @join__type",
+                                        type_name,
+                                        value_name,
+                                        enum_value_directive_application.graph,
+                                    ),
+                                }.into()
+                            );
+                        }
+                        value_pos.insert(
+                            &mut subgraph.schema,
+                            Component::new(EnumValueDefinition {
+                                description: None,
+                                value: value_name.clone(),
+                                directives: Default::default(),
+                            }),
+                        )?;
                     }
-                    value_pos.insert(
-                        &mut subgraph.schema,
-                        Component::new(EnumValueDefinition {
-                            description: None,
-                            value: value_name.clone(),
-                            directives: Default::default(),
-                        }),
-                    )?;
                 }
-            }
+                Ok(())
+            })?;
         }
     }
 
@@ -1196,12 +1553,10 @@ fn extract_input_object_type_content(
     supergraph_schema: &FederationSchema,
     subgraphs: &mut FederationSubgraphs,
     graph_enum_value_name_to_subgraph_name: &IndexMap<Name, NodeStr>,
-    join_spec_definition: &JoinSpecDefinition,
+    join_metadata: &JoinMetadata,
     info: &[TypeInfo],
+    diagnostics: &mut ExtractionDiagnostics,
 ) -> Result<(), FederationError> {
-    let field_directive_definition =
-        join_spec_definition.field_directive_definition(supergraph_schema)?;
-
     for TypeInfo {
         name: type_name,
         subgraph_info,
@@ -1213,57 +1568,59 @@ fn extract_input_object_type_content(
         let type_ = pos.get(supergraph_schema.schema())?;
 
         for (input_field_name, input_field) in type_.fields.iter() {
-            let input_field_pos = pos.field(input_field_name.clone());
-            let mut field_directive_applications = Vec::new();
-            for directive in input_field
-                .directives
-                .get_all(&field_directive_definition.name)
-            {
-                field_directive_applications
-                    .push(join_spec_definition.field_directive_arguments(directive)?);
-            }
-            if field_directive_applications.is_empty() {
-                for graph_enum_value in subgraph_info.keys() {
-                    let subgraph = get_subgraph(
-                        subgraphs,
-                        graph_enum_value_name_to_subgraph_name,
-                        graph_enum_value,
-                    )?;
-                    add_subgraph_input_field(input_field_pos.clone(), input_field, subgraph, None)?;
-                }
-            } else {
-                for field_directive_application in &field_directive_applications {
-                    let Some(graph_enum_value) = &field_directive_application.graph else {
-                        // We use a @join__field with no graph to indicates when a field in the
-                        // supergraph does not come directly from any subgraph and there is thus
-                        // nothing to do to "extract" it.
-                        continue;
-                    };
-                    let subgraph = get_subgraph(
-                        subgraphs,
-                        graph_enum_value_name_to_subgraph_name,
-                        graph_enum_value,
-                    )?;
-                    if !subgraph_info.contains_key(graph_enum_value) {
-                        return Err(
-                            SingleFederationError::InvalidFederationSupergraph {
-                                message: format!(
-                                    "@join__field cannot exist on {}.{} for subgraph {} without type-level @join__type",
-                                    type_name,
-                                    input_field_name,
-                                    graph_enum_value,
-                                ),
-                            }.into()
-                        );
+            diagnostics.recover_field(type_name, input_field_name, || {
+                let input_field_pos = pos.field(input_field_name.clone());
+                let field_directive_applications =
+                    join_metadata.field_applications(type_name, input_field_name);
+                if field_directive_applications.is_empty() {
+                    for graph_enum_value in subgraph_info.keys() {
+                        let subgraph = get_subgraph(
+                            subgraphs,
+                            graph_enum_value_name_to_subgraph_name,
+                            graph_enum_value,
+                        )?;
+                        add_subgraph_input_field(
+                            input_field_pos.clone(),
+                            input_field,
+                            subgraph,
+                            None,
+                        )?;
+                    }
+                } else {
+                    for field_directive_application in field_directive_applications {
+                        let Some(graph_enum_value) = &field_directive_application.graph else {
+                            // We use a @join__field with no graph to indicates when a field in the
+                            // supergraph does not come directly from any subgraph and there is thus
+                            // nothing to do to "extract" it.
+                            continue;
+                        };
+                        let subgraph = get_subgraph(
+                            subgraphs,
+                            graph_enum_value_name_to_subgraph_name,
+                            graph_enum_value,
+                        )?;
+                        if !subgraph_info.contains_key(graph_enum_value) {
+                            return Err(
+                                SingleFederationError::InvalidFederationSupergraph {
+                                    message: format!(
+                                        "@join__field cannot exist on {}.{} for subgraph {} without type-level @join__type",
+                                        type_name,
+                                        input_field_name,
+                                        graph_enum_value,
+                                    ),
+                                }.into()
+                            );
+                        }
+                        add_subgraph_input_field(
+                            input_field_pos.clone(),
+                            input_field,
+                            subgraph,
+                            Some(field_directive_application),
+                        )?;
                     }
-                    add_subgraph_input_field(
-                        input_field_pos.clone(),
-                        input_field,
-                        subgraph,
-                        Some(field_directive_application),
-                    )?;
                 }
-            }
+                Ok(())
+            })?;
         }
     }
 
@@ -1276,22 +1633,29 @@ fn add_subgraph_field(
     subgraph: &mut FederationSubgraph,
     federation_spec_definition: &'static FederationSpecDefinition,
     is_shareable: bool,
-    field_directive_application: Option<&FieldDirectiveArguments>,
+    field_directive_application: Option<&JoinFieldDirectiveArguments>,
 ) -> Result<(), FederationError> {
     let field_directive_application =
-        field_directive_application.unwrap_or_else(|| &FieldDirectiveArguments {
+        field_directive_application.unwrap_or_else(|| &JoinFieldDirectiveArguments {
             graph: None,
             requires: None,
             provides: None,
             type_: None,
             external: None,
             override_: None,
-            user_overridden: None,
+            used_overridden: None,
+            override_label: None,
+            context_arguments: None,
         });
     let subgraph_field_type = match &field_directive_application.type_ {
         Some(t) => decode_type(t)?,
         None => field.ty.clone(),
     };
+    check_field_type_is_extracted(
+        subgraph,
+        &object_or_interface_field_definition_position,
+        &subgraph_field_type,
+    )?;
     let mut subgraph_field = FieldDefinition {
         description: None,
         name: object_or_interface_field_definition_position
@@ -1313,6 +1677,36 @@ fn add_subgraph_field(
                 directives: Default::default(),
             }))
     }
+    // Arguments whose value comes from a context aren't in the supergraph field, so they're added
+    // back here along with their @fromContext.
+    for context_argument in field_directive_application
+        .context_arguments
+        .iter()
+        .flatten()
+    {
+        let (_, context) = split_context_name(&context_argument.context)?;
+        let from_context_directive = Node::new(federation_spec_definition.from_context_directive(
+            &subgraph.schema,
+            NodeStr::new(&format!("${} {}", context, context_argument.selection)),
+        )?);
+        let argument_name = Name::new(&context_argument.name)?;
+        match subgraph_field
+            .arguments
+            .iter_mut()
+            .find(|argument| argument.name == argument_name)
+        {
+            Some(argument) => argument.make_mut().directives.push(from_context_directive),
+            None => subgraph_field
+                .arguments
+                .push(Node::new(InputValueDefinition {
+                    description: None,
+                    name: argument_name,
+                    ty: decode_type(&context_argument.type_)?.into(),
+                    default_value: None,
+                    directives: apollo_compiler::ast::DirectiveList(vec![from_context_directive]),
+                })),
+        }
+    }
     if let Some(requires) = &field_directive_application.requires {
         subgraph_field.directives.push(Node::new(
             federation_spec_definition.requires_directive(&subgraph.schema, requires.clone())?,
@@ -1329,8 +1723,11 @@ fn add_subgraph_field(
             federation_spec_definition.external_directive(&subgraph.schema, None)?,
         ));
     }
-    let user_overridden = field_directive_application.user_overridden.unwrap_or(false);
-    if user_overridden {
+    // With progressive override (i.e. when the override has a label), the overridden field may
+    // still be resolved by this subgraph depending on the label, so it isn't marked as external.
+    let used_overridden = field_directive_application.used_overridden.unwrap_or(false)
+        && field_directive_application.override_label.is_none();
+    if used_overridden {
         subgraph_field.directives.push(Node::new(
             federation_spec_definition
                 .external_directive(&subgraph.schema, Some(NodeStr::new("[overridden]")))?,
@@ -1341,7 +1738,7 @@ fn add_subgraph_field(
             federation_spec_definition.override_directive(&subgraph.schema, override_.clone())?,
         ));
     }
-    if is_shareable && !external && !user_overridden {
+    if is_shareable && !external && !used_overridden {
         subgraph_field.directives.push(Node::new(
             federation_spec_definition.shareable_directive(&subgraph.schema)?,
         ));
@@ -1363,22 +1760,29 @@ fn add_subgraph_input_field(
     input_object_field_definition_position: InputObjectFieldDefinitionPosition,
     input_field: &InputValueDefinition,
     subgraph: &mut FederationSubgraph,
-    field_directive_application: Option<&FieldDirectiveArguments>,
+    field_directive_application: Option<&JoinFieldDirectiveArguments>,
 ) -> Result<(), FederationError> {
     let field_directive_application =
-        field_directive_application.unwrap_or_else(|| &FieldDirectiveArguments {
+        field_directive_application.unwrap_or_else(|| &JoinFieldDirectiveArguments {
             graph: None,
             requires: None,
             provides: None,
             type_: None,
             external: None,
             override_: None,
-            user_overridden: None,
+            used_overridden: None,
+            override_label: None,
+            context_arguments: None,
         });
     let subgraph_input_field_type = match &field_directive_application.type_ {
         Some(t) => Node::new(decode_type(t)?),
         None => input_field.ty.clone(),
     };
+    check_field_type_is_extracted(
+        subgraph,
+        &input_object_field_definition_position,
+        &subgraph_input_field_type,
+    )?;
     let subgraph_input_field = InputValueDefinition {
         description: None,
         name: input_object_field_definition_position.field_name.clone(),
@@ -1393,11 +1797,32 @@ fn add_subgraph_input_field(
     Ok(())
 }
 
+/// Errors if the type of a field isn't in the subgraph. This only happens when the extraction of
+/// that type failed (and we're recovering from it), or for corrupted supergraphs.
+fn check_field_type_is_extracted(
+    subgraph: &FederationSubgraph,
+    field_definition_position: &impl Display,
+    field_type: &Type,
+) -> Result<(), FederationError> {
+    let type_name = field_type.inner_named_type();
+    if subgraph.schema.schema().types.contains_key(type_name) {
+        return Ok(());
+    }
+    Err(SingleFederationError::InvalidFederationSupergraph {
+        message: format!(
+            "Cannot extract field \"{}\" in subgraph \"{}\": its type \"{}\" is not in the subgraph",
+            field_definition_position, subgraph.name, type_name,
+        ),
+    }
+    .into())
+}
+
 /// Parse a string encoding a type reference.
 fn decode_type(type_: &str) -> Result<Type, FederationError> {
     Type::parse(type_, "").map_err(|_| {
         SingleFederationError::InvalidGraphQL {
             message: format!("Cannot parse type \"{}\"", type_),
+            locations: Vec::new(),
         }
         .into()
     })
@@ -1473,13 +1898,28 @@ impl IntoIterator for FederationSubgraphs {
     }
 }
 
-pub(crate) struct ValidFederationSubgraph {
-    pub(crate) name: String,
-    pub(crate) url: String,
-    pub(crate) schema: ValidFederationSchema,
+/// A subgraph extracted from a supergraph.
+pub struct ValidFederationSubgraph {
+    /// The name of the subgraph, as given by its `@join__graph` application. This is also the name
+    /// the query planner uses for the subgraph (e.g. in the fetches of query plans).
+    pub name: String,
+    /// The routing URL of the subgraph, as given by its `@join__graph` application (unless it's
+    /// been overridden with `ValidFederationSubgraphs::resolve_urls()`).
+    pub url: String,
+    /// The schema of the subgraph.
+    pub schema: ValidFederationSchema,
 }
 
-pub(crate) struct ValidFederationSubgraphs {
+impl ValidFederationSubgraph {
+    /// Prints the schema of the subgraph, including its federation `@link` and directive
+    /// definitions.
+    pub fn to_sdl(&self) -> String {
+        self.schema.schema().to_string()
+    }
+}
+
+/// The subgraphs extracted from a supergraph, by name.
+pub struct ValidFederationSubgraphs {
     subgraphs: BTreeMap<String, ValidFederationSubgraph>,
 }
 
@@ -1501,9 +1941,31 @@ impl ValidFederationSubgraphs {
         Ok(())
     }
 
-    pub(crate) fn get(&self, name: &str) -> Option<&ValidFederationSubgraph> {
+    pub fn get(&self, name: &str) -> Option<&ValidFederationSubgraph> {
         self.subgraphs.get(name)
     }
+
+    /// Iterates over the subgraphs, in order of their names.
+    pub fn iter(&self) -> impl Iterator<Item = &ValidFederationSubgraph> {
+        self.subgraphs.values()
+    }
+
+    /// Iterates over the names and routing URLs of the subgraphs, in order of their names.
+    pub fn urls(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.subgraphs
+            .values()
+            .map(|subgraph| (subgraph.name.as_str(), subgraph.url.as_str()))
+    }
+
+    /// Overrides the routing URLs of the subgraphs. The callback is given the name and current URL
+    /// of each subgraph, and returns the URL to use instead (or `None` to keep the current one).
+    pub fn resolve_urls(&mut self, mut resolve: impl FnMut(&str, &str) -> Option<String>) {
+        for subgraph in self.subgraphs.values_mut() {
+            if let Some(url) = resolve(&subgraph.name, &subgraph.url) {
+                subgraph.url = url;
+            }
+        }
+    }
 }
 
 impl IntoIterator for ValidFederationSubgraphs {
@@ -1,5 +1,4 @@
 use crate::error::FederationError;
-use crate::link::federation_spec_definition::get_federation_spec_definition_from_subgraph;
 use crate::link::graphql_definition::{
     BooleanOrVariable, DeferDirectiveArguments, OperationConditional, OperationConditionalKind,
 };
@@ -7,14 +6,17 @@ use crate::query_graph::condition_resolver::{
     ConditionResolution, ConditionResolver, UnsatisfiedConditionReason,
 };
 use crate::query_graph::path_tree::OpPathTree;
-use crate::query_graph::{QueryGraph, QueryGraphEdgeTransition, QueryGraphNodeType};
+use crate::query_graph::{
+    ContextCondition, EnabledOverrideConditions, QueryGraph, QueryGraphEdgeTransition,
+    QueryGraphNodeType,
+};
 use crate::query_plan::operation::normalized_field_selection::{
     NormalizedField, NormalizedFieldData,
 };
 use crate::query_plan::operation::normalized_inline_fragment_selection::{
     NormalizedInlineFragment, NormalizedInlineFragmentData,
 };
-use crate::query_plan::operation::{NormalizedSelectionSet, SelectionId};
+use crate::query_plan::operation::{IdGenerator, NormalizedSelectionSet};
 use crate::query_plan::QueryPlanCost;
 use crate::schema::position::{
     AbstractTypeDefinitionPosition, CompositeTypeDefinitionPosition,
@@ -26,7 +28,7 @@ use apollo_compiler::ast::Value;
 use apollo_compiler::executable::DirectiveList;
 use apollo_compiler::schema::Name;
 use apollo_compiler::NodeStr;
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use petgraph::graph::{EdgeIndex, NodeIndex};
 use petgraph::visit::EdgeRef;
 use std::cmp::Ordering;
@@ -179,10 +181,49 @@ pub(crate) enum OpGraphPathTrigger {
     Context(OpGraphPathContext),
 }
 
+impl Display for OpGraphPathTrigger {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpGraphPathTrigger::Field(field) => field.fmt(f),
+            OpGraphPathTrigger::InlineFragment(inline_fragment) => inline_fragment.fmt(f),
+            OpGraphPathTrigger::Context(context) => context.fmt(f),
+        }
+    }
+}
+
 /// A path of operation elements within a GraphQL operation.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, derive_more::From)]
 pub(crate) struct OpPath(Vec<Arc<OpPathElement>>);
 
+impl Deref for OpPath {
+    type Target = [Arc<OpPathElement>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for OpPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, element) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "::")?;
+            }
+            element.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl OpPath {
+    /// Returns a copy of this path with the given element appended.
+    pub(crate) fn with_pushed(&self, element: Arc<OpPathElement>) -> Self {
+        let mut elements = self.0.clone();
+        elements.push(element);
+        Self(elements)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From)]
 pub(crate) enum OpPathElement {
     Field(NormalizedField),
@@ -190,6 +231,13 @@ pub(crate) enum OpPathElement {
 }
 
 impl OpPathElement {
+    pub(crate) fn schema(&self) -> &ValidFederationSchema {
+        match self {
+            OpPathElement::Field(field) => &field.data().schema,
+            OpPathElement::InlineFragment(inline_fragment) => &inline_fragment.data().schema,
+        }
+    }
+
     pub(crate) fn directives(&self) -> &Arc<DirectiveList> {
         match self {
             OpPathElement::Field(field) => &field.data().directives,
@@ -278,17 +326,39 @@ impl OpGraphPathContext {
         }
         Ok(new_context)
     }
+
+    pub(crate) fn conditionals(&self) -> &[Arc<OperationConditional>] {
+        &self.conditionals
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.conditionals.is_empty()
+    }
+}
+
+impl Display for OpGraphPathContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // The conditionals are printed in the order they were applied.
+        write!(f, "[")?;
+        for (i, conditional) in self.conditionals.iter().rev().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            conditional.to_directive().fmt(f)?;
+        }
+        write!(f, "]")
+    }
 }
 
 impl PartialEq for OpGraphPathContext {
-    fn eq(&self, _other: &Self) -> bool {
-        todo!()
+    fn eq(&self, other: &Self) -> bool {
+        self.conditionals == other.conditionals
     }
 }
 
 impl Hash for OpGraphPathContext {
-    fn hash<H: Hasher>(&self, _state: &mut H) {
-        todo!()
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.conditionals.hash(state)
     }
 }
 
@@ -312,6 +382,12 @@ pub(crate) struct SimultaneousPathsWithLazyIndirectPaths {
     pub(crate) excluded_destinations: ExcludedDestinations,
     pub(crate) excluded_conditions: ExcludedConditions,
     pub(crate) lazily_computed_indirect_paths: Vec<Option<OpIndirectPaths>>,
+    /// The progressive @override labels enabled for this query planning, which determine which
+    /// field edges with override conditions may be taken.
+    pub(crate) override_conditions: Arc<EnabledOverrideConditions>,
+    /// The generator of the selection IDs of the operation being planned, used for the inline
+    /// fragments made up when type exploding.
+    pub(crate) id_generator: Arc<IdGenerator>,
 }
 
 /// A "set" of excluded destinations (i.e. subgraph names). Note that we use a `Vec` instead of set
@@ -328,6 +404,18 @@ impl Default for ExcludedDestinations {
     }
 }
 
+impl ExcludedDestinations {
+    /// Whether both exclude the same destinations (regardless of order).
+    // PORT_NOTE: Named `sameExcludedDestinations` in the JS codebase.
+    pub(crate) fn is_equivalent(&self, other: &ExcludedDestinations) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .all(|destination| other.0.contains(destination))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct ExcludedConditions(Arc<Vec<Arc<NormalizedSelectionSet>>>);
 
@@ -337,6 +425,26 @@ impl Default for ExcludedConditions {
     }
 }
 
+impl ExcludedConditions {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether the given conditions are excluded, i.e. are being resolved already.
+    // PORT_NOTE: Named `isConditionExcluded` in the JS codebase.
+    pub(crate) fn contains(&self, condition: &NormalizedSelectionSet) -> bool {
+        self.0.iter().any(|excluded| **excluded == *condition)
+    }
+
+    /// These excluded conditions along with the given ones.
+    // PORT_NOTE: Named `addConditionExclusion` in the JS codebase.
+    pub(crate) fn add_item(&self, condition: Arc<NormalizedSelectionSet>) -> ExcludedConditions {
+        let mut excluded = (*self.0).clone();
+        excluded.push(condition);
+        ExcludedConditions(Arc::new(excluded))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct IndirectPaths<TTrigger, TEdge, TDeadEnds>
 where
@@ -438,13 +546,13 @@ where
             runtime_types_before_tail_if_last_is_cast: None,
             defer_on_tail: None,
         };
-        path.runtime_types_of_tail = Arc::new(path.head_possible_runtime_types()?);
+        path.runtime_types_of_tail = path.head_possible_runtime_types()?;
         Ok(path)
     }
 
     fn head_possible_runtime_types(
         &self,
-    ) -> Result<IndexSet<ObjectTypeDefinitionPosition>, FederationError> {
+    ) -> Result<Arc<IndexSet<ObjectTypeDefinitionPosition>>, FederationError> {
         let head_weight = self.graph.node_weight(self.head)?;
         Ok(match &head_weight.type_ {
             QueryGraphNodeType::SchemaType(head_type_pos) => {
@@ -452,9 +560,9 @@ where
                     head_type_pos.clone().try_into()?;
                 self.graph
                     .schema_by_source(&head_weight.source)?
-                    .possible_runtime_types(head_type_pos)?
+                    .cached_possible_runtime_types(head_type_pos)?
             }
-            QueryGraphNodeType::FederatedRootType(_) => IndexSet::new(),
+            QueryGraphNodeType::FederatedRootType(_) => Default::default(),
         })
     }
 
@@ -500,10 +608,9 @@ where
                 },
                 own_path_ids: self.own_path_ids.clone(),
                 overriding_path_ids: self.overriding_path_ids.clone(),
-                runtime_types_of_tail: Arc::new(
-                    self.graph
-                        .advance_possible_runtime_types(&self.runtime_types_of_tail, None)?,
-                ),
+                runtime_types_of_tail: self
+                    .graph
+                    .advance_possible_runtime_types(&self.runtime_types_of_tail, None)?,
                 runtime_types_before_tail_if_last_is_cast: None,
                 defer_on_tail: defer,
             });
@@ -629,7 +736,7 @@ where
                                             .clone(),
                                         own_path_ids: self.own_path_ids.clone(),
                                         overriding_path_ids: self.overriding_path_ids.clone(),
-                                        runtime_types_of_tail: Arc::new(new_runtime_types_of_tail),
+                                        runtime_types_of_tail: new_runtime_types_of_tail,
                                         runtime_types_before_tail_if_last_is_cast: self
                                             .runtime_types_before_tail_if_last_is_cast
                                             .clone(),
@@ -697,10 +804,10 @@ where
                     },
                     own_path_ids: self.own_path_ids.clone(),
                     overriding_path_ids: self.overriding_path_ids.clone(),
-                    runtime_types_of_tail: Arc::new(self.graph.advance_possible_runtime_types(
+                    runtime_types_of_tail: self.graph.advance_possible_runtime_types(
                         &self.runtime_types_of_tail,
                         Some(new_edge),
-                    )?),
+                    )?,
                     // We know last edge is not a cast.
                     runtime_types_before_tail_if_last_is_cast: None,
                     defer_on_tail: defer,
@@ -732,10 +839,9 @@ where
             },
             own_path_ids: self.own_path_ids.clone(),
             overriding_path_ids: self.overriding_path_ids.clone(),
-            runtime_types_of_tail: Arc::new(
-                self.graph
-                    .advance_possible_runtime_types(&self.runtime_types_of_tail, Some(new_edge))?,
-            ),
+            runtime_types_of_tail: self
+                .graph
+                .advance_possible_runtime_types(&self.runtime_types_of_tail, Some(new_edge))?,
             runtime_types_before_tail_if_last_is_cast: if matches!(
                 edge_weight.transition,
                 QueryGraphEdgeTransition::Downcast { .. }
@@ -813,29 +919,46 @@ where
         ))
     }
 
-    fn tail_is_interface_object(&self) -> Result<bool, FederationError> {
-        let tail_weight = self.graph.node_weight(self.tail)?;
-
-        let QueryGraphNodeType::SchemaType(OutputTypeDefinitionPosition::Object(
-            tail_type_position,
-        )) = &tail_weight.type_
-        else {
+    /// Whether this path ends on a root type, and the given operation element is a field of that
+    /// root type whose whole subtree (i.e. the field and its given sub-selection) can be resolved
+    /// from this subgraph without any key, @requires or context jump.
+    ///
+    /// In that case, there is no point looking for indirect options for the field: those would
+    /// jump to the same root field in other subgraphs (through root type resolution edges), and
+    /// while schemas often duplicate `Query` fields across many subgraphs, such a jump can never
+    /// do better than staying in the current subgraph (it only adds a fetch), so exploring them
+    /// only multiplies the plan options to evaluate.
+    // PORT_NOTE: This shortcut isn't in the JS codebase.
+    fn resolves_root_field_locally(
+        &self,
+        operation_element: &OpPathElement,
+        sub_selection: Option<&NormalizedSelectionSet>,
+    ) -> Result<bool, FederationError> {
+        let OpPathElement::Field(operation_field) = operation_element else {
             return Ok(false);
         };
-
-        let subgraph_schema = self.graph.schema_by_source(&tail_weight.source)?;
-        let federation_spec_definition =
-            get_federation_spec_definition_from_subgraph(subgraph_schema)?;
-        let Some(interface_object_directive_definition) =
-            federation_spec_definition.interface_object_directive_definition(subgraph_schema)?
+        if self.graph.node_weight(self.tail)?.root_kind.is_none() {
+            return Ok(false);
+        }
+        let Some(tail) = self
+            .graph
+            .edge_for_field(self.tail, operation_field)
+            .map(|edge| self.graph.unconditional_edge_tail(edge))
+            .transpose()?
+            .flatten()
         else {
             return Ok(false);
         };
-        let tail_type = tail_type_position.get(subgraph_schema.schema())?;
-        Ok(tail_type
-            .directives
-            .iter()
-            .any(|directive| directive.name == interface_object_directive_definition.name))
+        match sub_selection {
+            Some(sub_selection) => self
+                .graph
+                .resolves_selection_set_locally(tail, sub_selection),
+            None => Ok(true),
+        }
+    }
+
+    fn tail_is_interface_object(&self) -> Result<bool, FederationError> {
+        self.graph.is_interface_object_node(self.tail)
     }
 
     fn last_edge_is_interface_object_fake_down_cast(&self) -> Result<bool, FederationError> {
@@ -861,8 +984,12 @@ where
         excluded_conditions: &ExcludedConditions,
     ) -> Result<ConditionResolution, FederationError> {
         let edge_weight = self.graph.edge_weight(edge)?;
-        if edge_weight.conditions.is_none() {
+        let Some(conditions) = &edge_weight.conditions else {
             return Ok(ConditionResolution::no_conditions());
+        };
+        // Conditions that are being resolved already can't be used to resolve themselves.
+        if excluded_conditions.contains(conditions) {
+            return Ok(ConditionResolution::unsatisfied_conditions());
         }
         let resolution = condition_resolver.resolve(
             edge,
@@ -921,8 +1048,44 @@ where
 }
 
 impl OpGraphPath {
-    fn next_edge_for_field(&self, field: &NormalizedField) -> Option<EdgeIndex> {
-        self.graph.edge_for_field(self.tail, field)
+    fn next_edge_for_field(
+        &self,
+        field: &NormalizedField,
+        override_conditions: &EnabledOverrideConditions,
+    ) -> Option<EdgeIndex> {
+        self.graph.edge_for_field(self.tail, field).filter(|edge| {
+            self.graph.edge_weight(*edge).is_ok_and(|edge_weight| {
+                edge_weight.satisfies_override_conditions(override_conditions)
+                    && edge_weight
+                        .required_contexts
+                        .iter()
+                        .all(|context| self.sets_context(context))
+            })
+        })
+    }
+
+    /// Whether some type along this path (including its tail) sets the given context, meaning the
+    /// value of the corresponding @fromContext argument can be fetched from that type.
+    fn sets_context(&self, context: &ContextCondition) -> bool {
+        let edge_tails = self.edges.iter().filter_map(|edge| {
+            edge.and_then(|edge| self.graph.graph.edge_endpoints(edge))
+                .map(|(_, tail)| tail)
+        });
+        std::iter::once(self.head)
+            .chain(edge_tails)
+            .chain(std::iter::once(self.tail))
+            .any(|node| {
+                let Ok(node_weight) = self.graph.node_weight(node) else {
+                    return false;
+                };
+                let QueryGraphNodeType::SchemaType(type_pos) = &node_weight.type_ else {
+                    return false;
+                };
+                context
+                    .types_with_context_set
+                    .iter()
+                    .any(|context_type| context_type.type_name() == type_pos.type_name())
+            })
     }
 
     fn next_edge_for_inline_fragment(
@@ -1112,14 +1275,13 @@ impl OpGraphPath {
 
     /// Remove all trailing downcast edges and `None` edges.
     fn truncate_trailing_downcasts(&self) -> Result<OpGraphPath, FederationError> {
-        let mut runtime_types = Arc::new(self.head_possible_runtime_types()?);
+        let mut runtime_types = self.head_possible_runtime_types()?;
         let mut last_edge_index = None;
         let mut last_runtime_types = runtime_types.clone();
         for (edge_index, edge) in self.edges.iter().enumerate() {
-            runtime_types = Arc::new(
-                self.graph
-                    .advance_possible_runtime_types(&runtime_types, *edge)?,
-            );
+            runtime_types = self
+                .graph
+                .advance_possible_runtime_types(&runtime_types, *edge)?;
             let Some(edge) = edge else {
                 continue;
             };
@@ -1324,6 +1486,8 @@ impl OpGraphPath {
         operation_element: &OpPathElement,
         context: &OpGraphPathContext,
         condition_resolver: &mut impl ConditionResolver,
+        override_conditions: &Arc<EnabledOverrideConditions>,
+        id_generator: &Arc<IdGenerator>,
     ) -> Result<(Option<Vec<SimultaneousPaths>>, Option<bool>), FederationError> {
         let tail_weight = self.graph.node_weight(self.tail)?;
         let QueryGraphNodeType::SchemaType(tail_type_pos) = &tail_weight.type_ else {
@@ -1337,7 +1501,9 @@ impl OpGraphPath {
                     OutputTypeDefinitionPosition::Object(tail_type_pos) => {
                         // Just take the edge corresponding to the field, if it exists and can be
                         // used.
-                        let Some(edge) = self.next_edge_for_field(operation_field) else {
+                        let Some(edge) =
+                            self.next_edge_for_field(operation_field, override_conditions)
+                        else {
                             return Ok((None, None));
                         };
 
@@ -1416,7 +1582,7 @@ impl OpGraphPath {
                         let interface_edge = if field_is_of_an_implementation {
                             None
                         } else {
-                            self.next_edge_for_field(operation_field)
+                            self.next_edge_for_field(operation_field, override_conditions)
                         };
                         let interface_path = if let Some(interface_edge) = &interface_edge {
                             let field_path = self.add_field_edge(
@@ -1565,7 +1731,7 @@ impl OpGraphPath {
                                         implementation_type_pos.clone().into(),
                                     ),
                                     directives: Default::default(),
-                                    selection_id: SelectionId::new(),
+                                    selection_id: id_generator.next_id(),
                                 });
                             let implementation_options =
                                 SimultaneousPathsWithLazyIndirectPaths::new(
@@ -1573,10 +1739,13 @@ impl OpGraphPath {
                                     context.clone(),
                                     Default::default(),
                                     Default::default(),
+                                    override_conditions.clone(),
+                                    id_generator.clone(),
                                 )
                                 .advance_with_operation_element(
                                     supergraph_schema.clone(),
                                     &implementation_inline_fragment.into(),
+                                    None,
                                     condition_resolver,
                                 )?;
                             // If we find no options for that implementation, we bail (as we need to
@@ -1598,6 +1767,7 @@ impl OpGraphPath {
                                     .advance_with_operation_element(
                                         supergraph_schema.clone(),
                                         operation_element,
+                                        None,
                                         condition_resolver,
                                     )?;
                                 let Some(field_options_for_implementation) =
@@ -1653,7 +1823,9 @@ impl OpGraphPath {
                         }
                     }
                     OutputTypeDefinitionPosition::Union(_) => {
-                        let Some(typename_edge) = self.next_edge_for_field(operation_field) else {
+                        let Some(typename_edge) =
+                            self.next_edge_for_field(operation_field, override_conditions)
+                        else {
                             return Err(FederationError::internal(
                                 "Should always have an edge for __typename edge on an union",
                             ));
@@ -1735,7 +1907,7 @@ impl OpGraphPath {
                         // types of the tail type and the ones of the typecast. We need to be able
                         // to go into all those types simultaneously (a.k.a. type explosion).
                         let from_types = self.runtime_types_of_tail.clone();
-                        let to_types = supergraph_schema.possible_runtime_types(
+                        let to_types = supergraph_schema.cached_possible_runtime_types(
                             supergraph_schema
                                 .get_type(type_condition_name.clone())?
                                 .try_into()?,
@@ -1754,7 +1926,7 @@ impl OpGraphPath {
                                         implementation_type_pos.clone().into(),
                                     ),
                                     directives: operation_inline_fragment.data().directives.clone(),
-                                    selection_id: SelectionId::new(),
+                                    selection_id: id_generator.next_id(),
                                 });
                             let implementation_options =
                                 SimultaneousPathsWithLazyIndirectPaths::new(
@@ -1762,10 +1934,13 @@ impl OpGraphPath {
                                     context.clone(),
                                     Default::default(),
                                     Default::default(),
+                                    override_conditions.clone(),
+                                    id_generator.clone(),
                                 )
                                 .advance_with_operation_element(
                                     supergraph_schema.clone(),
                                     &implementation_inline_fragment.into(),
+                                    None,
                                     condition_resolver,
                                 )?;
                             let Some(implementation_options) = implementation_options else {
@@ -1809,7 +1984,7 @@ impl OpGraphPath {
                             type_condition_pos.clone().try_into().ok();
                         if let Some(type_condition_pos) = abstract_type_condition_pos {
                             if supergraph_schema
-                                .possible_runtime_types(type_condition_pos.clone().into())?
+                                .cached_possible_runtime_types(type_condition_pos.clone().into())?
                                 .contains(tail_type_pos)
                             {
                                 // Type condition is applicable on the tail type, so the types are
@@ -1834,7 +2009,7 @@ impl OpGraphPath {
                                             .data()
                                             .directives
                                             .clone(),
-                                        selection_id: SelectionId::new(),
+                                        selection_id: id_generator.next_id(),
                                     });
                                 let defer_directive_arguments = operation_inline_fragment
                                     .data()
@@ -1996,9 +2171,95 @@ impl OpGraphPath {
     }
 }
 
+impl OpGraphPath {
+    /// A more verbose rendering of this path than its `Display` implementation, which also
+    /// includes the paths taken to satisfy the conditions of its edges.
+    pub(crate) fn to_debug_string(&self) -> String {
+        let mut debug_string = self.to_string();
+        for (edge, _, conditions) in self.iter() {
+            let (Some(edge), Some(conditions)) = (edge, conditions) else {
+                continue;
+            };
+            let edge_label = self
+                .graph
+                .edge_weight(edge)
+                .map(|edge_weight| edge_weight.to_string())
+                .unwrap_or_default();
+            debug_string.push_str(&format!(
+                "\n  conditions of [{}]: {}",
+                edge_label, conditions
+            ));
+        }
+        debug_string
+    }
+}
+
 impl Display for OpGraphPath {
-    fn fmt(&self, _f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Paths starting at a federated root node don't print it, and an empty path starting at one
+        // is printed as `_`.
+        let head_weight = self
+            .graph
+            .node_weight(self.head)
+            .map_err(|_| std::fmt::Error)?;
+        let head_is_root = matches!(head_weight.type_, QueryGraphNodeType::FederatedRootType(_));
+        if head_is_root && self.edges.is_empty() {
+            return write!(f, "_");
+        }
+        if !head_is_root {
+            head_weight.fmt(f)?;
+        }
+        for (i, (edge, trigger, _)) in self.iter().enumerate() {
+            let Some(edge) = edge else {
+                write!(f, " ({}) ", trigger)?;
+                continue;
+            };
+            let edge_weight = self.graph.edge_weight(edge).map_err(|_| std::fmt::Error)?;
+            let (_, tail) = self
+                .graph
+                .edge_endpoints(edge)
+                .map_err(|_| std::fmt::Error)?;
+            let tail_weight = self.graph.node_weight(tail).map_err(|_| std::fmt::Error)?;
+            if head_is_root && i == 0 {
+                tail_weight.fmt(f)?;
+            } else {
+                write!(f, " -[{}]-> {}", edge_weight, tail_weight)?;
+            }
+        }
+        if let Some(defer) = &self.defer_on_tail {
+            write!(
+                f,
+                " <defer='{}'>",
+                defer
+                    .label()
+                    .map(|label| label.as_str())
+                    .unwrap_or_default()
+            )?;
+        }
+        if !self.runtime_types_of_tail.is_empty() {
+            write!(f, " (types: [")?;
+            for (i, runtime_type) in self.runtime_types_of_tail.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", runtime_type.type_name)?;
+            }
+            write!(f, "])")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for SimultaneousPaths {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let [path] = self.0.as_slice() {
+            return path.fmt(f);
+        }
+        writeln!(f, "{{")?;
+        for path in &self.0 {
+            writeln!(f, "  {}", path)?;
+        }
+        write!(f, "}}")
     }
 }
 
@@ -2094,6 +2355,8 @@ impl SimultaneousPathsWithLazyIndirectPaths {
         context: OpGraphPathContext,
         excluded_destinations: ExcludedDestinations,
         excluded_conditions: ExcludedConditions,
+        override_conditions: Arc<EnabledOverrideConditions>,
+        id_generator: Arc<IdGenerator>,
     ) -> SimultaneousPathsWithLazyIndirectPaths {
         SimultaneousPathsWithLazyIndirectPaths {
             lazily_computed_indirect_paths: std::iter::repeat_with(|| None)
@@ -2103,14 +2366,57 @@ impl SimultaneousPathsWithLazyIndirectPaths {
             context,
             excluded_destinations,
             excluded_conditions,
+            override_conditions,
+            id_generator,
         }
     }
 
+    /// The options to start query planning from the given path. When the path ends on the
+    /// federated root, those are the paths entering each subgraph at the corresponding root type,
+    /// since no operation element can be advanced from the federated root itself.
+    // PORT_NOTE: Named `createInitialOptions` in the JS codebase.
+    pub(crate) fn create_initial_options(
+        initial_path: OpGraphPath,
+        context: OpGraphPathContext,
+        condition_resolver: &mut impl ConditionResolver,
+        excluded_destinations: ExcludedDestinations,
+        excluded_conditions: ExcludedConditions,
+        override_conditions: Arc<EnabledOverrideConditions>,
+        id_generator: Arc<IdGenerator>,
+    ) -> Result<Vec<SimultaneousPathsWithLazyIndirectPaths>, FederationError> {
+        let is_federated_root = matches!(
+            initial_path.graph.node_weight(initial_path.tail)?.type_,
+            QueryGraphNodeType::FederatedRootType(_)
+        );
+        let mut lazy_initial_path = SimultaneousPathsWithLazyIndirectPaths::new(
+            initial_path.into(),
+            context.clone(),
+            excluded_destinations,
+            excluded_conditions,
+            override_conditions,
+            id_generator,
+        );
+        if !is_federated_root {
+            return Ok(vec![lazy_initial_path]);
+        }
+        let initial_options =
+            lazy_initial_path.indirect_options(&context, 0, condition_resolver)?;
+        Ok(lazy_initial_path.create_lazy_options(
+            initial_options
+                .paths
+                .iter()
+                .map(|path| SimultaneousPaths(vec![path.clone()]))
+                .collect(),
+            context,
+        ))
+    }
+
     /// For a given "input" path (identified by an idx in `paths`), each of its indirect options.
     fn indirect_options(
         &mut self,
         updated_context: &OpGraphPathContext,
         path_index: usize,
+        condition_resolver: &mut impl ConditionResolver,
     ) -> Result<OpIndirectPaths, FederationError> {
         // Note that the provided context will usually be one we had during construction (the
         // `updated_context` will be `self.context` updated by whichever operation we're looking at,
@@ -2118,22 +2424,96 @@ impl SimultaneousPathsWithLazyIndirectPaths {
         // rare), which is why we save recomputation by caching the computed value in that case, but
         // in case it's different, we compute without caching.
         if *updated_context != self.context {
-            self.compute_indirect_paths(path_index)?;
+            return self.compute_indirect_paths(path_index, updated_context, condition_resolver);
         }
         if let Some(indirect_paths) = &self.lazily_computed_indirect_paths[path_index] {
             Ok(indirect_paths.clone())
         } else {
-            let new_indirect_paths = self.compute_indirect_paths(path_index)?;
+            let new_indirect_paths =
+                self.compute_indirect_paths(path_index, &self.context, condition_resolver)?;
             self.lazily_computed_indirect_paths[path_index] = Some(new_indirect_paths.clone());
             Ok(new_indirect_paths)
         }
     }
 
+    /// The paths obtained by following only non-collecting edges (i.e. subgraph-entering, key,
+    /// root type resolution and extension resolution edges) from the given path (identified by an
+    /// idx in `paths`), keeping only the cheapest one (then the shortest one) for each subgraph
+    /// they end in. Paths going back to the subgraph the given path ends in are ignored, as they
+    /// can't do better than that path.
+    // PORT_NOTE: Named `advancePathWithNonCollectingAndTypePreservingTransitions` in the JS
+    // codebase, where it also records dead ends for the error messages of composition validation.
+    // Query planning has no use for those.
     fn compute_indirect_paths(
         &self,
-        _path_index: usize,
+        path_index: usize,
+        context: &OpGraphPathContext,
+        condition_resolver: &mut impl ConditionResolver,
     ) -> Result<OpIndirectPaths, FederationError> {
-        todo!()
+        let path = &self.paths.0[path_index];
+        let graph = &path.graph;
+        let original_source = &graph.node_weight(path.tail)?.source;
+        let mut best_path_by_source: IndexMap<NodeStr, (Arc<OpGraphPath>, QueryPlanCost)> =
+            IndexMap::new();
+        let mut to_try = vec![(path.clone(), 0)];
+        while let Some((to_advance, to_advance_cost)) = to_try.pop() {
+            for edge in to_advance.next_edges()? {
+                let edge_weight = graph.edge_weight(edge)?;
+                if edge_weight.transition.collect_operation_elements() {
+                    continue;
+                }
+                let (_, target) = graph.edge_endpoints(edge)?;
+                let target_source = &graph.node_weight(target)?.source;
+                if target_source == original_source
+                    || self
+                        .excluded_destinations
+                        .0
+                        .iter()
+                        .any(|excluded| excluded.as_str() == target_source.as_str())
+                {
+                    continue;
+                }
+                let condition_resolution = to_advance.can_satisfy_conditions(
+                    edge,
+                    condition_resolver,
+                    context,
+                    &self.excluded_destinations,
+                    &self.excluded_conditions,
+                )?;
+                let ConditionResolution::Satisfied {
+                    cost: condition_cost,
+                    ..
+                } = &condition_resolution
+                else {
+                    continue;
+                };
+                let cost = to_advance_cost + condition_cost;
+                if let Some((best_path, best_cost)) = best_path_by_source.get(target_source) {
+                    if *best_cost < cost
+                        || (*best_cost == cost && best_path.edges.len() <= to_advance.edges.len())
+                    {
+                        continue;
+                    }
+                }
+                let new_path = Arc::new(to_advance.add(
+                    OpGraphPathTrigger::Context(context.clone()),
+                    Some(edge),
+                    condition_resolution,
+                    None,
+                )?);
+                best_path_by_source.insert(target_source.clone(), (new_path.clone(), cost));
+                to_try.push((new_path, cost));
+            }
+        }
+        Ok(OpIndirectPaths {
+            paths: Arc::new(
+                best_path_by_source
+                    .into_values()
+                    .map(|(path, _)| path)
+                    .collect(),
+            ),
+            dead_ends: (),
+        })
     }
 
     fn create_lazy_options(
@@ -2149,6 +2529,8 @@ impl SimultaneousPathsWithLazyIndirectPaths {
                     context.clone(),
                     self.excluded_destinations.clone(),
                     self.excluded_conditions.clone(),
+                    self.override_conditions.clone(),
+                    self.id_generator.clone(),
                 )
             })
             .collect()
@@ -2162,11 +2544,16 @@ impl SimultaneousPathsWithLazyIndirectPaths {
     /// The lists of options can be empty, which has the special meaning that the operation is
     /// guaranteed to have no results (it corresponds to unsatisfiable conditions), meaning that as
     /// far as query planning goes, we can just ignore the operation but otherwise continue.
+    ///
+    /// `sub_selection` is the selection set of the operation element (if any), which lets root
+    /// fields skip looking for indirect options when their whole subtree can be resolved directly
+    /// (see `resolves_root_field_locally()`).
     // PORT_NOTE: In the JS codebase, this was named `advance_simultaneous_paths_with_operation`.
     pub(crate) fn advance_with_operation_element(
         &mut self,
         supergraph_schema: ValidFederationSchema,
         operation_element: &OpPathElement,
+        sub_selection: Option<&NormalizedSelectionSet>,
         condition_resolver: &mut impl ConditionResolver,
     ) -> Result<Option<Vec<SimultaneousPathsWithLazyIndirectPaths>>, FederationError> {
         let updated_context = self.context.with_context_of(operation_element)?;
@@ -2186,6 +2573,8 @@ impl SimultaneousPathsWithLazyIndirectPaths {
                         operation_element,
                         &updated_context,
                         condition_resolver,
+                        &self.override_conditions,
+                        &self.id_generator,
                     )?;
                 // If we've got some options, there are a number of cases where there is no point
                 // looking for indirect paths:
@@ -2218,6 +2607,9 @@ impl SimultaneousPathsWithLazyIndirectPaths {
                             options_for_each_path.push(advance_options);
                         }
                         continue;
+                    } else if path.resolves_root_field_locally(operation_element, sub_selection)? {
+                        options_for_each_path.push(advance_options);
+                        continue;
                     } else {
                         options = Some(advance_options);
                     }
@@ -2230,7 +2622,7 @@ impl SimultaneousPathsWithLazyIndirectPaths {
             if let OpPathElement::Field(operation_field) = operation_element {
                 // Add whatever options can be obtained by taking some non-collecting edges first.
                 let paths_with_non_collecting_edges = self
-                    .indirect_options(&updated_context, path_index)?
+                    .indirect_options(&updated_context, path_index, condition_resolver)?
                     .filter_non_collecting_paths_for_field(operation_field)?;
                 if !paths_with_non_collecting_edges.paths.is_empty() {
                     for paths_with_non_collecting_edges in
@@ -2242,6 +2634,8 @@ impl SimultaneousPathsWithLazyIndirectPaths {
                                 operation_element,
                                 &updated_context,
                                 condition_resolver,
+                                &self.override_conditions,
+                                &self.id_generator,
                             )?;
                         // If we can't advance the operation element after that path, ignore it,
                         // it's just not an option.
@@ -2321,6 +2715,8 @@ impl SimultaneousPathsWithLazyIndirectPaths {
                     operation_element,
                     &updated_context,
                     condition_resolver,
+                    &self.override_conditions,
+                    &self.id_generator,
                 )?;
                 options = advance_options.unwrap_or_else(Vec::new);
             }
@@ -2339,6 +2735,18 @@ impl SimultaneousPathsWithLazyIndirectPaths {
     }
 }
 
+impl ClosedPath {
+    /// The paths of this option, each along with the selection set (if any) to plan at its end.
+    pub(crate) fn flatten(
+        &self,
+    ) -> impl Iterator<Item = (&OpGraphPath, Option<&Arc<NormalizedSelectionSet>>)> {
+        self.paths
+            .0
+            .iter()
+            .map(|path| (path.as_ref(), self.selection_set.as_ref()))
+    }
+}
+
 impl ClosedBranch {
     /// This method is called on a closed branch (i.e. on all the possible options found to get a
     /// particular leaf of the query being planned), and when there is more than one option, it
@@ -2408,3 +2816,86 @@ impl ClosedBranch {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_graph::build_query_graph::build_federated_query_graph;
+    use crate::schema::position::{ObjectFieldDefinitionPosition, SchemaRootDefinitionKind};
+    use crate::test_supergraph::supergraph_sdl;
+    use apollo_compiler::name;
+
+    fn supergraph() -> String {
+        supergraph_sdl(
+            &["S1"],
+            r#"
+    type Query
+      @join__type(graph: S1)
+    {
+      t: T
+    }
+
+    type T
+      @join__type(graph: S1)
+    {
+      id: ID!
+    }
+            "#,
+        )
+    }
+
+    #[test]
+    fn displays_paths_and_path_trees() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&supergraph())?;
+        let supergraph_schema = supergraph.schema.clone();
+        let api_schema = ValidFederationSchema::new(supergraph.to_api_schema(Default::default())?)?;
+        let graph = Arc::new(build_federated_query_graph(
+            supergraph_schema,
+            api_schema,
+            Some(true),
+            Some(true),
+        )?);
+        let root = graph.root_kinds_to_nodes()?[&SchemaRootDefinitionKind::Query];
+        let path = OpGraphPath::new(graph.clone(), root)?;
+        assert_eq!(path.to_string(), "_");
+
+        let entering_edge = graph.out_edges(root).next().unwrap();
+        let path = path.add(
+            OpGraphPathTrigger::Context(Default::default()),
+            Some(entering_edge.id()),
+            ConditionResolution::no_conditions(),
+            None,
+        )?;
+        let field_edge = graph
+            .out_edges(entering_edge.target())
+            .find(|edge| edge.weight().to_string() == "t")
+            .unwrap();
+        let field = NormalizedField::new(NormalizedFieldData {
+            schema: graph.schema_by_source("S1")?.clone(),
+            field_position: ObjectFieldDefinitionPosition {
+                type_name: name!("Query"),
+                field_name: name!("t"),
+            }
+            .into(),
+            alias: None,
+            arguments: Default::default(),
+            directives: Default::default(),
+            sibling_typename: None,
+        });
+        let path = path.add(
+            OpGraphPathTrigger::Field(field),
+            Some(field_edge.id()),
+            ConditionResolution::no_conditions(),
+            None,
+        )?;
+        assert_eq!(path.to_string(), "Query(S1)* -[t]-> T(S1) (types: [T])");
+        assert_eq!(path.to_debug_string(), path.to_string());
+
+        let tree = OpPathTree::from_op_paths(graph, root, &[(&path, None)])?;
+        assert_eq!(
+            tree.to_string(),
+            "[query](_)*:\n -> [7] [] = Query(S1)*:\n   -> [2] t = T(S1)"
+        );
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
 use crate::error::{FederationError, SingleFederationError};
+use crate::query_graph::build_query_graph::{
+    update_federated_query_graph, FEDERATED_GRAPH_ROOT_SOURCE,
+};
+use crate::query_graph::extract_subgraphs_from_supergraph::ValidFederationSubgraph;
 use crate::query_plan::operation::normalized_field_selection::NormalizedField;
 use crate::query_plan::operation::normalized_inline_fragment_selection::NormalizedInlineFragment;
-use crate::query_plan::operation::NormalizedSelectionSet;
+use crate::query_plan::operation::{NormalizedSelection, NormalizedSelectionSet, TYPENAME_FIELD};
 use crate::schema::position::{
     CompositeTypeDefinitionPosition, FieldDefinitionPosition, InterfaceFieldDefinitionPosition,
     ObjectTypeDefinitionPosition, OutputTypeDefinitionPosition, SchemaRootDefinitionKind,
@@ -20,9 +24,13 @@ use std::sync::Arc;
 pub mod build_query_graph;
 pub(crate) mod condition_resolver;
 pub(crate) mod extract_subgraphs_from_supergraph;
-mod field_set;
 pub(crate) mod graph_path;
 pub(crate) mod path_tree;
+mod planning_diff;
+mod snapshot;
+
+pub use planning_diff::{KeyEdge, MovedField, PlanningDiff, RequiresChange};
+pub use snapshot::QueryGraphDiff;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct QueryGraphNode {
@@ -93,6 +101,28 @@ pub(crate) struct QueryGraphEdge {
     ///
     /// Outside of keys, @requires edges also rely on conditions.
     pub(crate) conditions: Option<Arc<NormalizedSelectionSet>>,
+    /// Edges can require that an override condition (provided during query planning) be met in
+    /// order to be taken. This is used for progressive @override, where (at least) 2 subgraphs can
+    /// resolve the same field, but one of them has an @override with a label. If the override
+    /// condition matches the query plan parameters, this edge can be taken.
+    pub(crate) override_condition: Option<OverrideCondition>,
+    /// Edges for fields with @fromContext arguments require that the values of those arguments be
+    /// available from some parent type that sets the corresponding @context. This is the list of
+    /// such contextual argument conditions (empty for most edges).
+    pub(crate) required_contexts: Vec<ContextCondition>,
+}
+
+impl QueryGraphEdge {
+    pub(crate) fn satisfies_override_conditions(
+        &self,
+        conditions_to_check: &EnabledOverrideConditions,
+    ) -> bool {
+        if let Some(override_condition) = &self.override_condition {
+            override_condition.condition == conditions_to_check.contains(&override_condition.label)
+        } else {
+            true
+        }
+    }
 }
 
 impl Display for QueryGraphEdge {
@@ -104,14 +134,75 @@ impl Display for QueryGraphEdge {
         {
             return Ok(());
         }
-        if let Some(conditions) = &self.conditions {
-            write!(f, "{} ⊢ {}", conditions, self.transition)
-        } else {
-            self.transition.fmt(f)
+        match (&self.conditions, &self.override_condition) {
+            (Some(conditions), Some(override_condition)) => write!(
+                f,
+                "{}, {} ⊢ {}",
+                conditions, override_condition, self.transition
+            ),
+            (Some(conditions), None) => write!(f, "{} ⊢ {}", conditions, self.transition),
+            (None, Some(override_condition)) => {
+                write!(f, "{} ⊢ {}", override_condition, self.transition)
+            }
+            (None, None) => self.transition.fmt(f),
         }
     }
 }
 
+/// The condition under which a progressive @override edge may be taken, i.e. whether the override
+/// label must be enabled (`condition` is `true`) or disabled (`condition` is `false`) during query
+/// planning.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct OverrideCondition {
+    pub(crate) label: NodeStr,
+    pub(crate) condition: bool,
+}
+
+impl Display for OverrideCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.label, self.condition)
+    }
+}
+
+/// The condition for an argument with @fromContext, i.e. that a parent type of the field sets the
+/// context, and that the selection on that parent type can be fetched to provide the argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ContextCondition {
+    /// The name of the context (as declared by @context, without the subgraph prefix).
+    pub(crate) context: NodeStr,
+    pub(crate) subgraph_name: NodeStr,
+    /// The argument whose value is provided by the context.
+    pub(crate) named_parameter: Name,
+    /// The selection (within the context type) providing the argument's value.
+    pub(crate) selection: NodeStr,
+    /// The types setting the context.
+    pub(crate) types_with_context_set: IndexSet<CompositeTypeDefinitionPosition>,
+    pub(crate) argument_type: apollo_compiler::Node<apollo_compiler::ast::Type>,
+    /// The coordinate of the argument, for error messages (e.g. `T.f(arg:)`).
+    pub(crate) coordinate: String,
+}
+
+impl Display for ContextCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "${} {}", self.context, self.selection)
+    }
+}
+
+/// The set of progressive @override labels that are enabled for a query planning request. Labels
+/// not in this set are considered disabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnabledOverrideConditions(IndexSet<String>);
+
+impl EnabledOverrideConditions {
+    pub fn new(labels: impl IntoIterator<Item = String>) -> Self {
+        Self(labels.into_iter().collect())
+    }
+
+    pub fn contains(&self, label: &str) -> bool {
+        self.0.contains(label)
+    }
+}
+
 /// The type of query graph edge "transition".
 ///
 /// An edge transition encodes what the edge corresponds to, in the underlying GraphQL schema.
@@ -170,6 +261,16 @@ pub(crate) enum QueryGraphEdgeTransition {
         /// The type of the type condition, i.e. the type coming after "... on".
         to_type_name: Name,
     },
+    /// An edge contributed by a `QueryGraphExtension` (only found in federated query graphs),
+    /// resolving a type through some mechanism that isn't part of the join spec (e.g. an
+    /// experimental entity resolution mechanism). These are planned like key edges: they _must_
+    /// have `conditions`, which are sent as the representation of the resolved type.
+    ExtensionResolution {
+        /// The name of the extension that added the edge.
+        extension: NodeStr,
+        /// The label the extension gave the edge, to tell its edges apart.
+        label: NodeStr,
+    },
 }
 
 impl QueryGraphEdgeTransition {
@@ -181,6 +282,7 @@ impl QueryGraphEdgeTransition {
             QueryGraphEdgeTransition::RootTypeResolution { .. } => false,
             QueryGraphEdgeTransition::SubgraphEnteringTransition => false,
             QueryGraphEdgeTransition::InterfaceObjectFakeDownCast { .. } => true,
+            QueryGraphEdgeTransition::ExtensionResolution { .. } => false,
         }
     }
 }
@@ -211,6 +313,9 @@ impl Display for QueryGraphEdgeTransition {
             QueryGraphEdgeTransition::InterfaceObjectFakeDownCast { to_type_name, .. } => {
                 write!(f, "... on {}", to_type_name)
             }
+            QueryGraphEdgeTransition::ExtensionResolution { extension, label } => {
+                write!(f, "{}:{}()", extension, label)
+            }
         }
     }
 }
@@ -258,6 +363,11 @@ pub struct QueryGraph {
     /// lowered composition validation on a big composition (100+ subgraphs) from ~4 minutes to
     /// ~10 seconds.
     non_trivial_followup_edges: IndexMap<EdgeIndex, IndexSet<EdgeIndex>>,
+    /// A map (keyed by source) of the object types marked with `@interfaceObject` in that source.
+    /// This is computed once while building the query graph, so that query planning doesn't need to
+    /// re-inspect type directives whenever it needs to know whether a node is an
+    /// `@interfaceObject`.
+    interface_object_types_by_source: IndexMap<NodeStr, IndexSet<NamedType>>,
 }
 
 impl QueryGraph {
@@ -333,6 +443,34 @@ impl QueryGraph {
         })
     }
 
+    /// The subgraph schemas of a federated query graph by subgraph name, i.e. the sources other
+    /// than the placeholder source of the federated root nodes.
+    pub(crate) fn subgraphs(&self) -> impl Iterator<Item = (&NodeStr, &ValidFederationSchema)> {
+        self.sources
+            .iter()
+            .filter(|(source, _)| source.as_str() != FEDERATED_GRAPH_ROOT_SOURCE)
+    }
+
+    /// Builds the federated query graph of an updated supergraph where only the given subgraph
+    /// changed, rebuilding only the nodes/edges of that subgraph (see
+    /// `build_query_graph::update_federated_query_graph()`). This query graph must have been built
+    /// for query planning, and without extensions.
+    pub fn with_updated_subgraph(
+        &self,
+        supergraph_schema: ValidFederationSchema,
+        api_schema: ValidFederationSchema,
+        subgraph: ValidFederationSubgraph,
+    ) -> Result<QueryGraph, FederationError> {
+        update_federated_query_graph(
+            self,
+            supergraph_schema,
+            api_schema,
+            subgraph,
+            Some(true),
+            &[],
+        )
+    }
+
     pub(crate) fn types_to_nodes(
         &self,
     ) -> Result<&IndexMap<NamedType, IndexSet<NodeIndex>>, FederationError> {
@@ -394,6 +532,27 @@ impl QueryGraph {
         &self.non_trivial_followup_edges
     }
 
+    /// Whether the given type is marked with `@interfaceObject` in the given source.
+    pub(crate) fn is_interface_object_type(&self, source: &str, type_name: &str) -> bool {
+        self.interface_object_types_by_source
+            .get(source)
+            .is_some_and(|types| types.contains(type_name))
+    }
+
+    /// Whether the type of the given node is an `@interfaceObject` in the node's source.
+    pub(crate) fn is_interface_object_node(
+        &self,
+        node: NodeIndex,
+    ) -> Result<bool, FederationError> {
+        let node_weight = self.node_weight(node)?;
+        let QueryGraphNodeType::SchemaType(OutputTypeDefinitionPosition::Object(type_pos)) =
+            &node_weight.type_
+        else {
+            return Ok(false);
+        };
+        Ok(self.is_interface_object_type(&node_weight.source, &type_pos.type_name))
+    }
+
     /// All outward edges from the given node (including self-key and self-root-type-resolution
     /// edges). Primarily used by `@defer`, when needing to re-enter a subgraph for a deferred
     /// section.
@@ -498,14 +657,98 @@ impl QueryGraph {
         }
     }
 
+    /// Whether the given selection set can be resolved entirely from the given node, i.e. by only
+    /// taking unconditional field collection and downcast edges (so without ever needing a key,
+    /// a @requires, a context or an override label), which keeps it within the node's subgraph.
+    pub(crate) fn resolves_selection_set_locally(
+        &self,
+        node: NodeIndex,
+        selection_set: &NormalizedSelectionSet,
+    ) -> Result<bool, FederationError> {
+        for selection in selection_set.selections.values() {
+            let is_resolved = match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    if *field_selection.field.data().name() == TYPENAME_FIELD {
+                        continue;
+                    }
+                    let Some(tail) = self
+                        .edge_for_field(node, &field_selection.field)
+                        .map(|edge| self.unconditional_edge_tail(edge))
+                        .transpose()?
+                        .flatten()
+                    else {
+                        return Ok(false);
+                    };
+                    match &field_selection.selection_set {
+                        Some(selection_set) => {
+                            self.resolves_selection_set_locally(tail, selection_set)?
+                        }
+                        None => true,
+                    }
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    let inline_fragment = &inline_fragment_selection.inline_fragment;
+                    let tail = match self.edge_for_inline_fragment(node, inline_fragment) {
+                        Some(edge) => self.unconditional_edge_tail(edge)?,
+                        // Without a downcast edge, the fragment must not change the type.
+                        None => {
+                            let QueryGraphNodeType::SchemaType(type_pos) =
+                                &self.node_weight(node)?.type_
+                            else {
+                                return Ok(false);
+                            };
+                            inline_fragment
+                                .data()
+                                .type_condition_position
+                                .as_ref()
+                                .is_none_or(|type_condition_pos| {
+                                    type_condition_pos.type_name() == type_pos.type_name()
+                                })
+                                .then_some(node)
+                        }
+                    };
+                    match tail {
+                        Some(tail) => self.resolves_selection_set_locally(
+                            tail,
+                            &inline_fragment_selection.selection_set,
+                        )?,
+                        None => false,
+                    }
+                }
+                NormalizedSelection::FragmentSpread(_) => false,
+            };
+            if !is_resolved {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The tail of the given edge, unless taking the edge depends on conditions, contexts or
+    /// override labels.
+    pub(crate) fn unconditional_edge_tail(
+        &self,
+        edge: EdgeIndex,
+    ) -> Result<Option<NodeIndex>, FederationError> {
+        let edge_weight = self.edge_weight(edge)?;
+        if edge_weight.conditions.is_some()
+            || edge_weight.override_condition.is_some()
+            || !edge_weight.required_contexts.is_empty()
+        {
+            return Ok(None);
+        }
+        let (_, tail) = self.edge_endpoints(edge)?;
+        Ok(Some(tail))
+    }
+
     /// Given the possible runtime types at the head of the given edge, returns the possible runtime
     /// types after traversing the edge.
     // PORT_NOTE: Named `updateRuntimeTypes` in the JS codebase.
     pub(crate) fn advance_possible_runtime_types(
         &self,
-        possible_runtime_types: &IndexSet<ObjectTypeDefinitionPosition>,
+        possible_runtime_types: &Arc<IndexSet<ObjectTypeDefinitionPosition>>,
         edge: Option<EdgeIndex>,
-    ) -> Result<IndexSet<ObjectTypeDefinitionPosition>, FederationError> {
+    ) -> Result<Arc<IndexSet<ObjectTypeDefinitionPosition>>, FederationError> {
         let Some(edge) = edge else {
             return Ok(possible_runtime_types.clone());
         };
@@ -527,11 +770,11 @@ impl QueryGraph {
                 let Ok(_): Result<CompositeTypeDefinitionPosition, _> =
                     tail_type_pos.clone().try_into()
                 else {
-                    return Ok(IndexSet::new());
+                    return Ok(Default::default());
                 };
                 let schema = self.schema_by_source(source)?;
                 let mut new_possible_runtime_types = IndexSet::new();
-                for possible_runtime_type in possible_runtime_types {
+                for possible_runtime_type in possible_runtime_types.iter() {
                     let field_pos =
                         possible_runtime_type.field(field_definition_position.field_name().clone());
                     let Some(field) = field_pos.try_get(schema.schema()) else {
@@ -540,27 +783,33 @@ impl QueryGraph {
                     let field_type_pos: CompositeTypeDefinitionPosition = schema
                         .get_type(field.ty.inner_named_type().clone())?
                         .try_into()?;
-                    new_possible_runtime_types
-                        .extend(schema.possible_runtime_types(field_type_pos)?);
+                    new_possible_runtime_types.extend(
+                        schema
+                            .cached_possible_runtime_types(field_type_pos)?
+                            .iter()
+                            .cloned(),
+                    );
                 }
-                Ok(new_possible_runtime_types)
+                Ok(Arc::new(new_possible_runtime_types))
             }
             QueryGraphEdgeTransition::Downcast {
                 source,
                 to_type_position,
                 ..
-            } => Ok(self
-                .schema_by_source(source)?
-                .possible_runtime_types(to_type_position.clone())?
-                .intersection(possible_runtime_types)
-                .cloned()
-                .collect()),
-            QueryGraphEdgeTransition::KeyResolution => {
+            } => Ok(Arc::new(
+                self.schema_by_source(source)?
+                    .cached_possible_runtime_types(to_type_position.clone())?
+                    .intersection(possible_runtime_types)
+                    .cloned()
+                    .collect(),
+            )),
+            QueryGraphEdgeTransition::KeyResolution
+            | QueryGraphEdgeTransition::ExtensionResolution { .. } => {
                 let tail_type_pos: CompositeTypeDefinitionPosition =
                     tail_type_pos.clone().try_into()?;
                 Ok(self
                     .schema_by_source(&tail_weight.source)?
-                    .possible_runtime_types(tail_type_pos)?)
+                    .cached_possible_runtime_types(tail_type_pos)?)
             }
             QueryGraphEdgeTransition::RootTypeResolution { .. } => {
                 let OutputTypeDefinitionPosition::Object(tail_type_pos) = tail_type_pos.clone()
@@ -569,7 +818,7 @@ impl QueryGraph {
                         "Unexpectedly encountered non-object root operation type.",
                     ));
                 };
-                Ok(IndexSet::from([tail_type_pos]))
+                Ok(Arc::new(IndexSet::from([tail_type_pos])))
             }
             QueryGraphEdgeTransition::SubgraphEnteringTransition => {
                 let OutputTypeDefinitionPosition::Object(tail_type_pos) = tail_type_pos.clone()
@@ -578,7 +827,7 @@ impl QueryGraph {
                         "Unexpectedly encountered non-object root operation type.",
                     ));
                 };
-                Ok(IndexSet::from([tail_type_pos]))
+                Ok(Arc::new(IndexSet::from([tail_type_pos])))
             }
             QueryGraphEdgeTransition::InterfaceObjectFakeDownCast { .. } => {
                 Ok(possible_runtime_types.clone())
@@ -74,7 +74,7 @@ impl OpPathTree {
     pub(crate) fn from_op_paths(
         graph: Arc<QueryGraph>,
         node: NodeIndex,
-        paths: &[(&OpGraphPath, &Arc<NormalizedSelectionSet>)],
+        paths: &[(&OpGraphPath, Option<&Arc<NormalizedSelectionSet>>)],
     ) -> Result<Self, FederationError> {
         assert!(
             !paths.is_empty(),
@@ -119,7 +119,7 @@ where
         node: NodeIndex,
         graph_paths_and_selections: Vec<(
             impl Iterator<Item = GraphPathItem<'inputs, TTrigger, TEdge>>,
-            &'inputs Arc<NormalizedSelectionSet>,
+            Option<&'inputs Arc<NormalizedSelectionSet>>,
         )>,
     ) -> Result<Self, FederationError>
     where
@@ -137,7 +137,8 @@ where
 
         struct PathTreeChildInputs<'inputs, GraphPathIter> {
             conditions: Option<Arc<OpPathTree>>,
-            sub_paths_and_selections: Vec<(GraphPathIter, &'inputs Arc<NormalizedSelectionSet>)>,
+            sub_paths_and_selections:
+                Vec<(GraphPathIter, Option<&'inputs Arc<NormalizedSelectionSet>>)>,
         }
 
         let mut local_selection_sets = Vec::new();
@@ -145,7 +146,9 @@ where
         for (mut graph_path_iter, selection) in graph_paths_and_selections {
             let Some((generic_edge, trigger, conditions)) = graph_path_iter.next() else {
                 // End of an input `GraphPath`
-                local_selection_sets.push(selection.clone());
+                if let Some(selection) = selection {
+                    local_selection_sets.push(selection.clone());
+                }
                 continue;
             };
             let for_edge = match merged.entry(generic_edge) {
@@ -230,7 +233,7 @@ where
             })
     }
 
-    fn merge(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+    pub(crate) fn merge(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
         if Arc::ptr_eq(self, other) {
             return self.clone();
         }
@@ -295,13 +298,43 @@ where
     }
 }
 
+impl<TTrigger, TEdge> PathTree<TTrigger, TEdge>
+where
+    TTrigger: Eq + Hash + Display,
+    TEdge: Copy + Into<Option<EdgeIndex>>,
+{
+    fn fmt_internal(&self, f: &mut Formatter<'_>, indent: &str) -> std::fmt::Result {
+        let node_weight = self
+            .graph
+            .node_weight(self.node)
+            .map_err(|_| std::fmt::Error)?;
+        node_weight.fmt(f)?;
+        if self.childs.is_empty() {
+            return Ok(());
+        }
+        write!(f, ":")?;
+        let child_indent = format!("{}  ", indent);
+        for child in &self.childs {
+            let edge: Option<EdgeIndex> = child.edge.into();
+            write!(f, "\n{} -> [", indent)?;
+            match edge {
+                Some(edge) => write!(f, "{}", edge.index())?,
+                None => write!(f, "None")?,
+            }
+            write!(f, "] {} = ", child.trigger)?;
+            child.tree.fmt_internal(f, &child_indent)?;
+        }
+        Ok(())
+    }
+}
+
 impl<TTrigger, TEdge> Display for PathTree<TTrigger, TEdge>
 where
-    TTrigger: Eq + Hash,
-    TEdge: Copy + Eq + Hash + Into<Option<EdgeIndex>>,
+    TTrigger: Eq + Hash + Display,
+    TEdge: Copy + Into<Option<EdgeIndex>>,
 {
-    fn fmt(&self, _f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_internal(f, "")
     }
 }
 
@@ -0,0 +1,253 @@
+use crate::error::FederationError;
+use crate::query_graph::{QueryGraph, QueryGraphEdgeTransition, QueryGraphNodeType};
+use indexmap::{IndexMap, IndexSet};
+use petgraph::visit::EdgeRef;
+use std::fmt::{Display, Formatter};
+
+impl QueryGraph {
+    /// The differences between this federated query graph and the given one that can change the
+    /// query plans of operations, as opposed to `diff()` which reports every node and edge. This is
+    /// meant for deploy-time checks of a new supergraph, e.g. to only re-plan (and compare the plans
+    /// of) the operations that select the types and fields reported here.
+    pub fn planning_diff(&self, new: &QueryGraph) -> Result<PlanningDiff, FederationError> {
+        let old_key_edges = key_edges(self)?;
+        let new_key_edges = key_edges(new)?;
+        let old_field_subgraphs = field_subgraphs(self)?;
+        let new_field_subgraphs = field_subgraphs(new)?;
+        let old_requires = requires_conditions(self)?;
+        let new_requires = requires_conditions(new)?;
+
+        let mut diff = PlanningDiff {
+            added_key_edges: new_key_edges.difference(&old_key_edges).cloned().collect(),
+            removed_key_edges: old_key_edges.difference(&new_key_edges).cloned().collect(),
+            ..Default::default()
+        };
+        // Fields only in one of the query graphs can't change the plans of operations valid against
+        // both, so they're ignored.
+        for (field, old_subgraphs) in &old_field_subgraphs {
+            let Some(new_subgraphs) = new_field_subgraphs.get(field) else {
+                continue;
+            };
+            if old_subgraphs != new_subgraphs {
+                diff.moved_fields.push(MovedField {
+                    field: field.clone(),
+                    old_subgraphs: old_subgraphs.iter().cloned().collect(),
+                    new_subgraphs: new_subgraphs.iter().cloned().collect(),
+                });
+            }
+        }
+        for (key, old_conditions) in &old_requires {
+            let new_conditions = new_requires.get(key);
+            if new_conditions != Some(old_conditions) {
+                diff.changed_requires.push(RequiresChange {
+                    field: key.0.clone(),
+                    subgraph: key.1.clone(),
+                    old_conditions: Some(old_conditions.clone()),
+                    new_conditions: new_conditions.cloned(),
+                });
+            }
+        }
+        for (key, new_conditions) in &new_requires {
+            if !old_requires.contains_key(key) {
+                diff.changed_requires.push(RequiresChange {
+                    field: key.0.clone(),
+                    subgraph: key.1.clone(),
+                    old_conditions: None,
+                    new_conditions: Some(new_conditions.clone()),
+                });
+            }
+        }
+        diff.added_key_edges.sort();
+        diff.removed_key_edges.sort();
+        diff.moved_fields.sort_by(|a, b| a.field.cmp(&b.field));
+        diff.changed_requires
+            .sort_by(|a, b| (&a.field, &a.subgraph).cmp(&(&b.field, &b.subgraph)));
+        Ok(diff)
+    }
+}
+
+/// The planning-relevant differences between two federated query graphs (see
+/// `QueryGraph::planning_diff()`). Each list is sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanningDiff {
+    /// The cross-subgraph key edges only found in the new query graph.
+    pub added_key_edges: Vec<KeyEdge>,
+    /// The cross-subgraph key edges only found in the old query graph.
+    pub removed_key_edges: Vec<KeyEdge>,
+    /// The fields (found in both query graphs) resolved by different subgraphs.
+    pub moved_fields: Vec<MovedField>,
+    /// The `@requires` conditions added, removed or changed on a field of a subgraph.
+    pub changed_requires: Vec<RequiresChange>,
+}
+
+impl PlanningDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_key_edges.is_empty()
+            && self.removed_key_edges.is_empty()
+            && self.moved_fields.is_empty()
+            && self.changed_requires.is_empty()
+    }
+}
+
+impl Display for PlanningDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for key_edge in &self.removed_key_edges {
+            writeln!(f, "- key {}", key_edge)?;
+        }
+        for key_edge in &self.added_key_edges {
+            writeln!(f, "+ key {}", key_edge)?;
+        }
+        for moved_field in &self.moved_fields {
+            writeln!(
+                f,
+                "~ field {}: [{}] -> [{}]",
+                moved_field.field,
+                moved_field.old_subgraphs.join(", "),
+                moved_field.new_subgraphs.join(", "),
+            )?;
+        }
+        for change in &self.changed_requires {
+            writeln!(
+                f,
+                "~ requires {} in {}: {} -> {}",
+                change.field,
+                change.subgraph,
+                change.old_conditions.as_deref().unwrap_or("none"),
+                change.new_conditions.as_deref().unwrap_or("none"),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A key edge between two subgraphs, i.e. a way for query plans to jump from one subgraph to
+/// another for an entity.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyEdge {
+    /// The name of the entity type in the subgraph jumped to. This is the @interfaceObject type for
+    /// edges from the implementations of its interface.
+    pub type_name: String,
+    pub from_subgraph: String,
+    pub to_subgraph: String,
+    /// The key fields, as a selection set.
+    pub key: String,
+}
+
+impl Display for KeyEdge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {} {}",
+            self.type_name, self.from_subgraph, self.to_subgraph, self.key
+        )
+    }
+}
+
+/// A field resolved by different subgraphs in the old and new query graphs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovedField {
+    /// The field, as a `Type.field` coordinate.
+    pub field: String,
+    /// The subgraphs resolving the field in the old query graph, sorted.
+    pub old_subgraphs: Vec<String>,
+    /// The subgraphs resolving the field in the new query graph, sorted.
+    pub new_subgraphs: Vec<String>,
+}
+
+/// A change of the `@requires` conditions of a field in a subgraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequiresChange {
+    /// The field, as a `Type.field` coordinate.
+    pub field: String,
+    pub subgraph: String,
+    /// The conditions (as a selection set) in the old query graph, if any.
+    pub old_conditions: Option<String>,
+    /// The conditions (as a selection set) in the new query graph, if any.
+    pub new_conditions: Option<String>,
+}
+
+fn key_edges(query_graph: &QueryGraph) -> Result<IndexSet<KeyEdge>, FederationError> {
+    let mut key_edges = IndexSet::new();
+    for edge in query_graph.graph().edge_references() {
+        let edge_weight = edge.weight();
+        if !matches!(
+            edge_weight.transition,
+            QueryGraphEdgeTransition::KeyResolution
+        ) || !query_graph.is_cross_subgraph_edge(edge.id())?
+        {
+            continue;
+        }
+        let head_weight = query_graph.node_weight(edge.source())?;
+        let tail_weight = query_graph.node_weight(edge.target())?;
+        let QueryGraphNodeType::SchemaType(type_position) = &tail_weight.type_ else {
+            continue;
+        };
+        // Nodes copied for @provides also copy the key edges of the original node, which the set
+        // deduplicates.
+        key_edges.insert(KeyEdge {
+            type_name: type_position.type_name().to_string(),
+            from_subgraph: head_weight.source.to_string(),
+            to_subgraph: tail_weight.source.to_string(),
+            key: edge_weight
+                .conditions
+                .as_ref()
+                .map(|conditions| conditions.to_string())
+                .unwrap_or_default(),
+        });
+    }
+    Ok(key_edges)
+}
+
+/// The subgraphs resolving each field (other than `__typename`), keyed by field coordinate. Fields
+/// that are only part of some @provides aren't counted as resolved by the subgraph.
+fn field_subgraphs(
+    query_graph: &QueryGraph,
+) -> Result<IndexMap<String, IndexSet<String>>, FederationError> {
+    let mut field_subgraphs: IndexMap<String, IndexSet<String>> = IndexMap::new();
+    for edge in query_graph.graph().edge_weights() {
+        let QueryGraphEdgeTransition::FieldCollection {
+            source,
+            field_definition_position,
+            is_part_of_provides: false,
+        } = &edge.transition
+        else {
+            continue;
+        };
+        if field_definition_position.is_introspection_typename_field() {
+            continue;
+        }
+        field_subgraphs
+            .entry(field_definition_position.to_string())
+            .or_default()
+            .insert(source.to_string());
+    }
+    for subgraphs in field_subgraphs.values_mut() {
+        subgraphs.sort();
+    }
+    Ok(field_subgraphs)
+}
+
+/// The `@requires` conditions of fields, keyed by field coordinate and subgraph.
+fn requires_conditions(
+    query_graph: &QueryGraph,
+) -> Result<IndexMap<(String, String), String>, FederationError> {
+    let mut requires_conditions = IndexMap::new();
+    for edge in query_graph.graph().edge_weights() {
+        let (
+            QueryGraphEdgeTransition::FieldCollection {
+                source,
+                field_definition_position,
+                ..
+            },
+            Some(conditions),
+        ) = (&edge.transition, &edge.conditions)
+        else {
+            continue;
+        };
+        requires_conditions.insert(
+            (field_definition_position.to_string(), source.to_string()),
+            conditions.to_string(),
+        );
+    }
+    Ok(requires_conditions)
+}
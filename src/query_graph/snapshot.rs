@@ -0,0 +1,220 @@
+use crate::error::FederationError;
+use crate::query_graph::QueryGraph;
+use indexmap::IndexMap;
+use petgraph::visit::EdgeRef;
+use std::fmt::{Display, Formatter};
+
+impl QueryGraph {
+    /// A canonical textual snapshot of this query graph, meant for golden tests. It lists every
+    /// node, then every edge (with its transition, conditions and override condition), each list
+    /// being sorted so the snapshot doesn't depend on the order nodes and edges were added in.
+    ///
+    /// Note the snapshot identifies nodes by their display (e.g. `T(S1)`), so nodes copied for
+    /// @provides are told apart by their provide ID, which depends on the order @provides are
+    /// processed in.
+    pub fn snapshot(&self) -> Result<String, FederationError> {
+        let (nodes, edges) = self.snapshot_lines()?;
+        let mut snapshot = String::from("nodes:\n");
+        for node in nodes {
+            snapshot.push_str("  ");
+            snapshot.push_str(&node);
+            snapshot.push('\n');
+        }
+        snapshot.push_str("edges:\n");
+        for edge in edges {
+            snapshot.push_str("  ");
+            snapshot.push_str(&edge);
+            snapshot.push('\n');
+        }
+        Ok(snapshot)
+    }
+
+    /// The nodes and edges that differ between this query graph and the given one.
+    pub fn diff(&self, other: &QueryGraph) -> Result<QueryGraphDiff, FederationError> {
+        Ok(QueryGraphDiff::between(
+            &self.snapshot()?,
+            &other.snapshot()?,
+        ))
+    }
+
+    fn snapshot_lines(&self) -> Result<(Vec<String>, Vec<String>), FederationError> {
+        let mut nodes = Vec::new();
+        for node in self.graph.node_indices() {
+            nodes.push(self.node_weight(node)?.to_string());
+        }
+        let mut edges = Vec::new();
+        for edge in self.graph.edge_references() {
+            let edge_weight = edge.weight();
+            // Edges display nothing for subgraph-entering transitions without conditions, so the
+            // transition is displayed on its own in that case.
+            let mut label = edge_weight.to_string();
+            if label.is_empty() {
+                label = edge_weight.transition.to_string();
+            }
+            edges.push(format!(
+                "{} -> {} [{}]",
+                self.node_weight(edge.source())?,
+                self.node_weight(edge.target())?,
+                label,
+            ));
+        }
+        nodes.sort();
+        edges.sort();
+        Ok((nodes, edges))
+    }
+}
+
+/// The difference between two query graph snapshots (see `QueryGraph::snapshot()`), as the lines
+/// only found in the old snapshot and those only found in the new one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryGraphDiff {
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl QueryGraphDiff {
+    /// Diffs the given snapshots. Lines are compared as multisets, so the same edge appearing one
+    /// more time in the new snapshot is reported as added.
+    pub fn between(old_snapshot: &str, new_snapshot: &str) -> Self {
+        let mut counts: IndexMap<&str, isize> = IndexMap::new();
+        for line in old_snapshot.lines() {
+            *counts.entry(line).or_default() -= 1;
+        }
+        for line in new_snapshot.lines() {
+            *counts.entry(line).or_default() += 1;
+        }
+        let mut diff = Self::default();
+        for (line, count) in counts {
+            let line = line.trim().to_owned();
+            if count < 0 {
+                diff.removed
+                    .extend(std::iter::repeat_n(line, count.unsigned_abs()));
+            } else if count > 0 {
+                diff.added
+                    .extend(std::iter::repeat_n(line, count.unsigned_abs()));
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty()
+    }
+}
+
+impl Display for QueryGraphDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for line in &self.removed {
+            writeln!(f, "- {}", line)?;
+        }
+        for line in &self.added {
+            writeln!(f, "+ {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::FederationError;
+    use crate::query_graph::build_query_graph::build_federated_query_graph;
+    use crate::query_graph::QueryGraph;
+    use crate::schema::ValidFederationSchema;
+    use crate::test_supergraph::supergraph_sdl;
+
+    fn supergraph(t_fields: &str) -> String {
+        supergraph_sdl(
+            &["S1", "S2"],
+            &format!(
+                r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {{
+              t: T @join__field(graph: S2)
+            }}
+
+            type T
+              @join__type(graph: S1, key: "id", resolvable: false)
+              @join__type(graph: S2, key: "id", resolvable: false)
+            {{
+              id: ID!
+              {t_fields}
+            }}
+            "#
+            ),
+        )
+    }
+
+    fn query_graph(t_fields: &str) -> Result<QueryGraph, FederationError> {
+        let supergraph = crate::Supergraph::new(&supergraph(t_fields))?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        build_federated_query_graph(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )
+    }
+
+    #[test]
+    fn snapshots_query_graphs() -> Result<(), FederationError> {
+        let query_graph = query_graph("x: Int @join__field(graph: S1)")?;
+        insta::assert_snapshot!(query_graph.snapshot()?, @r###"
+        nodes:
+          ID(S1)
+          ID(S2)
+          Int(S1)
+          Query(S1)*
+          Query(S2)*
+          String(S1)
+          String(S2)
+          T(S1)
+          T(S2)
+          [query](_)*
+          _Entity(S1)
+          _Entity(S2)
+          _Service(S1)
+          _Service(S2)
+        edges:
+          Query(S1)* -> Query(S1)* [query()]
+          Query(S1)* -> Query(S2)* [query()]
+          Query(S1)* -> String(S1) [__typename]
+          Query(S1)* -> _Entity(S1) [_entities]
+          Query(S1)* -> _Service(S1) [_service]
+          Query(S2)* -> Query(S1)* [query()]
+          Query(S2)* -> Query(S2)* [query()]
+          Query(S2)* -> String(S2) [__typename]
+          Query(S2)* -> T(S2) [t]
+          Query(S2)* -> _Entity(S2) [_entities]
+          Query(S2)* -> _Service(S2) [_service]
+          T(S1) -> ID(S1) [id]
+          T(S1) -> Int(S1) [x]
+          T(S1) -> String(S1) [__typename]
+          T(S2) -> ID(S2) [id]
+          T(S2) -> String(S2) [__typename]
+          [query](_)* -> Query(S1)* [∅]
+          [query](_)* -> Query(S2)* [∅]
+          _Entity(S1) -> String(S1) [__typename]
+          _Entity(S1) -> T(S1) [... on T]
+          _Entity(S2) -> String(S2) [__typename]
+          _Entity(S2) -> T(S2) [... on T]
+          _Service(S1) -> String(S1) [__typename]
+          _Service(S1) -> String(S1) [sdl]
+          _Service(S2) -> String(S2) [__typename]
+          _Service(S2) -> String(S2) [sdl]
+        "###);
+        Ok(())
+    }
+
+    #[test]
+    fn diffs_query_graphs() -> Result<(), FederationError> {
+        let old = query_graph("x: Int @join__field(graph: S1)")?;
+        let new = query_graph("x: Int @join__field(graph: S1)\ny: String @join__field(graph: S1)")?;
+        assert!(old.diff(&old)?.is_empty());
+        insta::assert_snapshot!(old.diff(&new)?, @r###"
+        + T(S1) -> String(S1) [y]
+        "###);
+        Ok(())
+    }
+}
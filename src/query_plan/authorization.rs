@@ -0,0 +1,208 @@
+//! Annotates the fetch nodes of query plans with the authorization requirements of what they
+//! fetch, so routers can authorize (or skip) fetches without inspecting their operations.
+use crate::query_plan::{FetchNode, PlanNode, QueryPlan, TopLevelPlanNode};
+use crate::{AuthorizationMetadata, AuthorizationRequirements};
+use std::sync::Arc;
+
+impl QueryPlan {
+    /// Annotates each fetch node of this plan with the authorization requirements of the types
+    /// and fields it fetches (see `FetchNode::authorization()`).
+    pub fn annotate_authorization(&mut self, metadata: &AuthorizationMetadata) {
+        let Some(node) = &mut self.node else {
+            return;
+        };
+        match node {
+            TopLevelPlanNode::Subscription(node) => {
+                node.primary.annotate_authorization(metadata);
+                if let Some(rest) = &mut node.rest {
+                    rest.annotate_authorization(metadata);
+                }
+            }
+            TopLevelPlanNode::Fetch(node) => node.annotate_authorization(metadata),
+            TopLevelPlanNode::Sequence(node) => {
+                for node in &mut node.nodes {
+                    node.annotate_authorization(metadata);
+                }
+            }
+            TopLevelPlanNode::Parallel(node) => {
+                for node in &mut node.nodes {
+                    node.annotate_authorization(metadata);
+                }
+            }
+            TopLevelPlanNode::Flatten(node) => node.node.annotate_authorization(metadata),
+            TopLevelPlanNode::Defer(node) => {
+                if let Some(node) = &mut node.primary.node {
+                    node.annotate_authorization(metadata);
+                }
+                for deferred in &mut node.deferred {
+                    if let Some(node) = &mut deferred.node {
+                        node.annotate_authorization(metadata);
+                    }
+                }
+            }
+            TopLevelPlanNode::Condition(node) => {
+                for node in [&mut node.if_clause, &mut node.else_clause]
+                    .into_iter()
+                    .flatten()
+                {
+                    node.annotate_authorization(metadata);
+                }
+            }
+        }
+    }
+}
+
+impl PlanNode {
+    fn annotate_authorization(&mut self, metadata: &AuthorizationMetadata) {
+        match self {
+            PlanNode::Fetch(node) => Arc::make_mut(node).annotate_authorization(metadata),
+            PlanNode::Sequence(node) => {
+                for node in &mut Arc::make_mut(node).nodes {
+                    node.annotate_authorization(metadata);
+                }
+            }
+            PlanNode::Parallel(node) => {
+                for node in &mut Arc::make_mut(node).nodes {
+                    node.annotate_authorization(metadata);
+                }
+            }
+            PlanNode::Flatten(node) => Arc::make_mut(node).node.annotate_authorization(metadata),
+            PlanNode::Defer(node) => {
+                let node = Arc::make_mut(node);
+                if let Some(node) = &mut node.primary.node {
+                    node.annotate_authorization(metadata);
+                }
+                for deferred in &mut node.deferred {
+                    if let Some(node) = &mut deferred.node {
+                        node.annotate_authorization(metadata);
+                    }
+                }
+            }
+            PlanNode::Condition(node) => {
+                let node = Arc::make_mut(node);
+                for node in [&mut node.if_clause, &mut node.else_clause]
+                    .into_iter()
+                    .flatten()
+                {
+                    node.annotate_authorization(metadata);
+                }
+            }
+        }
+    }
+}
+
+impl FetchNode {
+    /// The authorization requirements of the types and fields this fetch selects, if the plan was
+    /// annotated with them (see `QueryPlan::annotate_authorization()`).
+    pub fn authorization(&self) -> Option<&AuthorizationRequirements> {
+        self.authorization.as_ref()
+    }
+
+    fn annotate_authorization(&mut self, metadata: &AuthorizationMetadata) {
+        let mut requirements = AuthorizationRequirements::default();
+        for operation in self.operation_document.all_operations() {
+            requirements.merge(&metadata.selection_set_requirements(&operation.selection_set));
+        }
+        self.authorization = Some(requirements);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::{FetchNode, PlanNode, QueryPlan, SequenceNode, TopLevelPlanNode};
+    use crate::{AuthorizationRequirements, Supergraph};
+    use apollo_compiler::executable::OperationType;
+    use apollo_compiler::{ExecutableDocument, NodeStr};
+    use std::sync::Arc;
+
+    #[test]
+    fn annotates_fetches_with_authorization_requirements() {
+        let supergraph = Supergraph::new(
+            r#"
+            schema
+              @link(url: "https://specs.apollo.dev/link/v1.0")
+              @link(url: "https://specs.apollo.dev/authenticated/v0.1", for: SECURITY)
+            {
+              query: Query
+            }
+
+            directive @authenticated on OBJECT | FIELD_DEFINITION | INTERFACE | SCALAR | ENUM
+
+            directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+            scalar link__Import
+
+            enum link__Purpose {
+              SECURITY
+              EXECUTION
+            }
+
+            type Query {
+              me: User @authenticated
+              publicName: String
+            }
+
+            type User {
+              name: String
+            }
+            "#,
+        )
+        .unwrap();
+        let fetch = |operation: &str| {
+            PlanNode::Fetch(Arc::new(FetchNode {
+                subgraph_name: NodeStr::new("S1"),
+                id: None,
+                has_defers: None,
+                variable_usages: vec![],
+                requires: vec![],
+                operation_document: ExecutableDocument::parse_and_validate(
+                    supergraph.schema(),
+                    operation,
+                    "operation.graphql",
+                )
+                .unwrap(),
+                operation_name: None,
+                operation_kind: OperationType::Query,
+                input_rewrites: vec![],
+                output_rewrites: vec![],
+                context_rewrites: vec![],
+                authorization: None,
+            }))
+        };
+        let mut plan = QueryPlan {
+            node: Some(TopLevelPlanNode::Sequence(SequenceNode {
+                nodes: vec![fetch("{ publicName }"), fetch("{ me { name } }")],
+            })),
+            warnings: Vec::new(),
+            has_introspection: false,
+        };
+        plan.annotate_authorization(&supergraph.authorization_metadata().unwrap());
+
+        let Some(TopLevelPlanNode::Sequence(sequence)) = &plan.node else {
+            panic!("Expected a sequence");
+        };
+        let authorizations = sequence
+            .nodes
+            .iter()
+            .map(|node| match node {
+                PlanNode::Fetch(fetch) => fetch.authorization().cloned(),
+                _ => panic!("Expected a fetch"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            authorizations,
+            [
+                Some(AuthorizationRequirements::default()),
+                Some(AuthorizationRequirements {
+                    authenticated: true,
+                    scopes: vec![],
+                    policies: vec![],
+                }),
+            ]
+        );
+        assert!(plan
+            .to_json()
+            .to_string()
+            .contains(r#""authenticated":true"#));
+    }
+}
@@ -1,9 +1,16 @@
-use crate::error::FederationError;
+use crate::error::{FederationError, SingleFederationError};
+use crate::query_plan::{
+    ConditionNode, DeferNode, DeferredDeferBlock, FlattenNode, ParallelNode, PlanNode,
+    PrimaryDeferBlock, QueryPlan, SequenceNode, SubscriptionNode, TopLevelPlanNode,
+};
 use apollo_compiler::executable::DirectiveList;
 use apollo_compiler::executable::Name;
 use apollo_compiler::executable::Value;
+use apollo_compiler::executable::VariableDefinition;
+use apollo_compiler::Node;
 use indexmap::map::Entry;
 use indexmap::IndexMap;
+use serde_json::{Map, Value as JsonValue};
 use std::sync::Arc;
 
 /// This struct is meant for tracking whether a selection set in a `FetchDependencyGraphNode` needs
@@ -12,7 +19,7 @@ use std::sync::Arc;
 /// the more appropriate struct when trying to record the original structure/intent of those
 /// `@skip`/`@include` applications.
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) enum Conditions {
+pub enum Conditions {
     Variables(VariableConditions),
     Boolean(bool),
 }
@@ -27,7 +34,7 @@ pub(crate) enum Condition {
 /// is negated in the condition. We maintain the invariant that there's at least one condition (i.e.
 /// the map is non-empty), and that there's at most one condition per variable name.
 #[derive(Debug, Clone, PartialEq)]
-pub(crate) struct VariableConditions(Arc<IndexMap<Name, bool>>);
+pub struct VariableConditions(Arc<IndexMap<Name, bool>>);
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct VariableCondition {
@@ -83,6 +90,66 @@ impl Conditions {
         })
     }
 
+    /// Evaluates these conditions against the given variable values (as sent in a GraphQL
+    /// request), falling back to the default values of the given operation variable definitions.
+    /// Errors if a variable used by the conditions has no boolean value.
+    pub fn evaluate(
+        &self,
+        variables: &Map<String, JsonValue>,
+        variable_definitions: &[Node<VariableDefinition>],
+    ) -> Result<bool, FederationError> {
+        match self {
+            Conditions::Boolean(value) => Ok(*value),
+            Conditions::Variables(conditions) => {
+                for (name, negated) in conditions.0.iter() {
+                    let Some(value) = variable_value(variables, variable_definitions, name)? else {
+                        return Err(SingleFederationError::InvalidGraphQL {
+                            message: format!(
+                                "Missing value for variable \"${}\" used in @skip/@include",
+                                name
+                            ),
+                            locations: Vec::new(),
+                        }
+                        .into());
+                    };
+                    if value == *negated {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    /// Returns the conditions that remain to be checked once the given (already handled)
+    /// conditions are known to hold. This is used so that a fetch nested within a condition node
+    /// doesn't get wrapped again in a condition node for the same variable.
+    pub(crate) fn update_with(&self, handled_conditions: &Self) -> Self {
+        let (Conditions::Variables(new), Conditions::Variables(handled)) =
+            (self, handled_conditions)
+        else {
+            return self.clone();
+        };
+        let mut filtered = IndexMap::new();
+        for (name, negated) in new.0.iter() {
+            match handled.0.get(name) {
+                None => {
+                    filtered.insert(name.clone(), *negated);
+                }
+                // The handled conditions hold, so these conditions never can.
+                Some(handled_negated) if handled_negated != negated => {
+                    return Conditions::Boolean(false);
+                }
+                Some(_) => {}
+            }
+        }
+        if filtered.is_empty() {
+            Conditions::Boolean(true)
+        } else {
+            Conditions::Variables(VariableConditions(Arc::new(filtered)))
+        }
+    }
+
     pub(crate) fn merge(self, other: Self) -> Self {
         match (self, other) {
             // Absorbing element
@@ -113,3 +180,389 @@ impl Conditions {
         }
     }
 }
+
+impl VariableConditions {
+    /// The variables of these conditions, along with whether each is negated (i.e. comes from a
+    /// `@skip`).
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Name, bool)> {
+        self.0.iter().map(|(name, negated)| (name, *negated))
+    }
+}
+
+/// The boolean value of the given variable, or `None` if it has no value. A variable absent from
+/// the given values takes the default value of its definition, if any (an explicit `null` doesn't).
+/// Errors if the value isn't a boolean, since only boolean variables can be used in @skip/@include.
+fn variable_value(
+    variables: &Map<String, JsonValue>,
+    variable_definitions: &[Node<VariableDefinition>],
+    name: &Name,
+) -> Result<Option<bool>, FederationError> {
+    match variables.get(name.as_str()) {
+        None => Ok(variable_definitions
+            .iter()
+            .find(|definition| definition.name == *name)
+            .and_then(|definition| match definition.default_value.as_deref() {
+                Some(Value::Boolean(value)) => Some(*value),
+                _ => None,
+            })),
+        Some(JsonValue::Null) => Ok(None),
+        Some(JsonValue::Bool(value)) => Ok(Some(*value)),
+        Some(value) => Err(SingleFederationError::InvalidGraphQL {
+            message: format!(
+                "Variable \"${}\" used in @skip/@include must be a boolean, got {}",
+                name, value
+            ),
+            locations: Vec::new(),
+        }
+        .into()),
+    }
+}
+
+impl QueryPlan {
+    /// Removes the condition nodes of this plan whose variable has a value in the given variables
+    /// (or a default value in the given operation variable definitions), replacing each by the
+    /// branch that value selects. This is meant for executors that know the variable values before
+    /// running the plan: condition nodes on variables without a value are kept, and nodes left
+    /// empty by the pruning are removed.
+    pub fn prune_conditions(
+        self,
+        variables: &Map<String, JsonValue>,
+        variable_definitions: &[Node<VariableDefinition>],
+    ) -> Result<QueryPlan, FederationError> {
+        let variables = &PruningVariables {
+            values: variables,
+            definitions: variable_definitions,
+        };
+        let Some(node) = self.node else {
+            return Ok(self);
+        };
+        let node = match node {
+            TopLevelPlanNode::Subscription(SubscriptionNode { primary, rest }) => {
+                Some(TopLevelPlanNode::Subscription(SubscriptionNode {
+                    primary,
+                    rest: prune_optional(rest, variables)?,
+                }))
+            }
+            TopLevelPlanNode::Fetch(node) => Some(TopLevelPlanNode::Fetch(node)),
+            TopLevelPlanNode::Sequence(node) => {
+                prune(&PlanNode::Sequence(Arc::new(node)), variables)?.map(Into::into)
+            }
+            TopLevelPlanNode::Parallel(node) => {
+                prune(&PlanNode::Parallel(Arc::new(node)), variables)?.map(Into::into)
+            }
+            TopLevelPlanNode::Flatten(node) => {
+                prune(&PlanNode::Flatten(Arc::new(node)), variables)?.map(Into::into)
+            }
+            TopLevelPlanNode::Defer(node) => {
+                prune(&PlanNode::Defer(Arc::new(node)), variables)?.map(Into::into)
+            }
+            TopLevelPlanNode::Condition(node) => {
+                prune(&PlanNode::Condition(Arc::new(node)), variables)?.map(Into::into)
+            }
+        };
+        Ok(QueryPlan {
+            node,
+            warnings: self.warnings,
+            has_introspection: self.has_introspection,
+        })
+    }
+}
+
+impl From<PlanNode> for TopLevelPlanNode {
+    fn from(node: PlanNode) -> Self {
+        match node {
+            PlanNode::Fetch(node) => TopLevelPlanNode::Fetch(Arc::unwrap_or_clone(node)),
+            PlanNode::Sequence(node) => TopLevelPlanNode::Sequence(Arc::unwrap_or_clone(node)),
+            PlanNode::Parallel(node) => TopLevelPlanNode::Parallel(Arc::unwrap_or_clone(node)),
+            PlanNode::Flatten(node) => TopLevelPlanNode::Flatten(Arc::unwrap_or_clone(node)),
+            PlanNode::Defer(node) => TopLevelPlanNode::Defer(Arc::unwrap_or_clone(node)),
+            PlanNode::Condition(node) => TopLevelPlanNode::Condition(Arc::unwrap_or_clone(node)),
+        }
+    }
+}
+
+/// The variable values and definitions that `QueryPlan::prune_conditions()` prunes against.
+struct PruningVariables<'a> {
+    values: &'a Map<String, JsonValue>,
+    definitions: &'a [Node<VariableDefinition>],
+}
+
+fn prune_optional(
+    node: Option<PlanNode>,
+    variables: &PruningVariables,
+) -> Result<Option<PlanNode>, FederationError> {
+    node.map(|node| prune(&node, variables))
+        .transpose()
+        .map(Option::flatten)
+}
+
+/// Prunes the given node (see `QueryPlan::prune_conditions()`), returning `None` if nothing is
+/// left of it. Sequences and parallel nodes left with a single child are replaced by that child.
+fn prune(
+    node: &PlanNode,
+    variables: &PruningVariables,
+) -> Result<Option<PlanNode>, FederationError> {
+    Ok(match node {
+        PlanNode::Fetch(_) => Some(node.clone()),
+        PlanNode::Sequence(sequence) => {
+            let mut nodes = prune_all(&sequence.nodes, variables)?;
+            match nodes.len() {
+                0 => None,
+                1 => nodes.pop(),
+                _ => Some(PlanNode::Sequence(Arc::new(SequenceNode { nodes }))),
+            }
+        }
+        PlanNode::Parallel(parallel) => {
+            let mut nodes = prune_all(&parallel.nodes, variables)?;
+            match nodes.len() {
+                0 => None,
+                1 => nodes.pop(),
+                _ => Some(PlanNode::Parallel(Arc::new(ParallelNode { nodes }))),
+            }
+        }
+        PlanNode::Flatten(flatten) => prune(&flatten.node, variables)?.map(|child| {
+            PlanNode::Flatten(Arc::new(FlattenNode {
+                path: flatten.path.clone(),
+                node: child,
+            }))
+        }),
+        PlanNode::Defer(defer) => {
+            // Defer nodes are kept even if they end up without any fetch, as the executor still
+            // needs them to send the deferred responses.
+            Some(PlanNode::Defer(Arc::new(DeferNode {
+                primary: PrimaryDeferBlock {
+                    node: prune_optional(defer.primary.node.clone(), variables)?,
+                    ..defer.primary.clone()
+                },
+                deferred: defer
+                    .deferred
+                    .iter()
+                    .map(|deferred| {
+                        Ok(DeferredDeferBlock {
+                            node: prune_optional(deferred.node.clone(), variables)?,
+                            ..deferred.clone()
+                        })
+                    })
+                    .collect::<Result<_, FederationError>>()?,
+            })))
+        }
+        PlanNode::Condition(condition) => {
+            match variable_value(
+                variables.values,
+                variables.definitions,
+                &condition.condition_variable,
+            )? {
+                Some(true) => prune_optional(condition.if_clause.clone(), variables)?,
+                Some(false) => prune_optional(condition.else_clause.clone(), variables)?,
+                None => {
+                    let if_clause = prune_optional(condition.if_clause.clone(), variables)?;
+                    let else_clause = prune_optional(condition.else_clause.clone(), variables)?;
+                    if if_clause.is_none() && else_clause.is_none() {
+                        None
+                    } else {
+                        Some(PlanNode::Condition(Arc::new(ConditionNode {
+                            condition_variable: condition.condition_variable.clone(),
+                            if_clause,
+                            else_clause,
+                        })))
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn prune_all(
+    nodes: &[PlanNode],
+    variables: &PruningVariables,
+) -> Result<Vec<PlanNode>, FederationError> {
+    let mut pruned = Vec::new();
+    for node in nodes {
+        pruned.extend(prune(node, variables)?);
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::conditions::{Conditions, VariableConditions};
+    use crate::query_plan::{
+        ConditionNode, FetchNode, PlanNode, QueryPlan, SequenceNode, TopLevelPlanNode,
+    };
+    use apollo_compiler::executable::OperationType;
+    use apollo_compiler::{name, ExecutableDocument, NodeStr, Schema};
+    use indexmap::IndexMap;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn variables(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    fn fetch(subgraph_name: &str) -> PlanNode {
+        let schema = Schema::parse_and_validate("type Query { a: Int }", "schema.graphql").unwrap();
+        PlanNode::Fetch(Arc::new(FetchNode {
+            subgraph_name: NodeStr::new(subgraph_name),
+            id: None,
+            has_defers: None,
+            variable_usages: vec![],
+            requires: vec![],
+            operation_document: ExecutableDocument::parse_and_validate(
+                &schema,
+                "{ a }",
+                "operation.graphql",
+            )
+            .unwrap(),
+            operation_name: None,
+            operation_kind: OperationType::Query,
+            input_rewrites: vec![],
+            output_rewrites: vec![],
+            context_rewrites: vec![],
+            authorization: None,
+        }))
+    }
+
+    fn subgraph_names(node: &PlanNode) -> Vec<String> {
+        match node {
+            PlanNode::Fetch(fetch) => vec![fetch.subgraph_name.to_string()],
+            PlanNode::Sequence(sequence) => {
+                sequence.nodes.iter().flat_map(subgraph_names).collect()
+            }
+            PlanNode::Condition(condition) => {
+                let mut names = vec![format!("if({})", condition.condition_variable)];
+                names.extend(condition.if_clause.iter().flat_map(subgraph_names));
+                names.push("else".to_owned());
+                names.extend(condition.else_clause.iter().flat_map(subgraph_names));
+                names
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    #[test]
+    fn evaluates_conditions() {
+        let conditions = Conditions::Variables(VariableConditions(Arc::new(IndexMap::from([
+            (name!("skipped"), true),
+            (name!("included"), false),
+        ]))));
+        let values = variables(json!({ "skipped": false, "included": true }));
+        assert!(conditions.evaluate(&values, &[]).unwrap());
+        let values = variables(json!({ "skipped": true, "included": true }));
+        assert!(!conditions.evaluate(&values, &[]).unwrap());
+        let values = variables(json!({ "skipped": false }));
+        assert!(conditions.evaluate(&values, &[]).is_err());
+        let values = variables(json!({ "skipped": false, "included": "yes" }));
+        assert!(conditions.evaluate(&values, &[]).is_err());
+        assert!(Conditions::Boolean(true).evaluate(&values, &[]).unwrap());
+    }
+
+    #[test]
+    fn evaluates_conditions_with_variable_defaults() {
+        let schema = Schema::parse_and_validate("type Query { a: Int }", "schema.graphql").unwrap();
+        let document = ExecutableDocument::parse_and_validate(
+            &schema,
+            "query($s: Boolean = true) { a @skip(if: $s) }",
+            "operation.graphql",
+        )
+        .unwrap();
+        let operation = document.anonymous_operation.as_ref().unwrap();
+        let conditions = Conditions::from_directives(
+            &operation.selection_set.fields().next().unwrap().directives,
+        )
+        .unwrap();
+        let definitions = &operation.variables;
+        assert!(!conditions
+            .evaluate(&variables(json!({})), definitions)
+            .unwrap());
+        assert!(conditions
+            .evaluate(&variables(json!({ "s": false })), definitions)
+            .unwrap());
+        assert!(conditions
+            .evaluate(&variables(json!({ "s": null })), definitions)
+            .is_err());
+
+        let plan = QueryPlan {
+            node: Some(TopLevelPlanNode::Condition(ConditionNode {
+                condition_variable: name!("s"),
+                if_clause: Some(fetch("S1")),
+                else_clause: Some(fetch("S2")),
+            })),
+            warnings: Vec::new(),
+            has_introspection: false,
+        };
+        let pruned = plan
+            .prune_conditions(&variables(json!({})), definitions)
+            .unwrap();
+        let Some(TopLevelPlanNode::Fetch(fetch)) = pruned.node else {
+            panic!("expected a fetch");
+        };
+        assert_eq!(fetch.subgraph_name, "S1");
+    }
+
+    fn plan() -> QueryPlan {
+        QueryPlan {
+            node: Some(TopLevelPlanNode::Sequence(SequenceNode {
+                nodes: vec![
+                    fetch("S1"),
+                    PlanNode::Condition(Arc::new(ConditionNode {
+                        condition_variable: name!("known"),
+                        if_clause: Some(fetch("S2")),
+                        else_clause: None,
+                    })),
+                    PlanNode::Condition(Arc::new(ConditionNode {
+                        condition_variable: name!("unknown"),
+                        if_clause: Some(fetch("S3")),
+                        else_clause: Some(fetch("S4")),
+                    })),
+                ],
+            })),
+            warnings: Vec::new(),
+            has_introspection: false,
+        }
+    }
+
+    #[test]
+    fn prunes_known_conditions() {
+        let pruned = plan()
+            .prune_conditions(&variables(json!({ "known": false })), &[])
+            .unwrap();
+        let Some(TopLevelPlanNode::Sequence(sequence)) = pruned.node else {
+            panic!("expected a sequence");
+        };
+        assert_eq!(
+            subgraph_names(&PlanNode::Sequence(Arc::new(sequence))),
+            ["S1", "if(unknown)", "S3", "else", "S4"],
+        );
+
+        let pruned = plan()
+            .prune_conditions(&variables(json!({ "known": true, "unknown": false })), &[])
+            .unwrap();
+        let Some(TopLevelPlanNode::Sequence(sequence)) = pruned.node else {
+            panic!("expected a sequence");
+        };
+        assert_eq!(
+            subgraph_names(&PlanNode::Sequence(Arc::new(sequence))),
+            ["S1", "S2", "S4"],
+        );
+    }
+
+    #[test]
+    fn prunes_top_level_conditions() {
+        let plan = || QueryPlan {
+            node: Some(TopLevelPlanNode::Condition(ConditionNode {
+                condition_variable: name!("known"),
+                if_clause: Some(fetch("S1")),
+                else_clause: None,
+            })),
+            warnings: Vec::new(),
+            has_introspection: false,
+        };
+        assert!(plan()
+            .prune_conditions(&variables(json!({ "known": 1 })), &[])
+            .is_err());
+        let pruned = plan()
+            .prune_conditions(&variables(json!({ "known": false })), &[])
+            .unwrap();
+        assert!(pruned.node.is_none());
+    }
+}
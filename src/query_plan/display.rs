@@ -0,0 +1,324 @@
+use crate::query_plan::{
+    ConditionNode, DeferNode, FetchDataPathElement, FetchNode, FlattenNode, ParallelNode, PlanNode,
+    QueryPathElement, QueryPlan, SequenceNode, SubscriptionNode, TopLevelPlanNode,
+};
+use apollo_compiler::executable::{OperationType, Selection, SelectionSet};
+use apollo_compiler::name;
+
+const INDENT: &str = "  ";
+
+impl QueryPlan {
+    /// Prints this query plan in the human-readable format of the JS query planner's
+    /// `prettyFormatQueryPlan()`, e.g.
+    /// ```text
+    /// QueryPlan {
+    ///   Sequence {
+    ///     Fetch(service: "accounts") {
+    ///       {
+    ///         me {
+    ///           __typename
+    ///           id
+    ///         }
+    ///       }
+    ///     },
+    ///     Flatten(path: "me") {
+    ///       Fetch(service: "reviews") {
+    ///         {
+    ///           ... on User {
+    ///             __typename
+    ///             id
+    ///           }
+    ///         } =>
+    ///         {
+    ///           ... on User {
+    ///             reviews {
+    ///               body
+    ///             }
+    ///           }
+    ///         }
+    ///       },
+    ///     },
+    ///   },
+    /// }
+    /// ```
+    /// This is meant for snapshot tests, and for comparing plans with those of the JS query
+    /// planner.
+    pub fn to_pretty_string(&self) -> String {
+        let mut output = String::from("QueryPlan {\n");
+        if let Some(node) = &self.node {
+            output.push_str(INDENT);
+            node.write_pretty(&mut output, INDENT);
+            output.push_str(",\n");
+        }
+        output.push('}');
+        output
+    }
+}
+
+impl TopLevelPlanNode {
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        match self {
+            TopLevelPlanNode::Subscription(node) => node.write_pretty(output, indentation),
+            TopLevelPlanNode::Fetch(node) => node.write_pretty(output, indentation),
+            TopLevelPlanNode::Sequence(node) => node.write_pretty(output, indentation),
+            TopLevelPlanNode::Parallel(node) => node.write_pretty(output, indentation),
+            TopLevelPlanNode::Flatten(node) => node.write_pretty(output, indentation),
+            TopLevelPlanNode::Defer(node) => node.write_pretty(output, indentation),
+            TopLevelPlanNode::Condition(node) => node.write_pretty(output, indentation),
+        }
+    }
+}
+
+impl PlanNode {
+    /// Writes this node, whose first line is assumed to already be indented with `indentation`.
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        match self {
+            PlanNode::Fetch(node) => node.write_pretty(output, indentation),
+            PlanNode::Sequence(node) => node.write_pretty(output, indentation),
+            PlanNode::Parallel(node) => node.write_pretty(output, indentation),
+            PlanNode::Flatten(node) => node.write_pretty(output, indentation),
+            PlanNode::Defer(node) => node.write_pretty(output, indentation),
+            PlanNode::Condition(node) => node.write_pretty(output, indentation),
+        }
+    }
+}
+
+impl SubscriptionNode {
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        let indentation_next = format!("{indentation}{INDENT}");
+        let indentation_inner = format!("{indentation_next}{INDENT}");
+        output.push_str("Subscription {\n");
+        output.push_str(&format!(
+            "{indentation_next}Primary: {{\n{indentation_inner}"
+        ));
+        self.primary.write_pretty(output, &indentation_inner);
+        output.push_str(&format!("\n{indentation_next}}},\n"));
+        if let Some(rest) = &self.rest {
+            output.push_str(&format!("{indentation_next}Rest: {{\n"));
+            write_pretty_nodes(output, [rest], &indentation_inner);
+            output.push_str(&format!("{indentation_next}}},\n"));
+        }
+        output.push_str(indentation);
+        output.push('}');
+    }
+}
+
+impl FetchNode {
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        let indentation_next = format!("{indentation}{INDENT}");
+        output.push_str(&format!("Fetch(service: \"{}\"", self.subgraph_name));
+        if let Some(id) = &self.id {
+            output.push_str(&format!(", id: {id}"));
+        }
+        output.push_str(") {\n");
+        if !self.requires.is_empty() {
+            let requires = SelectionSet {
+                // The type isn't printed.
+                ty: name!("_Entity"),
+                selections: self.requires.clone(),
+            };
+            write_indented(output, &requires.serialize().to_string(), &indentation_next);
+            output.push_str(" =>\n");
+        }
+        write_indented(output, &self.pretty_operation(), &indentation_next);
+        output.push('\n');
+        output.push_str(indentation);
+        output.push('}');
+    }
+
+    /// The operation of this fetch, without its name and variable definitions. For entity
+    /// fetches, only the selections within the `_entities` field are printed.
+    fn pretty_operation(&self) -> String {
+        let mut definitions = Vec::new();
+        for operation in self.operation_document.all_operations() {
+            let entities_selection_set = match operation.selection_set.selections.first() {
+                Some(Selection::Field(field))
+                    if operation.operation_type == OperationType::Query
+                        && field.name == "_entities" =>
+                {
+                    Some(&field.selection_set)
+                }
+                _ => None,
+            };
+            definitions.push(match entities_selection_set {
+                Some(selection_set) => selection_set.serialize().to_string(),
+                None if operation.operation_type == OperationType::Query => {
+                    operation.selection_set.serialize().to_string()
+                }
+                None => format!(
+                    "{} {}",
+                    operation.operation_type.name(),
+                    operation.selection_set.serialize()
+                ),
+            });
+        }
+        for fragment in self.operation_document.fragments.values() {
+            definitions.push(fragment.serialize().to_string());
+        }
+        definitions
+            .iter()
+            .map(|definition| definition.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+impl SequenceNode {
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        output.push_str("Sequence {\n");
+        write_pretty_nodes(output, &self.nodes, &format!("{indentation}{INDENT}"));
+        output.push_str(indentation);
+        output.push('}');
+    }
+}
+
+impl ParallelNode {
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        output.push_str("Parallel {\n");
+        write_pretty_nodes(output, &self.nodes, &format!("{indentation}{INDENT}"));
+        output.push_str(indentation);
+        output.push('}');
+    }
+}
+
+impl FlattenNode {
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        let path = self
+            .path
+            .iter()
+            .map(|element| match element {
+                FetchDataPathElement::Key(key) => key.to_string(),
+                FetchDataPathElement::AnyIndex => "@".to_owned(),
+                FetchDataPathElement::TypenameEquals(type_name) => format!("... on {type_name}"),
+                FetchDataPathElement::Parent => "..".to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join(".");
+        output.push_str(&format!("Flatten(path: \"{path}\") {{\n"));
+        write_pretty_nodes(output, [&self.node], &format!("{indentation}{INDENT}"));
+        output.push_str(indentation);
+        output.push('}');
+    }
+}
+
+impl DeferNode {
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        let indentation_next = format!("{indentation}{INDENT}");
+        let indentation_inner = format!("{indentation_next}{INDENT}");
+        output.push_str("Defer {\n");
+        output.push_str(&format!("{indentation_next}Primary {{\n"));
+        if let Some(sub_selection) = &self.primary.sub_selection {
+            write_indented(
+                output,
+                &sub_selection.serialize().to_string(),
+                &indentation_inner,
+            );
+            output.push_str(":\n");
+        }
+        write_pretty_nodes(output, &self.primary.node, &indentation_inner);
+        output.push_str(&format!("{indentation_next}}}, [\n"));
+        for deferred in &self.deferred {
+            let depends = deferred
+                .depends
+                .iter()
+                .map(|dependency| match &dependency.defer_label {
+                    Some(defer_label) => format!("{}:{}", dependency.id, defer_label),
+                    None => dependency.id.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let path = deferred
+                .query_path
+                .iter()
+                .map(|element| match element {
+                    QueryPathElement::Field(field) => field.response_key().to_string(),
+                    QueryPathElement::InlineFragment(inline_fragment) => {
+                        match &inline_fragment.type_condition {
+                            Some(type_condition) => format!("... on {type_condition}"),
+                            None => "...".to_owned(),
+                        }
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            output.push_str(&format!(
+                "{indentation_next}Deferred(depends: [{depends}], path: \"{path}\""
+            ));
+            if let Some(label) = &deferred.label {
+                output.push_str(&format!(", label: \"{label}\""));
+            }
+            output.push_str(") {\n");
+            if let Some(sub_selection) = &deferred.sub_selection {
+                write_indented(
+                    output,
+                    &sub_selection.serialize().to_string(),
+                    &indentation_inner,
+                );
+                output.push_str(":\n");
+            }
+            write_pretty_nodes(output, &deferred.node, &indentation_inner);
+            output.push_str(&format!("{indentation_next}}},\n"));
+        }
+        output.push_str(&format!("{indentation}]"));
+    }
+}
+
+impl ConditionNode {
+    fn write_pretty(&self, output: &mut String, indentation: &str) {
+        let indentation_next = format!("{indentation}{INDENT}");
+        match (&self.if_clause, &self.else_clause) {
+            (Some(if_clause), Some(else_clause)) => {
+                let indentation_inner = format!("{indentation_next}{INDENT}");
+                output.push_str(&format!(
+                    "Condition(if: ${}) {{\n{indentation_next}Then {{\n",
+                    self.condition_variable
+                ));
+                write_pretty_nodes(output, [if_clause], &indentation_inner);
+                output.push_str(&format!(
+                    "{indentation_next}}}\n{indentation_next}Else {{\n"
+                ));
+                write_pretty_nodes(output, [else_clause], &indentation_inner);
+                output.push_str(&format!("{indentation_next}}}\n"));
+            }
+            (Some(if_clause), None) => {
+                output.push_str(&format!("Include(if: ${}) {{\n", self.condition_variable));
+                write_pretty_nodes(output, [if_clause], &indentation_next);
+            }
+            (None, Some(else_clause)) => {
+                output.push_str(&format!("Skip(if: ${}) {{\n", self.condition_variable));
+                write_pretty_nodes(output, [else_clause], &indentation_next);
+            }
+            (None, None) => {
+                output.push_str(&format!("Include(if: ${}) {{\n", self.condition_variable));
+            }
+        }
+        output.push_str(indentation);
+        output.push('}');
+    }
+}
+
+/// Writes the given nodes, each on its own lines indented with `indentation` and followed by a
+/// comma.
+fn write_pretty_nodes<'a>(
+    output: &mut String,
+    nodes: impl IntoIterator<Item = &'a PlanNode>,
+    indentation: &str,
+) {
+    for node in nodes {
+        output.push_str(indentation);
+        node.write_pretty(output, indentation);
+        output.push_str(",\n");
+    }
+}
+
+/// Writes the lines of the given text, each indented with `indentation`, without a trailing
+/// newline.
+fn write_indented(output: &mut String, text: &str, indentation: &str) {
+    for (i, line) in text.trim_end().lines().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        output.push_str(indentation);
+        output.push_str(line);
+    }
+}
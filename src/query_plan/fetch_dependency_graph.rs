@@ -1,18 +1,49 @@
 use crate::error::FederationError;
-use crate::query_graph::graph_path::{OpGraphPathContext, OpPath};
+use crate::query_graph::graph_path::{
+    OpGraphPathContext, OpGraphPathTrigger, OpPath, OpPathElement,
+};
 use crate::query_graph::path_tree::OpPathTree;
-use crate::query_graph::QueryGraph;
+use crate::query_graph::{QueryGraph, QueryGraphEdgeTransition, QueryGraphNodeType};
 use crate::query_plan::conditions::Conditions;
-use crate::query_plan::operation::NormalizedSelectionSet;
+use crate::query_plan::fetch_dependency_graph_processor::FetchDependencyGraphToQueryPlanProcessor;
+use crate::query_plan::operation::fragment_generation::QueryFragmentGenerator;
+use crate::query_plan::operation::normalized_field_selection::NormalizedField;
+use crate::query_plan::operation::normalized_inline_fragment_selection::{
+    NormalizedInlineFragment, NormalizedInlineFragmentData,
+};
+use crate::query_plan::operation::{
+    IdGenerator, NormalizedSelection, NormalizedSelectionSet, TYPENAME_FIELD,
+};
+use crate::query_plan::query_planner::QueryPlannerConfig;
+use crate::query_plan::{
+    FetchDataKeyRenamer, FetchDataRewrite, FetchDataValueSetter, QueryPlanCost,
+};
 use crate::query_plan::{FetchDataPathElement, QueryPathElement};
-use crate::query_plan::{FetchDataRewrite, QueryPlanCost};
-use crate::schema::position::{CompositeTypeDefinitionPosition, SchemaRootDefinitionKind};
+use crate::query_plan::{FetchNode, FlattenNode, PlanNode};
+use crate::schema::position::{
+    CompositeTypeDefinitionPosition, ObjectTypeDefinitionPosition, SchemaRootDefinitionKind,
+    SchemaRootDefinitionPosition, UnionTypeDefinitionPosition,
+};
 use crate::schema::ValidFederationSchema;
-use apollo_compiler::NodeStr;
+use crate::subgraph::spec::{ANY_SCALAR_NAME, ENTITIES_QUERY, ENTITY_UNION_NAME};
+use apollo_compiler::ast::{Argument, DirectiveList, Name, OperationType, Type, Value};
+use apollo_compiler::executable::{
+    Field, InlineFragment, Operation, Selection, SelectionSet, VariableDefinition,
+};
+use apollo_compiler::validation::Valid;
+use apollo_compiler::{name, ExecutableDocument, Node, NodeStr};
+use indexmap::map::Entry;
 use indexmap::{IndexMap, IndexSet};
+use petgraph::algo::{has_path_connecting, toposort};
 use petgraph::stable_graph::{NodeIndex, StableDiGraph};
+use petgraph::visit::{Dfs, EdgeRef};
+use petgraph::Direction;
+use std::ops::Deref;
 use std::sync::Arc;
 
+/// The variable (and `_entities` argument) holding the representations of entity fetches.
+const REPRESENTATIONS_VARIABLE: Name = name!("representations");
+
 /// Represents a subgraph fetch of a query plan.
 // PORT_NOTE: The JS codebase called this `FetchGroup`, but this naming didn't make it apparent that
 // this was a node in a fetch dependency graph, so we've renamed it accordingly.
@@ -38,6 +69,12 @@ pub(crate) struct FetchDependencyGraphNode {
     inputs: Option<Arc<FetchInputs>>,
     /// Input rewrites for query plan execution to perform prior to executing the fetch.
     input_rewrites: Arc<Vec<Arc<FetchDataRewrite>>>,
+    /// Output rewrites for query plan execution to perform on the data received from the fetch,
+    /// before it's merged into the response.
+    output_rewrites: Arc<Vec<Arc<FetchDataRewrite>>>,
+    /// Context rewrites for query plan execution to collect the values of contextual arguments
+    /// (i.e. arguments with `@fromContext`) from the fetch's inputs and their ancestors.
+    context_rewrites: Arc<Vec<Arc<FetchDataRewrite>>>,
     /// As query plan execution runs, it accumulates fetch data into a response object. This is the
     /// path at which to merge in the data for this particular fetch.
     merge_at: Option<Vec<FetchDataPathElement>>,
@@ -109,6 +146,9 @@ pub(crate) struct FetchDependencyGraph {
     /// The root nodes by subgraph name, representing the fetches against root operation types of
     /// the subgraphs.
     root_nodes_by_subgraph: IndexMap<NodeStr, NodeIndex>,
+    /// The root mutation fetches, in the order of the root fields they fetch. Unlike the root
+    /// nodes of queries, they're executed one after the other (see `new_serial_root_node()`).
+    serial_root_nodes: Vec<NodeIndex>,
     /// Tracks metadata about deferred blocks and their dependencies on one another.
     defer_tracking: Arc<DeferTracking>,
     /// The initial fetch ID generation (used when handling `@defer`).
@@ -120,6 +160,12 @@ pub(crate) struct FetchDependencyGraph {
     /// Whether this fetch dependency graph has undergone optimization (e.g. transitive reduction,
     /// removing empty/useless fetches, merging fetches with the same subgraph/path).
     is_optimized: bool,
+    /// The maximum serialized size of the selection set of a fetch, above which optimization
+    /// splits the fetch (see `split_oversized_nodes()`).
+    max_fetch_size: Option<usize>,
+    /// The generator of the selection IDs of the operation being planned, used for the inline
+    /// fragments of entity fetches.
+    id_generator: Arc<IdGenerator>,
 }
 
 // TODO: Write docstrings
@@ -148,14 +194,19 @@ pub(crate) struct FetchDependencyGraphPath {
     response_path: Vec<FetchDataPathElement>,
 }
 
-#[derive(Debug, Default)]
+/// The path of a fetch dependency graph node within the operation being planned, tracked while
+/// computing the nodes for a path tree.
+#[derive(Debug, Clone, Default)]
 pub(crate) struct FetchDependencyGraphNodePath {
-    full_path: Vec<QueryPathElement>,
-    path_in_group: Vec<QueryPathElement>,
+    /// The path from the root of the operation.
+    full_path: OpPath,
+    /// The path within the selection set of the fetch dependency graph node.
+    path_in_node: OpPath,
+    /// The path within the response data (i.e. the `merge_at` of a new entity fetch at this path).
     response_path: Vec<FetchDataPathElement>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct DeferContext {
     current_defer_ref: Option<NodeStr>,
     path_to_defer_parent: Vec<QueryPathElement>,
@@ -180,6 +231,8 @@ impl FetchDependencyGraph {
         federated_query_graph: Arc<QueryGraph>,
         root_type_for_defer: Option<CompositeTypeDefinitionPosition>,
         starting_id_generation: u64,
+        max_fetch_size: Option<usize>,
+        id_generator: Arc<IdGenerator>,
     ) -> Self {
         Self {
             defer_tracking: DeferTracking::empty(&supergraph_schema, root_type_for_defer),
@@ -187,10 +240,13 @@ impl FetchDependencyGraph {
             federated_query_graph,
             graph: Default::default(),
             root_nodes_by_subgraph: Default::default(),
+            serial_root_nodes: Default::default(),
             starting_id_generation,
             fetch_id_generation: starting_id_generation,
             is_reduced: false,
             is_optimized: false,
+            max_fetch_size,
+            id_generator,
         }
     }
 
@@ -221,6 +277,31 @@ impl FetchDependencyGraph {
         Ok(node)
     }
 
+    /// Returns the root mutation fetch for the next root field of a mutation, which is fetched from
+    /// the given subgraph. Root mutation fields must be executed serially, so consecutive fields
+    /// from the same subgraph share a fetch, but a field from another subgraph gets a new fetch.
+    /// That fetch is executed after the previous one and all the fetches it triggers (e.g. for
+    /// `@requires`) once the graph is optimized (see `enforce_serial_order()`).
+    pub(crate) fn new_serial_root_node(
+        &mut self,
+        subgraph_name: &NodeStr,
+        parent_type: CompositeTypeDefinitionPosition,
+    ) -> Result<NodeIndex, FederationError> {
+        if let Some(last) = self.serial_root_nodes.last() {
+            if self.node_weight(*last)?.subgraph_name == *subgraph_name {
+                return Ok(*last);
+            }
+        }
+        let node = self.new_node(
+            subgraph_name.clone(),
+            parent_type,
+            /* has_inputs: */ false,
+            SchemaRootDefinitionKind::Mutation,
+        )?;
+        self.serial_root_nodes.push(node);
+        Ok(node)
+    }
+
     pub(crate) fn new_node(
         &mut self,
         subgraph_name: NodeStr,
@@ -244,6 +325,8 @@ impl FetchDependencyGraph {
             inputs: has_inputs
                 .then(|| Arc::new(FetchInputs::empty(self.supergraph_schema.clone()))),
             input_rewrites: Default::default(),
+            output_rewrites: Default::default(),
+            context_rewrites: Default::default(),
             merge_at: None,
             id: None,
             defer_ref: None,
@@ -252,6 +335,955 @@ impl FetchDependencyGraph {
             is_known_useful: false,
         })))
     }
+
+    /// Creates a new entity fetch (against the `_entities` field of the given subgraph), whose
+    /// data is merged at `merge_at`.
+    pub(crate) fn new_key_node(
+        &mut self,
+        subgraph_name: NodeStr,
+        merge_at: Vec<FetchDataPathElement>,
+        defer_ref: Option<NodeStr>,
+    ) -> Result<NodeIndex, FederationError> {
+        let entity_type_position = UnionTypeDefinitionPosition {
+            type_name: ENTITY_UNION_NAME,
+        };
+        let subgraph_schema = self
+            .federated_query_graph
+            .schema_by_source(&subgraph_name)?;
+        // Ensure the subgraph has entities before creating a fetch against them.
+        entity_type_position.get(subgraph_schema.schema())?;
+        let node = self.new_node(
+            subgraph_name,
+            entity_type_position.into(),
+            /* has_inputs: */ true,
+            SchemaRootDefinitionKind::Query,
+        )?;
+        let node_weight = self.node_weight_mut(node)?;
+        node_weight.merge_at = Some(merge_at);
+        node_weight.defer_ref = defer_ref;
+        Ok(node)
+    }
+
+    pub(crate) fn node_weight(
+        &self,
+        node: NodeIndex,
+    ) -> Result<&Arc<FetchDependencyGraphNode>, FederationError> {
+        self.graph
+            .node_weight(node)
+            .ok_or_else(|| FederationError::internal("Node unexpectedly missing"))
+    }
+
+    fn node_weight_mut(
+        &mut self,
+        node: NodeIndex,
+    ) -> Result<&mut FetchDependencyGraphNode, FederationError> {
+        self.graph
+            .node_weight_mut(node)
+            .map(Arc::make_mut)
+            .ok_or_else(|| FederationError::internal("Node unexpectedly missing"))
+    }
+
+    pub(crate) fn is_parent_of(&self, parent: NodeIndex, child: NodeIndex) -> bool {
+        self.graph.find_edge(parent, child).is_some()
+    }
+
+    /// Records that `child` depends on `parent`, i.e. that `parent` must be executed before `child`.
+    /// The `path` is the path of `child` relative to `parent`, if known.
+    ///
+    /// Returns an error if this would create a cycle in the graph, as no valid query plan could be
+    /// generated from such a graph.
+    pub(crate) fn add_parent(
+        &mut self,
+        child: NodeIndex,
+        parent: NodeIndex,
+        path: Option<Arc<OpPath>>,
+    ) -> Result<(), FederationError> {
+        if self.is_parent_of(parent, child) {
+            return Ok(());
+        }
+        if child == parent || has_path_connecting(&self.graph, child, parent, None) {
+            return Err(FederationError::internal(format!(
+                "Cannot add fetch to \"{}\" as a parent of fetch to \"{}\": the fetches would depend on each other",
+                self.node_weight(parent)?.subgraph_name,
+                self.node_weight(child)?.subgraph_name,
+            )));
+        }
+        self.graph
+            .add_edge(parent, child, Arc::new(FetchDependencyGraphEdge { path }));
+        self.on_modification();
+        Ok(())
+    }
+
+    /// Plans the fetches for a field with `@requires` (a.k.a. the "required" fields):
+    /// 1. The required fields (along with the key used to get back to the subgraph of the field)
+    ///    are added at `path_in_parent` in the `parent` fetch, so that they're fetched before the
+    ///    field is.
+    /// 2. A new entity fetch against `subgraph_name` is created, with the required fields as
+    ///    inputs (along with any needed input rewrites), and is made to depend on `parent`.
+    ///
+    /// Returns the new entity fetch, in which the field with `@requires` should be added.
+    ///
+    /// `required_in_parent` must be expressed against the subgraph schema of `parent`, while
+    /// `inputs` must be expressed against the supergraph schema (usually it is the same selection
+    /// as `required_in_parent`, as computed by `compute_required_selection()`).
+    pub(crate) fn handle_requires(
+        &mut self,
+        parent: NodeIndex,
+        path_in_parent: Arc<OpPath>,
+        required_in_parent: NormalizedSelectionSet,
+        subgraph_name: NodeStr,
+        merge_at: Vec<FetchDataPathElement>,
+        inputs: &NormalizedSelectionSet,
+    ) -> Result<NodeIndex, FederationError> {
+        let defer_ref = self.node_weight(parent)?.defer_ref.clone();
+        self.node_weight_mut(parent)?
+            .add_at_path(&path_in_parent, Some(required_in_parent))?;
+        let input_rewrites = compute_input_rewrites_on_key_fetch(
+            &self.federated_query_graph,
+            &subgraph_name,
+            &NodeStr::new(inputs.type_position.type_name()),
+            &inputs.type_position,
+        )
+        .unwrap_or_default();
+        let node = self.new_key_node(subgraph_name, merge_at, defer_ref)?;
+        self.node_weight_mut(node)?
+            .add_inputs(inputs, input_rewrites)?;
+        self.add_parent(node, parent, Some(path_in_parent))?;
+        Ok(node)
+    }
+
+    /// Creates an entity fetch against `subgraph_name` for the entities at `parent_path` in the
+    /// `parent` fetch, using `key_in_parent` (expressed against the subgraph schema of `parent`,
+    /// and including the `__typename`) as inputs. The key is also added to `parent`, so that it's
+    /// fetched before the new fetch. `dest_type` is the type of the entities in `subgraph_name`,
+    /// and `context` holds the `@skip`/`@include` conditionals applying to those entities (see
+    /// `entity_fetch_initial_path()`).
+    ///
+    /// Returns the new fetch along with the path to use within it.
+    #[allow(clippy::too_many_arguments)]
+    fn new_key_node_at_path(
+        &mut self,
+        parent: NodeIndex,
+        parent_path: &FetchDependencyGraphNodePath,
+        key_in_parent: NormalizedSelectionSet,
+        subgraph_name: NodeStr,
+        dest_type: &CompositeTypeDefinitionPosition,
+        defer_ref: Option<NodeStr>,
+        context: &OpGraphPathContext,
+    ) -> Result<(NodeIndex, FetchDependencyGraphNodePath), FederationError> {
+        let input_type = self.supergraph_type(key_in_parent.type_position.type_name())?;
+        let inputs = key_in_parent.rebase_on(&input_type, &self.supergraph_schema)?;
+        let input_rewrites = compute_input_rewrites_on_key_fetch(
+            &self.federated_query_graph,
+            &subgraph_name,
+            &NodeStr::new(input_type.type_name()),
+            &self.supergraph_type(dest_type.type_name())?,
+        )
+        .unwrap_or_default();
+        let subgraph_schema = self
+            .federated_query_graph
+            .schema_by_source(&subgraph_name)?
+            .clone();
+        self.node_weight_mut(parent)?
+            .add_at_path(&parent_path.path_in_node, Some(key_in_parent))?;
+        let node =
+            self.new_key_node(subgraph_name, parent_path.response_path.clone(), defer_ref)?;
+        self.node_weight_mut(node)?
+            .add_inputs(&inputs, input_rewrites)?;
+        self.add_parent(
+            node,
+            parent,
+            Some(Arc::new(parent_path.path_in_node.clone())),
+        )?;
+        let path_in_node =
+            entity_fetch_initial_path(&subgraph_schema, dest_type, context, &self.id_generator)?;
+        Ok((node, parent_path.for_new_fetch(path_in_node)))
+    }
+
+    /// The supergraph type with the given name, which must be a composite type.
+    fn supergraph_type(
+        &self,
+        type_name: &Name,
+    ) -> Result<CompositeTypeDefinitionPosition, FederationError> {
+        self.supergraph_schema
+            .get_type(type_name.clone())?
+            .try_into()
+    }
+
+    fn parents_of(&self, node: NodeIndex) -> Vec<(NodeIndex, Option<Arc<OpPath>>)> {
+        self.graph
+            .edges_directed(node, Direction::Incoming)
+            .map(|edge| (edge.source(), edge.weight().path.clone()))
+            .collect()
+    }
+
+    fn children_of(&self, node: NodeIndex) -> Vec<(NodeIndex, Option<Arc<OpPath>>)> {
+        self.graph
+            .edges_directed(node, Direction::Outgoing)
+            .map(|edge| (edge.target(), edge.weight().path.clone()))
+            .collect()
+    }
+
+    fn single_parent_of(&self, node: NodeIndex) -> Option<NodeIndex> {
+        let mut parents = self.graph.neighbors_directed(node, Direction::Incoming);
+        let parent = parents.next()?;
+        parents.next().is_none().then_some(parent)
+    }
+
+    fn remove_node(&mut self, node: NodeIndex) {
+        self.graph.remove_node(node);
+        self.root_nodes_by_subgraph.retain(|_, root| *root != node);
+        self.serial_root_nodes.retain(|root| *root != node);
+        self.on_modification();
+    }
+
+    /// Reduces and optimizes the graph, by (in order):
+    /// 1. Ordering root mutation fetches (see `enforce_serial_order()`).
+    /// 2. Removing fetches with nothing to fetch.
+    /// 3. Merging fetches into their parent when both are against the same subgraph.
+    /// 4. Merging sibling fetches against the same subgraph and at the same path.
+    ///
+    /// The graph is transitively reduced before and after these passes.
+    pub(crate) fn optimize(&mut self) -> Result<(), FederationError> {
+        if self.is_optimized {
+            return Ok(());
+        }
+        self.enforce_serial_order()?;
+        self.reduce();
+        self.remove_empty_nodes()?;
+        self.merge_children_with_same_subgraph()?;
+        self.merge_siblings()?;
+        self.split_oversized_nodes()?;
+        self.reduce();
+        self.is_optimized = true;
+        Ok(())
+    }
+
+    /// The number of fetches in the graph.
+    pub(crate) fn fetch_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    /// The number of fetches in the longest chain of fetches of the graph, i.e. how many fetches
+    /// have to be executed one after the other in the worst case.
+    pub(crate) fn depth(&self) -> Result<usize, FederationError> {
+        let sorted = toposort(&self.graph, None).map_err(|_| {
+            FederationError::internal("Fetch dependency graph unexpectedly contains a cycle")
+        })?;
+        let mut depths = IndexMap::<NodeIndex, usize>::new();
+        let mut max_depth = 0;
+        for node in sorted {
+            let depth = 1 + self
+                .graph
+                .neighbors_directed(node, Direction::Incoming)
+                .filter_map(|parent| depths.get(&parent).copied())
+                .max()
+                .unwrap_or(0);
+            max_depth = max_depth.max(depth);
+            depths.insert(node, depth);
+        }
+        Ok(max_depth)
+    }
+
+    /// Processes the graph into a plan node, optimizing it first. Fetches are executed in stages,
+    /// where a stage runs in parallel the fetches whose parents all ran in previous stages, and
+    /// stages run in sequence. Since `enforce_serial_order()` makes each root mutation fetch depend
+    /// on everything triggered by the previous one, root mutation fetches each get their own stage,
+    /// and so are executed strictly one after the other, in the order of their fields.
+    // PORT_NOTE: The JS codebase starts processing the children of a fetch as soon as all their
+    // parents are processed, instead of waiting for the whole stage. The resulting plans are the
+    // same for mutations, whose root fetches (and what they trigger) never share a stage.
+    pub(crate) fn process(
+        &mut self,
+        processor: &FetchDependencyGraphToQueryPlanProcessor,
+        variable_definitions: &[Node<VariableDefinition>],
+        operation_name: Option<Name>,
+    ) -> Result<Option<PlanNode>, FederationError> {
+        self.optimize()?;
+        let sorted = toposort(&self.graph, None).map_err(|_| {
+            FederationError::internal("Fetch dependency graph unexpectedly contains a cycle")
+        })?;
+        let mut stages = IndexMap::<NodeIndex, usize>::new();
+        let mut nodes_by_stage = Vec::<Vec<NodeIndex>>::new();
+        for node in sorted {
+            let stage = self
+                .graph
+                .neighbors_directed(node, Direction::Incoming)
+                .filter_map(|parent| stages.get(&parent).map(|stage| stage + 1))
+                .max()
+                .unwrap_or(0);
+            stages.insert(node, stage);
+            if nodes_by_stage.len() <= stage {
+                nodes_by_stage.resize_with(stage + 1, Vec::new);
+            }
+            nodes_by_stage[stage].push(node);
+        }
+        let mut sequence = Vec::new();
+        for mut nodes in nodes_by_stage {
+            // Node indices follow the creation order of fetches, which keeps plans deterministic.
+            nodes.sort();
+            let mut parallel = Vec::new();
+            for node in nodes {
+                parallel.extend(processor.on_node(
+                    self.node_weight(node)?,
+                    &Conditions::Boolean(true),
+                    variable_definitions,
+                    operation_name.clone(),
+                )?);
+            }
+            sequence.extend(processor.reduce_parallel(parallel));
+        }
+        Ok(processor.reduce_sequence(sequence))
+    }
+
+    /// The estimated number of fields fetched by all the fetches of the graph, where the fields
+    /// selected within a list field are counted `list_size_estimate` times.
+    pub(crate) fn estimated_response_size(
+        &mut self,
+        list_size_estimate: QueryPlanCost,
+    ) -> Result<QueryPlanCost, FederationError> {
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        let mut size = 0;
+        for node in nodes {
+            size += match self.node_weight(node)?.cached_cost {
+                Some(cost) => cost,
+                None => self
+                    .node_weight_mut(node)?
+                    .estimated_response_size(list_size_estimate)?,
+            };
+        }
+        Ok(size)
+    }
+
+    /// Makes each root mutation fetch depend on the previous one and on all the fetches depending
+    /// on it, so that a root mutation field is only executed once the previous ones have been
+    /// fully resolved (including any `@requires` on their results), as GraphQL requires.
+    fn enforce_serial_order(&mut self) -> Result<(), FederationError> {
+        let serial_root_nodes = self.serial_root_nodes.clone();
+        for pair in serial_root_nodes.windows(2) {
+            let [previous, next] = [pair[0], pair[1]];
+            let mut descendants = Dfs::new(&self.graph, previous);
+            let mut to_order = Vec::new();
+            while let Some(node) = descendants.next(&self.graph) {
+                // Fetches already depending on the next root fetch can't also precede it.
+                if !has_path_connecting(&self.graph, next, node, None) {
+                    to_order.push(node);
+                }
+            }
+            for node in to_order {
+                self.add_parent(next, node, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Performs a transitive reduction of the graph, i.e. removes any edge from a parent to a child
+    /// when that child is also reachable through another child of the parent. Such edges don't add
+    /// any dependency, but removing them lets the optimizations see the closest parents of a fetch.
+    pub(crate) fn reduce(&mut self) {
+        if self.is_reduced {
+            return;
+        }
+        let edges = self.graph.edge_indices().collect::<Vec<_>>();
+        for edge in edges {
+            let Some((parent, child)) = self.graph.edge_endpoints(edge) else {
+                continue;
+            };
+            let is_redundant = self
+                .graph
+                .neighbors_directed(parent, Direction::Outgoing)
+                .any(|other| {
+                    other != child && has_path_connecting(&self.graph, other, child, None)
+                });
+            if is_redundant {
+                self.graph.remove_edge(edge);
+            }
+        }
+        self.is_reduced = true;
+    }
+
+    /// Removes the fetches whose selection set is empty (unless it must be preserved), making their
+    /// children depend on their parents instead.
+    fn remove_empty_nodes(&mut self) -> Result<(), FederationError> {
+        let empty_nodes = self
+            .graph
+            .node_indices()
+            .filter(|node| {
+                let node_weight = &self.graph[*node];
+                !node_weight.must_preserve_selection_set
+                    && node_weight
+                        .selection_set
+                        .selection_set
+                        .selections
+                        .is_empty()
+            })
+            .collect::<Vec<_>>();
+        for node in empty_nodes {
+            let parents = self.parents_of(node);
+            let children = self.children_of(node);
+            self.remove_node(node);
+            for (parent, _) in &parents {
+                for (child, _) in &children {
+                    self.add_parent(*child, *parent, None)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges each fetch into its parent when it only depends on that parent, both are against the
+    /// same subgraph, and the path of the fetch in its parent is known.
+    fn merge_children_with_same_subgraph(&mut self) -> Result<(), FederationError> {
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        for child in nodes {
+            if !self.graph.contains_node(child) {
+                continue;
+            }
+            let parents = self.parents_of(child);
+            let [(parent, Some(path))] = parents.as_slice() else {
+                continue;
+            };
+            let parent_weight = self.node_weight(*parent)?;
+            let child_weight = self.node_weight(child)?;
+            if parent_weight.subgraph_name != child_weight.subgraph_name
+                || parent_weight.defer_ref != child_weight.defer_ref
+                || !child_weight.output_rewrites.is_empty()
+                || !child_weight.context_rewrites.is_empty()
+            {
+                continue;
+            }
+            self.merge_child_in(*parent, child, path)?;
+        }
+        Ok(())
+    }
+
+    /// Merges `child` into `parent` at `path`, if the selections of `child` can be expressed at
+    /// that path. The children of `child` become children of `parent`.
+    fn merge_child_in(
+        &mut self,
+        parent: NodeIndex,
+        child: NodeIndex,
+        path: &OpPath,
+    ) -> Result<(), FederationError> {
+        let parent_type = self.node_weight(parent)?.parent_type.clone();
+        let target_type = type_at_path(&parent_type, path)?;
+        let child_selection_set = self.node_weight(child)?.selection_set.selection_set.clone();
+        let Some(selection_set) = selection_set_at_type(&child_selection_set, &target_type)? else {
+            return Ok(());
+        };
+        let grandchildren = self.children_of(child);
+        self.node_weight_mut(parent)?
+            .add_at_path(path, Some(selection_set))?;
+        self.remove_node(child);
+        for (grandchild, path_in_child) in grandchildren {
+            let path_in_parent = path_in_child.and_then(|path_in_child| {
+                path_after_merge(
+                    path,
+                    &path_in_child,
+                    &child_selection_set.type_position,
+                    &target_type,
+                )
+            });
+            self.add_parent(grandchild, parent, path_in_parent)?;
+        }
+        Ok(())
+    }
+
+    /// Merges fetches that have the same single parent, are against the same subgraph, and are
+    /// merged at the same path, as they can be made as a single fetch.
+    fn merge_siblings(&mut self) -> Result<(), FederationError> {
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        for node in nodes {
+            if !self.graph.contains_node(node) {
+                continue;
+            }
+            let Some(parent) = self.single_parent_of(node) else {
+                continue;
+            };
+            for (sibling, _) in self.children_of(parent) {
+                if sibling == node || self.single_parent_of(sibling) != Some(parent) {
+                    continue;
+                }
+                let node_weight = self.node_weight(node)?;
+                let sibling_weight = self.node_weight(sibling)?;
+                if node_weight.subgraph_name == sibling_weight.subgraph_name
+                    && node_weight.merge_at == sibling_weight.merge_at
+                    && node_weight.defer_ref == sibling_weight.defer_ref
+                    && node_weight.root_kind == sibling_weight.root_kind
+                    && node_weight.parent_type == sibling_weight.parent_type
+                {
+                    self.merge_sibling_in(node, sibling)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits each fetch whose selection set serializes to more than `max_fetch_size` bytes into
+    /// fetches over disjoint parts of that selection set (see `NormalizedSelectionSet::split()`),
+    /// as some subgraph servers reject very large queries. The new fetches have the same parents
+    /// as the original one, and its children depend on all of them, since the data they need
+    /// could be fetched by any part.
+    ///
+    /// Root mutation fetches aren't split, as their fields must be executed in order in a single
+    /// operation, and neither are fetches with an ID, which may be referenced by deferred fetches.
+    fn split_oversized_nodes(&mut self) -> Result<(), FederationError> {
+        let Some(max_fetch_size) = self.max_fetch_size else {
+            return Ok(());
+        };
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        for node in nodes {
+            let node_weight = self.node_weight(node)?;
+            if (node_weight.root_kind == SchemaRootDefinitionKind::Mutation
+                && !node_weight.is_entity_fetch)
+                || node_weight.id.is_some()
+            {
+                continue;
+            }
+            let selection_set = node_weight.selection_set.selection_set.clone();
+            if selection_set.serialized_size()? <= max_fetch_size {
+                continue;
+            }
+            let mut parts = selection_set.split(max_fetch_size)?.into_iter();
+            let Some(first_part) = parts.next() else {
+                continue;
+            };
+            let parents = self.parents_of(node);
+            let children = self.children_of(node);
+            let node_weight = self.node_weight_mut(node)?;
+            node_weight.set_selection_set(first_part)?;
+            let node_weight = node_weight.clone();
+            for part in parts {
+                let mut part_weight = node_weight.clone();
+                part_weight.set_selection_set(part)?;
+                let part_node = self.graph.add_node(Arc::new(part_weight));
+                for (parent, path) in &parents {
+                    self.add_parent(part_node, *parent, path.clone())?;
+                }
+                for (child, _) in &children {
+                    self.add_parent(*child, part_node, None)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges `sibling` into `node`, which must have the same parent type. The children of
+    /// `sibling` become children of `node`.
+    fn merge_sibling_in(
+        &mut self,
+        node: NodeIndex,
+        sibling: NodeIndex,
+    ) -> Result<(), FederationError> {
+        let sibling_weight = self.node_weight(sibling)?.clone();
+        let children = self.children_of(sibling);
+        let node_weight = self.node_weight_mut(node)?;
+        node_weight.add_at_path(
+            &OpPath::default(),
+            Some(sibling_weight.selection_set.selection_set.as_ref().clone()),
+        )?;
+        if let Some(sibling_inputs) = &sibling_weight.inputs {
+            for selection_set in sibling_inputs.selection_sets_per_parent_type.values() {
+                node_weight.add_inputs(selection_set, [])?;
+            }
+        }
+        Arc::make_mut(&mut node_weight.input_rewrites)
+            .extend(sibling_weight.input_rewrites.iter().cloned());
+        Arc::make_mut(&mut node_weight.output_rewrites)
+            .extend(sibling_weight.output_rewrites.iter().cloned());
+        Arc::make_mut(&mut node_weight.context_rewrites)
+            .extend(sibling_weight.context_rewrites.iter().cloned());
+        node_weight.must_preserve_selection_set |= sibling_weight.must_preserve_selection_set;
+        self.remove_node(sibling);
+        for (child, path) in children {
+            self.add_parent(child, node, path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the selection that must be fetched before a field with `@requires` can be fetched from
+/// its subgraph: the `__typename` of the entity, the key used to get back to the entity in that
+/// subgraph, and the `@requires` conditions themselves. All of them must be for the same type.
+pub(crate) fn compute_required_selection(
+    key_conditions: &NormalizedSelectionSet,
+    requires_conditions: &NormalizedSelectionSet,
+) -> Result<NormalizedSelectionSet, FederationError> {
+    let mut typename_selection_set = NormalizedSelectionSet::empty(
+        key_conditions.schema.clone(),
+        key_conditions.type_position.clone(),
+    );
+    typename_selection_set.add_typename_field()?;
+    NormalizedSelectionSet::merge_all(&[
+        &typename_selection_set,
+        key_conditions,
+        requires_conditions,
+    ])
+}
+
+impl FetchDependencyGraphNode {
+    /// Adds the given selection set at the given path of this fetch's selection set (or only the
+    /// path, if no selection set is given).
+    pub(crate) fn add_at_path(
+        &mut self,
+        path: &OpPath,
+        selection_set: Option<NormalizedSelectionSet>,
+    ) -> Result<(), FederationError> {
+        Arc::make_mut(&mut self.selection_set.selection_set).add_at_path(path, selection_set)?;
+        self.selection_set.conditions = self.selection_set.selection_set.conditions()?;
+        self.cached_cost = None;
+        Ok(())
+    }
+
+    /// Replaces the selection set of this fetch.
+    fn set_selection_set(
+        &mut self,
+        selection_set: NormalizedSelectionSet,
+    ) -> Result<(), FederationError> {
+        self.selection_set = FetchSelectionSet {
+            conditions: selection_set.conditions()?,
+            selection_set: Arc::new(selection_set),
+        };
+        self.cached_cost = None;
+        Ok(())
+    }
+
+    /// The estimated number of fields fetched by this fetch (see
+    /// `FetchDependencyGraph::estimated_response_size()`). This is cached until the selection set
+    /// of the fetch changes.
+    fn estimated_response_size(
+        &mut self,
+        list_size_estimate: QueryPlanCost,
+    ) -> Result<QueryPlanCost, FederationError> {
+        if let Some(cost) = self.cached_cost {
+            return Ok(cost);
+        }
+        let cost =
+            estimated_selection_set_size(&self.selection_set.selection_set, list_size_estimate)?;
+        self.cached_cost = Some(cost);
+        Ok(cost)
+    }
+
+    /// Adds the given selection set to the inputs of this fetch, which must be an entity fetch.
+    pub(crate) fn add_inputs(
+        &mut self,
+        selection_set: &NormalizedSelectionSet,
+        rewrites: impl IntoIterator<Item = FetchDataRewrite>,
+    ) -> Result<(), FederationError> {
+        let Some(inputs) = &mut self.inputs else {
+            return Err(FederationError::internal(format!(
+                "Cannot add inputs to non-entity fetch to \"{}\"",
+                self.subgraph_name,
+            )));
+        };
+        Arc::make_mut(inputs).add(selection_set)?;
+        self.add_input_rewrites(rewrites);
+        Ok(())
+    }
+
+    /// The conditions determining whether this fetch should be executed.
+    pub(crate) fn conditions(&self) -> &Conditions {
+        &self.selection_set.conditions
+    }
+
+    /// Converts this fetch into the plan node executing it, i.e. a fetch node wrapped in a flatten
+    /// node at `merge_at` (unless this is a top-level fetch). Returns `None` if there's nothing to
+    /// fetch.
+    ///
+    /// Entity fetches query the `_entities` field of the subgraph with a `$representations`
+    /// variable, which executors build from the data selected by `requires` (one inline fragment
+    /// per input type). The fetch operation only defines the variables of `variable_definitions`
+    /// it uses. With `config.generate_query_fragments`, its repeated inline fragments are factored
+    /// into generated named fragments (see `QueryFragmentGenerator`), and with
+    /// `config.subgraph_graphql_validation`, it is validated against the subgraph schema (see
+    /// `validate_fetch_operation()`).
+    pub(crate) fn to_plan_node(
+        &self,
+        config: &QueryPlannerConfig,
+        variable_definitions: &[Node<VariableDefinition>],
+        operation_name: Option<Name>,
+    ) -> Result<Option<PlanNode>, FederationError> {
+        let normalized_selection_set = &self.selection_set.selection_set;
+        if normalized_selection_set.selections.is_empty() {
+            return Ok(None);
+        }
+        let subgraph_schema = &normalized_selection_set.schema;
+        let mut used_variables = IndexSet::new();
+        normalized_selection_set.collect_variables(&mut used_variables);
+        let mut variables = variable_definitions
+            .iter()
+            .filter(|definition| used_variables.contains(&definition.name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let variable_usages = variables
+            .iter()
+            .map(|definition| definition.name.clone())
+            .collect();
+
+        // Type conditions can become redundant once in the subgraph, since its abstract types may
+        // have fewer possible runtime types than in the supergraph.
+        let mut simplified_selection_set = normalized_selection_set.simplify_type_conditions()?;
+        let mut fragments = IndexMap::new();
+        if config.generate_query_fragments {
+            let mut generator = QueryFragmentGenerator::new(Default::default(), Default::default());
+            simplified_selection_set = generator.minimize(&simplified_selection_set)?;
+            fragments = generator.into_fragments();
+        }
+        let mut selection_set: SelectionSet = (&simplified_selection_set).try_into()?;
+        let mut requires = Vec::new();
+        if let Some(inputs) = &self.inputs {
+            variables.insert(
+                0,
+                Node::new(VariableDefinition {
+                    name: REPRESENTATIONS_VARIABLE,
+                    ty: Node::new(Type::NonNullList(Box::new(Type::NonNullNamed(
+                        ANY_SCALAR_NAME,
+                    )))),
+                    default_value: None,
+                    directives: Default::default(),
+                }),
+            );
+            let query_type_name = SchemaRootDefinitionPosition {
+                root_kind: SchemaRootDefinitionKind::Query,
+            }
+            .get(subgraph_schema.schema())?
+            .name
+            .clone();
+            let entities_field = Field {
+                definition: ObjectTypeDefinitionPosition {
+                    type_name: query_type_name.clone(),
+                }
+                .field(ENTITIES_QUERY)
+                .get(subgraph_schema.schema())?
+                .node
+                .clone(),
+                alias: None,
+                name: ENTITIES_QUERY,
+                arguments: vec![Node::new(Argument {
+                    name: REPRESENTATIONS_VARIABLE,
+                    value: Node::new(Value::Variable(REPRESENTATIONS_VARIABLE)),
+                })],
+                directives: Default::default(),
+                selection_set,
+            };
+            selection_set = SelectionSet {
+                ty: query_type_name,
+                selections: vec![Selection::Field(Node::new(entities_field))],
+            };
+            for (type_position, inputs_selection_set) in &inputs.selection_sets_per_parent_type {
+                requires.push(Selection::InlineFragment(Node::new(InlineFragment {
+                    type_condition: Some(type_position.type_name().clone()),
+                    directives: Default::default(),
+                    selection_set: inputs_selection_set.as_ref().try_into()?,
+                })));
+            }
+        }
+
+        let operation_kind: OperationType = self.root_kind.into();
+        let operation = Node::new(Operation {
+            operation_type: operation_kind,
+            name: operation_name.clone(),
+            variables,
+            directives: Default::default(),
+            selection_set,
+        });
+        let mut operation_document = ExecutableDocument::new();
+        for (name, fragment) in &fragments {
+            operation_document
+                .fragments
+                .insert(name.clone(), Node::new(fragment.deref().try_into()?));
+        }
+        match &operation_name {
+            Some(name) => {
+                operation_document
+                    .named_operations
+                    .insert(name.clone(), operation);
+            }
+            None => operation_document.anonymous_operation = Some(operation),
+        }
+        let fetch_node = PlanNode::Fetch(Arc::new(FetchNode {
+            subgraph_name: self.subgraph_name.clone(),
+            id: self.id.map(|id| NodeStr::new(&id.to_string())),
+            // TODO: Set this once subgraph @defer support is ported.
+            has_defers: None,
+            variable_usages,
+            requires,
+            operation_document: validate_fetch_operation(
+                operation_document,
+                &self.subgraph_name,
+                subgraph_schema,
+                config.subgraph_graphql_validation,
+            )?,
+            operation_name: operation_name.map(|name| NodeStr::new(&name)),
+            operation_kind,
+            input_rewrites: self.input_rewrites.iter().map(|r| (**r).clone()).collect(),
+            output_rewrites: self.output_rewrites.iter().map(|r| (**r).clone()).collect(),
+            context_rewrites: self
+                .context_rewrites
+                .iter()
+                .map(|r| (**r).clone())
+                .collect(),
+            authorization: None,
+        }));
+        Ok(Some(match &self.merge_at {
+            Some(merge_at) => PlanNode::Flatten(Arc::new(FlattenNode {
+                path: merge_at.clone(),
+                node: fetch_node,
+            })),
+            None => fetch_node,
+        }))
+    }
+
+    pub(crate) fn add_input_rewrites(
+        &mut self,
+        rewrites: impl IntoIterator<Item = FetchDataRewrite>,
+    ) {
+        Arc::make_mut(&mut self.input_rewrites).extend(rewrites.into_iter().map(Arc::new));
+    }
+
+    pub(crate) fn add_output_rewrites(
+        &mut self,
+        rewrites: impl IntoIterator<Item = FetchDataRewrite>,
+    ) {
+        Arc::make_mut(&mut self.output_rewrites).extend(rewrites.into_iter().map(Arc::new));
+    }
+
+    pub(crate) fn add_context_rewrites(
+        &mut self,
+        rewrites: impl IntoIterator<Item = FetchDataRewrite>,
+    ) {
+        Arc::make_mut(&mut self.context_rewrites).extend(rewrites.into_iter().map(Arc::new));
+    }
+}
+
+/// The name of the variable passing the value of a contextual argument (i.e. an argument with
+/// `@fromContext`) to a subgraph fetch, given the index of the subgraph and of the argument's
+/// usage within the plan.
+pub(crate) fn contextual_argument_variable_name(
+    subgraph_index: usize,
+    usage_index: usize,
+) -> NodeStr {
+    NodeStr::new(&format!(
+        "contextualArgument_{subgraph_index}_{usage_index}"
+    ))
+}
+
+/// Computes the rewrite collecting the value of a contextual argument for a fetch, given how many
+/// levels above the fetch's inputs the context is set (at a type named `context_type_name`), and
+/// the path of the argument's value within the context's selection. The value is renamed to the
+/// variable it's passed as (see `contextual_argument_variable_name()`).
+pub(crate) fn compute_context_rewrite(
+    levels_up: usize,
+    context_type_name: &Name,
+    selection_path: impl IntoIterator<Item = NodeStr>,
+    variable_name: NodeStr,
+) -> FetchDataRewrite {
+    let mut path = vec![FetchDataPathElement::Parent; levels_up];
+    path.push(FetchDataPathElement::TypenameEquals(NodeStr::new(
+        context_type_name.as_str(),
+    )));
+    path.extend(selection_path.into_iter().map(FetchDataPathElement::Key));
+    FetchDataRewrite::KeyRenamer(FetchDataKeyRenamer {
+        path,
+        rename_key_to: variable_name,
+    })
+}
+
+/// When we send an entity fetch to a subgraph, the `__typename` of its inputs must essentially
+/// match `dest_type` so that the proper reference resolver is called. If `dest_type` is a "normal"
+/// object type, that's already the case, but if `dest_type` is an interface in the supergraph (so
+/// either an interface or an `@interfaceObject` in the subgraph), then the input objects will have
+/// the `__typename` of their concrete implementation type, which we need to rewrite.
+pub(crate) fn compute_input_rewrites_on_key_fetch(
+    federated_query_graph: &QueryGraph,
+    subgraph_name: &str,
+    input_type_name: &NodeStr,
+    dest_type: &CompositeTypeDefinitionPosition,
+) -> Option<Vec<FetchDataRewrite>> {
+    if !matches!(dest_type, CompositeTypeDefinitionPosition::Interface(_))
+        && !federated_query_graph.is_interface_object_type(subgraph_name, dest_type.type_name())
+    {
+        return None;
+    }
+    Some(vec![FetchDataRewrite::ValueSetter(FetchDataValueSetter {
+        path: vec![
+            FetchDataPathElement::TypenameEquals(input_type_name.clone()),
+            FetchDataPathElement::Key(NodeStr::new(TYPENAME_FIELD.as_str())),
+        ],
+        set_value_to: dest_type.type_name().as_str().into(),
+    })])
+}
+
+/// An `@interfaceObject` type has no knowledge of the implementations of its interface, so any
+/// `__typename` it returns is the interface name. When the query planner has "fake downcast" such
+/// a type to one of its implementations (using an `InterfaceObjectFakeDownCast` edge), then it
+/// knows which implementation the data at that path is for, and so it rewrites the `__typename`
+/// received from the fetch to that concrete type.
+///
+/// The `path` is the path to the downcast objects in the fetch's response data. The `__typename`
+/// is rewritten under each of the given aliases as well (see `typename_aliases()`).
+pub(crate) fn compute_output_rewrites_on_interface_object_fake_downcast(
+    path: &[FetchDataPathElement],
+    interface_type_name: &NodeStr,
+    implementation_type_name: &NodeStr,
+    typename_aliases: &IndexSet<Name>,
+) -> Vec<FetchDataRewrite> {
+    std::iter::once(&TYPENAME_FIELD)
+        .chain(typename_aliases)
+        .map(|response_name| {
+            let mut rewrite_path = path.to_vec();
+            rewrite_path.push(FetchDataPathElement::TypenameEquals(
+                interface_type_name.clone(),
+            ));
+            rewrite_path.push(FetchDataPathElement::Key(NodeStr::new(
+                response_name.as_str(),
+            )));
+            FetchDataRewrite::ValueSetter(FetchDataValueSetter {
+                path: rewrite_path,
+                set_value_to: implementation_type_name.as_str().into(),
+            })
+        })
+        .collect()
+}
+
+/// The aliases of the `__typename` fields selected at the root of the given path tree, including
+/// within its inline fragments (which apply to the same objects).
+fn typename_aliases(tree: &OpPathTree, aliases: &mut IndexSet<Name>) {
+    fn collect_from_selection_set(
+        selection_set: &NormalizedSelectionSet,
+        aliases: &mut IndexSet<Name>,
+    ) {
+        for selection in selection_set.selections.values() {
+            match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    let data = field_selection.field.data();
+                    if data.field_position.is_introspection_typename_field() {
+                        aliases.extend(data.alias.clone());
+                    }
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    collect_from_selection_set(&inline_fragment_selection.selection_set, aliases);
+                }
+                NormalizedSelection::FragmentSpread(_) => {}
+            }
+        }
+    }
+
+    for selection_set in &tree.local_selection_sets {
+        collect_from_selection_set(selection_set, aliases);
+    }
+    for child in &tree.childs {
+        match child.trigger.as_ref() {
+            OpGraphPathTrigger::Field(field) => {
+                let data = field.data();
+                if data.field_position.is_introspection_typename_field() {
+                    aliases.extend(data.alias.clone());
+                }
+            }
+            OpGraphPathTrigger::InlineFragment(_) => typename_aliases(&child.tree, aliases),
+            OpGraphPathTrigger::Context(_) => {}
+        }
+    }
 }
 
 impl FetchSelectionSet {
@@ -275,6 +1307,30 @@ impl FetchInputs {
             supergraph_schema,
         }
     }
+
+    /// Adds the given selection set to the inputs for its type, merging it with any existing inputs
+    /// for that type.
+    pub(crate) fn add(
+        &mut self,
+        selection_set: &NormalizedSelectionSet,
+    ) -> Result<(), FederationError> {
+        if selection_set.schema != self.supergraph_schema {
+            return Err(FederationError::internal(
+                "Inputs must be expressed against the supergraph schema",
+            ));
+        }
+        match self
+            .selection_sets_per_parent_type
+            .entry(selection_set.type_position.clone())
+        {
+            Entry::Occupied(entry) => Arc::make_mut(entry.into_mut())
+                .merge_into(std::iter::once(selection_set.clone()))?,
+            Entry::Vacant(entry) => {
+                entry.insert(Arc::new(selection_set.clone()));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl DeferTracking {
@@ -296,14 +1352,2019 @@ impl DeferTracking {
     }
 }
 
+impl FetchDependencyGraphNodePath {
+    fn add(&self, element: Arc<OpPathElement>) -> Result<Self, FederationError> {
+        let mut response_path = self.response_path.clone();
+        if let OpPathElement::Field(field) = element.as_ref() {
+            response_path.extend(field_response_path(field)?);
+        }
+        Ok(Self {
+            full_path: self.full_path.with_pushed(element.clone()),
+            path_in_node: self.path_in_node.with_pushed(element),
+            response_path,
+        })
+    }
+
+    fn for_new_fetch(&self, path_in_node: OpPath) -> Self {
+        Self {
+            full_path: self.full_path.clone(),
+            path_in_node,
+            response_path: self.response_path.clone(),
+        }
+    }
+}
+
+/// Marks the given fetch operation document for the given subgraph as valid. With `validate`, it
+/// is validated against the subgraph schema first, and an invalid document (which is a query
+/// planner bug) fails planning with an error showing the document, instead of failing in the
+/// subgraph at execution time.
+pub(crate) fn validate_fetch_operation(
+    operation_document: ExecutableDocument,
+    subgraph_name: &str,
+    subgraph_schema: &ValidFederationSchema,
+    validate: bool,
+) -> Result<Valid<ExecutableDocument>, FederationError> {
+    if !validate {
+        return Ok(Valid::assume_valid(operation_document));
+    }
+    operation_document
+        .validate(subgraph_schema.schema())
+        .map_err(|invalid| {
+            FederationError::internal(format!(
+                "Query planning produced an invalid operation for subgraph \"{}\": {}\n\nOperation:\n{}",
+                subgraph_name,
+                invalid.errors.to_string().trim_end(),
+                invalid.partial,
+            ))
+        })
+}
+
+/// The path in response data of the given field, i.e. its response name followed by an index
+/// wildcard for each list wrapping its type.
+pub(crate) fn field_response_path(
+    field: &NormalizedField,
+) -> Result<Vec<FetchDataPathElement>, FederationError> {
+    let data = field.data();
+    let mut path = vec![FetchDataPathElement::Key(NodeStr::new(
+        data.response_name().as_str(),
+    ))];
+    let mut ty = &data.field_position.get(data.schema.schema())?.ty;
+    while ty.is_list() {
+        path.push(FetchDataPathElement::AnyIndex);
+        ty = ty.item_type();
+    }
+    Ok(path)
+}
+
+/// The path in response data of the given operation path.
+fn op_path_response_path(path: &OpPath) -> Result<Vec<FetchDataPathElement>, FederationError> {
+    let mut response_path = vec![];
+    for element in path.iter() {
+        if let OpPathElement::Field(field) = element.as_ref() {
+            response_path.extend(field_response_path(field)?);
+        }
+    }
+    Ok(response_path)
+}
+
+/// The path at which the selections of an entity fetch for `type_position` start, i.e. an inline
+/// fragment on that type within the `_entities` field.
+///
+/// The `@skip`/`@include` conditionals of the given context (i.e. those applied on the way to the
+/// entities in the parent fetch) are applied to that fragment, so that the fetch's conditions
+/// reflect them. Since a fragment can't have the same directive twice, each conditional after the
+/// first gets its own fragment on the same type.
+fn entity_fetch_initial_path(
+    subgraph_schema: &ValidFederationSchema,
+    type_position: &CompositeTypeDefinitionPosition,
+    context: &OpGraphPathContext,
+    id_generator: &IdGenerator,
+) -> Result<OpPath, FederationError> {
+    let type_condition_position: CompositeTypeDefinitionPosition = subgraph_schema
+        .get_type(type_position.type_name().clone())?
+        .try_into()?;
+    let fragment = |parent_type_position, directives| {
+        Arc::new(OpPathElement::InlineFragment(
+            NormalizedInlineFragment::new(NormalizedInlineFragmentData {
+                schema: subgraph_schema.clone(),
+                parent_type_position,
+                type_condition_position: Some(type_condition_position.clone()),
+                directives: Arc::new(directives),
+                selection_id: id_generator.next_id(),
+            }),
+        ))
+    };
+    let mut directives = context
+        .conditionals()
+        .iter()
+        .map(|conditional| DirectiveList(vec![Node::new(conditional.to_directive())]));
+    let mut path = vec![fragment(
+        UnionTypeDefinitionPosition {
+            type_name: ENTITY_UNION_NAME,
+        }
+        .into(),
+        directives.next().unwrap_or_default(),
+    )];
+    for directives in directives {
+        path.push(fragment(type_condition_position.clone(), directives));
+    }
+    Ok(OpPath::from(path))
+}
+
+/// The type of the selection set at the end of the given path, which starts at `parent_type`.
+fn type_at_path(
+    parent_type: &CompositeTypeDefinitionPosition,
+    path: &OpPath,
+) -> Result<CompositeTypeDefinitionPosition, FederationError> {
+    match path.last().map(AsRef::as_ref) {
+        None => Ok(parent_type.clone()),
+        Some(OpPathElement::Field(field)) => field.data().output_composite_type(),
+        Some(OpPathElement::InlineFragment(inline_fragment)) => {
+            let data = inline_fragment.data();
+            Ok(data
+                .type_condition_position
+                .clone()
+                .unwrap_or_else(|| data.parent_type_position.clone()))
+        }
+    }
+}
+
+/// Expresses the selections of a fetch as a selection set on `type_position`, so they can be
+/// merged into another fetch at a path of that type. The selections of an entity fetch are inline
+/// fragments on the entity union, which only works if they are all on `type_position`; otherwise
+/// `None` is returned.
+fn selection_set_at_type(
+    selection_set: &NormalizedSelectionSet,
+    type_position: &CompositeTypeDefinitionPosition,
+) -> Result<Option<NormalizedSelectionSet>, FederationError> {
+    if selection_set.type_position == *type_position {
+        return Ok(Some(selection_set.clone()));
+    }
+    let mut fragment_selection_sets = vec![];
+    for selection in selection_set.selections.values() {
+        let NormalizedSelection::InlineFragment(inline_fragment_selection) = selection else {
+            return Ok(None);
+        };
+        let data = inline_fragment_selection.inline_fragment.data();
+        if data.type_condition_position.as_ref() != Some(type_position)
+            || !data.directives.is_empty()
+        {
+            return Ok(None);
+        }
+        fragment_selection_sets.push(inline_fragment_selection.selection_set.clone());
+    }
+    let mut merged =
+        NormalizedSelectionSet::empty(selection_set.schema.clone(), type_position.clone());
+    merged.merge_into(fragment_selection_sets.into_iter())?;
+    Ok(Some(merged))
+}
+
+/// When a child fetch is merged into its parent at `path_in_parent`, computes the path of a
+/// grandchild relative to the parent from its path relative to the child (dropping the leading
+/// entity type condition for entity fetches). Returns `None` if this can't be determined.
+fn path_after_merge(
+    path_in_parent: &OpPath,
+    path_in_child: &OpPath,
+    child_type: &CompositeTypeDefinitionPosition,
+    target_type: &CompositeTypeDefinitionPosition,
+) -> Option<Arc<OpPath>> {
+    let mut path_in_child = path_in_child.iter();
+    if child_type != target_type {
+        let Some(OpPathElement::InlineFragment(inline_fragment)) =
+            path_in_child.next().map(AsRef::as_ref)
+        else {
+            return None;
+        };
+        if inline_fragment.data().type_condition_position.as_ref() != Some(target_type) {
+            return None;
+        }
+    }
+    Some(Arc::new(OpPath::from(
+        path_in_parent
+            .iter()
+            .chain(path_in_child)
+            .cloned()
+            .collect::<Vec<_>>(),
+    )))
+}
+
+/// The composite type of a (non-root) query graph node.
+fn composite_type_of_node(
+    query_graph: &QueryGraph,
+    node: NodeIndex,
+) -> Result<CompositeTypeDefinitionPosition, FederationError> {
+    match &query_graph.node_weight(node)?.type_ {
+        QueryGraphNodeType::SchemaType(type_position) => type_position.clone().try_into(),
+        QueryGraphNodeType::FederatedRootType(_) => Err(FederationError::internal(
+            "Unexpected federated root node in path tree",
+        )),
+    }
+}
+
+/// Finds a key of the entity type of the given query graph node, in the node's subgraph. This is
+/// taken from the key edges leading to the node, expressed on the node's type.
+// TODO: Use `QueryGraph::get_locally_satisfiable_key()` once it's implemented, as the key must be
+// resolvable from the subgraph itself.
+fn key_for_node(
+    query_graph: &QueryGraph,
+    node: NodeIndex,
+) -> Result<NormalizedSelectionSet, FederationError> {
+    let type_position = composite_type_of_node(query_graph, node)?;
+    let source = &query_graph.node_weight(node)?.source;
+    let schema = query_graph.schema_by_source(source)?;
+    let key = query_graph
+        .graph()
+        .edges_directed(node, Direction::Incoming)
+        .find_map(|edge| match edge.weight().transition {
+            QueryGraphEdgeTransition::KeyResolution => edge.weight().conditions.clone(),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            FederationError::internal(format!(
+                "Cannot find a key for type \"{}\" in its subgraph",
+                type_position,
+            ))
+        })?;
+    key.rebase_on(&type_position, schema)
+        .map_err(|error| error.with_subgraph_name(source))
+}
+
+/// The estimated number of fields in the response to the given selection set, where the fields
+/// selected within a list field are counted `list_size_estimate` times.
+fn estimated_selection_set_size(
+    selection_set: &NormalizedSelectionSet,
+    list_size_estimate: QueryPlanCost,
+) -> Result<QueryPlanCost, FederationError> {
+    let mut size = 0;
+    for selection in selection_set.selections.values() {
+        size += match selection {
+            NormalizedSelection::Field(field_selection) => match &field_selection.selection_set {
+                None => 1,
+                Some(sub_selection_set) => {
+                    let field_data = field_selection.field.data();
+                    let multiplier = if field_data
+                        .field_position
+                        .get(field_data.schema.schema())?
+                        .ty
+                        .is_list()
+                    {
+                        list_size_estimate
+                    } else {
+                        1
+                    };
+                    1 + multiplier
+                        * estimated_selection_set_size(sub_selection_set, list_size_estimate)?
+                }
+            },
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                estimated_selection_set_size(
+                    &inline_fragment_selection.selection_set,
+                    list_size_estimate,
+                )?
+            }
+            NormalizedSelection::FragmentSpread(_) => {
+                return Err(FederationError::internal(
+                    "Unexpected fragment spread in fetch selection set",
+                ))
+            }
+        };
+    }
+    Ok(size)
+}
+
+/// The given selection set with its `__typename` added.
+fn with_typename(
+    selection_set: &NormalizedSelectionSet,
+) -> Result<NormalizedSelectionSet, FederationError> {
+    let mut selection_set = selection_set.clone();
+    selection_set.add_typename_field()?;
+    Ok(selection_set)
+}
+
+/// Adds the given path to the given node, for when nothing is selected after it (e.g. the path
+/// ends with a leaf field).
+fn add_path_to_node(
+    dependency_graph: &mut FetchDependencyGraph,
+    node: NodeIndex,
+    path: &OpPath,
+) -> Result<(), FederationError> {
+    // Composite fields must have a selection set (even if empty) to be merged with others.
+    let selection_set = match path.last().map(AsRef::as_ref) {
+        Some(OpPathElement::Field(field)) if !field.data().is_leaf()? => {
+            Some(NormalizedSelectionSet::empty(
+                field.data().schema.clone(),
+                field.data().output_composite_type()?,
+            ))
+        }
+        _ => None,
+    };
+    dependency_graph
+        .node_weight_mut(node)?
+        .add_at_path(path, selection_set)
+}
+
+struct ComputeNodesStackItem<'a> {
+    tree: &'a OpPathTree,
+    node: NodeIndex,
+    node_path: FetchDependencyGraphNodePath,
+    defer_context: DeferContext,
+    context: OpGraphPathContext,
+}
+
+/// Adds the selections of the given path tree to the fetch dependency graph, starting in the
+/// `start_node` fetch (which must be against the subgraph of the root of the tree) at the given
+/// path. New fetches are created when the tree jumps subgraphs (key and root type edges) or needs
+/// `@requires` conditions, and are returned.
+// TODO: `@defer` isn't handled yet, beyond carrying over the active defer label to new fetches.
 pub(crate) fn compute_nodes_for_tree(
-    _dependency_graph: &mut FetchDependencyGraph,
-    _tree: &OpPathTree,
-    _start_node: NodeIndex,
-    _initial_node_path: FetchDependencyGraphNodePath,
-    _initial_defer_context: DeferContext,
-    _initial_conditions: OpGraphPathContext,
-) -> Vec<NodeIndex> {
-    // TODO: port `computeGroupsForTree` in `query-planner-js/src/buildPlan.ts`
-    todo!()
+    dependency_graph: &mut FetchDependencyGraph,
+    tree: &OpPathTree,
+    start_node: NodeIndex,
+    initial_node_path: FetchDependencyGraphNodePath,
+    initial_defer_context: DeferContext,
+    initial_conditions: OpGraphPathContext,
+) -> Result<Vec<NodeIndex>, FederationError> {
+    let query_graph = dependency_graph.federated_query_graph.clone();
+    let mut created_nodes = vec![];
+    let mut stack = vec![ComputeNodesStackItem {
+        tree,
+        node: start_node,
+        node_path: initial_node_path,
+        defer_context: initial_defer_context,
+        context: initial_conditions,
+    }];
+    while let Some(item) = stack.pop() {
+        let source = &query_graph.node_weight(item.tree.node)?.source;
+        let schema = query_graph.schema_by_source(source)?;
+        if !item.tree.local_selection_sets.is_empty() {
+            let type_position = composite_type_of_node(&query_graph, item.tree.node)?;
+            for selection_set in &item.tree.local_selection_sets {
+                let selection_set = selection_set
+                    .rebase_on(&type_position, schema)
+                    .map_err(|error| error.with_subgraph_name(source))?;
+                dependency_graph
+                    .node_weight_mut(item.node)?
+                    .add_at_path(&item.node_path.path_in_node, Some(selection_set))?;
+            }
+        } else if item.tree.childs.is_empty() {
+            add_path_to_node(dependency_graph, item.node, &item.node_path.path_in_node)?;
+        }
+
+        for child in &item.tree.childs {
+            let Some(edge) = child.edge else {
+                // Operation elements without an edge stay on the same type (e.g. a fragment
+                // without type condition, or a `__typename` field).
+                let element = match child.trigger.as_ref() {
+                    OpGraphPathTrigger::Context(context) => {
+                        stack.push(ComputeNodesStackItem {
+                            tree: &child.tree,
+                            node: item.node,
+                            node_path: item.node_path.clone(),
+                            defer_context: item.defer_context.clone(),
+                            context: context.clone(),
+                        });
+                        continue;
+                    }
+                    OpGraphPathTrigger::Field(field) => {
+                        let type_position = composite_type_of_node(&query_graph, item.tree.node)?;
+                        OpPathElement::Field(
+                            field
+                                .rebase_on(&type_position, schema)
+                                .map_err(|error| error.with_subgraph_name(source))?,
+                        )
+                    }
+                    OpGraphPathTrigger::InlineFragment(inline_fragment) => {
+                        let type_position = composite_type_of_node(&query_graph, item.tree.node)?;
+                        OpPathElement::InlineFragment(
+                            inline_fragment
+                                .rebase_on(&type_position, schema)
+                                .map_err(|error| error.with_subgraph_name(source))?,
+                        )
+                    }
+                };
+                stack.push(ComputeNodesStackItem {
+                    tree: &child.tree,
+                    node: item.node,
+                    context: item.context.with_context_of(&element)?,
+                    node_path: item.node_path.add(Arc::new(element))?,
+                    defer_context: item.defer_context.clone(),
+                });
+                continue;
+            };
+
+            let edge_weight = query_graph.edge_weight(edge)?;
+            let (head, tail) = query_graph.edge_endpoints(edge)?;
+            match &edge_weight.transition {
+                // Extension edges are planned like key edges, with their conditions as the key.
+                QueryGraphEdgeTransition::KeyResolution
+                | QueryGraphEdgeTransition::ExtensionResolution { .. } => {
+                    // The key conditions may need fetches of their own, which the new fetch then
+                    // depends on.
+                    let conditions_nodes = match &child.conditions {
+                        Some(conditions) => compute_nodes_for_tree(
+                            dependency_graph,
+                            conditions,
+                            item.node,
+                            item.node_path.clone(),
+                            item.defer_context.clone(),
+                            item.context.clone(),
+                        )?,
+                        None => vec![],
+                    };
+                    let Some(key) = &edge_weight.conditions else {
+                        return Err(FederationError::internal(
+                            "Key resolution edge unexpectedly missing conditions",
+                        ));
+                    };
+                    let head_type = composite_type_of_node(&query_graph, head)?;
+                    let key_in_parent = with_typename(
+                        &key.rebase_on(&head_type, schema)
+                            .map_err(|error| error.with_subgraph_name(source))?,
+                    )?;
+                    let (node, node_path) = dependency_graph.new_key_node_at_path(
+                        item.node,
+                        &item.node_path,
+                        key_in_parent,
+                        query_graph.node_weight(tail)?.source.clone(),
+                        &composite_type_of_node(&query_graph, tail)?,
+                        item.defer_context.active_defer_ref.clone(),
+                        &item.context,
+                    )?;
+                    for conditions_node in &conditions_nodes {
+                        dependency_graph.add_parent(node, *conditions_node, None)?;
+                    }
+                    created_nodes.extend(conditions_nodes);
+                    created_nodes.push(node);
+                    stack.push(ComputeNodesStackItem {
+                        tree: &child.tree,
+                        node,
+                        node_path,
+                        defer_context: item.defer_context.clone(),
+                        context: item.context.clone(),
+                    });
+                }
+                QueryGraphEdgeTransition::RootTypeResolution { root_kind } => {
+                    // The field returning the root type still needs a selection in the parent.
+                    let head_type = composite_type_of_node(&query_graph, head)?;
+                    let mut typename_selection_set =
+                        NormalizedSelectionSet::empty(schema.clone(), head_type);
+                    typename_selection_set.add_typename_field()?;
+                    dependency_graph
+                        .node_weight_mut(item.node)?
+                        .add_at_path(&item.node_path.path_in_node, Some(typename_selection_set))?;
+                    let node = dependency_graph.new_node(
+                        query_graph.node_weight(tail)?.source.clone(),
+                        composite_type_of_node(&query_graph, tail)?,
+                        /* has_inputs: */ false,
+                        *root_kind,
+                    )?;
+                    let node_weight = dependency_graph.node_weight_mut(node)?;
+                    node_weight.merge_at = Some(item.node_path.response_path.clone());
+                    node_weight.defer_ref = item.defer_context.active_defer_ref.clone();
+                    dependency_graph.add_parent(
+                        node,
+                        item.node,
+                        Some(Arc::new(item.node_path.path_in_node.clone())),
+                    )?;
+                    created_nodes.push(node);
+                    stack.push(ComputeNodesStackItem {
+                        tree: &child.tree,
+                        node,
+                        node_path: item.node_path.for_new_fetch(OpPath::default()),
+                        defer_context: item.defer_context.clone(),
+                        context: item.context.clone(),
+                    });
+                }
+                QueryGraphEdgeTransition::FieldCollection {
+                    field_definition_position,
+                    ..
+                } => {
+                    let OpGraphPathTrigger::Field(field) = child.trigger.as_ref() else {
+                        return Err(FederationError::internal(format!(
+                            "Field collection edge for \"{}\" unexpectedly triggered by non-field",
+                            field_definition_position,
+                        )));
+                    };
+                    let mut field_data = field.data().clone();
+                    field_data.schema = schema.clone();
+                    field_data.field_position = field_definition_position.clone();
+                    let element = OpPathElement::Field(NormalizedField::new(field_data));
+                    let (node, node_path) = match &child.conditions {
+                        // The field has `@requires`, so its conditions are fetched first, and the
+                        // field is then fetched from a new entity fetch that depends on them.
+                        Some(conditions) => {
+                            let conditions_nodes = compute_nodes_for_tree(
+                                dependency_graph,
+                                conditions,
+                                item.node,
+                                item.node_path.clone(),
+                                item.defer_context.clone(),
+                                item.context.clone(),
+                            )?;
+                            let Some(requires) = &edge_weight.conditions else {
+                                return Err(FederationError::internal(format!(
+                                    "@requires edge for \"{}\" unexpectedly missing conditions",
+                                    field_definition_position,
+                                )));
+                            };
+                            let head_type = composite_type_of_node(&query_graph, head)?;
+                            let key = key_for_node(&query_graph, head)?;
+                            let required = compute_required_selection(
+                                &key,
+                                &requires
+                                    .rebase_on(&head_type, schema)
+                                    .map_err(|error| error.with_subgraph_name(source))?,
+                            )?;
+                            let (node, node_path) = dependency_graph.new_key_node_at_path(
+                                item.node,
+                                &item.node_path,
+                                with_typename(&key)?,
+                                source.clone(),
+                                &head_type,
+                                item.defer_context.active_defer_ref.clone(),
+                                &item.context,
+                            )?;
+                            let input_type =
+                                dependency_graph.supergraph_type(head_type.type_name())?;
+                            let inputs = required
+                                .rebase_on(&input_type, &dependency_graph.supergraph_schema)?;
+                            dependency_graph
+                                .node_weight_mut(node)?
+                                .add_inputs(&inputs, [])?;
+                            for conditions_node in &conditions_nodes {
+                                dependency_graph.add_parent(node, *conditions_node, None)?;
+                            }
+                            created_nodes.extend(conditions_nodes);
+                            created_nodes.push(node);
+                            (node, node_path)
+                        }
+                        None => (item.node, item.node_path.clone()),
+                    };
+                    stack.push(ComputeNodesStackItem {
+                        tree: &child.tree,
+                        node,
+                        context: item.context.with_context_of(&element)?,
+                        node_path: node_path.add(Arc::new(element))?,
+                        defer_context: item.defer_context.clone(),
+                    });
+                }
+                QueryGraphEdgeTransition::Downcast {
+                    from_type_position,
+                    to_type_position,
+                    ..
+                } => {
+                    let OpGraphPathTrigger::InlineFragment(inline_fragment) =
+                        child.trigger.as_ref()
+                    else {
+                        return Err(FederationError::internal(format!(
+                            "Downcast edge to \"{}\" unexpectedly triggered by non-fragment",
+                            to_type_position,
+                        )));
+                    };
+                    let mut inline_fragment_data = inline_fragment.data().clone();
+                    inline_fragment_data.schema = schema.clone();
+                    inline_fragment_data.parent_type_position = from_type_position.clone();
+                    inline_fragment_data.type_condition_position = Some(to_type_position.clone());
+                    let element = OpPathElement::InlineFragment(NormalizedInlineFragment::new(
+                        inline_fragment_data,
+                    ));
+                    stack.push(ComputeNodesStackItem {
+                        tree: &child.tree,
+                        node: item.node,
+                        context: item.context.with_context_of(&element)?,
+                        node_path: item.node_path.add(Arc::new(element))?,
+                        defer_context: item.defer_context.clone(),
+                    });
+                }
+                QueryGraphEdgeTransition::InterfaceObjectFakeDownCast {
+                    from_type_position,
+                    to_type_name,
+                    ..
+                } => {
+                    // The implementation type doesn't exist in the subgraph, so the path in the
+                    // fetch is unchanged, but the `__typename` of the fetched objects must be
+                    // rewritten to the implementation type (including where it's aliased).
+                    let mut aliases = IndexSet::new();
+                    typename_aliases(&child.tree, &mut aliases);
+                    let rewrites = compute_output_rewrites_on_interface_object_fake_downcast(
+                        &op_path_response_path(&item.node_path.path_in_node)?,
+                        &NodeStr::new(from_type_position.type_name()),
+                        &NodeStr::new(to_type_name),
+                        &aliases,
+                    );
+                    dependency_graph
+                        .node_weight_mut(item.node)?
+                        .add_output_rewrites(rewrites);
+                    let context = match child.trigger.as_ref() {
+                        OpGraphPathTrigger::InlineFragment(inline_fragment) => item
+                            .context
+                            .with_context_of(&OpPathElement::InlineFragment(
+                                inline_fragment.clone(),
+                            ))?,
+                        _ => item.context.clone(),
+                    };
+                    stack.push(ComputeNodesStackItem {
+                        tree: &child.tree,
+                        node: item.node,
+                        node_path: item.node_path.clone(),
+                        defer_context: item.defer_context.clone(),
+                        context,
+                    });
+                }
+                QueryGraphEdgeTransition::SubgraphEnteringTransition => {
+                    return Err(FederationError::internal(
+                        "Unexpected subgraph entering edge when computing fetches for a path tree",
+                    ));
+                }
+            }
+        }
+    }
+    Ok(created_nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::FederationError;
+    use crate::query_graph::build_query_graph::build_federated_query_graph;
+    use crate::query_graph::graph_path::{OpGraphPathContext, OpGraphPathTrigger};
+    use crate::query_graph::graph_path::{OpPath, OpPathElement};
+    use crate::query_graph::path_tree::{OpPathTree, PathTreeChild};
+    use crate::query_graph::QueryGraph;
+    use crate::query_plan::conditions::Conditions;
+    use crate::query_plan::fetch_dependency_graph::{
+        add_path_to_node, compute_input_rewrites_on_key_fetch, compute_nodes_for_tree,
+        compute_output_rewrites_on_interface_object_fake_downcast, compute_required_selection,
+        entity_fetch_initial_path, typename_aliases, validate_fetch_operation,
+        FetchDependencyGraph, FetchDependencyGraphNodePath,
+    };
+    use crate::query_plan::fetch_dependency_graph_processor::{
+        FetchDependencyGraphToCostProcessor, FetchDependencyGraphToQueryPlanProcessor,
+    };
+    use crate::query_plan::operation::normalized_field_selection::{
+        NormalizedField, NormalizedFieldData,
+    };
+    use crate::query_plan::operation::{FragmentSpreadNormalizationOption, NormalizedSelectionSet};
+    use crate::query_plan::query_planner::{QueryPlanCostConfig, QueryPlannerConfig};
+    use crate::query_plan::{
+        FetchDataPathElement, FetchDataRewrite, PlanNode, QueryPlan, SequenceNode, TopLevelPlanNode,
+    };
+    use crate::schema::position::SchemaRootDefinitionKind;
+    use crate::schema::position::{InterfaceTypeDefinitionPosition, ObjectTypeDefinitionPosition};
+    use crate::schema::ValidFederationSchema;
+    use crate::test_supergraph::supergraph_sdl;
+    use apollo_compiler::ast::{Argument, Directive, DirectiveList, Name, Value};
+    use apollo_compiler::executable::FieldSet;
+    use apollo_compiler::schema::NamedType;
+    use apollo_compiler::{name, ExecutableDocument, Node, NodeStr};
+    use indexmap::IndexSet;
+    use petgraph::graph::EdgeIndex;
+    use petgraph::visit::EdgeRef;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::sync::Arc;
+
+    fn interface_object_supergraph() -> String {
+        supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type A implements I
+              @join__implements(graph: S1, interface: "I")
+              @join__type(graph: S1, key: "id", resolvable: false)
+            {
+              id: ID!
+              x: Int @join__field
+            }
+
+            interface I
+              @join__type(graph: S1, key: "id", resolvable: false)
+              @join__type(graph: S2, key: "id", resolvable: false, isInterfaceObject: true)
+            {
+              id: ID!
+              x: Int @join__field(graph: S2)
+            }
+
+
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              i: I @join__field(graph: S1)
+            }
+            "#,
+        )
+    }
+
+    fn requires_supergraph() -> String {
+        supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              t: T @join__field(graph: S1)
+            }
+
+            type T
+              @join__type(graph: S1, key: "id", resolvable: false)
+              @join__type(graph: S2, key: "id", resolvable: false)
+            {
+              id: ID!
+              x: Int @join__field(graph: S1) @join__field(graph: S2, external: true)
+              y: Int @join__field(graph: S2, requires: "x")
+            }
+            "#,
+        )
+    }
+
+    fn mutation_supergraph() -> String {
+        supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              t: T @join__field(graph: S1)
+            }
+
+            type Mutation
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              updateT: T @join__field(graph: S1)
+              resetT: T @join__field(graph: S2)
+              deleteT: T @join__field(graph: S1)
+            }
+
+            type T
+              @join__type(graph: S1, key: "id", resolvable: false)
+              @join__type(graph: S2, key: "id", resolvable: false)
+            {
+              id: ID!
+              x: Int @join__field(graph: S1) @join__field(graph: S2, external: true)
+              y: Int @join__field(graph: S2, requires: "x")
+            }
+            "#,
+        )
+    }
+
+    fn parse_selection_set(
+        schema: &ValidFederationSchema,
+        type_name: &str,
+        value: &str,
+    ) -> NormalizedSelectionSet {
+        let field_set = FieldSet::parse_and_validate(
+            schema.schema(),
+            NamedType::new_unchecked(type_name.into()),
+            value,
+            "field_set.graphql",
+        )
+        .unwrap();
+        NormalizedSelectionSet::normalize_and_expand_fragments(
+            &field_set.selection_set,
+            &Default::default(),
+            schema,
+            FragmentSpreadNormalizationOption::InlineFragmentSpread,
+            &Default::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn handles_requires() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&requires_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let s1_schema = query_graph.schema_by_source("S1")?.clone();
+        let mut dependency_graph = FetchDependencyGraph::new(
+            supergraph_schema.clone(),
+            query_graph,
+            None,
+            0,
+            None,
+            Default::default(),
+        );
+        let root = dependency_graph.get_or_create_root_node(
+            &NodeStr::new("S1"),
+            SchemaRootDefinitionKind::Query,
+            ObjectTypeDefinitionPosition {
+                type_name: name!("Query"),
+            }
+            .into(),
+        )?;
+
+        let path_in_parent = Arc::new(OpPath::from(vec![Arc::new(OpPathElement::Field(
+            NormalizedField::new(NormalizedFieldData {
+                schema: s1_schema.clone(),
+                field_position: ObjectTypeDefinitionPosition {
+                    type_name: name!("Query"),
+                }
+                .field(name!("t"))
+                .into(),
+                alias: None,
+                arguments: Default::default(),
+                directives: Default::default(),
+                sibling_typename: None,
+            }),
+        ))]));
+        let required_in_parent = compute_required_selection(
+            &parse_selection_set(&s1_schema, "T", "id"),
+            &parse_selection_set(&s1_schema, "T", "x"),
+        )?;
+        assert_eq!(required_in_parent.to_string(), "{ __typename id x }");
+        let inputs = compute_required_selection(
+            &parse_selection_set(&supergraph_schema, "T", "id"),
+            &parse_selection_set(&supergraph_schema, "T", "x"),
+        )?;
+
+        let node = dependency_graph.handle_requires(
+            root,
+            path_in_parent.clone(),
+            required_in_parent,
+            NodeStr::new("S2"),
+            vec![FetchDataPathElement::Key(NodeStr::new("t"))],
+            &inputs,
+        )?;
+        let root_weight = dependency_graph.node_weight(root)?;
+        assert_eq!(
+            root_weight.selection_set.selection_set.to_string(),
+            "{ t { __typename id x } }"
+        );
+        let node_weight = dependency_graph.node_weight(node)?;
+        assert!(node_weight.is_entity_fetch);
+        assert_eq!(node_weight.subgraph_name, "S2");
+        let node_inputs = node_weight.inputs.as_ref().unwrap();
+        assert_eq!(
+            node_inputs
+                .selection_sets_per_parent_type
+                .values()
+                .map(|selection_set| selection_set.to_string())
+                .collect::<Vec<_>>(),
+            vec!["{ __typename id x }"]
+        );
+        assert!(dependency_graph.is_parent_of(root, node));
+
+        // The root fetch now depends on the S2 fetch, so it can't also be its dependency.
+        assert!(dependency_graph
+            .add_parent(root, node, Some(path_in_parent))
+            .is_err());
+        assert!(dependency_graph.add_parent(root, root, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn splits_oversized_fetches() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&requires_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let s1_schema = query_graph.schema_by_source("S1")?.clone();
+        let s2_schema = query_graph.schema_by_source("S2")?.clone();
+        // "{ t { id x } }" is 14 bytes, and "{ id x }" is 8 bytes.
+        let mut dependency_graph = FetchDependencyGraph::new(
+            supergraph_schema,
+            query_graph,
+            None,
+            0,
+            Some(7),
+            Default::default(),
+        );
+        let root = dependency_graph.get_or_create_root_node(
+            &NodeStr::new("S1"),
+            SchemaRootDefinitionKind::Query,
+            ObjectTypeDefinitionPosition {
+                type_name: name!("Query"),
+            }
+            .into(),
+        )?;
+        dependency_graph.node_weight_mut(root)?.add_at_path(
+            &OpPath::default(),
+            Some(parse_selection_set(&s1_schema, "Query", "t { id x }")),
+        )?;
+        let child = dependency_graph.new_key_node(
+            NodeStr::new("S2"),
+            vec![FetchDataPathElement::Key(NodeStr::new("t"))],
+            None,
+        )?;
+        dependency_graph.node_weight_mut(child)?.add_at_path(
+            &OpPath::default(),
+            Some(parse_selection_set(&s2_schema, "_Entity", "... on T { y }")),
+        )?;
+        dependency_graph.add_parent(child, root, None)?;
+
+        dependency_graph.optimize()?;
+        assert_eq!(dependency_graph.fetch_count(), 3);
+        let mut root_selection_sets = Vec::new();
+        for (parent, _) in dependency_graph.parents_of(child) {
+            let parent_weight = dependency_graph.node_weight(parent)?;
+            assert_eq!(parent_weight.subgraph_name, "S1");
+            root_selection_sets.push(parent_weight.selection_set.selection_set.to_string());
+        }
+        root_selection_sets.sort();
+        assert_eq!(root_selection_sets, ["{ t { id } }", "{ t { x } }"]);
+        // The entity fetch has a single (small enough) leaf field, so it is kept as is.
+        assert_eq!(
+            dependency_graph
+                .node_weight(child)?
+                .selection_set
+                .selection_set
+                .to_string(),
+            "{ ... on T { y } }"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn computes_interface_object_rewrites() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&interface_object_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let query_graph = build_federated_query_graph(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?;
+        assert!(query_graph.is_interface_object_type("S2", "I"));
+        assert!(!query_graph.is_interface_object_type("S1", "I"));
+        assert!(!query_graph.is_interface_object_type("S2", "A"));
+
+        let input_type_name = NodeStr::new("A");
+        let input_rewrites = compute_input_rewrites_on_key_fetch(
+            &query_graph,
+            "S2",
+            &input_type_name,
+            &ObjectTypeDefinitionPosition {
+                type_name: name!("I"),
+            }
+            .into(),
+        );
+        let Some([FetchDataRewrite::ValueSetter(setter)]) = input_rewrites.as_deref() else {
+            panic!("Expected a single value setter for an @interfaceObject key fetch");
+        };
+        assert!(matches!(
+            setter.path.as_slice(),
+            [
+                FetchDataPathElement::TypenameEquals(type_name),
+                FetchDataPathElement::Key(key),
+            ] if type_name == "A" && key == "__typename"
+        ));
+        assert_eq!(setter.set_value_to, "I");
+
+        // Interfaces need the same rewrite, but object types don't.
+        assert!(compute_input_rewrites_on_key_fetch(
+            &query_graph,
+            "S1",
+            &input_type_name,
+            &InterfaceTypeDefinitionPosition {
+                type_name: name!("I"),
+            }
+            .into(),
+        )
+        .is_some());
+        assert!(compute_input_rewrites_on_key_fetch(
+            &query_graph,
+            "S1",
+            &input_type_name,
+            &ObjectTypeDefinitionPosition {
+                type_name: name!("A"),
+            }
+            .into(),
+        )
+        .is_none());
+
+        let output_rewrites = compute_output_rewrites_on_interface_object_fake_downcast(
+            &[FetchDataPathElement::Key(NodeStr::new("i"))],
+            &NodeStr::new("I"),
+            &NodeStr::new("A"),
+            &IndexSet::from([name!("t")]),
+        );
+        let [FetchDataRewrite::ValueSetter(setter), FetchDataRewrite::ValueSetter(alias_setter)] =
+            output_rewrites.as_slice()
+        else {
+            panic!("Expected a value setter per typename for an @interfaceObject fake downcast");
+        };
+        assert!(matches!(
+            setter.path.as_slice(),
+            [
+                FetchDataPathElement::Key(field),
+                FetchDataPathElement::TypenameEquals(type_name),
+                FetchDataPathElement::Key(key),
+            ] if field == "i" && type_name == "I" && key == "__typename"
+        ));
+        assert_eq!(setter.set_value_to, "A");
+        assert!(matches!(
+            alias_setter.path.last(),
+            Some(FetchDataPathElement::Key(key)) if key == "t"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn collects_typename_aliases_of_fake_downcasts() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&interface_object_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let s2_schema = query_graph.schema_by_source("S2")?.clone();
+        let i_node = query_graph
+            .graph()
+            .node_indices()
+            .find(|node| {
+                let node_weight = &query_graph.graph()[*node];
+                node_weight.source == "S2" && node_weight.type_.to_string() == "I"
+            })
+            .unwrap();
+        let typename_child = |alias: Option<Name>| {
+            let field = NormalizedField::new(NormalizedFieldData {
+                schema: s2_schema.clone(),
+                field_position: ObjectTypeDefinitionPosition {
+                    type_name: name!("I"),
+                }
+                .introspection_typename_field()
+                .into(),
+                alias,
+                arguments: Default::default(),
+                directives: Default::default(),
+                sibling_typename: None,
+            });
+            Arc::new(PathTreeChild {
+                edge: None,
+                trigger: Arc::new(OpGraphPathTrigger::Field(field)),
+                conditions: None,
+                tree: Arc::new(OpPathTree::new(query_graph.clone(), i_node)),
+            })
+        };
+
+        // { i { id k: __typename ... on I { t: __typename } __typename u: __typename } }, where
+        // the last two `__typename` are added to the tree as path elements.
+        let mut tree = OpPathTree::new(query_graph.clone(), i_node);
+        tree.local_selection_sets.push(Arc::new(parse_selection_set(
+            &s2_schema,
+            "I",
+            "id k: __typename ... on I { t: __typename }",
+        )));
+        tree.childs.push(typename_child(None));
+        tree.childs.push(typename_child(Some(name!("u"))));
+
+        let mut aliases = IndexSet::new();
+        typename_aliases(&tree, &mut aliases);
+        assert_eq!(
+            aliases,
+            IndexSet::from([name!("k"), name!("t"), name!("u")])
+        );
+        Ok(())
+    }
+
+    fn root_type_supergraph() -> String {
+        supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              t: T @join__field(graph: S1)
+              u: Int @join__field(graph: S2)
+            }
+
+            type T
+              @join__type(graph: S1, key: "id", resolvable: false)
+              @join__type(graph: S2, key: "id", resolvable: false)
+            {
+              id: ID!
+              x: Int @join__field(graph: S1)
+              y: Int @join__field(graph: S2)
+            }
+            "#,
+        )
+    }
+
+    fn field(schema: &ValidFederationSchema, type_name: &str, field_name: &str) -> NormalizedField {
+        NormalizedField::new(NormalizedFieldData {
+            schema: schema.clone(),
+            field_position: ObjectTypeDefinitionPosition {
+                type_name: NamedType::new_unchecked(type_name.into()),
+            }
+            .field(NamedType::new_unchecked(field_name.into()))
+            .into(),
+            alias: None,
+            arguments: Default::default(),
+            directives: Default::default(),
+            sibling_typename: None,
+        })
+    }
+
+    fn tree_child(
+        query_graph: &Arc<QueryGraph>,
+        edge: EdgeIndex,
+        trigger: OpGraphPathTrigger,
+        conditions: Option<OpPathTree>,
+        tree: OpPathTree,
+    ) -> Arc<PathTreeChild<OpGraphPathTrigger, Option<EdgeIndex>>> {
+        assert_eq!(query_graph.edge_endpoints(edge).unwrap().1, tree.node);
+        Arc::new(PathTreeChild {
+            edge: Some(edge),
+            trigger: Arc::new(trigger),
+            conditions: conditions.map(Arc::new),
+            tree: Arc::new(tree),
+        })
+    }
+
+    #[test]
+    fn computes_and_optimizes_nodes_for_tree() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&root_type_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let node_for = |source: &str, type_name: &str| {
+            query_graph
+                .graph()
+                .node_indices()
+                .find(|node| {
+                    let node_weight = &query_graph.graph()[*node];
+                    node_weight.source == source && node_weight.type_.to_string() == type_name
+                })
+                .unwrap()
+        };
+        let s1_query = node_for("S1", "Query");
+        let s2_query = node_for("S2", "Query");
+        let leaf = |node| OpPathTree::new(query_graph.clone(), node);
+        let tree_for_field = |node, field: NormalizedField, tree: Option<OpPathTree>| {
+            let edge = query_graph.edge_for_field(node, &field).unwrap();
+            let tail = query_graph.edge_endpoints(edge).unwrap().1;
+            tree_child(
+                &query_graph,
+                edge,
+                OpGraphPathTrigger::Field(field),
+                None,
+                tree.unwrap_or_else(|| leaf(tail)),
+            )
+        };
+
+        // { t { x } u }, with `t` from S1 and `u` from S2 (through a root type edge).
+        let mut t_tree = leaf(node_for("S1", "T"));
+        t_tree.childs.push(tree_for_field(
+            t_tree.node,
+            field(&supergraph_schema, "T", "x"),
+            None,
+        ));
+        let mut s2_query_tree = leaf(s2_query);
+        s2_query_tree.childs.push(tree_for_field(
+            s2_query,
+            field(&supergraph_schema, "Query", "u"),
+            None,
+        ));
+        let mut tree = leaf(s1_query);
+        tree.childs.push(tree_for_field(
+            s1_query,
+            field(&supergraph_schema, "Query", "t"),
+            Some(t_tree),
+        ));
+        tree.childs.push(tree_child(
+            &query_graph,
+            query_graph
+                .out_edges(s1_query)
+                .find(|edge| edge.target() == s2_query)
+                .unwrap()
+                .id(),
+            OpGraphPathTrigger::Context(Default::default()),
+            None,
+            s2_query_tree,
+        ));
+
+        let mut dependency_graph = FetchDependencyGraph::new(
+            supergraph_schema.clone(),
+            query_graph.clone(),
+            None,
+            0,
+            None,
+            Default::default(),
+        );
+        let root = dependency_graph.get_or_create_root_node(
+            &NodeStr::new("S1"),
+            SchemaRootDefinitionKind::Query,
+            ObjectTypeDefinitionPosition {
+                type_name: name!("Query"),
+            }
+            .into(),
+        )?;
+        let root_selection_set = |dependency_graph: &FetchDependencyGraph| {
+            dependency_graph
+                .node_weight(root)
+                .unwrap()
+                .selection_set
+                .selection_set
+                .to_string()
+        };
+        let compute_nodes = |dependency_graph: &mut FetchDependencyGraph| {
+            compute_nodes_for_tree(
+                dependency_graph,
+                &tree,
+                root,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            )
+        };
+        let created_nodes = compute_nodes(&mut dependency_graph)?;
+        let [s2_node] = created_nodes.as_slice() else {
+            panic!("Expected a single fetch to S2");
+        };
+        assert_eq!(
+            root_selection_set(&dependency_graph),
+            "{ __typename t { x } }"
+        );
+        let s2_weight = dependency_graph.node_weight(*s2_node)?;
+        assert_eq!(s2_weight.subgraph_name, "S2");
+        assert!(!s2_weight.is_entity_fetch);
+        assert_eq!(s2_weight.selection_set.selection_set.to_string(), "{ u }");
+        assert_eq!(s2_weight.merge_at, Some(vec![]));
+        assert!(dependency_graph.is_parent_of(root, *s2_node));
+
+        // Computing the same tree again creates a sibling fetch to S2, which optimizing merges.
+        compute_nodes(&mut dependency_graph)?;
+        assert_eq!(dependency_graph.graph.node_count(), 3);
+        dependency_graph.optimize()?;
+        assert!(dependency_graph.is_optimized);
+        assert_eq!(dependency_graph.graph.node_count(), 2);
+
+        // An entity fetch to the same subgraph as its parent is merged into it.
+        let s1_schema = query_graph.schema_by_source("S1")?.clone();
+        let t_path = FetchDependencyGraphNodePath::default().add(Arc::new(
+            OpPathElement::Field(field(&s1_schema, "Query", "t")),
+        ))?;
+        let (s1_node, s1_path) = dependency_graph.new_key_node_at_path(
+            root,
+            &t_path,
+            parse_selection_set(&s1_schema, "T", "__typename id"),
+            NodeStr::new("S1"),
+            &ObjectTypeDefinitionPosition {
+                type_name: name!("T"),
+            }
+            .into(),
+            None,
+            &Default::default(),
+        )?;
+        add_path_to_node(
+            &mut dependency_graph,
+            s1_node,
+            &s1_path
+                .path_in_node
+                .with_pushed(Arc::new(OpPathElement::Field(field(&s1_schema, "T", "id")))),
+        )?;
+        assert_eq!(
+            dependency_graph
+                .node_weight(s1_node)?
+                .selection_set
+                .selection_set
+                .to_string(),
+            "{ ... on T { id } }"
+        );
+        assert_eq!(dependency_graph.graph.node_count(), 3);
+        dependency_graph.optimize()?;
+        assert_eq!(dependency_graph.graph.node_count(), 2);
+        assert_eq!(
+            root_selection_set(&dependency_graph),
+            "{ __typename t { x __typename id } }"
+        );
+
+        // 2 fetches, one after the other, fetching 5 fields from S1 and 1 from S2.
+        assert_eq!(dependency_graph.fetch_count(), 2);
+        assert_eq!(dependency_graph.depth()?, 2);
+        let cost_processor = FetchDependencyGraphToCostProcessor::new(Default::default());
+        assert_eq!(cost_processor.process(&mut dependency_graph)?, 2206);
+        let cost_processor = FetchDependencyGraphToCostProcessor::new(QueryPlanCostConfig {
+            fetch_weight: 1,
+            depth_weight: 0,
+            response_size_weight: 10,
+            list_size_estimate: 1,
+        });
+        assert_eq!(cost_processor.process(&mut dependency_graph)?, 62);
+        Ok(())
+    }
+
+    #[test]
+    fn orders_root_mutation_fetches() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&mutation_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let s1_schema = query_graph.schema_by_source("S1")?.clone();
+        let s2_schema = query_graph.schema_by_source("S2")?.clone();
+        let mut dependency_graph = FetchDependencyGraph::new(
+            supergraph_schema.clone(),
+            query_graph,
+            None,
+            0,
+            None,
+            Default::default(),
+        );
+        let mutation_type = ObjectTypeDefinitionPosition {
+            type_name: name!("Mutation"),
+        };
+        let s1 = NodeStr::new("S1");
+        let s2 = NodeStr::new("S2");
+
+        // mutation { updateT { y } resetT { id } deleteT { id } }, where `y` requires `x`.
+        let update_root =
+            dependency_graph.new_serial_root_node(&s1, mutation_type.clone().into())?;
+        let update_path = Arc::new(OpPath::from(vec![Arc::new(OpPathElement::Field(field(
+            &s1_schema, "Mutation", "updateT",
+        )))]));
+        let requires_node = dependency_graph.handle_requires(
+            update_root,
+            update_path,
+            compute_required_selection(
+                &parse_selection_set(&s1_schema, "T", "id"),
+                &parse_selection_set(&s1_schema, "T", "x"),
+            )?,
+            s2.clone(),
+            vec![FetchDataPathElement::Key(NodeStr::new("updateT"))],
+            &compute_required_selection(
+                &parse_selection_set(&supergraph_schema, "T", "id"),
+                &parse_selection_set(&supergraph_schema, "T", "x"),
+            )?,
+        )?;
+        add_path_to_node(
+            &mut dependency_graph,
+            requires_node,
+            &entity_fetch_initial_path(
+                &s2_schema,
+                &ObjectTypeDefinitionPosition {
+                    type_name: name!("T"),
+                }
+                .into(),
+                &Default::default(),
+                &Default::default(),
+            )?
+            .with_pushed(Arc::new(OpPathElement::Field(field(&s2_schema, "T", "y")))),
+        )?;
+        let reset_root =
+            dependency_graph.new_serial_root_node(&s2, mutation_type.clone().into())?;
+        let delete_root =
+            dependency_graph.new_serial_root_node(&s1, mutation_type.clone().into())?;
+        // Consecutive root fields from the same subgraph share their fetch.
+        assert_eq!(
+            dependency_graph.new_serial_root_node(&s1, mutation_type.clone().into())?,
+            delete_root
+        );
+        for (root, schema, field_name) in [
+            (reset_root, &s2_schema, "resetT"),
+            (delete_root, &s1_schema, "deleteT"),
+        ] {
+            add_path_to_node(
+                &mut dependency_graph,
+                root,
+                &OpPath::from(vec![
+                    Arc::new(OpPathElement::Field(field(schema, "Mutation", field_name))),
+                    Arc::new(OpPathElement::Field(field(schema, "T", "id"))),
+                ]),
+            )?;
+        }
+
+        dependency_graph.optimize()?;
+        // The second root fetch waits for the `@requires` fetch triggered by the first one, and
+        // isn't merged with the S2 fetch despite being against the same subgraph.
+        assert!(dependency_graph.is_parent_of(update_root, requires_node));
+        assert!(dependency_graph.is_parent_of(requires_node, reset_root));
+        assert!(!dependency_graph.is_parent_of(update_root, reset_root));
+        assert!(dependency_graph.is_parent_of(reset_root, delete_root));
+        assert_eq!(dependency_graph.fetch_count(), 4);
+        assert_eq!(dependency_graph.depth()?, 4);
+
+        // The root fields are executed in order, each once everything before it is resolved.
+        let plan_node = dependency_graph
+            .process(
+                &FetchDependencyGraphToQueryPlanProcessor::default(),
+                &[],
+                None,
+            )?
+            .unwrap();
+        let plan = QueryPlan {
+            node: Some(plan_node.into()),
+            warnings: Vec::new(),
+            has_introspection: false,
+        };
+        insta::assert_snapshot!(plan.to_pretty_string(), @r###"
+        QueryPlan {
+          Sequence {
+            Fetch(service: "S1") {
+              mutation {
+                updateT {
+                  __typename
+                  id
+                  x
+                }
+              }
+            },
+            Flatten(path: "updateT") {
+              Fetch(service: "S2") {
+                {
+                  ... on T {
+                    __typename
+                    id
+                    x
+                  }
+                } =>
+                {
+                  ... on T {
+                    y
+                  }
+                }
+              },
+            },
+            Fetch(service: "S2") {
+              mutation {
+                resetT {
+                  id
+                }
+              }
+            },
+            Fetch(service: "S1") {
+              mutation {
+                deleteT {
+                  id
+                }
+              }
+            },
+          },
+        }
+        "###);
+        Ok(())
+    }
+
+    #[test]
+    fn marks_only_query_fetches_as_retry_safe() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&mutation_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let s1_schema = query_graph.schema_by_source("S1")?.clone();
+        let s2_schema = query_graph.schema_by_source("S2")?.clone();
+        let mut dependency_graph = FetchDependencyGraph::new(
+            supergraph_schema.clone(),
+            query_graph,
+            None,
+            0,
+            None,
+            Default::default(),
+        );
+
+        // mutation { updateT { y } }, where `y` requires `x`.
+        let root = dependency_graph.new_serial_root_node(
+            &NodeStr::new("S1"),
+            ObjectTypeDefinitionPosition {
+                type_name: name!("Mutation"),
+            }
+            .into(),
+        )?;
+        let update_path = Arc::new(OpPath::from(vec![Arc::new(OpPathElement::Field(field(
+            &s1_schema, "Mutation", "updateT",
+        )))]));
+        let requires_node = dependency_graph.handle_requires(
+            root,
+            update_path,
+            compute_required_selection(
+                &parse_selection_set(&s1_schema, "T", "id"),
+                &parse_selection_set(&s1_schema, "T", "x"),
+            )?,
+            NodeStr::new("S2"),
+            vec![FetchDataPathElement::Key(NodeStr::new("updateT"))],
+            &compute_required_selection(
+                &parse_selection_set(&supergraph_schema, "T", "id"),
+                &parse_selection_set(&supergraph_schema, "T", "x"),
+            )?,
+        )?;
+        add_path_to_node(
+            &mut dependency_graph,
+            requires_node,
+            &entity_fetch_initial_path(
+                &s2_schema,
+                &ObjectTypeDefinitionPosition {
+                    type_name: name!("T"),
+                }
+                .into(),
+                &Default::default(),
+                &Default::default(),
+            )?
+            .with_pushed(Arc::new(OpPathElement::Field(field(&s2_schema, "T", "y")))),
+        )?;
+        let plan_node = dependency_graph
+            .process(
+                &FetchDependencyGraphToQueryPlanProcessor::default(),
+                &[],
+                None,
+            )?
+            .unwrap();
+        let plan = QueryPlan {
+            node: Some(plan_node.into()),
+            warnings: Vec::new(),
+            has_introspection: false,
+        };
+
+        // The mutation fetch has side effects, but the entity fetch it's followed by doesn't.
+        let plan_json = plan.to_json();
+        let nodes = plan_json["node"]["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0]["operationKind"], "mutation");
+        assert_eq!(nodes[0]["retrySafe"], false);
+        assert_eq!(nodes[1]["node"]["operationKind"], "query");
+        assert_eq!(nodes[1]["node"]["retrySafe"], true);
+        Ok(())
+    }
+
+    /// Builds the plan of the `@requires` fetches of `requires_supergraph()`, from scratch.
+    fn requires_fetches_plan() -> Result<QueryPlan, FederationError> {
+        let supergraph = crate::Supergraph::new(&requires_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let s1_schema = query_graph.schema_by_source("S1")?.clone();
+        let s2_schema = query_graph.schema_by_source("S2")?.clone();
+        let mut dependency_graph = FetchDependencyGraph::new(
+            supergraph_schema.clone(),
+            query_graph,
+            None,
+            0,
+            None,
+            Default::default(),
+        );
+        let root = dependency_graph.get_or_create_root_node(
+            &NodeStr::new("S1"),
+            SchemaRootDefinitionKind::Query,
+            ObjectTypeDefinitionPosition {
+                type_name: name!("Query"),
+            }
+            .into(),
+        )?;
+        let t_path = OpPath::from(vec![Arc::new(OpPathElement::Field(field(
+            &s1_schema, "Query", "t",
+        )))]);
+        let node = dependency_graph.handle_requires(
+            root,
+            Arc::new(t_path),
+            compute_required_selection(
+                &parse_selection_set(&s1_schema, "T", "id"),
+                &parse_selection_set(&s1_schema, "T", "x"),
+            )?,
+            NodeStr::new("S2"),
+            vec![FetchDataPathElement::Key(NodeStr::new("t"))],
+            &compute_required_selection(
+                &parse_selection_set(&supergraph_schema, "T", "id"),
+                &parse_selection_set(&supergraph_schema, "T", "x"),
+            )?,
+        )?;
+        add_path_to_node(
+            &mut dependency_graph,
+            node,
+            &entity_fetch_initial_path(
+                &s2_schema,
+                &ObjectTypeDefinitionPosition {
+                    type_name: name!("T"),
+                }
+                .into(),
+                &Default::default(),
+                &Default::default(),
+            )?
+            .with_pushed(Arc::new(OpPathElement::Field(field(&s2_schema, "T", "y")))),
+        )?;
+
+        // Unused variables aren't defined by fetch operations.
+        let document = ExecutableDocument::parse(
+            s1_schema.schema(),
+            "query Q($unused: Int) { t { id } }",
+            "operation.graphql",
+        )?;
+        let variable_definitions = &document.named_operations["Q"].variables;
+        let nodes = [root, node]
+            .into_iter()
+            .map(|node| {
+                dependency_graph.node_weight(node)?.to_plan_node(
+                    &Default::default(),
+                    variable_definitions,
+                    Some(name!("Q")),
+                )
+            })
+            .collect::<Result<Option<Vec<_>>, _>>()?
+            .unwrap();
+        Ok(QueryPlan {
+            node: Some(TopLevelPlanNode::Sequence(SequenceNode { nodes })),
+            warnings: Vec::new(),
+            has_introspection: false,
+        })
+    }
+
+    #[test]
+    fn generates_fetch_operations() -> Result<(), FederationError> {
+        let plan = requires_fetches_plan()?;
+        insta::assert_snapshot!(serde_json::to_string_pretty(&plan.to_json()).unwrap(), @r###"
+        {
+          "kind": "QueryPlan",
+          "node": {
+            "kind": "Sequence",
+            "nodes": [
+              {
+                "kind": "Fetch",
+                "operation": "query Q { t { __typename id x } }",
+                "operationKind": "query",
+                "operationName": "Q",
+                "retrySafe": true,
+                "serviceName": "S1",
+                "variableUsages": []
+              },
+              {
+                "kind": "Flatten",
+                "node": {
+                  "kind": "Fetch",
+                  "operation": "query Q($representations: [_Any!]!) { _entities(representations: $representations) { ... on T { y } } }",
+                  "operationKind": "query",
+                  "operationName": "Q",
+                  "requires": [
+                    "... on T { __typename id x }"
+                  ],
+                  "retrySafe": true,
+                  "serviceName": "S2",
+                  "variableUsages": []
+                },
+                "path": [
+                  "t"
+                ]
+              }
+            ]
+          }
+        }
+        "###);
+        insta::assert_snapshot!(plan.to_pretty_string(), @r###"
+        QueryPlan {
+          Sequence {
+            Fetch(service: "S1") {
+              {
+                t {
+                  __typename
+                  id
+                  x
+                }
+              }
+            },
+            Flatten(path: "t") {
+              Fetch(service: "S2") {
+                {
+                  ... on T {
+                    __typename
+                    id
+                    x
+                  }
+                } =>
+                {
+                  ... on T {
+                    y
+                  }
+                }
+              },
+            },
+          },
+        }
+        "###);
+        Ok(())
+    }
+
+    #[test]
+    fn generates_identical_plans_across_runs() -> Result<(), FederationError> {
+        // Each run builds its own schemas and query graph, so any iteration over hash-based
+        // collections would show up as differing plans.
+        let plan_hash = || -> Result<u64, FederationError> {
+            let mut hasher = DefaultHasher::new();
+            requires_fetches_plan()?
+                .to_json()
+                .to_string()
+                .hash(&mut hasher);
+            Ok(hasher.finish())
+        };
+        let expected = plan_hash()?;
+        for _ in 0..10 {
+            assert_eq!(plan_hash()?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn generates_fragments_in_fetch_operations() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&requires_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let s1_schema = query_graph.schema_by_source("S1")?.clone();
+        let s2_schema = query_graph.schema_by_source("S2")?.clone();
+        let mut dependency_graph = FetchDependencyGraph::new(
+            supergraph_schema,
+            query_graph,
+            None,
+            0,
+            None,
+            Default::default(),
+        );
+        let root = dependency_graph.get_or_create_root_node(
+            &NodeStr::new("S1"),
+            SchemaRootDefinitionKind::Query,
+            ObjectTypeDefinitionPosition {
+                type_name: name!("Query"),
+            }
+            .into(),
+        )?;
+
+        // { t { id y } }, where `id` and `y` are fetched from S2 through a key jump.
+        let t_element = OpPathElement::Field(field(&s1_schema, "Query", "t"));
+        let context = OpGraphPathContext::default().with_context_of(&t_element)?;
+        let t_path = FetchDependencyGraphNodePath::default().add(Arc::new(t_element))?;
+        let (node, node_path) = dependency_graph.new_key_node_at_path(
+            root,
+            &t_path,
+            parse_selection_set(&s1_schema, "T", "__typename id"),
+            NodeStr::new("S2"),
+            &ObjectTypeDefinitionPosition {
+                type_name: name!("T"),
+            }
+            .into(),
+            None,
+            &context,
+        )?;
+        for field_name in ["id", "y"] {
+            add_path_to_node(
+                &mut dependency_graph,
+                node,
+                &node_path
+                    .path_in_node
+                    .with_pushed(Arc::new(OpPathElement::Field(field(
+                        &s2_schema, "T", field_name,
+                    )))),
+            )?;
+        }
+        let node_weight = dependency_graph.node_weight(node)?;
+        let operation = |generate_query_fragments| -> Result<String, FederationError> {
+            let config = QueryPlannerConfig {
+                generate_query_fragments,
+                ..Default::default()
+            };
+            let Some(PlanNode::Flatten(flatten_node)) =
+                node_weight.to_plan_node(&config, &[], None)?
+            else {
+                panic!("expected a flatten node");
+            };
+            let PlanNode::Fetch(fetch_node) = &flatten_node.node else {
+                panic!("expected a fetch node");
+            };
+            Ok(fetch_node.operation_document.to_string())
+        };
+        insta::assert_snapshot!(operation(false)?, @r###"
+        query($representations: [_Any!]!) {
+          _entities(representations: $representations) {
+            ... on T {
+              id
+              y
+            }
+          }
+        }
+        "###);
+        insta::assert_snapshot!(operation(true)?, @r###"
+        query($representations: [_Any!]!) {
+          _entities(representations: $representations) {
+            ..._generated_onT2_0
+          }
+        }
+
+        fragment _generated_onT2_0 on T {
+          id
+          y
+        }
+        "###);
+        Ok(())
+    }
+
+    #[test]
+    fn validates_fetch_operations_only_if_configured() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&requires_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let query_graph = build_federated_query_graph(
+            supergraph.schema,
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?;
+        let s1_schema = query_graph.schema_by_source("S1")?;
+        let document = |operation: &str| {
+            ExecutableDocument::parse(s1_schema.schema(), operation, "operation.graphql").unwrap()
+        };
+
+        let valid = document("query Q($skip: Boolean!) { t { id @skip(if: $skip) } }");
+        assert!(validate_fetch_operation(valid.clone(), "S1", s1_schema, false).is_ok());
+        assert!(validate_fetch_operation(valid, "S1", s1_schema, true).is_ok());
+
+        // The definition of `$skip` is missing.
+        let invalid = document("{ t { id @skip(if: $skip) } }");
+        let operation = validate_fetch_operation(invalid.clone(), "S1", s1_schema, false)?;
+        assert_eq!(operation.to_string(), invalid.to_string());
+        let Err(error) = validate_fetch_operation(invalid, "S1", s1_schema, true) else {
+            panic!("the fetch operation should be invalid");
+        };
+        let message = error.to_string();
+        assert!(message.contains(
+            "Query planning produced an invalid operation for subgraph \"S1\": Error: variable `$skip` is not defined"
+        ));
+        assert!(message.contains("Operation:\n{\n  t {\n    id @skip(if: $skip)\n  }\n}"));
+        Ok(())
+    }
+
+    #[test]
+    fn wraps_key_fetches_in_their_path_conditions() -> Result<(), FederationError> {
+        let supergraph = crate::Supergraph::new(&requires_supergraph())?;
+        let api_schema = supergraph.to_api_schema(Default::default())?;
+        let supergraph_schema = supergraph.schema;
+        let query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            ValidFederationSchema::new(api_schema)?,
+            Some(false),
+            None,
+        )?);
+        let s1_schema = query_graph.schema_by_source("S1")?.clone();
+        let s2_schema = query_graph.schema_by_source("S2")?.clone();
+        let mut dependency_graph = FetchDependencyGraph::new(
+            supergraph_schema,
+            query_graph,
+            None,
+            0,
+            None,
+            Default::default(),
+        );
+        let root = dependency_graph.get_or_create_root_node(
+            &NodeStr::new("S1"),
+            SchemaRootDefinitionKind::Query,
+            ObjectTypeDefinitionPosition {
+                type_name: name!("Query"),
+            }
+            .into(),
+        )?;
+
+        // { t @include(if: $x) { y } }, where `y` is fetched from S2 through a key jump.
+        let mut t_data = field(&s1_schema, "Query", "t").data().clone();
+        t_data.directives = Arc::new(DirectiveList(vec![Node::new(Directive {
+            name: name!("include"),
+            arguments: vec![Node::new(Argument {
+                name: name!("if"),
+                value: Node::new(Value::Variable(name!("x"))),
+            })],
+        })]));
+        let t_element = OpPathElement::Field(NormalizedField::new(t_data));
+        let context = OpGraphPathContext::default().with_context_of(&t_element)?;
+        let t_path = FetchDependencyGraphNodePath::default().add(Arc::new(t_element))?;
+        let (node, node_path) = dependency_graph.new_key_node_at_path(
+            root,
+            &t_path,
+            parse_selection_set(&s1_schema, "T", "__typename id"),
+            NodeStr::new("S2"),
+            &ObjectTypeDefinitionPosition {
+                type_name: name!("T"),
+            }
+            .into(),
+            None,
+            &context,
+        )?;
+        add_path_to_node(
+            &mut dependency_graph,
+            node,
+            &node_path
+                .path_in_node
+                .with_pushed(Arc::new(OpPathElement::Field(field(&s2_schema, "T", "y")))),
+        )?;
+        let node_weight = dependency_graph.node_weight(node)?;
+        assert_eq!(
+            node_weight.selection_set.selection_set.to_string(),
+            "{ ... on T @include(if: $x) { y } }"
+        );
+
+        let document = ExecutableDocument::parse(
+            s1_schema.schema(),
+            "query($x: Boolean!) { t @include(if: $x) { id } }",
+            "operation.graphql",
+        )?;
+        let variable_definitions = &document.anonymous_operation.as_ref().unwrap().variables;
+        let processor = FetchDependencyGraphToQueryPlanProcessor::default();
+        let plan_node = processor
+            .on_node(
+                node_weight,
+                &Conditions::Boolean(true),
+                variable_definitions,
+                None,
+            )?
+            .unwrap();
+        let plan = QueryPlan {
+            node: Some(plan_node.into()),
+            warnings: Vec::new(),
+            has_introspection: false,
+        };
+        insta::assert_snapshot!(serde_json::to_string_pretty(&plan.to_json()).unwrap(), @r###"
+        {
+          "kind": "QueryPlan",
+          "node": {
+            "condition": "x",
+            "ifClause": {
+              "kind": "Flatten",
+              "node": {
+                "kind": "Fetch",
+                "operation": "query($representations: [_Any!]!, $x: Boolean!) { _entities(representations: $representations) { ... on T @include(if: $x) { y } } }",
+                "operationKind": "query",
+                "requires": [
+                  "... on T { __typename id }"
+                ],
+                "retrySafe": true,
+                "serviceName": "S2",
+                "variableUsages": [
+                  "x"
+                ]
+              },
+              "path": [
+                "t"
+              ]
+            },
+            "kind": "Condition"
+          }
+        }
+        "###);
+
+        // Within a condition node on `$x`, the fetch isn't wrapped again.
+        let handled_conditions = node_weight.conditions().clone();
+        let plan_node = processor
+            .on_node(node_weight, &handled_conditions, variable_definitions, None)?
+            .unwrap();
+        assert!(matches!(plan_node, PlanNode::Flatten(_)));
+
+        // Without the definition of `$x`, the fetch operation is invalid, which is only reported
+        // when subgraph validation is enabled.
+        assert!(processor
+            .on_node(node_weight, &handled_conditions, &[], None)
+            .is_ok());
+        let processor =
+            FetchDependencyGraphToQueryPlanProcessor::new(Arc::new(QueryPlannerConfig {
+                subgraph_graphql_validation: true,
+                ..Default::default()
+            }));
+        let Err(error) = processor.on_node(node_weight, &handled_conditions, &[], None) else {
+            panic!("the fetch operation should be invalid");
+        };
+        insta::assert_snapshot!(error, @r###"
+        An internal error has occurred, please report this bug to Apollo.
+
+        Details: Query planning produced an invalid operation for subgraph "S2": Error: variable `$x` is not defined
+
+        Operation:
+        query($representations: [_Any!]!) {
+          _entities(representations: $representations) {
+            ... on T @include(if: $x) {
+              y
+            }
+          }
+        }
+        "###);
+        Ok(())
+    }
 }
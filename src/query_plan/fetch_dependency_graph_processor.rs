@@ -1,2 +1,229 @@
-pub(crate) struct FetchDependencyGraphToQueryPlanProcessor;
-pub(crate) struct FetchDependencyGraphToCostProcessor;
+use crate::error::FederationError;
+use crate::query_plan::conditions::Conditions;
+use crate::query_plan::fetch_dependency_graph::{FetchDependencyGraph, FetchDependencyGraphNode};
+use crate::query_plan::query_planner::{QueryPlanCostConfig, QueryPlannerConfig};
+use crate::query_plan::{ConditionNode, ParallelNode, PlanNode, QueryPlanCost, SequenceNode};
+use apollo_compiler::ast::Name;
+use apollo_compiler::executable::VariableDefinition;
+use apollo_compiler::Node;
+use std::sync::Arc;
+
+/// Converts fetch dependency graphs to query plans.
+#[derive(Clone, Default)]
+pub(crate) struct FetchDependencyGraphToQueryPlanProcessor {
+    /// The configuration of the query planner, which determines how fetch operations are generated
+    /// (see `FetchDependencyGraphNode::to_plan_node()`).
+    config: Arc<QueryPlannerConfig>,
+}
+
+impl FetchDependencyGraphToQueryPlanProcessor {
+    pub(crate) fn new(config: Arc<QueryPlannerConfig>) -> Self {
+        Self { config }
+    }
+
+    /// Converts the given fetch into its plan node (see `FetchDependencyGraphNode::to_plan_node()`),
+    /// wrapped in the condition nodes for those of its `@skip`/`@include` conditions that aren't
+    /// already handled by an enclosing condition node.
+    pub(crate) fn on_node(
+        &self,
+        node: &FetchDependencyGraphNode,
+        handled_conditions: &Conditions,
+        variable_definitions: &[Node<VariableDefinition>],
+        operation_name: Option<Name>,
+    ) -> Result<Option<PlanNode>, FederationError> {
+        let conditions = node.conditions().update_with(handled_conditions);
+        Ok(self.on_conditions(
+            &conditions,
+            node.to_plan_node(&self.config, variable_definitions, operation_name)?,
+        ))
+    }
+
+    /// Combines the given plan nodes into a node executing them in parallel, or returns the only
+    /// one given as is.
+    pub(crate) fn reduce_parallel(&self, mut nodes: Vec<PlanNode>) -> Option<PlanNode> {
+        match nodes.len() {
+            0 => None,
+            1 => nodes.pop(),
+            _ => Some(PlanNode::Parallel(Arc::new(ParallelNode { nodes }))),
+        }
+    }
+
+    /// Combines the given plan nodes into a node executing them one after the other, or returns
+    /// the only one given as is.
+    pub(crate) fn reduce_sequence(&self, mut nodes: Vec<PlanNode>) -> Option<PlanNode> {
+        match nodes.len() {
+            0 => None,
+            1 => nodes.pop(),
+            _ => Some(PlanNode::Sequence(Arc::new(SequenceNode { nodes }))),
+        }
+    }
+
+    /// Wraps the plan node of a fetch (or of a group of fetches) in the condition nodes its
+    /// `@skip`/`@include` conditions require, with one condition node per variable. Constant
+    /// conditions either keep the node as is, or remove it entirely when they can never hold.
+    ///
+    /// Callers should remove the conditions already handled by an enclosing condition node (see
+    /// `Conditions::update_with()`) beforehand, to avoid checking the same variable twice.
+    pub(crate) fn on_conditions(
+        &self,
+        conditions: &Conditions,
+        value: Option<PlanNode>,
+    ) -> Option<PlanNode> {
+        let value = value?;
+        match conditions {
+            Conditions::Boolean(true) => Some(value),
+            Conditions::Boolean(false) => None,
+            Conditions::Variables(variables) => {
+                Some(variables.iter().fold(value, |node, (variable, negated)| {
+                    let (if_clause, else_clause) = if negated {
+                        (None, Some(node))
+                    } else {
+                        (Some(node), None)
+                    };
+                    PlanNode::Condition(Arc::new(ConditionNode {
+                        condition_variable: variable.clone(),
+                        if_clause,
+                        else_clause,
+                    }))
+                }))
+            }
+        }
+    }
+}
+
+/// Computes the cost of the query plan a fetch dependency graph would yield, which is used to pick
+/// the best plan among the candidates generated during query planning (the lower, the better).
+pub(crate) struct FetchDependencyGraphToCostProcessor {
+    config: QueryPlanCostConfig,
+}
+
+impl FetchDependencyGraphToCostProcessor {
+    pub(crate) fn new(config: QueryPlanCostConfig) -> Self {
+        Self { config }
+    }
+
+    /// Optimizes the given fetch dependency graph (so that plans are compared as they would be
+    /// executed), and returns its cost as the weighted sum of its number of fetches, its depth, and
+    /// the estimated size of the responses to its fetches.
+    pub(crate) fn process(
+        &self,
+        dependency_graph: &mut FetchDependencyGraph,
+    ) -> Result<QueryPlanCost, FederationError> {
+        dependency_graph.optimize()?;
+        let fetch_count = dependency_graph.fetch_count() as QueryPlanCost;
+        let depth = dependency_graph.depth()? as QueryPlanCost;
+        let response_size =
+            dependency_graph.estimated_response_size(self.config.list_size_estimate)?;
+        Ok(self.config.fetch_weight * fetch_count
+            + self.config.depth_weight * depth
+            + self.config.response_size_weight * response_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::conditions::Conditions;
+    use crate::query_plan::fetch_dependency_graph_processor::FetchDependencyGraphToQueryPlanProcessor;
+    use crate::query_plan::{FetchNode, PlanNode};
+    use apollo_compiler::executable::{DirectiveList, OperationType};
+    use apollo_compiler::{ExecutableDocument, NodeStr, Schema};
+    use std::sync::Arc;
+
+    fn fetch() -> PlanNode {
+        let schema = Schema::parse_and_validate("type Query { a: Int }", "schema.graphql").unwrap();
+        PlanNode::Fetch(Arc::new(FetchNode {
+            subgraph_name: NodeStr::new("S1"),
+            id: None,
+            has_defers: None,
+            variable_usages: vec![],
+            requires: vec![],
+            operation_document: ExecutableDocument::parse_and_validate(
+                &schema,
+                "{ a }",
+                "operation.graphql",
+            )
+            .unwrap(),
+            operation_name: None,
+            operation_kind: OperationType::Query,
+            input_rewrites: vec![],
+            output_rewrites: vec![],
+            context_rewrites: vec![],
+            authorization: None,
+        }))
+    }
+
+    /// The conditions of the given directives, e.g. `@include(if: $a) @skip(if: $b)`.
+    fn conditions(directives: &str) -> Conditions {
+        let schema = Schema::parse_and_validate(
+            "type Query { a(a: Boolean, b: Boolean): Int }",
+            "schema.graphql",
+        )
+        .unwrap();
+        let document = ExecutableDocument::parse_and_validate(
+            &schema,
+            format!("query($a: Boolean!, $b: Boolean!) {{ a(a: $a, b: $b) {directives} }}"),
+            "operation.graphql",
+        )
+        .unwrap();
+        let operation = document.anonymous_operation.as_ref().unwrap();
+        let directives: &DirectiveList = &operation.selection_set.selections[0]
+            .as_field()
+            .unwrap()
+            .directives;
+        Conditions::from_directives(directives).unwrap()
+    }
+
+    fn describe(node: &PlanNode) -> String {
+        match node {
+            PlanNode::Fetch(fetch) => format!("Fetch({})", fetch.subgraph_name),
+            PlanNode::Condition(condition) => format!(
+                "If(${}, {}, {})",
+                condition.condition_variable,
+                condition
+                    .if_clause
+                    .as_ref()
+                    .map_or("-".to_owned(), describe),
+                condition
+                    .else_clause
+                    .as_ref()
+                    .map_or("-".to_owned(), describe),
+            ),
+            _ => unimplemented!(),
+        }
+    }
+
+    #[test]
+    fn wraps_nodes_in_condition_nodes() {
+        let processor = FetchDependencyGraphToQueryPlanProcessor::default();
+        let on_conditions = |directives: &str| {
+            processor
+                .on_conditions(&conditions(directives), Some(fetch()))
+                .as_ref()
+                .map(describe)
+        };
+        assert_eq!(on_conditions("").unwrap(), "Fetch(S1)");
+        assert_eq!(on_conditions("@include(if: false)"), None);
+        assert_eq!(
+            on_conditions("@include(if: $a)").unwrap(),
+            "If($a, Fetch(S1), -)",
+        );
+        assert_eq!(
+            on_conditions("@include(if: $a) @skip(if: $b)").unwrap(),
+            "If($b, -, If($a, Fetch(S1), -))",
+        );
+    }
+
+    #[test]
+    fn skips_conditions_handled_by_enclosing_condition_nodes() {
+        let processor = FetchDependencyGraphToQueryPlanProcessor::default();
+        let handled = conditions("@include(if: $a)");
+        let nested = conditions("@include(if: $a) @skip(if: $b)").update_with(&handled);
+        let node = processor.on_conditions(&nested, Some(fetch())).unwrap();
+        assert_eq!(describe(&node), "If($b, -, Fetch(S1))");
+
+        let same = conditions("@include(if: $a)").update_with(&handled);
+        assert_eq!(same, Conditions::Boolean(true));
+        let contradicting = conditions("@skip(if: $a)").update_with(&handled);
+        assert_eq!(contradicting, Conditions::Boolean(false));
+    }
+}
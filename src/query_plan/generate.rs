@@ -11,6 +11,17 @@ struct Partial<Plan, Element> {
     index: Option<usize>,
 }
 
+/// The best plan found by `generate_all_plans_and_find_best`.
+pub struct GeneratedPlan<Plan> {
+    pub plan: Plan,
+    pub cost: QueryPlanCost,
+    /// The number of options that were evaluated to find this plan.
+    pub evaluated_options: usize,
+    /// Whether all the plans were considered, i.e. whether generation wasn't stopped early due to
+    /// the `max_evaluated_options` limit. If it was, then this plan may not be the best one.
+    pub is_exhaustive: bool,
+}
+
 struct Extracted<Element> {
     extracted: Element,
     is_last: bool,
@@ -83,13 +94,19 @@ struct Extracted<Element> {
 ///   with both the cost of that plan and the best cost we have generated thus far
 ///   (if that's not the first plan generated).
 ///   This mostly exists to allow some debugging.
+/// * `max_evaluated_options`: if set, the maximum number of options (i.e. elements added to a
+///   plan, whose cost is then computed) to evaluate. Once reached, generation stops and the best
+///   plan found so far is returned. Since the first plan generated picks the first choice of every
+///   element of `to_add`, that's at worst this "greedy" plan (generation always continues until it
+///   has a complete plan, so the limit may be exceeded when it's lower than `to_add.len()`).
 pub fn generate_all_plans_and_find_best<Plan, Element>(
     initial: Plan,
     to_add: Vec<Choices<Element>>,
     mut add_function: impl FnMut(&Plan, Element) -> Plan,
     mut cost_function: impl FnMut(&Plan) -> QueryPlanCost,
     mut on_plan: impl FnMut(&Plan, QueryPlanCost, Option<QueryPlanCost>),
-) -> (Plan, QueryPlanCost)
+    max_evaluated_options: Option<usize>,
+) -> GeneratedPlan<Plan>
 where
     Element: Clone,
     // Uncomment to use `dbg!()`
@@ -97,7 +114,12 @@ where
 {
     if to_add.is_empty() {
         let cost = cost_function(&initial);
-        return (initial, cost);
+        return GeneratedPlan {
+            plan: initial,
+            cost,
+            evaluated_options: 0,
+            is_exhaustive: true,
+        };
     }
     // Note: we save ourselves the computation of the cost of `initial`
     // (we pass no `partial_cost` in this initialization).
@@ -118,6 +140,8 @@ where
     });
 
     let mut min = None;
+    let mut evaluated_options = 0;
+    let mut is_exhaustive = true;
     while let Some(Partial {
         partial_plan,
         partial_cost,
@@ -135,6 +159,12 @@ where
             }
         }
 
+        if min.is_some() && max_evaluated_options.is_some_and(|max| evaluated_options >= max) {
+            // We're out of budget, so we settle for the best plan we have.
+            is_exhaustive = false;
+            break;
+        }
+
         // Does not panic as we only ever insert in the stack with non-empty `remaining`
         let next_choices = &mut remaining.as_mut_slice()[0];
 
@@ -142,6 +172,7 @@ where
         let Extracted { extracted, is_last } = extract(picked_index, next_choices);
         let new_partial_plan = add_function(&partial_plan, extracted);
         let cost = cost_function(&new_partial_plan);
+        evaluated_options += 1;
 
         if !is_last {
             // First, re-insert what correspond to all the choices not in `extracted`.
@@ -187,7 +218,13 @@ where
             )
         }
     }
-    min.expect("A plan should have been found")
+    let (plan, cost) = min.expect("A plan should have been found");
+    GeneratedPlan {
+        plan,
+        cost,
+        evaluated_options,
+        is_exhaustive,
+    }
 }
 
 fn insert_in_stack<Plan, Element>(
@@ -230,9 +267,18 @@ mod tests {
 
     /// Returns (best, generated)
     fn generate_test_plans(initial: Plan, choices: Vec<Vec<Option<Element>>>) -> (Plan, Vec<Plan>) {
+        let (best, generated) = generate_test_plans_with_limit(initial, choices, None);
+        (best.plan, generated)
+    }
+
+    fn generate_test_plans_with_limit(
+        initial: Plan,
+        choices: Vec<Vec<Option<Element>>>,
+        max_evaluated_options: Option<usize>,
+    ) -> (GeneratedPlan<Plan>, Vec<Plan>) {
         let mut generated = Vec::new();
         let target_len = initial.len() + choices.len();
-        let (best, _) = generate_all_plans_and_find_best::<Plan, Element>(
+        let best = generate_all_plans_and_find_best::<Plan, Element>(
             initial,
             choices,
             |partial_plan, new_element| {
@@ -252,6 +298,7 @@ mod tests {
                     .sum()
             },
             |_, _, _| {},
+            max_evaluated_options,
         );
         (best, generated)
     }
@@ -320,4 +367,23 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn settles_for_best_plan_so_far_when_out_of_budget() {
+        let choices = vec![
+            vec![Some("A1Costly"), Some("B1")],
+            vec![Some("A2"), Some("B2x")],
+            vec![Some("A3"), Some("B3x")],
+        ];
+        let (best, generated) = generate_test_plans_with_limit(vec!["I"], choices.clone(), Some(4));
+        // The first plan takes 3 options, and the 4th one doesn't complete a better plan.
+        assert_eq!(best.plan, ["I", "A1Costly", "A2", "A3"]);
+        assert_eq!(best.evaluated_options, 4);
+        assert!(!best.is_exhaustive);
+        assert_eq!(generated.len(), 1);
+
+        let (best, _) = generate_test_plans_with_limit(vec!["I"], choices, None);
+        assert_eq!(best.plan, ["I", "B1", "A2", "A3"]);
+        assert!(best.is_exhaustive);
+    }
 }
@@ -0,0 +1,332 @@
+use crate::query_plan::serialization::ChildSerializer;
+use crate::query_plan::{
+    ConditionNode, DeferNode, DeferredDeferBlock, FlattenNode, ParallelNode, PlanNode,
+    PrimaryDeferBlock, QueryPlan, SequenceNode, SubscriptionNode, TopLevelPlanNode,
+};
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::io;
+use std::sync::Arc;
+
+/// Interns the nodes of query plans, so that plan nodes with the same content are shared (i.e.
+/// point to the same `Arc`). Large operations over lists of entities tend to produce many identical
+/// fetch subtrees, and sharing them reduces the memory used by plans, as well as the size of their
+/// serialization with `QueryPlan::to_json_with_references()`.
+///
+/// Nodes are interned by a hash of their JSON serialization, and only compared to the interned
+/// nodes with the same hash. Since the children of a node are interned before the node itself,
+/// equal children are the same `Arc`, so the serialization used for a node only contains the
+/// address of its children, and is thus computed once per node (instead of once per node and
+/// ancestor).
+#[derive(Default)]
+pub(crate) struct PlanNodeInterner {
+    /// The interned nodes by content hash. This also keeps them alive, ensuring the addresses of
+    /// the children in those hashes aren't reused by other nodes.
+    nodes: IndexMap<u64, Vec<PlanNode>>,
+    /// The number of distinct plan nodes interned so far.
+    len: usize,
+}
+
+/// Serializes children by their address, as they've been interned already.
+struct InternedChildSerializer;
+
+impl ChildSerializer for InternedChildSerializer {
+    fn child(&mut self, node: &PlanNode) -> Value {
+        json!({ "ptr": node.ptr() as usize })
+    }
+}
+
+impl PlanNodeInterner {
+    /// Interns the nodes of the given query plan.
+    pub(crate) fn intern_plan(&mut self, plan: QueryPlan) -> QueryPlan {
+        let node = plan.node.map(|node| match node {
+            TopLevelPlanNode::Subscription(SubscriptionNode { primary, rest }) => {
+                TopLevelPlanNode::Subscription(SubscriptionNode {
+                    primary,
+                    rest: rest.map(|rest| self.intern(&rest)),
+                })
+            }
+            TopLevelPlanNode::Fetch(node) => TopLevelPlanNode::Fetch(node),
+            TopLevelPlanNode::Sequence(node) => {
+                TopLevelPlanNode::Sequence(node.with_interned_children(self))
+            }
+            TopLevelPlanNode::Parallel(node) => {
+                TopLevelPlanNode::Parallel(node.with_interned_children(self))
+            }
+            TopLevelPlanNode::Flatten(node) => {
+                TopLevelPlanNode::Flatten(node.with_interned_children(self))
+            }
+            TopLevelPlanNode::Defer(node) => {
+                TopLevelPlanNode::Defer(node.with_interned_children(self))
+            }
+            TopLevelPlanNode::Condition(node) => {
+                TopLevelPlanNode::Condition(node.with_interned_children(self))
+            }
+        });
+        QueryPlan {
+            node,
+            warnings: plan.warnings,
+            has_introspection: plan.has_introspection,
+        }
+    }
+
+    /// Returns the interned node with the same content as the given node (interning it if there's
+    /// none yet).
+    pub(crate) fn intern(&mut self, node: &PlanNode) -> PlanNode {
+        let node = match node {
+            PlanNode::Fetch(node) => PlanNode::Fetch(node.clone()),
+            PlanNode::Sequence(node) => {
+                PlanNode::Sequence(Arc::new(node.with_interned_children(self)))
+            }
+            PlanNode::Parallel(node) => {
+                PlanNode::Parallel(Arc::new(node.with_interned_children(self)))
+            }
+            PlanNode::Flatten(node) => {
+                PlanNode::Flatten(Arc::new(node.with_interned_children(self)))
+            }
+            PlanNode::Defer(node) => PlanNode::Defer(Arc::new(node.with_interned_children(self))),
+            PlanNode::Condition(node) => {
+                PlanNode::Condition(Arc::new(node.with_interned_children(self)))
+            }
+        };
+        let content = node.to_json(&mut InternedChildSerializer);
+        let candidates = self.nodes.entry(content_hash(&content)).or_default();
+        if let Some(interned) = candidates
+            .iter()
+            .find(|candidate| candidate.to_json(&mut InternedChildSerializer) == content)
+        {
+            return interned.clone();
+        }
+        candidates.push(node.clone());
+        self.len += 1;
+        node
+    }
+
+    /// The number of distinct plan nodes interned so far.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Hashes the given serialization of a plan node, without building its string.
+fn content_hash(content: &Value) -> u64 {
+    struct HashWriter(DefaultHasher);
+
+    impl io::Write for HashWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut writer = HashWriter(DefaultHasher::new());
+    // Writing to a hasher can't fail, and neither can serializing a JSON value.
+    let _ = serde_json::to_writer(&mut writer, content);
+    writer.0.finish()
+}
+
+impl SequenceNode {
+    fn with_interned_children(&self, interner: &mut PlanNodeInterner) -> Self {
+        Self {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| interner.intern(node))
+                .collect(),
+        }
+    }
+}
+
+impl ParallelNode {
+    fn with_interned_children(&self, interner: &mut PlanNodeInterner) -> Self {
+        Self {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| interner.intern(node))
+                .collect(),
+        }
+    }
+}
+
+impl FlattenNode {
+    fn with_interned_children(&self, interner: &mut PlanNodeInterner) -> Self {
+        Self {
+            path: self.path.clone(),
+            node: interner.intern(&self.node),
+        }
+    }
+}
+
+impl DeferNode {
+    fn with_interned_children(&self, interner: &mut PlanNodeInterner) -> Self {
+        Self {
+            primary: PrimaryDeferBlock {
+                node: self.primary.node.as_ref().map(|node| interner.intern(node)),
+                ..self.primary.clone()
+            },
+            deferred: self
+                .deferred
+                .iter()
+                .map(|deferred| DeferredDeferBlock {
+                    node: deferred.node.as_ref().map(|node| interner.intern(node)),
+                    ..deferred.clone()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ConditionNode {
+    fn with_interned_children(&self, interner: &mut PlanNodeInterner) -> Self {
+        Self {
+            condition_variable: self.condition_variable.clone(),
+            if_clause: self.if_clause.as_ref().map(|node| interner.intern(node)),
+            else_clause: self.else_clause.as_ref().map(|node| interner.intern(node)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::interning::PlanNodeInterner;
+    use crate::query_plan::{
+        FetchDataPathElement, FetchNode, FlattenNode, ParallelNode, PlanNode, QueryPlan,
+        SequenceNode, TopLevelPlanNode,
+    };
+    use apollo_compiler::executable::OperationType;
+    use apollo_compiler::{ExecutableDocument, NodeStr, Schema};
+    use std::sync::Arc;
+
+    fn fetch(subgraph_name: &str, operation: &str) -> FetchNode {
+        let schema = Schema::parse_and_validate(
+            "type Query { products: [Product] } type Product { upc: ID reviews: [Int] }",
+            "schema.graphql",
+        )
+        .unwrap();
+        FetchNode {
+            subgraph_name: NodeStr::new(subgraph_name),
+            id: None,
+            has_defers: None,
+            variable_usages: vec![],
+            requires: vec![],
+            operation_document: ExecutableDocument::parse_and_validate(
+                &schema,
+                operation,
+                "operation.graphql",
+            )
+            .unwrap(),
+            operation_name: None,
+            operation_kind: OperationType::Query,
+            input_rewrites: vec![],
+            output_rewrites: vec![],
+            context_rewrites: vec![],
+            authorization: None,
+        }
+    }
+
+    fn flatten_reviews() -> PlanNode {
+        PlanNode::Flatten(Arc::new(FlattenNode {
+            path: vec![
+                FetchDataPathElement::Key(NodeStr::new("products")),
+                FetchDataPathElement::AnyIndex,
+            ],
+            node: PlanNode::Fetch(Arc::new(fetch("reviews", "{ products { reviews } }"))),
+        }))
+    }
+
+    #[test]
+    fn interns_identical_subtrees() {
+        // Two identical (but separately built) flatten subtrees, and a distinct one.
+        let plan = QueryPlan {
+            node: Some(TopLevelPlanNode::Sequence(SequenceNode {
+                nodes: vec![
+                    PlanNode::Fetch(Arc::new(fetch("products", "{ products { upc } }"))),
+                    PlanNode::Parallel(Arc::new(ParallelNode {
+                        nodes: vec![flatten_reviews(), flatten_reviews()],
+                    })),
+                    flatten_reviews(),
+                ],
+            })),
+            warnings: Vec::new(),
+            has_introspection: false,
+        };
+        let json_before = plan.to_json();
+        let mut interner = PlanNodeInterner::default();
+        let plan = interner.intern_plan(plan);
+        assert_eq!(plan.to_json(), json_before);
+        // The products fetch, the reviews fetch, the flatten node and the parallel node.
+        assert_eq!(interner.len(), 4);
+
+        let Some(TopLevelPlanNode::Sequence(sequence)) = &plan.node else {
+            panic!("Expected a sequence");
+        };
+        let PlanNode::Parallel(parallel) = &sequence.nodes[1] else {
+            panic!("Expected a parallel node");
+        };
+        assert_eq!(parallel.nodes[0].ptr(), parallel.nodes[1].ptr());
+        assert_eq!(parallel.nodes[0].ptr(), sequence.nodes[2].ptr());
+
+        insta::assert_snapshot!(
+            serde_json::to_string_pretty(&plan.to_json_with_references()).unwrap(),
+            @r###"
+        {
+          "kind": "QueryPlan",
+          "node": {
+            "kind": "Sequence",
+            "nodes": [
+              {
+                "kind": "Fetch",
+                "operation": "{ products { upc } }",
+                "operationKind": "query",
+                "retrySafe": true,
+                "serviceName": "products",
+                "variableUsages": []
+              },
+              {
+                "kind": "Parallel",
+                "nodes": [
+                  {
+                    "id": 0,
+                    "kind": "Reference"
+                  },
+                  {
+                    "id": 0,
+                    "kind": "Reference"
+                  }
+                ]
+              },
+              {
+                "id": 0,
+                "kind": "Reference"
+              }
+            ]
+          },
+          "sharedNodes": [
+            {
+              "kind": "Flatten",
+              "node": {
+                "kind": "Fetch",
+                "operation": "{ products { reviews } }",
+                "operationKind": "query",
+                "retrySafe": true,
+                "serviceName": "reviews",
+                "variableUsages": []
+              },
+              "path": [
+                "products",
+                "@"
+              ]
+            }
+          ]
+        }
+        "###
+        );
+    }
+}
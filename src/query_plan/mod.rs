@@ -1,3 +1,4 @@
+use crate::AuthorizationRequirements;
 use apollo_compiler::executable::{
     Field, InlineFragment, Name, OperationType, Selection, SelectionSet,
 };
@@ -5,18 +6,68 @@ use apollo_compiler::validation::Valid;
 use apollo_compiler::{ExecutableDocument, NodeStr};
 use std::sync::Arc;
 
-pub(crate) mod conditions;
+mod authorization;
+pub mod conditions;
+mod display;
 pub(crate) mod fetch_dependency_graph;
 pub(crate) mod fetch_dependency_graph_processor;
+#[doc(hidden)]
 pub mod generate;
+pub(crate) mod interning;
+#[doc(hidden)]
 pub mod operation;
 pub mod query_planner;
 pub(crate) mod query_planning_traversal;
+mod rewrites;
+mod serialization;
+mod subgraph_request;
+
+pub use conditions::Conditions;
+pub use operation::{NormalizedOperation, NormalizedSelectionSet};
+pub use query_planner::{PlanningTrace, QueryPlanningWarning};
+pub use subgraph_request::SubgraphRequest;
 
 pub type QueryPlanCost = i64;
 
 pub struct QueryPlan {
     node: Option<TopLevelPlanNode>,
+    /// The non-fatal diagnostics reported while planning, which executors can log or monitor.
+    warnings: Vec<QueryPlanningWarning>,
+    /// Whether the operation selects `__schema` or `__type` at its root (see
+    /// `NormalizedOperation::has_introspection()`).
+    has_introspection: bool,
+}
+
+impl QueryPlan {
+    /// The plan of an operation that doesn't need to fetch anything, e.g. because it only selects
+    /// introspection fields, or because all of its selections are skipped. Its JSON serialization
+    /// is `{ "kind": "QueryPlan" }`.
+    pub fn empty() -> Self {
+        Self {
+            node: None,
+            warnings: Vec::new(),
+            has_introspection: false,
+        }
+    }
+
+    /// Whether this plan has nothing to fetch (see `empty()`). Note that it may still have
+    /// warnings, or introspection fields to resolve (see `has_introspection()`).
+    pub fn is_empty(&self) -> bool {
+        self.node.is_none()
+    }
+
+    /// The warnings reported while planning this plan. These don't make the plan incorrect, but
+    /// point at planner behavior worth monitoring (e.g. a possibly suboptimal plan).
+    pub fn warnings(&self) -> &[QueryPlanningWarning] {
+        &self.warnings
+    }
+
+    /// Whether the operation selects introspection fields (`__schema` or `__type`) that this plan
+    /// doesn't fetch, and that the executor must resolve itself against the API schema. The plan of
+    /// an operation only selecting those has no node.
+    pub fn has_introspection(&self) -> bool {
+        self.has_introspection
+    }
 }
 
 pub enum TopLevelPlanNode {
@@ -34,6 +85,7 @@ pub struct SubscriptionNode {
     rest: Option<PlanNode>,
 }
 
+#[derive(Clone)]
 pub enum PlanNode {
     Fetch(Arc<FetchNode>),
     Sequence(Arc<SequenceNode>),
@@ -43,6 +95,7 @@ pub enum PlanNode {
     Condition(Arc<ConditionNode>),
 }
 
+#[derive(Clone)]
 pub struct FetchNode {
     subgraph_name: NodeStr,
     /// Optional identifier for the fetch for defer support. All fetches of a given plan will be
@@ -74,16 +127,28 @@ pub struct FetchNode {
     /// Similar to `input_rewrites`, but for optional "rewrites" to apply to the data that is
     /// received from a fetch (and before it is applied to the current in-memory results).
     output_rewrites: Vec<FetchDataRewrite>,
+    /// Rewrites that query plan executors should apply to collect the values of contextual
+    /// arguments (i.e. arguments with `@fromContext`) for this fetch. Their paths start at each
+    /// input of the fetch, and may go up to its ancestors in the response (see
+    /// `FetchDataPathElement::Parent`), while the key they rename to is the name of the variable
+    /// the value is passed as.
+    context_rewrites: Vec<FetchDataRewrite>,
+    /// The authorization requirements of what this fetch selects, when the plan has been annotated
+    /// with them.
+    authorization: Option<AuthorizationRequirements>,
 }
 
+#[derive(Clone)]
 pub struct SequenceNode {
     nodes: Vec<PlanNode>,
 }
 
+#[derive(Clone)]
 pub struct ParallelNode {
     nodes: Vec<PlanNode>,
 }
 
+#[derive(Clone)]
 pub struct FlattenNode {
     path: Vec<FetchDataPathElement>,
     node: PlanNode,
@@ -105,6 +170,7 @@ pub struct FlattenNode {
 /// we implement more advanced server-side heuristics to decide if deferring is judicious or not.
 /// This allows the executor of the plan to consistently send a defer-abiding multipart response to
 /// the client.
+#[derive(Clone)]
 pub struct DeferNode {
     /// The "primary" part of a defer, that is the non-deferred part (though could be deferred
     /// itself for a nested defer).
@@ -116,6 +182,7 @@ pub struct DeferNode {
 }
 
 /// The primary block of a `DeferNode`.
+#[derive(Clone)]
 pub struct PrimaryDeferBlock {
     /// The part of the original query that "selects" the data to send in that primary response
     /// once the plan in `node` completes). Note that if the parent `DeferNode` is nested, then it
@@ -131,6 +198,7 @@ pub struct PrimaryDeferBlock {
 }
 
 /// A deferred block of a `DeferNode`.
+#[derive(Clone)]
 pub struct DeferredDeferBlock {
     /// References one or more fetch node(s) (by `id`) within `DeferNode.primary.node`. The plan of
     /// this deferred part should not be started until all such fetches return.
@@ -152,6 +220,7 @@ pub struct DeferredDeferBlock {
     node: Option<PlanNode>,
 }
 
+#[derive(Clone)]
 pub struct DeferredDependency {
     /// A `FetchNode` ID.
     id: NodeStr,
@@ -161,6 +230,7 @@ pub struct DeferredDependency {
     defer_label: Option<NodeStr>,
 }
 
+#[derive(Clone)]
 pub struct ConditionNode {
     condition_variable: Name,
     if_clause: Option<PlanNode>,
@@ -197,9 +267,10 @@ pub struct FetchDataKeyRenamer {
 }
 
 /// Vectors of this element match path(s) to a value in fetch data. Each element is (1) a key in
-/// object data, (2) _any_ index in array data (often serialized as `@`), or (3) a typename
-/// constraint on the object data at that point in the path(s) (a path should only match for objects
-/// whose `__typename` is the provided type).
+/// object data, (2) _any_ index in array data (often serialized as `@`), (3) a typename constraint
+/// on the object data at that point in the path(s) (a path should only match for objects whose
+/// `__typename` is the provided type), or (4) the parent of the data at that point in the path(s)
+/// (serialized as `..`, and only used by context rewrites).
 ///
 /// It's possible for vectors of this element to match no paths in fetch data, e.g. if an object key
 /// doesn't exist, or if an object's `__typename` doesn't equal the provided one. If this occurs,
@@ -211,11 +282,12 @@ pub struct FetchDataKeyRenamer {
 /// Note that the `@` is currently optional in some contexts, as query plan execution may assume
 /// upon encountering array data in a path that it should match the remaining path to the array's
 /// elements.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FetchDataPathElement {
     Key(NodeStr),
     AnyIndex,
     TypenameEquals(NodeStr),
+    Parent,
 }
 
 /// Vectors of this element match a path in a query. Each element is (1) a field in a query, or (2)
@@ -1,7 +1,10 @@
-use crate::error::FederationError;
 use crate::error::SingleFederationError::Internal;
+use crate::error::{
+    FederationError, MultipleFederationErrors, RebaseErrorContext, SingleFederationError,
+};
 use crate::query_graph::graph_path::OpPathElement;
 use crate::query_plan::conditions::Conditions;
+use crate::query_plan::operation::interning::SelectionSetInterner;
 use crate::query_plan::operation::normalized_field_selection::{
     NormalizedField, NormalizedFieldData, NormalizedFieldSelection,
 };
@@ -12,28 +15,38 @@ use crate::query_plan::operation::normalized_inline_fragment_selection::{
     NormalizedInlineFragment, NormalizedInlineFragmentData, NormalizedInlineFragmentSelection,
 };
 use crate::query_plan::operation::normalized_selection_map::{
-    Entry, NormalizedFieldSelectionValue, NormalizedFragmentSpreadSelectionValue,
+    NormalizedFieldSelectionValue, NormalizedFragmentSpreadSelectionValue,
     NormalizedInlineFragmentSelectionValue, NormalizedSelectionMap, NormalizedSelectionValue,
 };
 use crate::schema::position::{
     CompositeTypeDefinitionPosition, InterfaceTypeDefinitionPosition, SchemaRootDefinitionKind,
+    SchemaRootDefinitionPosition,
 };
 use crate::schema::ValidFederationSchema;
-use apollo_compiler::ast::{DirectiveList, Name, OperationType};
+use apollo_compiler::ast::{Directive, DirectiveList, Name, OperationType, Value};
 use apollo_compiler::executable::{
     Field, Fragment, FragmentSpread, InlineFragment, Operation, Selection, SelectionSet,
     VariableDefinition,
 };
-use apollo_compiler::{name, Node};
-use indexmap::{IndexMap, IndexSet};
+use apollo_compiler::validation::Valid;
+use apollo_compiler::{name, ExecutableDocument, Node};
+use indexmap::{Equivalent, IndexMap, IndexSet};
+use std::collections::hash_map::DefaultHasher;
 use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::sync::{atomic, Arc};
 
-const TYPENAME_FIELD: Name = name!("__typename");
+#[cfg(feature = "debug_display")]
+mod debug_display;
+mod defer;
+pub mod edit;
+pub(crate) mod fragment_generation;
+mod interning;
+pub mod lint;
+mod signature;
 
-// Global storage for the counter used to uniquely identify selections
-static NEXT_ID: atomic::AtomicUsize = atomic::AtomicUsize::new(1);
+pub(crate) const TYPENAME_FIELD: Name = name!("__typename");
 
 /// Opaque wrapper of the unique selection ID type.
 ///
@@ -42,13 +55,42 @@ static NEXT_ID: atomic::AtomicUsize = atomic::AtomicUsize::new(1);
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub(crate) struct SelectionId(usize);
 
-impl SelectionId {
+/// Allocates the selection IDs of an operation. Each normalized operation has its own generator,
+/// which the later planning stages of the operation allocate from as well, so that selection IDs
+/// only depend on the operation (and not on what was planned before it in the process).
+#[derive(Debug)]
+pub(crate) struct IdGenerator {
+    next_id: atomic::AtomicUsize,
+}
+
+impl IdGenerator {
     pub(crate) fn new() -> Self {
-        // atomically increment global counter
-        Self(NEXT_ID.fetch_add(1, atomic::Ordering::AcqRel))
+        Self {
+            next_id: atomic::AtomicUsize::new(1),
+        }
+    }
+
+    pub(crate) fn next_id(&self) -> SelectionId {
+        SelectionId(self.next_id.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+}
+
+impl Default for IdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The generator of an operation is bookkeeping rather than content, so it never makes operations
+/// differ.
+impl PartialEq for IdGenerator {
+    fn eq(&self, _other: &Self) -> bool {
+        true
     }
 }
 
+impl Eq for IdGenerator {}
+
 /// An analogue of the apollo-compiler type `Operation` with these changes:
 /// - Stores the schema that the operation is queried against.
 /// - Swaps `operation_type` with `root_kind` (using the analogous federation-next type).
@@ -64,6 +106,179 @@ pub struct NormalizedOperation {
     pub(crate) directives: Arc<DirectiveList>,
     pub(crate) selection_set: NormalizedSelectionSet,
     pub(crate) fragments: Arc<IndexMap<Name, Node<NormalizedFragment>>>,
+    /// Whether the operation selected `__schema` or `__type` at its root. Normalization removes
+    /// those fields, as the executor resolves them against the API schema.
+    pub(crate) has_introspection: bool,
+    /// The generator of the selection IDs of this operation.
+    pub(crate) id_generator: Arc<IdGenerator>,
+}
+
+/// Read-only accessors, which are what users outside this crate can rely on (the internal
+/// representation of normalized operations changes as the query planner evolves). The full
+/// operation is available through its `Display` implementation.
+impl NormalizedOperation {
+    /// The type of this operation (query, mutation or subscription).
+    pub fn operation_type(&self) -> OperationType {
+        self.root_kind.into()
+    }
+
+    /// The name of this operation, if it has one.
+    pub fn name(&self) -> Option<&Name> {
+        self.name.as_ref()
+    }
+
+    /// The variables defined by this operation.
+    pub fn variables(&self) -> &[Node<VariableDefinition>] {
+        &self.variables
+    }
+
+    /// The names of the fragments kept by this operation, in document order.
+    pub fn fragment_names(&self) -> impl Iterator<Item = &Name> {
+        self.fragments.keys()
+    }
+
+    /// The normalized selection set of this operation.
+    pub fn selection_set(&self) -> &NormalizedSelectionSet {
+        &self.selection_set
+    }
+
+    /// Whether the operation selects `__schema` or `__type` at its root. These introspection
+    /// fields aren't part of the normalized selection set (and so of query plans), and must be
+    /// resolved by the executor.
+    pub fn has_introspection(&self) -> bool {
+        self.has_introspection
+    }
+
+    /// Normalizes the operation of the given name (or the document's only operation if no name is
+    /// given) against the given schema. See `normalize_operation()` for the transformations this
+    /// applies.
+    ///
+    /// Unlike `QueryPlanner::normalize_operation()`, this doesn't depend on a supergraph, and so
+    /// keeps the operation's fragments and `@defer`s (there's no planner configuration to decide
+    /// otherwise).
+    pub fn from_document(
+        document: &Valid<ExecutableDocument>,
+        operation_name: Option<&str>,
+        schema: &ValidFederationSchema,
+    ) -> Result<Self, FederationError> {
+        let operation = get_operation(document, operation_name)?;
+        normalize_operation(operation, &document.fragments, schema, &IndexSet::new())
+    }
+
+    /// Lifts this operation, normalized against a subgraph schema, onto the given supergraph (or
+    /// API) schema (see `NormalizedSelectionSet::lift_to_supergraph()`). The selection set is
+    /// rebased onto the supergraph root type of the same kind, and named fragments are dropped,
+    /// since normalization already expanded them.
+    pub(crate) fn lift_to_supergraph(
+        &self,
+        supergraph_schema: &ValidFederationSchema,
+    ) -> Result<NormalizedOperation, FederationError> {
+        let root_type_name = SchemaRootDefinitionPosition {
+            root_kind: self.root_kind,
+        }
+        .get(supergraph_schema.schema())?;
+        let root_type = supergraph_schema
+            .get_type(root_type_name.name.clone())?
+            .try_into()?;
+        Ok(NormalizedOperation {
+            schema: supergraph_schema.clone(),
+            root_kind: self.root_kind,
+            name: self.name.clone(),
+            variables: self.variables.clone(),
+            directives: self.directives.clone(),
+            selection_set: self
+                .selection_set
+                .rebase_on(&root_type, supergraph_schema)?,
+            fragments: Default::default(),
+            has_introspection: self.has_introspection,
+            id_generator: self.id_generator.clone(),
+        })
+    }
+
+    /// The definitions of the variables of this operation that the given selection set (typically
+    /// the part of this operation that a fetch sends to a subgraph) uses, in the order this
+    /// operation declares them. Fragment spreads are followed into this operation's fragments.
+    ///
+    /// Fetch operations should declare exactly these variables, as subgraphs reject operations
+    /// declaring variables they don't use.
+    pub(crate) fn used_variable_definitions(
+        &self,
+        selection_set: &NormalizedSelectionSet,
+    ) -> Vec<Node<VariableDefinition>> {
+        let mut used_variables = IndexSet::new();
+        selection_set.collect_variables(&mut used_variables);
+        for fragment in self.used_fragments(selection_set) {
+            collect_directive_variables(&fragment.directives, &mut used_variables);
+            fragment
+                .selection_set
+                .collect_variables(&mut used_variables);
+        }
+        self.variables
+            .iter()
+            .filter(|definition| used_variables.contains(&definition.name))
+            .cloned()
+            .collect()
+    }
+
+    /// The fragments of this operation that the given selection set spreads, directly or through
+    /// other fragments, in the order they're first spread.
+    fn used_fragments<'a>(
+        &'a self,
+        selection_set: &'a NormalizedSelectionSet,
+    ) -> Vec<&'a Node<NormalizedFragment>> {
+        let mut spread_fragments = IndexSet::new();
+        selection_set.collect_fragment_spreads(&mut spread_fragments);
+        let mut used_fragments = Vec::new();
+        let mut index = 0;
+        while let Some(&fragment_name) = spread_fragments.get_index(index) {
+            if let Some(fragment) = self.fragments.get(fragment_name) {
+                fragment
+                    .selection_set
+                    .collect_fragment_spreads(&mut spread_fragments);
+                used_fragments.push(fragment);
+            }
+            index += 1;
+        }
+        used_fragments
+    }
+
+    /// Converts this operation back to an executable document, validated against the given schema
+    /// (typically the one this operation was normalized against, or a subgraph schema when this
+    /// operation is sent to a subgraph).
+    ///
+    /// The document has this operation as its only operation, along with the named fragments the
+    /// operation still spreads. Normalization expands fragment spreads, so those are only the
+    /// fragments kept by later transformations; fragments the operation doesn't use are dropped,
+    /// as validation would reject them.
+    pub fn to_executable_document(
+        &self,
+        schema: &ValidFederationSchema,
+    ) -> Result<Valid<ExecutableDocument>, FederationError> {
+        Ok(self.to_unvalidated_document()?.validate(schema.schema())?)
+    }
+
+    /// Like `to_executable_document()`, but without validating the document.
+    pub(crate) fn to_unvalidated_document(&self) -> Result<ExecutableDocument, FederationError> {
+        let mut document = ExecutableDocument::new();
+        let used_fragments = self.used_fragments(&self.selection_set);
+        // Fragments are emitted in the order this operation's document defined them.
+        for fragment in self.fragments.values() {
+            if used_fragments.iter().any(|used| used.name == fragment.name) {
+                document.fragments.insert(
+                    fragment.name.clone(),
+                    Node::new(fragment.deref().try_into()?),
+                );
+            }
+        }
+        let operation = Node::new(Operation::try_from(self)?);
+        match &self.name {
+            Some(name) => {
+                document.named_operations.insert(name.clone(), operation);
+            }
+            None => document.anonymous_operation = Some(operation),
+        }
+        Ok(document)
+    }
 }
 
 /// An analogue of the apollo-compiler type `SelectionSet` with these changes:
@@ -71,12 +286,31 @@ pub struct NormalizedOperation {
 ///   `NamedType`.
 /// - Stores selections in a map so they can be normalized efficiently.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct NormalizedSelectionSet {
+pub struct NormalizedSelectionSet {
     pub(crate) schema: ValidFederationSchema,
     pub(crate) type_position: CompositeTypeDefinitionPosition,
     pub(crate) selections: Arc<NormalizedSelectionMap>,
 }
 
+/// Read-only accessors, for the same reason as those of `NormalizedOperation`. The full selection
+/// set is available through its `Display` implementation.
+impl NormalizedSelectionSet {
+    /// The name of the type this selection set is selected on.
+    pub fn type_name(&self) -> &Name {
+        self.type_position.type_name()
+    }
+
+    /// Whether this selection set has no selection.
+    pub fn is_empty(&self) -> bool {
+        self.selections.is_empty()
+    }
+
+    /// The number of (top-level) selections in this selection set.
+    pub fn len(&self) -> usize {
+        self.selections.len()
+    }
+}
+
 pub(crate) mod normalized_selection_map {
     use crate::error::FederationError;
     use crate::error::SingleFederationError::Internal;
@@ -88,8 +322,7 @@ pub(crate) mod normalized_selection_map {
         NormalizedSelectionSet,
     };
     use apollo_compiler::ast::Name;
-    use indexmap::IndexMap;
-    use std::borrow::Borrow;
+    use indexmap::{Equivalent, IndexMap};
     use std::hash::Hash;
     use std::iter::Map;
     use std::ops::Deref;
@@ -131,8 +364,7 @@ pub(crate) mod normalized_selection_map {
 
         pub(crate) fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<NormalizedSelection>
         where
-            NormalizedSelectionKey: Borrow<Q>,
-            Q: Eq + Hash,
+            Q: Hash + Equivalent<NormalizedSelectionKey>,
         {
             // We specifically use shift_remove() instead of swap_remove() to maintain order.
             self.0.shift_remove(key)
@@ -140,8 +372,7 @@ pub(crate) mod normalized_selection_map {
 
         pub(crate) fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<NormalizedSelectionValue>
         where
-            NormalizedSelectionKey: Borrow<Q>,
-            Q: Eq + Hash,
+            Q: Hash + Equivalent<NormalizedSelectionKey>,
         {
             self.0.get_mut(key).map(NormalizedSelectionValue::new)
         }
@@ -335,21 +566,24 @@ pub(crate) mod normalized_selection_map {
 /// * reference the same field/inline fragment
 /// * specify the same directives
 /// * directives have to be applied in the same order
-/// * directive arguments order does not matter (they get automatically sorted by their names).
+/// * directive arguments order does not matter (they get automatically sorted by their names),
+///   and neither do arguments explicitly given their default value.
 /// * selection cannot specify @defer directive
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// Keys are computed once, when their selection is created, and stored alongside its data.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum NormalizedSelectionKey {
     Field {
         /// The field alias (if specified) or field name in the resulting selection set.
         response_name: Name,
         /// directives applied on the field
-        directives: Arc<DirectiveList>,
+        directives: SelectionKeyDirectives,
     },
     FragmentSpread {
         /// The fragment name referenced in the spread.
         name: Name,
         /// Directives applied on the fragment spread (does not contain @defer).
-        directives: Arc<DirectiveList>,
+        directives: SelectionKeyDirectives,
     },
     DeferredFragmentSpread {
         /// Unique selection ID used to distinguish deferred fragment spreads that cannot be merged.
@@ -359,7 +593,7 @@ pub(crate) enum NormalizedSelectionKey {
         /// The optional type condition of the inline fragment.
         type_condition: Option<Name>,
         /// Directives applied on the inline fragment (does not contain @defer).
-        directives: Arc<DirectiveList>,
+        directives: SelectionKeyDirectives,
     },
     DeferredInlineFragment {
         /// Unique selection ID used to distinguish deferred inline fragments that cannot be merged.
@@ -367,6 +601,112 @@ pub(crate) enum NormalizedSelectionKey {
     },
 }
 
+impl NormalizedSelectionKey {
+    pub(crate) fn as_key_ref(&self) -> SelectionKeyRef<'_> {
+        match self {
+            NormalizedSelectionKey::Field {
+                response_name,
+                directives,
+            } => SelectionKeyRef::Field {
+                response_name,
+                directives,
+            },
+            NormalizedSelectionKey::FragmentSpread { name, directives } => {
+                SelectionKeyRef::FragmentSpread { name, directives }
+            }
+            NormalizedSelectionKey::DeferredFragmentSpread { deferred_id } => {
+                SelectionKeyRef::DeferredFragmentSpread { deferred_id }
+            }
+            NormalizedSelectionKey::InlineFragment {
+                type_condition,
+                directives,
+            } => SelectionKeyRef::InlineFragment {
+                type_condition: type_condition.as_ref(),
+                directives,
+            },
+            NormalizedSelectionKey::DeferredInlineFragment { deferred_id } => {
+                SelectionKeyRef::DeferredInlineFragment { deferred_id }
+            }
+        }
+    }
+}
+
+/// The directives of a selection key, with their arguments normalized (see
+/// `directives_with_normalized_arguments()`), along with their hash. The hash is computed once
+/// when the key is created, so that hashing keys (e.g. to look selections up in selection maps)
+/// doesn't hash their directives over and over.
+#[derive(Debug, Clone)]
+pub(crate) struct SelectionKeyDirectives {
+    directives: Arc<DirectiveList>,
+    hash: u64,
+}
+
+impl SelectionKeyDirectives {
+    fn new(directives: DirectiveList) -> Self {
+        let mut hasher = DefaultHasher::new();
+        directives.hash(&mut hasher);
+        Self {
+            directives: Arc::new(directives),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+impl PartialEq for SelectionKeyDirectives {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+            && (Arc::ptr_eq(&self.directives, &other.directives)
+                || self.directives == other.directives)
+    }
+}
+
+impl Eq for SelectionKeyDirectives {}
+
+impl Hash for SelectionKeyDirectives {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash)
+    }
+}
+
+// The hash of a key is the hash of its borrowed view, so that `SelectionKeyRef`s can be used to
+// look keys up in maps.
+impl Hash for NormalizedSelectionKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_key_ref().hash(state)
+    }
+}
+
+/// A borrowed view of a `NormalizedSelectionKey`, used to look selections up in maps keyed by
+/// `NormalizedSelectionKey` without having to clone the key (and its directives) out of the
+/// selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum SelectionKeyRef<'a> {
+    Field {
+        response_name: &'a Name,
+        directives: &'a SelectionKeyDirectives,
+    },
+    FragmentSpread {
+        name: &'a Name,
+        directives: &'a SelectionKeyDirectives,
+    },
+    DeferredFragmentSpread {
+        deferred_id: &'a SelectionId,
+    },
+    InlineFragment {
+        type_condition: Option<&'a Name>,
+        directives: &'a SelectionKeyDirectives,
+    },
+    DeferredInlineFragment {
+        deferred_id: &'a SelectionId,
+    },
+}
+
+impl Equivalent<NormalizedSelectionKey> for SelectionKeyRef<'_> {
+    fn equivalent(&self, key: &NormalizedSelectionKey) -> bool {
+        *self == key.as_key_ref()
+    }
+}
+
 pub(crate) trait HasNormalizedSelectionKey {
     fn key(&self) -> NormalizedSelectionKey;
 }
@@ -385,7 +725,7 @@ impl NormalizedSelection {
         match self {
             NormalizedSelection::Field(field_selection) => &field_selection.field.data().directives,
             NormalizedSelection::FragmentSpread(fragment_spread_selection) => {
-                &fragment_spread_selection.data().directives
+                fragment_spread_selection.data().spread_directives()
             }
             NormalizedSelection::InlineFragment(inline_fragment_selection) => {
                 &inline_fragment_selection.inline_fragment.data().directives
@@ -408,6 +748,44 @@ impl NormalizedSelection {
         }
     }
 
+    /// Splits this selection along its sub-selections when they serialize to more than
+    /// `max_size` bytes (see `NormalizedSelectionSet::split()`).
+    fn split(&self, max_size: usize) -> Result<Vec<NormalizedSelection>, FederationError> {
+        Ok(match self {
+            NormalizedSelection::Field(field_selection) => match &field_selection.selection_set {
+                Some(selection_set) if selection_set.serialized_size()? > max_size => selection_set
+                    .split(max_size)?
+                    .into_iter()
+                    .map(|part| {
+                        NormalizedSelection::Field(Arc::new(NormalizedFieldSelection {
+                            field: field_selection.field.clone(),
+                            selection_set: Some(part),
+                        }))
+                    })
+                    .collect(),
+                _ => vec![self.clone()],
+            },
+            NormalizedSelection::InlineFragment(inline_fragment_selection)
+                if inline_fragment_selection.selection_set.serialized_size()? > max_size =>
+            {
+                inline_fragment_selection
+                    .selection_set
+                    .split(max_size)?
+                    .into_iter()
+                    .map(|part| {
+                        NormalizedSelection::InlineFragment(Arc::new(
+                            NormalizedInlineFragmentSelection {
+                                inline_fragment: inline_fragment_selection.inline_fragment.clone(),
+                                selection_set: part,
+                            },
+                        ))
+                    })
+                    .collect()
+            }
+            _ => vec![self.clone()],
+        })
+    }
+
     pub(crate) fn selection_set(&self) -> Result<Option<&NormalizedSelectionSet>, FederationError> {
         match self {
             NormalizedSelection::Field(field_selection) => {
@@ -442,15 +820,150 @@ impl NormalizedSelection {
                 NormalizedSelection::InlineFragment(inline) => {
                     Ok(self_conditions.merge(inline.selection_set.conditions()?))
                 }
-                NormalizedSelection::FragmentSpread(_x) => Err(FederationError::internal(
-                    "Unexpected fragment spread in NormalizedSelection::conditions()",
-                )),
+                // The selections of the spread fragment aren't known here, so only the conditions
+                // of the spread itself are used. Those are broader than the merged conditions would
+                // be, which at worst means querying the spread when it's not needed.
+                NormalizedSelection::FragmentSpread(_) => Ok(self_conditions),
             }
         }
     }
 
     pub(crate) fn has_defer(&self) -> Result<bool, FederationError> {
-        todo!()
+        match self {
+            NormalizedSelection::Field(field_selection) => field_selection
+                .selection_set
+                .as_ref()
+                .map_or(Ok(false), |selection_set| selection_set.has_defer()),
+            NormalizedSelection::FragmentSpread(_) => Err(FederationError::internal(
+                "Unexpected fragment spread in NormalizedSelection::has_defer()",
+            )),
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => Ok(
+                is_deferred_selection(&inline_fragment_selection.inline_fragment.data().directives)
+                    || inline_fragment_selection.selection_set.has_defer()?,
+            ),
+        }
+    }
+
+    /// This selection with each of its `@defer` applications replaced by the result of the given
+    /// function on it (or removed if that result is `None`). Selections without any `@defer` are
+    /// returned as is.
+    fn map_defer_directives(
+        &self,
+        map: &impl Fn(&Node<Directive>) -> Option<Node<Directive>>,
+    ) -> Result<NormalizedSelection, FederationError> {
+        match self {
+            NormalizedSelection::Field(field_selection) => match &field_selection.selection_set {
+                Some(selection_set) => {
+                    self.with_updated_selection_set(selection_set.map_defer_directives(map)?)
+                }
+                None => Ok(self.clone()),
+            },
+            NormalizedSelection::FragmentSpread(_) => Err(FederationError::internal(
+                "Unexpected fragment spread in NormalizedSelection::map_defer_directives()",
+            )),
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                let selection_set = inline_fragment_selection
+                    .selection_set
+                    .map_defer_directives(map)?;
+                let data = inline_fragment_selection.inline_fragment.data();
+                if !data.directives.has("defer") {
+                    return self.with_updated_selection_set(selection_set);
+                }
+                let mut data = data.clone();
+                data.directives = Arc::new(DirectiveList(
+                    data.directives
+                        .iter()
+                        .filter_map(|directive| {
+                            if directive.name == "defer" {
+                                map(directive)
+                            } else {
+                                Some(directive.clone())
+                            }
+                        })
+                        .collect(),
+                ));
+                Ok(NormalizedSelection::InlineFragment(Arc::new(
+                    NormalizedInlineFragmentSelection {
+                        inline_fragment: NormalizedInlineFragment::new(data),
+                        selection_set,
+                    },
+                )))
+            }
+        }
+    }
+
+    /// This selection with the given selection set instead of its own, or this selection itself
+    /// (sharing its `Arc`) if the given selection set shares the selections of its own, so that
+    /// passes built on `NormalizedSelectionSet::lazy_map()` only rebuild what they change.
+    pub(crate) fn with_updated_selection_set(
+        &self,
+        selection_set: NormalizedSelectionSet,
+    ) -> Result<NormalizedSelection, FederationError> {
+        match self {
+            NormalizedSelection::Field(field_selection) => {
+                if field_selection
+                    .selection_set
+                    .as_ref()
+                    .is_some_and(|current| {
+                        Arc::ptr_eq(&current.selections, &selection_set.selections)
+                    })
+                {
+                    return Ok(self.clone());
+                }
+                Ok(NormalizedSelection::Field(Arc::new(
+                    NormalizedFieldSelection {
+                        field: field_selection.field.clone(),
+                        selection_set: Some(selection_set),
+                    },
+                )))
+            }
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                if Arc::ptr_eq(
+                    &inline_fragment_selection.selection_set.selections,
+                    &selection_set.selections,
+                ) {
+                    return Ok(self.clone());
+                }
+                Ok(NormalizedSelection::InlineFragment(Arc::new(
+                    NormalizedInlineFragmentSelection {
+                        inline_fragment: inline_fragment_selection.inline_fragment.clone(),
+                        selection_set,
+                    },
+                )))
+            }
+            NormalizedSelection::FragmentSpread(_) => Err(FederationError::internal(
+                "Unexpected fragment spread in NormalizedSelection::with_updated_selection_set()",
+            )),
+        }
+    }
+
+    /// Whether this selection and the given one share their `Arc`.
+    fn ptr_eq(&self, other: &NormalizedSelection) -> bool {
+        match (self, other) {
+            (NormalizedSelection::Field(a), NormalizedSelection::Field(b)) => Arc::ptr_eq(a, b),
+            (NormalizedSelection::FragmentSpread(a), NormalizedSelection::FragmentSpread(b)) => {
+                Arc::ptr_eq(a, b)
+            }
+            (NormalizedSelection::InlineFragment(a), NormalizedSelection::InlineFragment(b)) => {
+                Arc::ptr_eq(a, b)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl NormalizedSelection {
+    /// The key of this selection, borrowed from the selection.
+    pub(crate) fn key_ref(&self) -> SelectionKeyRef<'_> {
+        match self {
+            NormalizedSelection::Field(field_selection) => field_selection.field.key_ref(),
+            NormalizedSelection::FragmentSpread(fragment_spread_selection) => {
+                fragment_spread_selection.key_ref()
+            }
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                inline_fragment_selection.inline_fragment.key_ref()
+            }
+        }
     }
 }
 
@@ -484,7 +997,9 @@ pub(crate) struct NormalizedFragment {
 impl NormalizedFragment {
     fn normalize(
         fragment: &Fragment,
+        fragments: &IndexMap<Name, Node<Fragment>>,
         schema: &ValidFederationSchema,
+        id_generator: &IdGenerator,
     ) -> Result<Self, FederationError> {
         Ok(Self {
             schema: schema.clone(),
@@ -495,9 +1010,10 @@ impl NormalizedFragment {
             directives: Arc::new(fragment.directives.clone()),
             selection_set: NormalizedSelectionSet::normalize_and_expand_fragments(
                 &fragment.selection_set,
-                &IndexMap::new(),
+                fragments,
                 schema,
                 FragmentSpreadNormalizationOption::PreserveFragmentSpread,
+                id_generator,
             )?,
         })
     }
@@ -506,10 +1022,12 @@ impl NormalizedFragment {
 pub(crate) mod normalized_field_selection {
     use crate::error::FederationError;
     use crate::query_plan::operation::{
-        directives_with_sorted_arguments, HasNormalizedSelectionKey, NormalizedSelectionKey,
-        NormalizedSelectionSet,
+        directives_with_normalized_arguments, rebase_error, HasNormalizedSelectionKey,
+        NormalizedSelectionKey, NormalizedSelectionSet, SelectionKeyDirectives, SelectionKeyRef,
+    };
+    use crate::schema::position::{
+        CompositeTypeDefinitionPosition, FieldDefinitionPosition, TypeDefinitionPosition,
     };
-    use crate::schema::position::{FieldDefinitionPosition, TypeDefinitionPosition};
     use crate::schema::ValidFederationSchema;
     use apollo_compiler::ast::{Argument, DirectiveList, Name};
     use apollo_compiler::Node;
@@ -556,9 +1074,51 @@ pub(crate) mod normalized_field_selection {
             &self.data
         }
 
+        pub(crate) fn key_ref(&self) -> SelectionKeyRef<'_> {
+            self.key.as_key_ref()
+        }
+
         pub(crate) fn sibling_typename_mut(&mut self) -> &mut Option<Name> {
             &mut self.data.sibling_typename
         }
+
+        /// Rebases this field onto the given parent type in the given schema, erroring if the
+        /// parent type doesn't have a field of the same name.
+        pub(crate) fn rebase_on(
+            &self,
+            parent_type: &CompositeTypeDefinitionPosition,
+            schema: &ValidFederationSchema,
+        ) -> Result<NormalizedField, FederationError> {
+            let field_position = if self.data.field_position.is_introspection_typename_field() {
+                parent_type.introspection_typename_field()
+            } else {
+                // Ensure the field exists in the target schema.
+                let field_name = self.data.field_position.field_name();
+                match parent_type.field(field_name.clone()) {
+                    Ok(field_position) if field_position.get(schema.schema()).is_ok() => {
+                        field_position
+                    }
+                    _ => {
+                        return Err(rebase_error(
+                            format!(
+                            "Cannot rebase field \"{}\" on type \"{}\" as it has no field \"{}\"",
+                            self.data.field_position, parent_type, field_name,
+                        ),
+                            parent_type,
+                            schema,
+                            Some((
+                                self.data.field_position.parent().type_name(),
+                                &self.data.schema,
+                            )),
+                        ))
+                    }
+                }
+            };
+            let mut data = self.data.clone();
+            data.schema = schema.clone();
+            data.field_position = field_position;
+            Ok(NormalizedField::new(data))
+        }
     }
 
     impl HasNormalizedSelectionKey for NormalizedField {
@@ -586,6 +1146,17 @@ pub(crate) mod normalized_field_selection {
             self.alias.clone().unwrap_or_else(|| self.name().clone())
         }
 
+        /// The composite type position of the field's base output type, erroring if the field
+        /// returns a non-composite type.
+        pub(crate) fn output_composite_type(
+            &self,
+        ) -> Result<CompositeTypeDefinitionPosition, FederationError> {
+            let definition = self.field_position.get(self.schema.schema())?;
+            self.schema
+                .get_type(definition.ty.inner_named_type().clone())?
+                .try_into()
+        }
+
         pub(crate) fn is_leaf(&self) -> Result<bool, FederationError> {
             let definition = self.field_position.get(self.schema.schema())?;
             let base_type_position = self
@@ -602,17 +1173,23 @@ pub(crate) mod normalized_field_selection {
         fn key(&self) -> NormalizedSelectionKey {
             NormalizedSelectionKey::Field {
                 response_name: self.response_name(),
-                directives: Arc::new(directives_with_sorted_arguments(&self.directives)),
+                directives: SelectionKeyDirectives::new(directives_with_normalized_arguments(
+                    &self.directives,
+                    &self.schema,
+                )),
             }
         }
     }
 }
 
 pub(crate) mod normalized_fragment_spread_selection {
+    use crate::error::FederationError;
     use crate::query_plan::operation::{
-        directives_with_sorted_arguments, is_deferred_selection, HasNormalizedSelectionKey,
-        NormalizedSelectionKey, SelectionId,
+        directives_with_normalized_arguments, is_deferred_selection, rebase_error,
+        runtime_types_intersect, type_condition_position, HasNormalizedSelectionKey, IdGenerator,
+        NormalizedSelectionKey, SelectionId, SelectionKeyDirectives, SelectionKeyRef,
     };
+    use crate::schema::position::CompositeTypeDefinitionPosition;
     use crate::schema::ValidFederationSchema;
     use apollo_compiler::ast::{DirectiveList, Name};
     use std::sync::Arc;
@@ -637,6 +1214,44 @@ pub(crate) mod normalized_fragment_spread_selection {
         pub(crate) fn data(&self) -> &NormalizedFragmentSpreadData {
             &self.data
         }
+
+        pub(crate) fn key_ref(&self) -> SelectionKeyRef<'_> {
+            self.key.as_key_ref()
+        }
+
+        /// Rebases this fragment spread onto the given schema, with the given parent type. This
+        /// fails if the type condition of the spread fragment doesn't exist in the target schema.
+        /// Note the spread fragment itself must be (re)defined against the target schema by the
+        /// caller.
+        pub(crate) fn rebase_on(
+            &self,
+            parent_type: &CompositeTypeDefinitionPosition,
+            schema: &ValidFederationSchema,
+        ) -> Result<NormalizedFragmentSpreadSelection, FederationError> {
+            let element = format!("fragment spread \"...{}\"", self.data.fragment_name);
+            let type_condition_position = type_condition_position(
+                self.data.type_condition_position.type_name(),
+                parent_type,
+                schema,
+                &self.data.schema,
+                &element,
+            )?;
+            if !runtime_types_intersect(parent_type, &type_condition_position, schema)? {
+                return Err(rebase_error(
+                    format!(
+                        "Cannot rebase {} on type \"{}\" as its type condition \"{}\" cannot apply to it",
+                        element, parent_type, type_condition_position,
+                    ),
+                    parent_type,
+                    schema,
+                    Some((type_condition_position.type_name(), schema)),
+                ));
+            }
+            let mut data = self.data.clone();
+            data.schema = schema.clone();
+            data.type_condition_position = type_condition_position;
+            Ok(NormalizedFragmentSpreadSelection::new(data))
+        }
     }
 
     impl HasNormalizedSelectionKey for NormalizedFragmentSpreadSelection {
@@ -645,24 +1260,68 @@ pub(crate) mod normalized_fragment_spread_selection {
         }
     }
 
+    /// The data of a fragment spread. Directives can be applied both to the spread (e.g.
+    /// `...F @include(if: $a)`) and to the definition of the spread fragment (e.g.
+    /// `fragment F on T @custom`), and both lists are stored. Only the spread directives apply to
+    /// the spread as a selection, i.e. they're the ones determining its key and conditions.
     #[derive(Debug, Clone, PartialEq, Eq, Hash)]
     pub(crate) struct NormalizedFragmentSpreadData {
         pub(crate) schema: ValidFederationSchema,
         pub(crate) fragment_name: Name,
-        pub(crate) directives: Arc<DirectiveList>,
+        type_condition_position: CompositeTypeDefinitionPosition,
+        spread_directives: Arc<DirectiveList>,
+        fragment_directives: Arc<DirectiveList>,
         pub(crate) selection_id: SelectionId,
     }
 
+    impl NormalizedFragmentSpreadData {
+        pub(crate) fn new(
+            schema: ValidFederationSchema,
+            fragment_name: Name,
+            type_condition_position: CompositeTypeDefinitionPosition,
+            spread_directives: DirectiveList,
+            fragment_directives: DirectiveList,
+            id_generator: &IdGenerator,
+        ) -> Self {
+            Self {
+                schema,
+                fragment_name,
+                type_condition_position,
+                spread_directives: Arc::new(spread_directives),
+                fragment_directives: Arc::new(fragment_directives),
+                selection_id: id_generator.next_id(),
+            }
+        }
+
+        /// The type condition of the spread fragment.
+        pub(crate) fn type_condition_position(&self) -> &CompositeTypeDefinitionPosition {
+            &self.type_condition_position
+        }
+
+        /// The directives applied to the spread.
+        pub(crate) fn spread_directives(&self) -> &Arc<DirectiveList> {
+            &self.spread_directives
+        }
+
+        /// The directives applied to the definition of the spread fragment.
+        pub(crate) fn fragment_directives(&self) -> &Arc<DirectiveList> {
+            &self.fragment_directives
+        }
+    }
+
     impl HasNormalizedSelectionKey for NormalizedFragmentSpreadData {
         fn key(&self) -> NormalizedSelectionKey {
-            if is_deferred_selection(&self.directives) {
+            if is_deferred_selection(&self.spread_directives) {
                 NormalizedSelectionKey::DeferredFragmentSpread {
                     deferred_id: self.selection_id.clone(),
                 }
             } else {
                 NormalizedSelectionKey::FragmentSpread {
                     name: self.fragment_name.clone(),
-                    directives: Arc::new(directives_with_sorted_arguments(&self.directives)),
+                    directives: SelectionKeyDirectives::new(directives_with_normalized_arguments(
+                        &self.spread_directives,
+                        &self.schema,
+                    )),
                 }
             }
         }
@@ -673,8 +1332,9 @@ pub(crate) mod normalized_inline_fragment_selection {
     use crate::error::FederationError;
     use crate::link::graphql_definition::{defer_directive_arguments, DeferDirectiveArguments};
     use crate::query_plan::operation::{
-        directives_with_sorted_arguments, is_deferred_selection, HasNormalizedSelectionKey,
-        NormalizedSelectionKey, NormalizedSelectionSet, SelectionId,
+        directives_with_normalized_arguments, is_deferred_selection, type_condition_position,
+        HasNormalizedSelectionKey, NormalizedSelectionKey, NormalizedSelectionSet, SelectionId,
+        SelectionKeyDirectives, SelectionKeyRef,
     };
     use crate::schema::position::CompositeTypeDefinitionPosition;
     use crate::schema::ValidFederationSchema;
@@ -720,6 +1380,34 @@ pub(crate) mod normalized_inline_fragment_selection {
         pub(crate) fn data(&self) -> &NormalizedInlineFragmentData {
             &self.data
         }
+
+        pub(crate) fn key_ref(&self) -> SelectionKeyRef<'_> {
+            self.key.as_key_ref()
+        }
+
+        /// Rebases this inline fragment onto the given parent type in the given schema, erroring
+        /// if its type condition doesn't exist in that schema.
+        pub(crate) fn rebase_on(
+            &self,
+            parent_type: &CompositeTypeDefinitionPosition,
+            schema: &ValidFederationSchema,
+        ) -> Result<NormalizedInlineFragment, FederationError> {
+            let type_condition_position = match &self.data.type_condition_position {
+                Some(type_condition) => Some(type_condition_position(
+                    type_condition.type_name(),
+                    parent_type,
+                    schema,
+                    &self.data.schema,
+                    &format!("inline fragment \"... on {}\"", type_condition),
+                )?),
+                None => None,
+            };
+            let mut data = self.data.clone();
+            data.schema = schema.clone();
+            data.parent_type_position = parent_type.clone();
+            data.type_condition_position = type_condition_position;
+            Ok(NormalizedInlineFragment::new(data))
+        }
     }
 
     impl HasNormalizedSelectionKey for NormalizedInlineFragment {
@@ -761,13 +1449,26 @@ pub(crate) mod normalized_inline_fragment_selection {
                         .type_condition_position
                         .as_ref()
                         .map(|pos| pos.type_name().clone()),
-                    directives: Arc::new(directives_with_sorted_arguments(&self.directives)),
+                    directives: SelectionKeyDirectives::new(directives_with_normalized_arguments(
+                        &self.directives,
+                        &self.schema,
+                    )),
                 }
             }
         }
     }
 }
 
+/// What a selection is mapped to by the function given to `NormalizedSelectionSet::lazy_map()`.
+pub(crate) enum SelectionMapperReturn {
+    /// The selection is removed.
+    None,
+    /// The selection is replaced by the given one (which is the selection itself to keep it).
+    Selection(NormalizedSelection),
+    /// The selection is replaced by the given ones.
+    SelectionList(Vec<NormalizedSelection>),
+}
+
 /// Available fragment spread normalization options
 #[derive(Copy, Clone)]
 pub(crate) enum FragmentSpreadNormalizationOption {
@@ -791,7 +1492,7 @@ impl NormalizedSelectionSet {
         &self,
         field: &NormalizedField,
     ) -> Result<bool, FederationError> {
-        if let Some(selection) = self.selections.get(&field.key()) {
+        if let Some(selection) = self.selections.get(&field.key_ref()) {
             let NormalizedSelection::Field(field_selection) = selection else {
                 return Err(Internal {
                     message: format!(
@@ -807,33 +1508,250 @@ impl NormalizedSelectionSet {
         }
     }
 
-    /// Normalize this selection set (merging selections with the same keys), with the following
-    /// additional transformations:
-    /// - Expand fragment spreads into inline fragments.
-    /// - Remove `__schema` or `__type` introspection fields, as these shouldn't be handled by query
-    ///   planning.
-    /// - Hoist fragment spreads/inline fragments into their parents if they have no directives and
-    ///   their parent type matches.
+    /// Whether this selection set selects everything the given one selects (and possibly more),
+    /// i.e. whether each selection of the given selection set has a selection with the same key
+    /// and element in this one, whose sub-selections (recursively) contain its own.
     ///
-    /// Note this function asserts that the type of the selection set is a composite type (i.e. this
-    /// isn't the empty selection set of some leaf field), and will return error if this is not the
-    /// case.
-    pub(crate) fn normalize_and_expand_fragments(
-        selection_set: &SelectionSet,
-        fragments: &IndexMap<Name, Node<Fragment>>,
-        schema: &ValidFederationSchema,
-        normalize_fragment_spread_option: FragmentSpreadNormalizationOption,
+    /// Fragment spreads with the same key are assumed to spread the same fragment definition.
+    pub(crate) fn contains(&self, other: &NormalizedSelectionSet) -> bool {
+        if Arc::ptr_eq(&self.selections, &other.selections) {
+            return true;
+        }
+        if self.type_position != other.type_position
+            || self.selections.len() < other.selections.len()
+        {
+            return false;
+        }
+        other.selections.iter().all(|(key, other_selection)| {
+            let Some(selection) = self.selections.get(key) else {
+                return false;
+            };
+            match (selection, other_selection) {
+                (NormalizedSelection::Field(field), NormalizedSelection::Field(other_field)) => {
+                    let (data, other_data) = (field.field.data(), other_field.field.data());
+                    same_field_element(data, other_data)
+                        // A sibling `__typename` is a selection of its own, just stored on the
+                        // field.
+                        && (other_data.sibling_typename.is_none()
+                            || data.sibling_typename == other_data.sibling_typename)
+                        && match (&field.selection_set, &other_field.selection_set) {
+                            (Some(selection_set), Some(other_selection_set)) => {
+                                selection_set.contains(other_selection_set)
+                            }
+                            (None, None) => true,
+                            _ => false,
+                        }
+                }
+                (
+                    NormalizedSelection::InlineFragment(inline_fragment),
+                    NormalizedSelection::InlineFragment(other_inline_fragment),
+                ) => inline_fragment
+                    .selection_set
+                    .contains(&other_inline_fragment.selection_set),
+                (
+                    NormalizedSelection::FragmentSpread(_),
+                    NormalizedSelection::FragmentSpread(_),
+                ) => true,
+                _ => false,
+            }
+        })
+    }
+
+    /// Whether this selection set selects exactly the same things as the given one, regardless of
+    /// the order of their selections (and of the arguments of their fields).
+    pub(crate) fn equals(&self, other: &NormalizedSelectionSet) -> bool {
+        self.selections.len() == other.selections.len()
+            && self.contains(other)
+            && other.contains(self)
+    }
+
+    /// Returns the selections of this selection set that the given one doesn't select, e.g. to
+    /// compute what remains to be fetched once the selections provided by `@provides` (or already
+    /// fetched for `@requires`) are removed. Selections with sub-selections are kept (with only the
+    /// remaining sub-selections) unless all of their sub-selections are removed.
+    ///
+    /// `__typename` is removed whether it's selected as a field or as the sibling typename of
+    /// another field, in either selection set. Fragment spreads with the same key are assumed to
+    /// spread the same fragment definition, and deferred fragments are never removed (as they're
+    /// distinct from any other selection).
+    pub(crate) fn minus(
+        &self,
+        other: &NormalizedSelectionSet,
     ) -> Result<NormalizedSelectionSet, FederationError> {
-        let type_position: CompositeTypeDefinitionPosition =
-            schema.get_type(selection_set.ty.clone())?.try_into()?;
-        let mut normalized_selections = vec![];
-        NormalizedSelectionSet::normalize_selections(
-            &selection_set.selections,
-            &type_position,
+        self.filter_against(other, false)
+    }
+
+    /// Returns the selections of this selection set that the given one also selects. Selections
+    /// with sub-selections are kept (with only the common sub-selections) if they have any
+    /// sub-selection in common.
+    ///
+    /// `__typename`, fragment spreads and deferred fragments are handled as in `minus()`.
+    pub(crate) fn intersection(
+        &self,
+        other: &NormalizedSelectionSet,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        self.filter_against(other, true)
+    }
+
+    /// Keeps the selections of this selection set that are (if `keep_common` is true) or aren't (if
+    /// it's false) selected by the given one, recursively.
+    fn filter_against(
+        &self,
+        other: &NormalizedSelectionSet,
+        keep_common: bool,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        if self.type_position != other.type_position {
+            return Err(Internal {
+                message: format!(
+                    "Cannot compare selection set for type \"{}\" with a selection set for type \"{}\"",
+                    self.type_position, other.type_position,
+                ),
+            }
+            .into());
+        }
+        let mut filtered =
+            NormalizedSelectionSet::empty(self.schema.clone(), self.type_position.clone());
+        let mut typenames_to_add = vec![];
+        for (key, selection) in self.selections.iter() {
+            let other_selection = other.selections.get(key);
+            let kept = match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    let data = field_selection.field.data();
+                    let is_common = if data.name() == &TYPENAME_FIELD && data.directives.is_empty()
+                    {
+                        other.selects_typename(&data.response_name())
+                    } else {
+                        matches!(
+                            other_selection,
+                            Some(NormalizedSelection::Field(other_field_selection))
+                                if same_field_element(data, other_field_selection.field.data())
+                        )
+                    };
+                    let other_selection_set = match other_selection {
+                        Some(NormalizedSelection::Field(other_field_selection)) if is_common => {
+                            other_field_selection.selection_set.as_ref()
+                        }
+                        _ => None,
+                    };
+                    // This is `None` if the field isn't kept, and `Some(None)` if it's a kept leaf.
+                    let selection_set = match (&field_selection.selection_set, other_selection_set)
+                    {
+                        (None, _) => (is_common == keep_common).then_some(None),
+                        (Some(selection_set), Some(other_selection_set)) => {
+                            let selection_set =
+                                selection_set.filter_against(other_selection_set, keep_common)?;
+                            (!selection_set.selections.is_empty()).then_some(Some(selection_set))
+                        }
+                        (Some(selection_set), None) => {
+                            (!keep_common).then(|| Some(selection_set.clone()))
+                        }
+                    };
+                    let sibling_typename = data
+                        .sibling_typename
+                        .as_ref()
+                        .filter(|typename| other.selects_typename(typename) == keep_common);
+                    match selection_set {
+                        Some(selection_set) => {
+                            let mut field = field_selection.field.clone();
+                            *field.sibling_typename_mut() = sibling_typename.cloned();
+                            Some(NormalizedSelection::Field(Arc::new(
+                                NormalizedFieldSelection {
+                                    field,
+                                    selection_set,
+                                },
+                            )))
+                        }
+                        None => {
+                            typenames_to_add.extend(sibling_typename.cloned());
+                            None
+                        }
+                    }
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    match other_selection {
+                        Some(NormalizedSelection::InlineFragment(
+                            other_inline_fragment_selection,
+                        )) => {
+                            let selection_set =
+                                inline_fragment_selection.selection_set.filter_against(
+                                    &other_inline_fragment_selection.selection_set,
+                                    keep_common,
+                                )?;
+                            (!selection_set.selections.is_empty()).then(|| {
+                                NormalizedSelection::InlineFragment(Arc::new(
+                                    NormalizedInlineFragmentSelection {
+                                        inline_fragment: inline_fragment_selection
+                                            .inline_fragment
+                                            .clone(),
+                                        selection_set,
+                                    },
+                                ))
+                            })
+                        }
+                        _ => (!keep_common).then(|| selection.clone()),
+                    }
+                }
+                NormalizedSelection::FragmentSpread(_) => {
+                    let is_common = matches!(
+                        other_selection,
+                        Some(NormalizedSelection::FragmentSpread(_))
+                    );
+                    (is_common == keep_common).then(|| selection.clone())
+                }
+            };
+            if let Some(kept) = kept {
+                Arc::make_mut(&mut filtered.selections).insert(kept);
+            }
+        }
+        for typename in typenames_to_add {
+            let typename_field = filtered.typename_field_selection(typename);
+            Arc::make_mut(&mut filtered.selections).insert(typename_field);
+        }
+        Ok(filtered)
+    }
+
+    /// Whether this selection set selects `__typename` with the given response name, either as a
+    /// field or as the sibling typename of another field.
+    fn selects_typename(&self, response_name: &Name) -> bool {
+        self.selections.values().any(|selection| {
+            let NormalizedSelection::Field(field_selection) = selection else {
+                return false;
+            };
+            let data = field_selection.field.data();
+            data.sibling_typename.as_ref() == Some(response_name)
+                || (data.name() == &TYPENAME_FIELD && &data.response_name() == response_name)
+        })
+    }
+
+    /// Normalize this selection set (merging selections with the same keys), with the following
+    /// additional transformations:
+    /// - Expand fragment spreads into inline fragments.
+    /// - Remove `__schema` or `__type` introspection fields, as these shouldn't be handled by query
+    ///   planning.
+    /// - Hoist fragment spreads/inline fragments into their parents if they have no directives and
+    ///   their parent type matches.
+    ///
+    /// Note this function asserts that the type of the selection set is a composite type (i.e. this
+    /// isn't the empty selection set of some leaf field), and will return error if this is not the
+    /// case.
+    pub(crate) fn normalize_and_expand_fragments(
+        selection_set: &SelectionSet,
+        fragments: &IndexMap<Name, Node<Fragment>>,
+        schema: &ValidFederationSchema,
+        normalize_fragment_spread_option: FragmentSpreadNormalizationOption,
+        id_generator: &IdGenerator,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        let type_position: CompositeTypeDefinitionPosition =
+            schema.get_type(selection_set.ty.clone())?.try_into()?;
+        let mut normalized_selections = vec![];
+        NormalizedSelectionSet::normalize_selections(
+            &selection_set.selections,
+            &type_position,
             &mut normalized_selections,
             fragments,
             schema,
             normalize_fragment_spread_option,
+            id_generator,
         )?;
         let mut merged = NormalizedSelectionSet {
             schema: schema.clone(),
@@ -852,6 +1770,7 @@ impl NormalizedSelectionSet {
         fragments: &IndexMap<Name, Node<Fragment>>,
         schema: &ValidFederationSchema,
         normalize_fragment_spread_option: FragmentSpreadNormalizationOption,
+        id_generator: &IdGenerator,
     ) -> Result<(), FederationError> {
         for selection in selections {
             match selection {
@@ -863,6 +1782,7 @@ impl NormalizedSelectionSet {
                             fragments,
                             schema,
                             normalize_fragment_spread_option,
+                            id_generator,
                         )?
                     else {
                         continue;
@@ -897,6 +1817,7 @@ impl NormalizedSelectionSet {
                                 fragments,
                                 schema,
                                 normalize_fragment_spread_option,
+                                id_generator,
                             )?;
                         } else {
                             let normalized_inline_fragment_selection =
@@ -906,6 +1827,7 @@ impl NormalizedSelectionSet {
                                     fragments,
                                     schema,
                                     normalize_fragment_spread_option,
+                                    id_generator,
                                 )?;
                             destination.push(NormalizedSelection::InlineFragment(Arc::new(
                                 normalized_inline_fragment_selection,
@@ -916,8 +1838,10 @@ impl NormalizedSelectionSet {
                         let normalized_fragment_spread =
                             NormalizedFragmentSpreadSelection::normalize(
                                 fragment_spread_selection,
+                                fragment,
                                 schema,
-                            );
+                                id_generator,
+                            )?;
                         destination.push(NormalizedSelection::FragmentSpread(Arc::new(
                             normalized_fragment_spread,
                         )));
@@ -947,6 +1871,7 @@ impl NormalizedSelectionSet {
                             fragments,
                             schema,
                             normalize_fragment_spread_option,
+                            id_generator,
                         )?;
                     } else {
                         let normalized_inline_fragment_selection =
@@ -956,6 +1881,7 @@ impl NormalizedSelectionSet {
                                 fragments,
                                 schema,
                                 normalize_fragment_spread_option,
+                                id_generator,
                             )?;
                         destination.push(NormalizedSelection::InlineFragment(Arc::new(
                             normalized_inline_fragment_selection,
@@ -967,28 +1893,44 @@ impl NormalizedSelectionSet {
         Ok(())
     }
 
-    /// Merges the given normalized selection sets into this one.
+    /// Merges the given normalized selection sets into this one. Merging doesn't stop at the first
+    /// incompatible selection: the error lists all of them, along with their selection paths. This
+    /// selection set is left unchanged when merging fails.
     pub(crate) fn merge_into(
         &mut self,
         others: impl Iterator<Item = NormalizedSelectionSet> + ExactSizeIterator,
     ) -> Result<(), FederationError> {
+        let mut errors = MergeErrors::default();
+        let mut merged = self.clone();
+        merged.merge_into_collecting_errors(others, &mut errors);
+        errors.into_result()?;
+        *self = merged;
+        Ok(())
+    }
+
+    fn merge_into_collecting_errors(
+        &mut self,
+        others: impl ExactSizeIterator<Item = NormalizedSelectionSet>,
+        errors: &mut MergeErrors,
+    ) {
         if others.len() > 0 {
             let mut selections_to_merge = vec![];
             for other in others {
                 if other.schema != self.schema {
-                    return Err(Internal {
-                        message: "Cannot merge selection sets from different schemas".to_owned(),
-                    }
-                    .into());
+                    errors.push("Cannot merge selection sets from different schemas".to_owned());
+                    continue;
                 }
                 if other.type_position != self.type_position {
-                    return Err(Internal {
-                        message: format!(
-                            "Cannot merge selection set for type \"{}\" into a selection set for type \"{}\"",
-                            other.type_position,
-                            self.type_position,
-                        ),
-                    }.into());
+                    errors.push(format!(
+                        "Cannot merge selection set for type \"{}\" into a selection set for type \"{}\"",
+                        other.type_position,
+                        self.type_position,
+                    ));
+                    continue;
+                }
+                // Merging a selection set into itself is a no-op, which interning makes common.
+                if Arc::ptr_eq(&other.selections, &self.selections) {
+                    continue;
                 }
                 let selections = Arc::try_unwrap(other.selections)
                     .unwrap_or_else(|selections| selections.deref().clone());
@@ -996,123 +1938,680 @@ impl NormalizedSelectionSet {
                     selections_to_merge.push(value);
                 }
             }
-            self.merge_selections_into(selections_to_merge.into_iter())?;
+            self.merge_selections_into_collecting_errors(selections_to_merge.into_iter(), errors);
+        }
+    }
+
+    /// Merges the given normalized selection sets into a new selection set. The selection sets
+    /// must all be for the same schema and type, and there must be at least one of them.
+    pub(crate) fn merge_all(
+        selection_sets: &[&NormalizedSelectionSet],
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        merge_selection_sets(
+            selection_sets
+                .iter()
+                .map(|selection_set| (*selection_set).clone()),
+        )
+    }
+
+    /// Rebases this selection set onto the given schema, with the given parent type. This is
+    /// used to move selections between the supergraph schema and subgraph schemas, and fails if
+    /// any of the selected fields or type conditions doesn't exist in the target schema.
+    /// Fragment spreads keep their fragment name, so the fragments they spread must be defined
+    /// against the target schema as well.
+    pub(crate) fn rebase_on(
+        &self,
+        parent_type: &CompositeTypeDefinitionPosition,
+        schema: &ValidFederationSchema,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        let mut rebased = NormalizedSelectionSet::empty(schema.clone(), parent_type.clone());
+        let mut rebased_selections = Vec::with_capacity(self.selections.len());
+        for selection in self.selections.values() {
+            rebased_selections.push(match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    let field = field_selection.field.rebase_on(parent_type, schema)?;
+                    let selection_set = match &field_selection.selection_set {
+                        Some(selection_set) => {
+                            let field_type = field.data().output_composite_type()?;
+                            Some(selection_set.rebase_on(&field_type, schema)?)
+                        }
+                        None => None,
+                    };
+                    NormalizedSelection::Field(Arc::new(NormalizedFieldSelection {
+                        field,
+                        selection_set,
+                    }))
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    let inline_fragment = inline_fragment_selection
+                        .inline_fragment
+                        .rebase_on(parent_type, schema)?;
+                    let fragment_type = inline_fragment
+                        .data()
+                        .type_condition_position
+                        .clone()
+                        .unwrap_or_else(|| parent_type.clone());
+                    let selection_set = inline_fragment_selection
+                        .selection_set
+                        .rebase_on(&fragment_type, schema)?;
+                    NormalizedSelection::InlineFragment(Arc::new(
+                        NormalizedInlineFragmentSelection {
+                            inline_fragment,
+                            selection_set,
+                        },
+                    ))
+                }
+                NormalizedSelection::FragmentSpread(fragment_spread_selection) => {
+                    NormalizedSelection::FragmentSpread(Arc::new(
+                        fragment_spread_selection.rebase_on(parent_type, schema)?,
+                    ))
+                }
+            });
+        }
+        rebased.merge_selections_into(rebased_selections.into_iter())?;
+        Ok(rebased)
+    }
+
+    /// Lifts this selection set, expressed against a subgraph schema, onto the given supergraph
+    /// (or API) schema. This is the reverse of rebasing supergraph selections onto a subgraph:
+    /// types are matched by name, so selections on an `@interfaceObject` type (an object type in
+    /// its subgraph) end up on the corresponding interface of the supergraph. This fails for
+    /// subgraph-only fields (like `_entities`), which have no supergraph counterpart.
+    pub(crate) fn lift_to_supergraph(
+        &self,
+        supergraph_schema: &ValidFederationSchema,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        let parent_type = supergraph_schema
+            .get_type(self.type_position.type_name().clone())?
+            .try_into()?;
+        self.rebase_on(&parent_type, supergraph_schema)
+    }
+
+    /// The size of this selection set once serialized (without indentation), which is how the
+    /// size of subgraph fetches is measured.
+    pub(crate) fn serialized_size(&self) -> Result<usize, FederationError> {
+        let selection_set: SelectionSet = self.try_into()?;
+        Ok(selection_set.serialize().no_indent().to_string().len())
+    }
+
+    /// The number of selections in this selection set, recursively (a fragment spread counts as a
+    /// single selection), which is how the memory used by selection sets is measured.
+    pub(crate) fn selection_count(&self) -> usize {
+        self.selections
+            .values()
+            .map(|selection| {
+                1 + match selection {
+                    NormalizedSelection::Field(field_selection) => field_selection
+                        .selection_set
+                        .as_ref()
+                        .map_or(0, |selection_set| selection_set.selection_count()),
+                    NormalizedSelection::FragmentSpread(_) => 0,
+                    NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                        inline_fragment_selection.selection_set.selection_count()
+                    }
+                }
+            })
+            .sum()
+    }
+
+    /// Splits this selection set into selection sets over disjoint parts of it, each serializing
+    /// to at most `max_size` bytes where possible. Selections too large on their own are split
+    /// along their sub-selections (repeating the enclosing field or inline fragment in each part),
+    /// so parts can exceed `max_size` by the size of those enclosing selections, and leaf fields
+    /// can't be split at all. Merging the parts back gives this selection set.
+    pub(crate) fn split(
+        &self,
+        max_size: usize,
+    ) -> Result<Vec<NormalizedSelectionSet>, FederationError> {
+        let mut parts = Vec::new();
+        let mut current =
+            NormalizedSelectionSet::empty(self.schema.clone(), self.type_position.clone());
+        for selection in self.selections.values() {
+            for piece in selection.split(max_size)? {
+                let mut candidate = current.clone();
+                candidate.merge_selections_into(std::iter::once(piece.clone()))?;
+                if !current.selections.is_empty() && candidate.serialized_size()? > max_size {
+                    parts.push(current);
+                    current = NormalizedSelectionSet::empty(
+                        self.schema.clone(),
+                        self.type_position.clone(),
+                    );
+                    current.merge_selections_into(std::iter::once(piece))?;
+                } else {
+                    current = candidate;
+                }
+            }
+        }
+        if !current.selections.is_empty() {
+            parts.push(current);
+        }
+        Ok(parts)
+    }
+
+    /// Whether this selection set contains some `@defer` application.
+    pub(crate) fn has_defer(&self) -> Result<bool, FederationError> {
+        for selection in self.selections.values() {
+            if selection.has_defer()? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The first field of this selection set (at any depth) with a `@stream` application that
+    /// isn't disabled by an `if: false` argument, if any.
+    pub(crate) fn streamed_field(&self) -> Option<&NormalizedFieldData> {
+        self.selections
+            .values()
+            .find_map(|selection| match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    let data = field_selection.field.data();
+                    if is_streamed_field(&data.directives) {
+                        Some(data)
+                    } else {
+                        field_selection
+                            .selection_set
+                            .as_ref()
+                            .and_then(|selection_set| selection_set.streamed_field())
+                    }
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    inline_fragment_selection.selection_set.streamed_field()
+                }
+                NormalizedSelection::FragmentSpread(_) => None,
+            })
+    }
+
+    /// This selection set with all its `@defer` applications removed (merging the selections that
+    /// were only kept apart by them).
+    pub(crate) fn without_defer(&self) -> Result<NormalizedSelectionSet, FederationError> {
+        self.map_defer_directives(&|_| None)
+    }
+
+    /// This selection set with its `@defer` applications normalized (merging the selections that
+    /// were only kept apart by them): those with an `if: false` argument are removed (their
+    /// selections aren't deferred), and `if: true` arguments are removed (as it's the default).
+    /// `@defer`s with a variable `if` argument are kept as is.
+    // PORT_NOTE: This is the part of `Operation.withNormalizedDefer()` in the JS codebase that
+    // doesn't depend on planning `@defer` (which would also assign labels to the `@defer`s without
+    // one, and turn variable `if` arguments into condition nodes).
+    pub(crate) fn with_normalized_defer(&self) -> Result<NormalizedSelectionSet, FederationError> {
+        self.map_defer_directives(&|directive| match directive
+            .argument_by_name("if")
+            .map(|value| value.as_ref())
+        {
+            Some(Value::Boolean(false)) => None,
+            Some(Value::Boolean(true)) => {
+                let mut directive = directive.clone();
+                directive
+                    .make_mut()
+                    .arguments
+                    .retain(|argument| argument.name != "if");
+                Some(directive)
+            }
+            _ => Some(directive.clone()),
+        })
+    }
+
+    fn map_defer_directives(
+        &self,
+        map: &impl Fn(&Node<Directive>) -> Option<Node<Directive>>,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        self.lazy_map(|selection| {
+            Ok(SelectionMapperReturn::Selection(
+                selection.map_defer_directives(map)?,
+            ))
+        })
+    }
+
+    /// This selection set without the selections that are never included, i.e. those with
+    /// `@skip(if: true)` or `@include(if: false)`, nor the inline fragments left without
+    /// selections. Fields whose sub-selections are all removed select `__typename` instead, so that
+    /// they stay valid (their value is still part of the response, as an empty object).
+    pub(crate) fn without_skipped_selections(
+        &self,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        self.filter_recursive_depth_first(&mut |selection| {
+            if Conditions::from_directives(selection.directives())? == Conditions::Boolean(false) {
+                return Ok(false);
+            }
+            Ok(match selection {
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    !inline_fragment_selection
+                        .selection_set
+                        .selections
+                        .is_empty()
+                }
+                _ => true,
+            })
+        })?
+        .with_typename_in_empty_fields()
+    }
+
+    /// This selection set with a `__typename` selection added to the fields (at any depth) that
+    /// have an empty selection set, e.g. once filtered.
+    fn with_typename_in_empty_fields(&self) -> Result<NormalizedSelectionSet, FederationError> {
+        self.lazy_map(|selection| {
+            let selection_set = match selection {
+                NormalizedSelection::FragmentSpread(_) => None,
+                _ => selection.selection_set()?,
+            };
+            let Some(selection_set) = selection_set else {
+                return Ok(SelectionMapperReturn::Selection(selection.clone()));
+            };
+            let mut selection_set = selection_set.with_typename_in_empty_fields()?;
+            if selection_set.selections.is_empty()
+                && matches!(selection, NormalizedSelection::Field(_))
+            {
+                selection_set.add_typename_field()?;
+            }
+            Ok(SelectionMapperReturn::Selection(
+                selection.with_updated_selection_set(selection_set)?,
+            ))
+        })
+    }
+
+    /// This selection set without the selections for which the given predicate is false. The
+    /// sub-selections of the kept selections are kept as is (see `filter_recursive_depth_first()`
+    /// to filter them as well).
+    pub(crate) fn filter(
+        &self,
+        mut predicate: impl FnMut(&NormalizedSelection) -> Result<bool, FederationError>,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        self.lazy_map(|selection| {
+            Ok(if predicate(selection)? {
+                SelectionMapperReturn::Selection(selection.clone())
+            } else {
+                SelectionMapperReturn::None
+            })
+        })
+    }
+
+    /// This selection set without the selections (at any depth) for which the given predicate is
+    /// false. Selections are filtered depth-first, so the predicate is given selections whose
+    /// sub-selections are already filtered (which lets it remove those left empty, for instance).
+    // PORT_NOTE: This is `SelectionSet.filterRecursiveDepthFirst()` in the JS codebase.
+    pub(crate) fn filter_recursive_depth_first(
+        &self,
+        predicate: &mut impl FnMut(&NormalizedSelection) -> Result<bool, FederationError>,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        self.lazy_map(|selection| {
+            let selection_set = match selection {
+                NormalizedSelection::FragmentSpread(_) => None,
+                _ => selection.selection_set()?,
+            };
+            let filtered = match selection_set {
+                Some(selection_set) => selection.with_updated_selection_set(
+                    selection_set.filter_recursive_depth_first(predicate)?,
+                )?,
+                None => selection.clone(),
+            };
+            Ok(if predicate(&filtered)? {
+                SelectionMapperReturn::Selection(filtered)
+            } else {
+                SelectionMapperReturn::None
+            })
+        })
+    }
+
+    /// Maps the selections of this selection set with the given function, where each selection
+    /// can be kept, removed, or replaced by any number of selections (which are merged into the
+    /// result, so they may share the key of another selection). This selection set is only
+    /// rebuilt if some selection isn't kept as is: otherwise this returns a clone of it, sharing
+    /// its selections. Recursive passes should use `NormalizedSelection::with_updated_selection_set()`
+    /// so that unchanged sub-selection sets are kept as is as well.
+    // PORT_NOTE: This is `SelectionSet.lazyMap()` in the JS codebase.
+    pub(crate) fn lazy_map(
+        &self,
+        mut mapper: impl FnMut(&NormalizedSelection) -> Result<SelectionMapperReturn, FederationError>,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        let mut updated_selections: Option<Vec<NormalizedSelection>> = None;
+        for (index, selection) in self.selections.values().enumerate() {
+            let mapped = mapper(selection)?;
+            let updated_selections = match &mut updated_selections {
+                Some(updated_selections) => updated_selections,
+                None => {
+                    if matches!(&mapped, SelectionMapperReturn::Selection(mapped) if mapped.ptr_eq(selection))
+                    {
+                        continue;
+                    }
+                    updated_selections
+                        .insert(self.selections.values().take(index).cloned().collect())
+                }
+            };
+            match mapped {
+                SelectionMapperReturn::None => {}
+                SelectionMapperReturn::Selection(mapped) => updated_selections.push(mapped),
+                SelectionMapperReturn::SelectionList(mapped) => updated_selections.extend(mapped),
+            }
+        }
+        let Some(updated_selections) = updated_selections else {
+            return Ok(self.clone());
+        };
+        let mut mapped =
+            NormalizedSelectionSet::empty(self.schema.clone(), self.type_position.clone());
+        mapped.merge_selections_into(updated_selections.into_iter())?;
+        Ok(mapped)
+    }
+
+    /// This selection set without the inline fragments whose type condition is redundant, their
+    /// selections being merged into their parent selection set instead. A type condition is
+    /// redundant when it includes all the possible runtime types of its parent type, in the schema
+    /// of this selection set (e.g. `... on Node` within a `User` selection, or `... on Product`
+    /// within a `Book` selection in a subgraph where `Book` is the only `Product`), as long as its
+    /// selections are also valid on the parent type. Inline fragments with directives are kept.
+    pub(crate) fn simplify_type_conditions(
+        &self,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        let mut selections = Vec::with_capacity(self.selections.len());
+        self.collect_simplified_selections(&self.type_position, &mut selections)?;
+        let mut simplified =
+            NormalizedSelectionSet::empty(self.schema.clone(), self.type_position.clone());
+        simplified.merge_selections_into(selections.into_iter())?;
+        Ok(simplified)
+    }
+
+    /// Collects the selections of this selection set with their type conditions simplified (see
+    /// `simplify_type_conditions()`), rebased onto the given parent type (which differs from the
+    /// type of this selection set when it's the selection set of a redundant inline fragment).
+    fn collect_simplified_selections(
+        &self,
+        parent_type: &CompositeTypeDefinitionPosition,
+        destination: &mut Vec<NormalizedSelection>,
+    ) -> Result<(), FederationError> {
+        let is_rebased = self.type_position != *parent_type;
+        for selection in self.selections.values() {
+            match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    let field = if is_rebased {
+                        field_selection.field.rebase_on(parent_type, &self.schema)?
+                    } else {
+                        field_selection.field.clone()
+                    };
+                    let selection_set = field_selection
+                        .selection_set
+                        .as_ref()
+                        .map(|selection_set| selection_set.simplify_type_conditions())
+                        .transpose()?;
+                    destination.push(NormalizedSelection::Field(Arc::new(
+                        NormalizedFieldSelection {
+                            field,
+                            selection_set,
+                        },
+                    )));
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    let inline_fragment_data = inline_fragment_selection.inline_fragment.data();
+                    if inline_fragment_data.directives.is_empty()
+                        && self.is_redundant_type_condition(
+                            parent_type,
+                            inline_fragment_data.type_condition_position.as_ref(),
+                        )?
+                    {
+                        inline_fragment_selection
+                            .selection_set
+                            .collect_simplified_selections(parent_type, destination)?;
+                        continue;
+                    }
+                    let inline_fragment = if is_rebased {
+                        inline_fragment_selection
+                            .inline_fragment
+                            .rebase_on(parent_type, &self.schema)?
+                    } else {
+                        inline_fragment_selection.inline_fragment.clone()
+                    };
+                    destination.push(NormalizedSelection::InlineFragment(Arc::new(
+                        NormalizedInlineFragmentSelection {
+                            inline_fragment,
+                            selection_set: inline_fragment_selection
+                                .selection_set
+                                .simplify_type_conditions()?,
+                        },
+                    )));
+                }
+                NormalizedSelection::FragmentSpread(_) => destination.push(selection.clone()),
+            }
         }
         Ok(())
     }
 
-    /// A helper function for merging the given selections into this one.
+    /// Whether an inline fragment with the given type condition is redundant within the given
+    /// parent type (see `simplify_type_conditions()`).
+    fn is_redundant_type_condition(
+        &self,
+        parent_type: &CompositeTypeDefinitionPosition,
+        type_condition: Option<&CompositeTypeDefinitionPosition>,
+    ) -> Result<bool, FederationError> {
+        let Some(type_condition) = type_condition else {
+            return Ok(true);
+        };
+        if type_condition == parent_type {
+            return Ok(true);
+        }
+        // The fields selected on the type condition must also exist on the parent type, which is
+        // the case for object types and interfaces implementing the type condition. Unions only
+        // have `__typename`, which exists on every type.
+        let has_type_condition_fields = match (parent_type, type_condition) {
+            (_, CompositeTypeDefinitionPosition::Union(_)) => true,
+            (CompositeTypeDefinitionPosition::Object(_), _) => true,
+            (CompositeTypeDefinitionPosition::Interface(parent_type), _) => parent_type
+                .get(self.schema.schema())?
+                .implements_interfaces
+                .iter()
+                .any(|interface| interface.name == *type_condition.type_name()),
+            (CompositeTypeDefinitionPosition::Union(_), _) => false,
+        };
+        if !has_type_condition_fields {
+            return Ok(false);
+        }
+        let type_condition_runtime_types = self
+            .schema
+            .cached_possible_runtime_types(type_condition.clone())?;
+        Ok(self
+            .schema
+            .cached_possible_runtime_types(parent_type.clone())?
+            .is_subset(&type_condition_runtime_types))
+    }
+
+    /// Adds a `__typename` field selection to this selection set (if not already present).
+    pub(crate) fn add_typename_field(&mut self) -> Result<(), FederationError> {
+        let typename_field = self.typename_field_selection(TYPENAME_FIELD);
+        self.merge_selections_into(std::iter::once(typename_field))
+    }
+
+    /// A `__typename` field selection for the type of this selection set, with the given response
+    /// name (aliasing the field if it isn't `__typename`).
+    fn typename_field_selection(&self, response_name: Name) -> NormalizedSelection {
+        let typename_field = NormalizedField::new(NormalizedFieldData {
+            schema: self.schema.clone(),
+            field_position: self.type_position.introspection_typename_field(),
+            alias: (response_name != TYPENAME_FIELD).then_some(response_name),
+            arguments: Arc::new(vec![]),
+            directives: Arc::new(Default::default()),
+            sibling_typename: None,
+        });
+        NormalizedSelection::Field(Arc::new(NormalizedFieldSelection {
+            field: typename_field,
+            selection_set: None,
+        }))
+    }
+
+    /// Adds the given selection set at the given path of this selection set, where the path is
+    /// relative to (and its first element is a selection of) this selection set. The elements of
+    /// the path that don't already exist in this selection set are created. If no selection set is
+    /// given, then only the path itself is added (e.g. when the path ends with a leaf field).
+    pub(crate) fn add_at_path(
+        &mut self,
+        path: &[Arc<OpPathElement>],
+        selection_set: Option<NormalizedSelectionSet>,
+    ) -> Result<(), FederationError> {
+        let mut wrapped = selection_set;
+        for element in path.iter().rev() {
+            let (type_position, selection) = match element.as_ref() {
+                OpPathElement::Field(field) => (
+                    field.data().field_position.parent(),
+                    NormalizedSelection::Field(Arc::new(NormalizedFieldSelection {
+                        field: field.clone(),
+                        selection_set: wrapped,
+                    })),
+                ),
+                OpPathElement::InlineFragment(inline_fragment) => {
+                    let data = inline_fragment.data();
+                    let selection_set = wrapped.unwrap_or_else(|| {
+                        NormalizedSelectionSet::empty(
+                            data.schema.clone(),
+                            data.type_condition_position
+                                .clone()
+                                .unwrap_or_else(|| data.parent_type_position.clone()),
+                        )
+                    });
+                    (
+                        data.parent_type_position.clone(),
+                        NormalizedSelection::InlineFragment(Arc::new(
+                            NormalizedInlineFragmentSelection {
+                                inline_fragment: inline_fragment.clone(),
+                                selection_set,
+                            },
+                        )),
+                    )
+                }
+            };
+            let mut parent_selection_set =
+                NormalizedSelectionSet::empty(selection.element()?.schema().clone(), type_position);
+            parent_selection_set.merge_selections_into(std::iter::once(selection))?;
+            wrapped = Some(parent_selection_set);
+        }
+        match wrapped {
+            Some(wrapped) => self.merge_into(std::iter::once(wrapped)),
+            None => Ok(()),
+        }
+    }
+
+    /// A helper function for merging the given selections into this one, which is left unchanged
+    /// when merging fails.
     fn merge_selections_into(
         &mut self,
         others: impl Iterator<Item = NormalizedSelection> + ExactSizeIterator,
     ) -> Result<(), FederationError> {
+        let mut errors = MergeErrors::default();
+        let mut merged = self.clone();
+        merged.merge_selections_into_collecting_errors(others, &mut errors);
+        errors.into_result()?;
+        *self = merged;
+        Ok(())
+    }
+
+    fn merge_selections_into_collecting_errors(
+        &mut self,
+        others: impl ExactSizeIterator<Item = NormalizedSelection>,
+        errors: &mut MergeErrors,
+    ) {
         if others.len() > 0 {
             let mut fields = IndexMap::new();
             let mut fragment_spreads = IndexMap::new();
             let mut inline_fragments = IndexMap::new();
+            // The selections to merge are grouped by the index of the selection they merge into,
+            // which avoids cloning their keys.
             for other_selection in others {
-                let other_key = other_selection.key();
-                match Arc::make_mut(&mut self.selections).entry(other_key.clone()) {
-                    Entry::Occupied(existing) => match existing.get() {
-                        NormalizedSelection::Field(self_field_selection) => {
-                            let NormalizedSelection::Field(other_field_selection) = other_selection
-                            else {
-                                return Err(Internal {
-                                        message: format!(
-                                            "Field selection key for field \"{}\" references non-field selection",
-                                            self_field_selection.field.data().field_position,
-                                        ),
-                                    }.into());
-                            };
-                            let other_field_selection = Arc::try_unwrap(other_field_selection)
+                let Some((index, _, existing)) =
+                    self.selections.get_full(&other_selection.key_ref())
+                else {
+                    Arc::make_mut(&mut self.selections).insert(other_selection);
+                    continue;
+                };
+                match existing {
+                    NormalizedSelection::Field(self_field_selection) => {
+                        let NormalizedSelection::Field(other_field_selection) = other_selection
+                        else {
+                            errors.push(format!(
+                                "Field selection key for field \"{}\" references non-field selection",
+                                self_field_selection.field.data().field_position,
+                            ));
+                            continue;
+                        };
+                        let other_field_selection = Arc::try_unwrap(other_field_selection)
+                            .unwrap_or_else(|selection| selection.deref().clone());
+                        fields
+                            .entry(index)
+                            .or_insert_with(Vec::new)
+                            .push(other_field_selection);
+                    }
+                    NormalizedSelection::FragmentSpread(self_fragment_spread_selection) => {
+                        let NormalizedSelection::FragmentSpread(other_fragment_spread_selection) =
+                            other_selection
+                        else {
+                            errors.push(format!(
+                                "Fragment spread selection key for fragment \"{}\" references non-field selection",
+                                self_fragment_spread_selection.data().fragment_name,
+                            ));
+                            continue;
+                        };
+                        let other_fragment_spread_selection =
+                            Arc::try_unwrap(other_fragment_spread_selection)
                                 .unwrap_or_else(|selection| selection.deref().clone());
-                            fields
-                                .entry(other_key)
-                                .or_insert_with(Vec::new)
-                                .push(other_field_selection);
-                        }
-                        NormalizedSelection::FragmentSpread(self_fragment_spread_selection) => {
-                            let NormalizedSelection::FragmentSpread(
-                                other_fragment_spread_selection,
-                            ) = other_selection
-                            else {
-                                return Err(Internal {
-                                        message: format!(
-                                            "Fragment spread selection key for fragment \"{}\" references non-field selection",
-                                            self_fragment_spread_selection.data().fragment_name,
-                                        ),
-                                    }.into());
-                            };
-                            let other_fragment_spread_selection =
-                                Arc::try_unwrap(other_fragment_spread_selection)
-                                    .unwrap_or_else(|selection| selection.deref().clone());
-                            fragment_spreads
-                                .entry(other_key)
-                                .or_insert_with(Vec::new)
-                                .push(other_fragment_spread_selection);
-                        }
-                        NormalizedSelection::InlineFragment(self_inline_fragment_selection) => {
-                            let NormalizedSelection::InlineFragment(
-                                other_inline_fragment_selection,
-                            ) = other_selection
-                            else {
-                                return Err(Internal {
-                                        message: format!(
-                                            "Inline fragment selection key under parent type \"{}\" {}references non-field selection",
-                                            self_inline_fragment_selection.inline_fragment.data().parent_type_position,
-                                            self_inline_fragment_selection.inline_fragment.data().type_condition_position.clone()
-                                                .map_or_else(
-                                                    String::new,
-                                                    |cond| format!("(type condition: {}) ", cond),
-                                                ),
-                                        ),
-                                    }.into());
-                            };
-                            let other_inline_fragment_selection =
-                                Arc::try_unwrap(other_inline_fragment_selection)
-                                    .unwrap_or_else(|selection| selection.deref().clone());
-                            inline_fragments
-                                .entry(other_key)
-                                .or_insert_with(Vec::new)
-                                .push(other_inline_fragment_selection);
-                        }
-                    },
-                    Entry::Vacant(vacant) => {
-                        vacant.insert(other_selection)?;
+                        fragment_spreads
+                            .entry(index)
+                            .or_insert_with(Vec::new)
+                            .push(other_fragment_spread_selection);
+                    }
+                    NormalizedSelection::InlineFragment(self_inline_fragment_selection) => {
+                        let NormalizedSelection::InlineFragment(other_inline_fragment_selection) =
+                            other_selection
+                        else {
+                            errors.push(format!(
+                                "Inline fragment selection key under parent type \"{}\" {}references non-field selection",
+                                self_inline_fragment_selection.inline_fragment.data().parent_type_position,
+                                self_inline_fragment_selection.inline_fragment.data().type_condition_position.clone()
+                                    .map_or_else(
+                                        String::new,
+                                        |cond| format!("(type condition: {}) ", cond),
+                                    ),
+                            ));
+                            continue;
+                        };
+                        let other_inline_fragment_selection =
+                            Arc::try_unwrap(other_inline_fragment_selection)
+                                .unwrap_or_else(|selection| selection.deref().clone());
+                        inline_fragments
+                            .entry(index)
+                            .or_insert_with(Vec::new)
+                            .push(other_inline_fragment_selection);
                     }
                 }
             }
-            for (key, self_selection) in Arc::make_mut(&mut self.selections).iter_mut() {
+            for (index, (_, self_selection)) in
+                Arc::make_mut(&mut self.selections).iter_mut().enumerate()
+            {
                 match self_selection {
                     NormalizedSelectionValue::Field(mut self_field_selection) => {
-                        if let Some(other_field_selections) = fields.shift_remove(key) {
-                            self_field_selection.merge_into(other_field_selections.into_iter())?;
+                        if let Some(other_field_selections) = fields.shift_remove(&index) {
+                            self_field_selection
+                                .merge_into(other_field_selections.into_iter(), errors);
                         }
                     }
                     NormalizedSelectionValue::FragmentSpread(
                         mut self_fragment_spread_selection,
                     ) => {
                         if let Some(other_fragment_spread_selections) =
-                            fragment_spreads.shift_remove(key)
+                            fragment_spreads.shift_remove(&index)
                         {
                             self_fragment_spread_selection
-                                .merge_into(other_fragment_spread_selections.into_iter())?;
+                                .merge_into(other_fragment_spread_selections.into_iter(), errors);
                         }
                     }
                     NormalizedSelectionValue::InlineFragment(
                         mut self_inline_fragment_selection,
                     ) => {
                         if let Some(other_inline_fragment_selections) =
-                            inline_fragments.shift_remove(key)
+                            inline_fragments.shift_remove(&index)
                         {
                             self_inline_fragment_selection
-                                .merge_into(other_inline_fragment_selections.into_iter())?;
+                                .merge_into(other_inline_fragment_selections.into_iter(), errors);
                         }
                     }
                 }
             }
         }
-        Ok(())
     }
 
     /// Modifies the provided selection set to optimize the handling of __typename selections for query planning.
@@ -1145,6 +2644,9 @@ impl NormalizedSelectionSet {
     /// __typename. Do note that if __typename is the "only" selection of some selection set, then we leave it
     /// untouched, and let the query planning algorithm treat it as any other field. We have no other choice in
     /// that case, and that's actually what we want.
+    ///
+    /// An aliased __typename is left untouched as well, and so is planned as any other field: as query plans
+    /// don't need it to be, it's not worth keeping its alias around to add it back.
     pub(crate) fn optimize_sibling_typenames(
         &mut self,
         interface_types_with_interface_objects: &IndexSet<InterfaceTypeDefinitionPosition>,
@@ -1160,7 +2662,9 @@ impl NormalizedSelectionSet {
         for (key, entry) in mutable_selection_map.iter_mut() {
             match entry {
                 NormalizedSelectionValue::Field(mut field_selection) => {
-                    if field_selection.get().field.data().name() == &TYPENAME_FIELD
+                    let field_data = field_selection.get().field.data();
+                    if field_data.name() == &TYPENAME_FIELD
+                        && field_data.alias.is_none()
                         && !is_interface_object
                         && typename_field_key.is_none()
                     {
@@ -1210,15 +2714,46 @@ impl NormalizedSelectionSet {
         Ok(())
     }
 
-    pub(crate) fn conditions(&self) -> Result<Conditions, FederationError> {
-        // If the conditions of all the selections within the set are the same,
-        // then those are conditions of the whole set and we return it.
-        // Otherwise, we just return `true`
-        // (which essentially translate to "that selection always need to be queried").
-        // Note that for the case where the set has only 1 selection,
-        // then this just mean we return the condition of that one selection.
-        // Also note that in theory we could be a tad more precise,
-        // and when all the selections have variable conditions,
+    /// Collects the types of the selection sets (recursively) that still have a `__typename` field
+    /// selection alongside other selections after `optimize_sibling_typenames()`, i.e. for which
+    /// that optimization had to fall back to planning `__typename` as a regular field.
+    pub(crate) fn collect_sibling_typename_fallbacks(&self, types: &mut IndexSet<Name>) {
+        let has_typename_field = self.selections.values().any(|selection| {
+            matches!(
+                selection,
+                NormalizedSelection::Field(field_selection)
+                    if field_selection.field.data().name() == &TYPENAME_FIELD
+            )
+        });
+        if has_typename_field && self.selections.len() > 1 {
+            types.insert(self.type_position.type_name().clone());
+        }
+        for selection in self.selections.values() {
+            match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    if let Some(selection_set) = &field_selection.selection_set {
+                        selection_set.collect_sibling_typename_fallbacks(types);
+                    }
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    inline_fragment_selection
+                        .selection_set
+                        .collect_sibling_typename_fallbacks(types);
+                }
+                NormalizedSelection::FragmentSpread(_) => {}
+            }
+        }
+    }
+
+    pub(crate) fn conditions(&self) -> Result<Conditions, FederationError> {
+        // If the conditions of all the selections within the set are the same,
+        // then those are conditions of the whole set and we return it.
+        // Otherwise, we just return `true`
+        // (which essentially translate to "that selection always need to be queried").
+        // Note that for the case where the set has only 1 selection,
+        // then this just mean we return the condition of that one selection.
+        // Also note that in theory we could be a tad more precise,
+        // and when all the selections have variable conditions,
         // we could return the intersection of all of them,
         // but we don't bother for now as that has probably extremely rarely an impact in practice.
         let mut selections = self.selections.values();
@@ -1236,16 +2771,141 @@ impl NormalizedSelectionSet {
         Ok(conditions)
     }
 
+    /// Adds the variables used by the arguments and directives of this selection set to the given
+    /// set, in the order they appear. Fragment spreads aren't followed.
+    pub(crate) fn collect_variables<'a>(&'a self, variables: &mut IndexSet<&'a Name>) {
+        for selection in self.selections.values() {
+            match selection {
+                NormalizedSelection::Field(field) => {
+                    let data = field.field.data();
+                    for argument in data.arguments.iter() {
+                        collect_value_variables(&argument.value, variables);
+                    }
+                    collect_directive_variables(&data.directives, variables);
+                    if let Some(selection_set) = &field.selection_set {
+                        selection_set.collect_variables(variables);
+                    }
+                }
+                NormalizedSelection::InlineFragment(fragment) => {
+                    collect_directive_variables(
+                        &fragment.inline_fragment.data().directives,
+                        variables,
+                    );
+                    fragment.selection_set.collect_variables(variables);
+                }
+                NormalizedSelection::FragmentSpread(spread) => {
+                    collect_directive_variables(spread.data().spread_directives(), variables);
+                }
+            }
+        }
+    }
+
+    /// Adds the names of the fragments spread by this selection set (at any depth, but without
+    /// following the spreads) to the given set, in the order they appear.
+    pub(crate) fn collect_fragment_spreads<'a>(&'a self, fragments: &mut IndexSet<&'a Name>) {
+        for selection in self.selections.values() {
+            match selection {
+                NormalizedSelection::Field(field) => {
+                    if let Some(selection_set) = &field.selection_set {
+                        selection_set.collect_fragment_spreads(fragments);
+                    }
+                }
+                NormalizedSelection::InlineFragment(fragment) => {
+                    fragment.selection_set.collect_fragment_spreads(fragments);
+                }
+                NormalizedSelection::FragmentSpread(spread) => {
+                    fragments.insert(&spread.data().fragment_name);
+                }
+            }
+        }
+    }
+
+    /// This selection set with the `__typename` fields removed by `optimize_sibling_typenames()`
+    /// added back (recursively), each just before the sibling field it was attached to.
     pub(crate) fn add_back_typename_in_attachments(
         &self,
     ) -> Result<NormalizedSelectionSet, FederationError> {
-        todo!()
+        self.lazy_map(|selection| {
+            if let NormalizedSelection::FragmentSpread(_) = selection {
+                return Ok(SelectionMapperReturn::Selection(selection.clone()));
+            }
+            let updated = match selection.selection_set()? {
+                Some(selection_set) => selection.with_updated_selection_set(
+                    selection_set.add_back_typename_in_attachments()?,
+                )?,
+                None => selection.clone(),
+            };
+            let NormalizedSelection::Field(field_selection) = &updated else {
+                return Ok(SelectionMapperReturn::Selection(updated));
+            };
+            let mut data = field_selection.field.data().clone();
+            let Some(response_name) = data.sibling_typename.take() else {
+                return Ok(SelectionMapperReturn::Selection(updated));
+            };
+            Ok(SelectionMapperReturn::SelectionList(vec![
+                self.typename_field_selection(response_name),
+                NormalizedSelection::Field(Arc::new(NormalizedFieldSelection {
+                    field: NormalizedField::new(data),
+                    selection_set: field_selection.selection_set.clone(),
+                })),
+            ]))
+        })
     }
 
+    /// This selection set with a `__typename` field added (recursively) to the sub-selection sets
+    /// whose type is abstract. Note that `__typename` isn't added to this selection set itself.
     pub(crate) fn add_typename_field_for_abstract_types(
         &self,
     ) -> Result<NormalizedSelectionSet, FederationError> {
-        todo!()
+        self.lazy_map(|selection| {
+            if let NormalizedSelection::FragmentSpread(_) = selection {
+                return Ok(SelectionMapperReturn::Selection(selection.clone()));
+            }
+            let Some(selection_set) = selection.selection_set()? else {
+                return Ok(SelectionMapperReturn::Selection(selection.clone()));
+            };
+            let mut updated = selection_set.add_typename_field_for_abstract_types()?;
+            if !matches!(
+                updated.type_position,
+                CompositeTypeDefinitionPosition::Object(_)
+            ) {
+                updated.add_typename_field()?;
+            }
+            Ok(SelectionMapperReturn::Selection(
+                selection.with_updated_selection_set(updated)?,
+            ))
+        })
+    }
+}
+
+/// Adds the variables used by the arguments of the given directives to the given set.
+pub(crate) fn collect_directive_variables<'a>(
+    directives: &'a DirectiveList,
+    variables: &mut IndexSet<&'a Name>,
+) {
+    for directive in directives.iter() {
+        for argument in &directive.arguments {
+            collect_value_variables(&argument.value, variables);
+        }
+    }
+}
+
+fn collect_value_variables<'a>(value: &'a Value, variables: &mut IndexSet<&'a Name>) {
+    match value {
+        Value::Variable(name) => {
+            variables.insert(name);
+        }
+        Value::List(values) => {
+            for value in values {
+                collect_value_variables(value, variables);
+            }
+        }
+        Value::Object(fields) => {
+            for (_, value) in fields {
+                collect_value_variables(value, variables);
+            }
+        }
+        _ => {}
     }
 }
 
@@ -1263,18 +2923,31 @@ impl NormalizedFieldSelection {
         fragments: &IndexMap<Name, Node<Fragment>>,
         schema: &ValidFederationSchema,
         normalize_fragment_spread_option: FragmentSpreadNormalizationOption,
+        id_generator: &IdGenerator,
     ) -> Result<Option<NormalizedFieldSelection>, FederationError> {
         // Skip __schema/__type introspection fields as router takes care of those, and they do not
         // need to be query planned.
         if field.name == "__schema" || field.name == "__type" {
             return Ok(None);
         }
-        let field_position = parent_type_position.field(field.name.clone())?;
         // We might be able to validate that the returned `FieldDefinition` matches that within
         // the given `field`, but on the off-chance there's a mutation somewhere in between
         // Operation creation and the creation of the ValidFederationSchema, it's safer to just
         // confirm it exists in this schema.
-        field_position.get(schema.schema())?;
+        let field_position = match parent_type_position.field(field.name.clone()) {
+            Ok(field_position) if field_position.get(schema.schema()).is_ok() => field_position,
+            _ => {
+                return Err(rebase_error(
+                    format!(
+                        "Cannot normalize field \"{}\" on type \"{}\" as it has no such field",
+                        field.name, parent_type_position,
+                    ),
+                    parent_type_position,
+                    schema,
+                    None,
+                ))
+            }
+        };
         let field_composite_type_result: Result<CompositeTypeDefinitionPosition, FederationError> =
             schema.get_type(field.selection_set.ty.clone())?.try_into();
 
@@ -1293,6 +2966,7 @@ impl NormalizedFieldSelection {
                     fragments,
                     schema,
                     normalize_fragment_spread_option,
+                    id_generator,
                 )?)
             } else {
                 None
@@ -1303,65 +2977,69 @@ impl NormalizedFieldSelection {
 
 impl<'a> NormalizedFieldSelectionValue<'a> {
     /// Merges the given normalized field selections into this one (this method assumes the keys
-    /// already match).
-    pub(crate) fn merge_into(
+    /// already match), recording the incompatible ones in the given errors.
+    fn merge_into(
         &mut self,
         others: impl Iterator<Item = NormalizedFieldSelection> + ExactSizeIterator,
-    ) -> Result<(), FederationError> {
+        errors: &mut MergeErrors,
+    ) {
         if others.len() > 0 {
             let self_field = &self.get().field;
+            errors
+                .path
+                .push(self_field.data().response_name().to_string());
             let mut selection_sets = vec![];
             for other in others {
                 let other_field = &other.field;
                 if other_field.data().schema != self_field.data().schema {
-                    return Err(Internal {
-                        message: "Cannot merge field selections from different schemas".to_owned(),
-                    }
-                    .into());
+                    errors.push("Cannot merge field selections from different schemas".to_owned());
+                    continue;
                 }
                 if other_field.data().field_position != self_field.data().field_position {
-                    return Err(Internal {
-                        message: format!(
-                            "Cannot merge field selection for field \"{}\" into a field selection for field \"{}\"",
-                            other_field.data().field_position,
-                            self_field.data().field_position,
-                        ),
-                    }.into());
+                    errors.push_conflict(format!(
+                        "Cannot merge field selection for field \"{}\" into a field selection for field \"{}\"",
+                        other_field.data().field_position,
+                        self_field.data().field_position,
+                    ));
+                    continue;
+                }
+                if !same_field_element(other_field.data(), self_field.data()) {
+                    errors.push_conflict(format!(
+                        "Cannot merge field selection \"{}\" into field selection \"{}\" with different arguments",
+                        other_field,
+                        self_field,
+                    ));
+                    continue;
                 }
                 if self.get().selection_set.is_some() {
                     let Some(other_selection_set) = other.selection_set else {
-                        return Err(Internal {
-                            message: format!(
-                                "Field \"{}\" has composite type but not a selection set",
-                                other_field.data().field_position,
-                            ),
-                        }
-                        .into());
+                        errors.push(format!(
+                            "Field \"{}\" has composite type but not a selection set",
+                            other_field.data().field_position,
+                        ));
+                        continue;
                     };
                     selection_sets.push(other_selection_set);
                 } else if other.selection_set.is_some() {
-                    return Err(Internal {
-                        message: format!(
-                            "Field \"{}\" has non-composite type but also has a selection set",
-                            other_field.data().field_position,
-                        ),
-                    }
-                    .into());
+                    errors.push(format!(
+                        "Field \"{}\" has non-composite type but also has a selection set",
+                        other_field.data().field_position,
+                    ));
                 }
             }
             if let Some(self_selection_set) = self.get_selection_set_mut() {
-                self_selection_set.merge_into(selection_sets.into_iter())?;
+                self_selection_set.merge_into_collecting_errors(selection_sets.into_iter(), errors);
             }
+            errors.path.pop();
         }
-        Ok(())
     }
 }
 
 impl NormalizedFragmentSpreadSelection {
     /// Copies fragment spread selection and assigns it a new unique selection ID.
-    pub(crate) fn with_unique_id(&self) -> Self {
+    pub(crate) fn with_unique_id(&self, id_generator: &IdGenerator) -> Self {
         let mut data = self.data().clone();
-        data.selection_id = SelectionId::new();
+        data.selection_id = id_generator.next_id();
         Self::new(data)
     }
 
@@ -1372,14 +3050,22 @@ impl NormalizedFragmentSpreadSelection {
     /// - Stores unique selection ID (used for deferred fragments)
     pub(crate) fn normalize(
         fragment_spread: &FragmentSpread,
+        fragment: &Fragment,
         schema: &ValidFederationSchema,
-    ) -> NormalizedFragmentSpreadSelection {
-        NormalizedFragmentSpreadSelection::new(NormalizedFragmentSpreadData {
-            schema: schema.clone(),
-            fragment_name: fragment_spread.fragment_name.clone(),
-            directives: Arc::new(fragment_spread.directives.clone()),
-            selection_id: SelectionId::new(),
-        })
+        id_generator: &IdGenerator,
+    ) -> Result<NormalizedFragmentSpreadSelection, FederationError> {
+        Ok(NormalizedFragmentSpreadSelection::new(
+            NormalizedFragmentSpreadData::new(
+                schema.clone(),
+                fragment_spread.fragment_name.clone(),
+                schema
+                    .get_type(fragment.type_condition().clone())?
+                    .try_into()?,
+                fragment_spread.directives.clone(),
+                fragment.directives.clone(),
+                id_generator,
+            ),
+        ))
     }
 
     /// Normalize this fragment spread (merging selections with the same keys), with the following
@@ -1395,6 +3081,7 @@ impl NormalizedFragmentSpreadSelection {
         fragments: &IndexMap<Name, Node<Fragment>>,
         schema: &ValidFederationSchema,
         normalize_fragment_spread_option: FragmentSpreadNormalizationOption,
+        id_generator: &IdGenerator,
     ) -> Result<NormalizedInlineFragmentSelection, FederationError> {
         let Some(fragment) = fragments.get(&fragment_spread.fragment_name) else {
             return Err(Internal {
@@ -1405,9 +3092,13 @@ impl NormalizedFragmentSpreadSelection {
             }
             .into());
         };
-        let type_condition_position: CompositeTypeDefinitionPosition = schema
-            .get_type(fragment.type_condition().clone())?
-            .try_into()?;
+        let type_condition_position = type_condition_position(
+            fragment.type_condition(),
+            parent_type_position,
+            schema,
+            schema,
+            &format!("fragment spread \"...{}\"", fragment_spread.fragment_name),
+        )?;
 
         // PORT_NOTE: The JS codebase combined the fragment spread's directives with the fragment
         // definition's directives. This was invalid GraphQL, so we're explicitly ignoring the
@@ -1419,13 +3110,14 @@ impl NormalizedFragmentSpreadSelection {
                 parent_type_position: parent_type_position.clone(),
                 type_condition_position: Some(type_condition_position),
                 directives: Arc::new(fragment_spread.directives.clone()),
-                selection_id: SelectionId::new(),
+                selection_id: id_generator.next_id(),
             }),
             selection_set: NormalizedSelectionSet::normalize_and_expand_fragments(
                 &fragment.selection_set,
                 fragments,
                 schema,
                 normalize_fragment_spread_option,
+                id_generator,
             )?,
         })
     }
@@ -1433,18 +3125,19 @@ impl NormalizedFragmentSpreadSelection {
 
 impl<'a> NormalizedFragmentSpreadSelectionValue<'a> {
     /// Merges the given normalized fragment spread selections into this one (this method assumes
-    /// the keys already match).
-    pub(crate) fn merge_into(
+    /// the keys already match), recording the incompatible ones in the given errors.
+    fn merge_into(
         &mut self,
         others: impl Iterator<Item = NormalizedFragmentSpreadSelection> + ExactSizeIterator,
-    ) -> Result<(), FederationError> {
+        errors: &mut MergeErrors,
+    ) {
         if others.len() > 0 {
+            errors
+                .path
+                .push(format!("...{}", self.get().data().fragment_name));
             for other in others {
                 if other.data().schema != self.get().data().schema {
-                    return Err(Internal {
-                        message: "Cannot merge fragment spread from different schemas".to_owned(),
-                    }
-                    .into());
+                    errors.push("Cannot merge fragment spread from different schemas".to_owned());
                 }
                 // Nothing to do since the fragment spread is already part of the selection set.
                 // Fragment spreads are uniquely identified by fragment name and applied directives.
@@ -1452,16 +3145,16 @@ impl<'a> NormalizedFragmentSpreadSelectionValue<'a> {
                 // in attempting to merge its sub-selections, as the underlying entry should be
                 // exactly the same as the currently processed one.
             }
+            errors.path.pop();
         }
-        Ok(())
     }
 }
 
 impl NormalizedInlineFragmentSelection {
     /// Copies inline fragment selection and assigns it a new unique selection ID.
-    pub(crate) fn with_unique_id(&self) -> Self {
+    pub(crate) fn with_unique_id(&self, id_generator: &IdGenerator) -> Self {
         let mut data = self.inline_fragment.data().clone();
-        data.selection_id = SelectionId::new();
+        data.selection_id = id_generator.next_id();
         Self {
             inline_fragment: NormalizedInlineFragment::new(data),
             selection_set: self.selection_set.clone(),
@@ -1481,26 +3174,32 @@ impl NormalizedInlineFragmentSelection {
         fragments: &IndexMap<Name, Node<Fragment>>,
         schema: &ValidFederationSchema,
         normalize_fragment_spread_option: FragmentSpreadNormalizationOption,
+        id_generator: &IdGenerator,
     ) -> Result<NormalizedInlineFragmentSelection, FederationError> {
-        let type_condition_position: Option<CompositeTypeDefinitionPosition> =
-            if let Some(type_condition) = &inline_fragment.type_condition {
-                Some(schema.get_type(type_condition.clone())?.try_into()?)
-            } else {
-                None
-            };
+        let type_condition_position = match &inline_fragment.type_condition {
+            Some(type_condition) => Some(type_condition_position(
+                type_condition,
+                parent_type_position,
+                schema,
+                schema,
+                &format!("inline fragment \"... on {}\"", type_condition),
+            )?),
+            None => None,
+        };
         Ok(NormalizedInlineFragmentSelection {
             inline_fragment: NormalizedInlineFragment::new(NormalizedInlineFragmentData {
                 schema: schema.clone(),
                 parent_type_position: parent_type_position.clone(),
                 type_condition_position,
                 directives: Arc::new(inline_fragment.directives.clone()),
-                selection_id: SelectionId::new(),
+                selection_id: id_generator.next_id(),
             }),
             selection_set: NormalizedSelectionSet::normalize_and_expand_fragments(
                 &inline_fragment.selection_set,
                 fragments,
                 schema,
                 normalize_fragment_spread_option,
+                id_generator,
             )?,
         })
     }
@@ -1508,48 +3207,105 @@ impl NormalizedInlineFragmentSelection {
 
 impl<'a> NormalizedInlineFragmentSelectionValue<'a> {
     /// Merges the given normalized inline fragment selections into this one (this method assumes
-    /// the keys already match).
-    pub(crate) fn merge_into(
+    /// the keys already match), recording the incompatible ones in the given errors.
+    fn merge_into(
         &mut self,
         others: impl Iterator<Item = NormalizedInlineFragmentSelection> + ExactSizeIterator,
-    ) -> Result<(), FederationError> {
+        errors: &mut MergeErrors,
+    ) {
         if others.len() > 0 {
             let self_inline_fragment = &self.get().inline_fragment;
+            errors
+                .path
+                .push(match &self_inline_fragment.data().type_condition_position {
+                    Some(type_condition) => format!("... on {}", type_condition.type_name()),
+                    None => "...".to_owned(),
+                });
             let mut selection_sets = vec![];
             for other in others {
                 let other_inline_fragment = &other.inline_fragment;
                 if other_inline_fragment.data().schema != self_inline_fragment.data().schema {
-                    return Err(Internal {
-                        message: "Cannot merge inline fragment from different schemas".to_owned(),
-                    }
-                    .into());
+                    errors.push("Cannot merge inline fragment from different schemas".to_owned());
+                    continue;
                 }
                 if other_inline_fragment.data().parent_type_position
                     != self_inline_fragment.data().parent_type_position
                 {
-                    return Err(Internal {
-                        message: format!(
-                            "Cannot merge inline fragment of parent type \"{}\" into an inline fragment of parent type \"{}\"",
-                            other_inline_fragment.data().parent_type_position,
-                            self_inline_fragment.data().parent_type_position,
-                        ),
-                    }.into());
+                    errors.push(format!(
+                        "Cannot merge inline fragment of parent type \"{}\" into an inline fragment of parent type \"{}\"",
+                        other_inline_fragment.data().parent_type_position,
+                        self_inline_fragment.data().parent_type_position,
+                    ));
+                    continue;
                 }
                 selection_sets.push(other.selection_set);
             }
             self.get_selection_set_mut()
-                .merge_into(selection_sets.into_iter())?;
+                .merge_into_collecting_errors(selection_sets.into_iter(), errors);
+            errors.path.pop();
+        }
+    }
+}
+
+/// The incompatibilities found while merging selections, which are all reported at once so that
+/// they can be fixed in one pass.
+#[derive(Default)]
+struct MergeErrors {
+    /// The path to the selection set being merged, each element being the response name of a
+    /// field, or a fragment (e.g. `... on T` or `...F`).
+    path: Vec<String>,
+    errors: Vec<SingleFederationError>,
+}
+
+impl MergeErrors {
+    /// Records a conflict between the selections of the merged selection sets, e.g. two fields
+    /// with the same response name but different arguments.
+    fn push_conflict(&mut self, message: String) {
+        let message = self.with_path(message);
+        self.errors
+            .push(SingleFederationError::SelectionMergeConflict { message });
+    }
+
+    /// Records a merge that callers should never attempt, e.g. of selection sets from different
+    /// schemas.
+    fn push(&mut self, message: String) {
+        let message = self.with_path(message);
+        self.errors.push(Internal { message });
+    }
+
+    fn with_path(&self, message: String) -> String {
+        if self.path.is_empty() {
+            message
+        } else {
+            format!(
+                "{} (at selection path \"{}\")",
+                message,
+                self.path.join("::")
+            )
+        }
+    }
+
+    fn into_result(mut self) -> Result<(), FederationError> {
+        match self.errors.len() {
+            0 => Ok(()),
+            1 => Err(self.errors.remove(0).into()),
+            _ => Err(MultipleFederationErrors {
+                errors: self.errors,
+            }
+            .into()),
         }
-        Ok(())
     }
 }
 
+/// Merges the given selection sets into a new selection set, reporting all the incompatible
+/// selections at once (see `NormalizedSelectionSet::merge_into()`). There must be at least one
+/// selection set.
 pub(crate) fn merge_selection_sets(
     mut selection_sets: impl Iterator<Item = NormalizedSelectionSet> + ExactSizeIterator,
 ) -> Result<NormalizedSelectionSet, FederationError> {
     let Some(mut first) = selection_sets.next() else {
         return Err(Internal {
-            message: "".to_owned(),
+            message: "Cannot merge an empty list of selection sets".to_owned(),
         }
         .into());
     };
@@ -1557,19 +3313,26 @@ pub(crate) fn merge_selection_sets(
     Ok(first)
 }
 
+/// Whether the given selection sets select the same things, regardless of the order of their
+/// selections (see `NormalizedSelectionSet::equals()`).
 pub(crate) fn equal_selection_sets(
-    _a: &NormalizedSelectionSet,
-    _b: &NormalizedSelectionSet,
+    a: &NormalizedSelectionSet,
+    b: &NormalizedSelectionSet,
 ) -> Result<bool, FederationError> {
-    // TODO: Once operation processing is done, we should be able to call into that logic here.
-    // We're specifically wanting the equivalent of something like
-    // ```
-    // selectionSetOfNode(...).equals(selectionSetOfNode(...));
-    // ```
-    // from the JS codebase. It may be more performant for federation-next to use its own
-    // representation instead of repeatedly inter-converting between its representation and the
-    // apollo-rs one, but we'll cross that bridge if we come to it.
-    todo!();
+    Ok(a.equals(b))
+}
+
+/// Whether the given fields (whose selections have the same key, so the same response name and
+/// directives) select the same schema field with the same arguments, in any order.
+fn same_field_element(field: &NormalizedFieldData, other_field: &NormalizedFieldData) -> bool {
+    field.field_position == other_field.field_position
+        && field.arguments.len() == other_field.arguments.len()
+        && field.arguments.iter().all(|argument| {
+            other_field
+                .arguments
+                .iter()
+                .any(|other_argument| other_argument == argument)
+        })
 }
 
 impl TryFrom<&NormalizedOperation> for Operation {
@@ -1587,6 +3350,18 @@ impl TryFrom<&NormalizedOperation> for Operation {
     }
 }
 
+impl TryFrom<&NormalizedFragment> for Fragment {
+    type Error = FederationError;
+
+    fn try_from(normalized_fragment: &NormalizedFragment) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: normalized_fragment.name.clone(),
+            directives: normalized_fragment.directives.deref().clone(),
+            selection_set: (&normalized_fragment.selection_set).try_into()?,
+        })
+    }
+}
+
 impl TryFrom<&NormalizedSelectionSet> for SelectionSet {
     type Error = FederationError;
 
@@ -1680,7 +3455,7 @@ impl From<&NormalizedFragmentSpreadSelection> for FragmentSpread {
     fn from(val: &NormalizedFragmentSpreadSelection) -> Self {
         Self {
             fragment_name: val.data().fragment_name.to_owned(),
-            directives: val.data().directives.deref().to_owned(),
+            directives: val.data().spread_directives().deref().to_owned(),
         }
     }
 }
@@ -1771,13 +3546,27 @@ impl Display for NormalizedInlineFragment {
     }
 }
 
-fn directives_with_sorted_arguments(directives: &DirectiveList) -> DirectiveList {
+/// The given directives with their arguments sorted by name, and without the arguments whose value
+/// is the default value of the argument in the schema, so that applications only differing by the
+/// order of their arguments, or by whether they explicitly give a default value, have the same
+/// selection key.
+fn directives_with_normalized_arguments(
+    directives: &DirectiveList,
+    schema: &ValidFederationSchema,
+) -> DirectiveList {
     let mut directives = directives.clone();
     for directive in &mut directives {
-        directive
-            .make_mut()
-            .arguments
-            .sort_by(|a1, a2| a1.name.cmp(&a2.name))
+        let definition = schema.schema().directive_definitions.get(&directive.name);
+        let directive = directive.make_mut();
+        if let Some(definition) = definition {
+            directive.arguments.retain(|argument| {
+                definition
+                    .argument_by_name(&argument.name)
+                    .and_then(|argument_definition| argument_definition.default_value.as_ref())
+                    != Some(&argument.value)
+            });
+        }
+        directive.arguments.sort_by(|a1, a2| a1.name.cmp(&a2.name))
     }
     directives
 }
@@ -1786,6 +3575,123 @@ fn is_deferred_selection(directives: &DirectiveList) -> bool {
     directives.has("defer")
 }
 
+/// Whether the given field directives stream the field, i.e. include a `@stream` application
+/// without an `if: false` argument.
+fn is_streamed_field(directives: &DirectiveList) -> bool {
+    directives.get("stream").is_some_and(|directive| {
+        !matches!(
+            directive.argument_by_name("if").map(|value| value.as_ref()),
+            Some(Value::Boolean(false))
+        )
+    })
+}
+
+/// The names of the possible runtime types of the type of the given name in the given schema, or
+/// nothing if the schema has no composite type of that name.
+// PORT_NOTE: Named `printPossibleRuntimes` in the JS codebase, where it printed them into the
+// message of a single error.
+fn print_possible_runtimes(type_name: &Name, schema: &ValidFederationSchema) -> Vec<String> {
+    let Some(Ok(type_position)) = schema
+        .try_get_type(type_name.clone())
+        .map(CompositeTypeDefinitionPosition::try_from)
+    else {
+        return Vec::new();
+    };
+    schema
+        .cached_possible_runtime_types(type_position)
+        .map(|runtime_types| {
+            runtime_types
+                .iter()
+                .map(|runtime_type| runtime_type.type_name.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// An error for an operation element that can't be rebased onto (or normalized against) the given
+/// parent type of the given schema. The attempted type is the type the element applies to, along
+/// with the schema the element comes from.
+fn rebase_error(
+    message: String,
+    parent_type: &CompositeTypeDefinitionPosition,
+    schema: &ValidFederationSchema,
+    attempted_type: Option<(&Name, &ValidFederationSchema)>,
+) -> FederationError {
+    SingleFederationError::OperationRebaseFailed {
+        message,
+        context: Box::new(RebaseErrorContext {
+            parent_type: parent_type.type_name().to_string(),
+            parent_runtime_types: print_possible_runtimes(parent_type.type_name(), schema),
+            attempted_type: attempted_type.map(|(type_name, _)| type_name.to_string()),
+            attempted_runtime_types: attempted_type
+                .map(|(type_name, schema)| print_possible_runtimes(type_name, schema))
+                .unwrap_or_default(),
+            subgraph_name: None,
+        }),
+    }
+    .into()
+}
+
+/// The position of the given type condition in the given schema, erroring (with the types
+/// involved) if it isn't a composite type of that schema. The type condition comes from the given
+/// source schema, and applies to the given parent type.
+fn type_condition_position(
+    type_condition: &Name,
+    parent_type: &CompositeTypeDefinitionPosition,
+    schema: &ValidFederationSchema,
+    source_schema: &ValidFederationSchema,
+    element: &str,
+) -> Result<CompositeTypeDefinitionPosition, FederationError> {
+    match schema
+        .try_get_type(type_condition.clone())
+        .map(CompositeTypeDefinitionPosition::try_from)
+    {
+        Some(Ok(type_condition_position)) => Ok(type_condition_position),
+        _ => Err(rebase_error(
+            format!(
+                "Cannot add {} on type \"{}\" as its type condition \"{}\" is not a composite type of the schema",
+                element, parent_type, type_condition,
+            ),
+            parent_type,
+            schema,
+            Some((type_condition, source_schema)),
+        )),
+    }
+}
+
+/// Whether the given types have some possible runtime type in common in the given schema.
+fn runtime_types_intersect(
+    type1: &CompositeTypeDefinitionPosition,
+    type2: &CompositeTypeDefinitionPosition,
+    schema: &ValidFederationSchema,
+) -> Result<bool, FederationError> {
+    let runtime_types1 = schema.cached_possible_runtime_types(type1.clone())?;
+    let runtime_types2 = schema.cached_possible_runtime_types(type2.clone())?;
+    Ok(runtime_types1
+        .iter()
+        .any(|runtime_type| runtime_types2.contains(runtime_type)))
+}
+
+/// Returns the operation of the given name in the given document, or its only operation if no name
+/// is given.
+pub(crate) fn get_operation<'doc>(
+    document: &'doc Valid<ExecutableDocument>,
+    operation_name: Option<&str>,
+) -> Result<&'doc Node<Operation>, FederationError> {
+    document.get_operation(operation_name).map_err(|_| {
+        SingleFederationError::InvalidGraphQL {
+            message: match operation_name {
+                Some(name) => format!("Unknown operation named \"{name}\""),
+                None => {
+                    "Must provide operation name if query contains multiple operations".to_owned()
+                }
+            },
+            locations: Vec::new(),
+        }
+        .into()
+    })
+}
+
 /// Normalizes the selection set of the specified operation.
 ///
 /// This method applies the following transformations:
@@ -1795,26 +3701,44 @@ fn is_deferred_selection(directives: &DirectiveList) -> bool {
 ///   handled by query planning.
 /// - Hoist fragment spreads/inline fragments into their parents if they have no directives and
 ///   their parent type matches.
+/// - Remove `@defer(if: false)` applications (see `NormalizedSelectionSet::with_normalized_defer()`).
+/// - Reject root `@defer`s that can't be honored (see `validate_root_defers()`).
+/// - Remove redundant type conditions (see `NormalizedSelectionSet::simplify_type_conditions()`).
+/// - Remove selections that are never included (see
+///   `NormalizedSelectionSet::without_skipped_selections()`).
+/// - Share equal sub-selection sets (see `SelectionSetInterner`).
 pub(crate) fn normalize_operation(
     operation: &Operation,
     fragments: &IndexMap<Name, Node<Fragment>>,
     schema: &ValidFederationSchema,
     interface_types_with_interface_objects: &IndexSet<InterfaceTypeDefinitionPosition>,
 ) -> Result<NormalizedOperation, FederationError> {
-    let mut normalized_selection_set = NormalizedSelectionSet::normalize_and_expand_fragments(
+    validate_incremental_delivery_labels(&operation.selection_set, fragments)?;
+    let id_generator = Arc::new(IdGenerator::new());
+    let normalized_selection_set = NormalizedSelectionSet::normalize_and_expand_fragments(
         &operation.selection_set,
         fragments,
         schema,
         FragmentSpreadNormalizationOption::InlineFragmentSpread,
-    )?;
+        &id_generator,
+    )?
+    .with_normalized_defer()?;
+    validate_root_defers(&normalized_selection_set, operation.operation_type)?;
+    let mut normalized_selection_set = normalized_selection_set
+        .simplify_type_conditions()?
+        .without_skipped_selections()?;
     normalized_selection_set.optimize_sibling_typenames(interface_types_with_interface_objects)?;
+    SelectionSetInterner::default().intern(&mut normalized_selection_set);
 
     let normalized_fragments: IndexMap<Name, Node<NormalizedFragment>> = fragments
         .iter()
         .map(|(name, fragment)| {
             (
                 name.clone(),
-                Node::new(NormalizedFragment::normalize(fragment, schema).unwrap()),
+                Node::new(
+                    NormalizedFragment::normalize(fragment, fragments, schema, &id_generator)
+                        .unwrap(),
+                ),
             )
         })
         .collect();
@@ -1832,54 +3756,934 @@ pub(crate) fn normalize_operation(
         directives: Arc::new(operation.directives.clone()),
         selection_set: normalized_selection_set,
         fragments: Arc::new(normalized_fragments),
+        has_introspection: selects_schema_introspection(&operation.selection_set, fragments),
+        id_generator,
     };
     Ok(normalized_operation)
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::query_plan::operation::normalize_operation;
-    use crate::schema::position::InterfaceTypeDefinitionPosition;
-    use crate::schema::ValidFederationSchema;
-    use apollo_compiler::{name, ExecutableDocument};
-    use indexmap::IndexSet;
-
-    fn parse_schema_and_operation(
-        schema_and_operation: &str,
-    ) -> (ValidFederationSchema, ExecutableDocument) {
-        let (schema, executable_document) =
-            apollo_compiler::parse_mixed_validate(schema_and_operation, "document.graphql")
-                .unwrap();
-        let executable_document = executable_document.into_inner();
-        let schema = ValidFederationSchema::new(schema).unwrap();
-        (schema, executable_document)
+/// Validates the labels of the `@defer` and `@stream` applications of the given selection set (and
+/// of the fragments it spreads): they must be static strings, and unique (a fragment's labels
+/// being counted once however many times it's spread), so that executors can tell the incremental
+/// responses of each application apart.
+// PORT_NOTE: This corresponds to the `DeferStreamDirectiveLabelRule` validation rule of graphql-js,
+// which the JS codebase relied on.
+fn validate_incremental_delivery_labels(
+    selection_set: &SelectionSet,
+    fragments: &IndexMap<Name, Node<Fragment>>,
+) -> Result<(), FederationError> {
+    fn validate<'a>(
+        selection_set: &'a SelectionSet,
+        fragments: &'a IndexMap<Name, Node<Fragment>>,
+        labels: &mut IndexSet<&'a str>,
+        visited_fragments: &mut IndexSet<&'a Name>,
+    ) -> Result<(), FederationError> {
+        for selection in &selection_set.selections {
+            let (directives, selection_set) = match selection {
+                Selection::Field(field) => (&field.directives, Some(&field.selection_set)),
+                Selection::InlineFragment(inline_fragment) => (
+                    &inline_fragment.directives,
+                    Some(&inline_fragment.selection_set),
+                ),
+                Selection::FragmentSpread(fragment_spread) => {
+                    let fragment = fragments
+                        .get(&fragment_spread.fragment_name)
+                        .filter(|_| visited_fragments.insert(&fragment_spread.fragment_name));
+                    (
+                        &fragment_spread.directives,
+                        fragment.map(|fragment| &fragment.selection_set),
+                    )
+                }
+            };
+            for directive in directives.iter() {
+                if directive.name != "defer" && directive.name != "stream" {
+                    continue;
+                }
+                match directive
+                    .argument_by_name("label")
+                    .map(|value| value.as_ref())
+                {
+                    Some(Value::String(label)) if !labels.insert(label.as_str()) => {
+                        return Err(SingleFederationError::InvalidGraphQL {
+                            message: format!(
+                                "Label \"{}\" is used by more than one @defer or @stream application",
+                                label,
+                            ),
+                            locations: Vec::new(),
+                        }
+                        .into());
+                    }
+                    Some(Value::Variable(variable)) => {
+                        return Err(SingleFederationError::InvalidGraphQL {
+                            message: format!(
+                                "The label of @{} must be a static string, but variable \"${}\" is used",
+                                directive.name, variable,
+                            ),
+                            locations: Vec::new(),
+                        }
+                        .into());
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(selection_set) = selection_set {
+                validate(selection_set, fragments, labels, visited_fragments)?;
+            }
+        }
+        Ok(())
     }
-
-    #[test]
-    fn expands_named_fragments() {
-        let operation_with_named_fragment = r#"
-query NamedFragmentQuery {
-  foo {
-    id
-    ...Bar
-  }
+    validate(
+        selection_set,
+        fragments,
+        &mut IndexSet::new(),
+        &mut IndexSet::new(),
+    )
 }
 
-fragment Bar on Foo {
-  bar
-  baz
-}
+/// Validates the `@defer` applications on the root fields of an operation (i.e. on the inline
+/// fragments of its root selection set), which must have had their `@defer`s normalized:
+/// - Subscriptions can't defer root fields, unless the `if` argument of the `@defer` is a variable
+///   (which is then expected to be false).
+/// - Mutation fields must execute serially, so a root mutation field can only follow another one
+///   in the same deferred fragment (or in a fragment deferred within it). Otherwise, it would
+///   either execute before the previous field, or in parallel to it.
+// PORT_NOTE: The subscription rule corresponds to the `DeferStreamDirectiveOnValidOperationsRule`
+// validation rule of graphql-js. The JS codebase has no equivalent for the mutation rule, and
+// planned such mutations with their fields out of order.
+fn validate_root_defers(
+    selection_set: &NormalizedSelectionSet,
+    operation_type: OperationType,
+) -> Result<(), FederationError> {
+    /// Collects the root fields of the given selection set with the (IDs of the) deferred
+    /// fragments they're nested in.
+    fn collect<'a>(
+        selection_set: &'a NormalizedSelectionSet,
+        operation_type: OperationType,
+        defer_path: &mut Vec<usize>,
+        deferred_fragment_count: &mut usize,
+        fields: &mut Vec<(&'a NormalizedField, Vec<usize>)>,
+    ) -> Result<(), FederationError> {
+        for selection in selection_set.selections.values() {
+            if Conditions::from_directives(selection.directives())? == Conditions::Boolean(false) {
+                continue;
+            }
+            match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    fields.push((&field_selection.field, defer_path.clone()));
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    let directives = &inline_fragment_selection.inline_fragment.data().directives;
+                    let is_deferred = match directives.get("defer") {
+                        Some(directive) => {
+                            if operation_type == OperationType::Subscription
+                                && directive.argument_by_name("if").is_none()
+                            {
+                                return Err(SingleFederationError::InvalidGraphQL {
+                                    message: "@defer can't be used on the root fields of a subscription, unless its \"if\" argument is a variable set to false".to_owned(),
+                                    locations: Vec::new(),
+                                }
+                                .into());
+                            }
+                            true
+                        }
+                        None => false,
+                    };
+                    if is_deferred {
+                        defer_path.push(*deferred_fragment_count);
+                        *deferred_fragment_count += 1;
+                    }
+                    collect(
+                        &inline_fragment_selection.selection_set,
+                        operation_type,
+                        defer_path,
+                        deferred_fragment_count,
+                        fields,
+                    )?;
+                    if is_deferred {
+                        defer_path.pop();
+                    }
+                }
+                NormalizedSelection::FragmentSpread(_) => {
+                    return Err(FederationError::internal(
+                        "Unexpected fragment spread in validate_root_defers()",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
 
-type Query {
-  foo: Foo
+    if operation_type == OperationType::Query {
+        return Ok(());
+    }
+    let mut fields = vec![];
+    collect(
+        selection_set,
+        operation_type,
+        &mut vec![],
+        &mut 0,
+        &mut fields,
+    )?;
+    if operation_type != OperationType::Mutation {
+        return Ok(());
+    }
+    let mutation_fields = fields
+        .iter()
+        .filter(|(field, _)| field.data().name() != &TYPENAME_FIELD)
+        .collect::<Vec<_>>();
+    for pair in mutation_fields.windows(2) {
+        let [(previous, previous_defer_path), (field, defer_path)] = pair else {
+            continue;
+        };
+        if !defer_path.starts_with(previous_defer_path) {
+            return Err(SingleFederationError::InvalidGraphQL {
+                message: format!(
+                    "Mutation field \"{}\" can't be executed after mutation field \"{}\" because of @defer: a root mutation field can only be deferred along with, or within the deferred fragment of, the one preceding it",
+                    field.data().response_name(),
+                    previous.data().response_name(),
+                ),
+                locations: Vec::new(),
+            }
+            .into());
+        }
+    }
+    Ok(())
 }
 
-type Foo {
-  id: ID!
-  bar: String!
-  baz: Int
+/// Whether the given root selection set selects `__schema` or `__type`, directly or through
+/// fragments.
+fn selects_schema_introspection(
+    selection_set: &SelectionSet,
+    fragments: &IndexMap<Name, Node<Fragment>>,
+) -> bool {
+    selection_set
+        .selections
+        .iter()
+        .any(|selection| match selection {
+            Selection::Field(field) => field.name == "__schema" || field.name == "__type",
+            Selection::FragmentSpread(fragment_spread) => fragments
+                .get(&fragment_spread.fragment_name)
+                .is_some_and(|fragment| {
+                    selects_schema_introspection(&fragment.selection_set, fragments)
+                }),
+            Selection::InlineFragment(inline_fragment) => {
+                selects_schema_introspection(&inline_fragment.selection_set, fragments)
+            }
+        })
 }
-"#;
+
+/// Programmatic construction of normalized operations, for planner tests and tooling that would
+/// otherwise have to write out (and parse) GraphQL text for every case.
+///
+/// Selections are described through closures and are only resolved against the schema when
+/// `OperationBuilder::build()` is called, at which point any unknown field or type, inapplicable
+/// type condition, invalid argument or directive, or undeclared variable is reported as an invalid
+/// GraphQL error. Selections with the same key are merged, exactly as they would be when
+/// normalizing a parsed operation.
+///
+/// ```ignore
+/// let operation = OperationBuilder::query(&schema)
+///     .name("MyQuery")
+///     .field("t", |t| {
+///         t.fields(["k", "a"]);
+///         t.inline_fragment(Some("T"), |f| {
+///             f.field("b", |_| {});
+///         });
+///     })
+///     .build()?;
+/// ```
+pub mod builder {
+    use crate::error::{FederationError, SingleFederationError};
+    use crate::query_plan::operation::normalized_field_selection::{
+        NormalizedField, NormalizedFieldData, NormalizedFieldSelection,
+    };
+    use crate::query_plan::operation::normalized_inline_fragment_selection::{
+        NormalizedInlineFragment, NormalizedInlineFragmentData, NormalizedInlineFragmentSelection,
+    };
+    use crate::query_plan::operation::{
+        IdGenerator, NormalizedOperation, NormalizedSelection, NormalizedSelectionSet,
+    };
+    use crate::schema::position::{
+        CompositeTypeDefinitionPosition, SchemaRootDefinitionKind, SchemaRootDefinitionPosition,
+    };
+    use crate::schema::ValidFederationSchema;
+    use apollo_compiler::ast::{
+        Argument, Directive, DirectiveList, DirectiveLocation, InputValueDefinition, Name, Type,
+        Value,
+    };
+    use apollo_compiler::executable::VariableDefinition;
+    use apollo_compiler::schema::ExtendedType;
+    use apollo_compiler::Node;
+    use indexmap::IndexMap;
+    use std::sync::Arc;
+
+    /// Builds a `NormalizedOperation` against a schema. See the module documentation for usage.
+    pub struct OperationBuilder {
+        schema: ValidFederationSchema,
+        root_kind: SchemaRootDefinitionKind,
+        name: Option<String>,
+        variables: Vec<Node<VariableDefinition>>,
+        directives: DirectiveList,
+        selection_set: SelectionSetBuilder,
+    }
+
+    impl OperationBuilder {
+        pub fn query(schema: &ValidFederationSchema) -> Self {
+            Self::new(schema, SchemaRootDefinitionKind::Query)
+        }
+
+        pub fn mutation(schema: &ValidFederationSchema) -> Self {
+            Self::new(schema, SchemaRootDefinitionKind::Mutation)
+        }
+
+        pub fn subscription(schema: &ValidFederationSchema) -> Self {
+            Self::new(schema, SchemaRootDefinitionKind::Subscription)
+        }
+
+        fn new(schema: &ValidFederationSchema, root_kind: SchemaRootDefinitionKind) -> Self {
+            Self {
+                schema: schema.clone(),
+                root_kind,
+                name: None,
+                variables: Vec::new(),
+                directives: DirectiveList::new(),
+                selection_set: SelectionSetBuilder::default(),
+            }
+        }
+
+        pub fn name(mut self, name: &str) -> Self {
+            self.name = Some(name.to_owned());
+            self
+        }
+
+        pub fn variable(mut self, definition: VariableDefinition) -> Self {
+            self.variables.push(Node::new(definition));
+            self
+        }
+
+        pub fn directive(mut self, directive: Directive) -> Self {
+            self.directives.push(Node::new(directive));
+            self
+        }
+
+        /// Adds a field to the root selection set, using the given closure to describe it.
+        pub fn field(mut self, name: &str, build: impl FnOnce(&mut FieldBuilder)) -> Self {
+            self.selection_set.field(name, build);
+            self
+        }
+
+        /// Adds the given leaf fields (fields without arguments, directives or sub-selections) to
+        /// the root selection set.
+        pub fn fields<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+            self.selection_set.fields(names);
+            self
+        }
+
+        /// Adds an inline fragment to the root selection set, using the given closure to describe
+        /// its selections.
+        pub fn inline_fragment(
+            mut self,
+            type_condition: Option<&str>,
+            build: impl FnOnce(&mut InlineFragmentBuilder),
+        ) -> Self {
+            self.selection_set.inline_fragment(type_condition, build);
+            self
+        }
+
+        /// Resolves the described selections against the schema and builds the operation.
+        pub fn build(self) -> Result<NormalizedOperation, FederationError> {
+            let root_type_name = SchemaRootDefinitionPosition {
+                root_kind: self.root_kind,
+            }
+            .get(self.schema.schema())?
+            .name
+            .clone();
+            let root_type_position: CompositeTypeDefinitionPosition =
+                self.schema.get_type(root_type_name)?.try_into()?;
+            let location = match self.root_kind {
+                SchemaRootDefinitionKind::Query => DirectiveLocation::Query,
+                SchemaRootDefinitionKind::Mutation => DirectiveLocation::Mutation,
+                SchemaRootDefinitionKind::Subscription => DirectiveLocation::Subscription,
+            };
+            check_directives(&self.schema, &self.variables, &self.directives, location)?;
+            let id_generator = Arc::new(IdGenerator::new());
+            Ok(NormalizedOperation {
+                root_kind: self.root_kind,
+                name: self.name.map(Name::new).transpose()?,
+                directives: Arc::new(self.directives),
+                selection_set: self.selection_set.build(
+                    &self.schema,
+                    root_type_position,
+                    &self.variables,
+                    &id_generator,
+                )?,
+                fragments: Arc::new(IndexMap::new()),
+                has_introspection: false,
+                variables: Arc::new(self.variables),
+                schema: self.schema,
+                id_generator,
+            })
+        }
+    }
+
+    /// Describes the selections of a selection set, whose type is only known once built.
+    #[derive(Default)]
+    pub struct SelectionSetBuilder {
+        selections: Vec<SelectionBuilder>,
+    }
+
+    enum SelectionBuilder {
+        Field(FieldBuilder),
+        InlineFragment(InlineFragmentBuilder),
+    }
+
+    impl SelectionSetBuilder {
+        pub fn field(&mut self, name: &str, build: impl FnOnce(&mut FieldBuilder)) -> &mut Self {
+            let mut field = FieldBuilder::new(name);
+            build(&mut field);
+            self.selections.push(SelectionBuilder::Field(field));
+            self
+        }
+
+        pub fn fields<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) -> &mut Self {
+            for name in names {
+                self.field(name, |_| {});
+            }
+            self
+        }
+
+        pub fn inline_fragment(
+            &mut self,
+            type_condition: Option<&str>,
+            build: impl FnOnce(&mut InlineFragmentBuilder),
+        ) -> &mut Self {
+            let mut inline_fragment = InlineFragmentBuilder::new(type_condition);
+            build(&mut inline_fragment);
+            self.selections
+                .push(SelectionBuilder::InlineFragment(inline_fragment));
+            self
+        }
+
+        fn build(
+            self,
+            schema: &ValidFederationSchema,
+            type_position: CompositeTypeDefinitionPosition,
+            variables: &[Node<VariableDefinition>],
+            id_generator: &IdGenerator,
+        ) -> Result<NormalizedSelectionSet, FederationError> {
+            let mut normalized_selections = vec![];
+            for selection in self.selections {
+                normalized_selections.push(match selection {
+                    SelectionBuilder::Field(field) => NormalizedSelection::Field(Arc::new(
+                        field.build(schema, &type_position, variables, id_generator)?,
+                    )),
+                    SelectionBuilder::InlineFragment(inline_fragment) => {
+                        NormalizedSelection::InlineFragment(Arc::new(inline_fragment.build(
+                            schema,
+                            &type_position,
+                            variables,
+                            id_generator,
+                        )?))
+                    }
+                });
+            }
+            let mut selection_set = NormalizedSelectionSet::empty(schema.clone(), type_position);
+            selection_set.merge_selections_into(normalized_selections.into_iter())?;
+            Ok(selection_set)
+        }
+    }
+
+    /// Describes a field selection. Sub-selections are only allowed (and are then required) when
+    /// the field has a composite type. Arguments must be defined by the field (with the required
+    /// ones given), and have values of the type of their definition, where variables must be
+    /// declared (with `OperationBuilder::variable()`) with a type usable for the argument.
+    pub struct FieldBuilder {
+        name: String,
+        alias: Option<String>,
+        arguments: Vec<Node<Argument>>,
+        directives: DirectiveList,
+        selection_set: SelectionSetBuilder,
+    }
+
+    impl FieldBuilder {
+        fn new(name: &str) -> Self {
+            Self {
+                name: name.to_owned(),
+                alias: None,
+                arguments: Vec::new(),
+                directives: DirectiveList::new(),
+                selection_set: SelectionSetBuilder::default(),
+            }
+        }
+
+        pub fn alias(&mut self, alias: &str) -> &mut Self {
+            self.alias = Some(alias.to_owned());
+            self
+        }
+
+        pub fn argument(&mut self, name: Name, value: Value) -> &mut Self {
+            self.arguments.push(Node::new(Argument {
+                name,
+                value: Node::new(value),
+            }));
+            self
+        }
+
+        pub fn directive(&mut self, directive: Directive) -> &mut Self {
+            self.directives.push(Node::new(directive));
+            self
+        }
+
+        pub fn field(&mut self, name: &str, build: impl FnOnce(&mut FieldBuilder)) -> &mut Self {
+            self.selection_set.field(name, build);
+            self
+        }
+
+        pub fn fields<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) -> &mut Self {
+            self.selection_set.fields(names);
+            self
+        }
+
+        pub fn inline_fragment(
+            &mut self,
+            type_condition: Option<&str>,
+            build: impl FnOnce(&mut InlineFragmentBuilder),
+        ) -> &mut Self {
+            self.selection_set.inline_fragment(type_condition, build);
+            self
+        }
+
+        fn build(
+            self,
+            schema: &ValidFederationSchema,
+            parent_type_position: &CompositeTypeDefinitionPosition,
+            variables: &[Node<VariableDefinition>],
+            id_generator: &IdGenerator,
+        ) -> Result<NormalizedFieldSelection, FederationError> {
+            let field_name = Name::new(self.name.as_str())?;
+            if schema
+                .schema()
+                .type_field(parent_type_position.type_name(), &field_name)
+                .is_err()
+            {
+                return Err(invalid(format!(
+                    "Cannot query field \"{}\" on type \"{}\"",
+                    field_name, parent_type_position,
+                )));
+            }
+            let field_position = parent_type_position.field(field_name)?;
+            let field_definition = field_position.get(schema.schema())?;
+            check_arguments(
+                schema,
+                variables,
+                &self.arguments,
+                &field_definition.arguments,
+                &format!("field \"{}\"", field_position),
+            )?;
+            check_directives(
+                schema,
+                variables,
+                &self.directives,
+                DirectiveLocation::Field,
+            )?;
+            let field_type_name = field_definition.ty.inner_named_type().clone();
+            let field_composite_type_position: Option<CompositeTypeDefinitionPosition> =
+                schema.get_type(field_type_name)?.try_into().ok();
+            let selection_set = match field_composite_type_position {
+                Some(type_position) => {
+                    if self.selection_set.selections.is_empty() {
+                        return Err(invalid(format!(
+                            "Field \"{}\" has composite type \"{}\" but no selections were given",
+                            field_position, type_position,
+                        )));
+                    }
+                    Some(self.selection_set.build(
+                        schema,
+                        type_position,
+                        variables,
+                        id_generator,
+                    )?)
+                }
+                None => {
+                    if !self.selection_set.selections.is_empty() {
+                        return Err(invalid(format!(
+                            "Field \"{}\" has non-composite type but selections were given",
+                            field_position,
+                        )));
+                    }
+                    None
+                }
+            };
+            Ok(NormalizedFieldSelection {
+                field: NormalizedField::new(NormalizedFieldData {
+                    schema: schema.clone(),
+                    field_position,
+                    alias: self.alias.map(Name::new).transpose()?,
+                    arguments: Arc::new(self.arguments),
+                    directives: Arc::new(self.directives),
+                    sibling_typename: None,
+                }),
+                selection_set,
+            })
+        }
+    }
+
+    /// Checks that the given arguments are defined by the field or directive they're given to
+    /// (described by `owner`, e.g. `field "T.f"`), are given at most once, have values of the type
+    /// of their definition, and that the required ones are given.
+    fn check_arguments(
+        schema: &ValidFederationSchema,
+        variables: &[Node<VariableDefinition>],
+        arguments: &[Node<Argument>],
+        argument_definitions: &[Node<InputValueDefinition>],
+        owner: &str,
+    ) -> Result<(), FederationError> {
+        for (index, argument) in arguments.iter().enumerate() {
+            let Some(argument_definition) = argument_definitions
+                .iter()
+                .find(|definition| definition.name == argument.name)
+            else {
+                return Err(invalid(format!(
+                    "Argument \"{}\" is not defined by {}",
+                    argument.name, owner,
+                )));
+            };
+            if arguments[..index]
+                .iter()
+                .any(|previous| previous.name == argument.name)
+            {
+                return Err(invalid(format!(
+                    "Argument \"{}\" of {} is given more than once",
+                    argument.name, owner,
+                )));
+            }
+            check_input_value(
+                schema,
+                variables,
+                &argument.value,
+                &argument_definition.ty,
+                argument_definition.default_value.is_some(),
+            )
+            .map_err(|reason| {
+                invalid(format!(
+                    "Invalid value for argument \"{}\" of {}: {}",
+                    argument.name, owner, reason,
+                ))
+            })?;
+        }
+        if let Some(missing) = argument_definitions.iter().find(|definition| {
+            definition.is_required()
+                && !arguments
+                    .iter()
+                    .any(|argument| argument.name == definition.name)
+        }) {
+            return Err(invalid(format!(
+                "Required argument \"{}\" of {} is not given",
+                missing.name, owner,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that the given directives are defined by the schema, can be used at the given
+    /// location, and have valid arguments (see `check_arguments()`).
+    fn check_directives(
+        schema: &ValidFederationSchema,
+        variables: &[Node<VariableDefinition>],
+        directives: &DirectiveList,
+        location: DirectiveLocation,
+    ) -> Result<(), FederationError> {
+        for directive in directives.iter() {
+            let Some(definition) = schema.schema().directive_definitions.get(&directive.name)
+            else {
+                return Err(invalid(format!(
+                    "Directive \"@{}\" is not defined by the schema",
+                    directive.name,
+                )));
+            };
+            if !definition.locations.contains(&location) {
+                return Err(invalid(format!(
+                    "Directive \"@{}\" cannot be used on {}",
+                    directive.name, location,
+                )));
+            }
+            check_arguments(
+                schema,
+                variables,
+                &directive.arguments,
+                &definition.arguments,
+                &format!("directive \"@{}\"", directive.name),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the given value can be given for the given input type, returning why it can't
+    /// otherwise. `has_default` is whether the value is for an argument or input field with a
+    /// default value, in which case nullable variables can be used for non-null types.
+    fn check_input_value(
+        schema: &ValidFederationSchema,
+        variables: &[Node<VariableDefinition>],
+        value: &Value,
+        ty: &Type,
+        has_default: bool,
+    ) -> Result<(), String> {
+        match value {
+            Value::Variable(name) => {
+                let definition = variables
+                    .iter()
+                    .find(|definition| definition.name == *name)
+                    .ok_or_else(|| format!("variable \"${}\" is not declared", name))?;
+                let target_ty = if has_default || definition.default_value.is_some() {
+                    ty.clone().nullable()
+                } else {
+                    ty.clone()
+                };
+                if definition.ty.is_assignable_to(&target_ty) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "variable \"${}\" of type \"{}\" cannot be used for type \"{}\"",
+                        name, definition.ty, ty,
+                    ))
+                }
+            }
+            Value::Null if ty.is_non_null() => {
+                Err(format!("null is not a valid value of type \"{}\"", ty))
+            }
+            Value::Null => Ok(()),
+            Value::List(items) if ty.is_list() => items.iter().try_for_each(|item| {
+                check_input_value(schema, variables, item, ty.item_type(), false)
+            }),
+            // A single value is coerced to a list of that value.
+            _ if ty.is_list() => check_input_value(schema, variables, value, ty.item_type(), false),
+            _ => {
+                let type_name = ty.inner_named_type();
+                let is_valid = match (schema.schema().types.get(type_name), value) {
+                    // Custom scalars accept any value.
+                    (Some(ExtendedType::Scalar(scalar)), _) if !scalar.is_built_in() => true,
+                    (Some(ExtendedType::Scalar(_)), Value::Int(_)) => {
+                        matches!(type_name.as_str(), "Int" | "Float" | "ID")
+                    }
+                    (Some(ExtendedType::Scalar(_)), Value::Float(_)) => type_name == "Float",
+                    (Some(ExtendedType::Scalar(_)), Value::String(_)) => {
+                        matches!(type_name.as_str(), "String" | "ID")
+                    }
+                    (Some(ExtendedType::Scalar(_)), Value::Boolean(_)) => type_name == "Boolean",
+                    (Some(ExtendedType::Enum(type_)), Value::Enum(value)) => {
+                        type_.values.contains_key(value)
+                    }
+                    (Some(ExtendedType::InputObject(type_)), Value::Object(fields)) => {
+                        for (field_name, field_value) in fields {
+                            let field_definition =
+                                type_.fields.get(field_name).ok_or_else(|| {
+                                    format!(
+                                        "input type \"{}\" has no field \"{}\"",
+                                        type_name, field_name,
+                                    )
+                                })?;
+                            check_input_value(
+                                schema,
+                                variables,
+                                field_value,
+                                &field_definition.ty,
+                                field_definition.default_value.is_some(),
+                            )?;
+                        }
+                        if let Some(missing) = type_.fields.values().find(|definition| {
+                            definition.is_required()
+                                && !fields.iter().any(|(name, _)| *name == definition.name)
+                        }) {
+                            return Err(format!(
+                                "required field \"{}\" of input type \"{}\" is not given",
+                                missing.name, type_name,
+                            ));
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                if is_valid {
+                    Ok(())
+                } else {
+                    Err(format!("{} is not a valid value of type \"{}\"", value, ty))
+                }
+            }
+        }
+    }
+
+    /// The error for a mistake in the described operation, which is reported when it's built.
+    fn invalid(message: String) -> FederationError {
+        SingleFederationError::InvalidGraphQL {
+            message,
+            locations: Vec::new(),
+        }
+        .into()
+    }
+
+    /// Describes an inline fragment selection.
+    pub struct InlineFragmentBuilder {
+        type_condition: Option<String>,
+        directives: DirectiveList,
+        selection_set: SelectionSetBuilder,
+    }
+
+    impl InlineFragmentBuilder {
+        fn new(type_condition: Option<&str>) -> Self {
+            Self {
+                type_condition: type_condition.map(|name| name.to_owned()),
+                directives: DirectiveList::new(),
+                selection_set: SelectionSetBuilder::default(),
+            }
+        }
+
+        pub fn directive(&mut self, directive: Directive) -> &mut Self {
+            self.directives.push(Node::new(directive));
+            self
+        }
+
+        pub fn field(&mut self, name: &str, build: impl FnOnce(&mut FieldBuilder)) -> &mut Self {
+            self.selection_set.field(name, build);
+            self
+        }
+
+        pub fn fields<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) -> &mut Self {
+            self.selection_set.fields(names);
+            self
+        }
+
+        pub fn inline_fragment(
+            &mut self,
+            type_condition: Option<&str>,
+            build: impl FnOnce(&mut InlineFragmentBuilder),
+        ) -> &mut Self {
+            self.selection_set.inline_fragment(type_condition, build);
+            self
+        }
+
+        fn build(
+            self,
+            schema: &ValidFederationSchema,
+            parent_type_position: &CompositeTypeDefinitionPosition,
+            variables: &[Node<VariableDefinition>],
+            id_generator: &IdGenerator,
+        ) -> Result<NormalizedInlineFragmentSelection, FederationError> {
+            let type_condition_position: Option<CompositeTypeDefinitionPosition> =
+                match self.type_condition {
+                    Some(type_condition) => {
+                        let type_condition = Name::new(type_condition)?;
+                        let position = schema
+                            .try_get_type(type_condition.clone())
+                            .and_then(|position| position.try_into().ok())
+                            .ok_or_else(|| {
+                                invalid(format!(
+                                    "Type condition \"{}\" is not a composite type of the schema",
+                                    type_condition,
+                                ))
+                            })?;
+                        Some(position)
+                    }
+                    None => None,
+                };
+            if let Some(type_condition_position) = &type_condition_position {
+                let parent_runtime_types =
+                    schema.cached_possible_runtime_types(parent_type_position.clone())?;
+                if schema
+                    .cached_possible_runtime_types(type_condition_position.clone())?
+                    .is_disjoint(&parent_runtime_types)
+                {
+                    return Err(invalid(format!(
+                        "Type condition \"{}\" can never apply within type \"{}\"",
+                        type_condition_position, parent_type_position,
+                    )));
+                }
+            }
+            check_directives(
+                schema,
+                variables,
+                &self.directives,
+                DirectiveLocation::InlineFragment,
+            )?;
+            let selection_set_type_position = type_condition_position
+                .clone()
+                .unwrap_or_else(|| parent_type_position.clone());
+            Ok(NormalizedInlineFragmentSelection {
+                inline_fragment: NormalizedInlineFragment::new(NormalizedInlineFragmentData {
+                    schema: schema.clone(),
+                    parent_type_position: parent_type_position.clone(),
+                    type_condition_position,
+                    directives: Arc::new(self.directives),
+                    selection_id: id_generator.next_id(),
+                }),
+                selection_set: self.selection_set.build(
+                    schema,
+                    selection_set_type_position,
+                    variables,
+                    id_generator,
+                )?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{ErrorCode, FederationError};
+    use crate::query_plan::conditions::Conditions;
+    use crate::query_plan::operation::builder::OperationBuilder;
+    use crate::query_plan::operation::{
+        normalize_operation, FragmentSpreadNormalizationOption, NormalizedOperation,
+        NormalizedSelection, NormalizedSelectionSet, SelectionMapperReturn,
+    };
+    use crate::schema::position::{
+        CompositeTypeDefinitionPosition, InterfaceTypeDefinitionPosition,
+        ObjectTypeDefinitionPosition,
+    };
+    use crate::schema::ValidFederationSchema;
+    use apollo_compiler::ast::{Argument, Directive, Name, Type, Value};
+    use apollo_compiler::executable::VariableDefinition;
+    use apollo_compiler::{name, ExecutableDocument, Node, Schema};
+    use indexmap::IndexSet;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::sync::Arc;
+
+    pub(super) fn parse_schema_and_operation(
+        schema_and_operation: &str,
+    ) -> (ValidFederationSchema, ExecutableDocument) {
+        let (schema, executable_document) =
+            apollo_compiler::parse_mixed_validate(schema_and_operation, "document.graphql")
+                .unwrap();
+        let executable_document = executable_document.into_inner();
+        let schema = ValidFederationSchema::new(schema).unwrap();
+        (schema, executable_document)
+    }
+
+    #[test]
+    fn expands_named_fragments() {
+        let operation_with_named_fragment = r#"
+query NamedFragmentQuery {
+  foo {
+    id
+    ...Bar
+  }
+}
+
+fragment Bar on Foo {
+  bar
+  baz
+}
+
+type Query {
+  foo: Foo
+}
+
+type Foo {
+  id: ID!
+  bar: String!
+  baz: Int
+}
+"#;
         let (schema, mut executable_document) =
             parse_schema_and_operation(operation_with_named_fragment);
         if let Some(operation) = executable_document
@@ -2126,6 +4930,52 @@ type T {
         }
     }
 
+    #[test]
+    fn merge_same_fields_with_same_directive_but_explicit_default_arg() {
+        let operation_with_directives_explicit_default_arg = r#"
+query Test($skipIf: Boolean!) {
+  t @customSkip(if: $skipIf, label: "foo") {
+    v1
+  }
+  t @customSkip(if: $skipIf) {
+    v2
+  }
+}
+
+directive @customSkip(if: Boolean!, label: String! = "foo") on FIELD | INLINE_FRAGMENT
+
+type Query {
+  t: T
+}
+
+type T {
+  v1: Int
+  v2: String
+}
+"#;
+        let (schema, mut executable_document) =
+            parse_schema_and_operation(operation_with_directives_explicit_default_arg);
+        if let Some((_, operation)) = executable_document.named_operations.first_mut() {
+            let normalized_operation = normalize_operation(
+                operation,
+                &executable_document.fragments,
+                &schema,
+                &IndexSet::new(),
+            )
+            .unwrap();
+            let expected = r#"query Test($skipIf: Boolean!) {
+  t @customSkip(if: $skipIf, label: "foo") {
+    v1
+    v2
+  }
+}"#;
+            let actual = normalized_operation.to_string();
+            assert_eq!(expected, actual);
+        } else {
+            panic!("unable to parse document")
+        }
+    }
+
     #[test]
     fn do_not_merge_when_only_one_field_specifies_directive() {
         let operation_one_field_with_directives = r#"
@@ -2701,13 +5551,105 @@ type V {
     }
 
     #[test]
-    fn removes_sibling_typename() {
-        let operation_with_typename = r#"
-query TestQuery {
-  foo {
-    __typename
-    v1
-    v2
+    fn reports_all_incompatible_selections_when_merging() {
+        let operations = r#"
+query A {
+  t {
+    x: a
+    y: c(n: 1)
+    w: c(n: 1)
+  }
+  i {
+    ... on T {
+      z: a
+    }
+  }
+}
+
+query B {
+  t {
+    x: b
+    y: c(n: 2)
+    w: c(n: 1)
+  }
+  i {
+    ... on T {
+      z: b
+    }
+  }
+}
+
+type Query {
+  t: T
+  i: I
+}
+
+interface I {
+  a: Int
+}
+
+type T implements I {
+  a: Int
+  b: Int
+  c(n: Int): Int
+}
+"#;
+        let (schema, mut executable_document) = parse_schema_and_operation(operations);
+        let mut normalize = |name: &str| {
+            let operation = executable_document.named_operations.get_mut(name).unwrap();
+            normalize_operation(
+                operation,
+                &executable_document.fragments,
+                &schema,
+                &IndexSet::new(),
+            )
+            .unwrap()
+            .selection_set
+        };
+        let (a, b) = (normalize("A"), normalize("B"));
+        let error = NormalizedSelectionSet::merge_all(&[&a, &b]).unwrap_err();
+        let errors = error
+            .errors()
+            .into_iter()
+            .map(|error| (error.code().code(), error.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                (
+                    "SELECTION_MERGE_CONFLICT",
+                    r#"Cannot merge field selection for field "T.b" into a field selection for field "T.a" (at selection path "t::x")"#.to_owned(),
+                ),
+                (
+                    "SELECTION_MERGE_CONFLICT",
+                    r#"Cannot merge field selection "y: c(n: 2)" into field selection "y: c(n: 1)" with different arguments (at selection path "t::y")"#.to_owned(),
+                ),
+                (
+                    "SELECTION_MERGE_CONFLICT",
+                    r#"Cannot merge field selection for field "T.b" into a field selection for field "T.a" (at selection path "i::... on T::z")"#.to_owned(),
+                ),
+            ]
+        );
+
+        // The selection set merged into is left as is when merging fails.
+        let mut merged_into = a.clone();
+        assert!(merged_into.merge_into(std::iter::once(b)).is_err());
+        assert_eq!(merged_into, a);
+
+        let error = NormalizedSelectionSet::merge_all(&[]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Cannot merge an empty list of selection sets"));
+    }
+
+    #[test]
+    fn removes_sibling_typename() {
+        let operation_with_typename = r#"
+query TestQuery {
+  foo {
+    __typename
+    v1
+    v2
   }
 }
 
@@ -2740,6 +5682,216 @@ type Foo {
         }
     }
 
+    #[test]
+    fn keeps_aliased_sibling_typename() {
+        let operation_with_typename = r#"
+query TestQuery {
+  foo {
+    t: __typename
+    v1
+  }
+  bar: foo {
+    __typename
+    v1
+  }
+}
+
+type Query {
+  foo: Foo
+}
+
+type Foo {
+  v1: ID!
+  v2: String
+}
+"#;
+        let (schema, executable_document) = parse_schema_and_operation(operation_with_typename);
+        let executable_document = executable_document.validate(schema.schema()).unwrap();
+        let normalized_operation =
+            NormalizedOperation::from_document(&executable_document, None, &schema).unwrap();
+        assert_eq!(
+            normalized_operation.selection_set.to_string(),
+            "{ foo { t: __typename v1 } bar: foo { v1 } }"
+        );
+        assert_eq!(
+            normalized_operation
+                .selection_set
+                .add_back_typename_in_attachments()
+                .unwrap()
+                .to_string(),
+            "{ foo { t: __typename v1 } bar: foo { __typename v1 } }"
+        );
+
+        // Rebasing keeps the alias, while the field is now the `__typename` of the new type.
+        let other_schema = ValidFederationSchema::new(
+            Schema::parse_and_validate(
+                "type Query { foo: Bar } type Bar { v1: ID! }",
+                "other.graphql",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let query_type: CompositeTypeDefinitionPosition = ObjectTypeDefinitionPosition {
+            type_name: name!("Query"),
+        }
+        .into();
+        let rebased = normalized_operation
+            .selection_set
+            .rebase_on(&query_type, &other_schema)
+            .unwrap();
+        assert_eq!(
+            rebased.to_string(),
+            normalized_operation.selection_set.to_string()
+        );
+        let Some(NormalizedSelection::Field(foo)) = rebased.selections.values().next() else {
+            panic!("expected a field selection");
+        };
+        let Some(NormalizedSelection::Field(typename)) = foo
+            .selection_set
+            .as_ref()
+            .and_then(|selection_set| selection_set.selections.values().next())
+        else {
+            panic!("expected a field selection");
+        };
+        assert_eq!(
+            typename.field.data().field_position.to_string(),
+            "Bar.__typename"
+        );
+        assert_eq!(typename.field.data().response_name(), "t");
+    }
+
+    #[test]
+    fn lazily_maps_selection_sets() {
+        let (schema, document) = parse_schema_and_operation(
+            r#"
+type Query {
+  t: T
+  u: T
+}
+
+type T {
+  v1: Int
+  v2: Int
+}
+
+{ t { v1 v2 } u { v1 } }
+"#,
+        );
+        let document = document.validate(schema.schema()).unwrap();
+        let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        let selection_set = &operation.selection_set;
+
+        // Nothing is rebuilt when all selections are kept as is.
+        let kept = selection_set
+            .lazy_map(|selection| Ok(SelectionMapperReturn::Selection(selection.clone())))
+            .unwrap();
+        assert!(Arc::ptr_eq(&kept.selections, &selection_set.selections));
+        let without_skipped = selection_set.without_skipped_selections().unwrap();
+        assert!(Arc::ptr_eq(
+            &without_skipped.selections,
+            &selection_set.selections
+        ));
+
+        // Only the changed selections are rebuilt.
+        let mapped = selection_set
+            .lazy_map(|selection| {
+                let NormalizedSelection::Field(field_selection) = selection else {
+                    return Ok(SelectionMapperReturn::None);
+                };
+                Ok(
+                    match field_selection.field.data().response_name().as_str() {
+                        "u" => SelectionMapperReturn::None,
+                        _ => SelectionMapperReturn::Selection(selection.clone()),
+                    },
+                )
+            })
+            .unwrap();
+        assert_eq!(mapped.to_string(), "{ t { v1 v2 } }");
+        let (Some(NormalizedSelection::Field(t)), Some(NormalizedSelection::Field(mapped_t))) = (
+            selection_set.selections.values().next(),
+            mapped.selections.values().next(),
+        ) else {
+            panic!("expected field selections");
+        };
+        assert!(Arc::ptr_eq(t, mapped_t));
+
+        // Replacements are merged into the result.
+        let merged = selection_set
+            .lazy_map(|_| {
+                Ok(SelectionMapperReturn::SelectionList(
+                    selection_set.selections.values().take(1).cloned().collect(),
+                ))
+            })
+            .unwrap();
+        assert_eq!(merged.to_string(), "{ t { v1 v2 } }");
+    }
+
+    #[test]
+    fn filters_selection_sets() {
+        let (schema, document) = parse_schema_and_operation(
+            r#"
+type Query {
+  t: T
+}
+
+type T {
+  v1: Int
+  v2: Int
+  t: T
+}
+
+{ t { v1 t { v1 v2 } ... on T { v1 } } }
+"#,
+        );
+        let document = document.validate(schema.schema()).unwrap();
+        let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        let is_not_v1 = |selection: &NormalizedSelection| {
+            Ok(!matches!(
+                selection,
+                NormalizedSelection::Field(field_selection)
+                    if field_selection.field.data().response_name() == "v1"
+            ))
+        };
+        let t_selection_set = operation
+            .selection_set
+            .selections
+            .values()
+            .next()
+            .and_then(|selection| selection.selection_set().ok().flatten())
+            .unwrap();
+        assert_eq!(t_selection_set.to_string(), "{ v1 t { v1 v2 } }");
+        assert_eq!(
+            t_selection_set.filter(is_not_v1).unwrap().to_string(),
+            "{ t { v1 v2 } }"
+        );
+        assert_eq!(
+            t_selection_set
+                .filter_recursive_depth_first(&mut |selection| is_not_v1(selection))
+                .unwrap()
+                .to_string(),
+            "{ t { v2 } }"
+        );
+
+        // Statically excluded selections are filtered out during normalization.
+        let (schema, document) = parse_schema_and_operation(
+            r#"
+type Query {
+  t: T
+}
+
+type T {
+  v1: Int
+  v2: Int
+}
+
+{ t { v1 @skip(if: true) ... on T @include(if: false) { v2 } } }
+"#,
+        );
+        let document = document.validate(schema.schema()).unwrap();
+        let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        assert_eq!(operation.selection_set.to_string(), "{ t { __typename } }");
+    }
+
     #[test]
     fn keeps_typename_if_no_other_selection() {
         let operation_with_single_typename = r#"
@@ -2829,4 +5981,1499 @@ scalar FieldSet
             assert_eq!(expected, actual);
         }
     }
+
+    #[test]
+    fn removes_redundant_type_conditions() {
+        let operation_with_type_conditions = r#"
+query Test {
+  me {
+    ... on Node {
+      id
+    }
+    ... on Named {
+      name
+    }
+  }
+  node {
+    ... on Named {
+      name
+    }
+    ... on Result {
+      ... on User {
+        name
+      }
+    }
+  }
+}
+
+type Query {
+  me: User
+  node: Node
+}
+
+interface Node {
+  id: ID!
+}
+
+interface Named {
+  name: String
+}
+
+type User implements Node & Named {
+  id: ID!
+  name: String
+}
+
+type Post implements Node & Named {
+  id: ID!
+  name: String
+}
+
+union Result = User | Post
+"#;
+        let (schema, executable_document) =
+            parse_schema_and_operation(operation_with_type_conditions);
+        let operation = executable_document.named_operations.get("Test").unwrap();
+        let normalized_operation = normalize_operation(
+            operation,
+            &executable_document.fragments,
+            &schema,
+            &IndexSet::new(),
+        )
+        .unwrap();
+        // `Node` doesn't implement `Named`, so `... on Named` is kept within `node` even though
+        // all its runtime types are `Named`.
+        insta::assert_snapshot!(normalized_operation, @r###"
+        query Test {
+          me {
+            id
+            name
+          }
+          node {
+            ... on Named {
+              name
+            }
+            ... on User {
+              name
+            }
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn builds_operation_matching_normalized_operation() {
+        let operation_and_schema = r#"
+query BuiltQuery {
+  foo {
+    id
+    bar
+    ... on Foo @skip(if: false) {
+      baz
+    }
+  }
+  foo {
+    baz
+  }
+}
+
+type Query {
+  foo: Foo
+}
+
+type Foo {
+  id: ID!
+  bar: String!
+  baz: Int
+}
+"#;
+        let (schema, mut executable_document) = parse_schema_and_operation(operation_and_schema);
+        let operation = executable_document
+            .named_operations
+            .get_mut("BuiltQuery")
+            .unwrap();
+        let normalized_operation = normalize_operation(
+            operation,
+            &executable_document.fragments,
+            &schema,
+            &IndexSet::new(),
+        )
+        .unwrap();
+
+        let skip = Directive {
+            name: name!("skip"),
+            arguments: vec![Node::new(Argument {
+                name: name!("if"),
+                value: Node::new(Value::Boolean(false)),
+            })],
+        };
+        let built_operation = OperationBuilder::query(&schema)
+            .name("BuiltQuery")
+            .field("foo", |foo| {
+                foo.fields(["id", "bar"]);
+                foo.inline_fragment(Some("Foo"), |fragment| {
+                    fragment.directive(skip).field("baz", |_| {});
+                });
+            })
+            .field("foo", |foo| {
+                foo.field("baz", |_| {});
+            })
+            .build()
+            .unwrap();
+        assert_eq!(
+            normalized_operation.to_string(),
+            built_operation.to_string()
+        );
+    }
+
+    #[test]
+    fn merges_all_selection_sets() {
+        let schema_source = r#"
+type Query {
+  t: T
+}
+
+type T {
+  t1: Int
+  t2: String
+}
+"#;
+        let schema = Schema::parse_and_validate(schema_source, "schema.graphql").unwrap();
+        let schema = ValidFederationSchema::new(schema).unwrap();
+        let first = OperationBuilder::query(&schema)
+            .field("t", |t| {
+                t.fields(["t1"]);
+            })
+            .build()
+            .unwrap()
+            .selection_set;
+        let second = OperationBuilder::query(&schema)
+            .field("t", |t| {
+                t.fields(["t2"]);
+            })
+            .build()
+            .unwrap()
+            .selection_set;
+        let expected = "{ t { t1 t2 } }";
+
+        let merged = NormalizedSelectionSet::merge_all(&[&first, &second]).unwrap();
+        assert_eq!(merged.to_string(), expected);
+
+        let mut merged_into = first.clone();
+        merged_into.merge_into(std::iter::once(second)).unwrap();
+        assert_eq!(merged_into.to_string(), expected);
+
+        let error = NormalizedSelectionSet::merge_all(&[]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Cannot merge an empty list of selection sets"));
+    }
+
+    #[test]
+    fn compares_selection_sets_structurally() {
+        let (schema, document) = parse_schema_and_operation(
+            r#"
+type Query {
+  t(a: Int, b: Int): T
+  u: T
+}
+
+type T {
+  t1: Int
+  t2: String
+}
+
+query A { t(a: 1, b: 2) { t1 t2 } }
+query B { t(b: 2, a: 1) { t2 t1 } }
+query C { t(a: 1, b: 2) { t1 } }
+query D { t: u { t1 } }
+query E { t(a: 1, b: 2) { t1 __typename } }
+"#,
+        );
+        let document = document.validate(schema.schema()).unwrap();
+        let selection_set = |name| {
+            NormalizedOperation::from_document(&document, Some(name), &schema)
+                .unwrap()
+                .selection_set
+        };
+        let (a, b, c, d, e) = (
+            selection_set("A"),
+            selection_set("B"),
+            selection_set("C"),
+            selection_set("D"),
+            selection_set("E"),
+        );
+
+        assert!(a.equals(&b));
+        assert!(a.contains(&c) && !c.contains(&a) && !a.equals(&c));
+        // `D` selects another field under the same response name.
+        assert!(!a.contains(&d));
+        // `E`'s `__typename` is stored as a sibling typename.
+        assert!(e.contains(&c) && !c.contains(&e) && !e.equals(&c));
+        assert!(!a.contains(&e) && !e.contains(&a));
+    }
+
+    #[test]
+    fn computes_selection_set_differences_and_intersections() {
+        let (schema, document) = parse_schema_and_operation(
+            r#"
+type Query {
+  t: T
+}
+
+type T {
+  id: ID!
+  name: String
+  u: U
+}
+
+type U {
+  a: Int
+  b: Int
+}
+
+query Selected { t { __typename id u { a b } } }
+query Provided { t { id u { a } } }
+query WithTypename { t { __typename name } }
+"#,
+        );
+        let document = document.validate(schema.schema()).unwrap();
+        let selection_set = |name| {
+            NormalizedOperation::from_document(&document, Some(name), &schema)
+                .unwrap()
+                .selection_set
+        };
+        let selected = selection_set("Selected");
+        let provided = selection_set("Provided");
+        let with_typename = selection_set("WithTypename");
+
+        // The sibling typename of `id` is kept as a field once `id` is removed.
+        assert_eq!(
+            selected.minus(&provided).unwrap().to_string(),
+            "{ t { u { b } __typename } }"
+        );
+        assert_eq!(
+            selected.intersection(&provided).unwrap().to_string(),
+            "{ t { id u { a } } }"
+        );
+        assert_eq!(
+            selected.minus(&with_typename).unwrap().to_string(),
+            "{ t { id u { a b } } }"
+        );
+        assert_eq!(
+            selected.intersection(&with_typename).unwrap().to_string(),
+            "{ t { __typename } }"
+        );
+        assert!(selected.minus(&selected).unwrap().selections.is_empty());
+        assert!(selected.intersection(&selected).unwrap().equals(&selected));
+    }
+
+    #[test]
+    fn computes_used_variable_definitions() {
+        let (schema, document) = parse_schema_and_operation(
+            r#"
+type Query {
+  t(a: Int): T
+  u(c: Int): T
+}
+
+type T {
+  x: Int
+}
+
+query Q($c: Int, $a: Int, $b: Boolean!) {
+  t(a: $a) { x }
+  ... on Query @include(if: $b) {
+    u(c: $c) { x }
+  }
+}
+"#,
+        );
+        let document = document.validate(schema.schema()).unwrap();
+        let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        let used_variables = |selection_set| {
+            operation
+                .used_variable_definitions(selection_set)
+                .iter()
+                .map(|definition| definition.name.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(used_variables(&operation.selection_set), ["c", "a", "b"]);
+
+        let Some(NormalizedSelection::InlineFragment(inline_fragment)) =
+            operation.selection_set.selections.values().nth(1)
+        else {
+            panic!("expected an inline fragment");
+        };
+        // The `@include` condition is on the fragment itself, so isn't part of its selection set.
+        assert_eq!(used_variables(&inline_fragment.selection_set), ["c"]);
+    }
+
+    #[test]
+    fn keeps_streamed_and_non_streamed_fields_apart() {
+        let (schema, document) = parse_schema_and_operation(
+            r#"
+directive @stream(label: String, initialCount: Int = 0, if: Boolean! = true) on FIELD
+
+type Query {
+  list: [T]
+}
+
+type T {
+  id: ID!
+  name: String
+}
+
+query Streamed { list @stream(initialCount: 1) { id } list { name } }
+query NotStreamed { list @stream(if: false) { id } }
+"#,
+        );
+        let document = document.validate(schema.schema()).unwrap();
+        let streamed = NormalizedOperation::from_document(&document, Some("Streamed"), &schema)
+            .unwrap()
+            .selection_set;
+        assert_eq!(
+            streamed.to_string(),
+            "{ list @stream(initialCount: 1) { id } list { name } }"
+        );
+        assert_eq!(
+            streamed
+                .streamed_field()
+                .map(|field| field.field_position.to_string()),
+            Some("Query.list".to_owned())
+        );
+
+        let not_streamed =
+            NormalizedOperation::from_document(&document, Some("NotStreamed"), &schema)
+                .unwrap()
+                .selection_set;
+        assert!(not_streamed.streamed_field().is_none());
+    }
+
+    #[test]
+    fn normalizes_defer_conditions() {
+        let (schema, document) = parse_schema_and_operation(
+            r#"
+directive @defer(label: String, if: Boolean! = true) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+type Query {
+  t: T
+}
+
+type T {
+  v1: Int
+  v2: Int
+  v3: Int
+}
+
+query Test($d: Boolean!) {
+  t {
+    ... on T @defer(if: false) { v1 }
+    ... on T @defer(if: true, label: "always") { v2 }
+    ... on T @defer(if: $d) { v3 }
+  }
+}
+"#,
+        );
+        let document = document.validate(schema.schema()).unwrap();
+        let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        assert_eq!(
+            operation.selection_set.to_string(),
+            r#"{ t { v1 ... on T @defer(label: "always") { v2 } ... on T @defer(if: $d) { v3 } } }"#
+        );
+    }
+
+    #[test]
+    fn validates_defer_labels() {
+        let schema_source = r#"
+directive @defer(label: String, if: Boolean! = true) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+type Query {
+  t: T
+  u: T
+}
+
+type T {
+  v1: Int
+  v2: Int
+}
+"#;
+        let normalize = |operation: &str| {
+            let (schema, document) =
+                parse_schema_and_operation(&format!("{schema_source}\n{operation}"));
+            let document = document.validate(schema.schema()).unwrap();
+            NormalizedOperation::from_document(&document, None, &schema)
+                .map(|_| ())
+                .map_err(|error| error.to_string())
+        };
+
+        // A fragment's labels are only counted once, however many times it's spread.
+        assert_eq!(
+            normalize(
+                r#"
+                { t { ...F } u { ...F } }
+                fragment F on T { ... @defer(label: "a") { v1 } }
+                "#
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            normalize(
+                r#"{ t { ... @defer(label: "a") { v1 } } u { ... @defer(label: "a") { v2 } } }"#
+            ),
+            Err(r#"Label "a" is used by more than one @defer or @stream application"#.to_owned())
+        );
+        assert_eq!(
+            normalize(r#"query($l: String) { t { ... @defer(label: $l) { v1 } } }"#),
+            Err(
+                r#"The label of @defer must be a static string, but variable "$l" is used"#
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn validates_root_defers() {
+        let schema_source = r#"
+directive @defer(label: String, if: Boolean! = true) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+type Query {
+  t: Int
+}
+
+type Mutation {
+  a: Int
+  b: Int
+  c: Int
+}
+
+type Subscription {
+  s: Int
+}
+"#;
+        let normalize = |operation: &str| {
+            let (schema, document) =
+                parse_schema_and_operation(&format!("{schema_source}\n{operation}"));
+            let document = document.validate(schema.schema()).unwrap();
+            NormalizedOperation::from_document(&document, None, &schema)
+                .map(|_| ())
+                .map_err(|error| error.to_string())
+        };
+
+        assert_eq!(
+            normalize("mutation { a ... @defer { b ... @defer { c } } }"),
+            Ok(())
+        );
+        assert_eq!(
+            normalize("mutation { a ... @defer { b __typename } ... @defer { __typename } }"),
+            Ok(())
+        );
+        assert_eq!(
+            normalize("mutation { ... @defer(if: false) { a } b }"),
+            Ok(())
+        );
+        assert_eq!(
+            normalize("mutation { ... @defer { a } b }"),
+            Err(r#"Mutation field "b" can't be executed after mutation field "a" because of @defer: a root mutation field can only be deferred along with, or within the deferred fragment of, the one preceding it"#.to_owned())
+        );
+        assert!(normalize("mutation { ... @defer { a } ... @defer { b } }").is_err());
+        assert!(normalize("mutation { ... @defer { a ... @defer { b } c } }").is_err());
+
+        assert_eq!(
+            normalize("subscription($d: Boolean!) { ... @defer(if: $d) { s } }"),
+            Ok(())
+        );
+        assert_eq!(
+            normalize("subscription { ... @defer { s } }"),
+            Err(r#"@defer can't be used on the root fields of a subscription, unless its "if" argument is a variable set to false"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn builder_rejects_invalid_selections() {
+        let schema = r#"
+type Query {
+  foo: Foo
+}
+
+type Foo {
+  id: ID!
+}
+
+type Bar {
+  id: ID!
+}
+"#;
+        let schema = ValidFederationSchema::new(
+            apollo_compiler::Schema::parse_and_validate(schema, "schema.graphql").unwrap(),
+        )
+        .unwrap();
+        let is_invalid_graphql = |result: Result<NormalizedOperation, FederationError>| {
+            result.is_err_and(|error| {
+                matches!(error.errors()[..], [error] if matches!(error.code(), ErrorCode::InvalidGraphQL))
+            })
+        };
+        assert!(is_invalid_graphql(
+            OperationBuilder::query(&schema)
+                .field("unknown", |_| {})
+                .build()
+        ));
+        // A type condition whose runtime types don't intersect those of the parent type.
+        assert!(is_invalid_graphql(
+            OperationBuilder::query(&schema)
+                .field("foo", |foo| {
+                    foo.inline_fragment(Some("Bar"), |fragment| {
+                        fragment.fields(["id"]);
+                    });
+                })
+                .build()
+        ));
+        assert!(is_invalid_graphql(
+            OperationBuilder::query(&schema).fields(["foo"]).build()
+        ));
+        assert!(is_invalid_graphql(
+            OperationBuilder::query(&schema)
+                .field("foo", |foo| {
+                    foo.field("id", |id| {
+                        id.fields(["id"]);
+                    });
+                })
+                .build()
+        ));
+        assert!(is_invalid_graphql(
+            OperationBuilder::query(&schema)
+                .field("foo", |foo| {
+                    foo.inline_fragment(Some("Unknown"), |fragment| {
+                        fragment.fields(["id"]);
+                    });
+                })
+                .build()
+        ));
+    }
+
+    #[test]
+    fn builder_rejects_invalid_arguments() {
+        let schema = r#"
+type Query {
+  foo(id: ID!, limit: Int = 10, filter: Filter): [Foo]
+}
+
+type Foo {
+  id: ID!
+}
+
+input Filter {
+  name: String!
+  kind: Kind
+}
+
+enum Kind {
+  A
+  B
+}
+"#;
+        let schema = ValidFederationSchema::new(
+            apollo_compiler::Schema::parse_and_validate(schema, "schema.graphql").unwrap(),
+        )
+        .unwrap();
+        let variable = |name: Name, ty: Type| VariableDefinition {
+            name,
+            ty: Node::new(ty),
+            default_value: None,
+            directives: Default::default(),
+        };
+        let build = |variables: Vec<VariableDefinition>, arguments: Vec<(Name, Value)>| {
+            let mut operation = OperationBuilder::query(&schema);
+            for definition in variables {
+                operation = operation.variable(definition);
+            }
+            operation
+                .field("foo", |foo| {
+                    for (name, value) in arguments {
+                        foo.argument(name, value);
+                    }
+                    foo.fields(["id"]);
+                })
+                .build()
+        };
+        let is_invalid_graphql = |result: Result<NormalizedOperation, FederationError>| {
+            result.is_err_and(|error| {
+                matches!(error.errors()[..], [error] if matches!(error.code(), ErrorCode::InvalidGraphQL))
+            })
+        };
+
+        let filter = |fields: Vec<(Name, Value)>| {
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, Node::new(value)))
+                    .collect(),
+            )
+        };
+        assert!(build(
+            vec![variable(name!("limit"), Type::Named(name!("Int")))],
+            vec![
+                (name!("id"), Value::String("1".into())),
+                (name!("limit"), Value::Variable(name!("limit"))),
+                (
+                    name!("filter"),
+                    filter(vec![
+                        (name!("name"), Value::String("a".into())),
+                        (name!("kind"), Value::Enum(name!("A"))),
+                    ]),
+                ),
+            ],
+        )
+        .is_ok());
+
+        // Unknown argument.
+        assert!(is_invalid_graphql(build(
+            vec![],
+            vec![
+                (name!("id"), Value::String("1".into())),
+                (name!("unknown"), Value::Int(1.into())),
+            ],
+        )));
+        // Argument given more than once.
+        assert!(is_invalid_graphql(build(
+            vec![],
+            vec![
+                (name!("id"), Value::String("1".into())),
+                (name!("id"), Value::String("2".into())),
+            ],
+        )));
+        // Missing required argument.
+        assert!(is_invalid_graphql(build(
+            vec![],
+            vec![(name!("limit"), Value::Int(1.into()))],
+        )));
+        // Literal of the wrong type.
+        assert!(is_invalid_graphql(build(
+            vec![],
+            vec![(name!("id"), Value::Boolean(true))],
+        )));
+        // Unknown enum value in an input object.
+        assert!(is_invalid_graphql(build(
+            vec![],
+            vec![
+                (name!("id"), Value::String("1".into())),
+                (
+                    name!("filter"),
+                    filter(vec![
+                        (name!("name"), Value::String("a".into())),
+                        (name!("kind"), Value::Enum(name!("C"))),
+                    ]),
+                ),
+            ],
+        )));
+        // Missing required input object field.
+        assert!(is_invalid_graphql(build(
+            vec![],
+            vec![
+                (name!("id"), Value::String("1".into())),
+                (name!("filter"), filter(vec![])),
+            ],
+        )));
+        // Undeclared variable.
+        assert!(is_invalid_graphql(build(
+            vec![],
+            vec![(name!("id"), Value::Variable(name!("id")))],
+        )));
+        // Variable of an incompatible type.
+        assert!(is_invalid_graphql(build(
+            vec![variable(name!("id"), Type::Named(name!("ID")))],
+            vec![(name!("id"), Value::Variable(name!("id")))],
+        )));
+    }
+
+    #[test]
+    fn builder_rejects_invalid_directives() {
+        let schema = r#"
+directive @fieldOnly on FIELD
+
+type Query {
+  foo: Foo
+}
+
+type Foo {
+  id: ID!
+}
+"#;
+        let schema = ValidFederationSchema::new(
+            apollo_compiler::Schema::parse_and_validate(schema, "schema.graphql").unwrap(),
+        )
+        .unwrap();
+        let directive = |name: Name, arguments: Vec<(Name, Value)>| Directive {
+            name,
+            arguments: arguments
+                .into_iter()
+                .map(|(name, value)| {
+                    Node::new(Argument {
+                        name,
+                        value: Node::new(value),
+                    })
+                })
+                .collect(),
+        };
+        let flag = |ty: Type| VariableDefinition {
+            name: name!("flag"),
+            ty: Node::new(ty),
+            default_value: None,
+            directives: Default::default(),
+        };
+        let build_with_field_directive = |variables: Vec<VariableDefinition>, field_directive| {
+            let mut operation = OperationBuilder::query(&schema);
+            for definition in variables {
+                operation = operation.variable(definition);
+            }
+            operation
+                .field("foo", |foo| {
+                    foo.directive(field_directive);
+                    foo.fields(["id"]);
+                })
+                .build()
+        };
+        let is_invalid_graphql = |result: Result<NormalizedOperation, FederationError>| {
+            result.is_err_and(|error| {
+                matches!(error.errors()[..], [error] if matches!(error.code(), ErrorCode::InvalidGraphQL))
+            })
+        };
+
+        assert!(build_with_field_directive(
+            vec![flag(Type::NonNullNamed(name!("Boolean")))],
+            directive(
+                name!("skip"),
+                vec![(name!("if"), Value::Variable(name!("flag")))]
+            ),
+        )
+        .is_ok());
+        assert!(build_with_field_directive(vec![], directive(name!("fieldOnly"), vec![])).is_ok());
+
+        // Undefined directive.
+        assert!(is_invalid_graphql(build_with_field_directive(
+            vec![],
+            directive(name!("unknown"), vec![]),
+        )));
+        // Missing required argument.
+        assert!(is_invalid_graphql(build_with_field_directive(
+            vec![],
+            directive(name!("include"), vec![]),
+        )));
+        // Argument of the wrong type.
+        assert!(is_invalid_graphql(build_with_field_directive(
+            vec![],
+            directive(
+                name!("skip"),
+                vec![(name!("if"), Value::String("yes".into()))]
+            ),
+        )));
+        // Nullable variable for a non-null argument.
+        assert!(is_invalid_graphql(build_with_field_directive(
+            vec![flag(Type::Named(name!("Boolean")))],
+            directive(
+                name!("skip"),
+                vec![(name!("if"), Value::Variable(name!("flag")))]
+            ),
+        )));
+        // Directives used at locations they aren't allowed at.
+        assert!(is_invalid_graphql(
+            OperationBuilder::query(&schema)
+                .field("foo", |foo| {
+                    foo.inline_fragment(None, |fragment| {
+                        fragment.directive(directive(name!("fieldOnly"), vec![]));
+                        fragment.fields(["id"]);
+                    });
+                })
+                .build()
+        ));
+        assert!(is_invalid_graphql(
+            OperationBuilder::query(&schema)
+                .directive(directive(name!("fieldOnly"), vec![]))
+                .field("foo", |foo| {
+                    foo.fields(["id"]);
+                })
+                .build()
+        ));
+    }
+
+    /// A document with many named fragments (nested spreads, spreads on abstract types, fragments
+    /// spread more than once and defined out of use order), for determinism tests.
+    const FRAGMENTS_HEAVY_DOCUMENT: &str = r#"
+query Feed {
+  me {
+    ...UserDetails
+  }
+  feed {
+    ...NodeId
+    ...PostDetails
+    ... on User {
+      ...UserDetails
+    }
+  }
+}
+
+fragment PostDetails on Post {
+  title
+  author {
+    ...UserDetails
+  }
+  ...NodeId
+}
+
+fragment UserDetails on User {
+  ...NodeId
+  name
+  friends {
+    ...NodeId
+    name
+  }
+}
+
+fragment NodeId on Node {
+  id
+}
+
+type Query {
+  me: User
+  feed: [Node]
+}
+
+interface Node {
+  id: ID!
+}
+
+type User implements Node {
+  id: ID!
+  name: String
+  friends: [User]
+}
+
+type Post implements Node {
+  id: ID!
+  title: String
+  author: User
+}
+"#;
+
+    /// Normalizes `FRAGMENTS_HEAVY_DOCUMENT` from scratch, returning the normalized operation and
+    /// its fragments (by name, along with their selection set).
+    fn normalize_fragments_heavy_document() -> (String, Vec<(String, String)>) {
+        let (schema, executable_document) = parse_schema_and_operation(FRAGMENTS_HEAVY_DOCUMENT);
+        let operation = executable_document.named_operations.get("Feed").unwrap();
+        let normalized_operation = normalize_operation(
+            operation,
+            &executable_document.fragments,
+            &schema,
+            &IndexSet::new(),
+        )
+        .unwrap();
+        let fragments = normalized_operation
+            .fragments
+            .values()
+            .map(|fragment| {
+                (
+                    fragment.name.to_string(),
+                    fragment.selection_set.to_string(),
+                )
+            })
+            .collect();
+        (normalized_operation.to_string(), fragments)
+    }
+
+    #[test]
+    fn keeps_fragments_in_document_order() {
+        let (_, fragments) = normalize_fragments_heavy_document();
+        let fragment_names = fragments
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fragment_names, ["PostDetails", "UserDetails", "NodeId"]);
+    }
+
+    #[test]
+    fn normalizes_fragments_heavy_documents_deterministically() {
+        // Each normalization parses the document again, so any hash-based iteration order would
+        // vary between runs.
+        let (operation, fragments) = normalize_fragments_heavy_document();
+        for _ in 0..32 {
+            assert_eq!(
+                normalize_fragments_heavy_document(),
+                (operation.clone(), fragments.clone())
+            );
+        }
+        insta::assert_snapshot!(operation, @r###"
+        query Feed {
+          me {
+            id
+            name
+            friends {
+              id
+              name
+            }
+          }
+          feed {
+            id
+            ... on Post {
+              title
+              author {
+                id
+                name
+                friends {
+                  id
+                  name
+                }
+              }
+              id
+            }
+            ... on User {
+              id
+              name
+              friends {
+                id
+                name
+              }
+            }
+          }
+        }
+        "###);
+    }
+
+    #[test]
+    fn lifts_subgraph_operations_onto_supergraph() {
+        // In the subgraph, `I` is an object type standing in for the supergraph interface (as
+        // with `@interfaceObject`).
+        let subgraph_schema_and_operation = r#"
+query LiftedQuery($first: Int) {
+  i {
+    id
+    x(first: $first)
+  }
+}
+
+type Query {
+  i: I
+  _service: String
+}
+
+type I {
+  id: ID!
+  x(first: Int): Int
+}
+"#;
+        let supergraph_schema = ValidFederationSchema::new(
+            Schema::parse_and_validate(
+                r#"
+type Query {
+  i: I
+}
+
+interface I {
+  id: ID!
+  x(first: Int): Int
+}
+
+type A implements I {
+  id: ID!
+  x(first: Int): Int
+}
+"#,
+                "supergraph.graphql",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let (subgraph_schema, executable_document) =
+            parse_schema_and_operation(subgraph_schema_and_operation);
+        let operation = executable_document.get_operation(None).unwrap();
+        let normalized_operation = normalize_operation(
+            operation,
+            &executable_document.fragments,
+            &subgraph_schema,
+            &IndexSet::new(),
+        )
+        .unwrap();
+
+        let lifted = normalized_operation
+            .lift_to_supergraph(&supergraph_schema)
+            .unwrap();
+        assert_eq!(lifted.schema, supergraph_schema);
+        let Some(NormalizedSelection::Field(i)) = lifted.selection_set.selections.values().next()
+        else {
+            panic!("Expected a field selection");
+        };
+        assert!(matches!(
+            i.selection_set.as_ref().unwrap().type_position,
+            CompositeTypeDefinitionPosition::Interface(_)
+        ));
+        insta::assert_snapshot!(lifted, @r###"
+        query LiftedQuery($first: Int) {
+          i {
+            id
+            x(first: $first)
+          }
+        }
+        "###);
+
+        // Subgraph-only fields have no supergraph counterpart.
+        let (subgraph_schema, executable_document) = parse_schema_and_operation(
+            r#"
+{
+  _service
+}
+
+type Query {
+  _service: String
+}
+"#,
+        );
+        let operation = executable_document.get_operation(None).unwrap();
+        let normalized_operation = normalize_operation(
+            operation,
+            &executable_document.fragments,
+            &subgraph_schema,
+            &IndexSet::new(),
+        )
+        .unwrap();
+        assert!(normalized_operation
+            .lift_to_supergraph(&supergraph_schema)
+            .is_err());
+    }
+
+    #[test]
+    fn preserves_fragment_spread_data() {
+        let schema_and_operation = r#"
+query SpreadQuery($include: Boolean!) {
+  t {
+    ...TFragment @include(if: $include)
+  }
+}
+
+fragment TFragment on T @custom {
+  id
+}
+
+directive @custom on FRAGMENT_DEFINITION
+
+type Query {
+  t: I
+}
+
+interface I {
+  id: ID!
+}
+
+type T implements I {
+  id: ID!
+}
+"#;
+        let (schema, executable_document) = parse_schema_and_operation(schema_and_operation);
+        let operation = executable_document.get_operation(None).unwrap();
+        let selection_set = NormalizedSelectionSet::normalize_and_expand_fragments(
+            &operation.selection_set,
+            &executable_document.fragments,
+            &schema,
+            FragmentSpreadNormalizationOption::PreserveFragmentSpread,
+            &Default::default(),
+        )
+        .unwrap();
+        let t_selection_set = selection_set
+            .selections
+            .values()
+            .next()
+            .and_then(|selection| selection.selection_set().ok().flatten())
+            .unwrap();
+        let Some(NormalizedSelection::FragmentSpread(spread)) =
+            t_selection_set.selections.values().next()
+        else {
+            panic!("expected a fragment spread");
+        };
+        let data = spread.data();
+        assert_eq!(data.type_condition_position().type_name(), "T");
+        assert!(data.spread_directives().has("include"));
+        assert!(!data.spread_directives().has("custom"));
+        assert!(data.fragment_directives().has("custom"));
+        // Only the spread directives are conditions of the spread.
+        assert!(matches!(
+            t_selection_set.conditions().unwrap(),
+            Conditions::Variables(_)
+        ));
+
+        // The type condition must apply to the parent type the spread is rebased on.
+        let other_schema = ValidFederationSchema::new(
+            Schema::parse_and_validate(
+                r#"
+type Query {
+  t: I
+  u: U
+}
+
+interface I {
+  id: ID!
+}
+
+type T implements I {
+  id: ID!
+}
+
+type U {
+  id: ID!
+}
+"#,
+                "other.graphql",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let interface_type: CompositeTypeDefinitionPosition = InterfaceTypeDefinitionPosition {
+            type_name: name!("I"),
+        }
+        .into();
+        let rebased = t_selection_set
+            .rebase_on(&interface_type, &other_schema)
+            .unwrap();
+        assert_eq!(rebased.schema, other_schema);
+        let object_type: CompositeTypeDefinitionPosition = ObjectTypeDefinitionPosition {
+            type_name: name!("U"),
+        }
+        .into();
+        let error = t_selection_set
+            .rebase_on(&object_type, &other_schema)
+            .unwrap_err()
+            .with_subgraph_name("other");
+        let FederationError::SingleFederationError(error) = error else {
+            panic!("expected a single error");
+        };
+        assert!(matches!(error.code(), ErrorCode::OperationRebaseFailed));
+        let context = error.rebase_context().unwrap();
+        assert_eq!(context.parent_type, "U");
+        assert_eq!(context.parent_runtime_types, ["U"]);
+        assert_eq!(context.attempted_type.as_deref(), Some("T"));
+        assert_eq!(context.attempted_runtime_types, ["T"]);
+        assert_eq!(context.subgraph_name.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn reports_types_involved_in_rebase_errors() {
+        let schema_and_operation = r#"
+{
+  t {
+    name
+  }
+  i {
+    ... on T {
+      id
+    }
+  }
+}
+
+type Query {
+  t: T
+  i: I
+}
+
+interface I {
+  id: ID!
+}
+
+type T implements I {
+  id: ID!
+  name: String
+}
+
+type U implements I {
+  id: ID!
+}
+"#;
+        let (schema, executable_document) = parse_schema_and_operation(schema_and_operation);
+        let operation = executable_document.get_operation(None).unwrap();
+        let selection_set = NormalizedSelectionSet::normalize_and_expand_fragments(
+            &operation.selection_set,
+            &executable_document.fragments,
+            &schema,
+            FragmentSpreadNormalizationOption::InlineFragmentSpread,
+            &Default::default(),
+        )
+        .unwrap();
+        let mut sub_selection_sets = selection_set
+            .selections
+            .values()
+            .map(|selection| selection.selection_set().unwrap().unwrap());
+        let t_selection_set = sub_selection_sets.next().unwrap();
+        let i_selection_set = sub_selection_sets.next().unwrap();
+        let other_schema = ValidFederationSchema::new(
+            Schema::parse_and_validate(
+                r#"
+type Query {
+  i: I
+}
+
+interface I {
+  id: ID!
+}
+
+type U implements I {
+  id: ID!
+}
+
+type V implements I {
+  id: ID!
+}
+"#,
+                "other.graphql",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let rebase_context = |error: FederationError| {
+            let FederationError::SingleFederationError(error) = error else {
+                panic!("expected a single error");
+            };
+            assert!(matches!(error.code(), ErrorCode::OperationRebaseFailed));
+            assert!(error
+                .to_string()
+                .ends_with(&error.rebase_context().unwrap().to_string()));
+            error.rebase_context().unwrap().clone()
+        };
+
+        // A field missing from the new parent type, reported with the type declaring it.
+        let u_type: CompositeTypeDefinitionPosition = ObjectTypeDefinitionPosition {
+            type_name: name!("U"),
+        }
+        .into();
+        let error = t_selection_set
+            .rebase_on(&u_type, &other_schema)
+            .unwrap_err();
+        let context = rebase_context(error.with_subgraph_name("S1").with_subgraph_name("S2"));
+        assert_eq!(context.parent_type, "U");
+        assert_eq!(context.parent_runtime_types, ["U"]);
+        assert_eq!(context.attempted_type.as_deref(), Some("T"));
+        assert_eq!(context.attempted_runtime_types, ["T"]);
+        // The first subgraph recorded is the one the error happened in.
+        assert_eq!(context.subgraph_name.as_deref(), Some("S1"));
+
+        // A type condition missing from the new schema, reported with its runtime types in the
+        // schema it comes from.
+        let i_type: CompositeTypeDefinitionPosition = InterfaceTypeDefinitionPosition {
+            type_name: name!("I"),
+        }
+        .into();
+        let error = i_selection_set
+            .rebase_on(&i_type, &other_schema)
+            .unwrap_err();
+        let context = rebase_context(error);
+        assert_eq!(context.parent_type, "I");
+        assert_eq!(context.parent_runtime_types, ["U", "V"]);
+        assert_eq!(context.attempted_type.as_deref(), Some("T"));
+        assert_eq!(context.attempted_runtime_types, ["T"]);
+        assert_eq!(context.subgraph_name, None);
+    }
+
+    #[test]
+    fn looks_selections_up_by_borrowed_keys() {
+        let schema_and_operation = r#"
+query KeyQuery($include: Boolean!) {
+  t {
+    a: id @include(if: $include)
+    id
+    ... on T @skip(if: $include) {
+      id
+    }
+    ...TFragment
+  }
+}
+
+fragment TFragment on T {
+  id
+}
+
+type Query {
+  t: T
+}
+
+type T {
+  id: ID!
+}
+"#;
+        let (schema, executable_document) = parse_schema_and_operation(schema_and_operation);
+        let operation = executable_document.get_operation(None).unwrap();
+        let selection_set = NormalizedSelectionSet::normalize_and_expand_fragments(
+            &operation.selection_set,
+            &executable_document.fragments,
+            &schema,
+            FragmentSpreadNormalizationOption::PreserveFragmentSpread,
+            &Default::default(),
+        )
+        .unwrap();
+        let t_selection_set = selection_set
+            .selections
+            .values()
+            .next()
+            .and_then(|selection| selection.selection_set().ok().flatten())
+            .unwrap();
+        assert_eq!(t_selection_set.selections.len(), 4);
+        let hash = |value: &dyn Fn(&mut DefaultHasher)| {
+            let mut hasher = DefaultHasher::new();
+            value(&mut hasher);
+            hasher.finish()
+        };
+        for (key, selection) in t_selection_set.selections.iter() {
+            let key_ref = selection.key_ref();
+            assert_eq!(
+                hash(&|hasher| key.hash(hasher)),
+                hash(&|hasher| key_ref.hash(hasher))
+            );
+            assert_eq!(t_selection_set.selections.get(&key_ref), Some(selection));
+        }
+    }
+
+    #[test]
+    fn assigns_reproducible_selection_ids() {
+        let schema_and_operation = r#"
+query DeferQuery {
+  t {
+    ... @defer {
+      id
+    }
+    ... @defer {
+      id
+    }
+  }
+}
+
+directive @defer(label: String, if: Boolean! = true) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+type Query {
+  t: T
+}
+
+type T {
+  id: ID!
+}
+"#;
+        let (schema, executable_document) = parse_schema_and_operation(schema_and_operation);
+        let operation = executable_document.get_operation(None).unwrap();
+        let normalize = || {
+            normalize_operation(
+                operation,
+                &executable_document.fragments,
+                &schema,
+                &IndexSet::new(),
+            )
+            .unwrap()
+        };
+        // Deferred fragments are keyed by their selection ID, so both are kept, and normalizing
+        // again (after other operations got normalized) gives the same IDs.
+        let normalized_operation = normalize();
+        let Some(NormalizedSelection::Field(t)) = normalized_operation
+            .selection_set
+            .selections
+            .values()
+            .next()
+        else {
+            panic!("Expected a field selection");
+        };
+        assert_eq!(t.selection_set.as_ref().unwrap().selections.len(), 2);
+        assert_eq!(normalize(), normalized_operation);
+    }
+
+    #[test]
+    fn converts_to_executable_document() {
+        let schema_and_operation = r#"
+query NamedFragmentQuery($skip: Boolean!) {
+  t {
+    ...TFields
+  }
+}
+
+fragment TFields on T {
+  id
+  ...TName @skip(if: $skip)
+}
+
+query OtherQuery {
+  t {
+    ...OtherFields
+  }
+}
+
+fragment OtherFields on T {
+  id
+}
+
+fragment TName on T {
+  name
+}
+
+type Query {
+  t: T
+}
+
+type T {
+  id: ID!
+  name: String
+}
+"#;
+        let (schema, executable_document) = parse_schema_and_operation(schema_and_operation);
+        let executable_document = executable_document.validate(schema.schema()).unwrap();
+        let mut normalized_operation = NormalizedOperation::from_document(
+            &executable_document,
+            Some("NamedFragmentQuery"),
+            &schema,
+        )
+        .unwrap();
+
+        // Normalization expands the fragments, so none of them is emitted.
+        let document = normalized_operation
+            .to_executable_document(&schema)
+            .unwrap();
+        assert!(document.fragments.is_empty());
+        insta::assert_snapshot!(document, @r###"
+        query NamedFragmentQuery($skip: Boolean!) {
+          t {
+            id
+            ... on T @skip(if: $skip) {
+              name
+            }
+          }
+        }
+        "###);
+
+        // Fragments spread by the selection set (directly or not) are emitted in document order,
+        // and those only used by other operations are dropped.
+        let operation = executable_document
+            .get_operation(Some("NamedFragmentQuery"))
+            .unwrap();
+        normalized_operation.selection_set =
+            NormalizedSelectionSet::normalize_and_expand_fragments(
+                &operation.selection_set,
+                &executable_document.fragments,
+                &schema,
+                FragmentSpreadNormalizationOption::PreserveFragmentSpread,
+                &Default::default(),
+            )
+            .unwrap();
+        let document = normalized_operation
+            .to_executable_document(&schema)
+            .unwrap();
+        assert_eq!(
+            document.fragments.keys().collect::<Vec<_>>(),
+            ["TFields", "TName"]
+        );
+        insta::assert_snapshot!(document, @r###"
+        query NamedFragmentQuery($skip: Boolean!) {
+          t {
+            ...TFields
+          }
+        }
+
+        fragment TFields on T {
+          id
+          ...TName @skip(if: $skip)
+        }
+
+        fragment TName on T {
+          name
+        }
+        "###);
+    }
 }
@@ -0,0 +1,167 @@
+//! A printer of normalized selection sets for debugging normalization and optimization passes: on
+//! top of the selections themselves, each line is annotated with the schema position of the
+//! selection, its computed `Conditions`, and the sibling `__typename` it carries, e.g.
+//! ```text
+//! { # Query
+//!   me { # Query.me, conditions: true
+//!     id # User.id, conditions: true, sibling typename: __typename
+//!     ... on User @include(if: $withName) { # User, conditions: $withName
+//!       name # User.name, conditions: true
+//!     }
+//!   }
+//! }
+//! ```
+//! This format isn't stable, and is only available with the `debug_display` feature.
+use crate::query_plan::conditions::Conditions;
+use crate::query_plan::operation::{
+    NormalizedOperation, NormalizedSelection, NormalizedSelectionSet,
+};
+use std::fmt::Write;
+
+const INDENT: &str = "  ";
+
+impl NormalizedOperation {
+    /// Prints the selection set of this operation for debugging (see
+    /// `NormalizedSelectionSet::to_debug_string()`).
+    pub fn to_debug_string(&self) -> String {
+        self.selection_set.to_debug_string()
+    }
+}
+
+impl NormalizedSelectionSet {
+    /// Prints this selection set with each selection annotated with its schema position, its
+    /// computed conditions and its sibling `__typename` (if any), for debugging.
+    pub fn to_debug_string(&self) -> String {
+        let mut output = String::new();
+        write!(output, "{{ # {}", self.type_position).unwrap();
+        self.write_debug(&mut output, "");
+        output
+    }
+
+    /// Writes the selections of this selection set and its closing brace, assuming its opening
+    /// line (at `indentation`) was already written.
+    fn write_debug(&self, output: &mut String, indentation: &str) {
+        let selection_indentation = format!("{indentation}{INDENT}");
+        for selection in self.selections.values() {
+            output.push('\n');
+            output.push_str(&selection_indentation);
+            selection.write_debug(output, &selection_indentation);
+        }
+        write!(output, "\n{indentation}}}").unwrap();
+    }
+}
+
+impl NormalizedSelection {
+    fn write_debug(&self, output: &mut String, indentation: &str) {
+        let conditions = match self.conditions() {
+            Ok(conditions) => print_conditions(&conditions),
+            Err(error) => format!("<error: {error}>"),
+        };
+        match self {
+            NormalizedSelection::Field(field_selection) => {
+                let data = field_selection.field.data();
+                write!(output, "{}", field_selection.field).unwrap();
+                if field_selection.selection_set.is_some() {
+                    output.push_str(" {");
+                }
+                write!(
+                    output,
+                    " # {}, conditions: {conditions}",
+                    data.field_position
+                )
+                .unwrap();
+                if let Some(sibling_typename) = &data.sibling_typename {
+                    write!(output, ", sibling typename: {sibling_typename}").unwrap();
+                }
+                if let Some(selection_set) = &field_selection.selection_set {
+                    selection_set.write_debug(output, indentation);
+                }
+            }
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                let selection_set = &inline_fragment_selection.selection_set;
+                write!(
+                    output,
+                    "{} {{ # {}, conditions: {conditions}",
+                    inline_fragment_selection.inline_fragment, selection_set.type_position,
+                )
+                .unwrap();
+                selection_set.write_debug(output, indentation);
+            }
+            NormalizedSelection::FragmentSpread(fragment_spread_selection) => {
+                write!(
+                    output,
+                    "{fragment_spread_selection} # {}, conditions: {conditions}",
+                    fragment_spread_selection.data().type_condition_position(),
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+/// Prints conditions as a boolean, or as a conjunction of (possibly negated) variables, e.g.
+/// `$a && !$b`.
+fn print_conditions(conditions: &Conditions) -> String {
+    match conditions {
+        Conditions::Boolean(value) => value.to_string(),
+        Conditions::Variables(variables) => variables
+            .iter()
+            .map(|(name, negated)| format!("{}${name}", if negated { "!" } else { "" }))
+            .collect::<Vec<_>>()
+            .join(" && "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::operation::NormalizedOperation;
+    use crate::schema::ValidFederationSchema;
+    use apollo_compiler::{ExecutableDocument, Schema};
+
+    #[test]
+    fn prints_selections_with_their_conditions() {
+        let schema = Schema::parse_and_validate(
+            r#"
+            type Query { me: User }
+            type User { id: ID! name: String friends: [User] }
+            "#,
+            "schema.graphql",
+        )
+        .unwrap();
+        let schema = ValidFederationSchema::new(schema).unwrap();
+        let document = ExecutableDocument::parse_and_validate(
+            schema.schema(),
+            r#"
+            query ($withName: Boolean!, $skipFriends: Boolean!) {
+              me {
+                __typename
+                id
+                ... on User @include(if: $withName) {
+                  name
+                }
+                friends @skip(if: $skipFriends) {
+                  id
+                }
+              }
+            }
+            "#,
+            "operation.graphql",
+        )
+        .unwrap();
+        let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        assert_eq!(
+            operation.to_debug_string(),
+            r#"{ # Query
+  me { # Query.me, conditions: true
+    id # User.id, conditions: true, sibling typename: __typename
+    ... on User @include(if: $withName) { # User, conditions: $withName
+      name # User.name, conditions: true
+    }
+    friends @skip(if: $skipFriends) { # User.friends, conditions: !$skipFriends
+      id # User.id, conditions: true
+    }
+  }
+}"#
+        );
+    }
+}
@@ -0,0 +1,418 @@
+use crate::error::FederationError;
+use crate::query_graph::graph_path::{OpPath, OpPathElement};
+use crate::query_plan::fetch_dependency_graph::field_response_path;
+use crate::query_plan::operation::normalized_field_selection::NormalizedFieldSelection;
+use crate::query_plan::operation::normalized_inline_fragment_selection::{
+    NormalizedInlineFragment, NormalizedInlineFragmentSelection,
+};
+use crate::query_plan::operation::{
+    NormalizedOperation, NormalizedSelection, NormalizedSelectionSet,
+};
+use crate::query_plan::{FetchDataPathElement, QueryPathElement};
+use apollo_compiler::ast::DirectiveList;
+use apollo_compiler::NodeStr;
+use indexmap::IndexSet;
+use std::sync::Arc;
+
+/// A normalized operation split along its `@defer` applications.
+#[derive(Debug)]
+pub(crate) struct DeferSplit {
+    /// The non-deferred part of the operation, i.e. its selection set without the deferred
+    /// fragments.
+    pub(crate) primary: NormalizedSelectionSet,
+    /// The deferred parts of the operation, in the order of their `@defer` applications (so a
+    /// deferred part always comes after the part it is nested in).
+    pub(crate) deferred: Vec<DeferredPart>,
+}
+
+/// The part of an operation deferred by one of its `@defer` applications.
+#[derive(Debug)]
+pub(crate) struct DeferredPart {
+    /// The reference of this part within the operation, i.e. the label of its `@defer`, or a
+    /// generated `qp__<n>` one (distinct from the operation's labels) if it has none.
+    pub(crate) defer_ref: NodeStr,
+    /// The label of the `@defer`, if any.
+    pub(crate) label: Option<NodeStr>,
+    /// The reference of the deferred part this one is nested in, or `None` if it is nested in the
+    /// primary part.
+    pub(crate) parent_defer_ref: Option<NodeStr>,
+    /// The path from the root of the parent part to the selection set of the deferred fragment.
+    /// For a part nested in the primary part, this is the path from the operation root; for a part
+    /// nested in another deferred part, it starts with the (non-deferred) fragment of that part.
+    pub(crate) path_from_parent: OpPath,
+    /// The path from the operation root to the selection set of the deferred fragment.
+    pub(crate) path: OpPath,
+    /// The path in response data at which the deferred data is delivered.
+    pub(crate) response_path: Vec<FetchDataPathElement>,
+    /// The deferred fragment without its `@defer` (nor its nested deferred fragments), as a
+    /// selection set of the type the fragment applies to.
+    pub(crate) sub_selection: NormalizedSelectionSet,
+}
+
+impl DeferredPart {
+    /// The path of this part in the query, as used by `DeferredDeferBlock::query_path`.
+    pub(crate) fn query_path(&self) -> Result<Vec<QueryPathElement>, FederationError> {
+        self.path
+            .iter()
+            .map(|element| match element.as_ref() {
+                OpPathElement::Field(field) => Ok(QueryPathElement::Field(
+                    (&NormalizedFieldSelection {
+                        field: field.clone(),
+                        selection_set: None,
+                    })
+                        .try_into()?,
+                )),
+                OpPathElement::InlineFragment(inline_fragment) => {
+                    Ok(QueryPathElement::InlineFragment(
+                        (&NormalizedInlineFragmentSelection {
+                            inline_fragment: inline_fragment.clone(),
+                            selection_set: NormalizedSelectionSet::empty(
+                                inline_fragment.data().schema.clone(),
+                                inline_fragment
+                                    .data()
+                                    .type_condition_position
+                                    .clone()
+                                    .unwrap_or_else(|| {
+                                        inline_fragment.data().parent_type_position.clone()
+                                    }),
+                            ),
+                        })
+                            .try_into()?,
+                    ))
+                }
+            })
+            .collect()
+    }
+}
+
+impl NormalizedOperation {
+    /// Splits this operation into its primary (non-deferred) part and one part per deferred
+    /// fragment, recording where each deferred part attaches to the part it is nested in. The
+    /// selections deferred by a `@defer` with a variable `if` argument are split out as well (the
+    /// planner makes the primary part include them when the condition is false).
+    ///
+    /// Fields of the primary part whose sub-selections are all deferred select `__typename`
+    /// instead, so that they stay valid.
+    // PORT_NOTE: In the JS codebase, this split happens while computing the fetch groups, with the
+    // `DeferContext` of each path tracking the defer it belongs to. Doing it upfront on the
+    // operation keeps that tracking out of the (not yet ported) traversal.
+    pub(crate) fn split_by_defer(&self) -> Result<DeferSplit, FederationError> {
+        let mut labels = IndexSet::new();
+        collect_defer_labels(&self.selection_set, &mut labels)?;
+        let mut splitter = DeferSplitter {
+            labels,
+            next_generated_label: 0,
+            deferred: vec![],
+        };
+        let primary = splitter.split(
+            &self.selection_set,
+            &DeferPosition {
+                defer_ref: None,
+                path_from_parent: OpPath::default(),
+                path: OpPath::default(),
+                response_path: vec![],
+            },
+        )?;
+        Ok(DeferSplit {
+            primary,
+            deferred: splitter.deferred,
+        })
+    }
+}
+
+/// Where a selection set is within the split operation.
+struct DeferPosition {
+    defer_ref: Option<NodeStr>,
+    path_from_parent: OpPath,
+    path: OpPath,
+    response_path: Vec<FetchDataPathElement>,
+}
+
+impl DeferPosition {
+    fn with_pushed(&self, element: OpPathElement, response_path: &[FetchDataPathElement]) -> Self {
+        let element = Arc::new(element);
+        Self {
+            defer_ref: self.defer_ref.clone(),
+            path_from_parent: self.path_from_parent.with_pushed(element.clone()),
+            path: self.path.with_pushed(element),
+            response_path: [self.response_path.as_slice(), response_path].concat(),
+        }
+    }
+}
+
+struct DeferSplitter {
+    /// The labels of the operation's `@defer`s, including the generated ones so far.
+    labels: IndexSet<NodeStr>,
+    next_generated_label: usize,
+    deferred: Vec<DeferredPart>,
+}
+
+impl DeferSplitter {
+    /// Returns the part of the given selection set that isn't deferred (relatively to the part at
+    /// the given position), adding its deferred parts to `self.deferred`.
+    fn split(
+        &mut self,
+        selection_set: &NormalizedSelectionSet,
+        position: &DeferPosition,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        let mut non_deferred = NormalizedSelectionSet::empty(
+            selection_set.schema.clone(),
+            selection_set.type_position.clone(),
+        );
+        for selection in selection_set.selections.values() {
+            let selection = match selection {
+                NormalizedSelection::Field(field_selection) => {
+                    let Some(sub_selection_set) = &field_selection.selection_set else {
+                        non_deferred.merge_selections_into(std::iter::once(selection.clone()))?;
+                        continue;
+                    };
+                    let response_path = field_response_path(&field_selection.field)?;
+                    let mut sub_selection_set = self.split(
+                        sub_selection_set,
+                        &position.with_pushed(
+                            OpPathElement::Field(field_selection.field.clone()),
+                            &response_path,
+                        ),
+                    )?;
+                    if sub_selection_set.selections.is_empty() {
+                        sub_selection_set.add_typename_field()?;
+                    }
+                    NormalizedSelection::Field(Arc::new(NormalizedFieldSelection {
+                        field: field_selection.field.clone(),
+                        selection_set: Some(sub_selection_set),
+                    }))
+                }
+                NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                    let inline_fragment = &inline_fragment_selection.inline_fragment;
+                    if let Some(arguments) = inline_fragment.data().defer_directive_arguments()? {
+                        let label = arguments.label().cloned();
+                        let defer_ref = match &label {
+                            Some(label) => label.clone(),
+                            None => self.generate_label(),
+                        };
+                        self.split_deferred(inline_fragment_selection, defer_ref, label, position)?;
+                        continue;
+                    }
+                    let sub_selection_set = self.split(
+                        &inline_fragment_selection.selection_set,
+                        &position.with_pushed(
+                            OpPathElement::InlineFragment(inline_fragment.clone()),
+                            &[],
+                        ),
+                    )?;
+                    if sub_selection_set.selections.is_empty() {
+                        continue;
+                    }
+                    NormalizedSelection::InlineFragment(Arc::new(
+                        NormalizedInlineFragmentSelection {
+                            inline_fragment: inline_fragment.clone(),
+                            selection_set: sub_selection_set,
+                        },
+                    ))
+                }
+                NormalizedSelection::FragmentSpread(_) => {
+                    return Err(FederationError::internal(
+                        "Unexpected fragment spread in NormalizedOperation::split_by_defer()",
+                    ))
+                }
+            };
+            non_deferred.merge_selections_into(std::iter::once(selection))?;
+        }
+        Ok(non_deferred)
+    }
+
+    /// Adds the part deferred by the given fragment (and then those nested in it) to
+    /// `self.deferred`.
+    fn split_deferred(
+        &mut self,
+        inline_fragment_selection: &NormalizedInlineFragmentSelection,
+        defer_ref: NodeStr,
+        label: Option<NodeStr>,
+        position: &DeferPosition,
+    ) -> Result<(), FederationError> {
+        let mut data = inline_fragment_selection.inline_fragment.data().clone();
+        data.directives = Arc::new(DirectiveList(
+            data.directives
+                .iter()
+                .filter(|directive| directive.name != "defer")
+                .cloned()
+                .collect(),
+        ));
+        let inline_fragment = NormalizedInlineFragment::new(data);
+        let element = Arc::new(OpPathElement::InlineFragment(inline_fragment.clone()));
+        let index = self.deferred.len();
+        let fragment_selection_set = self.split(
+            &inline_fragment_selection.selection_set,
+            &DeferPosition {
+                defer_ref: Some(defer_ref.clone()),
+                path_from_parent: OpPath::default().with_pushed(element.clone()),
+                path: position.path.with_pushed(element),
+                response_path: position.response_path.clone(),
+            },
+        )?;
+        let mut sub_selection = NormalizedSelectionSet::empty(
+            inline_fragment_selection.selection_set.schema.clone(),
+            inline_fragment.data().parent_type_position.clone(),
+        );
+        sub_selection.merge_selections_into(std::iter::once(
+            NormalizedSelection::InlineFragment(Arc::new(NormalizedInlineFragmentSelection {
+                inline_fragment,
+                selection_set: fragment_selection_set,
+            })),
+        ))?;
+        // The nested parts were added while splitting the fragment, but must come after this one.
+        self.deferred.insert(
+            index,
+            DeferredPart {
+                defer_ref,
+                label,
+                parent_defer_ref: position.defer_ref.clone(),
+                path_from_parent: position.path_from_parent.clone(),
+                path: position.path.clone(),
+                response_path: position.response_path.clone(),
+                sub_selection,
+            },
+        );
+        Ok(())
+    }
+
+    fn generate_label(&mut self) -> NodeStr {
+        loop {
+            let label = NodeStr::new(&format!("qp__{}", self.next_generated_label));
+            self.next_generated_label += 1;
+            if self.labels.insert(label.clone()) {
+                return label;
+            }
+        }
+    }
+}
+
+/// Collects the labels of the `@defer` applications of the given selection set.
+fn collect_defer_labels(
+    selection_set: &NormalizedSelectionSet,
+    labels: &mut IndexSet<NodeStr>,
+) -> Result<(), FederationError> {
+    for selection in selection_set.selections.values() {
+        match selection {
+            NormalizedSelection::Field(field_selection) => {
+                if let Some(selection_set) = &field_selection.selection_set {
+                    collect_defer_labels(selection_set, labels)?;
+                }
+            }
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                if let Some(arguments) = inline_fragment_selection
+                    .inline_fragment
+                    .data()
+                    .defer_directive_arguments()?
+                {
+                    labels.extend(arguments.label().cloned());
+                }
+                collect_defer_labels(&inline_fragment_selection.selection_set, labels)?;
+            }
+            NormalizedSelection::FragmentSpread(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::operation::tests::parse_schema_and_operation;
+    use crate::query_plan::operation::NormalizedOperation;
+    use crate::query_plan::FetchDataPathElement;
+    use apollo_compiler::NodeStr;
+
+    #[test]
+    fn splits_operation_by_defer() {
+        let operation = r#"
+directive @defer(label: String, if: Boolean! = true) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+type Query {
+  t: T
+}
+
+type T {
+  id: ID!
+  v1: Int
+  v2: Int
+  u: [U]
+}
+
+type U {
+  x: Int
+  y: Int
+}
+
+query {
+  t {
+    id
+    ... @defer(label: "outer") {
+      v1
+      u {
+        x
+        ... @defer {
+          y
+        }
+      }
+    }
+  }
+  ... @defer {
+    t {
+      v2
+    }
+  }
+}
+"#;
+        let (schema, document) = parse_schema_and_operation(operation);
+        let document = document.validate(schema.schema()).unwrap();
+        let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        let split = operation.split_by_defer().unwrap();
+
+        assert_eq!(split.primary.to_string(), "{ t { id } }");
+        let parts = split
+            .deferred
+            .iter()
+            .map(|part| {
+                (
+                    part.defer_ref.to_string(),
+                    part.parent_defer_ref.as_ref().map(|r| r.to_string()),
+                    part.path_from_parent.to_string(),
+                    part.sub_selection.to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parts,
+            vec![
+                (
+                    "outer".to_owned(),
+                    None,
+                    "t".to_owned(),
+                    "{ ... { v1 u { x } } }".to_owned(),
+                ),
+                (
+                    "qp__0".to_owned(),
+                    Some("outer".to_owned()),
+                    "...::u".to_owned(),
+                    "{ ... { y } }".to_owned(),
+                ),
+                (
+                    "qp__1".to_owned(),
+                    None,
+                    "".to_owned(),
+                    "{ ... { t { v2 } } }".to_owned(),
+                ),
+            ]
+        );
+        assert_eq!(split.deferred[1].label, None);
+        assert_eq!(
+            split.deferred[1].response_path,
+            vec![
+                FetchDataPathElement::Key(NodeStr::new("t")),
+                FetchDataPathElement::Key(NodeStr::new("u")),
+                FetchDataPathElement::AnyIndex,
+            ]
+        );
+        assert_eq!(split.deferred[1].query_path().unwrap().len(), 3);
+    }
+}
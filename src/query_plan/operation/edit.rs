@@ -0,0 +1,176 @@
+//! Experimental support for updating a plan incrementally as its operation is edited, e.g. for IDE
+//! integrations that show a live preview of the plan of the operation being typed.
+//!
+//! Small edits usually only touch a few root fields of a query, and the plan of a query root field
+//! doesn't depend on its sibling root fields (beyond which fetches end up merged). So rather than
+//! replanning the whole operation, only the root fields affected by the edit need to be planned,
+//! and the resulting plan can be merged with the previous one.
+// TODO: Once query planning is ported, add a `QueryPlanner` method that plans the affected
+// operation and merges its fetches with the unaffected ones of the previous plan.
+use crate::query_plan::operation::{NormalizedOperation, NormalizedSelection};
+use apollo_compiler::ast::Name;
+use indexmap::IndexMap;
+use std::sync::Arc;
+
+/// How an operation differs from a previous version of it, in terms of its root fields (identified
+/// by their response name).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationEdit {
+    /// The root fields only found in the edited operation.
+    pub added: Vec<Name>,
+    /// The root fields only found in the previous operation.
+    pub removed: Vec<Name>,
+    /// The root fields found in both operations, but whose selection differs.
+    pub changed: Vec<Name>,
+    /// The root fields whose selection is the same in both operations.
+    pub unchanged: Vec<Name>,
+}
+
+impl OperationEdit {
+    /// Computes the edit from the previous operation to the edited one. Returns `None` if the
+    /// operations can't be compared root field by root field, in which case the edited operation
+    /// must be fully replanned: this happens when the operation types or directives differ, or when
+    /// either operation has a root selection that isn't a field (e.g. a deferred fragment).
+    ///
+    /// Note variable definitions aren't compared, since variables only affect the fields using
+    /// them, which are already reported as changed.
+    pub fn between(previous: &NormalizedOperation, edited: &NormalizedOperation) -> Option<Self> {
+        if previous.root_kind != edited.root_kind || previous.directives != edited.directives {
+            return None;
+        }
+        let previous_root_fields = root_fields(previous)?;
+        let edited_root_fields = root_fields(edited)?;
+        let mut edit = OperationEdit::default();
+        for (response_name, printed) in &edited_root_fields {
+            match previous_root_fields.get(response_name) {
+                None => edit.added.push(response_name.clone()),
+                Some(previous_printed) if previous_printed != printed => {
+                    edit.changed.push(response_name.clone())
+                }
+                Some(_) => edit.unchanged.push(response_name.clone()),
+            }
+        }
+        edit.removed = previous_root_fields
+            .keys()
+            .filter(|response_name| !edited_root_fields.contains_key(*response_name))
+            .cloned()
+            .collect();
+        Some(edit)
+    }
+
+    /// Whether the edit leaves the operation's root fields as they were.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// The root fields of the edited operation that need to be planned, i.e. the added and changed
+    /// ones.
+    pub fn affected_root_fields(&self) -> impl Iterator<Item = &Name> {
+        self.added.iter().chain(self.changed.iter())
+    }
+
+    /// The part of the edited operation that needs to be planned, i.e. the edited operation
+    /// restricted to its affected root fields (see `affected_root_fields()`).
+    pub fn affected_operation(&self, edited: &NormalizedOperation) -> NormalizedOperation {
+        let affected = self.affected_root_fields().collect::<Vec<_>>();
+        edited.with_root_fields(|response_name| affected.contains(&response_name))
+    }
+}
+
+impl NormalizedOperation {
+    /// A copy of this operation only keeping the root fields whose response name matches the
+    /// given predicate.
+    pub fn with_root_fields(&self, mut predicate: impl FnMut(&Name) -> bool) -> Self {
+        let mut selection_set = self.selection_set.clone();
+        let selections = Arc::make_mut(&mut selection_set.selections);
+        let removed_keys = selections
+            .iter()
+            .filter(|(_, selection)| match selection {
+                NormalizedSelection::Field(field) => {
+                    !predicate(&field.field.data().response_name())
+                }
+                _ => false,
+            })
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in removed_keys {
+            selections.remove(&key);
+        }
+        NormalizedOperation {
+            selection_set,
+            ..self.clone()
+        }
+    }
+}
+
+/// The root fields of the given operation by response name, along with their printed selection,
+/// or `None` if the operation has a root selection that isn't a field.
+fn root_fields(operation: &NormalizedOperation) -> Option<IndexMap<Name, String>> {
+    operation
+        .selection_set
+        .selections
+        .values()
+        .map(|selection| match selection {
+            NormalizedSelection::Field(field) => {
+                Some((field.field.data().response_name(), selection.to_string()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::operation::edit::OperationEdit;
+    use crate::query_plan::operation::NormalizedOperation;
+    use crate::schema::ValidFederationSchema;
+    use apollo_compiler::{name, ExecutableDocument, Schema};
+
+    fn normalize(schema: &ValidFederationSchema, operation: &str) -> NormalizedOperation {
+        let document =
+            ExecutableDocument::parse_and_validate(schema.schema(), operation, "operation.graphql")
+                .unwrap();
+        NormalizedOperation::from_document(&document, None, schema).unwrap()
+    }
+
+    #[test]
+    fn computes_operation_edits() {
+        let schema = Schema::parse_and_validate(
+            r#"
+            type Query { me: User users: [User] count: Int }
+            type Mutation { count: Int }
+            type User { id: ID! name: String }
+            "#,
+            "schema.graphql",
+        )
+        .unwrap();
+        let schema = ValidFederationSchema::new(schema).unwrap();
+        let previous = normalize(&schema, "{ me { id } users { id } count }");
+        let edited = normalize(
+            &schema,
+            "{ me { id name } users { id } others: users { name } }",
+        );
+
+        let edit = OperationEdit::between(&previous, &edited).unwrap();
+        assert_eq!(edit.added, [name!("others")]);
+        assert_eq!(edit.removed, [name!("count")]);
+        assert_eq!(edit.changed, [name!("me")]);
+        assert_eq!(edit.unchanged, [name!("users")]);
+        assert!(!edit.is_empty());
+        insta::assert_snapshot!(edit.affected_operation(&edited), @r###"
+        {
+          me {
+            id
+            name
+          }
+          others: users {
+            name
+          }
+        }
+        "###);
+
+        assert!(OperationEdit::between(&edited, &edited).unwrap().is_empty());
+        let mutation = normalize(&schema, "mutation { count }");
+        assert!(OperationEdit::between(&previous, &mutation).is_none());
+    }
+}
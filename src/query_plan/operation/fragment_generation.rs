@@ -0,0 +1,277 @@
+use crate::error::FederationError;
+use crate::query_plan::operation::normalized_fragment_spread_selection::{
+    NormalizedFragmentSpreadData, NormalizedFragmentSpreadSelection,
+};
+use crate::query_plan::operation::{
+    IdGenerator, NormalizedFragment, NormalizedOperation, NormalizedSelection,
+    NormalizedSelectionSet, SelectionMapperReturn,
+};
+use crate::schema::position::CompositeTypeDefinitionPosition;
+use apollo_compiler::ast::Name;
+use apollo_compiler::Node;
+use indexmap::IndexMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Factors the inline fragments that selection sets repeat into generated named fragments, so
+/// that each repetition is a spread of the same fragment. This can significantly reduce the size
+/// of subgraph fetches, e.g. for operations selecting the same fields on the many implementations
+/// of an interface at several places.
+///
+/// Only the inline fragments with a type condition and no directives are factored, and only when
+/// their selection set is worth it (see `is_worth_factoring()`). A generator can be used for many
+/// selection sets of the same document, so that they share their generated fragments.
+// PORT_NOTE: This is `SelectionSet.minimizeSelectionSet()` in the JS codebase. The JS codebase
+// keyed its candidates by a string made of the type condition and the number of selections, which
+// is a tuple here.
+pub(crate) struct QueryFragmentGenerator {
+    /// The named fragments of the document, starting with the existing ones, if any (whose names
+    /// generated fragments must avoid).
+    fragments: IndexMap<Name, Node<NormalizedFragment>>,
+    /// The names of the generated fragments, by type condition and number of selections, which is
+    /// a cheap way to narrow down the fragments whose selection set may match a new one.
+    candidates: IndexMap<(Name, usize), Vec<Name>>,
+    /// The spread of each generated fragment. Spreads of a fragment are all the same selection, so
+    /// that selection sets spreading the same fragments compare equal.
+    spreads: IndexMap<Name, NormalizedSelection>,
+    id_generator: Arc<IdGenerator>,
+}
+
+impl QueryFragmentGenerator {
+    pub(crate) fn new(
+        fragments: IndexMap<Name, Node<NormalizedFragment>>,
+        id_generator: Arc<IdGenerator>,
+    ) -> Self {
+        Self {
+            fragments,
+            candidates: Default::default(),
+            spreads: Default::default(),
+            id_generator,
+        }
+    }
+
+    /// The named fragments of the document, including those generated so far.
+    pub(crate) fn into_fragments(self) -> IndexMap<Name, Node<NormalizedFragment>> {
+        self.fragments
+    }
+
+    /// The given selection set with its inline fragments (recursively) replaced by spreads of
+    /// generated fragments, reusing the fragments already generated for equal selection sets.
+    /// Fragment spreads are kept as is.
+    pub(crate) fn minimize(
+        &mut self,
+        selection_set: &NormalizedSelectionSet,
+    ) -> Result<NormalizedSelectionSet, FederationError> {
+        selection_set.lazy_map(|selection| {
+            let Some(sub_selection_set) = selection.selection_set()? else {
+                return Ok(SelectionMapperReturn::Selection(selection.clone()));
+            };
+            // Sub-selection sets are minimized first, so nested fragments are generated before
+            // the fragments using them.
+            let minimized = self.minimize(sub_selection_set)?;
+            let NormalizedSelection::InlineFragment(inline_fragment_selection) = selection else {
+                return Ok(SelectionMapperReturn::Selection(
+                    selection.with_updated_selection_set(minimized)?,
+                ));
+            };
+            let data = inline_fragment_selection.inline_fragment.data();
+            match &data.type_condition_position {
+                Some(type_condition_position)
+                    if data.directives.is_empty() && is_worth_factoring(&minimized) =>
+                {
+                    Ok(SelectionMapperReturn::Selection(
+                        self.spread_for(type_condition_position, minimized)?,
+                    ))
+                }
+                _ => Ok(SelectionMapperReturn::Selection(
+                    selection.with_updated_selection_set(minimized)?,
+                )),
+            }
+        })
+    }
+
+    /// A spread of the generated fragment with the given type condition and selection set,
+    /// generating it if there's none yet. Fragments are named `_generated_on<type><count>_<index>`,
+    /// where `count` is the number of selections of the fragment and `index` the number of named
+    /// fragments before it.
+    fn spread_for(
+        &mut self,
+        type_condition_position: &CompositeTypeDefinitionPosition,
+        selection_set: NormalizedSelectionSet,
+    ) -> Result<NormalizedSelection, FederationError> {
+        let type_name = type_condition_position.type_name();
+        let candidates = self
+            .candidates
+            .entry((type_name.clone(), selection_set.selections.len()))
+            .or_default();
+        if let Some(name) = candidates
+            .iter()
+            .find(|name| self.fragments[*name].selection_set == selection_set)
+        {
+            return Ok(self.spreads[name].clone());
+        }
+        let mut index = self.fragments.len();
+        let name = loop {
+            let name = Name::new(format!(
+                "_generated_on{}{}_{}",
+                type_name,
+                selection_set.selections.len(),
+                index
+            ))?;
+            if !self.fragments.contains_key(&name) {
+                break name;
+            }
+            index += 1;
+        };
+        candidates.push(name.clone());
+        let spread = NormalizedSelection::FragmentSpread(Arc::new(
+            NormalizedFragmentSpreadSelection::new(NormalizedFragmentSpreadData::new(
+                selection_set.schema.clone(),
+                name.clone(),
+                type_condition_position.clone(),
+                Default::default(),
+                Default::default(),
+                &self.id_generator,
+            )),
+        ));
+        self.spreads.insert(name.clone(), spread.clone());
+        self.fragments.insert(
+            name.clone(),
+            Node::new(NormalizedFragment {
+                schema: selection_set.schema.clone(),
+                name: name.clone(),
+                type_condition_position: type_condition_position.clone(),
+                directives: Default::default(),
+                selection_set,
+            }),
+        );
+        Ok(spread)
+    }
+}
+
+/// Whether replacing an inline fragment with the given selection set by a fragment spread is worth
+/// it, i.e. whether it selects more than a single leaf field (the spread being about as large as
+/// such an inline fragment).
+fn is_worth_factoring(selection_set: &NormalizedSelectionSet) -> bool {
+    let mut selections = selection_set.selections.values();
+    match (selections.next(), selections.next()) {
+        (None, _) => false,
+        (Some(NormalizedSelection::Field(field_selection)), None) => {
+            field_selection.selection_set.is_some()
+        }
+        _ => true,
+    }
+}
+
+impl NormalizedOperation {
+    /// This operation with its repeated inline fragments factored into generated named fragments
+    /// (see `QueryFragmentGenerator`), which are added to the named fragments of the operation.
+    pub(crate) fn generate_query_fragments(&self) -> Result<NormalizedOperation, FederationError> {
+        let mut generator =
+            QueryFragmentGenerator::new(self.fragments.deref().clone(), self.id_generator.clone());
+        let selection_set = generator.minimize(&self.selection_set)?;
+        Ok(NormalizedOperation {
+            selection_set,
+            fragments: Arc::new(generator.into_fragments()),
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::operation::tests::parse_schema_and_operation;
+    use crate::query_plan::operation::NormalizedOperation;
+
+    #[test]
+    fn generates_query_fragments() {
+        let schema_and_operation = r#"
+query Q($v: Boolean!) {
+  i {
+    ... on A {
+      id
+      b {
+        ... on B {
+          x
+          y
+        }
+      }
+    }
+    ... on B {
+      x
+    }
+  }
+  other: i {
+    ... on A {
+      b {
+        ... on B {
+          y
+          x
+        }
+      }
+      id
+    }
+    ... on B @include(if: $v) {
+      x
+      y
+    }
+  }
+}
+
+type Query {
+  i: I
+}
+
+interface I {
+  id: ID!
+}
+
+type A implements I {
+  id: ID!
+  b: I
+}
+
+type B implements I {
+  id: ID!
+  x: Int
+  y: Int
+}
+"#;
+        let (schema, executable_document) = parse_schema_and_operation(schema_and_operation);
+        let executable_document = executable_document.validate(schema.schema()).unwrap();
+        let operation =
+            NormalizedOperation::from_document(&executable_document, None, &schema).unwrap();
+        let operation = operation.generate_query_fragments().unwrap();
+        // Equal selection sets share their fragment regardless of the order of their selections,
+        // while inline fragments with a single leaf field or with directives are kept as is.
+        insta::assert_snapshot!(operation.to_executable_document(&schema).unwrap(), @r###"
+        query Q($v: Boolean!) {
+          i {
+            ..._generated_onA2_1
+            ... on B {
+              x
+            }
+          }
+          other: i {
+            ..._generated_onA2_1
+            ... on B @include(if: $v) {
+              x
+              y
+            }
+          }
+        }
+
+        fragment _generated_onB2_0 on B {
+          x
+          y
+        }
+
+        fragment _generated_onA2_1 on A {
+          id
+          b {
+            ..._generated_onB2_0
+          }
+        }
+        "###);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::query_plan::operation::normalized_selection_map::{
+    NormalizedSelectionMap, NormalizedSelectionValue,
+};
+use crate::query_plan::operation::{NormalizedSelection, NormalizedSelectionSet};
+use indexmap::{IndexMap, IndexSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Interns the selections of normalized selection sets, so that selection sets with the same
+/// content share their selections (i.e. point to the same `Arc`). Large operations tend to select
+/// the same sub-selections many times (especially once fragments are expanded), and sharing them
+/// reduces the memory used by the operation. It also makes comparing and merging equal selection
+/// sets cheap, since comparing `Arc`s of the same allocation doesn't look at their content.
+///
+/// Selection sets are interned bottom-up, so the sub-selection sets of a selection set have already
+/// been interned when it is, and its structural hash only needs the address of their selections
+/// (instead of their whole content).
+// PORT_NOTE: The JS codebase has no equivalent; it relied on the garbage collector to share
+// selection sets that were reused as is.
+#[derive(Default)]
+pub(crate) struct SelectionSetInterner {
+    /// The interned selections, by structural hash. This also keeps them alive, ensuring the
+    /// addresses of the sub-selections used in those hashes aren't reused by other selections.
+    selections: IndexMap<u64, Vec<Arc<NormalizedSelectionMap>>>,
+    /// The addresses of the interned selections, to skip interning them again.
+    interned: IndexSet<*const NormalizedSelectionMap>,
+}
+
+impl SelectionSetInterner {
+    /// Replaces the selections of the given selection set (recursively) by interned ones with the
+    /// same content, interning them if there are none yet.
+    pub(crate) fn intern(&mut self, selection_set: &mut NormalizedSelectionSet) {
+        if self
+            .interned
+            .contains(&Arc::as_ptr(&selection_set.selections))
+        {
+            return;
+        }
+        let has_sub_selections = selection_set
+            .selections
+            .values()
+            .any(|selection| !matches!(selection, NormalizedSelection::FragmentSpread(_)));
+        if has_sub_selections {
+            for (_, selection) in Arc::make_mut(&mut selection_set.selections).iter_mut() {
+                match selection {
+                    NormalizedSelectionValue::Field(mut field_selection) => {
+                        if field_selection.get().selection_set.is_some() {
+                            if let Some(sub_selection_set) = field_selection.get_selection_set_mut()
+                            {
+                                self.intern(sub_selection_set);
+                            }
+                        }
+                    }
+                    NormalizedSelectionValue::InlineFragment(mut inline_fragment_selection) => {
+                        self.intern(inline_fragment_selection.get_selection_set_mut());
+                    }
+                    NormalizedSelectionValue::FragmentSpread(_) => {}
+                }
+            }
+        }
+
+        let candidates = self
+            .selections
+            .entry(structural_hash(&selection_set.selections))
+            .or_default();
+        // Note that selection maps compare equal regardless of order, but interning must preserve
+        // the order of selections.
+        if let Some(interned) = candidates
+            .iter()
+            .find(|candidate| candidate.values().eq(selection_set.selections.values()))
+        {
+            selection_set.selections = interned.clone();
+        } else {
+            candidates.push(selection_set.selections.clone());
+            self.interned.insert(Arc::as_ptr(&selection_set.selections));
+        }
+    }
+
+    /// The number of distinct selection sets interned so far.
+    pub(crate) fn len(&self) -> usize {
+        self.interned.len()
+    }
+}
+
+/// Hashes the given selections, whose sub-selection sets must already be interned.
+fn structural_hash(selections: &NormalizedSelectionMap) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for selection in selections.values() {
+        match selection {
+            NormalizedSelection::Field(field_selection) => {
+                field_selection.field.data().hash(&mut hasher);
+                field_selection
+                    .selection_set
+                    .as_ref()
+                    .map(|selection_set| Arc::as_ptr(&selection_set.selections))
+                    .hash(&mut hasher);
+            }
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                inline_fragment_selection
+                    .inline_fragment
+                    .data()
+                    .hash(&mut hasher);
+                Arc::as_ptr(&inline_fragment_selection.selection_set.selections).hash(&mut hasher);
+            }
+            NormalizedSelection::FragmentSpread(fragment_spread_selection) => {
+                fragment_spread_selection.data().hash(&mut hasher);
+            }
+        }
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::operation::interning::SelectionSetInterner;
+    use crate::query_plan::operation::{NormalizedOperation, NormalizedSelection};
+    use crate::schema::ValidFederationSchema;
+    use apollo_compiler::{ExecutableDocument, Schema};
+    use std::sync::Arc;
+
+    #[test]
+    fn shares_equal_selection_sets() {
+        let schema = Schema::parse_and_validate(
+            r#"
+            type Query { me: User friend: User }
+            type User { id: ID! name: String friends: [User] }
+            "#,
+            "schema.graphql",
+        )
+        .unwrap();
+        let schema = ValidFederationSchema::new(schema).unwrap();
+        let document = ExecutableDocument::parse_and_validate(
+            schema.schema(),
+            r#"
+            {
+              me { ...UserFields friends { ...UserFields } }
+              friend { ...UserFields friends { id } }
+              other: me { id name }
+            }
+
+            fragment UserFields on User { id name }
+            "#,
+            "operation.graphql",
+        )
+        .unwrap();
+        let mut operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        let mut interner = SelectionSetInterner::default();
+        interner.intern(&mut operation.selection_set);
+
+        let sub_selection_set = |selection: &NormalizedSelection| match selection {
+            NormalizedSelection::Field(field_selection) => {
+                field_selection.selection_set.clone().unwrap()
+            }
+            _ => panic!("expected a field selection"),
+        };
+        let mut root_selections = operation.selection_set.selections.values();
+        let me = sub_selection_set(root_selections.next().unwrap());
+        let friend = sub_selection_set(root_selections.next().unwrap());
+        let other = sub_selection_set(root_selections.next().unwrap());
+        let me_friends = sub_selection_set(me.selections.values().nth(2).unwrap());
+        let friend_friends = sub_selection_set(friend.selections.values().nth(2).unwrap());
+
+        // `{ id name }`, `{ id }`, `me`, `friend` and the root selection set.
+        assert_eq!(interner.len(), 5);
+        assert!(Arc::ptr_eq(&me_friends.selections, &other.selections));
+        assert!(!Arc::ptr_eq(
+            &me_friends.selections,
+            &friend_friends.selections
+        ));
+        assert!(!Arc::ptr_eq(&me.selections, &friend.selections));
+    }
+}
@@ -0,0 +1,510 @@
+//! A small rule engine to lint operations.
+//!
+//! Rules run on the normalized form of the operation (see `NormalizedOperation::from_document()`),
+//! so they don't have to care about fragments, duplicated selections or how a selection set was
+//! written: every field is visited once per response path.
+use crate::error::FederationError;
+use crate::query_plan::operation::{
+    collect_directive_variables, NormalizedOperation, NormalizedSelection, NormalizedSelectionSet,
+};
+use crate::schema::position::CompositeTypeDefinitionPosition;
+use crate::schema::ValidFederationSchema;
+use apollo_compiler::ast::{Argument, DirectiveList, Name};
+use apollo_compiler::executable::Selection;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::{ExecutableDocument, Node};
+use indexmap::IndexSet;
+use std::fmt::{Display, Formatter};
+
+/// A problem found by a lint rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// The name of the rule that found the problem.
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.rule, self.message)
+    }
+}
+
+/// A lint rule, checking the operation of a `LintContext` for some kind of problem.
+pub trait LintRule {
+    /// The name of this rule, which the warnings it reports are labelled with.
+    fn name(&self) -> &'static str;
+
+    /// Checks the operation, pushing a warning for each problem found.
+    fn check(
+        &self,
+        context: &LintContext,
+        warnings: &mut Vec<LintWarning>,
+    ) -> Result<(), FederationError>;
+}
+
+/// What lint rules are given: the normalized operation being linted, and the document it comes
+/// from (for the few checks that are about how the operation was written).
+pub struct LintContext<'a> {
+    pub document: &'a Valid<ExecutableDocument>,
+    pub operation: &'a NormalizedOperation,
+}
+
+/// A field selected by an operation, at a given response path.
+#[derive(Debug)]
+pub struct FieldUsage<'a> {
+    /// The response names leading to the field, from the root type (the last one is the field's).
+    pub path: Vec<Name>,
+    /// The name of the type the field is selected on.
+    pub parent_type: &'a Name,
+    pub field_name: &'a Name,
+    pub arguments: &'a [Node<Argument>],
+    pub directives: &'a DirectiveList,
+}
+
+impl FieldUsage<'_> {
+    /// The depth of the field, where fields of the root type have depth 1.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    fn path_string(&self) -> String {
+        join_path(&self.path)
+    }
+}
+
+impl<'a> LintContext<'a> {
+    /// All the fields selected by the operation, in document order (parents before children).
+    pub fn fields(&self) -> Vec<FieldUsage<'a>> {
+        let mut fields = Vec::new();
+        collect_fields(&self.operation.selection_set, &mut Vec::new(), &mut fields);
+        fields
+    }
+}
+
+fn collect_fields<'a>(
+    selection_set: &'a NormalizedSelectionSet,
+    path: &mut Vec<Name>,
+    fields: &mut Vec<FieldUsage<'a>>,
+) {
+    for selection in selection_set.selections.values() {
+        match selection {
+            NormalizedSelection::Field(field) => {
+                let data = field.field.data();
+                path.push(data.response_name());
+                fields.push(FieldUsage {
+                    path: path.clone(),
+                    parent_type: data.field_position.type_name(),
+                    field_name: data.name(),
+                    arguments: &data.arguments,
+                    directives: &data.directives,
+                });
+                if let Some(selection_set) = &field.selection_set {
+                    collect_fields(selection_set, path, fields);
+                }
+                path.pop();
+            }
+            NormalizedSelection::InlineFragment(fragment) => {
+                collect_fields(&fragment.selection_set, path, fields);
+            }
+            // Normalization expands all fragment spreads into inline fragments.
+            NormalizedSelection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+fn join_path(path: &[Name]) -> String {
+    path.iter()
+        .map(|name| name.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Runs a set of lint rules over operations.
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+}
+
+impl Default for Linter {
+    /// A linter with all the built-in rules, using their default settings.
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                Box::new(DeprecatedFieldRule),
+                Box::new(MissingTypenameRule),
+                Box::new(MaxDepthRule::default()),
+                Box::new(UnusedVariableRule),
+                Box::new(UnusedFragmentRule),
+            ],
+        }
+    }
+}
+
+impl Linter {
+    /// A linter without any rule.
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Adds a rule to this linter.
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Lints the operation of the given name (or the document's only operation if no name is
+    /// given), returning the warnings of all rules, in rule order.
+    pub fn lint(
+        &self,
+        document: &Valid<ExecutableDocument>,
+        operation_name: Option<&str>,
+        schema: &ValidFederationSchema,
+    ) -> Result<Vec<LintWarning>, FederationError> {
+        let operation = NormalizedOperation::from_document(document, operation_name, schema)?;
+        let context = LintContext {
+            document,
+            operation: &operation,
+        };
+        let mut warnings = Vec::new();
+        for rule in &self.rules {
+            rule.check(&context, &mut warnings)?;
+        }
+        Ok(warnings)
+    }
+}
+
+/// Reports uses of fields marked `@deprecated`.
+pub struct DeprecatedFieldRule;
+
+impl LintRule for DeprecatedFieldRule {
+    fn name(&self) -> &'static str {
+        "deprecated-field"
+    }
+
+    fn check(
+        &self,
+        context: &LintContext,
+        warnings: &mut Vec<LintWarning>,
+    ) -> Result<(), FederationError> {
+        let schema = context.operation.schema.schema();
+        for field in context.fields() {
+            let Some(definition) = schema.type_field(field.parent_type, field.field_name).ok()
+            else {
+                continue;
+            };
+            let Some(deprecated) = definition.directives.get("deprecated") else {
+                continue;
+            };
+            let reason = deprecated
+                .argument_by_name("reason")
+                .and_then(|reason| reason.as_str())
+                .map(|reason| format!(": {}", reason))
+                .unwrap_or_default();
+            warnings.push(LintWarning {
+                rule: self.name(),
+                message: format!(
+                    "Field \"{}.{}\" (at \"{}\") is deprecated{}",
+                    field.parent_type,
+                    field.field_name,
+                    field.path_string(),
+                    reason,
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reports selection sets on interfaces and unions that don't select `__typename`, which clients
+/// usually need to tell the concrete type of the result apart.
+pub struct MissingTypenameRule;
+
+impl MissingTypenameRule {
+    fn check_selection_set(
+        &self,
+        selection_set: &NormalizedSelectionSet,
+        path: &mut Vec<Name>,
+        warnings: &mut Vec<LintWarning>,
+    ) {
+        if matches!(
+            selection_set.type_position,
+            CompositeTypeDefinitionPosition::Interface(_)
+                | CompositeTypeDefinitionPosition::Union(_)
+        ) && !selects_typename(selection_set)
+        {
+            warnings.push(LintWarning {
+                rule: self.name(),
+                message: format!(
+                    "Selection on abstract type \"{}\" at \"{}\" doesn't select __typename",
+                    selection_set.type_position.type_name(),
+                    join_path(path),
+                ),
+            });
+        }
+        for selection in selection_set.selections.values() {
+            match selection {
+                NormalizedSelection::Field(field) => {
+                    if let Some(field_selection_set) = &field.selection_set {
+                        path.push(field.field.data().response_name());
+                        self.check_selection_set(field_selection_set, path, warnings);
+                        path.pop();
+                    }
+                }
+                NormalizedSelection::InlineFragment(fragment) => {
+                    // The fragment's selection set only needs its own __typename if it's still
+                    // abstract, in which case the check above applies to it too.
+                    self.check_selection_set(&fragment.selection_set, path, warnings);
+                }
+                NormalizedSelection::FragmentSpread(_) => {}
+            }
+        }
+    }
+}
+
+/// Whether the selection set directly selects `__typename`. Note that normalization moves
+/// `__typename` onto the data of a sibling field, so this has to be checked as well.
+fn selects_typename(selection_set: &NormalizedSelectionSet) -> bool {
+    selection_set
+        .selections
+        .values()
+        .any(|selection| match selection {
+            NormalizedSelection::Field(field) => {
+                let data = field.field.data();
+                data.field_position.is_introspection_typename_field()
+                    || data.sibling_typename.is_some()
+            }
+            _ => false,
+        })
+}
+
+impl LintRule for MissingTypenameRule {
+    fn name(&self) -> &'static str {
+        "missing-typename"
+    }
+
+    fn check(
+        &self,
+        context: &LintContext,
+        warnings: &mut Vec<LintWarning>,
+    ) -> Result<(), FederationError> {
+        self.check_selection_set(&context.operation.selection_set, &mut Vec::new(), warnings);
+        Ok(())
+    }
+}
+
+/// Reports fields selected deeper than a maximum depth (where fields of the root type have depth
+/// 1). Only the first field past the maximum is reported for each path.
+pub struct MaxDepthRule {
+    pub max_depth: usize,
+}
+
+impl Default for MaxDepthRule {
+    fn default() -> Self {
+        Self { max_depth: 10 }
+    }
+}
+
+impl LintRule for MaxDepthRule {
+    fn name(&self) -> &'static str {
+        "max-depth"
+    }
+
+    fn check(
+        &self,
+        context: &LintContext,
+        warnings: &mut Vec<LintWarning>,
+    ) -> Result<(), FederationError> {
+        for field in context.fields() {
+            if field.depth() == self.max_depth + 1 {
+                warnings.push(LintWarning {
+                    rule: self.name(),
+                    message: format!(
+                        "Field at \"{}\" is deeper than the maximum depth of {}",
+                        field.path_string(),
+                        self.max_depth,
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports variables defined by the operation but never used by it. Note that normalization removes
+/// introspection fields (`__schema` and `__type`), so variables only used by those are reported.
+pub struct UnusedVariableRule;
+
+impl LintRule for UnusedVariableRule {
+    fn name(&self) -> &'static str {
+        "unused-variable"
+    }
+
+    fn check(
+        &self,
+        context: &LintContext,
+        warnings: &mut Vec<LintWarning>,
+    ) -> Result<(), FederationError> {
+        let mut used = IndexSet::new();
+        collect_directive_variables(&context.operation.directives, &mut used);
+        context.operation.selection_set.collect_variables(&mut used);
+        for variable in context.operation.variables() {
+            if !used.contains(&variable.name) {
+                warnings.push(LintWarning {
+                    rule: self.name(),
+                    message: format!("Variable \"${}\" is never used", variable.name),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports fragments of the document that the linted operation doesn't use. Since normalization
+/// expands fragments, this is the one built-in rule that looks at the document.
+pub struct UnusedFragmentRule;
+
+impl UnusedFragmentRule {
+    fn collect_spreads<'a>(
+        document: &'a ExecutableDocument,
+        selections: &'a [Selection],
+        used: &mut IndexSet<&'a Name>,
+    ) {
+        for selection in selections {
+            match selection {
+                Selection::Field(field) => {
+                    Self::collect_spreads(document, &field.selection_set.selections, used);
+                }
+                Selection::InlineFragment(fragment) => {
+                    Self::collect_spreads(document, &fragment.selection_set.selections, used);
+                }
+                Selection::FragmentSpread(spread) => {
+                    if used.insert(&spread.fragment_name) {
+                        if let Some(fragment) = document.fragments.get(&spread.fragment_name) {
+                            Self::collect_spreads(
+                                document,
+                                &fragment.selection_set.selections,
+                                used,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl LintRule for UnusedFragmentRule {
+    fn name(&self) -> &'static str {
+        "unused-fragment"
+    }
+
+    fn check(
+        &self,
+        context: &LintContext,
+        warnings: &mut Vec<LintWarning>,
+    ) -> Result<(), FederationError> {
+        let operation_name = context.operation.name().map(|name| name.as_str());
+        let Ok(operation) = context.document.get_operation(operation_name) else {
+            return Ok(());
+        };
+        let mut used = IndexSet::new();
+        Self::collect_spreads(
+            context.document,
+            &operation.selection_set.selections,
+            &mut used,
+        );
+        for fragment_name in context.document.fragments.keys() {
+            if !used.contains(fragment_name) {
+                warnings.push(LintWarning {
+                    rule: self.name(),
+                    message: format!(
+                        "Fragment \"{}\" is not used by the operation",
+                        fragment_name
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::operation::lint::{Linter, MaxDepthRule};
+    use crate::schema::ValidFederationSchema;
+    use apollo_compiler::{ExecutableDocument, Schema};
+
+    const SCHEMA: &str = r#"
+        type Query {
+          node(id: ID!): Node
+          user: User
+        }
+
+        interface Node {
+          id: ID!
+        }
+
+        type User implements Node {
+          id: ID!
+          name: String @deprecated(reason: "Use fullName")
+          fullName: String
+          friend: User
+        }
+    "#;
+
+    fn lint(linter: &Linter, operation: &str, operation_name: Option<&str>) -> Vec<String> {
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        let document =
+            ExecutableDocument::parse_and_validate(&schema, operation, "operation.graphql")
+                .unwrap();
+        let schema = ValidFederationSchema::new(schema).unwrap();
+        linter
+            .lint(&document, operation_name, &schema)
+            .unwrap()
+            .into_iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn reports_built_in_rule_warnings() {
+        let operation = r#"
+            query A($id: ID!, $typeName: String!) {
+              __type(name: $typeName) { name }
+              node(id: $id) { id }
+              user { ...UserFields }
+            }
+
+            query B {
+              user { ...OtherFields }
+            }
+
+            fragment UserFields on User { id name }
+            fragment OtherFields on User { fullName }
+        "#;
+        insta::assert_snapshot!(lint(&Linter::default(), operation, Some("A")).join("\n"), @r###"
+        [deprecated-field] Field "User.name" (at "user.name") is deprecated: Use fullName
+        [missing-typename] Selection on abstract type "Node" at "node" doesn't select __typename
+        [unused-variable] Variable "$typeName" is never used
+        [unused-fragment] Fragment "OtherFields" is not used by the operation
+        "###);
+    }
+
+    #[test]
+    fn reports_overly_deep_selections() {
+        let linter = Linter::empty().with_rule(MaxDepthRule { max_depth: 2 });
+        let operation = r#"
+            {
+              user { friend { id friend { id friend { id } } } }
+              node(id: "1") { __typename id }
+            }
+        "#;
+        assert_eq!(
+            lint(&linter, operation, None),
+            vec![
+                r#"[max-depth] Field at "user.friend.id" is deeper than the maximum depth of 2"#,
+                r#"[max-depth] Field at "user.friend.friend" is deeper than the maximum depth of 2"#
+            ],
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Computes the operation signatures used by Apollo usage reporting, following the default
+//! signature algorithm of the reference implementation: literals are hidden, aliases removed,
+//! everything that can be reordered is sorted, and the result is printed with reduced whitespace.
+//!
+//! The main difference with the reference implementation is that signatures are computed on
+//! normalized operations, and so fragments are already expanded (and selections with the same
+//! response name already merged) instead of being printed as fragment definitions.
+use crate::query_plan::operation::{
+    NormalizedOperation, NormalizedSelection, NormalizedSelectionSet, TYPENAME_FIELD,
+};
+use apollo_compiler::ast::{Argument, DirectiveList, OperationType, Value, VariableDefinition};
+use apollo_compiler::Node;
+
+impl NormalizedOperation {
+    /// The usage reporting signature of this operation, e.g. `query Q($id:ID){user(id:$id){id}}`.
+    pub fn signature(&self) -> String {
+        let operation_type: OperationType = self.root_kind.into();
+        let mut printed = String::new();
+        // Like the GraphQL printer, use the query shorthand when nothing else needs printing.
+        if operation_type != OperationType::Query
+            || self.name.is_some()
+            || !self.variables.is_empty()
+            || !self.directives.is_empty()
+        {
+            printed.push_str(operation_type.name());
+            if let Some(name) = &self.name {
+                printed.push(' ');
+                printed.push_str(name);
+            }
+            print_variable_definitions(&self.variables, &mut printed);
+            print_directives(&self.directives, &mut printed);
+            printed.push(' ');
+        }
+        print_selection_set(&self.selection_set, &mut printed);
+        reduce_whitespace(&printed)
+    }
+
+    /// The key under which usage reporting aggregates the stats of this operation, i.e. its name
+    /// (or `-` if it's anonymous) followed by its signature.
+    pub fn stats_report_key(&self) -> String {
+        let name = self.name.as_ref().map_or("-", |name| name.as_str());
+        format!("# {}\n{}", name, self.signature())
+    }
+}
+
+fn print_variable_definitions(variables: &[Node<VariableDefinition>], printed: &mut String) {
+    if variables.is_empty() {
+        return;
+    }
+    let mut variables = variables.iter().collect::<Vec<_>>();
+    variables.sort_by(|a, b| a.name.cmp(&b.name));
+    let variables = variables
+        .into_iter()
+        .map(|variable| {
+            let mut printed = format!("${}: {}", variable.name, variable.ty);
+            if let Some(default_value) = &variable.default_value {
+                printed.push_str(" = ");
+                print_value(default_value, &mut printed);
+            }
+            print_directives(&variable.directives, &mut printed);
+            printed
+        })
+        .collect::<Vec<_>>();
+    printed.push('(');
+    printed.push_str(&variables.join(", "));
+    printed.push(')');
+}
+
+fn print_directives(directives: &DirectiveList, printed: &mut String) {
+    let mut directives = directives.iter().collect::<Vec<_>>();
+    directives.sort_by(|a, b| a.name.cmp(&b.name));
+    for directive in directives {
+        printed.push_str(" @");
+        printed.push_str(&directive.name);
+        print_arguments(&directive.arguments, printed);
+    }
+}
+
+fn print_arguments(arguments: &[Node<Argument>], printed: &mut String) {
+    if arguments.is_empty() {
+        return;
+    }
+    let mut arguments = arguments.iter().collect::<Vec<_>>();
+    arguments.sort_by(|a, b| a.name.cmp(&b.name));
+    let arguments = arguments
+        .into_iter()
+        .map(|argument| {
+            let mut printed = format!("{}: ", argument.name);
+            print_value(&argument.value, &mut printed);
+            printed
+        })
+        .collect::<Vec<_>>();
+    printed.push('(');
+    printed.push_str(&arguments.join(", "));
+    printed.push(')');
+}
+
+/// Prints the given value with its literals hidden: numbers become `0`, strings `""`, and lists and
+/// objects are emptied. Booleans, enum values, nulls and variables are kept.
+fn print_value(value: &Value, printed: &mut String) {
+    match value {
+        Value::Null => printed.push_str("null"),
+        Value::Enum(name) => printed.push_str(name),
+        Value::Variable(name) => {
+            printed.push('$');
+            printed.push_str(name);
+        }
+        Value::String(_) => printed.push_str("\"\""),
+        Value::Float(_) | Value::Int(_) => printed.push('0'),
+        Value::Boolean(value) => printed.push_str(if *value { "true" } else { "false" }),
+        Value::List(_) => printed.push_str("[]"),
+        Value::Object(_) => printed.push_str("{}"),
+    }
+}
+
+fn print_selection_set(selection_set: &NormalizedSelectionSet, printed: &mut String) {
+    // Selections are sorted by kind, then by name (inline fragments, which have no name, keep
+    // their relative order).
+    let mut selections: Vec<(u8, String, String)> = Vec::new();
+    for selection in selection_set.selections.values() {
+        match selection {
+            NormalizedSelection::Field(field) => {
+                let data = field.field.data();
+                if data.sibling_typename.is_some() {
+                    // Normalization moves `__typename` onto a sibling field, so it's restored here.
+                    selections.push((0, TYPENAME_FIELD.to_string(), TYPENAME_FIELD.to_string()));
+                }
+                let mut printed_field = data.name().to_string();
+                print_arguments(&data.arguments, &mut printed_field);
+                print_directives(&data.directives, &mut printed_field);
+                if let Some(selection_set) = &field.selection_set {
+                    printed_field.push(' ');
+                    print_selection_set(selection_set, &mut printed_field);
+                }
+                selections.push((0, data.name().to_string(), printed_field));
+            }
+            NormalizedSelection::FragmentSpread(spread) => {
+                let data = spread.data();
+                let mut printed_spread = format!("...{}", data.fragment_name);
+                print_directives(data.spread_directives(), &mut printed_spread);
+                selections.push((1, data.fragment_name.to_string(), printed_spread));
+            }
+            NormalizedSelection::InlineFragment(fragment) => {
+                let data = fragment.inline_fragment.data();
+                let mut printed_fragment = "...".to_owned();
+                if let Some(type_condition) = &data.type_condition_position {
+                    printed_fragment.push_str(" on ");
+                    printed_fragment.push_str(type_condition.type_name());
+                }
+                print_directives(&data.directives, &mut printed_fragment);
+                printed_fragment.push(' ');
+                print_selection_set(&fragment.selection_set, &mut printed_fragment);
+                selections.push((2, String::new(), printed_fragment));
+            }
+        }
+    }
+    selections.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+    printed.push_str("{ ");
+    printed.push_str(
+        &selections
+            .into_iter()
+            .map(|(_, _, printed)| printed)
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    printed.push_str(" }");
+}
+
+/// Collapses whitespace to single spaces, and removes the spaces next to anything but a name
+/// character (so only spaces separating two names remain). Since literals are hidden, strings are
+/// always empty, and so there's no string content to preserve.
+fn reduce_whitespace(printed: &str) -> String {
+    let is_name_char = |c: char| c == '_' || c.is_ascii_alphanumeric();
+    let mut reduced = String::with_capacity(printed.len());
+    let mut pending_space = false;
+    for c in printed.chars() {
+        if c.is_whitespace() {
+            pending_space = !reduced.is_empty();
+            continue;
+        }
+        if pending_space && is_name_char(c) && reduced.ends_with(is_name_char) {
+            reduced.push(' ');
+        }
+        pending_space = false;
+        reduced.push(c);
+    }
+    reduced
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::operation::NormalizedOperation;
+    use crate::schema::ValidFederationSchema;
+    use apollo_compiler::{ExecutableDocument, Schema};
+
+    fn normalize(operation: &str) -> NormalizedOperation {
+        let schema = Schema::parse_and_validate(
+            r#"
+            type Query {
+              user(id: ID!, options: Options): User
+              search(term: String, first: Int): [Result]
+            }
+
+            input Options { locale: String }
+
+            type User { id: ID! name: String friends(first: Int): [User] }
+            type Post { id: ID! title: String }
+            union Result = User | Post
+            "#,
+            "schema.graphql",
+        )
+        .unwrap();
+        let document =
+            ExecutableDocument::parse_and_validate(&schema, operation, "operation.graphql")
+                .unwrap();
+        let schema = ValidFederationSchema::new(schema).unwrap();
+        NormalizedOperation::from_document(&document, None, &schema).unwrap()
+    }
+
+    #[test]
+    fn computes_signatures() {
+        let operation = normalize(
+            r#"
+            query GetUser($id: ID!, $first: Int = 10) {
+              me: user(id: $id, options: { locale: "fr" }) {
+                name
+                ...UserFields
+                friends(first: $first) @include(if: true) { name id }
+              }
+              search(term: "secret", first: 3) {
+                ... on Post { title }
+                __typename
+              }
+            }
+
+            fragment UserFields on User { id }
+            "#,
+        );
+        assert_eq!(
+            operation.stats_report_key(),
+            "# GetUser\n\
+             query GetUser($first:Int=0,$id:ID!){search(first:0,term:\"\"){__typename...on Post{title}}user(id:$id,options:{}){friends(first:$first)@include(if:true){id name}id name}}",
+        );
+    }
+
+    #[test]
+    fn uses_query_shorthand_for_anonymous_queries() {
+        let operation = normalize("{ user(id: 1) { id } }");
+        assert_eq!(operation.signature(), "{user(id:0){id}}");
+        assert_eq!(operation.stats_report_key(), "# -\n{user(id:0){id}}");
+    }
+}
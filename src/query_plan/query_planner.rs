@@ -1,11 +1,34 @@
-use crate::link::federation_spec_definition::FederationSpecDefinition;
-use crate::query_graph::QueryGraph;
-use crate::schema::position::{AbstractTypeDefinitionPosition, InterfaceTypeDefinitionPosition};
+use crate::error::{FederationError, SingleFederationError};
+use crate::link::federation_spec_definition::{
+    get_federation_spec_definition_from_subgraph, FederationSpecDefinition,
+};
+use crate::query_graph::build_query_graph::build_federated_query_graph;
+use crate::query_graph::{EnabledOverrideConditions, QueryGraph};
+use crate::query_plan::fetch_dependency_graph::validate_fetch_operation;
+use crate::query_plan::fetch_dependency_graph_processor::FetchDependencyGraphToQueryPlanProcessor;
+use crate::query_plan::interning::PlanNodeInterner;
+use crate::query_plan::operation::{
+    get_operation, normalize_operation, NormalizedFragment, NormalizedOperation,
+};
+use crate::query_plan::query_planning_traversal::{
+    QueryPlanningParameters, QueryPlanningTraversal,
+};
+use crate::query_plan::{FetchNode, QueryPlan, QueryPlanCost, SubscriptionNode, TopLevelPlanNode};
+use crate::schema::position::{
+    AbstractTypeDefinitionPosition, CompositeTypeDefinitionPosition,
+    InterfaceTypeDefinitionPosition, SchemaRootDefinitionKind,
+};
 use crate::schema::ValidFederationSchema;
-use apollo_compiler::NodeStr;
+use crate::{ApiSchemaOptions, Supergraph};
+use apollo_compiler::ast::Name;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::{name, ExecutableDocument, Node, NodeStr};
 use indexmap::{IndexMap, IndexSet};
+use std::fmt::{Display, Formatter};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+#[derive(Debug, Clone)]
 pub struct QueryPlannerConfig {
     /// Whether the query planner should try to reused the named fragments of the planned query in
     /// subgraph fetches.
@@ -22,9 +45,23 @@ pub struct QueryPlannerConfig {
     /// Defaults to true.
     pub reuse_query_fragments: bool,
 
+    /// If enabled, the query planner will extract inline fragments into fragment definitions before
+    /// sending queries to subgraphs. This can significantly reduce the size of the query sent to
+    /// subgraphs, but may increase the time it takes to plan the query.
+    ///
+    /// When enabled, `reuse_query_fragments` is ignored (the named fragments of the planned query
+    /// are not reused, since fragments are generated instead).
+    ///
+    /// Defaults to false.
+    pub generate_query_fragments: bool,
+
     /// Whether to run GraphQL validation against the extracted subgraph schemas. Recommended in
     /// non-production settings or when debugging.
     ///
+    /// When enabled, every subgraph fetch operation of a plan is validated against the schema of
+    /// its subgraph, and query planning fails (with an error showing the operation) if one is
+    /// invalid, instead of the subgraph rejecting it at execution time.
+    ///
     /// Defaults to false.
     pub subgraph_graphql_validation: bool,
 
@@ -35,6 +72,51 @@ pub struct QueryPlannerConfig {
     // support @stream, grouping the options here will make sense too.
     pub incremental_delivery: QueryPlanIncrementalDeliveryConfig,
 
+    /// The weights used to compute the cost of candidate query plans, which is how the query
+    /// planner picks a plan when a query can be planned in more than one way (e.g. when fields are
+    /// @shareable across many subgraphs).
+    pub cost: QueryPlanCostConfig,
+
+    /// A limit on the number of plan options (i.e. choices for the parts of a query that can be
+    /// planned in more than one way) evaluated when looking for the cheapest plan. This bounds
+    /// query planning time for queries whose candidate plans explode combinatorially. Once the limit
+    /// is reached, the query planner settles for the cheapest plan found so far (which is at worst
+    /// the plan picking the most likely-to-be-good option for each part of the query), and reports
+    /// a `QueryPlanningWarning::EvaluatedPlanOptionsLimitReached` warning as this plan may be
+    /// suboptimal.
+    ///
+    /// Defaults to None, which specifies no limit.
+    pub max_evaluated_plan_options: Option<u32>,
+
+    /// A limit on the size (in bytes) of the selection set of each subgraph fetch, once serialized.
+    /// Fetches over that limit are split into several fetches over disjoint parts of their
+    /// selection set, which the router sends in parallel, as some subgraph servers reject very
+    /// large queries. Root mutation fetches are never split, as their fields must be executed in
+    /// order, and a single leaf field larger than the limit can't be split.
+    ///
+    /// Defaults to None, which specifies no limit.
+    pub max_subgraph_fetch_size: Option<usize>,
+
+    /// Whether to remove the `__typename` selections that have sibling selections from operations
+    /// before planning them, adding them back to the fetches of the plan, as planning them as
+    /// regular fields can needlessly multiply the options to evaluate (see
+    /// `NormalizedSelectionSet::optimize_sibling_typenames()`). When disabled, the operations
+    /// returned by `normalize_operation()` keep their `__typename` selections as written.
+    ///
+    /// Defaults to true.
+    pub optimize_sibling_typenames: bool,
+
+    /// Whether to explore the options of independent branches of the query in parallel (using the
+    /// rayon global thread pool). This can reduce query planning latency for very large operations,
+    /// at the expense of using more threads. The generated plans are deterministic, but when
+    /// `max_evaluated_plan_options` is set, it applies to the exploration of each option of the
+    /// branch with the most options separately, so plans may differ from those found without it.
+    ///
+    /// This is a no-op unless the `rayon` feature of this crate is enabled.
+    ///
+    /// Defaults to false.
+    pub parallel_exploration: bool,
+
     /// A sub-set of configurations that are meant for debugging or testing. All the configurations
     /// in this sub-set are provided without guarantees of stability (they may be dangerous) or
     /// continued support (they may be removed without warning).
@@ -45,14 +127,20 @@ impl Default for QueryPlannerConfig {
     fn default() -> Self {
         Self {
             reuse_query_fragments: true,
+            generate_query_fragments: false,
             subgraph_graphql_validation: false,
             incremental_delivery: Default::default(),
+            cost: Default::default(),
+            max_evaluated_plan_options: None,
+            max_subgraph_fetch_size: None,
+            optimize_sibling_typenames: true,
+            parallel_exploration: false,
             debug: Default::default(),
         }
     }
 }
 
-#[derive(Default)]
+#[derive(Debug, Clone, Default)]
 pub struct QueryPlanIncrementalDeliveryConfig {
     /// Enables @defer support by the query planner.
     ///
@@ -60,14 +148,56 @@ pub struct QueryPlanIncrementalDeliveryConfig {
     /// (see `query_plan/mod.rs`).
     ///
     /// Defaults to false (meaning that the @defer are ignored).
-    enable_defer: bool,
+    pub enable_defer: bool,
+}
+
+/// The cost of a query plan is the weighted sum of the number of subgraph fetches it makes, the
+/// depth of the plan (the longest chain of fetches that must run one after the other), and the
+/// estimated size of the responses of its fetches.
+///
+/// When two plans have the same cost, the first one generated is kept, so plan selection is
+/// deterministic for a given supergraph and configuration.
+#[derive(Debug, Clone)]
+pub struct QueryPlanCostConfig {
+    /// The cost of each subgraph fetch in the plan.
+    ///
+    /// Defaults to 1000.
+    pub fetch_weight: QueryPlanCost,
+
+    /// The cost of each level of fetches that have to be executed sequentially.
+    ///
+    /// Defaults to 100.
+    pub depth_weight: QueryPlanCost,
+
+    /// The cost of each field the plan is estimated to fetch from subgraphs.
+    ///
+    /// Defaults to 1.
+    pub response_size_weight: QueryPlanCost,
+
+    /// How many elements a list field is assumed to return when estimating the size of responses
+    /// (the fields selected within a list are counted that many times).
+    ///
+    /// Defaults to 10.
+    pub list_size_estimate: QueryPlanCost,
+}
+
+impl Default for QueryPlanCostConfig {
+    fn default() -> Self {
+        Self {
+            fetch_weight: 1000,
+            depth_weight: 100,
+            response_size_weight: 1,
+            list_size_estimate: 10,
+        }
+    }
 }
 
+#[derive(Debug, Clone)]
 pub struct QueryPlannerDebugConfig {
     /// If used and the supergraph is built from a single subgraph, then user queries do not go
     /// through the normal query planning and instead a fetch to the one subgraph is built directly
     /// from the input query.
-    bypass_planner_for_single_subgraph: bool,
+    pub bypass_planner_for_single_subgraph: bool,
 
     /// Query planning is an exploratory process. Depending on the specificities and feature used by
     /// subgraphs, there could exist may different theoretical valid (if not always efficient) plans
@@ -85,7 +215,7 @@ pub struct QueryPlannerDebugConfig {
     /// debug a specific issue (with unexpectedly slow query planning for instance). Remember that
     /// setting this value too low can negatively affect query runtime (due to the use of
     /// sub-optimal query plans).
-    pub(crate) max_evaluated_plans: u32,
+    pub max_evaluated_plans: u32,
 
     /// Before creating query plans, for each path of fields in the query we compute all the
     /// possible options to traverse that path via the subgraphs. Multiple options can arise because
@@ -99,7 +229,12 @@ pub struct QueryPlannerDebugConfig {
     /// path's options exceeds this limit, query planning will abort and the operation will fail.
     ///
     /// The default value is None, which specifies no limit.
-    pub(crate) paths_limit: Option<u32>,
+    pub paths_limit: Option<u32>,
+
+    /// If set, query planning records a `PlanningTrace` of the paths it considered and rejected,
+    /// and of the plan it chose. This is meant to debug unexpected plans, and slows planning down
+    /// noticeably.
+    pub record_planning_trace: bool,
 }
 
 impl Default for QueryPlannerDebugConfig {
@@ -108,10 +243,304 @@ impl Default for QueryPlannerDebugConfig {
             bypass_planner_for_single_subgraph: false,
             max_evaluated_plans: 10000,
             paths_limit: None,
+            record_planning_trace: false,
         }
     }
 }
 
+/// A warning about a query plan, meant for routers to log. Warnings don't prevent planning: the
+/// plan is still correct, but it may be suboptimal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryPlanningWarning {
+    /// The query had more possible plans than `QueryPlannerDebugConfig::max_evaluated_plans`, so
+    /// some plan options were discarded upfront.
+    PossiblePlansReduced {
+        /// The number of possible plans before discarding options.
+        possible_plans: usize,
+        /// The configured `max_evaluated_plans`.
+        max_evaluated_plans: u32,
+    },
+    /// The search for the cheapest plan stopped after evaluating
+    /// `QueryPlannerConfig::max_evaluated_plan_options` plan options.
+    EvaluatedPlanOptionsLimitReached {
+        /// The number of plan options evaluated.
+        evaluated_options: usize,
+        /// The configured `max_evaluated_plan_options`.
+        max_evaluated_plan_options: u32,
+    },
+    /// A directive the query planner doesn't support was removed from the operation before
+    /// planning (e.g. `@defer` when `QueryPlanIncrementalDeliveryConfig::enable_defer` is
+    /// disabled).
+    UnsupportedDirectiveDropped {
+        /// The name of the dropped directive.
+        directive_name: Name,
+    },
+    /// A named fragment of the operation can't be reused in the fetches to a subgraph, as its type
+    /// condition isn't defined in that subgraph, so its selections are inlined instead.
+    FragmentNotReused {
+        /// The name of the fragment.
+        fragment_name: Name,
+        /// The name of the subgraph.
+        subgraph_name: NodeStr,
+    },
+    /// A `__typename` selection couldn't be attached to one of its sibling selections (e.g. on an
+    /// @interfaceObject type, whose `__typename` has to be fetched like a regular field), so it's
+    /// planned as a regular field, which adds plan options.
+    SiblingTypenameFallback {
+        /// The type of the selection set with the `__typename` selection.
+        type_name: Name,
+    },
+}
+
+impl QueryPlanningWarning {
+    /// The code of this warning (e.g. `POSSIBLE_PLANS_REDUCED`). These codes are stable, and are
+    /// what monitoring should match warnings on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            QueryPlanningWarning::PossiblePlansReduced { .. } => "POSSIBLE_PLANS_REDUCED",
+            QueryPlanningWarning::EvaluatedPlanOptionsLimitReached { .. } => {
+                "EVALUATED_PLAN_OPTIONS_LIMIT_REACHED"
+            }
+            QueryPlanningWarning::UnsupportedDirectiveDropped { .. } => {
+                "UNSUPPORTED_DIRECTIVE_DROPPED"
+            }
+            QueryPlanningWarning::FragmentNotReused { .. } => "FRAGMENT_NOT_REUSED",
+            QueryPlanningWarning::SiblingTypenameFallback { .. } => "SIBLING_TYPENAME_FALLBACK",
+        }
+    }
+}
+
+impl Display for QueryPlanningWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryPlanningWarning::PossiblePlansReduced {
+                possible_plans,
+                max_evaluated_plans,
+            } => write!(
+                f,
+                "Query has {} possible plans, more than the maximum of {} evaluated plans: some options were discarded and the plan may be suboptimal",
+                possible_plans, max_evaluated_plans,
+            ),
+            QueryPlanningWarning::EvaluatedPlanOptionsLimitReached {
+                evaluated_options,
+                max_evaluated_plan_options,
+            } => write!(
+                f,
+                "Query planning stopped after evaluating {} plan options (the limit is {}): the plan may be suboptimal",
+                evaluated_options, max_evaluated_plan_options,
+            ),
+            QueryPlanningWarning::UnsupportedDirectiveDropped { directive_name } => write!(
+                f,
+                "Directive \"@{}\" is not supported by the query planner and was removed from the operation",
+                directive_name,
+            ),
+            QueryPlanningWarning::FragmentNotReused {
+                fragment_name,
+                subgraph_name,
+            } => write!(
+                f,
+                "Fragment \"{}\" cannot be reused in the fetches to subgraph \"{}\": its selections are inlined instead",
+                fragment_name, subgraph_name,
+            ),
+            QueryPlanningWarning::SiblingTypenameFallback { type_name } => write!(
+                f,
+                "Selection of \"__typename\" on type \"{}\" cannot be attached to a sibling selection and is planned as a regular field",
+                type_name,
+            ),
+        }
+    }
+}
+
+/// A record of how query planning explored the options for an operation, recorded when
+/// `QueryPlannerDebugConfig::record_planning_trace` is set. Paths are printed as in the JS query
+/// planner's debug output, e.g. `Query(S1)* -[t]-> T(S1) (types: [T])`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanningTrace {
+    /// The paths considered for each operation element, in the order they were computed.
+    pub considered_paths: Vec<ConsideredPath>,
+    /// The paths rejected along the way, in the order they were rejected.
+    pub rejected_paths: Vec<RejectedPath>,
+    /// The plan chosen, if any.
+    pub chosen_plan: Option<ChosenPlan>,
+}
+
+/// A path considered by query planning to advance an operation element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsideredPath {
+    /// The operation element advanced, e.g. a field or an inline fragment.
+    pub element: String,
+    /// The path (or simultaneous paths, for type-exploded abstract types) reached.
+    pub path: String,
+}
+
+/// A path rejected by query planning, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedPath {
+    pub path: String,
+    pub reason: PathRejectionReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathRejectionReason {
+    /// The operation element can't be advanced from the path, e.g. because no subgraph reachable
+    /// from its tail resolves the field.
+    CannotAdvance { element: String },
+    /// Another option for the same operation element is known to be always at least as good.
+    Overridden,
+    /// The option was discarded to keep the number of evaluated plans within
+    /// `QueryPlannerDebugConfig::max_evaluated_plans`.
+    PlansReduced,
+}
+
+/// The plan chosen by query planning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChosenPlan {
+    /// The tree of the paths taken by the plan.
+    pub path_tree: String,
+    pub cost: QueryPlanCost,
+}
+
+impl Display for PlanningTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for considered in &self.considered_paths {
+            writeln!(
+                f,
+                "considered for {}: {}",
+                considered.element, considered.path
+            )?;
+        }
+        for rejected in &self.rejected_paths {
+            writeln!(f, "rejected {}: {}", rejected.path, rejected.reason)?;
+        }
+        match &self.chosen_plan {
+            Some(chosen) => writeln!(f, "chosen (cost {}): {}", chosen.cost, chosen.path_tree),
+            None => writeln!(f, "no plan chosen"),
+        }
+    }
+}
+
+impl Display for PathRejectionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathRejectionReason::CannotAdvance { element } => {
+                write!(f, "cannot advance {}", element)
+            }
+            PathRejectionReason::Overridden => write!(f, "overridden by another option"),
+            PathRejectionReason::PlansReduced => {
+                write!(f, "discarded to reduce the number of evaluated plans")
+            }
+        }
+    }
+}
+
+/// Limits on the resources a single query planning call may use, so that services planning the
+/// operations of many tenants can keep one tenant's expensive operations from starving the others.
+/// Planning calls going over budget fail with a `QueryPlanningBudgetExceeded` error.
+///
+/// Defaults to no limits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryPlanningBudget {
+    /// A limit on the number of selections (counted recursively) in the selection sets built while
+    /// planning.
+    pub max_selections: Option<usize>,
+    /// A limit on the number of graph paths (i.e. options to traverse the subgraphs for the fields
+    /// of the operation) created while planning.
+    pub max_graph_paths: Option<usize>,
+    /// A limit on the wall-clock time spent planning. It's checked between planning steps, so a
+    /// planning call may run over it by the duration of one step.
+    pub max_duration: Option<Duration>,
+}
+
+/// The resources used by a query planning call, as measured by a `QueryPlanningMeter`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryPlanningStatistics {
+    /// The largest number of selections (counted recursively) of a selection set built while
+    /// planning.
+    pub peak_selections: usize,
+    /// The number of graph paths created while planning.
+    pub graph_paths: usize,
+    /// The wall-clock time spent planning, up to the last time resource usage was measured.
+    pub duration: Duration,
+}
+
+/// Measures the resources used by a query planning call and enforces its budget. A meter is
+/// created for each call (it starts the clock), and its statistics remain available after the call,
+/// including when it failed for going over budget.
+#[derive(Debug, Clone)]
+pub struct QueryPlanningMeter {
+    budget: QueryPlanningBudget,
+    start: Instant,
+    statistics: QueryPlanningStatistics,
+}
+
+impl QueryPlanningMeter {
+    pub fn new(budget: QueryPlanningBudget) -> Self {
+        Self {
+            budget,
+            start: Instant::now(),
+            statistics: Default::default(),
+        }
+    }
+
+    pub fn budget(&self) -> &QueryPlanningBudget {
+        &self.budget
+    }
+
+    pub fn statistics(&self) -> &QueryPlanningStatistics {
+        &self.statistics
+    }
+
+    /// Records a selection set built while planning with the given number of selections.
+    pub(crate) fn record_selections(&mut self, selections: usize) -> Result<(), FederationError> {
+        self.statistics.peak_selections = self.statistics.peak_selections.max(selections);
+        match self.budget.max_selections {
+            Some(max_selections) if selections > max_selections => Err(Self::exceeded(format!(
+                "Query planning built a selection set of {} selections, over the budget of {}",
+                selections, max_selections,
+            ))),
+            _ => self.check_duration(),
+        }
+    }
+
+    /// Records the given number of new graph paths created while planning.
+    pub(crate) fn record_graph_paths(&mut self, graph_paths: usize) -> Result<(), FederationError> {
+        self.statistics.graph_paths += graph_paths;
+        match self.budget.max_graph_paths {
+            Some(max_graph_paths) if self.statistics.graph_paths > max_graph_paths => {
+                Err(Self::exceeded(format!(
+                    "Query planning created {} graph paths, over the budget of {}",
+                    self.statistics.graph_paths, max_graph_paths,
+                )))
+            }
+            _ => self.check_duration(),
+        }
+    }
+
+    /// Records the time spent planning so far.
+    pub(crate) fn check_duration(&mut self) -> Result<(), FederationError> {
+        self.statistics.duration = self.start.elapsed();
+        match self.budget.max_duration {
+            Some(max_duration) if self.statistics.duration > max_duration => {
+                Err(Self::exceeded(format!(
+                    "Query planning took {:?}, over the budget of {:?}",
+                    self.statistics.duration, max_duration,
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn exceeded(message: String) -> FederationError {
+        SingleFederationError::QueryPlanningBudgetExceeded { message }.into()
+    }
+}
+
+impl Default for QueryPlanningMeter {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
 pub struct QueryPlanner {
     config: Arc<QueryPlannerConfig>,
     federated_query_graph: Arc<QueryGraph>,
@@ -126,6 +555,1074 @@ pub struct QueryPlanner {
     // PORT_NOTE: Named `inconsistentAbstractTypesRuntimes` in the JS codebase, which was slightly
     // confusing.
     abstract_types_with_inconsistent_runtime_types: Arc<IndexSet<AbstractTypeDefinitionPosition>>,
+    /// The name of the only subgraph of the supergraph, when it has a single one and
+    /// `debug.bypass_planner_for_single_subgraph` is enabled. Operations are then passed through to
+    /// that subgraph as is (see `single_subgraph_plan()`).
+    single_subgraph_name: Option<NodeStr>,
     // TODO: Port _lastGeneratedPlanStatistics from the JS codebase in a way that keeps QueryPlanner
     // immutable.
 }
+
+impl QueryPlanner {
+    pub fn new(
+        supergraph: &Supergraph,
+        config: QueryPlannerConfig,
+    ) -> Result<Self, FederationError> {
+        if config.debug.bypass_planner_for_single_subgraph
+            && config.incremental_delivery.enable_defer
+        {
+            return Err(SingleFederationError::InvalidGraphQL {
+                message: "Cannot use the \"debug.bypass_planner_for_single_subgraph\" query planner option when @defer support is enabled".to_owned(),
+                locations: Vec::new(),
+            }
+            .into());
+        }
+        let supergraph_schema = supergraph.schema.clone();
+        // Operations may use @defer even when the query planner doesn't support it, in which case
+        // it's ignored, and @stream, which is rejected with a clear error (see
+        // `normalize_operation()`).
+        let api_schema =
+            ValidFederationSchema::new(supergraph.to_api_schema(ApiSchemaOptions {
+                include_defer: true,
+                include_stream: true,
+            })?)?;
+        let federated_query_graph = Arc::new(build_federated_query_graph(
+            supergraph_schema.clone(),
+            api_schema.clone(),
+            Some(true),
+            Some(true),
+        )?);
+
+        let mut subgraph_federation_spec_definitions = IndexMap::new();
+        for (source, schema) in federated_query_graph.subgraphs() {
+            subgraph_federation_spec_definitions.insert(
+                source.clone(),
+                get_federation_spec_definition_from_subgraph(schema)?,
+            );
+        }
+
+        let single_subgraph_name = match subgraph_federation_spec_definitions
+            .keys()
+            .collect::<Vec<_>>()[..]
+        {
+            [subgraph_name] if config.debug.bypass_planner_for_single_subgraph => {
+                Some(subgraph_name.clone())
+            }
+            _ => None,
+        };
+
+        let mut interface_types_with_interface_objects = IndexSet::new();
+        let mut abstract_types_with_inconsistent_runtime_types = IndexSet::new();
+        for type_position in supergraph_schema.get_types() {
+            let Ok(type_position): Result<AbstractTypeDefinitionPosition, _> =
+                type_position.try_into()
+            else {
+                continue;
+            };
+            if let AbstractTypeDefinitionPosition::Interface(interface_type_position) =
+                &type_position
+            {
+                if federated_query_graph.subgraphs().any(|(source, _)| {
+                    federated_query_graph
+                        .is_interface_object_type(source, &interface_type_position.type_name)
+                }) {
+                    interface_types_with_interface_objects.insert(interface_type_position.clone());
+                }
+            }
+            if has_inconsistent_runtime_types(
+                &supergraph_schema,
+                &federated_query_graph,
+                &type_position,
+            )? {
+                abstract_types_with_inconsistent_runtime_types.insert(type_position);
+            }
+        }
+
+        Ok(Self {
+            config: Arc::new(config),
+            federated_query_graph,
+            supergraph_schema,
+            api_schema,
+            subgraph_federation_spec_definitions: Arc::new(subgraph_federation_spec_definitions),
+            interface_types_with_interface_objects: Arc::new(
+                interface_types_with_interface_objects,
+            ),
+            abstract_types_with_inconsistent_runtime_types: Arc::new(
+                abstract_types_with_inconsistent_runtime_types,
+            ),
+            single_subgraph_name,
+        })
+    }
+
+    pub fn config(&self) -> &QueryPlannerConfig {
+        &self.config
+    }
+
+    /// Normalizes the operation named `operation_name` of the given document (against the API
+    /// schema) for query planning, according to the configuration of this query planner:
+    /// - The named fragments of the operation are kept (to be reused in subgraph fetches) only if
+    ///   `reuse_query_fragments` is enabled and `generate_query_fragments` isn't.
+    /// - The `@defer` applications of the operation are removed unless
+    ///   `incremental_delivery.enable_defer` is enabled.
+    /// - The `__typename` selections removed by the sibling typename optimization are added back
+    ///   unless `optimize_sibling_typenames` is enabled.
+    pub fn normalize_operation(
+        &self,
+        document: &Valid<ExecutableDocument>,
+        operation_name: Option<&str>,
+    ) -> Result<NormalizedOperation, FederationError> {
+        self.normalize_operation_with_warnings(document, operation_name, &mut Vec::new())
+    }
+
+    /// Like `normalize_operation()`, but also reports the non-fatal oddities of the normalization
+    /// to `warnings` (e.g. the directives it dropped).
+    pub fn normalize_operation_with_warnings(
+        &self,
+        document: &Valid<ExecutableDocument>,
+        operation_name: Option<&str>,
+        warnings: &mut Vec<QueryPlanningWarning>,
+    ) -> Result<NormalizedOperation, FederationError> {
+        self.normalize_operation_with_meter(
+            document,
+            operation_name,
+            warnings,
+            &mut Default::default(),
+        )
+    }
+
+    /// Like `normalize_operation_with_warnings()`, but also measures the resources used to
+    /// `meter`, failing if they go over its budget.
+    pub fn normalize_operation_with_meter(
+        &self,
+        document: &Valid<ExecutableDocument>,
+        operation_name: Option<&str>,
+        warnings: &mut Vec<QueryPlanningWarning>,
+        meter: &mut QueryPlanningMeter,
+    ) -> Result<NormalizedOperation, FederationError> {
+        let operation = get_operation(document, operation_name)?;
+        let mut normalized_operation = normalize_operation(
+            operation,
+            &document.fragments,
+            &self.api_schema,
+            &self.interface_types_with_interface_objects,
+        )?;
+        meter.record_selections(normalized_operation.selection_set.selection_count())?;
+        if !self.config.reuse_query_fragments || self.config.generate_query_fragments {
+            normalized_operation.fragments = Default::default();
+        }
+        if !self.config.incremental_delivery.enable_defer {
+            if normalized_operation.selection_set.has_defer()? {
+                warnings.push(QueryPlanningWarning::UnsupportedDirectiveDropped {
+                    directive_name: name!("defer"),
+                });
+            }
+            normalized_operation.selection_set =
+                normalized_operation.selection_set.without_defer()?;
+        }
+        // TODO: Plan @stream (as incremental delivery of list items) once it's supported by the
+        // query plan format, instead of rejecting it.
+        if let Some(streamed_field) = normalized_operation.selection_set.streamed_field() {
+            return Err(SingleFederationError::UnsupportedFeature {
+                message: format!(
+                    "Directive \"@stream\" (used on field \"{}\") is not supported by the query planner",
+                    streamed_field.field_position,
+                ),
+            }
+            .into());
+        }
+        if self.config.optimize_sibling_typenames {
+            let mut typename_fallback_types = IndexSet::new();
+            normalized_operation
+                .selection_set
+                .collect_sibling_typename_fallbacks(&mut typename_fallback_types);
+            warnings.extend(
+                typename_fallback_types
+                    .into_iter()
+                    .map(|type_name| QueryPlanningWarning::SiblingTypenameFallback { type_name }),
+            );
+        } else {
+            normalized_operation.selection_set = normalized_operation
+                .selection_set
+                .add_back_typename_in_attachments()?;
+        }
+        meter.check_duration()?;
+        Ok(normalized_operation)
+    }
+
+    /// The plan for the given (normalized) operation when the supergraph has a single subgraph and
+    /// `debug.bypass_planner_for_single_subgraph` is enabled, or `None` otherwise. The plan is a
+    /// single fetch of the operation to that subgraph, so graph traversal is skipped entirely.
+    ///
+    /// Normalization already removed the introspection fields of the operation (which are resolved
+    /// by the executor, see `QueryPlan::has_introspection()`), so if it only selected those, the
+    /// plan has no node.
+    // PORT_NOTE: The JS codebase passed the original operation through. The normalized operation is
+    // used here instead, so that introspection and `@defer` are handled like for other plans.
+    pub fn single_subgraph_plan(
+        &self,
+        operation: &NormalizedOperation,
+    ) -> Result<Option<QueryPlan>, FederationError> {
+        let Some(subgraph_name) = &self.single_subgraph_name else {
+            return Ok(None);
+        };
+        if operation.selection_set.selections.is_empty() {
+            return Ok(Some(QueryPlan {
+                has_introspection: operation.has_introspection,
+                ..QueryPlan::empty()
+            }));
+        }
+        let subgraph_schema = self.federated_query_graph.schema_by_source(subgraph_name)?;
+        // The named fragments are spread inline during normalization, so the document has none
+        // (unless `generate_query_fragments` is enabled).
+        // The `__typename` selections moved onto sibling fields by normalization are added back.
+        // Normalization may also have removed all the uses of some variables (e.g. in
+        // introspection fields), which the subgraph would reject if they were still declared.
+        let mut subgraph_operation = NormalizedOperation {
+            selection_set: operation.selection_set.add_back_typename_in_attachments()?,
+            variables: Arc::new(operation.used_variable_definitions(&operation.selection_set)),
+            ..operation.clone()
+        };
+        if self.config.generate_query_fragments {
+            subgraph_operation = subgraph_operation.generate_query_fragments()?;
+        }
+        let variable_usages = subgraph_operation
+            .variables
+            .iter()
+            .map(|variable| variable.name.clone())
+            .collect();
+        let operation_document = validate_fetch_operation(
+            subgraph_operation.to_unvalidated_document()?,
+            subgraph_name,
+            subgraph_schema,
+            self.config.subgraph_graphql_validation,
+        )?;
+        let fetch_node = FetchNode {
+            subgraph_name: subgraph_name.clone(),
+            id: None,
+            has_defers: None,
+            variable_usages,
+            requires: Vec::new(),
+            operation_document,
+            operation_name: operation.name.as_ref().map(|name| NodeStr::new(name)),
+            operation_kind: operation.root_kind.into(),
+            input_rewrites: Vec::new(),
+            output_rewrites: Vec::new(),
+            context_rewrites: Vec::new(),
+            authorization: None,
+        };
+        let node = match operation.root_kind {
+            SchemaRootDefinitionKind::Subscription => {
+                TopLevelPlanNode::Subscription(SubscriptionNode {
+                    primary: fetch_node,
+                    rest: None,
+                })
+            }
+            _ => TopLevelPlanNode::Fetch(fetch_node),
+        };
+        Ok(Some(QueryPlan {
+            node: Some(node),
+            warnings: Vec::new(),
+            has_introspection: operation.has_introspection,
+        }))
+    }
+
+    /// Plans the given (normalized) operation by traversing the federated query graph, with the
+    /// given progressive @override labels enabled: a field overridden with a label is fetched from
+    /// the overriding subgraph when that label is enabled, and from the overridden one otherwise.
+    /// When several plans are possible, the cheapest one (according to `cost`) is returned.
+    pub fn build_query_plan(
+        &self,
+        operation: &NormalizedOperation,
+        override_conditions: EnabledOverrideConditions,
+    ) -> Result<QueryPlan, FederationError> {
+        if let Some(plan) = self.single_subgraph_plan(operation)? {
+            return Ok(plan);
+        }
+        // TODO: Plan subscriptions, whose plan must start with a single fetch to the subgraph
+        // serving the subscription (see `SubscriptionNode`).
+        if operation.root_kind == SchemaRootDefinitionKind::Subscription {
+            return Err(SingleFederationError::UnsupportedFeature {
+                message: "Subscriptions can only be planned for a single subgraph".to_owned(),
+            }
+            .into());
+        }
+        let head = self.federated_query_graph.root_kinds_to_nodes()?[&operation.root_kind];
+        let processor = FetchDependencyGraphToQueryPlanProcessor::new(self.config.clone());
+        let parameters = QueryPlanningParameters {
+            supergraph_schema: self.supergraph_schema.clone(),
+            federated_query_graph: self.federated_query_graph.clone(),
+            operation: Arc::new(operation.clone()),
+            processor: processor.clone(),
+            head,
+            head_must_be_root: true,
+            abstract_types_with_inconsistent_runtime_types: self
+                .abstract_types_with_inconsistent_runtime_types
+                .clone(),
+            config: self.config.clone(),
+            override_conditions: Arc::new(override_conditions),
+        };
+        let has_defers =
+            self.config.incremental_delivery.enable_defer && operation.selection_set.has_defer()?;
+        let mut traversal = QueryPlanningTraversal::new(
+            parameters,
+            &operation.selection_set,
+            has_defers,
+            operation.root_kind,
+            Default::default(),
+        )?;
+        let node = match traversal.find_best_plan()? {
+            Some(best_plan) => best_plan.fetch_dependency_graph.clone().process(
+                &processor,
+                &operation.variables,
+                operation.name.clone(),
+            )?,
+            None => None,
+        };
+        // Plans over lists of entities tend to repeat the same fetch subtrees, which are shared.
+        Ok(PlanNodeInterner::default().intern_plan(QueryPlan {
+            node: node.map(TopLevelPlanNode::from),
+            warnings: traversal.warnings().to_vec(),
+            has_introspection: operation.has_introspection,
+        }))
+    }
+
+    /// The named fragments of the given (normalized) operation that can be reused in the fetches
+    /// to the given subgraph, i.e. those whose type condition is defined in that subgraph. The other
+    /// fragments are reported to `warnings`.
+    pub(crate) fn reusable_fragments(
+        &self,
+        operation: &NormalizedOperation,
+        subgraph_name: &NodeStr,
+        warnings: &mut Vec<QueryPlanningWarning>,
+    ) -> Result<IndexMap<Name, Node<NormalizedFragment>>, FederationError> {
+        let subgraph_schema = self.federated_query_graph.schema_by_source(subgraph_name)?;
+        let mut reusable_fragments = IndexMap::new();
+        for (fragment_name, fragment) in operation.fragments.iter() {
+            if subgraph_schema
+                .try_get_type(fragment.type_condition_position.type_name().clone())
+                .is_some()
+            {
+                reusable_fragments.insert(fragment_name.clone(), fragment.clone());
+            } else {
+                warnings.push(QueryPlanningWarning::FragmentNotReused {
+                    fragment_name: fragment_name.clone(),
+                    subgraph_name: subgraph_name.clone(),
+                });
+            }
+        }
+        Ok(reusable_fragments)
+    }
+}
+
+/// Whether the given abstract type has some runtime type in a subgraph that isn't one of its
+/// runtime types in that subgraph, but is one in the supergraph. The query planner can't rely on
+/// the runtime types of such an abstract type being the same in all the subgraphs.
+fn has_inconsistent_runtime_types(
+    supergraph_schema: &ValidFederationSchema,
+    federated_query_graph: &QueryGraph,
+    type_position: &AbstractTypeDefinitionPosition,
+) -> Result<bool, FederationError> {
+    let supergraph_runtime_types =
+        supergraph_schema.cached_possible_runtime_types(type_position.clone().into())?;
+    for (_, subgraph_schema) in federated_query_graph.subgraphs() {
+        let Some(subgraph_type_position) =
+            subgraph_schema.try_get_type(type_position.type_name().clone())
+        else {
+            continue;
+        };
+        let Ok(subgraph_type_position): Result<CompositeTypeDefinitionPosition, _> =
+            subgraph_type_position.try_into()
+        else {
+            continue;
+        };
+        let subgraph_runtime_types =
+            subgraph_schema.cached_possible_runtime_types(subgraph_type_position)?;
+        let is_inconsistent = supergraph_runtime_types.iter().any(|runtime_type| {
+            subgraph_schema
+                .try_get_type(runtime_type.type_name.clone())
+                .is_some()
+                && !subgraph_runtime_types.contains(runtime_type)
+        });
+        if is_inconsistent {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::FederationError;
+    use crate::query_graph::EnabledOverrideConditions;
+    use crate::query_plan::query_planner::{
+        QueryPlanner, QueryPlannerConfig, QueryPlanningBudget, QueryPlanningMeter,
+        QueryPlanningWarning,
+    };
+    use crate::query_plan::{PlanNode, QueryPlan, TopLevelPlanNode};
+    use crate::schema::position::InterfaceTypeDefinitionPosition;
+    use crate::test_supergraph::supergraph_sdl;
+    use crate::Supergraph;
+    use apollo_compiler::validation::Valid;
+    use apollo_compiler::{name, ExecutableDocument};
+
+    fn interface_object_supergraph() -> String {
+        supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type A implements I
+              @join__implements(graph: S1, interface: "I")
+              @join__type(graph: S1, key: "id", resolvable: false)
+            {
+              id: ID!
+              x: Int @join__field
+            }
+
+            interface I
+              @join__type(graph: S1, key: "id", resolvable: false)
+              @join__type(graph: S2, key: "id", resolvable: false, isInterfaceObject: true)
+            {
+              id: ID!
+              x: Int @join__field(graph: S2)
+            }
+
+
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              i: I @join__field(graph: S1)
+            }
+            "#,
+        )
+    }
+
+    const OPERATION: &str = r#"
+        query Q {
+          i {
+            ...F
+            ... on A @defer {
+              x
+            }
+          }
+        }
+
+        fragment F on I {
+          id
+        }
+    "#;
+
+    fn normalize(config: QueryPlannerConfig) -> Result<(String, usize), FederationError> {
+        let supergraph = Supergraph::new(&interface_object_supergraph())?;
+        let planner = QueryPlanner::new(&supergraph, config)?;
+        // The API schema currently only allows @defer on fields, so the document isn't validated.
+        let document = Valid::assume_valid(
+            ExecutableDocument::parse(planner.api_schema.schema(), OPERATION, "operation.graphql")
+                .unwrap(),
+        );
+        let operation = planner.normalize_operation(&document, Some("Q"))?;
+        Ok((operation.to_string(), operation.fragments.len()))
+    }
+
+    #[test]
+    fn collects_interface_object_types() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&interface_object_supergraph())?;
+        let planner = QueryPlanner::new(&supergraph, Default::default())?;
+        assert!(planner.interface_types_with_interface_objects.contains(
+            &InterfaceTypeDefinitionPosition {
+                type_name: name!("I"),
+            }
+        ));
+        assert!(planner
+            .abstract_types_with_inconsistent_runtime_types
+            .is_empty());
+        assert_eq!(
+            planner
+                .subgraph_federation_spec_definitions
+                .keys()
+                .collect::<Vec<_>>(),
+            ["S1", "S2"]
+        );
+
+        let mut config = QueryPlannerConfig::default();
+        config.incremental_delivery.enable_defer = true;
+        config.debug.bypass_planner_for_single_subgraph = true;
+        assert!(QueryPlanner::new(&supergraph, config).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn normalizes_operation_according_to_config() -> Result<(), FederationError> {
+        let (operation, fragment_count) = normalize(Default::default())?;
+        assert_eq!(fragment_count, 1);
+        insta::assert_snapshot!(operation, @r###"
+        query Q {
+          i {
+            id
+            ... on A {
+              x
+            }
+          }
+        }
+        "###);
+
+        let mut config = QueryPlannerConfig::default();
+        config.incremental_delivery.enable_defer = true;
+        config.generate_query_fragments = true;
+        let (operation, fragment_count) = normalize(config)?;
+        assert_eq!(fragment_count, 0);
+        insta::assert_snapshot!(operation, @r###"
+        query Q {
+          i {
+            id
+            ... on A @defer {
+              x
+            }
+          }
+        }
+        "###);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_planning_warnings() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&interface_object_supergraph())?;
+        let planner = QueryPlanner::new(&supergraph, Default::default())?;
+        let document = Valid::assume_valid(
+            ExecutableDocument::parse(
+                planner.api_schema.schema(),
+                r#"
+                query Q {
+                  i {
+                    __typename
+                    id
+                    ...F
+                    ... on A @defer {
+                      x
+                    }
+                  }
+                }
+
+                fragment F on A {
+                  id
+                }
+                "#,
+                "operation.graphql",
+            )
+            .unwrap(),
+        );
+        let mut warnings = Vec::new();
+        let operation =
+            planner.normalize_operation_with_warnings(&document, Some("Q"), &mut warnings)?;
+        planner.reusable_fragments(&operation, &"S2".into(), &mut warnings)?;
+        assert_eq!(
+            warnings,
+            [
+                QueryPlanningWarning::UnsupportedDirectiveDropped {
+                    directive_name: name!("defer"),
+                },
+                QueryPlanningWarning::SiblingTypenameFallback {
+                    type_name: name!("I"),
+                },
+                QueryPlanningWarning::FragmentNotReused {
+                    fragment_name: name!("F"),
+                    subgraph_name: "S2".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            warnings.iter().map(|w| w.code()).collect::<Vec<_>>(),
+            [
+                "UNSUPPORTED_DIRECTIVE_DROPPED",
+                "SIBLING_TYPENAME_FALLBACK",
+                "FRAGMENT_NOT_REUSED",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_sibling_typenames_if_configured() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&interface_object_supergraph())?;
+        let normalize = |config| -> Result<String, FederationError> {
+            let planner = QueryPlanner::new(&supergraph, config)?;
+            let document = ExecutableDocument::parse_and_validate(
+                planner.api_schema.schema(),
+                "query Q { i { id ... on A { __typename x } } }",
+                "operation.graphql",
+            )
+            .unwrap();
+            let mut warnings = Vec::new();
+            let operation =
+                planner.normalize_operation_with_warnings(&document, Some("Q"), &mut warnings)?;
+            assert!(warnings.is_empty());
+            Ok(operation.selection_set.to_string())
+        };
+
+        assert_eq!(
+            normalize(Default::default())?,
+            "{ i { id ... on A { x } } }"
+        );
+        let config = QueryPlannerConfig {
+            optimize_sibling_typenames: false,
+            ..Default::default()
+        };
+        assert_eq!(normalize(config)?, "{ i { id ... on A { __typename x } } }");
+        Ok(())
+    }
+
+    #[test]
+    fn enforces_planning_budgets() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&interface_object_supergraph())?;
+        let planner = QueryPlanner::new(&supergraph, Default::default())?;
+        let document = ExecutableDocument::parse_and_validate(
+            planner.api_schema.schema(),
+            "query Q { i { id ... on A { x } } }",
+            "operation.graphql",
+        )
+        .unwrap();
+        let normalize = |budget: QueryPlanningBudget| {
+            let mut meter = QueryPlanningMeter::new(budget);
+            let result = planner.normalize_operation_with_meter(
+                &document,
+                Some("Q"),
+                &mut Vec::new(),
+                &mut meter,
+            );
+            (result, meter.statistics().clone())
+        };
+
+        let (result, statistics) = normalize(Default::default());
+        assert!(result.is_ok());
+        assert_eq!(statistics.peak_selections, 4);
+
+        let (result, statistics) = normalize(QueryPlanningBudget {
+            max_selections: Some(3),
+            ..Default::default()
+        });
+        let Err(FederationError::SingleFederationError(error)) = result else {
+            panic!("normalization should go over the selections budget");
+        };
+        assert_eq!(error.code().code(), "QUERY_PLANNING_BUDGET_EXCEEDED");
+        assert_eq!(
+            error.to_string(),
+            "Query planning built a selection set of 4 selections, over the budget of 3"
+        );
+        // Statistics are still measured when going over budget.
+        assert_eq!(statistics.peak_selections, 4);
+        Ok(())
+    }
+
+    fn single_subgraph_supergraph() -> String {
+        supergraph_sdl(
+            &["S1"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+            {
+              t(id: ID!): T
+            }
+
+            type T
+              @join__type(graph: S1)
+            {
+              id: ID!
+              name: String
+            }
+            "#,
+        )
+    }
+
+    #[test]
+    fn rejects_stream() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&interface_object_supergraph())?;
+        let planner = QueryPlanner::new(&supergraph, Default::default())?;
+        let normalize = |operation: &str| {
+            let document = ExecutableDocument::parse_and_validate(
+                planner.api_schema.schema(),
+                operation,
+                "operation.graphql",
+            )
+            .unwrap();
+            planner.normalize_operation(&document, None)
+        };
+
+        let Err(error) = normalize("{ i { id ... on I { x @stream(initialCount: 1) } } }") else {
+            panic!("@stream should be rejected");
+        };
+        assert_eq!(
+            error.to_string(),
+            "Directive \"@stream\" (used on field \"I.x\") is not supported by the query planner"
+        );
+        assert!(normalize("{ i { x @stream(if: false) } }").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn bypasses_planner_for_single_subgraph() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&single_subgraph_supergraph())?;
+        let mut config = QueryPlannerConfig::default();
+        config.debug.bypass_planner_for_single_subgraph = true;
+        let planner = QueryPlanner::new(&supergraph, config)?;
+        let document = ExecutableDocument::parse_and_validate(
+            planner.api_schema.schema(),
+            r#"
+              query Q($id: ID!, $withTypes: Boolean!) {
+                __schema { queryType { name } types @include(if: $withTypes) { name } }
+                t(id: $id) { ...F }
+              }
+
+              fragment F on T {
+                __typename
+                name
+                kind: __typename
+              }
+            "#,
+            "operation.graphql",
+        )
+        .unwrap();
+        let operation = planner.normalize_operation(&document, Some("Q"))?;
+        let Some(plan) = planner.single_subgraph_plan(&operation)? else {
+            panic!("the planner should be bypassed");
+        };
+        let Some(TopLevelPlanNode::Fetch(fetch_node)) = &plan.node else {
+            panic!("the plan should be a single fetch");
+        };
+        assert!(plan.has_introspection);
+        assert_eq!(fetch_node.subgraph_name, "S1");
+        assert_eq!(fetch_node.variable_usages, [name!("id")]);
+        assert_eq!(
+            fetch_node.operation_document.to_string(),
+            "query Q($id: ID!) {\n  t(id: $id) {\n    __typename\n    name\n    kind: __typename\n  }\n}\n"
+        );
+
+        // Operations only selecting introspection fields don't fetch anything.
+        let document = ExecutableDocument::parse_and_validate(
+            planner.api_schema.schema(),
+            "{ __type(name: \"T\") { name } }",
+            "operation.graphql",
+        )
+        .unwrap();
+        let operation = planner.normalize_operation(&document, None)?;
+        let plan = planner.single_subgraph_plan(&operation)?;
+        assert!(plan.is_some_and(|plan| plan.is_empty() && plan.has_introspection));
+
+        // The planner isn't bypassed unless configured to, or for supergraphs with more subgraphs.
+        let planner = QueryPlanner::new(&supergraph, Default::default())?;
+        let operation = planner.normalize_operation(&document, None)?;
+        assert!(planner.single_subgraph_plan(&operation)?.is_none());
+        let mut config = QueryPlannerConfig::default();
+        config.debug.bypass_planner_for_single_subgraph = true;
+        assert!(
+            QueryPlanner::new(&Supergraph::new(&interface_object_supergraph())?, config)?
+                .single_subgraph_name
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn plans_operations_emptied_by_normalization() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&single_subgraph_supergraph())?;
+        let mut config = QueryPlannerConfig::default();
+        config.debug.bypass_planner_for_single_subgraph = true;
+        let planner = QueryPlanner::new(&supergraph, config)?;
+        let plan_operation = |operation: &str| -> Result<QueryPlan, FederationError> {
+            let document = ExecutableDocument::parse_and_validate(
+                planner.api_schema.schema(),
+                operation,
+                "operation.graphql",
+            )
+            .unwrap();
+            let operation = planner.normalize_operation(&document, None)?;
+            Ok(planner.single_subgraph_plan(&operation)?.unwrap())
+        };
+
+        let plan = plan_operation(
+            r#"{ t(id: "1") @skip(if: true) { name } ... @include(if: false) { other: t(id: "2") { id } } }"#,
+        )?;
+        assert!(plan.is_empty());
+        assert!(!plan.has_introspection());
+        assert_eq!(plan.to_json(), QueryPlan::empty().to_json());
+        assert_eq!(plan.to_json(), serde_json::json!({ "kind": "QueryPlan" }));
+        assert_eq!(plan.to_pretty_string(), "QueryPlan {\n}");
+
+        // Fields left without sub-selections select `__typename` instead.
+        let plan = plan_operation(r#"{ t(id: "1") { name @include(if: false) } }"#)?;
+        let Some(TopLevelPlanNode::Fetch(fetch_node)) = &plan.node else {
+            panic!("the plan should be a single fetch");
+        };
+        assert_eq!(
+            fetch_node.operation_document.to_string(),
+            "{\n  t(id: \"1\") {\n    __typename\n  }\n}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn plans_progressive_overrides_according_to_enabled_labels() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              a: Int @join__field(graph: S1, override: "S2", overrideLabel: "percent(50)") @join__field(graph: S2, overrideLabel: "percent(50)")
+              b: Int @join__field(graph: S2)
+            }
+            "#,
+        ))?;
+        let planner = QueryPlanner::new(&supergraph, Default::default())?;
+        let document = ExecutableDocument::parse_and_validate(
+            planner.api_schema.schema(),
+            "{ a }",
+            "operation.graphql",
+        )
+        .unwrap();
+        let operation = planner.normalize_operation(&document, None)?;
+        let fetched_subgraph = |override_conditions| -> Result<String, FederationError> {
+            let plan = planner.build_query_plan(&operation, override_conditions)?;
+            let Some(TopLevelPlanNode::Fetch(fetch_node)) = &plan.node else {
+                panic!("the plan should be a single fetch");
+            };
+            assert_eq!(fetch_node.operation_document.to_string(), "{\n  a\n}\n");
+            Ok(fetch_node.subgraph_name.to_string())
+        };
+
+        assert_eq!(
+            fetched_subgraph(EnabledOverrideConditions::new(["percent(50)".to_owned()]))?,
+            "S1"
+        );
+        assert_eq!(
+            fetched_subgraph(EnabledOverrideConditions::default())?,
+            "S2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn picks_the_cheapest_plan_for_shareable_fields() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              a: Int
+              b: Int
+              c: Int @join__field(graph: S2)
+            }
+            "#,
+        ))?;
+        let planner = QueryPlanner::new(&supergraph, Default::default())?;
+        let fetches = |operation: &str| -> Result<Vec<String>, FederationError> {
+            let document = ExecutableDocument::parse_and_validate(
+                planner.api_schema.schema(),
+                operation,
+                "operation.graphql",
+            )
+            .unwrap();
+            let operation = planner.normalize_operation(&document, None)?;
+            let plan = planner.build_query_plan(&operation, Default::default())?;
+            let fetch_nodes = match &plan.node {
+                Some(TopLevelPlanNode::Fetch(fetch_node)) => vec![fetch_node.clone()],
+                Some(TopLevelPlanNode::Parallel(parallel_node)) => parallel_node
+                    .nodes
+                    .iter()
+                    .map(|node| match node {
+                        PlanNode::Fetch(fetch_node) => (**fetch_node).clone(),
+                        _ => panic!("the plan should only have fetches"),
+                    })
+                    .collect(),
+                _ => panic!("the plan should only have fetches"),
+            };
+            Ok(fetch_nodes
+                .iter()
+                .map(|fetch_node| {
+                    let document = fetch_node.operation_document.to_string();
+                    format!(
+                        "{}: {}",
+                        fetch_node.subgraph_name,
+                        document.split_whitespace().collect::<Vec<_>>().join(" "),
+                    )
+                })
+                .collect())
+        };
+
+        // Fetching all the fields from the same subgraph saves a fetch.
+        let ab_fetches = fetches("{ a b }")?;
+        assert_eq!(ab_fetches.len(), 1);
+        assert!(ab_fetches[0].ends_with(": { a b }"));
+        assert_eq!(fetches("{ a c }")?, ["S2: { a c }"]);
+        assert_eq!(fetches("{ c a }")?, ["S2: { c a }"]);
+        Ok(())
+    }
+
+    #[test]
+    fn limits_evaluated_plans_if_configured() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              a: Int
+              b: Int
+            }
+            "#,
+        ))?;
+        let plan = |config: QueryPlannerConfig| -> Result<QueryPlan, FederationError> {
+            let planner = QueryPlanner::new(&supergraph, config)?;
+            let document = ExecutableDocument::parse_and_validate(
+                planner.api_schema.schema(),
+                "{ a b }",
+                "operation.graphql",
+            )
+            .unwrap();
+            let operation = planner.normalize_operation(&document, None)?;
+            planner.build_query_plan(&operation, Default::default())
+        };
+
+        let default_plan = plan(Default::default())?;
+        assert!(default_plan.warnings().is_empty());
+        let expected_plan =
+            "QueryPlan {\n  Fetch(service: \"S2\") {\n    {\n      a\n      b\n    }\n  },\n}";
+        assert_eq!(default_plan.to_pretty_string(), expected_plan);
+
+        // Each of the 2 fields can be fetched from either subgraph, so there are 4 possible plans,
+        // and the limits still give the cheapest one here.
+        let mut config = QueryPlannerConfig::default();
+        config.debug.max_evaluated_plans = 1;
+        let reduced_plan = plan(config)?;
+        assert_eq!(
+            reduced_plan.warnings(),
+            [QueryPlanningWarning::PossiblePlansReduced {
+                possible_plans: 4,
+                max_evaluated_plans: 1,
+            }]
+        );
+        assert_eq!(reduced_plan.to_pretty_string(), expected_plan);
+
+        let limited_plan = plan(QueryPlannerConfig {
+            max_evaluated_plan_options: Some(1),
+            ..Default::default()
+        })?;
+        assert_eq!(
+            limited_plan.warnings(),
+            [QueryPlanningWarning::EvaluatedPlanOptionsLimitReached {
+                evaluated_options: 2,
+                max_evaluated_plan_options: 1,
+            }]
+        );
+        assert_eq!(limited_plan.to_pretty_string(), expected_plan);
+        Ok(())
+    }
+
+    #[test]
+    fn explores_options_in_parallel_if_configured() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&supergraph_sdl(
+            &["S1", "S2"],
+            r#"
+            type Query
+              @join__type(graph: S1)
+              @join__type(graph: S2)
+            {
+              a: Int
+              b: Int
+              c: Int @join__field(graph: S2)
+            }
+            "#,
+        ))?;
+        let plan = |parallel_exploration| -> Result<String, FederationError> {
+            let planner = QueryPlanner::new(
+                &supergraph,
+                QueryPlannerConfig {
+                    parallel_exploration,
+                    ..Default::default()
+                },
+            )?;
+            let document = ExecutableDocument::parse_and_validate(
+                planner.api_schema.schema(),
+                "{ a b c }",
+                "operation.graphql",
+            )
+            .unwrap();
+            let operation = planner.normalize_operation(&document, None)?;
+            Ok(planner
+                .build_query_plan(&operation, Default::default())?
+                .to_pretty_string())
+        };
+
+        let sequential_plan = plan(false)?;
+        assert_eq!(
+            sequential_plan,
+            "QueryPlan {\n  Fetch(service: \"S2\") {\n    {\n      a\n      b\n      c\n    }\n  },\n}"
+        );
+        assert_eq!(plan(true)?, sequential_plan);
+        Ok(())
+    }
+
+    #[test]
+    fn shares_identical_entity_fetches() -> Result<(), FederationError> {
+        let supergraph = Supergraph::new(&supergraph_sdl(
+            &["PRODUCTS", "REVIEWS"],
+            r#"
+            type Query
+              @join__type(graph: PRODUCTS)
+              @join__type(graph: REVIEWS)
+            {
+              products: [Product] @join__field(graph: PRODUCTS)
+            }
+
+            type Product
+              @join__type(graph: PRODUCTS, key: "upc")
+              @join__type(graph: REVIEWS, key: "upc")
+            {
+              upc: ID!
+              name: String @join__field(graph: PRODUCTS)
+              reviews: [Int] @join__field(graph: REVIEWS)
+            }
+            "#,
+        ))?;
+        let planner = QueryPlanner::new(&supergraph, Default::default())?;
+        let document = ExecutableDocument::parse_and_validate(
+            planner.api_schema.schema(),
+            "{ top: products { reviews } bottom: products { reviews } }",
+            "operation.graphql",
+        )
+        .unwrap();
+        let operation = planner.normalize_operation(&document, None)?;
+        let plan = planner.build_query_plan(&operation, Default::default())?;
+
+        // Both lists of products get their reviews from the same entity fetch, only merged at
+        // different paths, so that fetch is shared.
+        let Some(TopLevelPlanNode::Sequence(sequence)) = &plan.node else {
+            panic!("the plan should be a sequence");
+        };
+        let PlanNode::Parallel(parallel) = &sequence.nodes[1] else {
+            panic!("the entity fetches should be in parallel");
+        };
+        let entity_fetches = parallel
+            .nodes
+            .iter()
+            .map(|node| match node {
+                PlanNode::Flatten(flatten) => {
+                    assert!(matches!(flatten.node, PlanNode::Fetch(_)));
+                    flatten.node.ptr()
+                }
+                _ => panic!("the entity fetches should be flattened"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(entity_fetches.len(), 2);
+        assert_eq!(entity_fetches[0], entity_fetches[1]);
+        assert_eq!(
+            plan.to_json_with_references()["sharedNodes"]
+                .as_array()
+                .map(Vec::len),
+            Some(1)
+        );
+        Ok(())
+    }
+}
@@ -1,52 +1,64 @@
 use crate::error::FederationError;
-use crate::query_graph::condition_resolver::CachingConditionResolver;
+use crate::query_graph::condition_resolver::{
+    ConditionResolution, ConditionResolutionCacheResult, ConditionResolver, ConditionResolverCache,
+};
 use crate::query_graph::graph_path::{
-    ClosedBranch, ClosedPath, OpPathElement, OpenBranch, SimultaneousPaths,
+    ClosedBranch, ClosedPath, ExcludedConditions, ExcludedDestinations, OpGraphPath,
+    OpGraphPathContext, OpPathElement, OpenBranch, SimultaneousPaths,
     SimultaneousPathsWithLazyIndirectPaths,
 };
 use crate::query_graph::path_tree::OpPathTree;
-use crate::query_graph::{QueryGraph, QueryGraphNodeType};
+use crate::query_graph::{EnabledOverrideConditions, QueryGraph, QueryGraphNodeType};
 use crate::query_plan::fetch_dependency_graph::{compute_nodes_for_tree, FetchDependencyGraph};
 use crate::query_plan::fetch_dependency_graph_processor::{
     FetchDependencyGraphToCostProcessor, FetchDependencyGraphToQueryPlanProcessor,
 };
+use crate::query_plan::generate::{generate_all_plans_and_find_best, GeneratedPlan};
 use crate::query_plan::operation::{
     NormalizedOperation, NormalizedSelection, NormalizedSelectionSet,
 };
-use crate::query_plan::query_planner::QueryPlannerConfig;
+use crate::query_plan::query_planner::{
+    ChosenPlan, ConsideredPath, PathRejectionReason, PlanningTrace, QueryPlannerConfig,
+    QueryPlanningMeter, QueryPlanningWarning, RejectedPath,
+};
 use crate::query_plan::QueryPlanCost;
 use crate::schema::position::ObjectTypeDefinitionPosition;
 use crate::schema::position::SchemaRootDefinitionKind;
 use crate::schema::position::{AbstractTypeDefinitionPosition, OutputTypeDefinitionPosition};
 use crate::schema::ValidFederationSchema;
 use indexmap::IndexSet;
-use petgraph::graph::NodeIndex;
+use petgraph::graph::{EdgeIndex, NodeIndex};
 use std::sync::Arc;
 
 // PORT_NOTE: Named `PlanningParameters` in the JS codebase, but there was no particular reason to
 // leave out to the `Query` prefix, so it's been added for consistency. Similar to `GraphPath`, we
 // don't have a distinguished type for when the head is a root vertex, so we instead check this at
 // runtime (introducing the new field `head_must_be_root`).
+#[derive(Clone)]
 pub(crate) struct QueryPlanningParameters {
     /// The supergraph schema that generated the federated query graph.
-    supergraph_schema: ValidFederationSchema,
+    pub(crate) supergraph_schema: ValidFederationSchema,
     /// The federated query graph used for query planning.
-    federated_query_graph: Arc<QueryGraph>,
+    pub(crate) federated_query_graph: Arc<QueryGraph>,
     /// The operation to be query planned.
-    operation: Arc<NormalizedOperation>,
+    pub(crate) operation: Arc<NormalizedOperation>,
     /// A processor for converting fetch dependency graphs to query plans.
-    processor: FetchDependencyGraphToQueryPlanProcessor,
+    pub(crate) processor: FetchDependencyGraphToQueryPlanProcessor,
     /// The query graph node at which query planning begins.
-    head: NodeIndex,
+    pub(crate) head: NodeIndex,
     /// Whether the head must be a root node for query planning.
-    head_must_be_root: bool,
+    pub(crate) head_must_be_root: bool,
     /// A set of the names of interface or union types that have inconsistent "runtime types" across
     /// subgraphs.
     // PORT_NOTE: Named `inconsistentAbstractTypesRuntimes` in the JS codebase, which was slightly
     // confusing.
-    abstract_types_with_inconsistent_runtime_types: Arc<IndexSet<AbstractTypeDefinitionPosition>>,
+    pub(crate) abstract_types_with_inconsistent_runtime_types:
+        Arc<IndexSet<AbstractTypeDefinitionPosition>>,
     /// The configuration for the query planner.
-    config: Arc<QueryPlannerConfig>,
+    pub(crate) config: Arc<QueryPlannerConfig>,
+    /// The progressive override labels enabled for this operation. Edges whose override condition
+    /// doesn't match these labels are ignored during query planning.
+    pub(crate) override_conditions: Arc<EnabledOverrideConditions>,
     // TODO: When `PlanningStatistics` is ported, add a field for it.
 }
 
@@ -65,8 +77,9 @@ pub(crate) struct QueryPlanningTraversal {
     /// True if this query planning is at top-level (note that query planning can recursively start
     /// further query planning).
     is_top_level: bool,
-    /// A query plan resolver for edge conditions that caches the outcome per edge.
-    condition_resolver: CachingConditionResolver,
+    /// The cached resolutions of edge conditions, which this traversal resolves by planning them
+    /// (see its `ConditionResolver` implementation).
+    resolver_cache: ConditionResolverCache,
     /// The stack of open branches left to plan, along with state indicating the next selection to
     /// plan for them.
     // PORT_NOTE: The `stack` in the JS codebase only contained one selection per stack entry, but
@@ -78,6 +91,14 @@ pub(crate) struct QueryPlanningTraversal {
     closed_branches: Vec<ClosedBranch>,
     /// The best plan found as a result of query planning.
     best_plan: Option<BestQueryPlanInfo>,
+    /// The warnings about the best plan, e.g. when it may be suboptimal because some plans weren't
+    /// evaluated.
+    warnings: Vec<QueryPlanningWarning>,
+    /// Measures the resources used by this query planning, and enforces its budget.
+    meter: QueryPlanningMeter,
+    /// The trace of this query planning, recorded if
+    /// `QueryPlannerDebugConfig::record_planning_trace` is set.
+    trace: Option<PlanningTrace>,
 }
 
 struct OpenBranchAndSelections {
@@ -87,18 +108,104 @@ struct OpenBranchAndSelections {
     selections: Vec<NormalizedSelection>,
 }
 
-struct BestQueryPlanInfo {
+pub(crate) struct BestQueryPlanInfo {
     /// The fetch dependency graph for this query plan.
-    fetch_dependency_graph: FetchDependencyGraph,
+    pub(crate) fetch_dependency_graph: FetchDependencyGraph,
     /// The path tree for the closed branch options chosen for this query plan.
-    path_tree: OpPathTree,
+    pub(crate) path_tree: Arc<OpPathTree>,
     /// The cost of this query plan.
-    cost: QueryPlanCost,
+    pub(crate) cost: QueryPlanCost,
 }
 
 impl QueryPlanningTraversal {
-    fn find_best_plan(&mut self) -> Result<Option<&BestQueryPlanInfo>, FederationError> {
+    /// Starts query planning for the given selection set from the head of the parameters, with
+    /// the given root kind (which determines how root fetches are created) and meter.
+    pub(crate) fn new(
+        parameters: QueryPlanningParameters,
+        selection_set: &NormalizedSelectionSet,
+        has_defers: bool,
+        root_kind: SchemaRootDefinitionKind,
+        meter: QueryPlanningMeter,
+    ) -> Result<Self, FederationError> {
+        let mut traversal = Self::without_open_branches(parameters, has_defers, root_kind, meter);
+        traversal.open_initial_branch(
+            selection_set,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )?;
+        Ok(traversal)
+    }
+
+    fn without_open_branches(
+        parameters: QueryPlanningParameters,
+        has_defers: bool,
+        root_kind: SchemaRootDefinitionKind,
+        meter: QueryPlanningMeter,
+    ) -> Self {
+        let trace = parameters
+            .config
+            .debug
+            .record_planning_trace
+            .then(PlanningTrace::default);
+        Self {
+            cost_processor: FetchDependencyGraphToCostProcessor::new(
+                parameters.config.cost.clone(),
+            ),
+            is_top_level: parameters.head_must_be_root,
+            parameters,
+            root_kind,
+            has_defers,
+            starting_id_generation: 0,
+            resolver_cache: ConditionResolverCache::new(),
+            open_branches: vec![],
+            closed_branches: vec![],
+            best_plan: None,
+            warnings: vec![],
+            meter,
+            trace,
+        }
+    }
+
+    /// Opens the branch planning the given selection set from the head of the parameters, whose
+    /// paths start with the given context and exclusions.
+    fn open_initial_branch(
+        &mut self,
+        selection_set: &NormalizedSelectionSet,
+        context: OpGraphPathContext,
+        excluded_destinations: ExcludedDestinations,
+        excluded_conditions: ExcludedConditions,
+    ) -> Result<(), FederationError> {
+        if selection_set.selections.is_empty() {
+            return Ok(());
+        }
+        let initial_path = OpGraphPath::new(
+            self.parameters.federated_query_graph.clone(),
+            self.parameters.head,
+        )?;
+        let override_conditions = self.parameters.override_conditions.clone();
+        let id_generator = self.parameters.operation.id_generator.clone();
+        let initial_options = SimultaneousPathsWithLazyIndirectPaths::create_initial_options(
+            initial_path,
+            context,
+            self,
+            excluded_destinations,
+            excluded_conditions,
+            override_conditions,
+            id_generator,
+        )?;
+        self.open_branches.push(OpenBranchAndSelections {
+            open_branch: OpenBranch(initial_options),
+            selections: selection_set.selections.values().cloned().rev().collect(),
+        });
+        Ok(())
+    }
+
+    /// Explores the options for each branch of the selection set, and returns the cheapest plan
+    /// they can be combined into (or `None` if there is nothing to plan).
+    pub(crate) fn find_best_plan(&mut self) -> Result<Option<&BestQueryPlanInfo>, FederationError> {
         while let Some(mut current_branch) = self.open_branches.pop() {
+            self.meter.check_duration()?;
             let Some(current_selection) = current_branch.selections.pop() else {
                 return Err(FederationError::internal(
                     "Sub-stack unexpectedly empty during query plan traversal",
@@ -124,6 +231,18 @@ impl QueryPlanningTraversal {
         return Ok(self.best_plan.as_ref());
     }
 
+    pub(crate) fn warnings(&self) -> &[QueryPlanningWarning] {
+        &self.warnings
+    }
+
+    pub(crate) fn meter(&self) -> &QueryPlanningMeter {
+        &self.meter
+    }
+
+    pub(crate) fn trace(&self) -> Option<&PlanningTrace> {
+        self.trace.as_ref()
+    }
+
     /// Returns whether to terminate planning immediately, and any new open branches to push onto
     /// the stack.
     fn handle_open_branch(
@@ -138,12 +257,21 @@ impl QueryPlanningTraversal {
             let followups_for_option = option.advance_with_operation_element(
                 self.parameters.supergraph_schema.clone(),
                 &operation_element,
-                &mut self.condition_resolver,
+                selection.selection_set()?,
+                self,
             )?;
             let Some(followups_for_option) = followups_for_option else {
                 // There is no valid way to advance the current operation element from this option
                 // so this option is a dead branch that cannot produce a valid query plan. So we
                 // simply ignore it and rely on other options.
+                if let Some(trace) = &mut self.trace {
+                    trace.rejected_paths.push(RejectedPath {
+                        path: option.paths.to_string(),
+                        reason: PathRejectionReason::CannotAdvance {
+                            element: operation_element.to_string(),
+                        },
+                    });
+                }
                 continue;
             };
             if followups_for_option.is_empty() {
@@ -151,6 +279,15 @@ impl QueryPlanningTraversal {
                 no_followups = true;
                 break;
             }
+            self.meter.record_graph_paths(followups_for_option.len())?;
+            if let Some(trace) = &mut self.trace {
+                trace
+                    .considered_paths
+                    .extend(followups_for_option.iter().map(|followup| ConsideredPath {
+                        element: operation_element.to_string(),
+                        path: followup.paths.to_string(),
+                    }));
+            }
             new_options.extend(followups_for_option);
             if let Some(options_limit) = self.parameters.config.debug.paths_limit {
                 if new_options.len() > options_limit as usize {
@@ -306,12 +443,51 @@ impl QueryPlanningTraversal {
         Ok(())
     }
 
+    /// Whether the given selection set is guaranteed to be planned entirely within the subgraph of
+    /// each of the given nodes, in which case its options don't need to be explored. This is the
+    /// case when no edge reachable from those nodes leaves their subgraph, and the selection set
+    /// doesn't use abstract types whose runtime types differ across subgraphs (which may require
+    /// type explosion).
+    // PORT_NOTE: Named `selectionSetIsFullyLocalFromAllVertices` in the JS codebase.
     fn selection_set_is_fully_local_from_all_nodes(
         &self,
-        _selection: &NormalizedSelectionSet,
-        _nodes: &IndexSet<NodeIndex>,
+        selection: &NormalizedSelectionSet,
+        nodes: &IndexSet<NodeIndex>,
+    ) -> Result<bool, FederationError> {
+        for node in nodes {
+            let node_weight = self.parameters.federated_query_graph.node_weight(*node)?;
+            if node_weight.has_reachable_cross_subgraph_edges {
+                return Ok(false);
+            }
+        }
+        self.has_no_inconsistent_abstract_types(selection)
+    }
+
+    /// Whether no (nested) selection set of the given one has an abstract type with inconsistent
+    /// runtime types across subgraphs. Fragment spreads are conservatively assumed to have some.
+    fn has_no_inconsistent_abstract_types(
+        &self,
+        selection_set: &NormalizedSelectionSet,
     ) -> Result<bool, FederationError> {
-        todo!()
+        for selection in selection_set.selections.values() {
+            if let NormalizedSelection::FragmentSpread(_) = selection {
+                return Ok(false);
+            }
+            let Some(sub_selection_set) = selection.selection_set()? else {
+                continue;
+            };
+            let type_name = sub_selection_set.type_position.type_name();
+            if self
+                .parameters
+                .abstract_types_with_inconsistent_runtime_types
+                .iter()
+                .any(|abstract_type| abstract_type.type_name() == type_name)
+                || !self.has_no_inconsistent_abstract_types(sub_selection_set)?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
     }
 
     fn compute_best_plan_from_closed_branches(&mut self) -> Result<(), FederationError> {
@@ -322,7 +498,215 @@ impl QueryPlanningTraversal {
         self.sort_options_in_closed_branches()?;
         self.reduce_options_if_needed();
 
-        todo!() // the rest of the owl
+        // All the branches with a single option are part of every plan, so we plan them once
+        // upfront. Since branches are sorted by decreasing number of options, they are at the end.
+        let first_single_option_branch = self
+            .closed_branches
+            .iter()
+            .position(|branch| branch.0.len() <= 1)
+            .unwrap_or(self.closed_branches.len());
+        let graph = &self.parameters.federated_query_graph;
+        let head = self.parameters.head;
+        let mut initial_dependency_graph = self.new_dependency_graph();
+        let initial_tree = if first_single_option_branch == self.closed_branches.len() {
+            Arc::new(OpPathTree::new(graph.clone(), head))
+        } else {
+            let single_option_paths = self.closed_branches[first_single_option_branch..]
+                .iter()
+                .flat_map(|branch| &branch.0)
+                .flat_map(|option| option.flatten())
+                .collect::<Vec<_>>();
+            let initial_tree = Arc::new(OpPathTree::from_op_paths(
+                graph.clone(),
+                head,
+                &single_option_paths,
+            )?);
+            self.updated_dependency_graph(&mut initial_dependency_graph, &initial_tree)?;
+            if first_single_option_branch == 0 {
+                // There is only one possible plan.
+                let cost = self.cost_processor.process(&mut initial_dependency_graph)?;
+                self.on_new_plan(initial_dependency_graph, initial_tree, cost);
+                return Ok(());
+            }
+            initial_tree
+        };
+
+        let other_trees = self.option_trees(&self.closed_branches[..first_single_option_branch])?;
+
+        let max_evaluated_plan_options = self.parameters.config.max_evaluated_plan_options;
+        let generated = self.generate_best_plan(
+            (initial_dependency_graph, initial_tree),
+            other_trees,
+            max_evaluated_plan_options.map(|max| max as usize),
+        )?;
+        if let (false, Some(max_evaluated_plan_options)) =
+            (generated.is_exhaustive, max_evaluated_plan_options)
+        {
+            self.warnings
+                .push(QueryPlanningWarning::EvaluatedPlanOptionsLimitReached {
+                    evaluated_options: generated.evaluated_options,
+                    max_evaluated_plan_options,
+                });
+        }
+        let (dependency_graph, tree) = generated.plan;
+        self.on_new_plan(dependency_graph, tree, generated.cost);
+        Ok(())
+    }
+
+    /// Generates the plans obtained by adding one of the options of each of the given branches to
+    /// the given initial plan, and returns the cheapest one (see
+    /// `generate_all_plans_and_find_best()`).
+    ///
+    /// With the `rayon` feature and `QueryPlannerConfig::parallel_exploration`, the options of the
+    /// first branch are evaluated (along with the plans they lead to) concurrently. Each option is
+    /// then explored on its own, with its own `max_evaluated_plan_options` budget, and the results
+    /// are reduced in option order, so that ties go to the earliest option whatever the thread
+    /// scheduling.
+    fn generate_best_plan(
+        &self,
+        initial: (FetchDependencyGraph, Arc<OpPathTree>),
+        other_trees: Vec<Vec<Option<Arc<OpPathTree>>>>,
+        max_evaluated_plan_options: Option<usize>,
+    ) -> Result<GeneratedPlan<(FetchDependencyGraph, Arc<OpPathTree>)>, FederationError> {
+        #[cfg(feature = "rayon")]
+        if self.parameters.config.parallel_exploration && !other_trees.is_empty() {
+            use rayon::prelude::*;
+            let mut other_trees = other_trees;
+            let first_branch_trees = other_trees.remove(0);
+            let first_branch_options = first_branch_trees.len();
+            let generated_by_option = first_branch_trees
+                .into_par_iter()
+                .map(|tree| {
+                    let tree = tree.ok_or_else(|| {
+                        FederationError::internal("Unexpectedly missing option tree")
+                    })?;
+                    let (dependency_graph, initial_tree) = &initial;
+                    let mut dependency_graph = dependency_graph.clone();
+                    self.updated_dependency_graph(&mut dependency_graph, &tree)?;
+                    self.generate_best_plan_sequentially(
+                        (dependency_graph, initial_tree.merge(&tree)),
+                        other_trees.clone(),
+                        max_evaluated_plan_options,
+                    )
+                })
+                .collect::<Result<Vec<_>, FederationError>>()?;
+            let mut best: Option<GeneratedPlan<_>> = None;
+            let mut evaluated_options = first_branch_options;
+            let mut is_exhaustive = true;
+            for generated in generated_by_option {
+                evaluated_options += generated.evaluated_options;
+                is_exhaustive &= generated.is_exhaustive;
+                if best.as_ref().is_none_or(|best| generated.cost < best.cost) {
+                    best = Some(generated);
+                }
+            }
+            let best =
+                best.ok_or_else(|| FederationError::internal("Unexpectedly empty closed branch"))?;
+            return Ok(GeneratedPlan {
+                evaluated_options,
+                is_exhaustive,
+                ..best
+            });
+        }
+        self.generate_best_plan_sequentially(initial, other_trees, max_evaluated_plan_options)
+    }
+
+    fn generate_best_plan_sequentially(
+        &self,
+        initial: (FetchDependencyGraph, Arc<OpPathTree>),
+        other_trees: Vec<Vec<Option<Arc<OpPathTree>>>>,
+        max_evaluated_plan_options: Option<usize>,
+    ) -> Result<GeneratedPlan<(FetchDependencyGraph, Arc<OpPathTree>)>, FederationError> {
+        // The callbacks can't abort plan generation, so errors are stored to be returned once it's
+        // done.
+        let mut add_result = Ok(());
+        let mut cost_result = Ok(());
+        let generated = generate_all_plans_and_find_best(
+            initial,
+            other_trees,
+            |(dependency_graph, tree), to_add: Arc<OpPathTree>| {
+                let mut updated_dependency_graph = dependency_graph.clone();
+                if let Err(err) =
+                    self.updated_dependency_graph(&mut updated_dependency_graph, &to_add)
+                {
+                    add_result = Err(err);
+                }
+                (updated_dependency_graph, tree.merge(&to_add))
+            },
+            |(dependency_graph, _)| {
+                self.cost_processor
+                    .process(&mut dependency_graph.clone())
+                    .unwrap_or_else(|err| {
+                        cost_result = Err(err);
+                        QueryPlanCost::MAX
+                    })
+            },
+            |_, _, _| {},
+            max_evaluated_plan_options,
+        );
+        add_result?;
+        cost_result?;
+        Ok(generated)
+    }
+
+    /// Computes the path tree of each option of the given closed branches. The branches are
+    /// independent, so with the `rayon` feature, this can be done in parallel (see
+    /// `QueryPlannerConfig::parallel_exploration`), which still returns trees in branch order.
+    fn option_trees(
+        &self,
+        closed_branches: &[ClosedBranch],
+    ) -> Result<Vec<Vec<Option<Arc<OpPathTree>>>>, FederationError> {
+        let graph = &self.parameters.federated_query_graph;
+        let head = self.parameters.head;
+        let branch_option_trees = |branch: &ClosedBranch| {
+            branch
+                .0
+                .iter()
+                .map(|option| {
+                    let paths = option.flatten().collect::<Vec<_>>();
+                    Ok(Some(Arc::new(OpPathTree::from_op_paths(
+                        graph.clone(),
+                        head,
+                        &paths,
+                    )?)))
+                })
+                .collect::<Result<Vec<_>, FederationError>>()
+        };
+        #[cfg(feature = "rayon")]
+        if self.parameters.config.parallel_exploration {
+            use rayon::prelude::*;
+            return closed_branches
+                .par_iter()
+                .map(branch_option_trees)
+                .collect();
+        }
+        closed_branches.iter().map(branch_option_trees).collect()
+    }
+
+    /// Records the given plan as the best one, unless the best plan found so far is at most as
+    /// costly (in which case the existing plan is kept, so ties go to the plan found first).
+    fn on_new_plan(
+        &mut self,
+        fetch_dependency_graph: FetchDependencyGraph,
+        path_tree: Arc<OpPathTree>,
+        cost: QueryPlanCost,
+    ) {
+        if let Some(best_plan) = &self.best_plan {
+            if best_plan.cost <= cost {
+                return;
+            }
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.chosen_plan = Some(ChosenPlan {
+                path_tree: path_tree.to_string(),
+                cost,
+            });
+        }
+        self.best_plan = Some(BestQueryPlanInfo {
+            fetch_dependency_graph,
+            path_tree,
+            cost,
+        });
     }
 
     /// Remove closed branches that are known to be overridden by others.
@@ -343,6 +727,11 @@ impl QueryPlanningTraversal {
             for (i, to_check) in branch.0.iter().enumerate() {
                 if !Self::option_is_overriden(i, &to_check.paths, branch) {
                     pruned.0.push(to_check.clone());
+                } else if let Some(trace) = &mut self.trace {
+                    trace.rejected_paths.push(RejectedPath {
+                        path: to_check.paths.to_string(),
+                        reason: PathRejectionReason::Overridden,
+                    });
                 }
             }
 
@@ -446,7 +835,15 @@ impl QueryPlanningTraversal {
             .unwrap_or(0);
         // debug!("Query has {plan_count} possible plans");
 
-        let max_evaluated_plans = self.parameters.config.debug.max_evaluated_plans as usize;
+        let max_evaluated_plans = self.parameters.config.debug.max_evaluated_plans;
+        if plan_count > max_evaluated_plans as usize && self.closed_branches[0].0.len() > 1 {
+            self.warnings
+                .push(QueryPlanningWarning::PossiblePlansReduced {
+                    possible_plans: plan_count,
+                    max_evaluated_plans,
+                });
+        }
+        let max_evaluated_plans = max_evaluated_plans as usize;
         loop {
             // Note that if `self.closed_branches[0]` is our only branch, it's fine,
             // we'll continue to remove options from it (but that is beyond unlikely).
@@ -454,6 +851,14 @@ impl QueryPlanningTraversal {
             if plan_count <= max_evaluated_plans || first_branch_len <= 1 {
                 break;
             }
+            if let (Some(trace), Some(discarded)) =
+                (&mut self.trace, self.closed_branches[0].0.last())
+            {
+                trace.rejected_paths.push(RejectedPath {
+                    path: discarded.paths.to_string(),
+                    reason: PathRejectionReason::PlansReduced,
+                });
+            }
             Self::prune_and_reorder_first_branch(&mut self.closed_branches);
             plan_count -= plan_count / first_branch_len;
 
@@ -524,6 +929,8 @@ impl QueryPlanningTraversal {
             self.parameters.federated_query_graph.clone(),
             root_type,
             self.starting_id_generation,
+            self.parameters.config.max_subgraph_fetch_size,
+            self.parameters.operation.id_generator.clone(),
         )
     }
 
@@ -558,11 +965,16 @@ impl QueryPlanningTraversal {
                         )))
                     }
                 };
-                let fetch_dependency_node = dependency_graph.get_or_create_root_node(
-                    subgraph_name,
-                    self.root_kind,
-                    root_type,
-                )?;
+                let fetch_dependency_node = if self.root_kind == SchemaRootDefinitionKind::Mutation
+                {
+                    dependency_graph.new_serial_root_node(subgraph_name, root_type)?
+                } else {
+                    dependency_graph.get_or_create_root_node(
+                        subgraph_name,
+                        self.root_kind,
+                        root_type,
+                    )?
+                };
                 compute_nodes_for_tree(
                     dependency_graph,
                     &child.tree,
@@ -570,7 +982,7 @@ impl QueryPlanningTraversal {
                     Default::default(),
                     Default::default(),
                     Default::default(),
-                );
+                )?;
             }
         } else {
             let query_graph_node = path_tree.graph.node_weight(path_tree.node)?;
@@ -583,11 +995,15 @@ impl QueryPlanningTraversal {
                     ))
                 }
             };
-            let fetch_dependency_node = dependency_graph.get_or_create_root_node(
-                subgraph_name,
-                self.root_kind,
-                root_type,
-            )?;
+            let fetch_dependency_node = if self.root_kind == SchemaRootDefinitionKind::Mutation {
+                dependency_graph.new_serial_root_node(subgraph_name, root_type)?
+            } else {
+                dependency_graph.get_or_create_root_node(
+                    subgraph_name,
+                    self.root_kind,
+                    root_type,
+                )?
+            };
             compute_nodes_for_tree(
                 dependency_graph,
                 path_tree,
@@ -595,12 +1011,97 @@ impl QueryPlanningTraversal {
                 Default::default(),
                 Default::default(),
                 Default::default(),
-            );
+            )?;
         }
         Ok(())
     }
 }
 
+impl QueryPlanningTraversal {
+    /// Plans the conditions of the given edge from its head, through a nested query planning
+    /// traversal (whose paths can't use those same conditions again).
+    // PORT_NOTE: Named `resolveConditionPlan` in the JS codebase.
+    fn resolve_condition_plan(
+        &mut self,
+        edge: EdgeIndex,
+        context: &OpGraphPathContext,
+        excluded_destinations: &ExcludedDestinations,
+        excluded_conditions: &ExcludedConditions,
+    ) -> Result<ConditionResolution, FederationError> {
+        let graph = &self.parameters.federated_query_graph;
+        let (head, _) = graph.edge_endpoints(edge)?;
+        let Some(conditions) = graph.edge_weight(edge)?.conditions.clone() else {
+            return Err(FederationError::internal(
+                "Unexpectedly resolving the conditions of an edge without conditions",
+            ));
+        };
+        let parameters = QueryPlanningParameters {
+            head,
+            head_must_be_root: false,
+            ..self.parameters.clone()
+        };
+        let mut traversal = Self::without_open_branches(
+            parameters,
+            false,
+            SchemaRootDefinitionKind::Query,
+            self.meter.clone(),
+        );
+        traversal.open_initial_branch(
+            &conditions,
+            context.clone(),
+            excluded_destinations.clone(),
+            excluded_conditions.add_item(conditions.clone()),
+        )?;
+        let planned = traversal.find_best_plan().map(|_| ());
+        // The nested traversal counts against the budget of this one.
+        self.meter = traversal.meter;
+        planned?;
+        Ok(match traversal.best_plan {
+            Some(best_plan) => ConditionResolution::Satisfied {
+                cost: best_plan.cost,
+                path_tree: Some(best_plan.path_tree),
+            },
+            None => ConditionResolution::unsatisfied_conditions(),
+        })
+    }
+}
+
+impl ConditionResolver for QueryPlanningTraversal {
+    fn resolve(
+        &mut self,
+        edge: EdgeIndex,
+        context: &OpGraphPathContext,
+        excluded_destinations: &ExcludedDestinations,
+        excluded_conditions: &ExcludedConditions,
+    ) -> Result<ConditionResolution, FederationError> {
+        match self.resolver_cache.contains(
+            edge,
+            context,
+            excluded_destinations,
+            excluded_conditions,
+        ) {
+            ConditionResolutionCacheResult::Hit(resolution) => Ok(resolution),
+            ConditionResolutionCacheResult::Miss => {
+                let resolution = self.resolve_condition_plan(
+                    edge,
+                    context,
+                    excluded_destinations,
+                    excluded_conditions,
+                )?;
+                self.resolver_cache
+                    .insert(edge, resolution.clone(), excluded_destinations.clone());
+                Ok(resolution)
+            }
+            ConditionResolutionCacheResult::NotApplicable => self.resolve_condition_plan(
+                edge,
+                context,
+                excluded_destinations,
+                excluded_conditions,
+            ),
+        }
+    }
+}
+
 trait ClosedBranchLike {
     fn len(&self) -> usize;
     fn pop(&mut self);
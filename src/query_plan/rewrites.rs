@@ -0,0 +1,171 @@
+//! Read access to the rewrites of fetch nodes, and their application to fetch data, for query plan
+//! executors.
+use crate::query_plan::{
+    FetchDataKeyRenamer, FetchDataPathElement, FetchDataRewrite, FetchDataValueSetter, FetchNode,
+};
+use apollo_compiler::NodeStr;
+use serde_json::Value;
+
+impl FetchNode {
+    /// The rewrites to apply to each input object of this fetch (i.e. each entity representation)
+    /// before sending it, in order.
+    pub fn input_rewrites(&self) -> &[FetchDataRewrite] {
+        &self.input_rewrites
+    }
+
+    /// The rewrites to apply to the data received from this fetch before merging it into the
+    /// response, in order.
+    pub fn output_rewrites(&self) -> &[FetchDataRewrite] {
+        &self.output_rewrites
+    }
+
+    /// The rewrites collecting the values of the contextual arguments of this fetch. Unlike other
+    /// rewrites, their paths may go up from each input to its ancestors in the response, so
+    /// executors must resolve them against the response rather than `apply()` them to an input.
+    pub fn context_rewrites(&self) -> &[FetchDataRewrite] {
+        &self.context_rewrites
+    }
+}
+
+impl FetchDataRewrite {
+    /// Applies this rewrite to the given data. The rewrite's path minus its last element selects
+    /// the objects to rewrite (see `FetchDataPathElement` for how paths match data), while its last
+    /// element, which must be a key, is the key set or renamed within those objects. Objects
+    /// without that key are left untouched.
+    pub fn apply(&self, data: &mut Value) {
+        match self {
+            FetchDataRewrite::ValueSetter(setter) => {
+                let Some((FetchDataPathElement::Key(key), parent_path)) = setter.path.split_last()
+                else {
+                    return;
+                };
+                for_each_match(data, parent_path, &mut |object| {
+                    if let Some(value) = object.get_mut(key.as_str()) {
+                        *value = setter.set_value_to.clone();
+                    }
+                });
+            }
+            FetchDataRewrite::KeyRenamer(renamer) => {
+                let Some((FetchDataPathElement::Key(key), parent_path)) = renamer.path.split_last()
+                else {
+                    return;
+                };
+                for_each_match(data, parent_path, &mut |object| {
+                    if let Some(object) = object.as_object_mut() {
+                        if let Some(value) = object.remove(key.as_str()) {
+                            object.insert(renamer.rename_key_to.to_string(), value);
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl FetchDataValueSetter {
+    /// The path to the value set by this rewrite.
+    pub fn path(&self) -> &[FetchDataPathElement] {
+        &self.path
+    }
+
+    /// The value set by this rewrite.
+    pub fn set_value_to(&self) -> &Value {
+        &self.set_value_to
+    }
+}
+
+impl FetchDataKeyRenamer {
+    /// The path to the key renamed by this rewrite.
+    pub fn path(&self) -> &[FetchDataPathElement] {
+        &self.path
+    }
+
+    /// The new name of the key.
+    pub fn rename_key_to(&self) -> &NodeStr {
+        &self.rename_key_to
+    }
+}
+
+/// Calls `f` on each value of `data` matched by `path`. Arrays met where the path doesn't have an
+/// `AnyIndex` are traversed as if it had one.
+fn for_each_match(data: &mut Value, path: &[FetchDataPathElement], f: &mut impl FnMut(&mut Value)) {
+    if let Value::Array(values) = data {
+        let rest = match path.split_first() {
+            Some((FetchDataPathElement::AnyIndex, rest)) => rest,
+            _ => path,
+        };
+        for value in values {
+            for_each_match(value, rest, f);
+        }
+        return;
+    }
+    let Some((element, rest)) = path.split_first() else {
+        f(data);
+        return;
+    };
+    match element {
+        FetchDataPathElement::Key(key) => {
+            if let Some(value) = data.get_mut(key.as_str()) {
+                for_each_match(value, rest, f);
+            }
+        }
+        // Only arrays match an index, and the data has no parent (see `context_rewrites()`).
+        FetchDataPathElement::AnyIndex | FetchDataPathElement::Parent => {}
+        FetchDataPathElement::TypenameEquals(type_name) => {
+            if data.get("__typename").and_then(Value::as_str) == Some(type_name.as_str()) {
+                for_each_match(data, rest, f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::{
+        FetchDataKeyRenamer, FetchDataPathElement, FetchDataRewrite, FetchDataValueSetter,
+    };
+    use apollo_compiler::NodeStr;
+    use serde_json::json;
+
+    #[test]
+    fn sets_values() {
+        // The input rewrite used for entity fetches on an @interfaceObject.
+        let rewrite = FetchDataRewrite::ValueSetter(FetchDataValueSetter {
+            path: vec![
+                FetchDataPathElement::TypenameEquals(NodeStr::new("Book")),
+                FetchDataPathElement::Key(NodeStr::new("__typename")),
+            ],
+            set_value_to: json!("Product"),
+        });
+        let mut representations = json!([
+            { "__typename": "Book", "id": "1" },
+            { "__typename": "Movie", "id": "2" },
+        ]);
+        rewrite.apply(&mut representations);
+        assert_eq!(
+            representations,
+            json!([
+                { "__typename": "Product", "id": "1" },
+                { "__typename": "Movie", "id": "2" },
+            ])
+        );
+    }
+
+    #[test]
+    fn renames_keys() {
+        let rewrite = FetchDataRewrite::KeyRenamer(FetchDataKeyRenamer {
+            path: vec![
+                FetchDataPathElement::Key(NodeStr::new("products")),
+                FetchDataPathElement::AnyIndex,
+                FetchDataPathElement::Key(NodeStr::new("id")),
+            ],
+            rename_key_to: NodeStr::new("productId"),
+        });
+        let mut data = json!({ "products": [{ "id": "1" }, { "upc": "2" }] });
+        rewrite.apply(&mut data);
+        assert_eq!(
+            data,
+            json!({ "products": [{ "productId": "1" }, { "upc": "2" }] })
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use crate::query_plan::{
+    ConditionNode, DeferNode, DeferredDeferBlock, DeferredDependency, FetchDataPathElement,
+    FetchDataRewrite, FetchNode, FlattenNode, ParallelNode, PlanNode, PrimaryDeferBlock,
+    QueryPathElement, QueryPlan, SequenceNode, SubscriptionNode, TopLevelPlanNode,
+};
+use apollo_compiler::executable::{OperationType, SelectionSet};
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+impl QueryPlan {
+    /// Serializes this query plan to JSON, in the format expected by query plan executors. Plan
+    /// nodes that are shared within the plan (see `PlanNode::ptr()`) are serialized in full every
+    /// time they appear.
+    pub fn to_json(&self) -> Value {
+        self.to_json_internal(&mut FullSerializer)
+    }
+
+    /// Serializes this query plan to JSON like `to_json()`, except that plan nodes appearing more
+    /// than once in the plan are serialized only once, in the `sharedNodes` list of the plan. Each
+    /// of their occurrences is replaced by a `{ "kind": "Reference", "id": <index> }` node, where
+    /// `<index>` is the index of the node in `sharedNodes`.
+    ///
+    /// A shared node is always serialized after the shared nodes it references.
+    pub fn to_json_with_references(&self) -> Value {
+        let mut serializer = ReferencingSerializer::default();
+        if let Some(node) = &self.node {
+            node.for_each_child(&mut |child| serializer.count(child));
+        }
+        let mut value = self.to_json_internal(&mut serializer);
+        value["sharedNodes"] = Value::Array(serializer.shared_nodes);
+        value
+    }
+
+    fn to_json_internal(&self, serializer: &mut impl ChildSerializer) -> Value {
+        let mut value = json!({ "kind": "QueryPlan" });
+        if let Some(node) = &self.node {
+            value["node"] = node.to_json(serializer);
+        }
+        if self.has_introspection {
+            value["hasIntrospection"] = Value::Bool(true);
+        }
+        value
+    }
+}
+
+/// How to serialize the plan nodes within another plan node.
+pub(crate) trait ChildSerializer {
+    fn child(&mut self, node: &PlanNode) -> Value;
+}
+
+struct FullSerializer;
+
+impl ChildSerializer for FullSerializer {
+    fn child(&mut self, node: &PlanNode) -> Value {
+        node.to_json(self)
+    }
+}
+
+#[derive(Default)]
+struct ReferencingSerializer {
+    /// The number of occurrences of each plan node in the plan, by pointer.
+    occurrences: IndexMap<*const (), usize>,
+    /// The index in `shared_nodes` of the plan nodes appearing more than once in the plan that have
+    /// been serialized, by pointer.
+    shared_node_ids: IndexMap<*const (), usize>,
+    shared_nodes: Vec<Value>,
+}
+
+impl ReferencingSerializer {
+    fn count(&mut self, node: &PlanNode) {
+        let occurrences = self.occurrences.entry(node.ptr()).or_default();
+        *occurrences += 1;
+        // The children of a node are only counted once, as all the occurrences of the node
+        // reference the same serialization.
+        if *occurrences == 1 {
+            node.for_each_child(&mut |child| self.count(child));
+        }
+    }
+}
+
+impl ChildSerializer for ReferencingSerializer {
+    fn child(&mut self, node: &PlanNode) -> Value {
+        let ptr = node.ptr();
+        if self.occurrences.get(&ptr).copied().unwrap_or_default() <= 1 {
+            return node.to_json(self);
+        }
+        let id = match self.shared_node_ids.get(&ptr) {
+            Some(id) => *id,
+            None => {
+                let value = node.to_json(self);
+                let id = self.shared_nodes.len();
+                self.shared_nodes.push(value);
+                self.shared_node_ids.insert(ptr, id);
+                id
+            }
+        };
+        json!({ "kind": "Reference", "id": id })
+    }
+}
+
+impl TopLevelPlanNode {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        match self {
+            TopLevelPlanNode::Subscription(node) => node.to_json(serializer),
+            TopLevelPlanNode::Fetch(node) => node.to_json(),
+            TopLevelPlanNode::Sequence(node) => node.to_json(serializer),
+            TopLevelPlanNode::Parallel(node) => node.to_json(serializer),
+            TopLevelPlanNode::Flatten(node) => node.to_json(serializer),
+            TopLevelPlanNode::Defer(node) => node.to_json(serializer),
+            TopLevelPlanNode::Condition(node) => node.to_json(serializer),
+        }
+    }
+
+    fn for_each_child(&self, f: &mut impl FnMut(&PlanNode)) {
+        match self {
+            TopLevelPlanNode::Subscription(node) => node.rest.iter().for_each(f),
+            TopLevelPlanNode::Fetch(_) => {}
+            TopLevelPlanNode::Sequence(node) => node.nodes.iter().for_each(f),
+            TopLevelPlanNode::Parallel(node) => node.nodes.iter().for_each(f),
+            TopLevelPlanNode::Flatten(node) => f(&node.node),
+            TopLevelPlanNode::Defer(node) => node.for_each_child(f),
+            TopLevelPlanNode::Condition(node) => node.for_each_child(f),
+        }
+    }
+}
+
+impl PlanNode {
+    /// The address of the node this `PlanNode` points to. Two `PlanNode`s with the same address
+    /// are the same (shared) plan node.
+    pub(crate) fn ptr(&self) -> *const () {
+        match self {
+            PlanNode::Fetch(node) => Arc::as_ptr(node) as *const (),
+            PlanNode::Sequence(node) => Arc::as_ptr(node) as *const (),
+            PlanNode::Parallel(node) => Arc::as_ptr(node) as *const (),
+            PlanNode::Flatten(node) => Arc::as_ptr(node) as *const (),
+            PlanNode::Defer(node) => Arc::as_ptr(node) as *const (),
+            PlanNode::Condition(node) => Arc::as_ptr(node) as *const (),
+        }
+    }
+
+    /// Serializes this plan node, using `serializer` for the plan nodes within it.
+    pub(crate) fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        match self {
+            PlanNode::Fetch(node) => node.to_json(),
+            PlanNode::Sequence(node) => node.to_json(serializer),
+            PlanNode::Parallel(node) => node.to_json(serializer),
+            PlanNode::Flatten(node) => node.to_json(serializer),
+            PlanNode::Defer(node) => node.to_json(serializer),
+            PlanNode::Condition(node) => node.to_json(serializer),
+        }
+    }
+
+    fn for_each_child(&self, f: &mut impl FnMut(&PlanNode)) {
+        match self {
+            PlanNode::Fetch(_) => {}
+            PlanNode::Sequence(node) => node.nodes.iter().for_each(f),
+            PlanNode::Parallel(node) => node.nodes.iter().for_each(f),
+            PlanNode::Flatten(node) => f(&node.node),
+            PlanNode::Defer(node) => node.for_each_child(f),
+            PlanNode::Condition(node) => node.for_each_child(f),
+        }
+    }
+}
+
+impl SubscriptionNode {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        let mut value = json!({
+            "kind": "Subscription",
+            "primary": self.primary.to_json(),
+        });
+        if let Some(rest) = &self.rest {
+            value["rest"] = serializer.child(rest);
+        }
+        value
+    }
+}
+
+impl FetchNode {
+    fn to_json(&self) -> Value {
+        let mut value = Map::new();
+        value.insert("kind".to_owned(), json!("Fetch"));
+        value.insert("serviceName".to_owned(), json!(self.subgraph_name.as_str()));
+        if let Some(id) = &self.id {
+            value.insert("id".to_owned(), json!(id.as_str()));
+        }
+        if let Some(has_defers) = self.has_defers {
+            value.insert("hasDefers".to_owned(), json!(has_defers));
+        }
+        value.insert(
+            "variableUsages".to_owned(),
+            self.variable_usages
+                .iter()
+                .map(|name| json!(name.as_str()))
+                .collect(),
+        );
+        if !self.requires.is_empty() {
+            value.insert(
+                "requires".to_owned(),
+                self.requires
+                    .iter()
+                    .map(|selection| json!(selection.serialize().no_indent().to_string()))
+                    .collect(),
+            );
+        }
+        value.insert(
+            "operation".to_owned(),
+            json!(self.operation_document.serialize().no_indent().to_string()),
+        );
+        if let Some(operation_name) = &self.operation_name {
+            value.insert("operationName".to_owned(), json!(operation_name.as_str()));
+        }
+        value.insert(
+            "operationKind".to_owned(),
+            json!(operation_kind_to_json(self.operation_kind)),
+        );
+        value.insert("retrySafe".to_owned(), json!(self.is_retry_safe()));
+        if !self.input_rewrites.is_empty() {
+            value.insert(
+                "inputRewrites".to_owned(),
+                self.input_rewrites.iter().map(|r| r.to_json()).collect(),
+            );
+        }
+        if !self.output_rewrites.is_empty() {
+            value.insert(
+                "outputRewrites".to_owned(),
+                self.output_rewrites.iter().map(|r| r.to_json()).collect(),
+            );
+        }
+        if !self.context_rewrites.is_empty() {
+            value.insert(
+                "contextRewrites".to_owned(),
+                self.context_rewrites.iter().map(|r| r.to_json()).collect(),
+            );
+        }
+        if let Some(authorization) = self.authorization.as_ref().filter(|a| !a.is_empty()) {
+            value.insert(
+                "authorization".to_owned(),
+                json!({
+                    "authenticated": authorization.authenticated,
+                    "scopes": authorization.scopes,
+                    "policies": authorization.policies,
+                }),
+            );
+        }
+        Value::Object(value)
+    }
+}
+
+impl SequenceNode {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        json!({
+            "kind": "Sequence",
+            "nodes": self.nodes.iter().map(|node| serializer.child(node)).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl ParallelNode {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        json!({
+            "kind": "Parallel",
+            "nodes": self.nodes.iter().map(|node| serializer.child(node)).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl FlattenNode {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        json!({
+            "kind": "Flatten",
+            "path": fetch_data_path_to_json(&self.path),
+            "node": serializer.child(&self.node),
+        })
+    }
+}
+
+impl DeferNode {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        json!({
+            "kind": "Defer",
+            "primary": self.primary.to_json(serializer),
+            "deferred": self
+                .deferred
+                .iter()
+                .map(|deferred| deferred.to_json(serializer))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn for_each_child(&self, f: &mut impl FnMut(&PlanNode)) {
+        self.primary.node.iter().for_each(&mut *f);
+        for deferred in &self.deferred {
+            deferred.node.iter().for_each(&mut *f);
+        }
+    }
+}
+
+impl PrimaryDeferBlock {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        let mut value = json!({});
+        if let Some(sub_selection) = &self.sub_selection {
+            value["subselection"] = selection_set_to_json(sub_selection);
+        }
+        if let Some(node) = &self.node {
+            value["node"] = serializer.child(node);
+        }
+        value
+    }
+}
+
+impl DeferredDeferBlock {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        let mut value = json!({
+            "depends": self.depends.iter().map(|d| d.to_json()).collect::<Vec<_>>(),
+            "queryPath": self
+                .query_path
+                .iter()
+                .map(|element| element.to_json())
+                .collect::<Vec<_>>(),
+        });
+        if let Some(label) = &self.label {
+            value["label"] = json!(label.as_str());
+        }
+        if let Some(sub_selection) = &self.sub_selection {
+            value["subselection"] = selection_set_to_json(sub_selection);
+        }
+        if let Some(node) = &self.node {
+            value["node"] = serializer.child(node);
+        }
+        value
+    }
+}
+
+impl DeferredDependency {
+    fn to_json(&self) -> Value {
+        let mut value = json!({ "id": self.id.as_str() });
+        if let Some(defer_label) = &self.defer_label {
+            value["deferLabel"] = json!(defer_label.as_str());
+        }
+        value
+    }
+}
+
+impl ConditionNode {
+    fn to_json(&self, serializer: &mut impl ChildSerializer) -> Value {
+        let mut value = json!({
+            "kind": "Condition",
+            "condition": self.condition_variable.as_str(),
+        });
+        if let Some(if_clause) = &self.if_clause {
+            value["ifClause"] = serializer.child(if_clause);
+        }
+        if let Some(else_clause) = &self.else_clause {
+            value["elseClause"] = serializer.child(else_clause);
+        }
+        value
+    }
+
+    fn for_each_child(&self, f: &mut impl FnMut(&PlanNode)) {
+        self.if_clause.iter().for_each(&mut *f);
+        self.else_clause.iter().for_each(&mut *f);
+    }
+}
+
+impl FetchDataRewrite {
+    fn to_json(&self) -> Value {
+        match self {
+            FetchDataRewrite::ValueSetter(setter) => json!({
+                "kind": "ValueSetter",
+                "path": fetch_data_path_to_json(&setter.path),
+                "setValueTo": setter.set_value_to,
+            }),
+            FetchDataRewrite::KeyRenamer(renamer) => json!({
+                "kind": "KeyRenamer",
+                "path": fetch_data_path_to_json(&renamer.path),
+                "renameKeyTo": renamer.rename_key_to.as_str(),
+            }),
+        }
+    }
+}
+
+impl QueryPathElement {
+    fn to_json(&self) -> Value {
+        match self {
+            QueryPathElement::Field(field) => json!(field.response_key().as_str()),
+            QueryPathElement::InlineFragment(inline_fragment) => {
+                match &inline_fragment.type_condition {
+                    Some(type_condition) => json!(format!("... on {type_condition}")),
+                    None => json!("..."),
+                }
+            }
+        }
+    }
+}
+
+fn fetch_data_path_to_json(path: &[FetchDataPathElement]) -> Value {
+    path.iter()
+        .map(|element| match element {
+            FetchDataPathElement::Key(key) => json!(key.as_str()),
+            FetchDataPathElement::AnyIndex => json!("@"),
+            FetchDataPathElement::TypenameEquals(type_name) => {
+                json!(format!("... on {type_name}"))
+            }
+            FetchDataPathElement::Parent => json!(".."),
+        })
+        .collect()
+}
+
+fn selection_set_to_json(selection_set: &SelectionSet) -> Value {
+    json!(selection_set.serialize().no_indent().to_string())
+}
+
+fn operation_kind_to_json(operation_kind: OperationType) -> &'static str {
+    match operation_kind {
+        OperationType::Query => "query",
+        OperationType::Mutation => "mutation",
+        OperationType::Subscription => "subscription",
+    }
+}
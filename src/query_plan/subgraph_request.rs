@@ -0,0 +1,234 @@
+//! Builds the GraphQL-over-HTTP requests that query plan executors send to subgraphs for fetch
+//! nodes, so executors only have to fill in the values known at runtime.
+use crate::query_plan::FetchNode;
+use apollo_compiler::executable::{Name, OperationType};
+use apollo_compiler::NodeStr;
+use serde_json::{json, Map, Value};
+
+/// The variable holding the representations of the entities fetched by entity fetches.
+const REPRESENTATIONS_VARIABLE: &str = "representations";
+
+/// The request sent to a subgraph for a fetch node, following the GraphQL-over-HTTP request format
+/// (see `to_json()`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubgraphRequest {
+    /// The name of the subgraph to send the request to.
+    pub subgraph_name: NodeStr,
+    /// The fetch operation, printed.
+    pub query: String,
+    pub operation_name: Option<NodeStr>,
+    /// The variables of the request, one per variable defined by the fetch operation. Their values
+    /// are placeholders until set: an empty list for `$representations` (see
+    /// `set_representations()`), and `null` for the other variables.
+    pub variables: Map<String, Value>,
+}
+
+impl SubgraphRequest {
+    /// Whether the request fetches entities, i.e. whether its operation has a `$representations`
+    /// variable.
+    pub fn is_entity_fetch(&self) -> bool {
+        self.variables.contains_key(REPRESENTATIONS_VARIABLE)
+    }
+
+    /// Sets the value of the given variable. Returns `false` (and leaves the request untouched) if
+    /// the fetch operation doesn't define the variable, since subgraphs reject unknown variables.
+    pub fn set_variable(&mut self, name: &str, value: Value) -> bool {
+        match self.variables.get_mut(name) {
+            Some(variable) => {
+                *variable = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the representations of the entities to fetch, i.e. the inputs collected for the fetch
+    /// node (with its input rewrites applied). Returns `false` if this isn't an entity fetch.
+    pub fn set_representations(&mut self, representations: Vec<Value>) -> bool {
+        self.set_variable(REPRESENTATIONS_VARIABLE, Value::Array(representations))
+    }
+
+    /// The body of the request, as a JSON object with `query`, `operationName` (unless the fetch
+    /// operation is anonymous) and `variables` keys.
+    pub fn to_json(&self) -> Value {
+        let mut value = Map::new();
+        value.insert("query".to_owned(), json!(self.query));
+        if let Some(operation_name) = &self.operation_name {
+            value.insert("operationName".to_owned(), json!(operation_name.as_str()));
+        }
+        value.insert(
+            "variables".to_owned(),
+            Value::Object(self.variables.clone()),
+        );
+        Value::Object(value)
+    }
+}
+
+impl FetchNode {
+    /// The client variables this fetch forwards to its subgraph, i.e. the variables of the planned
+    /// operation that the fetch operation uses (and defines). This doesn't include the variables
+    /// added by the planner, like `$representations`.
+    pub fn variable_usages(&self) -> &[Name] {
+        &self.variable_usages
+    }
+
+    /// The values of the variables this fetch forwards to its subgraph (see `variable_usages()`),
+    /// taken from the variables of the client request. Variables the client didn't provide are
+    /// left out, so that the subgraph applies their default value.
+    pub fn forwarded_variables(&self, client_variables: &Map<String, Value>) -> Map<String, Value> {
+        self.variable_usages
+            .iter()
+            .filter_map(|name| {
+                let value = client_variables.get(name.as_str())?;
+                Some((name.to_string(), value.clone()))
+            })
+            .collect()
+    }
+
+    /// Whether executors can safely retry this fetch (e.g. after a network error), i.e. whether
+    /// sending it to the subgraph more than once has no additional side effects. This is the case
+    /// of query fetches, including the entity fetches of mutation plans, but not of the mutation
+    /// fetches themselves, nor of subscription fetches.
+    pub fn is_retry_safe(&self) -> bool {
+        self.operation_kind == OperationType::Query
+    }
+
+    /// The request to send to the subgraph for this fetch, with placeholder variable values (see
+    /// `SubgraphRequest::variables`).
+    pub fn subgraph_request(&self) -> SubgraphRequest {
+        let variables = self
+            .operation_document
+            .all_operations()
+            .flat_map(|operation| &operation.variables)
+            .map(|variable| {
+                let placeholder = if variable.name == REPRESENTATIONS_VARIABLE {
+                    json!([])
+                } else {
+                    Value::Null
+                };
+                (variable.name.to_string(), placeholder)
+            })
+            .collect();
+        SubgraphRequest {
+            subgraph_name: self.subgraph_name.clone(),
+            query: self.operation_document.serialize().no_indent().to_string(),
+            operation_name: self.operation_name.clone(),
+            variables,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_plan::FetchNode;
+    use apollo_compiler::executable::OperationType;
+    use apollo_compiler::{name, ExecutableDocument, NodeStr, Schema};
+    use serde_json::json;
+
+    #[test]
+    fn builds_subgraph_requests() {
+        let schema = Schema::parse_and_validate(
+            r#"
+            scalar _Any
+            union _Entity = User
+            type Query { _entities(representations: [_Any!]!): [_Entity]! }
+            type User { id: ID! name(format: String): String }
+            "#,
+            "schema.graphql",
+        )
+        .unwrap();
+        let operation = r#"
+            query Q__S1__1($representations: [_Any!]!, $format: String) {
+              _entities(representations: $representations) { ... on User { name(format: $format) } }
+            }
+        "#;
+        let fetch = FetchNode {
+            subgraph_name: NodeStr::new("S1"),
+            id: None,
+            has_defers: None,
+            variable_usages: vec![name!("format")],
+            requires: vec![],
+            operation_document: ExecutableDocument::parse_and_validate(
+                &schema,
+                operation,
+                "operation.graphql",
+            )
+            .unwrap(),
+            operation_name: Some(NodeStr::new("Q__S1__1")),
+            operation_kind: OperationType::Query,
+            input_rewrites: vec![],
+            output_rewrites: vec![],
+            context_rewrites: vec![],
+            authorization: None,
+        };
+
+        let client_variables = json!({ "format": "short", "limit": 10 });
+        let forwarded_variables = fetch.forwarded_variables(client_variables.as_object().unwrap());
+        assert_eq!(json!(forwarded_variables), json!({ "format": "short" }));
+
+        // Entity fetches are queries, even in the plans of mutations.
+        assert!(fetch.is_retry_safe());
+
+        let mut request = fetch.subgraph_request();
+        assert!(request.is_entity_fetch());
+        assert!(request.set_representations(vec![json!({ "__typename": "User", "id": "1" })]));
+        for (name, value) in forwarded_variables {
+            assert!(request.set_variable(&name, value));
+        }
+        assert!(!request.set_variable("unknown", json!(1)));
+        assert_eq!(
+            request.to_json(),
+            json!({
+                "query": "query Q__S1__1($representations: [_Any!]!, $format: String) { _entities(representations: $representations) { ... on User { name(format: $format) } } }",
+                "operationName": "Q__S1__1",
+                "variables": {
+                    "representations": [{ "__typename": "User", "id": "1" }],
+                    "format": "short",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn forwards_only_used_client_variables() {
+        let schema = Schema::parse_and_validate(
+            r#"
+            type Query { users(limit: Int = 10, offset: Int): [User] }
+            type User { id: ID! }
+            "#,
+            "schema.graphql",
+        )
+        .unwrap();
+        let operation = r#"
+            query Q__S1__0($limit: Int = 10, $offset: Int) {
+              users(limit: $limit, offset: $offset) { id }
+            }
+        "#;
+        let fetch = FetchNode {
+            subgraph_name: NodeStr::new("S1"),
+            id: None,
+            has_defers: None,
+            variable_usages: vec![name!("limit"), name!("offset")],
+            requires: vec![],
+            operation_document: ExecutableDocument::parse_and_validate(
+                &schema,
+                operation,
+                "operation.graphql",
+            )
+            .unwrap(),
+            operation_name: Some(NodeStr::new("Q__S1__0")),
+            operation_kind: OperationType::Query,
+            input_rewrites: vec![],
+            output_rewrites: vec![],
+            context_rewrites: vec![],
+            authorization: None,
+        };
+        assert_eq!(fetch.variable_usages(), [name!("limit"), name!("offset")]);
+
+        // Variables the client didn't provide (`$limit`) are left out for the subgraph to apply
+        // their default, as are those of other fetches, while explicit nulls are forwarded.
+        let client_variables = json!({ "offset": null, "format": "short" });
+        let forwarded_variables = fetch.forwarded_variables(client_variables.as_object().unwrap());
+        assert_eq!(json!(forwarded_variables), json!({ "offset": null }));
+    }
+}
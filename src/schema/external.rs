@@ -0,0 +1,143 @@
+use crate::error::FederationError;
+use crate::link::federation_spec_definition::{
+    get_federation_spec_definition_from_subgraph, FEDERATION_EXTENDS_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_FIELDS_ARGUMENT_NAME, FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC,
+};
+use crate::link::spec_definition::SpecDefinition;
+use crate::schema::position::{FieldDefinitionPosition, ObjectOrInterfaceTypeDefinitionPosition};
+use crate::schema::ValidFederationSchema;
+use apollo_compiler::executable::{FieldSet, Selection, SelectionSet};
+use apollo_compiler::schema::{ComponentOrigin, ExtendedType, Name, Value};
+use indexmap::IndexSet;
+
+/// Determines which fields of a subgraph schema are external, i.e. not resolved by the subgraph.
+///
+/// A field is external when it's marked `@external` (or its type is), except for "fake" external
+/// fields. Those come from federation 1 schemas, where the key fields of a type extension had to be
+/// marked `@external` even though the subgraph does resolve them (they're needed to resolve the
+/// entity in the first place). Such fields are treated as if they weren't marked `@external`.
+// PORT_NOTE: This was named `ExternalTester` in the JS codebase, and was computed lazily.
+#[derive(Debug, Clone)]
+pub(crate) struct ExternalMetadata {
+    external_fields: IndexSet<FieldDefinitionPosition>,
+    fake_external_fields: IndexSet<FieldDefinitionPosition>,
+}
+
+impl ExternalMetadata {
+    pub(crate) fn new(schema: &ValidFederationSchema) -> Result<Self, FederationError> {
+        let federation_spec_definition = get_federation_spec_definition_from_subgraph(schema)?;
+        let external_directive_name = federation_spec_definition
+            .external_directive_definition(schema)?
+            .name
+            .clone();
+        let key_directive_name = federation_spec_definition
+            .directive_name_in_schema(schema, &FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC)?;
+        let extends_directive_name = federation_spec_definition
+            .directive_name_in_schema(schema, &FEDERATION_EXTENDS_DIRECTIVE_NAME_IN_SPEC)?;
+
+        let mut metadata = Self {
+            external_fields: IndexSet::new(),
+            fake_external_fields: IndexSet::new(),
+        };
+        for type_pos in schema.get_types() {
+            let Ok(type_pos) = ObjectOrInterfaceTypeDefinitionPosition::try_from(type_pos) else {
+                continue;
+            };
+            let (directives, fields) = match schema.schema().types.get(type_pos.type_name()) {
+                Some(ExtendedType::Object(type_)) => (&type_.directives, &type_.fields),
+                Some(ExtendedType::Interface(type_)) => (&type_.directives, &type_.fields),
+                _ => continue,
+            };
+            let is_type_external = directives.has(&external_directive_name);
+            for (field_name, field) in fields {
+                if is_type_external || field.directives.has(&external_directive_name) {
+                    metadata
+                        .external_fields
+                        .insert(type_pos.field(field_name.clone()).into());
+                }
+            }
+
+            // Key fields marked @external on a type extension are fake external fields.
+            let Some(key_directive_name) = &key_directive_name else {
+                continue;
+            };
+            let is_extension = extends_directive_name
+                .as_ref()
+                .is_some_and(|name| directives.has(name));
+            for key in directives.get_all(key_directive_name) {
+                if !is_extension && !matches!(key.origin, ComponentOrigin::Extension(_)) {
+                    continue;
+                }
+                let Some(Value::String(fields)) = key
+                    .argument_by_name(&FEDERATION_FIELDS_ARGUMENT_NAME)
+                    .map(|value| value.as_ref())
+                else {
+                    continue;
+                };
+                // Invalid keys are reported by subgraph validation, so they're ignored here.
+                let Ok(field_set) = FieldSet::parse_and_validate(
+                    schema.schema(),
+                    type_pos.type_name().clone(),
+                    fields.as_str(),
+                    "field_set.graphql",
+                ) else {
+                    continue;
+                };
+                let mut key_fields = IndexSet::new();
+                collect_fields(&field_set.selection_set, &mut key_fields);
+                for (parent_type_name, field_name) in key_fields {
+                    let Some(parent_type_pos) =
+                        schema.try_get_type(parent_type_name).and_then(|pos| {
+                            ObjectOrInterfaceTypeDefinitionPosition::try_from(pos).ok()
+                        })
+                    else {
+                        continue;
+                    };
+                    let field_pos = parent_type_pos.field(field_name);
+                    if field_pos
+                        .get(schema.schema())?
+                        .directives
+                        .has(&external_directive_name)
+                    {
+                        metadata.fake_external_fields.insert(field_pos.into());
+                    }
+                }
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Whether the field is external, i.e. marked `@external` (directly or through its type) and
+    /// not a fake external field.
+    pub(crate) fn is_external(&self, field_definition_position: &FieldDefinitionPosition) -> bool {
+        self.external_fields.contains(field_definition_position)
+            && !self.is_fake_external(field_definition_position)
+    }
+
+    /// Whether the field is a key field of a type extension marked `@external`, which the subgraph
+    /// actually resolves.
+    pub(crate) fn is_fake_external(
+        &self,
+        field_definition_position: &FieldDefinitionPosition,
+    ) -> bool {
+        self.fake_external_fields
+            .contains(field_definition_position)
+    }
+}
+
+/// Collects the fields selected by the given selection set (recursively), as parent type and field
+/// names.
+pub(crate) fn collect_fields(selection_set: &SelectionSet, fields: &mut IndexSet<(Name, Name)>) {
+    for selection in &selection_set.selections {
+        match selection {
+            Selection::Field(field) => {
+                fields.insert((selection_set.ty.clone(), field.name.clone()));
+                collect_fields(&field.selection_set, fields);
+            }
+            Selection::InlineFragment(inline_fragment) => {
+                collect_fields(&inline_fragment.selection_set, fields);
+            }
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+}
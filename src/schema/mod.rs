@@ -1,4 +1,5 @@
 use crate::error::{FederationError, SingleFederationError};
+use crate::link::join_metadata::JoinMetadata;
 use crate::link::LinksMetadata;
 use crate::schema::position::{
     CompositeTypeDefinitionPosition, DirectiveDefinitionPosition, EnumTypeDefinitionPosition,
@@ -9,14 +10,18 @@ use crate::schema::position::{
 use apollo_compiler::schema::{ExtendedType, Name};
 use apollo_compiler::validation::Valid;
 use apollo_compiler::Schema;
-use indexmap::IndexSet;
-use referencer::Referencers;
+use indexmap::{IndexMap, IndexSet};
+use referencer::{ReferencerPosition, Referencers};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::sync::Arc;
+use subgraph_metadata::SubgraphMetadata;
 
-pub(crate) mod position;
-pub(crate) mod referencer;
+pub(crate) mod external;
+mod mutation;
+pub mod position;
+pub mod referencer;
+pub(crate) mod subgraph_metadata;
 
 #[derive(Debug)]
 pub struct FederationSchema {
@@ -26,7 +31,8 @@ pub struct FederationSchema {
 }
 
 impl FederationSchema {
-    pub(crate) fn schema(&self) -> &Schema {
+    /// The apollo-compiler schema.
+    pub fn schema(&self) -> &Schema {
         &self.schema
     }
 
@@ -39,11 +45,66 @@ impl FederationSchema {
         self.metadata.as_ref()
     }
 
-    pub(crate) fn referencers(&self) -> &Referencers {
+    /// The index of the schema elements referencing each type and directive of this schema.
+    pub fn referencers(&self) -> &Referencers {
         &self.referencers
     }
 
-    pub(crate) fn get_types(&self) -> impl Iterator<Item = TypeDefinitionPosition> + '_ {
+    /// The positions of the schema elements referencing the given type (e.g. the fields returning
+    /// it, the arguments of its type, or the object types implementing it for an interface).
+    pub fn get_type_referencers(
+        &self,
+        position: &TypeDefinitionPosition,
+    ) -> Result<Vec<ReferencerPosition>, FederationError> {
+        let type_name = position.type_name();
+        Ok(match position {
+            TypeDefinitionPosition::Scalar(_) => self
+                .referencers
+                .get_scalar_type(type_name)?
+                .positions()
+                .collect(),
+            TypeDefinitionPosition::Object(_) => self
+                .referencers
+                .get_object_type(type_name)?
+                .positions()
+                .collect(),
+            TypeDefinitionPosition::Interface(_) => self
+                .referencers
+                .get_interface_type(type_name)?
+                .positions()
+                .collect(),
+            TypeDefinitionPosition::Union(_) => self
+                .referencers
+                .get_union_type(type_name)?
+                .positions()
+                .collect(),
+            TypeDefinitionPosition::Enum(_) => self
+                .referencers
+                .get_enum_type(type_name)?
+                .positions()
+                .collect(),
+            TypeDefinitionPosition::InputObject(_) => self
+                .referencers
+                .get_input_object_type(type_name)?
+                .positions()
+                .collect(),
+        })
+    }
+
+    /// The positions of the schema elements the given directive is applied to.
+    pub fn get_directive_referencers(
+        &self,
+        position: &DirectiveDefinitionPosition,
+    ) -> Result<Vec<ReferencerPosition>, FederationError> {
+        Ok(self
+            .referencers
+            .get_directive(&position.directive_name)?
+            .positions()
+            .collect())
+    }
+
+    /// The positions of the types of this schema, in definition order.
+    pub fn get_types(&self) -> impl Iterator<Item = TypeDefinitionPosition> + '_ {
         self.schema.types.iter().map(|(type_name, type_)| {
             let type_name = type_name.clone();
             match type_ {
@@ -59,7 +120,8 @@ impl FederationSchema {
         })
     }
 
-    pub(crate) fn get_directive_definitions(
+    /// The positions of the directive definitions of this schema, in definition order.
+    pub fn get_directive_definitions(
         &self,
     ) -> impl Iterator<Item = DirectiveDefinitionPosition> + '_ {
         self.schema
@@ -70,10 +132,8 @@ impl FederationSchema {
             })
     }
 
-    pub(crate) fn get_type(
-        &self,
-        type_name: Name,
-    ) -> Result<TypeDefinitionPosition, FederationError> {
+    /// The position of the type with the given name, failing if this schema has no such type.
+    pub fn get_type(&self, type_name: Name) -> Result<TypeDefinitionPosition, FederationError> {
         let type_ =
             self.schema
                 .types
@@ -91,11 +151,15 @@ impl FederationSchema {
         })
     }
 
-    pub(crate) fn try_get_type(&self, type_name: Name) -> Option<TypeDefinitionPosition> {
+    /// Like `get_type()`, but returns `None` if this schema has no such type.
+    pub fn try_get_type(&self, type_name: Name) -> Option<TypeDefinitionPosition> {
         self.get_type(type_name).ok()
     }
 
-    pub(crate) fn possible_runtime_types(
+    /// The object types the values of the given composite type can have at runtime: the type
+    /// itself for an object type, its implementations for an interface, and its members for a
+    /// union.
+    pub fn possible_runtime_types(
         &self,
         composite_type_definition_position: CompositeTypeDefinitionPosition,
     ) -> Result<IndexSet<ObjectTypeDefinitionPosition>, FederationError> {
@@ -117,21 +181,18 @@ impl FederationSchema {
         })
     }
 
-    pub(crate) fn validate(self) -> Result<ValidFederationSchema, FederationError> {
+    /// Validates this schema, e.g. once done mutating it.
+    pub fn validate(self) -> Result<ValidFederationSchema, FederationError> {
         let schema = self.schema.validate()?.into_inner();
-        Ok(ValidFederationSchema(Arc::new(Valid::assume_valid(
-            FederationSchema {
-                schema,
-                metadata: self.metadata,
-                referencers: self.referencers,
-            },
-        ))))
+        ValidFederationSchema::new_assume_valid(FederationSchema {
+            schema,
+            metadata: self.metadata,
+            referencers: self.referencers,
+        })
     }
 
-    pub(crate) fn get_directive_definition(
-        &self,
-        name: &Name,
-    ) -> Option<DirectiveDefinitionPosition> {
+    /// The position of the directive definition with the given name, if this schema has one.
+    pub fn get_directive_definition(&self, name: &Name) -> Option<DirectiveDefinitionPosition> {
         self.schema
             .directive_definitions
             .contains_key(name)
@@ -142,16 +203,82 @@ impl FederationSchema {
 }
 
 #[derive(Debug, Clone)]
-pub struct ValidFederationSchema(pub(crate) Arc<Valid<FederationSchema>>);
+pub struct ValidFederationSchema {
+    schema: Arc<Valid<FederationSchema>>,
+    subgraph_metadata: Option<Arc<SubgraphMetadata>>,
+    /// The join spec directive applications of this schema, if it's a supergraph schema. They're
+    /// parsed once when the schema is wrapped, instead of by each of their readers.
+    join_metadata: Arc<JoinMetadata>,
+    /// The possible runtime types of each composite type of the schema, which query planning looks
+    /// up repeatedly. The schema is immutable, so they're computed once when it's wrapped.
+    possible_runtime_types: Arc<IndexMap<Name, Arc<IndexSet<ObjectTypeDefinitionPosition>>>>,
+}
 
 impl ValidFederationSchema {
     pub fn new(schema: Valid<Schema>) -> Result<ValidFederationSchema, FederationError> {
         let schema = FederationSchema::new(schema.into_inner())?;
-        Ok(ValidFederationSchema(Arc::new(Valid::assume_valid(schema))))
+        Self::new_assume_valid(schema)
+    }
+
+    /// Wraps the given schema, which must already be valid GraphQL, computing its subgraph
+    /// metadata if it's a subgraph schema, and its join metadata if it's a supergraph schema.
+    pub(crate) fn new_assume_valid(
+        schema: FederationSchema,
+    ) -> Result<ValidFederationSchema, FederationError> {
+        let subgraph_metadata = SubgraphMetadata::new(&schema)?.map(Arc::new);
+        let join_metadata = Arc::new(JoinMetadata::new(&schema)?);
+        let mut possible_runtime_types = IndexMap::new();
+        for type_pos in schema.get_types() {
+            let Ok(type_pos) = CompositeTypeDefinitionPosition::try_from(type_pos) else {
+                continue;
+            };
+            possible_runtime_types.insert(
+                type_pos.type_name().clone(),
+                Arc::new(schema.possible_runtime_types(type_pos)?),
+            );
+        }
+        Ok(ValidFederationSchema {
+            schema: Arc::new(Valid::assume_valid(schema)),
+            subgraph_metadata,
+            join_metadata,
+            possible_runtime_types: Arc::new(possible_runtime_types),
+        })
+    }
+
+    /// The apollo-compiler schema.
+    pub fn schema(&self) -> &Valid<Schema> {
+        Valid::assume_valid_ref(&self.schema.schema)
+    }
+
+    /// The precomputed federation metadata of this schema, if it's a subgraph schema.
+    pub(crate) fn subgraph_metadata(&self) -> Option<&SubgraphMetadata> {
+        self.subgraph_metadata.as_deref()
     }
 
-    pub(crate) fn schema(&self) -> &Valid<Schema> {
-        Valid::assume_valid_ref(&self.schema)
+    /// The join spec directive applications of this schema (see `JoinMetadata`), which are empty if
+    /// it isn't a supergraph schema.
+    pub(crate) fn join_metadata(&self) -> &JoinMetadata {
+        &self.join_metadata
+    }
+
+    /// The possible runtime types of the given composite type, like
+    /// `FederationSchema::possible_runtime_types()` but without recomputing them.
+    pub(crate) fn cached_possible_runtime_types(
+        &self,
+        composite_type_definition_position: CompositeTypeDefinitionPosition,
+    ) -> Result<Arc<IndexSet<ObjectTypeDefinitionPosition>>, FederationError> {
+        self.possible_runtime_types
+            .get(composite_type_definition_position.type_name())
+            .cloned()
+            .ok_or_else(|| {
+                SingleFederationError::Internal {
+                    message: format!(
+                        "Schema has no composite type \"{}\"",
+                        composite_type_definition_position.type_name()
+                    ),
+                }
+                .into()
+            })
     }
 }
 
@@ -159,7 +286,7 @@ impl Deref for ValidFederationSchema {
     type Target = FederationSchema;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.schema
     }
 }
 
@@ -167,12 +294,74 @@ impl Eq for ValidFederationSchema {}
 
 impl PartialEq for ValidFederationSchema {
     fn eq(&self, other: &ValidFederationSchema) -> bool {
-        Arc::ptr_eq(&self.0, &other.0)
+        Arc::ptr_eq(&self.schema, &other.schema)
     }
 }
 
 impl Hash for ValidFederationSchema {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        Arc::as_ptr(&self.0).hash(state);
+        Arc::as_ptr(&self.schema).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::position::CompositeTypeDefinitionPosition;
+    use crate::schema::ValidFederationSchema;
+    use apollo_compiler::{name, Schema};
+
+    #[test]
+    fn caches_possible_runtime_types() {
+        let schema = ValidFederationSchema::new(
+            Schema::parse_and_validate(
+                r#"
+type Query {
+  i: I
+  u: U
+}
+
+interface I {
+  id: ID!
+}
+
+type A implements I {
+  id: ID!
+}
+
+type B implements I {
+  id: ID!
+}
+
+type C {
+  id: ID!
+}
+
+union U = A | C
+"#,
+                "schema.graphql",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        for type_name in [name!("I"), name!("U"), name!("A"), name!("C")] {
+            let position =
+                CompositeTypeDefinitionPosition::try_from(schema.get_type(type_name).unwrap())
+                    .unwrap();
+            assert_eq!(
+                *schema
+                    .cached_possible_runtime_types(position.clone())
+                    .unwrap(),
+                schema.possible_runtime_types(position).unwrap(),
+            );
+        }
+        assert!(schema
+            .cached_possible_runtime_types(
+                CompositeTypeDefinitionPosition::try_from(schema.get_type(name!("I")).unwrap())
+                    .unwrap()
+            )
+            .unwrap()
+            .iter()
+            .map(|type_| type_.type_name.as_str())
+            .eq(["A", "B"]));
     }
 }
@@ -0,0 +1,570 @@
+//! Schema-level mutations of a `FederationSchema`, built on top of the mutations of positions.
+//!
+//! Positions only mutate the element they point to (and the referencers of the elements it
+//! references), and some of them must be called in a specific order (e.g. types must be
+//! pre-inserted before any type can reference them). The methods here wrap those into operations
+//! that keep the schema and its referencers consistent on their own.
+use crate::error::{FederationError, SingleFederationError};
+use crate::schema::position::{
+    is_graphql_reserved_name, EnumTypeDefinitionPosition, InputObjectTypeDefinitionPosition,
+    InterfaceTypeDefinitionPosition, ObjectOrInterfaceFieldDefinitionPosition,
+    ObjectOrInterfaceTypeDefinitionPosition, ObjectTypeDefinitionPosition,
+    ScalarTypeDefinitionPosition, TypeDefinitionPosition, UnionTypeDefinitionPosition,
+};
+use crate::schema::FederationSchema;
+use apollo_compiler::schema::{
+    Component, ComponentName, ExtendedType, FieldDefinition, InputValueDefinition, Name,
+};
+use apollo_compiler::{ast, Node};
+use indexmap::{IndexMap, IndexSet};
+
+impl FederationSchema {
+    /// Inserts the given types, which may reference each other (as well as the types already in
+    /// this schema), and returns their positions. Fails if this schema already has a type with the
+    /// name of a given one, or if a given type references a type that doesn't exist, in which case
+    /// none of the given types is inserted.
+    pub fn insert_types(
+        &mut self,
+        types: impl IntoIterator<Item = ExtendedType>,
+    ) -> Result<Vec<TypeDefinitionPosition>, FederationError> {
+        // The types are inserted into a copy of this schema, which replaces it only once the whole
+        // batch is inserted, as position insertions can fail midway.
+        let mut schema = FederationSchema {
+            schema: self.schema.clone(),
+            metadata: self.metadata.take(),
+            referencers: self.referencers.clone(),
+        };
+        match schema.insert_types_partially(types.into_iter().collect()) {
+            Ok(positions) => {
+                *self = schema;
+                Ok(positions)
+            }
+            Err(error) => {
+                self.metadata = schema.metadata;
+                Err(error)
+            }
+        }
+    }
+
+    /// Same as `insert_types()`, but leaves the types inserted so far in this schema on failure.
+    fn insert_types_partially(
+        &mut self,
+        types: Vec<ExtendedType>,
+    ) -> Result<Vec<TypeDefinitionPosition>, FederationError> {
+        let positions = types
+            .iter()
+            .map(|type_| {
+                let type_name = type_.name().clone();
+                match type_ {
+                    ExtendedType::Scalar(_) => ScalarTypeDefinitionPosition { type_name }.into(),
+                    ExtendedType::Object(_) => ObjectTypeDefinitionPosition { type_name }.into(),
+                    ExtendedType::Interface(_) => {
+                        InterfaceTypeDefinitionPosition { type_name }.into()
+                    }
+                    ExtendedType::Union(_) => UnionTypeDefinitionPosition { type_name }.into(),
+                    ExtendedType::Enum(_) => EnumTypeDefinitionPosition { type_name }.into(),
+                    ExtendedType::InputObject(_) => {
+                        InputObjectTypeDefinitionPosition { type_name }.into()
+                    }
+                }
+            })
+            .collect::<Vec<TypeDefinitionPosition>>();
+        for position in &positions {
+            if self.schema.types.contains_key(position.type_name()) {
+                return Err(SingleFederationError::Internal {
+                    message: format!("Type \"{}\" already exists in schema", position),
+                }
+                .into());
+            }
+        }
+        // Types are first inserted without their fields, implemented interfaces or members, which
+        // are then inserted once all types exist, so that types can reference each other
+        // regardless of their order.
+        for position in &positions {
+            match position {
+                TypeDefinitionPosition::Scalar(position) => position.pre_insert(self)?,
+                TypeDefinitionPosition::Object(position) => position.pre_insert(self)?,
+                TypeDefinitionPosition::Interface(position) => position.pre_insert(self)?,
+                TypeDefinitionPosition::Union(position) => position.pre_insert(self)?,
+                TypeDefinitionPosition::Enum(position) => position.pre_insert(self)?,
+                TypeDefinitionPosition::InputObject(position) => position.pre_insert(self)?,
+            }
+        }
+        let mut components = Vec::with_capacity(types.len());
+        for (position, type_) in positions.iter().zip(types) {
+            match (position, type_) {
+                (TypeDefinitionPosition::Scalar(position), ExtendedType::Scalar(type_)) => {
+                    position.insert(self, type_)?;
+                }
+                (TypeDefinitionPosition::Object(position), ExtendedType::Object(mut type_)) => {
+                    let type_mut = type_.make_mut();
+                    let interfaces = std::mem::take(&mut type_mut.implements_interfaces);
+                    let fields = std::mem::take(&mut type_mut.fields);
+                    position.insert(self, type_)?;
+                    components.push(TypeComponents::Object(position, interfaces, fields));
+                }
+                (
+                    TypeDefinitionPosition::Interface(position),
+                    ExtendedType::Interface(mut type_),
+                ) => {
+                    let type_mut = type_.make_mut();
+                    let interfaces = std::mem::take(&mut type_mut.implements_interfaces);
+                    let fields = std::mem::take(&mut type_mut.fields);
+                    position.insert(self, type_)?;
+                    components.push(TypeComponents::Interface(position, interfaces, fields));
+                }
+                (TypeDefinitionPosition::Union(position), ExtendedType::Union(mut type_)) => {
+                    let members = std::mem::take(&mut type_.make_mut().members);
+                    position.insert(self, type_)?;
+                    components.push(TypeComponents::Union(position, members));
+                }
+                (TypeDefinitionPosition::Enum(position), ExtendedType::Enum(type_)) => {
+                    position.insert(self, type_)?;
+                }
+                (
+                    TypeDefinitionPosition::InputObject(position),
+                    ExtendedType::InputObject(mut type_),
+                ) => {
+                    let fields = std::mem::take(&mut type_.make_mut().fields);
+                    position.insert(self, type_)?;
+                    components.push(TypeComponents::InputObject(position, fields));
+                }
+                _ => unreachable!("positions are computed from the kinds of the types"),
+            }
+        }
+        for components in components {
+            match components {
+                TypeComponents::Object(position, interfaces, fields) => {
+                    for interface in interfaces {
+                        position.insert_implements_interface(self, interface)?;
+                    }
+                    for (field_name, field) in fields {
+                        position.field(field_name).insert(self, field)?;
+                    }
+                }
+                TypeComponents::Interface(position, interfaces, fields) => {
+                    for interface in interfaces {
+                        position.insert_implements_interface(self, interface)?;
+                    }
+                    for (field_name, field) in fields {
+                        position.field(field_name).insert(self, field)?;
+                    }
+                }
+                TypeComponents::Union(position, members) => {
+                    for member in members {
+                        position.insert_member(self, member)?;
+                    }
+                }
+                TypeComponents::InputObject(position, fields) => {
+                    for (field_name, field) in fields {
+                        position.field(field_name).insert(self, field)?;
+                    }
+                }
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Inserts the given field into the given object or interface type, and returns its position.
+    /// Fails if the type doesn't exist or already has a field with that name, or if the field
+    /// references a type that doesn't exist.
+    pub fn insert_field(
+        &mut self,
+        parent: &ObjectOrInterfaceTypeDefinitionPosition,
+        field: Component<FieldDefinition>,
+    ) -> Result<ObjectOrInterfaceFieldDefinitionPosition, FederationError> {
+        let position = parent.field(field.name.clone());
+        match &position {
+            ObjectOrInterfaceFieldDefinitionPosition::Object(position) => {
+                position.insert(self, field)?
+            }
+            ObjectOrInterfaceFieldDefinitionPosition::Interface(position) => {
+                position.insert(self, field)?
+            }
+        }
+        Ok(position)
+    }
+
+    /// Removes the given type, along with the schema elements referencing it (e.g. the fields
+    /// returning it), recursively (e.g. an object type left without fields is removed as well).
+    /// Does nothing if this schema has no such type.
+    pub fn remove_type(
+        &mut self,
+        position: &TypeDefinitionPosition,
+    ) -> Result<(), FederationError> {
+        match position {
+            TypeDefinitionPosition::Scalar(position) => position.remove_recursive(self),
+            TypeDefinitionPosition::Object(position) => position.remove_recursive(self),
+            TypeDefinitionPosition::Interface(position) => position.remove_recursive(self),
+            TypeDefinitionPosition::Union(position) => position.remove_recursive(self),
+            TypeDefinitionPosition::Enum(position) => position.remove_recursive(self),
+            TypeDefinitionPosition::InputObject(position) => position.remove_recursive(self),
+        }
+    }
+
+    /// Renames the given type, updating all the references to it (root operation types, field and
+    /// argument types, implemented interfaces and union members), and returns its new position.
+    /// The type keeps its place in the definition order of the schema. Fails for built-in types,
+    /// or if this schema already has a type with the new name.
+    ///
+    /// Unlike the other mutations, which update referencers incrementally, renaming recomputes the
+    /// referencers of the whole schema.
+    pub fn rename_type(
+        &mut self,
+        position: &TypeDefinitionPosition,
+        new_name: Name,
+    ) -> Result<TypeDefinitionPosition, FederationError> {
+        let type_ = position.get(&self.schema)?;
+        if type_.is_built_in() || is_graphql_reserved_name(position.type_name()) {
+            return Err(SingleFederationError::Internal {
+                message: format!("Built-in type \"{}\" cannot be renamed", position),
+            }
+            .into());
+        }
+        if self.schema.types.contains_key(&new_name) {
+            return Err(SingleFederationError::Internal {
+                message: format!(
+                    "Type \"{}\" cannot be renamed to \"{}\", which already exists in schema",
+                    position, new_name,
+                ),
+            }
+            .into());
+        }
+        let old_name = position.type_name();
+        let mut schema = self.schema.clone();
+        schema.types = std::mem::take(&mut schema.types)
+            .into_iter()
+            .map(|(type_name, mut type_)| {
+                if type_name == *old_name {
+                    set_type_name(&mut type_, new_name.clone());
+                }
+                rename_type_references(&mut type_, old_name, &new_name);
+                (type_.name().clone(), type_)
+            })
+            .collect();
+        let schema_definition = schema.schema_definition.make_mut();
+        for root in [
+            &mut schema_definition.query,
+            &mut schema_definition.mutation,
+            &mut schema_definition.subscription,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            rename_component_name(root, old_name, &new_name);
+        }
+        for directive_definition in schema.directive_definitions.values_mut() {
+            for argument in &mut directive_definition.make_mut().arguments {
+                rename_argument_type(argument, old_name, &new_name);
+            }
+        }
+        *self = FederationSchema::new(schema)?;
+        self.get_type(new_name)
+    }
+}
+
+/// The components of a type inserted by `FederationSchema::insert_types()` once all types exist.
+enum TypeComponents<'a> {
+    Object(
+        &'a ObjectTypeDefinitionPosition,
+        IndexSet<ComponentName>,
+        IndexMap<Name, Component<FieldDefinition>>,
+    ),
+    Interface(
+        &'a InterfaceTypeDefinitionPosition,
+        IndexSet<ComponentName>,
+        IndexMap<Name, Component<FieldDefinition>>,
+    ),
+    Union(&'a UnionTypeDefinitionPosition, IndexSet<ComponentName>),
+    InputObject(
+        &'a InputObjectTypeDefinitionPosition,
+        IndexMap<Name, Component<InputValueDefinition>>,
+    ),
+}
+
+fn set_type_name(type_: &mut ExtendedType, name: Name) {
+    match type_ {
+        ExtendedType::Scalar(type_) => type_.make_mut().name = name,
+        ExtendedType::Object(type_) => type_.make_mut().name = name,
+        ExtendedType::Interface(type_) => type_.make_mut().name = name,
+        ExtendedType::Union(type_) => type_.make_mut().name = name,
+        ExtendedType::Enum(type_) => type_.make_mut().name = name,
+        ExtendedType::InputObject(type_) => type_.make_mut().name = name,
+    }
+}
+
+/// Renames the references to the type `old_name` within the definition of the given type.
+fn rename_type_references(type_: &mut ExtendedType, old_name: &Name, new_name: &Name) {
+    match type_ {
+        ExtendedType::Scalar(_) | ExtendedType::Enum(_) => {}
+        ExtendedType::Object(type_) => {
+            let type_ = type_.make_mut();
+            rename_component_names(&mut type_.implements_interfaces, old_name, new_name);
+            for field in type_.fields.values_mut() {
+                rename_field_types(field, old_name, new_name);
+            }
+        }
+        ExtendedType::Interface(type_) => {
+            let type_ = type_.make_mut();
+            rename_component_names(&mut type_.implements_interfaces, old_name, new_name);
+            for field in type_.fields.values_mut() {
+                rename_field_types(field, old_name, new_name);
+            }
+        }
+        ExtendedType::Union(type_) => {
+            rename_component_names(&mut type_.make_mut().members, old_name, new_name);
+        }
+        ExtendedType::InputObject(type_) => {
+            for field in type_.make_mut().fields.values_mut() {
+                if field.ty.inner_named_type() == old_name {
+                    rename_named_type(field.make_mut().ty.make_mut(), new_name);
+                }
+            }
+        }
+    }
+}
+
+fn rename_component_names(names: &mut IndexSet<ComponentName>, old_name: &Name, new_name: &Name) {
+    if names.iter().any(|name| name.name == *old_name) {
+        *names = std::mem::take(names)
+            .into_iter()
+            .map(|mut name| {
+                rename_component_name(&mut name, old_name, new_name);
+                name
+            })
+            .collect();
+    }
+}
+
+fn rename_component_name(name: &mut ComponentName, old_name: &Name, new_name: &Name) {
+    if name.name == *old_name {
+        name.name = new_name.clone();
+    }
+}
+
+fn rename_field_types(field: &mut Component<FieldDefinition>, old_name: &Name, new_name: &Name) {
+    let references_type = field.ty.inner_named_type() == old_name
+        || field
+            .arguments
+            .iter()
+            .any(|argument| argument.ty.inner_named_type() == old_name);
+    if !references_type {
+        return;
+    }
+    let field = field.make_mut();
+    if field.ty.inner_named_type() == old_name {
+        rename_named_type(&mut field.ty, new_name);
+    }
+    for argument in &mut field.arguments {
+        rename_argument_type(argument, old_name, new_name);
+    }
+}
+
+fn rename_argument_type(
+    argument: &mut Node<InputValueDefinition>,
+    old_name: &Name,
+    new_name: &Name,
+) {
+    if argument.ty.inner_named_type() == old_name {
+        rename_named_type(argument.make_mut().ty.make_mut(), new_name);
+    }
+}
+
+/// Replaces the named type within the given (possibly wrapped) type.
+fn rename_named_type(ty: &mut ast::Type, new_name: &Name) {
+    match ty {
+        ast::Type::Named(name) | ast::Type::NonNullNamed(name) => *name = new_name.clone(),
+        ast::Type::List(ty) | ast::Type::NonNullList(ty) => rename_named_type(ty, new_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::position::{
+        CompositeTypeDefinitionPosition, InterfaceTypeDefinitionPosition,
+        ObjectOrInterfaceTypeDefinitionPosition, ObjectTypeDefinitionPosition,
+        TypeDefinitionPosition,
+    };
+    use crate::schema::FederationSchema;
+    use apollo_compiler::schema::{Component, ExtendedType, FieldDefinition};
+    use apollo_compiler::{name, ty, Schema};
+
+    fn parse(schema: &str) -> FederationSchema {
+        FederationSchema::new(Schema::parse(schema, "schema.graphql").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn inserts_no_type_of_a_failing_batch() {
+        let mut schema = parse(
+            r#"
+type Query {
+  a: Int
+}
+"#,
+        );
+        let types = Schema::parse(
+            r#"
+type A {
+  b: B
+}
+
+type B {
+  missing: Missing
+}
+"#,
+            "types.graphql",
+        )
+        .unwrap()
+        .types;
+        let a = types["A"].clone();
+        let b = types["B"].clone();
+        let sdl = schema.schema().to_string();
+        // The second type conflicts with the first one, or references a type that doesn't exist.
+        for batch in [vec![a.clone(), a.clone()], vec![a, b]] {
+            assert!(schema.insert_types(batch).is_err());
+            assert!(schema.try_get_type(name!("A")).is_none());
+            assert!(schema.try_get_type(name!("B")).is_none());
+            assert!(!schema.referencers().contains_type_name(&name!("A")));
+            assert!(!schema.referencers().contains_type_name(&name!("B")));
+            assert_eq!(schema.schema().to_string(), sdl);
+        }
+        // The schema is still usable after a failed insertion.
+        schema
+            .insert_types([parse("type C { c: Int }").schema().types["C"].clone()])
+            .unwrap();
+        assert!(schema.try_get_type(name!("C")).is_some());
+    }
+
+    #[test]
+    fn inserts_renames_and_removes_types() {
+        let mut schema = parse(
+            r#"
+type Query {
+  i: I
+}
+
+interface I {
+  id: ID!
+}
+"#,
+        );
+        let new_types = Schema::parse(
+            r#"
+interface I {
+  id: ID!
+}
+
+type A implements I {
+  id: ID!
+  b: [B!]
+}
+
+type B implements I {
+  id: ID!
+}
+"#,
+            "types.graphql",
+        )
+        .unwrap()
+        .types
+        .into_iter()
+        .filter(|(type_name, _)| type_name == "A" || type_name == "B")
+        .map(|(_, type_)| type_)
+        .collect::<Vec<ExtendedType>>();
+        // "A" references "B", which is inserted after it.
+        let positions = schema.insert_types(new_types).unwrap();
+        assert_eq!(
+            positions,
+            vec![
+                TypeDefinitionPosition::from(ObjectTypeDefinitionPosition {
+                    type_name: name!("A")
+                }),
+                ObjectTypeDefinitionPosition {
+                    type_name: name!("B")
+                }
+                .into(),
+            ]
+        );
+        let interface = InterfaceTypeDefinitionPosition {
+            type_name: name!("I"),
+        };
+        schema
+            .insert_field(
+                &ObjectOrInterfaceTypeDefinitionPosition::Interface(interface.clone()),
+                Component::new(FieldDefinition {
+                    description: None,
+                    name: name!("b"),
+                    arguments: vec![],
+                    ty: ty!(B),
+                    directives: Default::default(),
+                }),
+            )
+            .unwrap();
+        assert!(schema
+            .insert_types([schema.schema().types["B"].clone()])
+            .is_err());
+
+        let renamed = schema
+            .rename_type(&schema.get_type(name!("B")).unwrap(), name!("C"))
+            .unwrap();
+        assert_eq!(renamed.type_name(), "C");
+        let possible_runtime_types = schema
+            .possible_runtime_types(CompositeTypeDefinitionPosition::Interface(
+                interface.clone(),
+            ))
+            .unwrap();
+        assert_eq!(
+            possible_runtime_types
+                .iter()
+                .map(|type_| type_.type_name.as_str())
+                .collect::<Vec<_>>(),
+            ["A", "C"]
+        );
+        assert!(schema
+            .rename_type(&schema.get_type(name!("A")).unwrap(), name!("Query"))
+            .is_err());
+        assert!(schema
+            .rename_type(&schema.get_type(name!("String")).unwrap(), name!("Text"))
+            .is_err());
+        insta::assert_snapshot!(schema.schema(), @r###"
+        type Query {
+          i: I
+        }
+
+        interface I {
+          id: ID!
+          b: C
+        }
+
+        type A implements I {
+          id: ID!
+          b: [C!]
+        }
+
+        type C implements I {
+          id: ID!
+        }
+        "###);
+
+        // Removing "C" removes the fields returning it, and "I" is left with its other field.
+        schema.remove_type(&renamed).unwrap();
+        assert!(schema.try_get_type(name!("C")).is_none());
+        assert!(schema.referencers().interface_types["I"]
+            .object_types
+            .iter()
+            .all(|type_| type_.type_name != "C"));
+        insta::assert_snapshot!(schema.schema(), @r###"
+        type Query {
+          i: I
+        }
+
+        interface I {
+          id: ID!
+        }
+
+        type A implements I {
+          id: ID!
+        }
+        "###);
+    }
+}
@@ -1,3 +1,38 @@
+//! Typed references to the definitions of a schema (types, fields, arguments, enum values,
+//! directive definitions, and the schema definition and its root operations).
+//!
+//! A position only stores names (e.g. `User.id` is the field `id` of the object type `User`), so
+//! it is cheap to clone, hash and compare, and can be used with any schema that has the
+//! definition it points to: `get()` looks the definition up in a schema (failing if it's not
+//! there, or not of the kind of the position), and `try_get()` returns `None` instead. Positions of
+//! different kinds convert into each other with `From`/`TryFrom` (e.g. an
+//! `ObjectTypeDefinitionPosition` is a `CompositeTypeDefinitionPosition`, and a
+//! `TypeDefinitionPosition` may be one). `FederationSchema::get_type()` gives the position of a
+//! type from its name.
+//!
+//! ```
+//! use apollo_compiler::{name, Schema};
+//! use apollo_federation::schema::position::{
+//!     CompositeTypeDefinitionPosition, ObjectTypeDefinitionPosition,
+//! };
+//! use apollo_federation::schema::ValidFederationSchema;
+//!
+//! let schema = Schema::parse_and_validate(
+//!     "type Query { me: User } type User { id: ID! }",
+//!     "schema.graphql",
+//! )
+//! .unwrap();
+//! let schema = ValidFederationSchema::new(schema)?;
+//! let user: ObjectTypeDefinitionPosition = schema.get_type(name!("User"))?.try_into()?;
+//! let id = user.field(name!("id"));
+//! assert_eq!(id.to_string(), "User.id");
+//! assert_eq!(id.get(schema.schema())?.ty.to_string(), "ID!");
+//! assert!(user.field(name!("name")).try_get(schema.schema()).is_none());
+//!
+//! let user = CompositeTypeDefinitionPosition::from(user);
+//! assert_eq!(user.introspection_typename_field().to_string(), "User.__typename");
+//! # Ok::<(), apollo_federation::error::FederationError>(())
+//! ```
 use crate::error::{FederationError, SingleFederationError};
 use crate::link::database::links_metadata;
 use crate::link::spec_definition::SpecDefinition;
@@ -21,7 +56,7 @@ use std::ops::Deref;
 use strum::IntoEnumIterator;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From, derive_more::Display)]
-pub(crate) enum TypeDefinitionPosition {
+pub enum TypeDefinitionPosition {
     Scalar(ScalarTypeDefinitionPosition),
     Object(ObjectTypeDefinitionPosition),
     Interface(InterfaceTypeDefinitionPosition),
@@ -31,7 +66,7 @@ pub(crate) enum TypeDefinitionPosition {
 }
 
 impl TypeDefinitionPosition {
-    pub(crate) fn type_name(&self) -> &Name {
+    pub fn type_name(&self) -> &Name {
         match self {
             TypeDefinitionPosition::Scalar(type_) => &type_.type_name,
             TypeDefinitionPosition::Object(type_) => &type_.type_name,
@@ -42,7 +77,7 @@ impl TypeDefinitionPosition {
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema ExtendedType, FederationError> {
@@ -71,16 +106,100 @@ impl TypeDefinitionPosition {
         }
     }
 
-    pub(crate) fn try_get<'schema>(
-        &self,
-        schema: &'schema Schema,
-    ) -> Option<&'schema ExtendedType> {
+    pub fn try_get<'schema>(&self, schema: &'schema Schema) -> Option<&'schema ExtendedType> {
         self.get(schema).ok()
     }
 }
 
+impl TryFrom<TypeDefinitionPosition> for ScalarTypeDefinitionPosition {
+    type Error = FederationError;
+
+    fn try_from(value: TypeDefinitionPosition) -> Result<Self, Self::Error> {
+        match value {
+            TypeDefinitionPosition::Scalar(value) => Ok(value),
+            _ => Err(SingleFederationError::Internal {
+                message: format!("Type \"{}\" was unexpectedly not a scalar type", value),
+            }
+            .into()),
+        }
+    }
+}
+
+impl TryFrom<TypeDefinitionPosition> for ObjectTypeDefinitionPosition {
+    type Error = FederationError;
+
+    fn try_from(value: TypeDefinitionPosition) -> Result<Self, Self::Error> {
+        match value {
+            TypeDefinitionPosition::Object(value) => Ok(value),
+            _ => Err(SingleFederationError::Internal {
+                message: format!("Type \"{}\" was unexpectedly not an object type", value),
+            }
+            .into()),
+        }
+    }
+}
+
+impl TryFrom<TypeDefinitionPosition> for InterfaceTypeDefinitionPosition {
+    type Error = FederationError;
+
+    fn try_from(value: TypeDefinitionPosition) -> Result<Self, Self::Error> {
+        match value {
+            TypeDefinitionPosition::Interface(value) => Ok(value),
+            _ => Err(SingleFederationError::Internal {
+                message: format!("Type \"{}\" was unexpectedly not an interface type", value),
+            }
+            .into()),
+        }
+    }
+}
+
+impl TryFrom<TypeDefinitionPosition> for UnionTypeDefinitionPosition {
+    type Error = FederationError;
+
+    fn try_from(value: TypeDefinitionPosition) -> Result<Self, Self::Error> {
+        match value {
+            TypeDefinitionPosition::Union(value) => Ok(value),
+            _ => Err(SingleFederationError::Internal {
+                message: format!("Type \"{}\" was unexpectedly not a union type", value),
+            }
+            .into()),
+        }
+    }
+}
+
+impl TryFrom<TypeDefinitionPosition> for EnumTypeDefinitionPosition {
+    type Error = FederationError;
+
+    fn try_from(value: TypeDefinitionPosition) -> Result<Self, Self::Error> {
+        match value {
+            TypeDefinitionPosition::Enum(value) => Ok(value),
+            _ => Err(SingleFederationError::Internal {
+                message: format!("Type \"{}\" was unexpectedly not an enum type", value),
+            }
+            .into()),
+        }
+    }
+}
+
+impl TryFrom<TypeDefinitionPosition> for InputObjectTypeDefinitionPosition {
+    type Error = FederationError;
+
+    fn try_from(value: TypeDefinitionPosition) -> Result<Self, Self::Error> {
+        match value {
+            TypeDefinitionPosition::InputObject(value) => Ok(value),
+            _ => Err(SingleFederationError::Internal {
+                message: format!(
+                    "Type \"{}\" was unexpectedly not an input object type",
+                    value
+                ),
+            }
+            .into()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From, derive_more::Display)]
-pub(crate) enum OutputTypeDefinitionPosition {
+pub enum OutputTypeDefinitionPosition {
     Scalar(ScalarTypeDefinitionPosition),
     Object(ObjectTypeDefinitionPosition),
     Interface(InterfaceTypeDefinitionPosition),
@@ -89,7 +208,7 @@ pub(crate) enum OutputTypeDefinitionPosition {
 }
 
 impl OutputTypeDefinitionPosition {
-    pub(crate) fn type_name(&self) -> &Name {
+    pub fn type_name(&self) -> &Name {
         match self {
             OutputTypeDefinitionPosition::Scalar(type_) => &type_.type_name,
             OutputTypeDefinitionPosition::Object(type_) => &type_.type_name,
@@ -138,14 +257,14 @@ impl From<AbstractTypeDefinitionPosition> for OutputTypeDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From, derive_more::Display)]
-pub(crate) enum CompositeTypeDefinitionPosition {
+pub enum CompositeTypeDefinitionPosition {
     Object(ObjectTypeDefinitionPosition),
     Interface(InterfaceTypeDefinitionPosition),
     Union(UnionTypeDefinitionPosition),
 }
 
 impl CompositeTypeDefinitionPosition {
-    pub(crate) fn type_name(&self) -> &Name {
+    pub fn type_name(&self) -> &Name {
         match self {
             CompositeTypeDefinitionPosition::Object(type_) => &type_.type_name,
             CompositeTypeDefinitionPosition::Interface(type_) => &type_.type_name,
@@ -153,10 +272,7 @@ impl CompositeTypeDefinitionPosition {
         }
     }
 
-    pub(crate) fn field(
-        &self,
-        field_name: Name,
-    ) -> Result<FieldDefinitionPosition, FederationError> {
+    pub fn field(&self, field_name: Name) -> Result<FieldDefinitionPosition, FederationError> {
         match self {
             CompositeTypeDefinitionPosition::Object(type_) => Ok(type_.field(field_name).into()),
             CompositeTypeDefinitionPosition::Interface(type_) => Ok(type_.field(field_name).into()),
@@ -178,7 +294,7 @@ impl CompositeTypeDefinitionPosition {
         }
     }
 
-    pub(crate) fn introspection_typename_field(&self) -> FieldDefinitionPosition {
+    pub fn introspection_typename_field(&self) -> FieldDefinitionPosition {
         match self {
             CompositeTypeDefinitionPosition::Object(type_) => {
                 type_.introspection_typename_field().into()
@@ -256,13 +372,13 @@ impl From<ObjectOrInterfaceTypeDefinitionPosition> for CompositeTypeDefinitionPo
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From, derive_more::Display)]
-pub(crate) enum AbstractTypeDefinitionPosition {
+pub enum AbstractTypeDefinitionPosition {
     Interface(InterfaceTypeDefinitionPosition),
     Union(UnionTypeDefinitionPosition),
 }
 
 impl AbstractTypeDefinitionPosition {
-    pub(crate) fn type_name(&self) -> &Name {
+    pub fn type_name(&self) -> &Name {
         match self {
             AbstractTypeDefinitionPosition::Interface(type_) => &type_.type_name,
             AbstractTypeDefinitionPosition::Union(type_) => &type_.type_name,
@@ -309,20 +425,20 @@ impl TryFrom<OutputTypeDefinitionPosition> for AbstractTypeDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From, derive_more::Display)]
-pub(crate) enum ObjectOrInterfaceTypeDefinitionPosition {
+pub enum ObjectOrInterfaceTypeDefinitionPosition {
     Object(ObjectTypeDefinitionPosition),
     Interface(InterfaceTypeDefinitionPosition),
 }
 
 impl ObjectOrInterfaceTypeDefinitionPosition {
-    pub(crate) fn type_name(&self) -> &Name {
+    pub fn type_name(&self) -> &Name {
         match self {
             ObjectOrInterfaceTypeDefinitionPosition::Object(type_) => &type_.type_name,
             ObjectOrInterfaceTypeDefinitionPosition::Interface(type_) => &type_.type_name,
         }
     }
 
-    pub(crate) fn field(&self, field_name: Name) -> ObjectOrInterfaceFieldDefinitionPosition {
+    pub fn field(&self, field_name: Name) -> ObjectOrInterfaceFieldDefinitionPosition {
         match self {
             ObjectOrInterfaceTypeDefinitionPosition::Object(type_) => {
                 type_.field(field_name).into()
@@ -379,14 +495,14 @@ impl TryFrom<OutputTypeDefinitionPosition> for ObjectOrInterfaceTypeDefinitionPo
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From, derive_more::Display)]
-pub(crate) enum FieldDefinitionPosition {
+pub enum FieldDefinitionPosition {
     Object(ObjectFieldDefinitionPosition),
     Interface(InterfaceFieldDefinitionPosition),
     Union(UnionTypenameFieldDefinitionPosition),
 }
 
 impl FieldDefinitionPosition {
-    pub(crate) fn type_name(&self) -> &Name {
+    pub fn type_name(&self) -> &Name {
         match self {
             FieldDefinitionPosition::Object(field) => &field.type_name,
             FieldDefinitionPosition::Interface(field) => &field.type_name,
@@ -394,7 +510,7 @@ impl FieldDefinitionPosition {
         }
     }
 
-    pub(crate) fn field_name(&self) -> &Name {
+    pub fn field_name(&self) -> &Name {
         match self {
             FieldDefinitionPosition::Object(field) => &field.field_name,
             FieldDefinitionPosition::Interface(field) => &field.field_name,
@@ -402,11 +518,11 @@ impl FieldDefinitionPosition {
         }
     }
 
-    pub(crate) fn is_introspection_typename_field(&self) -> bool {
+    pub fn is_introspection_typename_field(&self) -> bool {
         *self.field_name() == *INTROSPECTION_TYPENAME_FIELD_NAME
     }
 
-    pub(crate) fn parent(&self) -> CompositeTypeDefinitionPosition {
+    pub fn parent(&self) -> CompositeTypeDefinitionPosition {
         match self {
             FieldDefinitionPosition::Object(field) => field.parent().into(),
             FieldDefinitionPosition::Interface(field) => field.parent().into(),
@@ -414,7 +530,7 @@ impl FieldDefinitionPosition {
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Component<FieldDefinition>, FederationError> {
@@ -436,38 +552,38 @@ impl From<ObjectOrInterfaceFieldDefinitionPosition> for FieldDefinitionPosition
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From, derive_more::Display)]
-pub(crate) enum ObjectOrInterfaceFieldDefinitionPosition {
+pub enum ObjectOrInterfaceFieldDefinitionPosition {
     Object(ObjectFieldDefinitionPosition),
     Interface(InterfaceFieldDefinitionPosition),
 }
 
 impl ObjectOrInterfaceFieldDefinitionPosition {
-    pub(crate) fn type_name(&self) -> &Name {
+    pub fn type_name(&self) -> &Name {
         match self {
             ObjectOrInterfaceFieldDefinitionPosition::Object(field) => &field.type_name,
             ObjectOrInterfaceFieldDefinitionPosition::Interface(field) => &field.type_name,
         }
     }
 
-    pub(crate) fn field_name(&self) -> &Name {
+    pub fn field_name(&self) -> &Name {
         match self {
             ObjectOrInterfaceFieldDefinitionPosition::Object(field) => &field.field_name,
             ObjectOrInterfaceFieldDefinitionPosition::Interface(field) => &field.field_name,
         }
     }
 
-    pub(crate) fn is_introspection_typename_field(&self) -> bool {
+    pub fn is_introspection_typename_field(&self) -> bool {
         *self.field_name() == *INTROSPECTION_TYPENAME_FIELD_NAME
     }
 
-    pub(crate) fn parent(&self) -> ObjectOrInterfaceTypeDefinitionPosition {
+    pub fn parent(&self) -> ObjectOrInterfaceTypeDefinitionPosition {
         match self {
             ObjectOrInterfaceFieldDefinitionPosition::Object(field) => field.parent().into(),
             ObjectOrInterfaceFieldDefinitionPosition::Interface(field) => field.parent().into(),
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Component<FieldDefinition>, FederationError> {
@@ -509,10 +625,10 @@ impl ObjectOrInterfaceFieldDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct SchemaDefinitionPosition;
+pub struct SchemaDefinitionPosition;
 
 impl SchemaDefinitionPosition {
-    pub(crate) fn get<'schema>(&self, schema: &'schema Schema) -> &'schema Node<SchemaDefinition> {
+    pub fn get<'schema>(&self, schema: &'schema Schema) -> &'schema Node<SchemaDefinition> {
         &schema.schema_definition
     }
 
@@ -668,10 +784,16 @@ impl SchemaDefinitionPosition {
     }
 }
 
+impl Display for SchemaDefinitionPosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "schema")
+    }
+}
+
 #[derive(
     Debug, Copy, Clone, PartialEq, Eq, Hash, strum_macros::Display, strum_macros::EnumIter,
 )]
-pub(crate) enum SchemaRootDefinitionKind {
+pub enum SchemaRootDefinitionKind {
     #[strum(to_string = "query")]
     Query,
     #[strum(to_string = "mutation")]
@@ -691,16 +813,16 @@ impl From<SchemaRootDefinitionKind> for ast::OperationType {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct SchemaRootDefinitionPosition {
-    pub(crate) root_kind: SchemaRootDefinitionKind,
+pub struct SchemaRootDefinitionPosition {
+    pub root_kind: SchemaRootDefinitionKind,
 }
 
 impl SchemaRootDefinitionPosition {
-    pub(crate) fn parent(&self) -> SchemaDefinitionPosition {
+    pub fn parent(&self) -> SchemaDefinitionPosition {
         SchemaDefinitionPosition
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema ComponentName, FederationError> {
@@ -732,10 +854,7 @@ impl SchemaRootDefinitionPosition {
         }
     }
 
-    pub(crate) fn try_get<'schema>(
-        &self,
-        schema: &'schema Schema,
-    ) -> Option<&'schema ComponentName> {
+    pub fn try_get<'schema>(&self, schema: &'schema Schema) -> Option<&'schema ComponentName> {
         self.get(schema).ok()
     }
 
@@ -886,12 +1005,12 @@ impl Display for SchemaRootDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct ScalarTypeDefinitionPosition {
-    pub(crate) type_name: Name,
+pub struct ScalarTypeDefinitionPosition {
+    pub type_name: Name,
 }
 
 impl ScalarTypeDefinitionPosition {
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<ScalarType>, FederationError> {
@@ -916,10 +1035,7 @@ impl ScalarTypeDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
-        &self,
-        schema: &'schema Schema,
-    ) -> Option<&'schema Node<ScalarType>> {
+    pub fn try_get<'schema>(&self, schema: &'schema Schema) -> Option<&'schema Node<ScalarType>> {
         self.get(schema).ok()
     }
 
@@ -1211,31 +1327,31 @@ impl Display for ScalarTypeDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct ObjectTypeDefinitionPosition {
-    pub(crate) type_name: Name,
+pub struct ObjectTypeDefinitionPosition {
+    pub type_name: Name,
 }
 
 impl ObjectTypeDefinitionPosition {
-    pub(crate) fn field(&self, field_name: Name) -> ObjectFieldDefinitionPosition {
+    pub fn field(&self, field_name: Name) -> ObjectFieldDefinitionPosition {
         ObjectFieldDefinitionPosition {
             type_name: self.type_name.clone(),
             field_name,
         }
     }
 
-    pub(crate) fn introspection_typename_field(&self) -> ObjectFieldDefinitionPosition {
+    pub fn introspection_typename_field(&self) -> ObjectFieldDefinitionPosition {
         self.field(INTROSPECTION_TYPENAME_FIELD_NAME.clone())
     }
 
-    pub(crate) fn introspection_schema_field(&self) -> ObjectFieldDefinitionPosition {
+    pub fn introspection_schema_field(&self) -> ObjectFieldDefinitionPosition {
         self.field(name!("__schema"))
     }
 
-    pub(crate) fn introspection_type_field(&self) -> ObjectFieldDefinitionPosition {
+    pub fn introspection_type_field(&self) -> ObjectFieldDefinitionPosition {
         self.field(name!("__type"))
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<ObjectType>, FederationError> {
@@ -1260,10 +1376,7 @@ impl ObjectTypeDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
-        &self,
-        schema: &'schema Schema,
-    ) -> Option<&'schema Node<ObjectType>> {
+    pub fn try_get<'schema>(&self, schema: &'schema Schema) -> Option<&'schema Node<ObjectType>> {
         self.get(schema).ok()
     }
 
@@ -1705,23 +1818,23 @@ impl Display for ObjectTypeDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct ObjectFieldDefinitionPosition {
-    pub(crate) type_name: Name,
-    pub(crate) field_name: Name,
+pub struct ObjectFieldDefinitionPosition {
+    pub type_name: Name,
+    pub field_name: Name,
 }
 
 impl ObjectFieldDefinitionPosition {
-    pub(crate) fn is_introspection_typename_field(&self) -> bool {
+    pub fn is_introspection_typename_field(&self) -> bool {
         self.field_name == *INTROSPECTION_TYPENAME_FIELD_NAME
     }
 
-    pub(crate) fn parent(&self) -> ObjectTypeDefinitionPosition {
+    pub fn parent(&self) -> ObjectTypeDefinitionPosition {
         ObjectTypeDefinitionPosition {
             type_name: self.type_name.clone(),
         }
     }
 
-    pub(crate) fn argument(&self, argument_name: Name) -> ObjectFieldArgumentDefinitionPosition {
+    pub fn argument(&self, argument_name: Name) -> ObjectFieldArgumentDefinitionPosition {
         ObjectFieldArgumentDefinitionPosition {
             type_name: self.type_name.clone(),
             field_name: self.field_name.clone(),
@@ -1729,7 +1842,7 @@ impl ObjectFieldDefinitionPosition {
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Component<FieldDefinition>, FederationError> {
@@ -1749,7 +1862,7 @@ impl ObjectFieldDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Component<FieldDefinition>> {
@@ -2129,6 +2242,8 @@ impl ObjectFieldDefinitionPosition {
                 };
                 enum_type_referencers.object_fields.shift_remove(self);
             }
+            // The type has already been removed, along with its referencers.
+            None => {}
             _ => {
                 return Err(
                     SingleFederationError::Internal {
@@ -2152,21 +2267,21 @@ impl Display for ObjectFieldDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct ObjectFieldArgumentDefinitionPosition {
-    pub(crate) type_name: Name,
-    pub(crate) field_name: Name,
-    pub(crate) argument_name: Name,
+pub struct ObjectFieldArgumentDefinitionPosition {
+    pub type_name: Name,
+    pub field_name: Name,
+    pub argument_name: Name,
 }
 
 impl ObjectFieldArgumentDefinitionPosition {
-    pub(crate) fn parent(&self) -> ObjectFieldDefinitionPosition {
+    pub fn parent(&self) -> ObjectFieldDefinitionPosition {
         ObjectFieldDefinitionPosition {
             type_name: self.type_name.clone(),
             field_name: self.field_name.clone(),
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<InputValueDefinition>, FederationError> {
@@ -2188,7 +2303,7 @@ impl ObjectFieldArgumentDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Node<InputValueDefinition>> {
@@ -2508,6 +2623,8 @@ impl ObjectFieldArgumentDefinitionPosition {
                     .object_field_arguments
                     .shift_remove(self);
             }
+            // The type has already been removed, along with its referencers.
+            None => {}
             _ => {
                 return Err(
                     SingleFederationError::Internal {
@@ -2535,23 +2652,23 @@ impl Display for ObjectFieldArgumentDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct InterfaceTypeDefinitionPosition {
-    pub(crate) type_name: Name,
+pub struct InterfaceTypeDefinitionPosition {
+    pub type_name: Name,
 }
 
 impl InterfaceTypeDefinitionPosition {
-    pub(crate) fn field(&self, field_name: Name) -> InterfaceFieldDefinitionPosition {
+    pub fn field(&self, field_name: Name) -> InterfaceFieldDefinitionPosition {
         InterfaceFieldDefinitionPosition {
             type_name: self.type_name.clone(),
             field_name,
         }
     }
 
-    pub(crate) fn introspection_typename_field(&self) -> InterfaceFieldDefinitionPosition {
+    pub fn introspection_typename_field(&self) -> InterfaceFieldDefinitionPosition {
         self.field(INTROSPECTION_TYPENAME_FIELD_NAME.clone())
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<InterfaceType>, FederationError> {
@@ -2576,7 +2693,7 @@ impl InterfaceTypeDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Node<InterfaceType>> {
@@ -2958,23 +3075,23 @@ impl Display for InterfaceTypeDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct InterfaceFieldDefinitionPosition {
-    pub(crate) type_name: Name,
-    pub(crate) field_name: Name,
+pub struct InterfaceFieldDefinitionPosition {
+    pub type_name: Name,
+    pub field_name: Name,
 }
 
 impl InterfaceFieldDefinitionPosition {
-    pub(crate) fn is_introspection_typename_field(&self) -> bool {
+    pub fn is_introspection_typename_field(&self) -> bool {
         self.field_name == *INTROSPECTION_TYPENAME_FIELD_NAME
     }
 
-    pub(crate) fn parent(&self) -> InterfaceTypeDefinitionPosition {
+    pub fn parent(&self) -> InterfaceTypeDefinitionPosition {
         InterfaceTypeDefinitionPosition {
             type_name: self.type_name.clone(),
         }
     }
 
-    pub(crate) fn argument(&self, argument_name: Name) -> InterfaceFieldArgumentDefinitionPosition {
+    pub fn argument(&self, argument_name: Name) -> InterfaceFieldArgumentDefinitionPosition {
         InterfaceFieldArgumentDefinitionPosition {
             type_name: self.type_name.clone(),
             field_name: self.field_name.clone(),
@@ -2982,7 +3099,7 @@ impl InterfaceFieldDefinitionPosition {
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Component<FieldDefinition>, FederationError> {
@@ -3002,7 +3119,7 @@ impl InterfaceFieldDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Component<FieldDefinition>> {
@@ -3387,6 +3504,8 @@ impl InterfaceFieldDefinitionPosition {
                 };
                 enum_type_referencers.interface_fields.shift_remove(self);
             }
+            // The type has already been removed, along with its referencers.
+            None => {}
             _ => {
                 return Err(
                     SingleFederationError::Internal {
@@ -3410,21 +3529,21 @@ impl Display for InterfaceFieldDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct InterfaceFieldArgumentDefinitionPosition {
-    pub(crate) type_name: Name,
-    pub(crate) field_name: Name,
-    pub(crate) argument_name: Name,
+pub struct InterfaceFieldArgumentDefinitionPosition {
+    pub type_name: Name,
+    pub field_name: Name,
+    pub argument_name: Name,
 }
 
 impl InterfaceFieldArgumentDefinitionPosition {
-    pub(crate) fn parent(&self) -> InterfaceFieldDefinitionPosition {
+    pub fn parent(&self) -> InterfaceFieldDefinitionPosition {
         InterfaceFieldDefinitionPosition {
             type_name: self.type_name.clone(),
             field_name: self.field_name.clone(),
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<InputValueDefinition>, FederationError> {
@@ -3446,7 +3565,7 @@ impl InterfaceFieldArgumentDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Node<InputValueDefinition>> {
@@ -3773,6 +3892,8 @@ impl InterfaceFieldArgumentDefinitionPosition {
                     .interface_field_arguments
                     .shift_remove(self);
             }
+            // The type has already been removed, along with its referencers.
+            None => {}
             _ => {
                 return Err(
                     SingleFederationError::Internal {
@@ -3800,18 +3921,18 @@ impl Display for InterfaceFieldArgumentDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct UnionTypeDefinitionPosition {
-    pub(crate) type_name: Name,
+pub struct UnionTypeDefinitionPosition {
+    pub type_name: Name,
 }
 
 impl UnionTypeDefinitionPosition {
-    pub(crate) fn introspection_typename_field(&self) -> UnionTypenameFieldDefinitionPosition {
+    pub fn introspection_typename_field(&self) -> UnionTypenameFieldDefinitionPosition {
         UnionTypenameFieldDefinitionPosition {
             type_name: self.type_name.clone(),
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<UnionType>, FederationError> {
@@ -3836,10 +3957,7 @@ impl UnionTypeDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
-        &self,
-        schema: &'schema Schema,
-    ) -> Option<&'schema Node<UnionType>> {
+    pub fn try_get<'schema>(&self, schema: &'schema Schema) -> Option<&'schema Node<UnionType>> {
         self.get(schema).ok()
     }
 
@@ -4180,22 +4298,22 @@ impl Display for UnionTypeDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct UnionTypenameFieldDefinitionPosition {
-    pub(crate) type_name: Name,
+pub struct UnionTypenameFieldDefinitionPosition {
+    pub type_name: Name,
 }
 
 impl UnionTypenameFieldDefinitionPosition {
-    pub(crate) fn field_name(&self) -> &Name {
+    pub fn field_name(&self) -> &Name {
         &INTROSPECTION_TYPENAME_FIELD_NAME
     }
 
-    pub(crate) fn parent(&self) -> UnionTypeDefinitionPosition {
+    pub fn parent(&self) -> UnionTypeDefinitionPosition {
         UnionTypeDefinitionPosition {
             type_name: self.type_name.clone(),
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Component<FieldDefinition>, FederationError> {
@@ -4216,7 +4334,7 @@ impl UnionTypenameFieldDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Component<FieldDefinition>> {
@@ -4266,19 +4384,19 @@ impl Display for UnionTypenameFieldDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct EnumTypeDefinitionPosition {
-    pub(crate) type_name: Name,
+pub struct EnumTypeDefinitionPosition {
+    pub type_name: Name,
 }
 
 impl EnumTypeDefinitionPosition {
-    pub(crate) fn value(&self, value_name: Name) -> EnumValueDefinitionPosition {
+    pub fn value(&self, value_name: Name) -> EnumValueDefinitionPosition {
         EnumValueDefinitionPosition {
             type_name: self.type_name.clone(),
             value_name,
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<EnumType>, FederationError> {
@@ -4303,10 +4421,7 @@ impl EnumTypeDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
-        &self,
-        schema: &'schema Schema,
-    ) -> Option<&'schema Node<EnumType>> {
+    pub fn try_get<'schema>(&self, schema: &'schema Schema) -> Option<&'schema Node<EnumType>> {
         self.get(schema).ok()
     }
 
@@ -4600,19 +4715,19 @@ impl Display for EnumTypeDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct EnumValueDefinitionPosition {
-    pub(crate) type_name: Name,
-    pub(crate) value_name: Name,
+pub struct EnumValueDefinitionPosition {
+    pub type_name: Name,
+    pub value_name: Name,
 }
 
 impl EnumValueDefinitionPosition {
-    pub(crate) fn parent(&self) -> EnumTypeDefinitionPosition {
+    pub fn parent(&self) -> EnumTypeDefinitionPosition {
         EnumTypeDefinitionPosition {
             type_name: self.type_name.clone(),
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Component<EnumValueDefinition>, FederationError> {
@@ -4630,7 +4745,7 @@ impl EnumValueDefinitionPosition {
         })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Component<EnumValueDefinition>> {
@@ -4852,19 +4967,19 @@ impl Display for EnumValueDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct InputObjectTypeDefinitionPosition {
-    pub(crate) type_name: Name,
+pub struct InputObjectTypeDefinitionPosition {
+    pub type_name: Name,
 }
 
 impl InputObjectTypeDefinitionPosition {
-    pub(crate) fn field(&self, field_name: Name) -> InputObjectFieldDefinitionPosition {
+    pub fn field(&self, field_name: Name) -> InputObjectFieldDefinitionPosition {
         InputObjectFieldDefinitionPosition {
             type_name: self.type_name.clone(),
             field_name,
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<InputObjectType>, FederationError> {
@@ -4889,7 +5004,7 @@ impl InputObjectTypeDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Node<InputObjectType>> {
@@ -5189,19 +5304,19 @@ impl Display for InputObjectTypeDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct InputObjectFieldDefinitionPosition {
-    pub(crate) type_name: Name,
-    pub(crate) field_name: Name,
+pub struct InputObjectFieldDefinitionPosition {
+    pub type_name: Name,
+    pub field_name: Name,
 }
 
 impl InputObjectFieldDefinitionPosition {
-    pub(crate) fn parent(&self) -> InputObjectTypeDefinitionPosition {
+    pub fn parent(&self) -> InputObjectTypeDefinitionPosition {
         InputObjectTypeDefinitionPosition {
             type_name: self.type_name.clone(),
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Component<InputValueDefinition>, FederationError> {
@@ -5219,7 +5334,7 @@ impl InputObjectFieldDefinitionPosition {
         })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Component<InputValueDefinition>> {
@@ -5542,6 +5657,8 @@ impl InputObjectFieldDefinitionPosition {
                     .input_object_fields
                     .shift_remove(self);
             }
+            // The type has already been removed, along with its referencers.
+            None => {}
             _ => {
                 return Err(
                     SingleFederationError::Internal {
@@ -5565,19 +5682,19 @@ impl Display for InputObjectFieldDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct DirectiveDefinitionPosition {
-    pub(crate) directive_name: Name,
+pub struct DirectiveDefinitionPosition {
+    pub directive_name: Name,
 }
 
 impl DirectiveDefinitionPosition {
-    pub(crate) fn argument(&self, argument_name: Name) -> DirectiveArgumentDefinitionPosition {
+    pub fn argument(&self, argument_name: Name) -> DirectiveArgumentDefinitionPosition {
         DirectiveArgumentDefinitionPosition {
             directive_name: self.directive_name.clone(),
             argument_name,
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<DirectiveDefinition>, FederationError> {
@@ -5592,7 +5709,7 @@ impl DirectiveDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Node<DirectiveDefinition>> {
@@ -5806,19 +5923,19 @@ impl Display for DirectiveDefinitionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct DirectiveArgumentDefinitionPosition {
-    pub(crate) directive_name: Name,
-    pub(crate) argument_name: Name,
+pub struct DirectiveArgumentDefinitionPosition {
+    pub directive_name: Name,
+    pub argument_name: Name,
 }
 
 impl DirectiveArgumentDefinitionPosition {
-    pub(crate) fn parent(&self) -> DirectiveDefinitionPosition {
+    pub fn parent(&self) -> DirectiveDefinitionPosition {
         DirectiveDefinitionPosition {
             directive_name: self.directive_name.clone(),
         }
     }
 
-    pub(crate) fn get<'schema>(
+    pub fn get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Result<&'schema Node<InputValueDefinition>, FederationError> {
@@ -5840,7 +5957,7 @@ impl DirectiveArgumentDefinitionPosition {
             })
     }
 
-    pub(crate) fn try_get<'schema>(
+    pub fn try_get<'schema>(
         &self,
         schema: &'schema Schema,
     ) -> Option<&'schema Node<InputValueDefinition>> {
@@ -6153,6 +6270,8 @@ impl DirectiveArgumentDefinitionPosition {
                     .directive_arguments
                     .shift_remove(self);
             }
+            // The type has already been removed, along with its referencers.
+            None => {}
             _ => {
                 return Err(
                     SingleFederationError::Internal {
@@ -6175,7 +6294,7 @@ impl Display for DirectiveArgumentDefinitionPosition {
     }
 }
 
-pub(crate) fn is_graphql_reserved_name(name: &str) -> bool {
+pub fn is_graphql_reserved_name(name: &str) -> bool {
     name.starts_with("__")
 }
 
@@ -6273,7 +6392,7 @@ impl FederationSchema {
     ///    valid.
     /// The input schema may be otherwise invalid GraphQL (e.g. it may not contain a Query type). If
     /// you want a ValidFederationSchema, use ValidFederationSchema::new() instead.
-    pub(crate) fn new(schema: Schema) -> Result<FederationSchema, FederationError> {
+    pub fn new(schema: Schema) -> Result<FederationSchema, FederationError> {
         let metadata = links_metadata(&schema)?;
         let mut referencers: Referencers = Default::default();
 
@@ -1,3 +1,8 @@
+//! The index of the schema elements referencing each type and directive of a `FederationSchema`,
+//! which is kept up to date as the schema is mutated through positions. It answers questions like
+//! "which fields return this type?" or "where is this directive applied?" without walking the
+//! schema, e.g. to remove a type along with its references, or to check that hidden elements
+//! aren't referenced by visible ones.
 use crate::error::{FederationError, SingleFederationError};
 use crate::schema::position::{
     DirectiveArgumentDefinitionPosition, EnumTypeDefinitionPosition, EnumValueDefinitionPosition,
@@ -12,19 +17,47 @@ use apollo_compiler::schema::Name;
 use indexmap::{Equivalent, IndexMap, IndexSet};
 use std::hash::Hash;
 
+/// The position of a schema element referencing a type or directive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, derive_more::From, derive_more::Display)]
+pub enum ReferencerPosition {
+    Schema(SchemaDefinitionPosition),
+    SchemaRoot(SchemaRootDefinitionPosition),
+    ScalarType(ScalarTypeDefinitionPosition),
+    ObjectType(ObjectTypeDefinitionPosition),
+    ObjectField(ObjectFieldDefinitionPosition),
+    ObjectFieldArgument(ObjectFieldArgumentDefinitionPosition),
+    InterfaceType(InterfaceTypeDefinitionPosition),
+    InterfaceField(InterfaceFieldDefinitionPosition),
+    InterfaceFieldArgument(InterfaceFieldArgumentDefinitionPosition),
+    UnionType(UnionTypeDefinitionPosition),
+    UnionTypenameField(UnionTypenameFieldDefinitionPosition),
+    EnumType(EnumTypeDefinitionPosition),
+    EnumValue(EnumValueDefinitionPosition),
+    InputObjectType(InputObjectTypeDefinitionPosition),
+    InputObjectField(InputObjectFieldDefinitionPosition),
+    DirectiveArgument(DirectiveArgumentDefinitionPosition),
+}
+
+fn positions_of<'a, T: Clone + Into<ReferencerPosition> + 'a>(
+    positions: impl IntoIterator<Item = &'a T> + 'a,
+) -> impl Iterator<Item = ReferencerPosition> + 'a {
+    positions.into_iter().cloned().map(Into::into)
+}
+
+/// The referencers of each type and directive of a schema, by type or directive name.
 #[derive(Debug, Clone, Default)]
-pub(crate) struct Referencers {
-    pub(crate) scalar_types: IndexMap<Name, ScalarTypeReferencers>,
-    pub(crate) object_types: IndexMap<Name, ObjectTypeReferencers>,
-    pub(crate) interface_types: IndexMap<Name, InterfaceTypeReferencers>,
-    pub(crate) union_types: IndexMap<Name, UnionTypeReferencers>,
-    pub(crate) enum_types: IndexMap<Name, EnumTypeReferencers>,
-    pub(crate) input_object_types: IndexMap<Name, InputObjectTypeReferencers>,
-    pub(crate) directives: IndexMap<Name, DirectiveReferencers>,
+pub struct Referencers {
+    pub scalar_types: IndexMap<Name, ScalarTypeReferencers>,
+    pub object_types: IndexMap<Name, ObjectTypeReferencers>,
+    pub interface_types: IndexMap<Name, InterfaceTypeReferencers>,
+    pub union_types: IndexMap<Name, UnionTypeReferencers>,
+    pub enum_types: IndexMap<Name, EnumTypeReferencers>,
+    pub input_object_types: IndexMap<Name, InputObjectTypeReferencers>,
+    pub directives: IndexMap<Name, DirectiveReferencers>,
 }
 
 impl Referencers {
-    pub(crate) fn contains_type_name<Q: Hash + Equivalent<Name>>(&self, name: &Q) -> bool {
+    pub fn contains_type_name<Q: Hash + Equivalent<Name>>(&self, name: &Q) -> bool {
         self.scalar_types.contains_key(name)
             || self.object_types.contains_key(name)
             || self.interface_types.contains_key(name)
@@ -33,7 +66,7 @@ impl Referencers {
             || self.input_object_types.contains_key(name)
     }
 
-    pub(crate) fn get_scalar_type<Q: Hash + Equivalent<Name>>(
+    pub fn get_scalar_type<Q: Hash + Equivalent<Name>>(
         &self,
         name: &Q,
     ) -> Result<&ScalarTypeReferencers, FederationError> {
@@ -45,7 +78,7 @@ impl Referencers {
         })
     }
 
-    pub(crate) fn get_object_type<Q: Hash + Equivalent<Name>>(
+    pub fn get_object_type<Q: Hash + Equivalent<Name>>(
         &self,
         name: &Q,
     ) -> Result<&ObjectTypeReferencers, FederationError> {
@@ -57,7 +90,7 @@ impl Referencers {
         })
     }
 
-    pub(crate) fn get_interface_type<Q: Hash + Equivalent<Name>>(
+    pub fn get_interface_type<Q: Hash + Equivalent<Name>>(
         &self,
         name: &Q,
     ) -> Result<&InterfaceTypeReferencers, FederationError> {
@@ -69,7 +102,7 @@ impl Referencers {
         })
     }
 
-    pub(crate) fn get_union_type<Q: Hash + Equivalent<Name>>(
+    pub fn get_union_type<Q: Hash + Equivalent<Name>>(
         &self,
         name: &Q,
     ) -> Result<&UnionTypeReferencers, FederationError> {
@@ -81,7 +114,7 @@ impl Referencers {
         })
     }
 
-    pub(crate) fn get_enum_type<Q: Hash + Equivalent<Name>>(
+    pub fn get_enum_type<Q: Hash + Equivalent<Name>>(
         &self,
         name: &Q,
     ) -> Result<&EnumTypeReferencers, FederationError> {
@@ -93,7 +126,7 @@ impl Referencers {
         })
     }
 
-    pub(crate) fn get_input_object_type<Q: Hash + Equivalent<Name>>(
+    pub fn get_input_object_type<Q: Hash + Equivalent<Name>>(
         &self,
         name: &Q,
     ) -> Result<&InputObjectTypeReferencers, FederationError> {
@@ -105,7 +138,7 @@ impl Referencers {
         })
     }
 
-    pub(crate) fn get_directive<Q: Hash + Equivalent<Name>>(
+    pub fn get_directive<Q: Hash + Equivalent<Name>>(
         &self,
         name: &Q,
     ) -> Result<&DirectiveReferencers, FederationError> {
@@ -119,70 +152,231 @@ impl Referencers {
 }
 
 #[derive(Debug, Clone, Default)]
-pub(crate) struct ScalarTypeReferencers {
-    pub(crate) object_fields: IndexSet<ObjectFieldDefinitionPosition>,
-    pub(crate) object_field_arguments: IndexSet<ObjectFieldArgumentDefinitionPosition>,
-    pub(crate) interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
-    pub(crate) interface_field_arguments: IndexSet<InterfaceFieldArgumentDefinitionPosition>,
-    pub(crate) union_fields: IndexSet<UnionTypenameFieldDefinitionPosition>,
-    pub(crate) input_object_fields: IndexSet<InputObjectFieldDefinitionPosition>,
-    pub(crate) directive_arguments: IndexSet<DirectiveArgumentDefinitionPosition>,
+pub struct ScalarTypeReferencers {
+    pub object_fields: IndexSet<ObjectFieldDefinitionPosition>,
+    pub object_field_arguments: IndexSet<ObjectFieldArgumentDefinitionPosition>,
+    pub interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
+    pub interface_field_arguments: IndexSet<InterfaceFieldArgumentDefinitionPosition>,
+    pub union_fields: IndexSet<UnionTypenameFieldDefinitionPosition>,
+    pub input_object_fields: IndexSet<InputObjectFieldDefinitionPosition>,
+    pub directive_arguments: IndexSet<DirectiveArgumentDefinitionPosition>,
+}
+
+impl ScalarTypeReferencers {
+    /// The positions of the elements referencing the scalar type.
+    pub fn positions(&self) -> impl Iterator<Item = ReferencerPosition> + '_ {
+        positions_of(&self.object_fields)
+            .chain(positions_of(&self.object_field_arguments))
+            .chain(positions_of(&self.interface_fields))
+            .chain(positions_of(&self.interface_field_arguments))
+            .chain(positions_of(&self.union_fields))
+            .chain(positions_of(&self.input_object_fields))
+            .chain(positions_of(&self.directive_arguments))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
-pub(crate) struct ObjectTypeReferencers {
-    pub(crate) schema_roots: IndexSet<SchemaRootDefinitionPosition>,
-    pub(crate) object_fields: IndexSet<ObjectFieldDefinitionPosition>,
-    pub(crate) interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
-    pub(crate) union_types: IndexSet<UnionTypeDefinitionPosition>,
+pub struct ObjectTypeReferencers {
+    pub schema_roots: IndexSet<SchemaRootDefinitionPosition>,
+    pub object_fields: IndexSet<ObjectFieldDefinitionPosition>,
+    pub interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
+    pub union_types: IndexSet<UnionTypeDefinitionPosition>,
+}
+
+impl ObjectTypeReferencers {
+    /// The positions of the elements referencing the object type.
+    pub fn positions(&self) -> impl Iterator<Item = ReferencerPosition> + '_ {
+        positions_of(&self.schema_roots)
+            .chain(positions_of(&self.object_fields))
+            .chain(positions_of(&self.interface_fields))
+            .chain(positions_of(&self.union_types))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
-pub(crate) struct InterfaceTypeReferencers {
-    pub(crate) object_types: IndexSet<ObjectTypeDefinitionPosition>,
-    pub(crate) object_fields: IndexSet<ObjectFieldDefinitionPosition>,
-    pub(crate) interface_types: IndexSet<InterfaceTypeDefinitionPosition>,
-    pub(crate) interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
+pub struct InterfaceTypeReferencers {
+    pub object_types: IndexSet<ObjectTypeDefinitionPosition>,
+    pub object_fields: IndexSet<ObjectFieldDefinitionPosition>,
+    pub interface_types: IndexSet<InterfaceTypeDefinitionPosition>,
+    pub interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
+}
+
+impl InterfaceTypeReferencers {
+    /// The positions of the elements referencing the interface type.
+    pub fn positions(&self) -> impl Iterator<Item = ReferencerPosition> + '_ {
+        positions_of(&self.object_types)
+            .chain(positions_of(&self.object_fields))
+            .chain(positions_of(&self.interface_types))
+            .chain(positions_of(&self.interface_fields))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
-pub(crate) struct UnionTypeReferencers {
-    pub(crate) object_fields: IndexSet<ObjectFieldDefinitionPosition>,
-    pub(crate) interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
+pub struct UnionTypeReferencers {
+    pub object_fields: IndexSet<ObjectFieldDefinitionPosition>,
+    pub interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
+}
+
+impl UnionTypeReferencers {
+    /// The positions of the elements referencing the union type.
+    pub fn positions(&self) -> impl Iterator<Item = ReferencerPosition> + '_ {
+        positions_of(&self.object_fields).chain(positions_of(&self.interface_fields))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
-pub(crate) struct EnumTypeReferencers {
-    pub(crate) object_fields: IndexSet<ObjectFieldDefinitionPosition>,
-    pub(crate) object_field_arguments: IndexSet<ObjectFieldArgumentDefinitionPosition>,
-    pub(crate) interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
-    pub(crate) interface_field_arguments: IndexSet<InterfaceFieldArgumentDefinitionPosition>,
-    pub(crate) input_object_fields: IndexSet<InputObjectFieldDefinitionPosition>,
-    pub(crate) directive_arguments: IndexSet<DirectiveArgumentDefinitionPosition>,
+pub struct EnumTypeReferencers {
+    pub object_fields: IndexSet<ObjectFieldDefinitionPosition>,
+    pub object_field_arguments: IndexSet<ObjectFieldArgumentDefinitionPosition>,
+    pub interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
+    pub interface_field_arguments: IndexSet<InterfaceFieldArgumentDefinitionPosition>,
+    pub input_object_fields: IndexSet<InputObjectFieldDefinitionPosition>,
+    pub directive_arguments: IndexSet<DirectiveArgumentDefinitionPosition>,
+}
+
+impl EnumTypeReferencers {
+    /// The positions of the elements referencing the enum type.
+    pub fn positions(&self) -> impl Iterator<Item = ReferencerPosition> + '_ {
+        positions_of(&self.object_fields)
+            .chain(positions_of(&self.object_field_arguments))
+            .chain(positions_of(&self.interface_fields))
+            .chain(positions_of(&self.interface_field_arguments))
+            .chain(positions_of(&self.input_object_fields))
+            .chain(positions_of(&self.directive_arguments))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
-pub(crate) struct InputObjectTypeReferencers {
-    pub(crate) object_field_arguments: IndexSet<ObjectFieldArgumentDefinitionPosition>,
-    pub(crate) interface_field_arguments: IndexSet<InterfaceFieldArgumentDefinitionPosition>,
-    pub(crate) input_object_fields: IndexSet<InputObjectFieldDefinitionPosition>,
-    pub(crate) directive_arguments: IndexSet<DirectiveArgumentDefinitionPosition>,
+pub struct InputObjectTypeReferencers {
+    pub object_field_arguments: IndexSet<ObjectFieldArgumentDefinitionPosition>,
+    pub interface_field_arguments: IndexSet<InterfaceFieldArgumentDefinitionPosition>,
+    pub input_object_fields: IndexSet<InputObjectFieldDefinitionPosition>,
+    pub directive_arguments: IndexSet<DirectiveArgumentDefinitionPosition>,
+}
+
+impl InputObjectTypeReferencers {
+    /// The positions of the elements referencing the input object type.
+    pub fn positions(&self) -> impl Iterator<Item = ReferencerPosition> + '_ {
+        positions_of(&self.object_field_arguments)
+            .chain(positions_of(&self.interface_field_arguments))
+            .chain(positions_of(&self.input_object_fields))
+            .chain(positions_of(&self.directive_arguments))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
-pub(crate) struct DirectiveReferencers {
-    pub(crate) schema: Option<SchemaDefinitionPosition>,
-    pub(crate) scalar_types: IndexSet<ScalarTypeDefinitionPosition>,
-    pub(crate) object_types: IndexSet<ObjectTypeDefinitionPosition>,
-    pub(crate) object_fields: IndexSet<ObjectFieldDefinitionPosition>,
-    pub(crate) object_field_arguments: IndexSet<ObjectFieldArgumentDefinitionPosition>,
-    pub(crate) interface_types: IndexSet<InterfaceTypeDefinitionPosition>,
-    pub(crate) interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
-    pub(crate) interface_field_arguments: IndexSet<InterfaceFieldArgumentDefinitionPosition>,
-    pub(crate) union_types: IndexSet<UnionTypeDefinitionPosition>,
-    pub(crate) enum_types: IndexSet<EnumTypeDefinitionPosition>,
-    pub(crate) enum_values: IndexSet<EnumValueDefinitionPosition>,
-    pub(crate) input_object_types: IndexSet<InputObjectTypeDefinitionPosition>,
-    pub(crate) input_object_fields: IndexSet<InputObjectFieldDefinitionPosition>,
-    pub(crate) directive_arguments: IndexSet<DirectiveArgumentDefinitionPosition>,
+pub struct DirectiveReferencers {
+    pub schema: Option<SchemaDefinitionPosition>,
+    pub scalar_types: IndexSet<ScalarTypeDefinitionPosition>,
+    pub object_types: IndexSet<ObjectTypeDefinitionPosition>,
+    pub object_fields: IndexSet<ObjectFieldDefinitionPosition>,
+    pub object_field_arguments: IndexSet<ObjectFieldArgumentDefinitionPosition>,
+    pub interface_types: IndexSet<InterfaceTypeDefinitionPosition>,
+    pub interface_fields: IndexSet<InterfaceFieldDefinitionPosition>,
+    pub interface_field_arguments: IndexSet<InterfaceFieldArgumentDefinitionPosition>,
+    pub union_types: IndexSet<UnionTypeDefinitionPosition>,
+    pub enum_types: IndexSet<EnumTypeDefinitionPosition>,
+    pub enum_values: IndexSet<EnumValueDefinitionPosition>,
+    pub input_object_types: IndexSet<InputObjectTypeDefinitionPosition>,
+    pub input_object_fields: IndexSet<InputObjectFieldDefinitionPosition>,
+    pub directive_arguments: IndexSet<DirectiveArgumentDefinitionPosition>,
+}
+
+impl DirectiveReferencers {
+    /// The positions of the elements referencing the directive.
+    pub fn positions(&self) -> impl Iterator<Item = ReferencerPosition> + '_ {
+        positions_of(&self.schema)
+            .chain(positions_of(&self.scalar_types))
+            .chain(positions_of(&self.object_types))
+            .chain(positions_of(&self.object_fields))
+            .chain(positions_of(&self.object_field_arguments))
+            .chain(positions_of(&self.interface_types))
+            .chain(positions_of(&self.interface_fields))
+            .chain(positions_of(&self.interface_field_arguments))
+            .chain(positions_of(&self.union_types))
+            .chain(positions_of(&self.enum_types))
+            .chain(positions_of(&self.enum_values))
+            .chain(positions_of(&self.input_object_types))
+            .chain(positions_of(&self.input_object_fields))
+            .chain(positions_of(&self.directive_arguments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::position::{
+        DirectiveDefinitionPosition, InterfaceTypeDefinitionPosition, ObjectTypeDefinitionPosition,
+        ScalarTypeDefinitionPosition,
+    };
+    use crate::schema::referencer::ReferencerPosition;
+    use crate::schema::FederationSchema;
+    use apollo_compiler::{name, Schema};
+
+    #[test]
+    fn lists_referencers() {
+        let schema = FederationSchema::new(
+            Schema::parse(
+                r#"
+directive @tag(name: String!) repeatable on OBJECT | FIELD_DEFINITION | ARGUMENT_DEFINITION
+
+scalar Date
+
+type Query {
+  i(since: Date): I @tag(name: "i")
+  u: U
+}
+
+interface I {
+  id: ID!
+  updatedAt: Date
+}
+
+type A implements I @tag(name: "A") {
+  id: ID!
+  updatedAt: Date
+  a(format: String @tag(name: "format")): String
+}
+
+union U = A
+"#,
+                "schema.graphql",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let referencers = |positions: Vec<ReferencerPosition>| {
+            positions
+                .iter()
+                .map(|position| position.to_string())
+                .collect::<Vec<_>>()
+        };
+        let date = ScalarTypeDefinitionPosition {
+            type_name: name!("Date"),
+        };
+        assert_eq!(
+            referencers(schema.get_type_referencers(&date.into()).unwrap()),
+            ["A.updatedAt", "Query.i(since:)", "I.updatedAt"]
+        );
+        let i = InterfaceTypeDefinitionPosition {
+            type_name: name!("I"),
+        };
+        assert_eq!(
+            referencers(schema.get_type_referencers(&i.into()).unwrap()),
+            ["A", "Query.i"]
+        );
+        let a = ObjectTypeDefinitionPosition {
+            type_name: name!("A"),
+        };
+        assert_eq!(
+            referencers(schema.get_type_referencers(&a.into()).unwrap()),
+            ["U"]
+        );
+        let tag = DirectiveDefinitionPosition {
+            directive_name: name!("tag"),
+        };
+        assert_eq!(
+            referencers(schema.get_directive_referencers(&tag).unwrap()),
+            ["A", "Query.i", "A.a(format:)"]
+        );
+    }
 }
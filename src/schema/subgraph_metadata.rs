@@ -0,0 +1,250 @@
+use crate::error::FederationError;
+use crate::link::federation_spec_definition::{
+    get_federation_spec_definition_from_subgraph, FederationSpecDefinition,
+    FEDERATION_FIELDS_ARGUMENT_NAME, FEDERATION_INTERFACEOBJECT_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC, FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC, FEDERATION_SHAREABLE_DIRECTIVE_NAME_IN_SPEC,
+};
+use crate::link::spec::{Identity, Version};
+use crate::link::spec_definition::SpecDefinition;
+use crate::schema::position::{FieldDefinitionPosition, ObjectOrInterfaceTypeDefinitionPosition};
+use crate::schema::FederationSchema;
+use apollo_compiler::schema::{Directive, ExtendedType, Name, Value};
+use apollo_compiler::NodeStr;
+use indexmap::{IndexMap, IndexSet};
+
+/// The federation directive applications of a subgraph schema that query planning looks up
+/// repeatedly, computed once when the schema is validated (see
+/// `ValidFederationSchema::subgraph_metadata()`), so that lookups don't need to resolve the
+/// federation spec and scan directives every time.
+// PORT_NOTE: This corresponds to part of `FederationMetadata` in the JS codebase, which computed
+// these lazily.
+#[derive(Debug)]
+pub(crate) struct SubgraphMetadata {
+    federation_spec_definition: &'static FederationSpecDefinition,
+    interface_object_types: IndexSet<Name>,
+    /// The field sets of the `@key`s of each object or interface type, in application order.
+    keys: IndexMap<Name, Vec<NodeStr>>,
+    requires: IndexMap<FieldDefinitionPosition, NodeStr>,
+    provides: IndexMap<FieldDefinitionPosition, NodeStr>,
+    shareable_fields: IndexSet<FieldDefinitionPosition>,
+}
+
+impl SubgraphMetadata {
+    /// Computes the metadata of the given schema, or returns `None` if it isn't a subgraph schema
+    /// (i.e. it doesn't link the federation spec).
+    pub(crate) fn new(schema: &FederationSchema) -> Result<Option<Self>, FederationError> {
+        let is_subgraph = schema.metadata().is_some_and(|metadata| {
+            metadata
+                .for_identity(&Identity::federation_identity())
+                .is_some()
+        });
+        if !is_subgraph {
+            return Ok(None);
+        }
+        let federation_spec_definition = get_federation_spec_definition_from_subgraph(schema)?;
+        let directive_name = |name_in_spec: &Name| {
+            federation_spec_definition.directive_name_in_schema(schema, name_in_spec)
+        };
+        let key_directive_name = directive_name(&FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC)?;
+        let requires_directive_name = directive_name(&FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC)?;
+        let provides_directive_name = directive_name(&FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC)?;
+        let shareable_directive_name =
+            directive_name(&FEDERATION_SHAREABLE_DIRECTIVE_NAME_IN_SPEC)?;
+        let interface_object_directive_name =
+            if *federation_spec_definition.version() >= (Version { major: 2, minor: 3 }) {
+                directive_name(&FEDERATION_INTERFACEOBJECT_DIRECTIVE_NAME_IN_SPEC)?
+            } else {
+                None
+            };
+
+        let mut metadata = Self {
+            federation_spec_definition,
+            interface_object_types: IndexSet::new(),
+            keys: IndexMap::new(),
+            requires: IndexMap::new(),
+            provides: IndexMap::new(),
+            shareable_fields: IndexSet::new(),
+        };
+        for type_pos in schema.get_types() {
+            let Ok(type_pos) = ObjectOrInterfaceTypeDefinitionPosition::try_from(type_pos) else {
+                continue;
+            };
+            let (directives, fields) = match schema.schema().types.get(type_pos.type_name()) {
+                Some(ExtendedType::Object(type_)) => (&type_.directives, &type_.fields),
+                Some(ExtendedType::Interface(type_)) => (&type_.directives, &type_.fields),
+                _ => continue,
+            };
+            if interface_object_directive_name
+                .as_ref()
+                .is_some_and(|name| directives.has(name))
+            {
+                metadata
+                    .interface_object_types
+                    .insert(type_pos.type_name().clone());
+            }
+            if let Some(key_directive_name) = &key_directive_name {
+                let keys = directives
+                    .get_all(key_directive_name)
+                    .filter_map(|key| fields_argument(key))
+                    .collect::<Vec<_>>();
+                if !keys.is_empty() {
+                    metadata.keys.insert(type_pos.type_name().clone(), keys);
+                }
+            }
+            let is_type_shareable = shareable_directive_name
+                .as_ref()
+                .is_some_and(|name| directives.has(name));
+            for (field_name, field) in fields {
+                let field_pos: FieldDefinitionPosition = type_pos.field(field_name.clone()).into();
+                if is_type_shareable
+                    || shareable_directive_name
+                        .as_ref()
+                        .is_some_and(|name| field.directives.has(name))
+                {
+                    metadata.shareable_fields.insert(field_pos.clone());
+                }
+                let fields_of =
+                    |name: &Option<Name>| fields_argument(field.directives.get(name.as_ref()?)?);
+                if let Some(fields) = fields_of(&requires_directive_name) {
+                    metadata.requires.insert(field_pos.clone(), fields);
+                }
+                if let Some(fields) = fields_of(&provides_directive_name) {
+                    metadata.provides.insert(field_pos, fields);
+                }
+            }
+        }
+        Ok(Some(metadata))
+    }
+
+    pub(crate) fn federation_spec_definition(&self) -> &'static FederationSpecDefinition {
+        self.federation_spec_definition
+    }
+
+    /// Whether the given type is marked with `@interfaceObject`.
+    pub(crate) fn is_interface_object_type(&self, type_name: &Name) -> bool {
+        self.interface_object_types.contains(type_name)
+    }
+
+    /// The types marked with `@interfaceObject`.
+    pub(crate) fn interface_object_types(&self) -> &IndexSet<Name> {
+        &self.interface_object_types
+    }
+
+    /// The field sets of the `@key`s of the given object or interface type (empty if it isn't an
+    /// entity). Unresolvable keys are included.
+    pub(crate) fn keys(&self, type_name: &Name) -> &[NodeStr] {
+        self.keys.get(type_name).map_or(&[], |keys| keys.as_slice())
+    }
+
+    /// The field set of the `@requires` of the given field, if any.
+    pub(crate) fn requires(&self, field: &FieldDefinitionPosition) -> Option<&NodeStr> {
+        self.requires.get(field)
+    }
+
+    /// The field set of the `@provides` of the given field, if any.
+    pub(crate) fn provides(&self, field: &FieldDefinitionPosition) -> Option<&NodeStr> {
+        self.provides.get(field)
+    }
+
+    /// Whether the given field is marked `@shareable`, directly or through its type.
+    pub(crate) fn is_shareable(&self, field: &FieldDefinitionPosition) -> bool {
+        self.shareable_fields.contains(field)
+    }
+}
+
+/// The `fields` argument of the given directive application, if it's a string.
+fn fields_argument(directive: &Directive) -> Option<NodeStr> {
+    match directive
+        .argument_by_name(&FEDERATION_FIELDS_ARGUMENT_NAME)
+        .map(|value| value.as_ref())
+    {
+        Some(Value::String(fields)) => Some(fields.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::position::ObjectFieldDefinitionPosition;
+    use crate::schema::ValidFederationSchema;
+    use crate::subgraph::Subgraph;
+    use apollo_compiler::{name, Schema};
+
+    #[test]
+    fn precomputes_federation_directive_applications() {
+        let subgraph = Subgraph::parse_and_expand(
+            "S1",
+            "http://s1",
+            r#"
+            extend schema
+              @link(
+                url: "https://specs.apollo.dev/federation/v2.3"
+                import: ["@key", "@requires", "@provides", "@external", "@shareable", "@interfaceObject"]
+              )
+
+            type Query {
+              t: T @provides(fields: "name")
+              i: I
+            }
+
+            type T @key(fields: "id") @key(fields: "name", resolvable: false) {
+              id: ID!
+              name: String @external
+              weight: Int @external
+              shipping: Int @requires(fields: "weight")
+            }
+
+            type S @shareable {
+              x: Int
+            }
+
+            type I @key(fields: "id") @interfaceObject {
+              id: ID!
+            }
+            "#,
+        )
+        .unwrap();
+        let schema = ValidFederationSchema::new(subgraph.schema).unwrap();
+        let metadata = schema.subgraph_metadata().unwrap();
+        let field = |type_name, field_name| {
+            ObjectFieldDefinitionPosition {
+                type_name,
+                field_name,
+            }
+            .into()
+        };
+
+        assert!(metadata.is_interface_object_type(&name!("I")));
+        assert!(!metadata.is_interface_object_type(&name!("T")));
+        assert_eq!(
+            metadata
+                .keys(&name!("T"))
+                .iter()
+                .map(|fields| fields.as_str())
+                .collect::<Vec<_>>(),
+            ["id", "name"]
+        );
+        assert!(metadata.keys(&name!("S")).is_empty());
+        assert_eq!(
+            metadata
+                .requires(&field(name!("T"), name!("shipping")))
+                .map(|fields| fields.as_str()),
+            Some("weight")
+        );
+        assert_eq!(
+            metadata
+                .provides(&field(name!("Query"), name!("t")))
+                .map(|fields| fields.as_str()),
+            Some("name")
+        );
+        assert!(metadata.is_shareable(&field(name!("S"), name!("x"))));
+        assert!(!metadata.is_shareable(&field(name!("T"), name!("id"))));
+
+        let non_subgraph_schema = ValidFederationSchema::new(
+            Schema::parse_and_validate("type Query { x: Int }", "schema.graphql").unwrap(),
+        )
+        .unwrap();
+        assert!(non_subgraph_schema.subgraph_metadata().is_none());
+    }
+}
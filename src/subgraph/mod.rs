@@ -20,6 +20,11 @@ use apollo_compiler::validation::Valid;
 
 mod database;
 pub mod spec;
+mod upgrade;
+mod validation;
+
+pub use upgrade::upgrade_subgraphs_if_necessary;
+pub use validation::{validate_subgraph, validate_subgraphs};
 
 pub struct Subgraph {
     pub name: String,
@@ -38,16 +43,37 @@ impl Subgraph {
         })
     }
 
+    /// Parses the given subgraph schema without expanding it. Unlike `new()`, type extensions
+    /// without a definition are accepted (as federation 1 subgraphs commonly extend the entities
+    /// they don't own), and become the definition of their type.
+    pub fn parse(name: &str, url: &str, schema_str: &str) -> Result<Self, FederationError> {
+        let schema = Schema::builder()
+            .adopt_orphan_extensions()
+            .parse(schema_str, name)
+            .build()?;
+        Ok(Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            schema,
+        })
+    }
+
     pub fn parse_and_expand(
         name: &str,
         url: &str,
         schema_str: &str,
     ) -> Result<ValidSubgraph, FederationError> {
-        let mut schema = Schema::builder()
-            .adopt_orphan_extensions()
-            .parse(schema_str, name)
-            .build()?;
+        Self::parse(name, url, schema_str)?.expand_links()
+    }
 
+    /// Adds the definitions of the `@link`ed specs (and the `@link`s to the link and federation
+    /// specs when missing) to this subgraph's schema, and validates it.
+    pub fn expand_links(self) -> Result<ValidSubgraph, FederationError> {
+        let Self {
+            name,
+            url,
+            mut schema,
+        } = self;
         let mut imported_federation_definitions: Option<FederationSpecDefinitions> = None;
         let mut imported_link_definitions: Option<LinkSpecDefinitions> = None;
         let default_link_name = DEFAULT_LINK_NAME;
@@ -84,11 +110,7 @@ impl Subgraph {
             imported_link_definitions,
         )?;
         let schema = schema.validate()?;
-        Ok(ValidSubgraph {
-            name: name.to_owned(),
-            url: url.to_owned(),
-            schema,
-        })
+        Ok(ValidSubgraph { name, url, schema })
     }
 
     fn populate_missing_type_definitions(
@@ -104,7 +104,7 @@ lazy_static! {
 }
 
 const MIN_FEDERATION_VERSION: Version = Version { major: 2, minor: 0 };
-const MAX_FEDERATION_VERSION: Version = Version { major: 2, minor: 5 };
+const MAX_FEDERATION_VERSION: Version = Version { major: 2, minor: 8 };
 
 #[derive(Error, Debug, PartialEq)]
 pub enum FederationSpecError {
@@ -701,6 +701,7 @@ impl Default for LinkSpecDefinitions {
 
 #[cfg(test)]
 mod tests {
+    use crate::link::federation_spec_definition::FEDERATION_VERSIONS;
     use crate::subgraph::database::federation_link_identity;
 
     use super::*;
@@ -721,4 +722,11 @@ mod tests {
         })
         .expect_err("federation version 99 is not yet supported");
     }
+
+    #[test]
+    fn supports_all_known_federation_versions() {
+        for version in FEDERATION_VERSIONS.versions() {
+            assert!(version.satisfies_range(&MIN_FEDERATION_VERSION, &MAX_FEDERATION_VERSION));
+        }
+    }
 }
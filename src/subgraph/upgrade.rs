@@ -0,0 +1,343 @@
+//! Upgrades federation 1 subgraph schemas to federation 2 ones before composition, so that legacy
+//! subgraphs can be composed with the federation 2 rules.
+// PORT_NOTE: This mirrors the JS codebase's `SchemaUpgrader`, but only implements the changes
+// composition depends on: type extensions become definitions, the key fields of entity extensions
+// stop being `@external`, unused `@external` fields are removed, and fields resolved by several
+// subgraphs (which federation 1 implicitly shared) are marked `@shareable`.
+use crate::error::FederationError;
+use crate::link::federation_spec_definition::{
+    get_federation_spec_definition_from_subgraph, FEDERATION_EXTENDS_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC, FEDERATION_FIELDS_ARGUMENT_NAME,
+    FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC, FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC,
+};
+use crate::link::spec::Identity;
+use crate::link::spec_definition::SpecDefinition;
+use crate::link::{Link, DEFAULT_LINK_NAME};
+use crate::schema::external::{collect_fields, ExternalMetadata};
+use crate::schema::position::ObjectOrInterfaceTypeDefinitionPosition;
+use crate::schema::ValidFederationSchema;
+use crate::subgraph::spec::{ENTITIES_QUERY, SERVICE_SDL_QUERY, SERVICE_TYPE};
+use crate::subgraph::{Subgraph, ValidSubgraph};
+use apollo_compiler::ast::{Directive, Value};
+use apollo_compiler::executable::FieldSet;
+use apollo_compiler::schema::{
+    Component, ComponentName, ComponentOrigin, ExtendedType, FieldDefinition, Name, NamedType,
+};
+use apollo_compiler::validation::Valid;
+use apollo_compiler::{Node, Schema};
+use indexmap::{IndexMap, IndexSet};
+
+/// Expands the given subgraphs (see `Subgraph::expand_links()`), upgrading those that are
+/// federation 1 subgraphs (i.e. that don't `@link` to the federation spec) to federation 2.
+/// Federation 2 subgraphs are only expanded.
+///
+/// Subgraphs are expanded and upgraded independently of each other, so with the `rayon` feature,
+/// this is done in parallel (using the rayon global thread pool), which matters for compositions
+/// of many subgraphs. The returned subgraphs are in the same order either way.
+pub fn upgrade_subgraphs_if_necessary(
+    subgraphs: Vec<Subgraph>,
+) -> Result<Vec<ValidSubgraph>, FederationError> {
+    let expand_and_upgrade = |subgraph: Subgraph| {
+        let is_federation_1 = !links_to_federation(&subgraph.schema);
+        let subgraph = subgraph.expand_links()?;
+        Ok(if is_federation_1 {
+            (upgrade_subgraph(subgraph)?, true)
+        } else {
+            (subgraph, false)
+        })
+    };
+    #[cfg(feature = "rayon")]
+    let expanded = {
+        use rayon::prelude::*;
+        subgraphs
+            .into_par_iter()
+            .map(expand_and_upgrade)
+            .collect::<Result<Vec<_>, FederationError>>()?
+    };
+    #[cfg(not(feature = "rayon"))]
+    let expanded = subgraphs
+        .into_iter()
+        .map(expand_and_upgrade)
+        .collect::<Result<Vec<_>, FederationError>>()?;
+
+    // Shareability depends on which other subgraphs resolve a field, so it's only computed once
+    // all subgraphs are upgraded (and unused external fields are removed).
+    let mut resolving_subgraph_counts: IndexMap<(NamedType, Name), usize> = IndexMap::new();
+    for (subgraph, _) in &expanded {
+        for field in resolved_fields(&subgraph.schema)? {
+            *resolving_subgraph_counts.entry(field).or_default() += 1;
+        }
+    }
+    expanded
+        .into_iter()
+        .map(|(subgraph, is_federation_1)| {
+            if is_federation_1 {
+                add_shareable(subgraph, &resolving_subgraph_counts)
+            } else {
+                Ok(subgraph)
+            }
+        })
+        .collect()
+}
+
+fn links_to_federation(schema: &Schema) -> bool {
+    schema
+        .schema_definition
+        .directives
+        .get_all(&DEFAULT_LINK_NAME)
+        .filter_map(|directive| Link::from_directive_application(directive).ok())
+        .any(|link| link.url.identity == Identity::federation_identity())
+}
+
+/// The federation 2 equivalent of the given (expanded) federation 1 subgraph, minus shareability.
+fn upgrade_subgraph(subgraph: ValidSubgraph) -> Result<ValidSubgraph, FederationError> {
+    let federation_schema = ValidFederationSchema::new(subgraph.schema.clone())?;
+    let federation_spec_definition =
+        get_federation_spec_definition_from_subgraph(&federation_schema)?;
+    let directive_name = |name_in_spec: &Name| {
+        federation_spec_definition
+            .directive_name_in_schema(&federation_schema, name_in_spec)?
+            .ok_or_else(|| {
+                FederationError::internal("Unexpectedly could not find federation spec in schema")
+            })
+    };
+    let extends_directive_name = directive_name(&FEDERATION_EXTENDS_DIRECTIVE_NAME_IN_SPEC)?;
+    let external_directive_name = directive_name(&FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC)?;
+    let external_metadata = ExternalMetadata::new(&federation_schema)?;
+    let used_fields = fields_used_by_directives(&federation_schema)?;
+
+    // Federation 1 allowed `@external` on fields used by no directive, while federation 2 rejects
+    // them, and key fields of entity extensions had to be `@external` even though the subgraph
+    // resolves them.
+    let mut unused_external_fields = IndexSet::new();
+    let mut fake_external_fields = IndexSet::new();
+    for type_pos in federation_schema.get_types() {
+        let Ok(type_pos) = ObjectOrInterfaceTypeDefinitionPosition::try_from(type_pos) else {
+            continue;
+        };
+        let fields = fields_of(federation_schema.schema(), type_pos.type_name());
+        for field_name in fields.into_iter().flat_map(|fields| fields.keys()) {
+            let field_pos = type_pos.field(field_name.clone()).into();
+            let field = (type_pos.type_name().clone(), field_name.clone());
+            if external_metadata.is_fake_external(&field_pos) {
+                fake_external_fields.insert(field);
+            } else if external_metadata.is_external(&field_pos) && !used_fields.contains(&field) {
+                unused_external_fields.insert(field);
+            }
+        }
+    }
+
+    let ValidSubgraph { name, url, schema } = subgraph;
+    let mut schema = schema.into_inner();
+    for (type_name, type_) in schema.types.iter_mut() {
+        let (directives, fields, implements_interfaces) = match type_ {
+            ExtendedType::Object(type_) => {
+                let type_ = type_.make_mut();
+                (
+                    &mut type_.directives,
+                    &mut type_.fields,
+                    &mut type_.implements_interfaces,
+                )
+            }
+            ExtendedType::Interface(type_) => {
+                let type_ = type_.make_mut();
+                (
+                    &mut type_.directives,
+                    &mut type_.fields,
+                    &mut type_.implements_interfaces,
+                )
+            }
+            _ => continue,
+        };
+        // Type extensions become the definition of their type, as federation 2 has no special
+        // semantics for them.
+        directives.retain(|directive| directive.name != extends_directive_name);
+        for directive in directives.iter_mut() {
+            directive.origin = ComponentOrigin::Definition;
+        }
+        *implements_interfaces = implements_interfaces
+            .iter()
+            .map(|interface| ComponentName::from(&interface.name))
+            .collect();
+        fields.retain(|field_name, _| {
+            !unused_external_fields.contains(&(type_name.clone(), field_name.clone()))
+        });
+        for (field_name, field) in fields.iter_mut() {
+            field.origin = ComponentOrigin::Definition;
+            if fake_external_fields.contains(&(type_name.clone(), field_name.clone())) {
+                field
+                    .make_mut()
+                    .directives
+                    .retain(|directive| directive.name != external_directive_name);
+            }
+        }
+    }
+    let schema = schema.validate()?;
+    Ok(ValidSubgraph { name, url, schema })
+}
+
+/// Marks `@shareable` the fields of the given upgraded subgraph that other subgraphs also resolve,
+/// except key fields, which federation 2 already considers shareable.
+fn add_shareable(
+    subgraph: ValidSubgraph,
+    resolving_subgraph_counts: &IndexMap<(NamedType, Name), usize>,
+) -> Result<ValidSubgraph, FederationError> {
+    let federation_schema = ValidFederationSchema::new(subgraph.schema.clone())?;
+    let federation_spec_definition =
+        get_federation_spec_definition_from_subgraph(&federation_schema)?;
+    let shareable_directive = federation_spec_definition.shareable_directive(&federation_schema)?;
+    let key_directive_name = federation_spec_definition
+        .directive_name_in_schema(&federation_schema, &FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC)?;
+    let mut key_fields = IndexSet::new();
+    for (type_name, type_) in &federation_schema.schema().types {
+        for key in key_directive_name
+            .iter()
+            .flat_map(|name| type_.directives().get_all(name))
+        {
+            collect_field_set_fields(federation_schema.schema(), type_name, key, &mut key_fields);
+        }
+    }
+    let shared_fields = resolved_fields(&subgraph.schema)?
+        .into_iter()
+        .filter(|field| {
+            !key_fields.contains(field)
+                && resolving_subgraph_counts
+                    .get(field)
+                    .is_some_and(|count| *count > 1)
+        })
+        .collect::<IndexSet<_>>();
+    if shared_fields.is_empty() {
+        return Ok(subgraph);
+    }
+
+    let ValidSubgraph { name, url, schema } = subgraph;
+    let mut schema = schema.into_inner();
+    for (type_name, type_) in schema.types.iter_mut() {
+        let ExtendedType::Object(type_) = type_ else {
+            continue;
+        };
+        for (field_name, field) in type_.make_mut().fields.iter_mut() {
+            let is_shared = shared_fields.contains(&(type_name.clone(), field_name.clone()));
+            if is_shared && !field.directives.has(&shareable_directive.name) {
+                field
+                    .make_mut()
+                    .directives
+                    .push(Node::new(shareable_directive.clone()));
+            }
+        }
+    }
+    let schema = schema.validate()?;
+    Ok(ValidSubgraph { name, url, schema })
+}
+
+/// The fields of object types the given (expanded) subgraph resolves, i.e. the non-external ones,
+/// as type and field names. Federation-specific fields, which every subgraph resolves on its own,
+/// are ignored.
+fn resolved_fields(schema: &Valid<Schema>) -> Result<IndexSet<(NamedType, Name)>, FederationError> {
+    let federation_schema = ValidFederationSchema::new(schema.clone())?;
+    let external_metadata = ExternalMetadata::new(&federation_schema)?;
+    let mut fields = IndexSet::new();
+    for (type_name, type_) in &schema.types {
+        let ExtendedType::Object(type_) = type_ else {
+            continue;
+        };
+        if type_.is_built_in() || *type_name == SERVICE_TYPE {
+            continue;
+        }
+        let type_pos = ObjectOrInterfaceTypeDefinitionPosition::try_from(
+            federation_schema.get_type(type_name.clone())?,
+        )?;
+        for field_name in type_.fields.keys() {
+            if *field_name == SERVICE_SDL_QUERY || *field_name == ENTITIES_QUERY {
+                continue;
+            }
+            if !external_metadata.is_external(&type_pos.field(field_name.clone()).into()) {
+                fields.insert((type_name.clone(), field_name.clone()));
+            }
+        }
+    }
+    Ok(fields)
+}
+
+/// The fields selected by the `@key`, `@requires` and `@provides` applications of the given
+/// subgraph, as type and field names.
+fn fields_used_by_directives(
+    schema: &ValidFederationSchema,
+) -> Result<IndexSet<(NamedType, Name)>, FederationError> {
+    let federation_spec_definition = get_federation_spec_definition_from_subgraph(schema)?;
+    let directive_name = |name_in_spec: &Name| {
+        federation_spec_definition.directive_name_in_schema(schema, name_in_spec)
+    };
+    let key_directive_name = directive_name(&FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC)?;
+    let requires_directive_name = directive_name(&FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC)?;
+    let provides_directive_name = directive_name(&FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC)?;
+    let schema = schema.schema();
+    let mut used_fields = IndexSet::new();
+    for (type_name, type_) in &schema.types {
+        for key in key_directive_name
+            .iter()
+            .flat_map(|name| type_.directives().get_all(name))
+        {
+            collect_field_set_fields(schema, type_name, key, &mut used_fields);
+        }
+        for field in fields_of(schema, type_name)
+            .into_iter()
+            .flat_map(|fields| fields.values())
+        {
+            for requires in requires_directive_name
+                .iter()
+                .flat_map(|name| field.directives.get_all(name))
+            {
+                collect_field_set_fields(schema, type_name, requires, &mut used_fields);
+            }
+            for provides in provides_directive_name
+                .iter()
+                .flat_map(|name| field.directives.get_all(name))
+            {
+                collect_field_set_fields(
+                    schema,
+                    field.ty.inner_named_type(),
+                    provides,
+                    &mut used_fields,
+                );
+            }
+        }
+    }
+    Ok(used_fields)
+}
+
+fn fields_of<'schema>(
+    schema: &'schema Schema,
+    type_name: &NamedType,
+) -> Option<&'schema IndexMap<Name, Component<FieldDefinition>>> {
+    match schema.types.get(type_name) {
+        Some(ExtendedType::Object(type_)) => Some(&type_.fields),
+        Some(ExtendedType::Interface(type_)) => Some(&type_.fields),
+        _ => None,
+    }
+}
+
+/// Collects the fields selected by the field set of the given `@key`, `@requires` or `@provides`
+/// application. Invalid field sets are ignored, as they're reported by subgraph validation.
+fn collect_field_set_fields(
+    schema: &Valid<Schema>,
+    parent_type_name: &NamedType,
+    application: &Directive,
+    fields: &mut IndexSet<(NamedType, Name)>,
+) {
+    let Some(Value::String(field_set)) = application
+        .argument_by_name(&FEDERATION_FIELDS_ARGUMENT_NAME)
+        .map(|value| value.as_ref())
+    else {
+        return;
+    };
+    if let Ok(field_set) = FieldSet::parse_and_validate(
+        schema,
+        parent_type_name.clone(),
+        field_set.as_str(),
+        "field_set.graphql",
+    ) {
+        collect_fields(&field_set.selection_set, fields);
+    }
+}
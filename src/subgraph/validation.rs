@@ -0,0 +1,503 @@
+//! Implements the federation-specific checks on subgraph schemas that run before composition
+//! (on top of GraphQL validation), e.g. that the field sets of `@key`, `@requires` and `@provides`
+//! select existing fields in a supported way, and that `@external` is used consistently.
+use crate::error::{FederationError, MultipleFederationErrors, SingleFederationError};
+use crate::link::federation_spec_definition::{
+    get_federation_spec_definition_from_subgraph, FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_FIELDS_ARGUMENT_NAME, FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC, FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC,
+    FEDERATION_TAG_DIRECTIVE_NAME_IN_SPEC,
+};
+use crate::link::spec::Version;
+use crate::link::spec_definition::SpecDefinition;
+use crate::schema::external::ExternalMetadata;
+use crate::schema::position::ObjectOrInterfaceTypeDefinitionPosition;
+use crate::schema::ValidFederationSchema;
+use crate::subgraph::ValidSubgraph;
+use apollo_compiler::ast::{Directive, FieldDefinition, Name, Value};
+use apollo_compiler::executable::{FieldSet, Selection, SelectionSet};
+use apollo_compiler::schema::{ExtendedType, NamedType};
+use apollo_compiler::validation::Valid;
+use apollo_compiler::{Node, Schema};
+use indexmap::IndexSet;
+
+/// The maximum length of a `@tag` name.
+const MAX_TAG_NAME_LENGTH: usize = 128;
+
+/// Runs the federation-specific checks on the given subgraph schema (which must use the federation
+/// spec, e.g. as returned by `Subgraph::parse_and_expand()`), returning all the problems found.
+pub fn validate_subgraph(schema: &Valid<Schema>) -> Result<(), FederationError> {
+    let schema = ValidFederationSchema::new(schema.clone())?;
+    let mut validator = SubgraphValidator::new(&schema)?;
+    validator.validate()?;
+    if validator.errors.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(validator.errors.into())
+    }
+}
+
+/// Runs the federation-specific checks on each of the given subgraphs (see `validate_subgraph()`),
+/// returning the problems found in all of them, in subgraph order.
+///
+/// Subgraphs are validated independently of each other, so with the `rayon` feature, this is done
+/// in parallel (using the rayon global thread pool).
+pub fn validate_subgraphs(subgraphs: &[ValidSubgraph]) -> Result<(), FederationError> {
+    let validate = |subgraph: &ValidSubgraph| validate_subgraph(&subgraph.schema);
+    #[cfg(feature = "rayon")]
+    let results: Vec<_> = {
+        use rayon::prelude::*;
+        subgraphs.par_iter().map(validate).collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let results: Vec<_> = subgraphs.iter().map(validate).collect();
+
+    let mut errors = MultipleFederationErrors { errors: Vec::new() };
+    for result in results {
+        if let Err(error) = result {
+            errors.push(error);
+        }
+    }
+    if errors.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.into())
+    }
+}
+
+/// The kind of field set being validated, which determines which checks apply to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldSetKind {
+    Key,
+    Requires,
+    Provides,
+}
+
+struct SubgraphValidator<'schema> {
+    schema: &'schema ValidFederationSchema,
+    federation_version: Version,
+    key_directive_name: Name,
+    requires_directive_name: Name,
+    provides_directive_name: Name,
+    external_directive_name: Name,
+    tag_directive_name: Name,
+    external_metadata: ExternalMetadata,
+    /// The fields (as type and field names) selected by some `@key`, `@requires` or `@provides`,
+    /// which is what makes an `@external` field useful.
+    fields_used_by_directives: IndexSet<(NamedType, Name)>,
+    errors: MultipleFederationErrors,
+}
+
+impl<'schema> SubgraphValidator<'schema> {
+    fn new(schema: &'schema ValidFederationSchema) -> Result<Self, FederationError> {
+        let federation_spec_definition = get_federation_spec_definition_from_subgraph(schema)?;
+        let directive_name = |name_in_spec: &Name| -> Result<Name, FederationError> {
+            federation_spec_definition
+                .directive_name_in_schema(schema, name_in_spec)?
+                .ok_or_else(|| {
+                    FederationError::internal(
+                        "Unexpectedly could not find federation spec in schema",
+                    )
+                })
+        };
+        Ok(Self {
+            schema,
+            federation_version: federation_spec_definition.version().clone(),
+            key_directive_name: directive_name(&FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC)?,
+            requires_directive_name: directive_name(&FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC)?,
+            provides_directive_name: directive_name(&FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC)?,
+            external_directive_name: directive_name(&FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC)?,
+            tag_directive_name: directive_name(&FEDERATION_TAG_DIRECTIVE_NAME_IN_SPEC)?,
+            external_metadata: ExternalMetadata::new(schema)?,
+            fields_used_by_directives: IndexSet::new(),
+            errors: MultipleFederationErrors { errors: Vec::new() },
+        })
+    }
+
+    fn validate(&mut self) -> Result<(), FederationError> {
+        let schema = self.schema.schema();
+        // These are cloned as they're used while validating (which mutably borrows self).
+        let key_directive_name = self.key_directive_name.clone();
+        let requires_directive_name = self.requires_directive_name.clone();
+        let provides_directive_name = self.provides_directive_name.clone();
+        let tag_directive_name = self.tag_directive_name.clone();
+        for (type_name, type_) in &schema.types {
+            if type_.is_built_in() {
+                continue;
+            }
+            self.validate_tags(
+                type_name.as_ref(),
+                type_
+                    .directives()
+                    .get_all(&tag_directive_name)
+                    .map(|tag| tag.as_ref()),
+            );
+            let (fields, is_interface) = match type_ {
+                ExtendedType::Object(type_) => (&type_.fields, false),
+                ExtendedType::Interface(type_) => (&type_.fields, true),
+                _ => continue,
+            };
+            for key in type_.directives().get_all(&key_directive_name) {
+                if is_interface && self.federation_version < (Version { major: 2, minor: 3 }) {
+                    self.errors.errors.push(SingleFederationError::KeyUnsupportedOnInterface {
+                        message: format!(
+                            "Cannot use @key on interface \"{}\": @key is not yet supported on interfaces",
+                            type_name,
+                        ),
+                    });
+                    continue;
+                }
+                self.validate_field_set(
+                    FieldSetKind::Key,
+                    &format!("type \"{}\"", type_name),
+                    type_name,
+                    key,
+                )?;
+            }
+            for (field_name, field) in fields.iter() {
+                let coordinate = format!("{}.{}", type_name, field_name);
+                self.validate_tags(
+                    &coordinate,
+                    field
+                        .directives
+                        .get_all(&tag_directive_name)
+                        .map(|tag| tag.as_ref()),
+                );
+                for requires in field.directives.get_all(&requires_directive_name) {
+                    self.validate_field_set(
+                        FieldSetKind::Requires,
+                        &format!("field \"{}\"", coordinate),
+                        type_name,
+                        requires,
+                    )?;
+                }
+                for provides in field.directives.get_all(&provides_directive_name) {
+                    self.validate_provides(&coordinate, field, provides)?;
+                }
+                if is_interface && field.directives.has(&self.external_directive_name) {
+                    self.errors.errors.push(SingleFederationError::ExternalOnInterface {
+                        message: format!(
+                            "Interface type field \"{}\" is marked @external but @external is not allowed on interface fields (it is nonsensical).",
+                            coordinate,
+                        ),
+                    });
+                }
+            }
+        }
+        self.validate_external_fields_are_used();
+        Ok(())
+    }
+
+    /// Checks that `@provides` is on a field returning a composite type, before checking its
+    /// field set against that type.
+    fn validate_provides(
+        &mut self,
+        coordinate: &str,
+        field: &FieldDefinition,
+        provides: &Node<Directive>,
+    ) -> Result<(), FederationError> {
+        let return_type_name = field.ty.inner_named_type();
+        let is_composite = matches!(
+            self.schema.schema().types.get(return_type_name),
+            Some(ExtendedType::Object(_) | ExtendedType::Interface(_) | ExtendedType::Union(_))
+        );
+        if !is_composite {
+            self.errors.errors.push(SingleFederationError::ProvidesOnNonObjectField {
+                message: format!(
+                    "Invalid @provides directive on field \"{}\": field has type \"{}\" which is not a Composite Type",
+                    coordinate, field.ty,
+                ),
+            });
+            return Ok(());
+        }
+        self.validate_field_set(
+            FieldSetKind::Provides,
+            &format!("field \"{}\"", coordinate),
+            return_type_name,
+            provides,
+        )
+    }
+
+    /// Checks the field set of a `@key`, `@requires` or `@provides` application, whose selections
+    /// are on `parent_type_name`.
+    fn validate_field_set(
+        &mut self,
+        kind: FieldSetKind,
+        location: &str,
+        parent_type_name: &NamedType,
+        application: &Node<Directive>,
+    ) -> Result<(), FederationError> {
+        let prefix = format!("On {}, for {}", location, application);
+        let fields = match application
+            .argument_by_name(&FEDERATION_FIELDS_ARGUMENT_NAME)
+            .map(|value| value.as_ref())
+        {
+            Some(Value::String(fields)) => fields.clone(),
+            _ => {
+                let message = format!(
+                    "{}: Invalid value for argument \"fields\": must be a string.",
+                    prefix
+                );
+                self.errors.errors.push(match kind {
+                    FieldSetKind::Key => SingleFederationError::KeyInvalidFieldsType { message },
+                    FieldSetKind::Requires => {
+                        SingleFederationError::RequiresInvalidFieldsType { message }
+                    }
+                    FieldSetKind::Provides => {
+                        SingleFederationError::ProvidesInvalidFieldsType { message }
+                    }
+                });
+                return Ok(());
+            }
+        };
+        let field_set = match FieldSet::parse_and_validate(
+            self.schema.schema(),
+            parent_type_name.clone(),
+            fields.as_str(),
+            "field_set.graphql",
+        ) {
+            Ok(field_set) => field_set,
+            Err(with_errors) => {
+                for diagnostic in with_errors.errors.iter() {
+                    self.push_invalid_fields_error(
+                        kind,
+                        format!("{}: {}", prefix, diagnostic.error),
+                    );
+                }
+                return Ok(());
+            }
+        };
+        self.validate_selection_set(kind, &prefix, &field_set.selection_set, true)
+    }
+
+    fn validate_selection_set(
+        &mut self,
+        kind: FieldSetKind,
+        prefix: &str,
+        selection_set: &SelectionSet,
+        is_top_level: bool,
+    ) -> Result<(), FederationError> {
+        for selection in &selection_set.selections {
+            match selection {
+                Selection::Field(field) => {
+                    let coordinate = format!("{}.{}", selection_set.ty, field.name);
+                    if !field.directives.is_empty() {
+                        let message = format!(
+                            "{}: cannot have directive applications in the @{}(fields:) argument but found {}.",
+                            prefix,
+                            self.directive_name(kind),
+                            field
+                                .directives
+                                .iter()
+                                .map(|directive| directive.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        );
+                        self.errors.errors.push(match kind {
+                            FieldSetKind::Key => {
+                                SingleFederationError::KeyDirectiveInFieldsArgs { message }
+                            }
+                            FieldSetKind::Requires => {
+                                SingleFederationError::RequiresDirectiveInFieldsArgs { message }
+                            }
+                            FieldSetKind::Provides => {
+                                SingleFederationError::ProvidesDirectiveInFieldsArgs { message }
+                            }
+                        });
+                    }
+                    if let Some(alias) = &field.alias {
+                        self.push_invalid_fields_error(
+                            kind,
+                            format!(
+                                "{}: Cannot use alias \"{}\" in \"{}: {}\": aliases are not currently supported in @{}",
+                                prefix,
+                                alias,
+                                alias,
+                                field.name,
+                                self.directive_name(kind),
+                            ),
+                        );
+                    }
+                    if field.name.as_str() == "__typename" {
+                        continue;
+                    }
+                    self.fields_used_by_directives
+                        .insert((selection_set.ty.clone(), field.name.clone()));
+                    if !field.definition.arguments.is_empty() {
+                        let message = format!(
+                            "{}: field {} cannot be included because it has arguments (fields with argument are not allowed in @{})",
+                            prefix,
+                            coordinate,
+                            self.directive_name(kind),
+                        );
+                        match kind {
+                            FieldSetKind::Key => self
+                                .errors
+                                .errors
+                                .push(SingleFederationError::KeyFieldsHasArgs { message }),
+                            FieldSetKind::Provides => self
+                                .errors
+                                .errors
+                                .push(SingleFederationError::ProvidesFieldsHasArgs { message }),
+                            // Fields with arguments are allowed in @requires.
+                            FieldSetKind::Requires => {}
+                        }
+                    }
+                    if kind == FieldSetKind::Key {
+                        self.validate_key_field_type(prefix, &coordinate, &field.definition);
+                    } else if is_top_level && !self.is_external(&selection_set.ty, &field.name) {
+                        let message = format!(
+                            "{}: field \"{}\" should not be part of a @{} since it is already provided by this subgraph (it is not marked @external)",
+                            prefix,
+                            coordinate,
+                            self.directive_name(kind),
+                        );
+                        self.errors.errors.push(match kind {
+                            FieldSetKind::Provides => {
+                                SingleFederationError::ProvidesFieldsMissingExternal { message }
+                            }
+                            _ => SingleFederationError::RequiresFieldsMissingExternal { message },
+                        });
+                    }
+                    self.validate_selection_set(kind, prefix, &field.selection_set, false)?;
+                }
+                Selection::InlineFragment(inline_fragment) => {
+                    self.validate_selection_set(
+                        kind,
+                        prefix,
+                        &inline_fragment.selection_set,
+                        is_top_level,
+                    )?;
+                }
+                Selection::FragmentSpread(_) => {
+                    return Err(FederationError::internal(
+                        "Unexpectedly found a fragment spread in a field set",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that a `@key` field isn't a list, interface or union (whose values can't be used to
+    /// identify an entity).
+    fn validate_key_field_type(&mut self, prefix: &str, coordinate: &str, field: &FieldDefinition) {
+        let kind = if field.ty.is_list() {
+            "a List"
+        } else {
+            match self.schema.schema().types.get(field.ty.inner_named_type()) {
+                Some(ExtendedType::Interface(_)) => "an Interface",
+                Some(ExtendedType::Union(_)) => "a Union",
+                _ => return,
+            }
+        };
+        self.errors
+            .errors
+            .push(SingleFederationError::KeyFieldsSelectInvalidType {
+                message: format!(
+                    "{}: field \"{}\" is {} type which is not allowed in @key",
+                    prefix, coordinate, kind,
+                ),
+            });
+    }
+
+    /// Checks that every `@external` field is used by some `@key`, `@requires` or `@provides`, or
+    /// to satisfy an interface.
+    fn validate_external_fields_are_used(&mut self) {
+        let schema = self.schema.schema();
+        for (type_name, type_) in &schema.types {
+            let ExtendedType::Object(object_type) = type_ else {
+                continue;
+            };
+            for field_name in object_type.fields.keys() {
+                if !self.is_external(type_name, field_name)
+                    || self
+                        .fields_used_by_directives
+                        .contains(&(type_name.clone(), field_name.clone()))
+                {
+                    continue;
+                }
+                let satisfies_interface =
+                    object_type
+                        .implements_interfaces
+                        .iter()
+                        .any(
+                            |interface_name| match schema.types.get(&interface_name.name) {
+                                Some(ExtendedType::Interface(interface_type)) => {
+                                    interface_type.fields.contains_key(field_name)
+                                }
+                                _ => false,
+                            },
+                        );
+                if satisfies_interface {
+                    continue;
+                }
+                self.errors.errors.push(SingleFederationError::ExternalUnused {
+                    message: format!(
+                        "Field \"{}.{}\" is marked @external but is not used in any federation directive (@key, @provides, @requires) or to satisfy an interface; the field declaration has no use and should be removed (or the field should not be @external).",
+                        type_name, field_name,
+                    ),
+                });
+            }
+        }
+    }
+
+    /// Whether the given field is external (see `ExternalMetadata`), which notably isn't the case
+    /// of the fake external key fields of federation 1 type extensions.
+    fn is_external(&self, type_name: &NamedType, field_name: &Name) -> bool {
+        self.schema
+            .try_get_type(type_name.clone())
+            .and_then(|pos| ObjectOrInterfaceTypeDefinitionPosition::try_from(pos).ok())
+            .is_some_and(|pos| {
+                self.external_metadata
+                    .is_external(&pos.field(field_name.clone()).into())
+            })
+    }
+
+    /// Checks that the names of the given `@tag` applications (on the element at `coordinate`) are
+    /// valid.
+    fn validate_tags<'a>(&mut self, coordinate: &str, tags: impl Iterator<Item = &'a Directive>) {
+        for tag in tags {
+            let Some(Value::String(tag_name)) = tag.argument_by_name("name").map(|v| v.as_ref())
+            else {
+                continue;
+            };
+            if !is_valid_tag_name(tag_name.as_str()) {
+                self.errors.errors.push(SingleFederationError::InvalidGraphQL {
+                    message: format!(
+                        "Invalid name \"{}\" for @tag on \"{}\": tag names must be at most {} characters long, start with a letter or an underscore, and only contain letters, digits, underscores, hyphens and slashes.",
+                        tag_name, coordinate, MAX_TAG_NAME_LENGTH,
+                    ),
+                    locations: Vec::new(),
+                });
+            }
+        }
+    }
+
+    fn directive_name(&self, kind: FieldSetKind) -> &Name {
+        match kind {
+            FieldSetKind::Key => &self.key_directive_name,
+            FieldSetKind::Requires => &self.requires_directive_name,
+            FieldSetKind::Provides => &self.provides_directive_name,
+        }
+    }
+
+    fn push_invalid_fields_error(&mut self, kind: FieldSetKind, message: String) {
+        self.errors.errors.push(match kind {
+            FieldSetKind::Key => SingleFederationError::KeyInvalidFields { message },
+            FieldSetKind::Requires => SingleFederationError::RequiresInvalidFields { message },
+            FieldSetKind::Provides => SingleFederationError::ProvidesInvalidFields { message },
+        });
+    }
+}
+
+/// Whether the given `@tag` name matches `^[_A-Za-z][-/_A-Za-z0-9]*$` and isn't too long.
+fn is_valid_tag_name(tag_name: &str) -> bool {
+    let mut chars = tag_name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    tag_name.len() <= MAX_TAG_NAME_LENGTH
+        && (first == '_' || first.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '/'))
+}
@@ -0,0 +1,76 @@
+//! Implements the diagnostic mode for loading supergraphs, which reports every problem of a broken
+//! supergraph at once.
+use crate::error::{FederationError, SingleFederationError};
+use crate::query_graph::build_query_graph::build_federated_query_graph_from_subgraphs;
+use crate::query_graph::extract_subgraphs_from_supergraph::{
+    extract_subgraphs_from_supergraph_with_diagnostics, ExtractionDiagnostics,
+};
+use crate::query_graph::QueryGraph;
+use crate::schema::ValidFederationSchema;
+use crate::Supergraph;
+
+/// The problems found when loading a supergraph in diagnostic mode.
+///
+/// Unlike regular loading, which stops at the first error, diagnostic mode keeps going past errors
+/// in individual types, fields and subgraphs, skipping what they affect. This lets operators fix
+/// every problem of a broken supergraph in one pass, instead of fixing and recomposing one error
+/// at a time.
+#[derive(Debug)]
+pub struct SupergraphDiagnostics {
+    /// All the errors found in the supergraph, in the order they were encountered.
+    pub errors: Vec<SingleFederationError>,
+    /// The types that couldn't be (fully) extracted into some subgraph.
+    pub unusable_types: Vec<String>,
+    /// The fields and enum values (as `Type.field` coordinates) that couldn't be extracted into
+    /// some subgraph.
+    pub unusable_fields: Vec<String>,
+    /// The subgraphs whose extracted schema is invalid. These are left out of the query graph.
+    pub unusable_subgraphs: Vec<String>,
+    /// The federated query graph built from the usable parts of the supergraph, or `None` if it
+    /// couldn't be built at all. Unusable fields and subgraphs have no edges in this graph.
+    pub query_graph: Option<QueryGraph>,
+}
+
+impl SupergraphDiagnostics {
+    /// Whether the supergraph loaded without any error.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+pub(crate) fn diagnose(supergraph: &Supergraph) -> SupergraphDiagnostics {
+    let mut diagnostics = ExtractionDiagnostics::new(true);
+    let query_graph = match build_query_graph(supergraph, &mut diagnostics) {
+        Ok(query_graph) => Some(query_graph),
+        Err(error) => {
+            diagnostics.errors.push(error);
+            None
+        }
+    };
+    SupergraphDiagnostics {
+        errors: diagnostics.errors.errors,
+        unusable_types: diagnostics
+            .unusable_types
+            .into_iter()
+            .map(|type_name| type_name.to_string())
+            .collect(),
+        unusable_fields: diagnostics
+            .unusable_fields
+            .into_iter()
+            .map(|(type_name, field_name)| format!("{}.{}", type_name, field_name))
+            .collect(),
+        unusable_subgraphs: diagnostics.unusable_subgraphs.into_iter().collect(),
+        query_graph,
+    }
+}
+
+fn build_query_graph(
+    supergraph: &Supergraph,
+    diagnostics: &mut ExtractionDiagnostics,
+) -> Result<QueryGraph, FederationError> {
+    let supergraph_schema = supergraph.schema.clone();
+    let subgraphs =
+        extract_subgraphs_from_supergraph_with_diagnostics(&supergraph_schema, None, diagnostics)?;
+    let api_schema = ValidFederationSchema::new(supergraph.to_api_schema(Default::default())?)?;
+    build_federated_query_graph_from_subgraphs(supergraph_schema, api_schema, subgraphs, None, &[])
+}
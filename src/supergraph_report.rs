@@ -0,0 +1,248 @@
+//! Implements the supergraph report, a summary of the shape of a supergraph.
+use crate::error::FederationError;
+use crate::query_graph::build_query_graph::build_federated_query_graph;
+use crate::query_graph::{QueryGraph, QueryGraphEdgeTransition, QueryGraphNodeType};
+use crate::query_plan::operation::{NormalizedSelection, NormalizedSelectionSet};
+use crate::schema::position::{FieldDefinitionPosition, OutputTypeDefinitionPosition};
+use crate::schema::ValidFederationSchema;
+use crate::subgraph::spec::ENTITY_UNION_NAME;
+use crate::Supergraph;
+use apollo_compiler::schema::{ExtendedType, Name};
+use indexmap::{IndexMap, IndexSet};
+use petgraph::visit::EdgeRef;
+use std::fmt::{Display, Formatter};
+
+/// Load-time statistics about a supergraph, meant as a quick health overview (e.g. for operators
+/// to log when deploying a new supergraph).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupergraphReport {
+    /// The number of subgraphs in the supergraph.
+    pub subgraph_count: usize,
+    /// The number of entity types, i.e. types with a key in at least one subgraph.
+    pub entity_count: usize,
+    /// The number of key edges between different subgraphs, i.e. the ways of jumping from one
+    /// subgraph to another through an entity key.
+    pub key_edge_count: usize,
+    /// The fields (as `Type.field` coordinates) that some subgraph defining their parent type can't
+    /// resolve, so fetching them may require jumping to another subgraph.
+    pub cross_subgraph_fields: Vec<String>,
+    /// The name and field count of the object or interface type with the most fields.
+    pub largest_type: Option<(String, usize)>,
+    /// The length of the longest chain of `@requires`, where a field with `@requires` that requires
+    /// another field with `@requires` makes a chain of length 2. This is 0 when no field has
+    /// `@requires`.
+    pub deepest_requires_chain: usize,
+}
+
+impl Display for SupergraphReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "subgraphs: {}", self.subgraph_count)?;
+        writeln!(f, "entities: {}", self.entity_count)?;
+        writeln!(f, "key edges: {}", self.key_edge_count)?;
+        writeln!(
+            f,
+            "cross-subgraph fields: {}",
+            self.cross_subgraph_fields.len()
+        )?;
+        match &self.largest_type {
+            Some((type_name, field_count)) => {
+                writeln!(f, "largest type: {} ({} fields)", type_name, field_count)?
+            }
+            None => writeln!(f, "largest type: none")?,
+        }
+        write!(
+            f,
+            "deepest @requires chain: {}",
+            self.deepest_requires_chain
+        )
+    }
+}
+
+pub(crate) fn report(supergraph: &Supergraph) -> Result<SupergraphReport, FederationError> {
+    let api_schema = ValidFederationSchema::new(supergraph.to_api_schema(Default::default())?)?;
+    let supergraph_schema = supergraph.schema.clone();
+    let query_graph =
+        build_federated_query_graph(supergraph_schema, api_schema.clone(), None, None)?;
+    Ok(SupergraphReport {
+        subgraph_count: query_graph.subgraphs().count(),
+        entity_count: entity_count(&query_graph),
+        key_edge_count: key_edge_count(&query_graph)?,
+        cross_subgraph_fields: cross_subgraph_fields(&query_graph)?,
+        largest_type: largest_type(&api_schema),
+        deepest_requires_chain: deepest_requires_chain(&query_graph),
+    })
+}
+
+fn entity_count(query_graph: &QueryGraph) -> usize {
+    let mut entities = IndexSet::new();
+    for (_, schema) in query_graph.subgraphs() {
+        if let Some(ExtendedType::Union(entity_union)) =
+            schema.schema().types.get(&ENTITY_UNION_NAME)
+        {
+            entities.extend(
+                entity_union
+                    .members
+                    .iter()
+                    .map(|member| member.name.clone()),
+            );
+        }
+    }
+    entities.len()
+}
+
+fn key_edge_count(query_graph: &QueryGraph) -> Result<usize, FederationError> {
+    let mut count = 0;
+    for edge in query_graph.graph().edge_references() {
+        if matches!(
+            edge.weight().transition,
+            QueryGraphEdgeTransition::KeyResolution
+        ) && query_graph.is_cross_subgraph_edge(edge.id())?
+        {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn cross_subgraph_fields(query_graph: &QueryGraph) -> Result<Vec<String>, FederationError> {
+    // The fields collectable from each (non-@provides) node of a type, across subgraphs.
+    let mut fields_by_type: IndexMap<Name, Vec<IndexSet<Name>>> = IndexMap::new();
+    for node in query_graph.graph().node_indices() {
+        let node_weight = query_graph.node_weight(node)?;
+        let QueryGraphNodeType::SchemaType(
+            type_position @ (OutputTypeDefinitionPosition::Object(_)
+            | OutputTypeDefinitionPosition::Interface(_)),
+        ) = &node_weight.type_
+        else {
+            continue;
+        };
+        if node_weight.provide_id.is_some() {
+            continue;
+        }
+        let fields = query_graph
+            .out_edges(node)
+            .filter_map(|edge| match &edge.weight().transition {
+                QueryGraphEdgeTransition::FieldCollection {
+                    field_definition_position,
+                    is_part_of_provides: false,
+                    ..
+                } if !field_definition_position.is_introspection_typename_field() => {
+                    Some(field_definition_position.field_name().clone())
+                }
+                _ => None,
+            })
+            .collect();
+        fields_by_type
+            .entry(type_position.type_name().clone())
+            .or_default()
+            .push(fields);
+    }
+    let mut cross_subgraph_fields = vec![];
+    for (type_name, fields_per_subgraph) in fields_by_type {
+        let all_fields = fields_per_subgraph
+            .iter()
+            .flatten()
+            .collect::<IndexSet<_>>();
+        for field_name in all_fields {
+            if fields_per_subgraph
+                .iter()
+                .any(|fields| !fields.contains(field_name))
+            {
+                cross_subgraph_fields.push(format!("{}.{}", type_name, field_name));
+            }
+        }
+    }
+    Ok(cross_subgraph_fields)
+}
+
+fn largest_type(api_schema: &ValidFederationSchema) -> Option<(String, usize)> {
+    let mut largest_type: Option<(String, usize)> = None;
+    for (type_name, type_) in &api_schema.schema().types {
+        let field_count = match type_ {
+            ExtendedType::Object(type_) if !type_.is_built_in() => type_.fields.len(),
+            ExtendedType::Interface(type_) if !type_.is_built_in() => type_.fields.len(),
+            _ => continue,
+        };
+        if !matches!(&largest_type, Some((_, largest_count)) if *largest_count >= field_count) {
+            largest_type = Some((type_name.to_string(), field_count));
+        }
+    }
+    largest_type
+}
+
+fn deepest_requires_chain(query_graph: &QueryGraph) -> usize {
+    // The fields required by each field with @requires (across subgraphs).
+    let mut requires: IndexMap<FieldDefinitionPosition, IndexSet<FieldDefinitionPosition>> =
+        IndexMap::new();
+    for edge in query_graph.graph().edge_weights() {
+        if let (
+            QueryGraphEdgeTransition::FieldCollection {
+                field_definition_position,
+                ..
+            },
+            Some(conditions),
+        ) = (&edge.transition, &edge.conditions)
+        {
+            collect_fields(
+                conditions,
+                requires
+                    .entry(field_definition_position.clone())
+                    .or_default(),
+            );
+        }
+    }
+    let mut depths = IndexMap::new();
+    requires
+        .keys()
+        .map(|field| requires_depth(field, &requires, &mut depths, &mut IndexSet::new()))
+        .max()
+        .unwrap_or(0)
+}
+
+fn collect_fields(
+    selection_set: &NormalizedSelectionSet,
+    fields: &mut IndexSet<FieldDefinitionPosition>,
+) {
+    for selection in selection_set.selections.values() {
+        match selection {
+            NormalizedSelection::Field(field_selection) => {
+                fields.insert(field_selection.field.data().field_position.clone());
+                if let Some(selection_set) = &field_selection.selection_set {
+                    collect_fields(selection_set, fields);
+                }
+            }
+            NormalizedSelection::InlineFragment(inline_fragment_selection) => {
+                collect_fields(&inline_fragment_selection.selection_set, fields);
+            }
+            // Conditions are parsed from field sets, which can't contain fragment spreads.
+            NormalizedSelection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+fn requires_depth(
+    field: &FieldDefinitionPosition,
+    requires: &IndexMap<FieldDefinitionPosition, IndexSet<FieldDefinitionPosition>>,
+    depths: &mut IndexMap<FieldDefinitionPosition, usize>,
+    visiting: &mut IndexSet<FieldDefinitionPosition>,
+) -> usize {
+    if let Some(depth) = depths.get(field) {
+        return *depth;
+    }
+    let Some(required_fields) = requires.get(field) else {
+        return 0;
+    };
+    // A cycle of @requires can't be satisfied, and is rejected by composition, so we just avoid
+    // recursing infinitely here.
+    if !visiting.insert(field.clone()) {
+        return 0;
+    }
+    let depth = 1 + required_fields
+        .iter()
+        .map(|required_field| requires_depth(required_field, requires, depths, visiting))
+        .max()
+        .unwrap_or(0);
+    visiting.shift_remove(field);
+    depths.insert(field.clone(), depth);
+    depth
+}
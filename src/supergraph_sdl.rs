@@ -0,0 +1,145 @@
+//! Implements the printing of supergraph SDL in the format of the JS composer.
+use crate::schema::FederationSchema;
+use apollo_compiler::ast::{self, OperationType};
+use apollo_compiler::schema::{ComponentName, DirectiveList, ExtendedType};
+use apollo_compiler::{Node, Schema};
+
+/// Prints the given (composed) supergraph schema the way the JS composer does, so that supergraphs
+/// composed by either can be diffed:
+/// - the schema definition comes first, then the directive definitions and the types, both sorted
+///   by name (the fields, arguments and values of each definition keep their order),
+/// - the directive applications of the schema definition and of types (e.g. `@link` and
+///   `@join__type`) are printed on separate lines, while those of fields and enum values stay on
+///   the line of their element.
+///
+/// Built-in types and directives are omitted, as in any SDL printed by apollo-compiler.
+// PORT_NOTE: The JS composer prints its supergraphs with `printSchema()`, using the
+// `shallowOrderPrintedDefinitions()` option.
+pub fn print_supergraph_sdl(schema: &FederationSchema) -> String {
+    let schema = schema.schema();
+    let mut definitions = Vec::new();
+    definitions.extend(print_schema_definition(schema));
+    let mut directive_definitions = schema
+        .directive_definitions
+        .values()
+        .filter(|definition| !definition.is_built_in())
+        .collect::<Vec<_>>();
+    directive_definitions.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+    definitions.extend(
+        directive_definitions
+            .into_iter()
+            .map(|definition| definition.to_string().trim_end().to_owned()),
+    );
+    let mut types = schema
+        .types
+        .values()
+        .filter(|type_| !type_.is_built_in())
+        .collect::<Vec<_>>();
+    types.sort_by(|a, b| a.name().as_str().cmp(b.name().as_str()));
+    definitions.extend(types.into_iter().map(print_type));
+    let mut sdl = definitions.join("\n\n");
+    sdl.push('\n');
+    sdl
+}
+
+/// Prints the schema definition, unless it's implicit (i.e. it has no description or directive,
+/// and its root types are the types with the default root names).
+fn print_schema_definition(schema: &Schema) -> Option<String> {
+    let schema_definition = &schema.schema_definition;
+    let roots = [
+        (OperationType::Query, &schema_definition.query),
+        (OperationType::Mutation, &schema_definition.mutation),
+        (OperationType::Subscription, &schema_definition.subscription),
+    ];
+    let is_implicit = schema_definition.description.is_none()
+        && schema_definition.directives.is_empty()
+        && roots.iter().all(|(operation_type, root)| {
+            let default_name = operation_type.default_type_name();
+            let default_root = schema
+                .types
+                .get(&default_name)
+                .filter(|type_| type_.is_object())
+                .map(|type_| type_.name().as_str());
+            root.as_ref().map(|root| root.name.as_str()) == default_root
+        });
+    if is_implicit {
+        return None;
+    }
+    let definition = |description| ast::SchemaDefinition {
+        description,
+        directives: Default::default(),
+        root_operations: roots
+            .iter()
+            .filter_map(|(operation_type, root)| {
+                root.as_ref()
+                    .map(|ComponentName { name, .. }| Node::new((*operation_type, name.clone())))
+            })
+            .collect(),
+    };
+    Some(print_with_directives_on_separate_lines(
+        definition(schema_definition.description.clone()).to_string(),
+        definition(None).to_string(),
+        &schema_definition.directives,
+    ))
+}
+
+fn print_type(type_: &ExtendedType) -> String {
+    let mut without_directives = type_.clone();
+    match &mut without_directives {
+        ExtendedType::Scalar(type_) => type_.make_mut().directives = Default::default(),
+        ExtendedType::Object(type_) => type_.make_mut().directives = Default::default(),
+        ExtendedType::Interface(type_) => type_.make_mut().directives = Default::default(),
+        ExtendedType::Union(type_) => type_.make_mut().directives = Default::default(),
+        ExtendedType::Enum(type_) => type_.make_mut().directives = Default::default(),
+        ExtendedType::InputObject(type_) => type_.make_mut().directives = Default::default(),
+    }
+    let mut without_description = without_directives.clone();
+    match &mut without_description {
+        ExtendedType::Scalar(type_) => type_.make_mut().description = None,
+        ExtendedType::Object(type_) => type_.make_mut().description = None,
+        ExtendedType::Interface(type_) => type_.make_mut().description = None,
+        ExtendedType::Union(type_) => type_.make_mut().description = None,
+        ExtendedType::Enum(type_) => type_.make_mut().description = None,
+        ExtendedType::InputObject(type_) => type_.make_mut().description = None,
+    }
+    print_with_directives_on_separate_lines(
+        without_directives.to_string(),
+        without_description.to_string(),
+        type_.directives(),
+    )
+}
+
+/// Inserts the given directive applications, one per line, in the header of a definition printed
+/// without them. The definition is given both with and without its description, the latter
+/// starting with its header (e.g. `type T implements I {` or `union U = A | B`).
+fn print_with_directives_on_separate_lines(
+    with_description: String,
+    without_description: String,
+    directives: &DirectiveList,
+) -> String {
+    let with_description = with_description.trim_end();
+    let without_description = without_description.trim_end();
+    if directives.is_empty() {
+        return with_description.to_owned();
+    }
+    let description = &with_description[..with_description.len() - without_description.len()];
+    let (header, rest) = without_description
+        .split_once('\n')
+        .unwrap_or((without_description, ""));
+    let directives = directives
+        .iter()
+        .map(|directive| format!("\n  {}", directive.node))
+        .collect::<String>();
+    let header = if let Some(header) = header.strip_suffix(" {") {
+        format!("{header}{directives}\n{{")
+    } else if let Some((header, members)) = header.split_once(" = ") {
+        format!("{header}{directives}\n = {members}")
+    } else {
+        format!("{header}{directives}")
+    };
+    if rest.is_empty() {
+        format!("{description}{header}")
+    } else {
+        format!("{description}{header}\n{rest}")
+    }
+}
@@ -0,0 +1,71 @@
+//! The `@link`/`@join` boilerplate of the supergraphs written out in tests, so that they only
+//! have to spell out their types. This is shared with the integration tests (see `tests/main.rs`),
+//! so it doesn't depend on the rest of the crate.
+
+/// The SDL of a supergraph with the given subgraphs (named and with a URL of `http://<name>`), and
+/// with the given types (using join v0.5 directives). Its root operation types are the `Query`
+/// type and, when the given types define them, the `Mutation` and `Subscription` types. Other specs
+/// can be linked with an `extend schema @link(...)` in the types, along with their definitions.
+pub(crate) fn supergraph_sdl(graphs: &[&str], types: &str) -> String {
+    let root_types = ["Mutation", "Subscription"]
+        .into_iter()
+        .filter(|root_type| {
+            types.lines().any(|line| {
+                line.trim_start()
+                    .strip_prefix("type ")
+                    .is_some_and(|rest| rest.split_whitespace().next() == Some(root_type))
+            })
+        })
+        .map(|root_type| format!("\n  {}: {}", root_type.to_lowercase(), root_type))
+        .collect::<String>();
+    let graphs = graphs
+        .iter()
+        .map(|graph| {
+            format!("\n  {graph} @join__graph(name: \"{graph}\", url: \"http://{graph}\")")
+        })
+        .collect::<String>();
+    format!(
+        r#"schema
+  @link(url: "https://specs.apollo.dev/link/v1.0")
+  @link(url: "https://specs.apollo.dev/join/v0.5", for: EXECUTION)
+{{
+  query: Query{root_types}
+}}
+
+directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean, overrideLabel: String, contextArguments: [join__ContextArgument!]) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+input join__ContextArgument {{
+  name: String!
+  type: String!
+  context: String!
+  selection: join__FieldValue!
+}}
+
+scalar join__FieldSet
+
+scalar join__FieldValue
+
+enum join__Graph {{{graphs}
+}}
+
+scalar link__Import
+
+enum link__Purpose {{
+  SECURITY
+  EXECUTION
+}}
+{types}"#
+    )
+}
@@ -0,0 +1,65 @@
+//! Canonical supergraph, operation and query plan vectors, for router integrators to check that
+//! their executor handles the plans this crate produces. Each vector's plan is the one this crate
+//! guarantees to produce for its operation (with `TestVector::query_planner_config()`), in both the
+//! JSON format executors consume (see `QueryPlan::to_json()`) and the human-readable one (see
+//! `QueryPlan::to_pretty_string()`).
+//!
+//! Any change to the plans of these vectors, or to the format of plans, bumps
+//! `PLAN_FORMAT_VERSION`, so that integrators notice when they need to check their executor again.
+//!
+//! This module is only available with the `test_vectors` feature.
+use crate::query_plan::query_planner::QueryPlannerConfig;
+
+/// The version of the plans of the test vectors (and of their format). It's bumped whenever any of
+/// them changes.
+pub const PLAN_FORMAT_VERSION: u32 = 2;
+
+const PRODUCTS_SUPERGRAPH: &str = include_str!("test_vectors/products.graphql");
+
+/// A supergraph, an operation on its API schema, and the query plan of that operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// A unique name for this vector.
+    pub name: &'static str,
+    /// The supergraph SDL.
+    pub supergraph: &'static str,
+    /// The executable document holding the (single) operation to plan.
+    pub operation: &'static str,
+    /// The expected plan, serialized with `QueryPlan::to_json()`.
+    pub plan_json: &'static str,
+    /// The expected plan, printed with `QueryPlan::to_pretty_string()`.
+    pub plan_pretty: &'static str,
+}
+
+impl TestVector {
+    /// The configuration of the query planner the plans of the vectors are produced with.
+    // TODO: Use the default configuration once graph traversal is ported; until then, only
+    // supergraphs with a single subgraph can be planned (by bypassing the planner).
+    pub fn query_planner_config() -> QueryPlannerConfig {
+        let mut config = QueryPlannerConfig::default();
+        config.debug.bypass_planner_for_single_subgraph = true;
+        config
+    }
+}
+
+macro_rules! test_vector {
+    ($name:literal, $supergraph:expr) => {
+        TestVector {
+            name: $name,
+            supergraph: $supergraph,
+            operation: include_str!(concat!("test_vectors/", $name, "/operation.graphql")),
+            plan_json: include_str!(concat!("test_vectors/", $name, "/plan.json")),
+            plan_pretty: include_str!(concat!("test_vectors/", $name, "/plan.txt")),
+        }
+    };
+}
+
+/// The test vectors, in a stable order.
+pub fn test_vectors() -> Vec<TestVector> {
+    vec![
+        test_vector!("query", PRODUCTS_SUPERGRAPH),
+        test_vector!("conditions", PRODUCTS_SUPERGRAPH),
+        test_vector!("mutation", PRODUCTS_SUPERGRAPH),
+        test_vector!("introspection", PRODUCTS_SUPERGRAPH),
+    ]
+}
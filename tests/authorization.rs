@@ -0,0 +1,158 @@
+use crate::test_supergraph::supergraph_sdl;
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::{AuthorizationRequirements, Supergraph};
+use indexmap::IndexSet;
+
+fn supergraph() -> String {
+    supergraph_sdl(
+        &["S1"],
+        r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/authenticated/v0.1", for: SECURITY)
+          @link(url: "https://specs.apollo.dev/requiresScopes/v0.1", for: SECURITY)
+          @link(url: "https://specs.apollo.dev/policy/v0.1", for: SECURITY)
+
+        directive @authenticated on OBJECT | FIELD_DEFINITION | INTERFACE | SCALAR | ENUM
+
+        directive @policy(policies: [[policy__Policy!]!]!) on OBJECT | FIELD_DEFINITION | INTERFACE | SCALAR | ENUM
+
+        directive @requiresScopes(scopes: [[requiresScopes__Scope!]!]!) on OBJECT | FIELD_DEFINITION | INTERFACE | SCALAR | ENUM
+
+        scalar policy__Policy
+
+        scalar requiresScopes__Scope
+
+        type Query
+          @join__type(graph: S1)
+        {
+          me: User @authenticated
+          publicName: String
+          secret: Secret
+        }
+
+        type Secret
+          @join__type(graph: S1)
+          @policy(policies: [["admin"]])
+        {
+          value: String
+        }
+
+        type User
+          @join__type(graph: S1)
+          @requiresScopes(scopes: [["read:user"]])
+        {
+          name: String
+          email: String @requiresScopes(scopes: [["read:email"], ["admin"]])
+          ssn: String @policy(policies: [["hr"], ["admin"]])
+        }
+        "#,
+    )
+}
+
+#[test]
+fn collects_authorization_metadata() {
+    let supergraph = Supergraph::new(&supergraph()).unwrap();
+    let metadata = supergraph.authorization_metadata().unwrap();
+    assert!(!metadata.is_empty());
+    assert_eq!(
+        metadata.field_requirements("Query", "me"),
+        Some(&AuthorizationRequirements {
+            authenticated: true,
+            scopes: vec![],
+            policies: vec![],
+        })
+    );
+    assert_eq!(metadata.field_requirements("Query", "publicName"), None);
+    assert_eq!(
+        metadata.type_requirements("User"),
+        Some(&AuthorizationRequirements {
+            authenticated: false,
+            scopes: vec![vec!["read:user".to_owned()]],
+            policies: vec![],
+        })
+    );
+
+    let document = ExecutableDocument::parse_and_validate(
+        supergraph.schema(),
+        "{ publicName me { name email } }",
+        "operation.graphql",
+    )
+    .unwrap();
+    let operation = document.get_operation(None).unwrap();
+    assert_eq!(
+        metadata.selection_set_requirements(&operation.selection_set),
+        AuthorizationRequirements {
+            authenticated: true,
+            scopes: vec![
+                vec!["read:user".to_owned(), "read:email".to_owned()],
+                vec!["read:user".to_owned(), "admin".to_owned()],
+            ],
+            policies: vec![],
+        }
+    );
+}
+
+#[test]
+fn filters_selections_by_policies() {
+    let supergraph = Supergraph::new(&supergraph()).unwrap();
+    let metadata = supergraph.authorization_metadata().unwrap();
+    assert_eq!(
+        metadata.field_requirements("User", "ssn"),
+        Some(&AuthorizationRequirements {
+            authenticated: false,
+            scopes: vec![],
+            policies: vec![vec!["hr".to_owned()], vec!["admin".to_owned()]],
+        })
+    );
+
+    let filter = |operation: &str, satisfied_policies: &[&str]| {
+        let document =
+            ExecutableDocument::parse_and_validate(supergraph.schema(), operation, "op.graphql")
+                .unwrap();
+        let satisfied_policies = satisfied_policies
+            .iter()
+            .map(|policy| policy.to_string())
+            .collect::<IndexSet<_>>();
+        let filtered = metadata
+            .filter_by_policies(supergraph.schema(), &document, &satisfied_policies)
+            .unwrap();
+        (filtered.document.to_string(), filtered.is_filtered)
+    };
+    let operation = "{ publicName me { name ssn } secret { value } }";
+    insta::assert_snapshot!(filter(operation, &["admin"]).0, @r###"
+    {
+      publicName
+      me {
+        name
+        ssn
+      }
+      secret {
+        value
+      }
+    }
+    "###);
+    insta::assert_snapshot!(filter(operation, &["hr"]).0, @r###"
+    {
+      publicName
+      me {
+        name
+        ssn
+      }
+    }
+    "###);
+    insta::assert_snapshot!(filter(operation, &[]).0, @r###"
+    {
+      publicName
+      me {
+        name
+      }
+    }
+    "###);
+    insta::assert_snapshot!(filter("{ secret { value } }", &[]).0, @r###"
+    {
+      __typename
+    }
+    "###);
+    assert!(!filter(operation, &["admin"]).1);
+    assert!(filter(operation, &["hr"]).1);
+}
@@ -1,6 +1,8 @@
 use apollo_compiler::Schema;
-use apollo_federation::subgraph::Subgraph;
-use apollo_federation::Supergraph;
+use apollo_federation::merge::merge_subgraphs;
+use apollo_federation::schema::FederationSchema;
+use apollo_federation::subgraph::{Subgraph, ValidSubgraph};
+use apollo_federation::{print_supergraph_sdl, Supergraph};
 
 fn print_sdl(schema: &Schema) -> String {
     let mut schema = schema.clone();
@@ -50,7 +52,7 @@ fn can_compose_supergraph() {
     .unwrap();
 
     let supergraph = Supergraph::compose(vec![&s1, &s2]).unwrap();
-    insta::assert_snapshot!(print_sdl(&supergraph.schema));
+    insta::assert_snapshot!(print_sdl(supergraph.schema()));
     insta::assert_snapshot!(print_sdl(
         &supergraph.to_api_schema(Default::default()).unwrap()
     ));
@@ -104,7 +106,7 @@ fn can_compose_with_descriptions() {
     .unwrap();
 
     let supergraph = Supergraph::compose(vec![&s1, &s2]).unwrap();
-    insta::assert_snapshot!(print_sdl(&supergraph.schema));
+    insta::assert_snapshot!(print_sdl(supergraph.schema()));
     insta::assert_snapshot!(print_sdl(
         &supergraph.to_api_schema(Default::default()).unwrap()
     ));
@@ -140,7 +142,7 @@ fn can_compose_types_from_different_subgraphs() {
     )
     .unwrap();
     let supergraph = Supergraph::compose(vec![&s1, &s2]).unwrap();
-    insta::assert_snapshot!(print_sdl(&supergraph.schema));
+    insta::assert_snapshot!(print_sdl(supergraph.schema()));
     insta::assert_snapshot!(print_sdl(
         &supergraph.to_api_schema(Default::default()).unwrap()
     ));
@@ -181,8 +183,357 @@ fn compose_removes_federation_directives() {
         .unwrap();
 
     let supergraph = Supergraph::compose(vec![&s1, &s2]).unwrap();
-    insta::assert_snapshot!(print_sdl(&supergraph.schema));
+    insta::assert_snapshot!(print_sdl(supergraph.schema()));
     insta::assert_snapshot!(print_sdl(
         &supergraph.to_api_schema(Default::default()).unwrap()
     ));
 }
+
+#[test]
+fn compose_validates_requires_against_all_subgraphs() {
+    let subgraph = |name: &str, schema: &str| {
+        Subgraph::parse_and_expand(name, &format!("https://{name}"), schema).unwrap()
+    };
+    let shipping = subgraph(
+        "shipping",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@external", "@requires" ])
+
+            type Product @key(fields: "upc") {
+              upc: ID!
+              weight: Int @external
+              dimensions: Dimensions @external
+              shippingEstimate: Int @requires(fields: "weight dimensions { size }")
+            }
+
+            type Dimensions {
+              size: Int @external
+            }
+        "#,
+    );
+    let reviews = subgraph(
+        "reviews",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@external", "@requires" ])
+
+            type Product @key(fields: "upc") {
+              upc: ID!
+              weight: Int @external
+              heavy: Boolean @requires(fields: "weight")
+            }
+        "#,
+    );
+    let inventory = subgraph(
+        "inventory",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@shareable" ])
+
+            type Query {
+              products: [Product]
+            }
+
+            type Product @key(fields: "upc") {
+              upc: ID!
+              weight: Int
+              dimensions: Dimensions
+            }
+
+            type Dimensions @shareable {
+              size: Int
+            }
+        "#,
+    );
+
+    // `weight` is external in both requiring subgraphs, but provided by a third one.
+    assert!(Supergraph::compose(vec![&shipping, &reviews, &inventory]).is_ok());
+
+    let Err(failure) = Supergraph::compose(vec![&shipping, &reviews]) else {
+        panic!("composition should fail as no subgraph resolves `weight`");
+    };
+    assert_eq!(
+        failure.errors,
+        vec![
+            "@requires field is not resolvable by any subgraph",
+            "@requires field is not resolvable by any subgraph",
+        ]
+    );
+}
+
+#[test]
+fn compose_hints_at_federation_versions_read_as_another_one() {
+    // Expanding links rejects federation versions it doesn't know of, so these subgraphs are
+    // built directly.
+    let subgraph = |name: &str, version: &str| {
+        ValidSubgraph {
+        name: name.to_owned(),
+        url: format!("https://{name}"),
+        schema: Schema::parse_and_validate(
+            format!(
+                r#"
+                    extend schema
+                      @link(url: "https://specs.apollo.dev/link/v1.0")
+                      @link(url: "https://specs.apollo.dev/federation/{version}")
+
+                    directive @link(url: String, as: String, import: [link__Import]) repeatable on SCHEMA
+
+                    scalar link__Import
+
+                    type Query {{
+                      {name}: Int
+                    }}
+                "#
+            ),
+            "schema.graphql",
+        )
+        .unwrap(),
+    }
+    };
+    let newer = subgraph("newer", "v2.99");
+    let known = subgraph("known", "v2.5");
+    let success = merge_subgraphs(vec![&newer, &known]).unwrap();
+    assert_eq!(
+        success.composition_hints,
+        vec![
+            "Subgraph \"newer\": Federation spec version 2.99 is newer than the latest version known to this library (2.8), and will be handled as if it were 2.8",
+        ]
+    );
+
+    let unsupported = subgraph("unsupported", "v3.0");
+    let Err(failure) = merge_subgraphs(vec![&unsupported, &known]) else {
+        panic!("composition should fail for an unsupported federation version");
+    };
+    assert_eq!(failure.errors.len(), 1);
+    assert!(failure.errors[0].starts_with("Subgraph \"unsupported\": Invalid version 3.0"));
+}
+
+#[test]
+fn compose_reads_federation_directives_under_their_name_in_subgraphs() {
+    let products = Subgraph::parse_and_expand(
+        "products",
+        "https://products",
+        r#"
+            extend schema
+              @link(url: "https://specs.apollo.dev/federation/v2.5", import: [{ name: "@key", as: "@primaryKey" }, { name: "@tag", as: "@label" }])
+
+            type Query {
+              products: [Product]
+            }
+
+            type Product @primaryKey(fields: "upc") @label(name: "public") {
+              upc: ID!
+              name: String
+            }
+        "#,
+    )
+    .unwrap();
+    let reviews = Subgraph::parse_and_expand(
+        "reviews",
+        "https://reviews",
+        r#"
+            extend schema
+              @link(url: "https://specs.apollo.dev/federation/v2.5")
+
+            type Product @federation__key(fields: "upc") {
+              upc: ID!
+              reviewCount: Int
+            }
+        "#,
+    )
+    .unwrap();
+
+    let supergraph = Supergraph::compose(vec![&products, &reviews]).unwrap();
+    let sdl = print_sdl(supergraph.schema());
+    assert!(sdl.contains(r#"@join__type(graph: PRODUCTS, key: "upc")"#));
+    assert!(sdl.contains(r#"@join__type(graph: REVIEWS, key: "upc")"#));
+    assert!(sdl.contains(r#"@tag(name: "public")"#));
+    assert!(!sdl.contains("primaryKey"));
+    assert!(!sdl.contains("label"));
+}
+
+#[test]
+fn compose_carries_composed_directives() {
+    let subgraph = |name: &str, version: &str, schema: &str| {
+        let schema = format!(
+            r#"
+            extend schema
+              @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@composeDirective" ])
+              @link(url: "https://myspecs.dev/custom/{version}", import: [ "@custom" ])
+              @composeDirective(name: "@custom")
+
+            directive @custom on OBJECT | FIELD_DEFINITION
+            {schema}
+            "#
+        );
+        Subgraph::parse_and_expand(name, &format!("https://{name}"), &schema).unwrap()
+    };
+    let products = subgraph(
+        "products",
+        "v1.0",
+        r#"
+            type Query {
+              products: [Product]
+            }
+
+            type Product @key(fields: "upc") @custom {
+              upc: ID!
+            }
+        "#,
+    );
+    let reviews = subgraph(
+        "reviews",
+        "v1.2",
+        r#"
+            type Product @key(fields: "upc") {
+              upc: ID!
+              reviews: [String] @custom
+            }
+        "#,
+    );
+
+    let supergraph = Supergraph::compose(vec![&products, &reviews]).unwrap();
+    let sdl = print_sdl(supergraph.schema());
+    assert!(sdl.contains(r#"@link(url: "https://myspecs.dev/custom/v1.2", import: ["@custom"])"#));
+    assert!(sdl.contains("directive @custom on OBJECT | FIELD_DEFINITION"));
+    assert!(sdl.contains(r#"type Product @join__type(graph: PRODUCTS, key: "upc") @custom"#));
+    assert!(sdl.contains("reviews: [String] @custom @join__field(graph: REVIEWS)"));
+
+    // The composed directive definition and applications are copied back into the subgraphs.
+    supergraph.extract_subgraphs().unwrap();
+
+    let other_major = subgraph(
+        "other",
+        "v2.0",
+        r#"
+            type Product @key(fields: "upc") {
+              upc: ID!
+              price: Int @custom
+            }
+        "#,
+    );
+    let Err(failure) = Supergraph::compose(vec![&products, &other_major]) else {
+        panic!("composition should fail as the custom feature versions are incompatible");
+    };
+    assert_eq!(
+        failure.errors,
+        vec!["@composeDirective features are linked with different major versions"]
+    );
+}
+
+#[test]
+fn prints_supergraph_sdl() {
+    let s1 = Subgraph::parse_and_expand(
+        "Subgraph1",
+        "https://subgraph1",
+        r#"
+            type Query {
+              t: T
+            }
+
+            """A T."""
+            type T @key(fields: "k") {
+              k: ID
+            }
+
+            type S {
+              x: Int
+            }
+
+            union U = S | T
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "Subgraph2",
+        "https://subgraph2",
+        r#"
+            type T @key(fields: "k") {
+              k: ID
+              a: Int
+            }
+
+            enum E {
+              V1
+              V2
+            }
+        "#,
+    )
+    .unwrap();
+
+    let supergraph = Supergraph::compose(vec![&s1, &s2]).unwrap();
+    let schema = FederationSchema::new(supergraph.into_schema().into_inner()).unwrap();
+    insta::assert_snapshot!(print_supergraph_sdl(&schema), @r###"
+    schema
+      @link(url: "https://specs.apollo.dev/link/v1.0")
+      @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION)
+    {
+      query: Query
+    }
+
+    directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+    directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+    directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+    directive @join__implements(graph: join__Graph!, interface: String!) repeatable on INTERFACE | OBJECT
+
+    directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on ENUM | INPUT_OBJECT | INTERFACE | OBJECT | SCALAR | UNION
+
+    directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+    directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+    enum E
+      @join__type(graph: SUBGRAPH2)
+    {
+      V1 @join__enumValue(graph: SUBGRAPH2)
+      V2 @join__enumValue(graph: SUBGRAPH2)
+    }
+
+    type Query
+      @join__type(graph: SUBGRAPH1)
+      @join__type(graph: SUBGRAPH2)
+    {
+      t: T @join__field(graph: SUBGRAPH1)
+    }
+
+    type S
+      @join__type(graph: SUBGRAPH1)
+    {
+      x: Int
+    }
+
+    """A T."""
+    type T
+      @join__type(graph: SUBGRAPH1, key: "k")
+      @join__type(graph: SUBGRAPH2, key: "k")
+    {
+      k: ID
+      a: Int @join__field(graph: SUBGRAPH2)
+    }
+
+    union U
+      @join__type(graph: SUBGRAPH1)
+      @join__unionMember(graph: SUBGRAPH1, member: "S")
+      @join__unionMember(graph: SUBGRAPH1, member: "T")
+     = S | T
+
+    scalar join__FieldSet
+
+    enum join__Graph {
+      SUBGRAPH1 @join__graph(name: "Subgraph1", url: "https://subgraph1")
+      SUBGRAPH2 @join__graph(name: "Subgraph2", url: "https://subgraph2")
+    }
+
+    scalar link__Import
+
+    enum link__Purpose {
+      """
+      SECURITY features provide metadata necessary to securely resolve fields.
+      """
+      SECURITY
+      """EXECUTION features provide metadata necessary for operation execution."""
+      EXECUTION
+    }
+    "###);
+}
@@ -0,0 +1,117 @@
+use apollo_compiler::Schema;
+use apollo_federation::subgraph::Subgraph;
+use apollo_federation::Supergraph;
+use indexmap::IndexSet;
+
+fn print_sdl(schema: &Schema) -> String {
+    let mut schema = schema.clone();
+    schema.types.sort_keys();
+    schema.directive_definitions.sort_keys();
+    schema.to_string()
+}
+
+fn tags(tags: &[&str]) -> IndexSet<String> {
+    tags.iter().map(|tag| tag.to_string()).collect()
+}
+
+fn supergraph() -> Supergraph {
+    let products = Subgraph::parse_and_expand(
+        "products",
+        "https://products",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@tag" ])
+
+            type Query {
+              products: [Product] @tag(name: "public")
+              internalProducts: [Product] @tag(name: "internal")
+            }
+
+            type Product @key(fields: "upc") @tag(name: "public") {
+              upc: ID!
+              name: String
+              cost: Int @tag(name: "internal")
+            }
+
+            type Supplier @tag(name: "internal") {
+              id: ID!
+            }
+        "#,
+    )
+    .unwrap();
+    let reviews = Subgraph::parse_and_expand(
+        "reviews",
+        "https://reviews",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key" ])
+
+            type Product @key(fields: "upc") {
+              upc: ID!
+              reviews: [Review] @federation__tag(name: "public")
+            }
+
+            type Review {
+              body: String
+              supplier: Supplier
+            }
+
+            type Supplier {
+              id: ID!
+            }
+        "#,
+    )
+    .unwrap();
+    Supergraph::compose(vec![&products, &reviews]).unwrap()
+}
+
+#[test]
+fn compose_keeps_tags() {
+    let sdl = print_sdl(supergraph().schema());
+    assert!(sdl.contains(r#"@link(url: "https://specs.apollo.dev/tag/v0.3")"#));
+    assert!(sdl.contains("directive @tag(name: String!) repeatable on"));
+    assert!(sdl.contains(r#"cost: Int @tag(name: "internal") @join__field(graph: PRODUCTS)"#));
+    // Tags under the `federation__` prefix are renamed.
+    assert!(sdl.contains(r#"reviews: [Review] @tag(name: "public") @join__field(graph: REVIEWS)"#));
+}
+
+#[test]
+fn filters_supergraph_by_tags() {
+    let supergraph = supergraph();
+
+    let public = supergraph
+        .filter_by_tags(&tags(&["public"]), &tags(&["internal"]))
+        .unwrap();
+    insta::assert_snapshot!(print_sdl(&public.to_api_schema(Default::default()).unwrap()), @r###"
+    type Product {
+      upc: ID!
+      name: String
+    }
+
+    type Query {
+      products: [Product]
+    }
+    "###);
+
+    let external = supergraph
+        .filter_by_tags(&tags(&[]), &tags(&["internal"]))
+        .unwrap();
+    insta::assert_snapshot!(print_sdl(&external.to_api_schema(Default::default()).unwrap()), @r###"
+    type Product {
+      upc: ID!
+      name: String
+      reviews: [Review]
+    }
+
+    type Query {
+      products: [Product]
+    }
+
+    type Review {
+      body: String
+    }
+    "###);
+
+    // Hiding every root field leaves no query type.
+    assert!(supergraph
+        .filter_by_tags(&tags(&["unknown"]), &tags(&[]))
+        .is_err());
+}
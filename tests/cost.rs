@@ -0,0 +1,141 @@
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::cost;
+use apollo_federation::query_plan::NormalizedOperation;
+use apollo_federation::schema::ValidFederationSchema;
+use apollo_federation::Supergraph;
+use serde_json::json;
+
+const SUPERGRAPH: &str = r#"
+    schema
+      @link(url: "https://specs.apollo.dev/link/v1.0")
+      @link(url: "https://specs.apollo.dev/cost/v0.1", import: ["@cost", "@listSize"])
+    {
+      query: Query
+      mutation: Mutation
+    }
+
+    directive @cost(weight: Int!) on ARGUMENT_DEFINITION | ENUM | FIELD_DEFINITION | INPUT_FIELD_DEFINITION | OBJECT | SCALAR
+
+    directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+    directive @listSize(assumedSize: Int, slicingArguments: [String!], sizedFields: [String!], requireOneSlicingArgument: Boolean = true) on FIELD_DEFINITION
+
+    scalar link__Import
+
+    enum link__Purpose {
+      SECURITY
+      EXECUTION
+    }
+
+    type Query {
+      books(first: Int): [Book] @listSize(slicingArguments: ["first"])
+      recommended(first: Int, last: Int): [Book] @listSize(slicingArguments: ["first", "last"], requireOneSlicingArgument: false)
+      authors: [Author] @listSize(assumedSize: 5)
+      search(filter: SearchFilter): BookConnection @listSize(assumedSize: 3, sizedFields: ["edges"])
+      bestseller: Book @cost(weight: 5)
+    }
+
+    type Mutation {
+      rate(rating: Float): Book
+    }
+
+    input SearchFilter {
+      title: String @cost(weight: 2)
+    }
+
+    type BookConnection {
+      edges: [Book]
+    }
+
+    type Book {
+      title: String
+      rating: Float @cost(weight: 3)
+      author: Author
+    }
+
+    type Author @cost(weight: 2) {
+      name: String
+    }
+"#;
+
+#[test]
+fn estimates_operation_costs() {
+    let supergraph = Supergraph::new(SUPERGRAPH).unwrap();
+    let schema = ValidFederationSchema::new(supergraph.schema().clone()).unwrap();
+    let metadata = supergraph.cost_metadata().unwrap();
+    assert!(!metadata.is_empty());
+    let estimate = |operation: &str, variables: serde_json::Value| {
+        let document =
+            ExecutableDocument::parse_and_validate(supergraph.schema(), operation, "op.graphql")
+                .unwrap();
+        let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+        let variables = variables.as_object().unwrap();
+        let cost = metadata.estimate(&operation, variables).unwrap();
+        assert_eq!(
+            cost::estimate(&operation, &supergraph, variables).unwrap(),
+            cost
+        );
+        cost
+    };
+
+    // Slicing arguments give the list size: 4 * (1 + 3 + 2).
+    assert_eq!(
+        estimate(
+            "query($n: Int) { books(first: $n) { title rating author { name } } }",
+            json!({ "n": 4 }),
+        ),
+        24.0
+    );
+    // Without slicing argument nor assumed size, lists have the default size.
+    assert_eq!(
+        estimate("{ recommended { title } }", json!({})),
+        cost::DEFAULT_LIST_SIZE
+    );
+    // Unless one slicing argument is required, the largest one gives the list size.
+    assert_eq!(
+        estimate("{ recommended(first: 2, last: 3) { title } }", json!({})),
+        3.0
+    );
+    // Type weights apply to the fields of that type: 5 * 2.
+    assert_eq!(estimate("{ authors { name } }", json!({})), 10.0);
+    // Sized fields get the list size of their parent, and input fields add their weight.
+    assert_eq!(
+        estimate(
+            r#"{ search(filter: { title: "Dune" }) { edges { title } } }"#,
+            json!({}),
+        ),
+        (1.0 + 3.0) + 2.0
+    );
+    assert_eq!(estimate("{ bestseller { rating } }", json!({})), 8.0);
+    assert_eq!(
+        estimate("mutation { rate(rating: 5) { title } }", json!({})),
+        cost::MUTATION_BASE_COST + 1.0
+    );
+}
+
+#[test]
+fn requires_one_slicing_argument_by_default() {
+    let supergraph = Supergraph::new(SUPERGRAPH).unwrap();
+    let schema = ValidFederationSchema::new(supergraph.schema().clone()).unwrap();
+    let document = ExecutableDocument::parse_and_validate(
+        supergraph.schema(),
+        "{ books { title } }",
+        "op.graphql",
+    )
+    .unwrap();
+    let operation = NormalizedOperation::from_document(&document, None, &schema).unwrap();
+    let error = supergraph
+        .cost_metadata()
+        .unwrap()
+        .estimate(&operation, &Default::default())
+        .unwrap_err();
+    insta::assert_snapshot!(error, @r###"Field "Query.books" requires exactly one of its slicing arguments ("first"), but 0 were given"###);
+}
+
+#[test]
+fn reads_newer_minor_cost_spec_versions_and_rejects_unsupported_ones() {
+    let supergraph = Supergraph::new(&SUPERGRAPH.replace("cost/v0.1", "cost/v0.9")).unwrap();
+    assert!(!supergraph.cost_metadata().unwrap().is_empty());
+    let supergraph = Supergraph::new(&SUPERGRAPH.replace("cost/v0.1", "cost/v1.0")).unwrap();
+    insta::assert_snapshot!(supergraph.cost_metadata().unwrap_err(), @"Invalid supergraph: uses unsupported cost spec version 1.0");
+}
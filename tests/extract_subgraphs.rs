@@ -0,0 +1,193 @@
+use apollo_federation::Supergraph;
+
+const SUPERGRAPH: &str = r#"
+    schema
+      @link(url: "https://specs.apollo.dev/link/v1.0")
+      @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION)
+    {
+      query: Query
+    }
+
+    directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+    directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, type: String, external: Boolean, override: String, usedOverridden: Boolean) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+    directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+    directive @join__implements(graph: join__Graph!, interface: String!) repeatable on OBJECT | INTERFACE
+
+    directive @join__type(graph: join__Graph!, key: join__FieldSet, extension: Boolean! = false, resolvable: Boolean! = true, isInterfaceObject: Boolean! = false) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+    directive @join__unionMember(graph: join__Graph!, member: String!) repeatable on UNION
+
+    directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+    scalar join__FieldSet
+
+    enum join__Graph {
+      PRODUCTS @join__graph(name: "products", url: "http://products:4001/graphql")
+      REVIEWS @join__graph(name: "reviews", url: "")
+    }
+
+    scalar link__Import
+
+    enum link__Purpose {
+      SECURITY
+      EXECUTION
+    }
+
+    type Query
+      @join__type(graph: PRODUCTS)
+      @join__type(graph: REVIEWS)
+    {
+      products: [Int] @join__field(graph: PRODUCTS)
+      reviews: [Int] @join__field(graph: REVIEWS)
+    }
+"#;
+
+#[test]
+fn extracts_subgraph_urls() {
+    let supergraph = Supergraph::new(SUPERGRAPH).unwrap();
+    let subgraphs = supergraph.extract_subgraphs().unwrap();
+    assert_eq!(
+        subgraphs.urls().collect::<Vec<_>>(),
+        vec![
+            ("products", "http://products:4001/graphql"),
+            ("reviews", ""),
+        ]
+    );
+    assert_eq!(subgraphs.get("products").unwrap().name, "products");
+    assert!(subgraphs.get("PRODUCTS").is_none());
+}
+
+#[test]
+fn parses_join_directive_applications() {
+    let supergraph = Supergraph::new(SUPERGRAPH).unwrap();
+    let join_metadata = supergraph.join_metadata();
+    assert_eq!(
+        join_metadata
+            .graphs()
+            .values()
+            .map(|graph| (graph.name.as_str(), graph.url.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("products", "http://products:4001/graphql"),
+            ("reviews", ""),
+        ]
+    );
+    assert_eq!(join_metadata.subgraph_name("REVIEWS"), Some("reviews"));
+    assert_eq!(
+        join_metadata
+            .type_applications("Query")
+            .iter()
+            .map(|application| application.graph.as_str())
+            .collect::<Vec<_>>(),
+        vec!["PRODUCTS", "REVIEWS"]
+    );
+    let field_applications = join_metadata.field_applications("Query", "reviews");
+    assert_eq!(field_applications.len(), 1);
+    assert_eq!(
+        field_applications[0]
+            .graph
+            .as_ref()
+            .map(|graph| graph.as_str()),
+        Some("REVIEWS")
+    );
+    assert!(join_metadata
+        .field_applications("Query", "missing")
+        .is_empty());
+}
+
+#[test]
+fn resolves_subgraph_urls() {
+    let supergraph = Supergraph::new(SUPERGRAPH).unwrap();
+    let mut subgraphs = supergraph.extract_subgraphs().unwrap();
+    // Only fill in the URLs missing from the supergraph.
+    subgraphs.resolve_urls(|name, url| url.is_empty().then(|| format!("http://{name}.local")));
+    assert_eq!(
+        subgraphs
+            .iter()
+            .map(|subgraph| subgraph.url.as_str())
+            .collect::<Vec<_>>(),
+        vec!["http://products:4001/graphql", "http://reviews.local"]
+    );
+}
+
+#[test]
+fn prints_extracted_subgraphs() {
+    let supergraph = Supergraph::new(SUPERGRAPH).unwrap();
+    let subgraphs = supergraph.extract_subgraphs().unwrap();
+    insta::assert_snapshot!(subgraphs.get("reviews").unwrap().to_sdl(), @r###"
+    schema {
+      query: Query
+    }
+
+    extend schema @link(url: "https://specs.apollo.dev/link/v1.0") @link(url: "https://specs.apollo.dev/federation/v2.8")
+
+    directive @link(url: String, as: String, for: link__Purpose, import: [link__Import]) repeatable on SCHEMA
+
+    directive @federation__key(fields: federation__FieldSet!, resolvable: Boolean = true) repeatable on OBJECT | INTERFACE
+
+    directive @federation__requires(fields: federation__FieldSet!) on FIELD_DEFINITION
+
+    directive @federation__provides(fields: federation__FieldSet!) on FIELD_DEFINITION
+
+    directive @federation__external(reason: String) on OBJECT | FIELD_DEFINITION
+
+    directive @federation__tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION | SCHEMA
+
+    directive @federation__extends on OBJECT | INTERFACE
+
+    directive @federation__shareable on OBJECT | FIELD_DEFINITION
+
+    directive @federation__inaccessible on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION
+
+    directive @federation__override(from: String!, label: String) on FIELD_DEFINITION
+
+    directive @federation__composeDirective(name: String) repeatable on SCHEMA
+
+    directive @federation__interfaceObject on OBJECT
+
+    directive @federation__authenticated on FIELD_DEFINITION | OBJECT | INTERFACE | SCALAR | ENUM
+
+    directive @federation__requiresScopes(scopes: [[federation__Scope!]!]!) on FIELD_DEFINITION | OBJECT | INTERFACE | SCALAR | ENUM
+
+    directive @federation__policy(policies: [[federation__Policy!]!]!) on FIELD_DEFINITION | OBJECT | INTERFACE | SCALAR | ENUM
+
+    directive @federation__context(name: String!) repeatable on INTERFACE | OBJECT | UNION
+
+    directive @federation__fromContext(field: federation__ContextFieldValue) on ARGUMENT_DEFINITION
+
+    scalar link__Import
+
+    enum link__Purpose {
+      """
+      \`SECURITY\` features provide metadata necessary to securely resolve fields.
+      """
+      SECURITY
+      """
+      \`EXECUTION\` features provide metadata necessary for operation execution.
+      """
+      EXECUTION
+    }
+
+    scalar federation__FieldSet
+
+    scalar federation__Scope
+
+    scalar federation__Policy
+
+    scalar federation__ContextFieldValue
+
+    type Query {
+      reviews: [Int]
+      _service: _Service!
+    }
+
+    scalar _Any
+
+    type _Service {
+      sdl: String
+    }
+    "###);
+}
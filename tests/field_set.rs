@@ -0,0 +1,51 @@
+use apollo_compiler::{name, NodeStr, Schema};
+use apollo_federation::field_set::{parse_field_set, print_field_set, validate_field_set};
+use apollo_federation::schema::ValidFederationSchema;
+
+const SCHEMA: &str = r#"
+    type Query {
+      user: User
+    }
+
+    type User {
+      id: ID!
+      name: String
+      organization: Organization
+    }
+
+    type Organization {
+      id: ID!
+    }
+"#;
+
+#[test]
+fn parses_and_prints_field_sets() {
+    let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+    let schema = ValidFederationSchema::new(schema).unwrap();
+    let selection_set = parse_field_set(
+        &schema,
+        name!("User"),
+        NodeStr::new("id organization { id }"),
+    )
+    .unwrap();
+    assert_eq!(selection_set.type_name(), "User");
+    assert_eq!(selection_set.len(), 2);
+    assert_eq!(print_field_set(&selection_set), "id organization { id }");
+}
+
+#[test]
+fn validates_field_sets() {
+    let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+    validate_field_set(&schema, name!("User"), "id organization { id }").unwrap();
+    validate_field_set(&schema, name!("User"), "{ id name }").unwrap();
+
+    let error = validate_field_set(&schema, name!("User"), "unknown").unwrap_err();
+    assert_eq!(error.errors().len(), 1);
+
+    let error =
+        validate_field_set(&schema, name!("User"), "id orgId: organization { id }").unwrap_err();
+    assert_eq!(
+        error.errors()[0].to_string(),
+        "Cannot use alias \"orgId\" in \"orgId: organization\": aliases are not currently supported in field sets",
+    );
+}
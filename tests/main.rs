@@ -1,4 +1,17 @@
 mod api_schema;
+mod authorization;
 mod composition_tests;
+mod contracts;
+mod cost;
+mod extract_subgraphs;
+mod field_set;
+mod prelude;
 mod query_plan;
 mod subgraph;
+mod supergraph_diagnostics;
+mod supergraph_planning_diff;
+mod supergraph_report;
+#[path = "../src/test_supergraph.rs"]
+mod test_supergraph;
+#[cfg(feature = "test_vectors")]
+mod test_vectors;
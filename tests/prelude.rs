@@ -0,0 +1,106 @@
+use crate::test_supergraph::supergraph_sdl;
+use apollo_compiler::ast::OperationType;
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::prelude::*;
+
+fn supergraph() -> String {
+    supergraph_sdl(
+        &["S1", "S2", "S3"],
+        r#"
+        type Query
+          @join__type(graph: S1)
+          @join__type(graph: S2)
+          @join__type(graph: S3)
+        {
+          t: T @join__field(graph: S1)
+        }
+
+        type T
+          @join__type(graph: S1, key: "id", resolvable: false)
+          @join__type(graph: S2, key: "id", resolvable: false)
+          @join__type(graph: S3, key: "id", resolvable: false)
+        {
+          id: ID!
+          x: Int @join__field(graph: S1) @join__field(graph: S2, external: true)
+          y: Int @join__field(graph: S2, requires: "x") @join__field(graph: S3, external: true)
+          z: Int @join__field(graph: S3, requires: "y")
+        }
+        "#,
+    )
+}
+
+const OPERATION: &str = r#"
+    query GetT($withZ: Boolean!) {
+      t {
+        ...TFields
+        z @include(if: $withZ)
+      }
+    }
+
+    fragment TFields on T {
+      id
+      x
+    }
+"#;
+
+#[test]
+fn normalizes_operations_through_the_prelude() -> Result<(), FederationError> {
+    let supergraph = Supergraph::new(&supergraph())?;
+    let planner = QueryPlanner::new(&supergraph, QueryPlannerConfig::default())?;
+    let api_schema = supergraph.to_api_schema(ApiSchemaOptions::default())?;
+    let document =
+        ExecutableDocument::parse_and_validate(&api_schema, OPERATION, "operation.graphql")
+            .unwrap();
+
+    let operation: NormalizedOperation = planner.normalize_operation(&document, None)?;
+    assert_eq!(operation.operation_type(), OperationType::Query);
+    assert_eq!(operation.name().map(|name| name.as_str()), Some("GetT"));
+    assert_eq!(operation.variables().len(), 1);
+    assert_eq!(operation.variables()[0].name.as_str(), "withZ");
+    assert_eq!(
+        operation
+            .fragment_names()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["TFields"]
+    );
+    Ok(())
+}
+
+#[test]
+fn reports_unknown_operations() -> Result<(), FederationError> {
+    let supergraph = Supergraph::new(&supergraph())?;
+    let planner = QueryPlanner::new(&supergraph, QueryPlannerConfig::default())?;
+    let api_schema = supergraph.to_api_schema(ApiSchemaOptions::default())?;
+    let document =
+        ExecutableDocument::parse_and_validate(&api_schema, OPERATION, "operation.graphql")
+            .unwrap();
+
+    let error = planner
+        .normalize_operation(&document, Some("Unknown"))
+        .unwrap_err();
+    assert_eq!(error.to_string(), r#"Unknown operation named "Unknown""#);
+    Ok(())
+}
+
+#[test]
+fn normalizes_operations_without_a_planner() -> Result<(), FederationError> {
+    let api_schema = Supergraph::new(&supergraph())?.to_api_schema(ApiSchemaOptions::default())?;
+    let document =
+        ExecutableDocument::parse_and_validate(&api_schema, OPERATION, "operation.graphql")
+            .unwrap();
+    let api_schema = ValidFederationSchema::new(api_schema)?;
+
+    let operation = NormalizedOperation::from_document(&document, Some("GetT"), &api_schema)?;
+    let selection_set: &NormalizedSelectionSet = operation.selection_set();
+    assert_eq!(selection_set.type_name(), "Query");
+    assert_eq!(
+        selection_set.to_string(),
+        "{ t { id x z @include(if: $withZ) } }"
+    );
+
+    let error =
+        NormalizedOperation::from_document(&document, Some("Unknown"), &api_schema).unwrap_err();
+    assert_eq!(error.to_string(), r#"Unknown operation named "Unknown""#);
+    Ok(())
+}
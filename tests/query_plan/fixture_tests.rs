@@ -0,0 +1,107 @@
+//! Checks the query plans of the fixtures in `tests/query_plan/fixtures`, to measure how close the
+//! planner is to the JS query planner.
+//!
+//! Each fixture is a directory with:
+//! - `supergraph.graphql`, the supergraph schema.
+//! - `operation.graphql`, the planned operation (which must have a single operation).
+//! - `plan.txt`, the expected plan in the format of the JS query planner's
+//!   `prettyFormatQueryPlan()` (see `QueryPlan::to_pretty_string()`). For fixtures generated by
+//!   the JS query planner, this is its output as-is.
+//!
+//! Fixtures listed in `fixtures/pending.txt` aren't expected to match yet (e.g. because they need
+//! parts of the planner that aren't ported), but the test fails once they do, so that the list only
+//! shrinks. Running the test with `UPDATE_QUERY_PLAN_FIXTURES=1` overwrites the `plan.txt` of the
+//! fixtures that aren't pending with the generated plan.
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const UPDATE_ENV_VAR: &str = "UPDATE_QUERY_PLAN_FIXTURES";
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/query_plan/fixtures")
+}
+
+/// Plans the operation of the given fixture, returning `None` if it needs parts of the planner
+/// that aren't ported yet.
+// TODO: Use the full query planner once graph traversal is ported; until then, only supergraphs
+// with a single subgraph can be planned (by bypassing the planner).
+fn plan_fixture(fixture_dir: &Path) -> Result<Option<QueryPlan>, FederationError> {
+    let read = |file_name: &str| {
+        fs::read_to_string(fixture_dir.join(file_name))
+            .unwrap_or_else(|error| panic!("could not read {file_name}: {error}"))
+    };
+    let supergraph = Supergraph::new(&read("supergraph.graphql"))?;
+    let mut config = QueryPlannerConfig::default();
+    config.debug.bypass_planner_for_single_subgraph = true;
+    let planner = QueryPlanner::new(&supergraph, config)?;
+    let api_schema = supergraph.to_api_schema(ApiSchemaOptions {
+        include_defer: true,
+        ..Default::default()
+    })?;
+    let document = ExecutableDocument::parse_and_validate(
+        &api_schema,
+        read("operation.graphql"),
+        "operation.graphql",
+    )
+    .unwrap_or_else(|error| panic!("invalid operation: {error}"));
+    let operation = planner.normalize_operation(&document, None)?;
+    planner.single_subgraph_plan(&operation)
+}
+
+#[test]
+fn query_plans_match_fixtures() {
+    let fixtures_dir = fixtures_dir();
+    let pending = fs::read_to_string(fixtures_dir.join("pending.txt")).unwrap();
+    let pending = pending
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect::<Vec<_>>();
+    let update = std::env::var_os(UPDATE_ENV_VAR).is_some();
+
+    let mut fixture_dirs = fs::read_dir(&fixtures_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    fixture_dirs.sort();
+
+    let mut matching = 0;
+    let mut failures = Vec::new();
+    for fixture_dir in &fixture_dirs {
+        let name = fixture_dir.file_name().unwrap().to_str().unwrap();
+        let is_pending = pending.contains(&name);
+        let plan = match plan_fixture(fixture_dir) {
+            Ok(Some(plan)) => plan.to_pretty_string(),
+            Ok(None) => "<not supported yet>".to_owned(),
+            Err(error) => format!("<error: {error}>"),
+        };
+        let expected_path = fixture_dir.join("plan.txt");
+        if update && !is_pending && !plan.starts_with('<') {
+            fs::write(&expected_path, format!("{plan}\n")).unwrap();
+        }
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        let matches = plan == expected.trim_end();
+        if matches {
+            matching += 1;
+        }
+        match (matches, is_pending) {
+            (true, true) => failures.push(format!(
+                "{name}: matches its expected plan, remove it from pending.txt"
+            )),
+            (false, false) => failures.push(format!(
+                "{name}: expected plan\n{}\nbut got\n{plan}",
+                expected.trim_end()
+            )),
+            _ => {}
+        }
+    }
+
+    println!(
+        "{matching}/{} query plan fixtures match their expected plan",
+        fixture_dirs.len()
+    );
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
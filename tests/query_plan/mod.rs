@@ -1,2 +1,3 @@
+mod fixture_tests;
 mod operation_optimization_tests;
 mod operation_validations_tests;
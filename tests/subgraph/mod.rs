@@ -1 +1,3 @@
 mod parse_expand_tests;
+mod upgrade_tests;
+mod validation_tests;
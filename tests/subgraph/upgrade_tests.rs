@@ -0,0 +1,132 @@
+use apollo_compiler::schema::ExtendedType;
+use apollo_federation::subgraph::{upgrade_subgraphs_if_necessary, Subgraph, ValidSubgraph};
+use apollo_federation::Supergraph;
+
+fn print_types(subgraph: &ValidSubgraph, type_names: &[&str]) -> String {
+    type_names
+        .iter()
+        .map(|type_name| match &subgraph.schema.types[*type_name] {
+            ExtendedType::Object(type_) => type_.to_string(),
+            _ => panic!("{type_name} is not an object type"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn upgrades_federation_1_subgraphs() {
+    let products = Subgraph::parse(
+        "products",
+        "https://products",
+        r#"
+            type Query {
+              topProducts: [Product]
+            }
+
+            type Product @key(fields: "upc") {
+              upc: String!
+              name: String
+              price: Int
+              weight: Int
+              image: Image
+            }
+
+            type Image {
+              url: String
+            }
+        "#,
+    )
+    .unwrap();
+    let inventory = Subgraph::parse(
+        "inventory",
+        "https://inventory",
+        r#"
+            extend type Product @key(fields: "upc") {
+              upc: String! @external
+              weight: Int @external
+              price: Int @external
+              name: String @external
+              inStock: Boolean
+              shippingEstimate: Int @requires(fields: "price weight")
+            }
+        "#,
+    )
+    .unwrap();
+    let reviews = Subgraph::parse(
+        "reviews",
+        "https://reviews",
+        r#"
+            type Review {
+              body: String
+              product: Product
+              image: Image
+            }
+
+            type Product @extends @key(fields: "upc") {
+              upc: String! @external
+              reviews: [Review]
+            }
+
+            type Image {
+              url: String
+            }
+        "#,
+    )
+    .unwrap();
+    // Federation 2 subgraphs are left as is.
+    let accounts = Subgraph::parse(
+        "accounts",
+        "https://accounts",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.3", import: ["@key"])
+
+            type Query {
+              me: String
+            }
+        "#,
+    )
+    .unwrap();
+
+    let subgraphs =
+        upgrade_subgraphs_if_necessary(vec![products, inventory, reviews, accounts]).unwrap();
+    insta::assert_snapshot!(print_types(&subgraphs[0], &["Product", "Image"]), @r###"
+    type Product @key(fields: "upc") {
+      upc: String!
+      name: String
+      price: Int
+      weight: Int
+      image: Image
+    }
+
+    type Image {
+      url: String @federation__shareable
+    }
+    "###);
+    insta::assert_snapshot!(print_types(&subgraphs[1], &["Product"]), @r###"
+    type Product @key(fields: "upc") {
+      upc: String!
+      weight: Int @external
+      price: Int @external
+      inStock: Boolean
+      shippingEstimate: Int @requires(fields: "price weight")
+    }
+    "###);
+    insta::assert_snapshot!(print_types(&subgraphs[2], &["Product", "Image"]), @r###"
+    type Product @key(fields: "upc") {
+      upc: String!
+      reviews: [Review]
+    }
+
+    type Image {
+      url: String @federation__shareable
+    }
+    "###);
+    insta::assert_snapshot!(print_types(&subgraphs[3], &["Query"]), @r###"
+    type Query {
+      me: String
+      _service: _Service!
+    }
+    "###);
+
+    assert!(Supergraph::compose(subgraphs.iter().collect()).is_ok());
+}
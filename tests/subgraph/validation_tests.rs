@@ -0,0 +1,117 @@
+use apollo_federation::subgraph::{validate_subgraph, validate_subgraphs, Subgraph};
+
+#[test]
+fn accepts_valid_subgraph() {
+    let schema = r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/federation/v2.3", import: [ "@key", "@external", "@provides", "@tag" ])
+
+        type Query {
+            t: T @provides(fields: "x")
+        }
+
+        type T @key(fields: "id") @tag(name: "team/core") {
+            id: ID!
+            x: Int @external
+        }
+        "#;
+
+    let subgraph = Subgraph::parse_and_expand("S1", "http://s1", schema).unwrap();
+    validate_subgraph(&subgraph.schema).unwrap();
+}
+
+#[test]
+fn reports_all_federation_errors() {
+    let schema = r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/federation/v2.3", import: [ "@key", "@external", "@tag" ])
+
+        type Query {
+            t: T
+        }
+
+        type T @key(fields: "myId: id") @tag(name: "not a tag") {
+            id: ID!
+            x: Int @external
+        }
+        "#;
+
+    let subgraph = Subgraph::parse_and_expand("S1", "http://s1", schema).unwrap();
+    let error = validate_subgraph(&subgraph.schema).unwrap_err();
+    let codes: Vec<_> = error
+        .errors()
+        .into_iter()
+        .map(|error| error.code().code().to_string())
+        .collect();
+    assert_eq!(
+        codes,
+        ["INVALID_GRAPHQL", "KEY_INVALID_FIELDS", "EXTERNAL_UNUSED"]
+    );
+}
+
+#[test]
+fn treats_external_key_fields_of_type_extensions_as_fake_externals() {
+    let schema = r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/federation/v2.3", import: [ "@key", "@external", "@requires" ])
+
+        type Query {
+            t: T
+        }
+
+        extend type T @key(fields: "id") {
+            id: ID! @external
+            x: Int @requires(fields: "id")
+        }
+        "#;
+
+    // The `id` key field is resolved by the subgraph despite being marked @external, so it's not
+    // reported as unused, but it can't be required either.
+    let subgraph = Subgraph::parse_and_expand("S1", "http://s1", schema).unwrap();
+    let error = validate_subgraph(&subgraph.schema).unwrap_err();
+    let codes: Vec<_> = error
+        .errors()
+        .into_iter()
+        .map(|error| error.code().code().to_string())
+        .collect();
+    assert_eq!(codes, ["REQUIRES_FIELDS_MISSING_EXTERNAL"]);
+}
+
+#[test]
+fn validates_all_subgraphs() {
+    let valid_schema = r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/federation/v2.3", import: [ "@key" ])
+
+        type Query {
+            t: T
+        }
+
+        type T @key(fields: "id") {
+            id: ID!
+        }
+        "#;
+    let invalid_schema = r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/federation/v2.3", import: [ "@key", "@external" ])
+
+        type T @key(fields: "id") {
+            id: ID!
+            x: Int @external
+        }
+        "#;
+
+    let subgraphs = [
+        Subgraph::parse_and_expand("S1", "http://s1", invalid_schema).unwrap(),
+        Subgraph::parse_and_expand("S2", "http://s2", valid_schema).unwrap(),
+        Subgraph::parse_and_expand("S3", "http://s3", invalid_schema).unwrap(),
+    ];
+    validate_subgraphs(&subgraphs[1..2]).unwrap();
+    let error = validate_subgraphs(&subgraphs).unwrap_err();
+    let codes: Vec<_> = error
+        .errors()
+        .into_iter()
+        .map(|error| error.code().code().to_string())
+        .collect();
+    assert_eq!(codes, ["EXTERNAL_UNUSED", "EXTERNAL_UNUSED"]);
+}
@@ -0,0 +1,132 @@
+use crate::test_supergraph::supergraph_sdl;
+use apollo_federation::Supergraph;
+
+// A supergraph with a few broken join directives:
+// - `T.bad` has a `type` that isn't a valid type reference,
+// - `T.orphan` is in S2, but `T` isn't,
+// - `U` implements `I` in S2, but `U` isn't in S2,
+// - `V` has no `@join__type`, which also makes `T.v` unusable.
+fn supergraph() -> String {
+    supergraph_sdl(
+        &["S1", "S2"],
+        r#"
+        type Query
+          @join__type(graph: S1)
+          @join__type(graph: S2)
+        {
+          t: T @join__field(graph: S1)
+          i: I @join__field(graph: S2)
+        }
+
+        type T
+          @join__type(graph: S1)
+        {
+          id: ID!
+          bad: Int @join__field(graph: S1, type: "[Int")
+          orphan: Int @join__field(graph: S2)
+          v: V
+        }
+
+        interface I
+          @join__type(graph: S1)
+          @join__type(graph: S2)
+        {
+          id: ID!
+        }
+
+        type U implements I
+          @join__type(graph: S1)
+          @join__implements(graph: S1, interface: "I")
+          @join__implements(graph: S2, interface: "I")
+        {
+          id: ID!
+        }
+
+        type V {
+          id: ID!
+        }
+        "#,
+    )
+}
+
+#[test]
+fn collects_all_errors_of_broken_supergraph() {
+    let supergraph = Supergraph::new(&supergraph()).unwrap();
+    // Regular loading stops at the first error.
+    assert!(supergraph.extract_subgraphs().is_err());
+
+    let diagnostics = supergraph.diagnose();
+    assert!(!diagnostics.is_valid());
+    let errors = diagnostics
+        .errors
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+    insta::assert_snapshot!(errors.join("\n"), @r###"
+    Missing @join__type on "V"
+    Cannot parse type "[Int"
+    @join__field cannot exist on T.orphan for subgraph S2 without type-level @join__type
+    Cannot extract field "T.v" in subgraph "S1": its type "V" is not in the subgraph
+    @join__implements cannot exist on "U" for subgraph "S2" without type-level @join__type
+    "###);
+    assert_eq!(diagnostics.unusable_types, ["V", "U"]);
+    assert_eq!(diagnostics.unusable_fields, ["T.bad", "T.orphan", "T.v"]);
+    assert!(diagnostics.unusable_subgraphs.is_empty());
+    assert!(diagnostics.query_graph.is_some());
+}
+
+// A Fed 1 supergraph (using `@core` and join v0.1). The test also checks the same supergraph with
+// `@link` instead of `@core`, as the join spec version alone marks a supergraph as Fed 1.
+const FED_1_SUPERGRAPH: &str = r#"
+    schema
+      @core(feature: "https://specs.apollo.dev/core/v0.1")
+      @core(feature: "https://specs.apollo.dev/join/v0.1")
+    {
+      query: Query
+    }
+
+    directive @core(feature: String!) repeatable on SCHEMA
+
+    directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet) on FIELD_DEFINITION
+
+    directive @join__type(graph: join__Graph!, key: join__FieldSet) repeatable on OBJECT | INTERFACE
+
+    directive @join__owner(graph: join__Graph!) on OBJECT | INTERFACE
+
+    directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+    scalar join__FieldSet
+
+    enum join__Graph {
+      S1 @join__graph(name: "S1", url: "http://S1")
+    }
+
+    type Query {
+      t: Int @join__field(graph: S1)
+    }
+"#;
+
+#[test]
+fn reports_fed_1_supergraphs_as_unsupported() {
+    let linked = FED_1_SUPERGRAPH
+        .replace(
+            "directive @core(feature: String!) repeatable on SCHEMA",
+            "directive @link(url: String, as: String, import: [link__Import]) repeatable on SCHEMA\n\n    scalar link__Import",
+        )
+        .replace(
+            r#"@core(feature: "https://specs.apollo.dev/core/v0.1")"#,
+            r#"@link(url: "https://specs.apollo.dev/link/v1.0")"#,
+        )
+        .replace("@core(feature:", "@link(url:");
+    for supergraph in [FED_1_SUPERGRAPH, linked.as_str()] {
+        let diagnostics = Supergraph::new(supergraph).unwrap().diagnose();
+        assert!(!diagnostics.is_valid());
+        let codes = diagnostics
+            .errors
+            .iter()
+            .map(|error| error.code().code())
+            .collect::<Vec<_>>();
+        assert_eq!(codes, ["UNSUPPORTED_FEDERATION_VERSION"]);
+        assert!(diagnostics.query_graph.is_none());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::test_supergraph::supergraph_sdl;
+use apollo_federation::Supergraph;
+
+fn supergraph(t: &str) -> Supergraph {
+    Supergraph::new(&supergraph_sdl(
+        &["S1", "S2"],
+        &format!(
+            r#"
+    type Query
+      @join__type(graph: S1)
+      @join__type(graph: S2)
+    {{
+      t: T @join__field(graph: S1)
+    }}
+
+    {t}
+"#
+        ),
+    ))
+    .unwrap()
+}
+
+#[test]
+fn reports_planning_relevant_supergraph_changes() {
+    let old = supergraph(
+        r#"
+        type T
+          @join__type(graph: S1, key: "id")
+          @join__type(graph: S2, key: "id")
+        {
+          id: ID!
+          x: Int @join__field(graph: S1) @join__field(graph: S2, external: true)
+          y: Int @join__field(graph: S2, requires: "x")
+          w: Int @join__field(graph: S1)
+        }
+        "#,
+    );
+    assert!(old.planning_diff(&old).unwrap().is_empty());
+
+    // S2 can't be jumped to anymore, `y` doesn't require `x` anymore, and `w` moved to S2.
+    let new = supergraph(
+        r#"
+        type T
+          @join__type(graph: S1, key: "id")
+          @join__type(graph: S2, key: "id", resolvable: false)
+        {
+          id: ID!
+          x: Int @join__field(graph: S1)
+          y: Int @join__field(graph: S2)
+          w: Int @join__field(graph: S2)
+          v: Int @join__field(graph: S2)
+        }
+        "#,
+    );
+    let diff = old.planning_diff(&new).unwrap();
+    assert_eq!(diff.removed_key_edges.len(), 1);
+    assert!(diff.added_key_edges.is_empty());
+    insta::assert_snapshot!(diff, @r###"
+    - key T: S1 -> S2 { id }
+    ~ field T.w: [S1] -> [S2]
+    ~ requires T.y in S2: { x } -> none
+    "###);
+}
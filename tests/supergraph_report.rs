@@ -0,0 +1,53 @@
+use crate::test_supergraph::supergraph_sdl;
+use apollo_federation::Supergraph;
+
+fn supergraph() -> String {
+    supergraph_sdl(
+        &["S1", "S2", "S3"],
+        r#"
+        type Query
+          @join__type(graph: S1)
+          @join__type(graph: S2)
+          @join__type(graph: S3)
+        {
+          t: T @join__field(graph: S1)
+        }
+
+        type T
+          @join__type(graph: S1, key: "id", resolvable: false)
+          @join__type(graph: S2, key: "id", resolvable: false)
+          @join__type(graph: S3, key: "id", resolvable: false)
+        {
+          id: ID!
+          x: Int @join__field(graph: S1) @join__field(graph: S2, external: true)
+          y: Int @join__field(graph: S2, requires: "x") @join__field(graph: S3, external: true)
+          z: Int @join__field(graph: S3, requires: "y")
+        }
+        "#,
+    )
+}
+
+#[test]
+fn reports_supergraph_statistics() {
+    let supergraph = Supergraph::new(&supergraph()).unwrap();
+    let report = supergraph.report().unwrap();
+    assert_eq!(report.subgraph_count, 3);
+    assert_eq!(report.entity_count, 1);
+    // Non-resolvable keys can only be used to resolve entities elsewhere, so there is no key edge.
+    assert_eq!(report.key_edge_count, 0);
+    assert_eq!(
+        report.cross_subgraph_fields,
+        vec!["Query.t", "T.x", "T.y", "T.z"]
+    );
+    assert_eq!(report.largest_type, Some(("T".to_owned(), 4)));
+    // `z` requires `y`, which itself requires `x`.
+    assert_eq!(report.deepest_requires_chain, 2);
+    insta::assert_snapshot!(report, @r###"
+    subgraphs: 3
+    entities: 1
+    key edges: 0
+    cross-subgraph fields: 4
+    largest type: T (4 fields)
+    deepest @requires chain: 2
+    "###);
+}
@@ -0,0 +1,37 @@
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::prelude::*;
+use apollo_federation::test_vectors::{test_vectors, TestVector};
+
+fn plan(vector: &TestVector) -> QueryPlan {
+    let supergraph = Supergraph::new(vector.supergraph).unwrap();
+    let planner = QueryPlanner::new(&supergraph, TestVector::query_planner_config()).unwrap();
+    let api_schema = supergraph
+        .to_api_schema(ApiSchemaOptions {
+            include_defer: true,
+            ..Default::default()
+        })
+        .unwrap();
+    let document =
+        ExecutableDocument::parse_and_validate(&api_schema, vector.operation, "operation.graphql")
+            .unwrap();
+    let operation = planner.normalize_operation(&document, None).unwrap();
+    planner.single_subgraph_plan(&operation).unwrap().unwrap()
+}
+
+#[test]
+fn test_vector_plans_are_produced() {
+    let vectors = test_vectors();
+    assert!(!vectors.is_empty());
+    for vector in &vectors {
+        let plan = plan(vector);
+        let expected_json: serde_json::Value = serde_json::from_str(vector.plan_json)
+            .unwrap_or_else(|error| panic!("{}: invalid plan.json: {error}", vector.name));
+        assert_eq!(plan.to_json(), expected_json, "{}", vector.name);
+        assert_eq!(
+            plan.to_pretty_string(),
+            vector.plan_pretty.trim_end(),
+            "{}",
+            vector.name
+        );
+    }
+}